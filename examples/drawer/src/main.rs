@@ -1,6 +1,7 @@
 use std::sync::Arc;
 
 use flow_ngin::{
+    Error,
     context::{Context, InitContext},
     flow::{FlowConstructor, GraphicsFlow, Out},
     render::Render,
@@ -40,17 +41,17 @@ struct DrawerExample {
 }
 
 impl DrawerExample {
-    async fn new(ctx: InitContext) -> Self {
+    async fn new(ctx: InitContext) -> Result<Self, Error> {
         let atlas = Arc::new(
-            Atlas::new(&ctx.device, &ctx.queue, "card_atlas.png", 16, 16).await,
+            Atlas::new(&ctx.device, &ctx.queue, "card_atlas.png", 16, 16).await?,
         );
-        Self {
+        Ok(Self {
             atlas,
             toggle_btn: None,
             drawer: None,
             drawer_progress: 0.0,
             drawer_width: 300,
-        }
+        })
     }
 
     fn resolve_drawer(&mut self, ctx: &Context) {
@@ -249,7 +250,7 @@ impl GraphicsFlow<State, Event> for DrawerExample {
 fn main() {
     let drawer: FlowConstructor<State, Event> = Box::new(|ctx| {
         Box::pin(async move {
-            Box::new(DrawerExample::new(ctx).await) as Box<dyn GraphicsFlow<_, _>>
+            Ok(Box::new(DrawerExample::new(ctx).await?) as Box<dyn GraphicsFlow<_, _>>)
         })
     });
 
@@ -1,6 +1,7 @@
 use std::sync::Arc;
 
 use flow_ngin::{
+    Error,
     context::{Context, InitContext},
     flow::{FlowConstructor, GraphicsFlow, Out},
     render::Render,
@@ -54,18 +55,18 @@ struct DrawerExample {
 }
 
 impl DrawerExample {
-    async fn new(ctx: InitContext) -> Self {
-        let atlas = Arc::new(Atlas::new(&ctx.device, &ctx.queue, "atlas.png", 8, 8).await);
+    async fn new(ctx: InitContext) -> Result<Self, Error> {
+        let atlas = Arc::new(Atlas::new(&ctx.device, &ctx.queue, "atlas.png", 8, 8).await?);
         let bg =
-            Arc::new(BackgroundTexture::new(&ctx.device, &ctx.queue, "container-slim.png").await);
-        Self {
+            Arc::new(BackgroundTexture::new(&ctx.device, &ctx.queue, "container-slim.png").await?);
+        Ok(Self {
             atlas,
             bg,
             actions: None,
             drawer: None,
             drawer_progress: 0.0,
             drawer_width: 240,
-        }
+        })
     }
 
     fn resolve_drawer(&mut self, ctx: &Context) {
@@ -286,16 +287,16 @@ struct DetailCard {
 }
 
 impl DetailCard {
-    async fn new(ctx: InitContext) -> Self {
-        let atlas = Arc::new(Atlas::new(&ctx.device, &ctx.queue, "atlas.png", 8, 8).await);
+    async fn new(ctx: InitContext) -> Result<Self, Error> {
+        let atlas = Arc::new(Atlas::new(&ctx.device, &ctx.queue, "atlas.png", 8, 8).await?);
         let bg =
-            Arc::new(BackgroundTexture::new(&ctx.device, &ctx.queue, "container-slim.png").await);
-        Self {
+            Arc::new(BackgroundTexture::new(&ctx.device, &ctx.queue, "container-slim.png").await?);
+        Ok(Self {
             atlas,
             bg,
             cards: Vec::new(),
             current_id: 0,
-        }
+        })
     }
 
     fn card_info(id: u32) -> Option<(u8, &'static str, &'static str, &'static str, f32)> {
@@ -449,15 +450,15 @@ impl GraphicsFlow<State, Event> for DetailCard {
 
 fn main() {
     let card: FlowConstructor<State, Event> = Box::new(|ctx| {
-        Box::pin(
-            async move { Box::new(DetailCard::new(ctx).await) as Box<dyn GraphicsFlow<_, _>> },
-        )
+        Box::pin(async move {
+            Ok(Box::new(DetailCard::new(ctx).await?) as Box<dyn GraphicsFlow<_, _>>)
+        })
     });
 
     let drawer: FlowConstructor<State, Event> = Box::new(|ctx| {
-        Box::pin(
-            async move { Box::new(DrawerExample::new(ctx).await) as Box<dyn GraphicsFlow<_, _>> },
-        )
+        Box::pin(async move {
+            Ok(Box::new(DrawerExample::new(ctx).await?) as Box<dyn GraphicsFlow<_, _>>)
+        })
     });
 
     let _ = flow_ngin::flow::run(vec![card, drawer]);
@@ -1,7 +1,7 @@
 use std::sync::Arc;
 
 use flow_ngin::{
-    WindowEvent,
+    Error, WindowEvent,
     context::{Context, InitContext},
     flow::{GraphicsFlow, Out},
     pick::PickId,
@@ -27,9 +27,9 @@ pub struct GuiFlow {
 }
 
 impl GuiFlow {
-    pub async fn new(ctx: InitContext) -> Self {
-        let atlas = Arc::new(Atlas::new(&ctx.device, &ctx.queue, "card_atlas.png", 16, 16).await);
-        Self {
+    pub async fn new(ctx: InitContext) -> Result<Self, Error> {
+        let atlas = Arc::new(Atlas::new(&ctx.device, &ctx.queue, "card_atlas.png", 16, 16).await?);
+        Ok(Self {
             atlas,
             panel: None,
             dim_value: Value::new(0.5),
@@ -37,7 +37,7 @@ impl GuiFlow {
             fps_label: None,
             fps_smoothed: 60.0,
             fps_timer: 0.0,
-        }
+        })
     }
 }
 
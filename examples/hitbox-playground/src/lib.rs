@@ -99,25 +99,25 @@ pub enum Event {
 pub fn launch() {
     let scene: FlowConstructor<State, Event> = Box::new(|ctx| {
         Box::pin(async move {
-            Box::new(SceneFlow::new(ctx).await) as Box<dyn GraphicsFlow<_, _>>
+            Ok(Box::new(SceneFlow::new(ctx).await) as Box<dyn GraphicsFlow<_, _>>)
         })
     });
 
     let overlay: FlowConstructor<State, Event> = Box::new(|ctx| {
         Box::pin(async move {
-            Box::new(OverlayFlow::new(ctx).await) as Box<dyn GraphicsFlow<_, _>>
+            Ok(Box::new(OverlayFlow::new(ctx).await) as Box<dyn GraphicsFlow<_, _>>)
         })
     });
 
     let partition_viz: FlowConstructor<State, Event> = Box::new(|ctx| {
         Box::pin(async move {
-            Box::new(PartitionVizFlow::new(ctx).await) as Box<dyn GraphicsFlow<_, _>>
+            Ok(Box::new(PartitionVizFlow::new(ctx).await) as Box<dyn GraphicsFlow<_, _>>)
         })
     });
 
     let gui: FlowConstructor<State, Event> = Box::new(|ctx| {
         Box::pin(async move {
-            Box::new(GuiFlow::new(ctx).await) as Box<dyn GraphicsFlow<_, _>>
+            Ok(Box::new(GuiFlow::new(ctx).await?) as Box<dyn GraphicsFlow<_, _>>)
         })
     });
 
@@ -36,11 +36,11 @@ impl OverlayFlow {
         let o = Vector3::new(0.0, 0.0, 0.0);
         let r = Quaternion::one();
 
-        let overlay_clear   = BuildingBlocks::new(0u32, &ctx.queue, &ctx.device, o, r, 0, "overlay-white.obj").await;
-        let overlay_broad   = BuildingBlocks::new(0u32, &ctx.queue, &ctx.device, o, r, 0, "overlay-white.obj").await;
-        let overlay_overlap = BuildingBlocks::new(0u32, &ctx.queue, &ctx.device, o, r, 0, "overlay-white.obj").await;
+        let overlay_clear   = BuildingBlocks::new(0u32, &ctx.queue, &ctx.device, o, r, 0, "overlay-white.obj").await.expect("failed to load overlay-white.obj");
+        let overlay_broad   = BuildingBlocks::new(0u32, &ctx.queue, &ctx.device, o, r, 0, "overlay-white.obj").await.expect("failed to load overlay-white.obj");
+        let overlay_overlap = BuildingBlocks::new(0u32, &ctx.queue, &ctx.device, o, r, 0, "overlay-white.obj").await.expect("failed to load overlay-white.obj");
 
-        let drag_overlay = BuildingBlocks::new(0u32, &ctx.queue, &ctx.device, o, r, 1, "overlay-white.obj").await;
+        let drag_overlay = BuildingBlocks::new(0u32, &ctx.queue, &ctx.device, o, r, 1, "overlay-white.obj").await.expect("failed to load overlay-white.obj");
 
         let backend = CollisionBackend::new(Strategy::SparseGrid, 2);
 
@@ -192,7 +192,7 @@ impl GraphicsFlow<State, Event> for OverlayFlow {
                 if !$bb.instances().is_empty() {
                     renders.push(Render::Transparent(
                         $bb.to_instanced(),
-                        TransparencyUniform { tint: $tint, alpha: 0.4 },
+                        TransparencyUniform { tint: $tint, alpha: 0.4, ..Default::default() },
                     ));
                 }
             };
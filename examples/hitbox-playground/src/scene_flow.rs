@@ -1,6 +1,6 @@
 use flow_ngin::{
     InnerSpace, One, Point3, Quaternion, Rad, Rotation, Rotation3, Vector3, WindowEvent,
-    context::{Context, GPUResource, InitContext},
+    context::{Context, FullscreenMode, GPUResource, InitContext},
     data_structures::{block::BuildingBlocks, instance::Instance},
     flow::{GraphicsFlow, Out},
     pick::PickId,
@@ -40,10 +40,10 @@ impl SceneFlow {
         let origin = Vector3::new(0.0, 0.0, 0.0);
         let rot = Quaternion::one();
 
-        let drag_cube = BuildingBlocks::new(DRAG_PICK_ID, &ctx.queue, &ctx.device, origin, rot, 1, "cube.obj").await;
-        let drag_plane = BuildingBlocks::new(DRAG_PICK_ID, &ctx.queue, &ctx.device, origin, rot, 1, "plane.obj").await;
-        let placed_cubes = BuildingBlocks::new(0u32, &ctx.queue, &ctx.device, origin, rot, 0, "cube.obj").await;
-        let placed_planes = BuildingBlocks::new(0u32, &ctx.queue, &ctx.device, origin, rot, 0, "plane.obj").await;
+        let drag_cube = BuildingBlocks::new(DRAG_PICK_ID, &ctx.queue, &ctx.device, origin, rot, 1, "cube.obj").await.expect("failed to load cube.obj");
+        let drag_plane = BuildingBlocks::new(DRAG_PICK_ID, &ctx.queue, &ctx.device, origin, rot, 1, "plane.obj").await.expect("failed to load plane.obj");
+        let placed_cubes = BuildingBlocks::new(0u32, &ctx.queue, &ctx.device, origin, rot, 0, "cube.obj").await.expect("failed to load cube.obj");
+        let placed_planes = BuildingBlocks::new(0u32, &ctx.queue, &ctx.device, origin, rot, 0, "plane.obj").await.expect("failed to load plane.obj");
 
         Self {
             drag_cube,
@@ -205,6 +205,12 @@ impl GraphicsFlow<State, Event> for SceneFlow {
                     PhysicalKey::Code(KeyCode::KeyX) if !key_event.repeat => {
                         state.rotation_axis = (state.rotation_axis + 1) % 3;
                     }
+                    PhysicalKey::Code(KeyCode::F11) if !key_event.repeat => {
+                        return Out::Configure(Box::new(|ctx| {
+                            let mode = (!ctx.is_fullscreen()).then_some(FullscreenMode::Borderless);
+                            ctx.set_fullscreen(mode);
+                        }));
+                    }
                     _ => {}
                 }
             }
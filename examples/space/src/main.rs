@@ -1,10 +1,11 @@
 use std::sync::Arc;
 
 use flow_ngin::{
-    Color, Deg, One, Quaternion, Rotation3, Vector3,
+    Color, Deg, Error, One, Quaternion, Rotation3, Vector3,
     context::{Context, GPUResource, InitContext},
     data_structures::block::BuildingBlocks,
-    flow::{FlowConstructor, GraphicsFlow, Out},
+    flow::{FlowConstructor, GraphicsFlow, Out, RunConfig},
+    loading::LoadingScreen,
     ui::{
         Button, Checkbox, Grid, HAlign, VAlign, Value, image::{Atlas, Icon}
     },
@@ -44,7 +45,8 @@ impl Astroids {
             10000,
             "Rock1.obj",
         )
-        .await;
+        .await
+        .expect("failed to load Rock1.obj");
         let background = Color::BLACK;
         Self {
             astroids,
@@ -129,9 +131,9 @@ struct GUI {
     grid: Option<Grid<State, Event>>,
 }
 impl GUI {
-    async fn new(ctx: InitContext) -> GUI {
-        let atlas = Arc::new(Atlas::new(&ctx.device, &ctx.queue, "card_atlas.png", 16, 16).await);
-        Self { atlas, grid: None }
+    async fn new(ctx: InitContext) -> Result<GUI, Error> {
+        let atlas = Arc::new(Atlas::new(&ctx.device, &ctx.queue, "card_atlas.png", 16, 16).await?);
+        Ok(Self { atlas, grid: None })
     }
 
     fn make_button(
@@ -218,11 +220,15 @@ impl<'a> GraphicsFlow<State, Event> for GUI {
 
 fn main() {
     let astroids: FlowConstructor<State, Event> = Box::new(|ctx| {
-        Box::pin(async move { Box::new(Astroids::new(ctx).await) as Box<dyn GraphicsFlow<_, _>> })
+        Box::pin(async move {
+            Ok(Box::new(Astroids::new(ctx).await) as Box<dyn GraphicsFlow<_, _>>)
+        })
     });
     let gui: FlowConstructor<State, Event> = Box::new(|ctx| {
-        Box::pin(async move { Box::new(GUI::new(ctx).await) as Box<dyn GraphicsFlow<_, _>> })
+        Box::pin(async move { Ok(Box::new(GUI::new(ctx).await?) as Box<dyn GraphicsFlow<_, _>>) })
     });
 
-    let _ = flow_ngin::flow::run(vec![astroids, gui]);
+    // The rocks take a moment to load, the spinner shows the window is alive meanwhile
+    let config = RunConfig::default().with_loading_screen(LoadingScreen::Spinner);
+    let _ = flow_ngin::flow::run_with(config, vec![astroids, gui]);
 }
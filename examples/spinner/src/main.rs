@@ -0,0 +1,50 @@
+use flow_ngin::{
+    Deg, Error, One, Quaternion, Rotation3,
+    context::{Context, GPUResource, InitContext},
+    data_structures::block::BuildingBlocks,
+    flow::{GraphicsFlow, NoEvent, Out, TryIntoFlowConstructor, run_simple},
+    render::Render,
+};
+
+/// A single rock spinning around its vertical axis
+struct Spinner(BuildingBlocks);
+
+impl Spinner {
+    async fn new(ctx: InitContext) -> Result<Self, Error> {
+        let rock = [0.0; 3].into();
+        let blocks = BuildingBlocks::new(
+            0,
+            &ctx.queue,
+            &ctx.device,
+            rock,
+            Quaternion::one(),
+            1,
+            "Rock1.obj",
+        )
+        .await?;
+        Ok(Self(blocks))
+    }
+}
+
+impl GraphicsFlow<(), NoEvent> for Spinner {
+    fn on_update(
+        &mut self,
+        ctx: &Context,
+        _: &mut (),
+        dt: std::time::Duration,
+    ) -> Out<(), NoEvent> {
+        for rock in self.0.instances_mut_size_unchanged() {
+            rock.rotation = rock.rotation * Quaternion::from_angle_y(Deg(90.0 * dt.as_secs_f32()));
+        }
+        self.0.write_to_buffer(&ctx.queue, &ctx.device);
+        Out::Empty
+    }
+
+    fn on_render<'pass>(&self) -> Render<'_, 'pass> {
+        self.0.get_render()
+    }
+}
+
+fn main() {
+    run_simple(vec![Spinner::new.try_into_flow()]).expect("the app failed to start");
+}
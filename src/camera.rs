@@ -5,8 +5,14 @@ use std::f32::consts::FRAC_PI_2;
 use std::time::Duration;
 use winit::event::*;
 use winit::keyboard::KeyCode;
+use wgpu::util::DeviceExt;
 use winit::{dpi::PhysicalPosition, keyboard::PhysicalKey};
 
+use crate::{
+    data_structures::{aabb::Aabb, frustum::Frustum},
+    profiling,
+};
+
 #[rustfmt::skip]
 pub const OPENGL_TO_WGPU_MATRIX: cgmath::Matrix4<f32> = cgmath::Matrix4::from_cols(
     cgmath::Vector4::new(1.0, 0.0, 0.0, 0.0),
@@ -15,7 +21,32 @@ pub const OPENGL_TO_WGPU_MATRIX: cgmath::Matrix4<f32> = cgmath::Matrix4::from_co
     cgmath::Vector4::new(0.0, 0.0, 0.5, 1.0),
 );
 
+/// Maps a clip space depth of `z` to `w - z`, swapping the near and far plane.
+const REVERSE_Z_MATRIX: cgmath::Matrix4<f32> = cgmath::Matrix4::from_cols(
+    cgmath::Vector4::new(1.0, 0.0, 0.0, 0.0),
+    cgmath::Vector4::new(0.0, 1.0, 0.0, 0.0),
+    cgmath::Vector4::new(0.0, 0.0, -1.0, 0.0),
+    cgmath::Vector4::new(0.0, 0.0, 1.0, 1.0),
+);
+
 const SAFE_FRAC_PI_2: f32 = FRAC_PI_2 - 0.0001;
+/// Upper bound for scroll input that hasn't been applied by `update` yet.
+const MAX_PENDING_SCROLL: f32 = 50.0;
+/// Distance [`CameraController`] moves per pixel of panning, relative to its speed.
+const PAN_PER_PIXEL: f32 = 0.005;
+/// Pixels of trackpad scrolling that count as one mouse wheel notch. A short two-finger
+/// swipe scrolls about this far.
+const PIXELS_PER_SCROLL_LINE: f64 = 50.0;
+
+/// Vertical scroll in wheel notches, positive when scrolling up or away from the user.
+fn scroll_lines(delta: &MouseScrollDelta) -> f32 {
+    match delta {
+        MouseScrollDelta::LineDelta(_, lines) => *lines,
+        MouseScrollDelta::PixelDelta(PhysicalPosition { y, .. }) => {
+            (y / PIXELS_PER_SCROLL_LINE) as f32
+        }
+    }
+}
 
 pub(crate) fn screen_to_ndc(mouse_x: f32, mouse_y: f32, width: f32, height: f32) -> cgmath::Vector3<f32> {
     let x = if width == 0.0 { 0.0 } else { (2.0 * mouse_x / width) - 1.0 };
@@ -38,15 +69,61 @@ impl Ray {
      * Returns None if the ray is not pointed towards the floor.
      */
     pub fn intersect_with_floor(&self) -> Option<Point2<f32>> {
-        if self.direction.y.abs() < f32::EPSILON {
+        let intersection_point = self.intersect_plane(Vector3::zero(), Vector3::unit_y())?;
+        Some(Point2::new(intersection_point.x, intersection_point.z))
+    }
+
+    /**
+     * Calculates the intersection of the ray `self` with the plane through `origin` that is
+     * perpendicular to `normal`. The normal does not need to be normalized.
+     *
+     * Returns None if the ray runs parallel to the plane or points away from it.
+     */
+    pub fn intersect_plane(
+        &self,
+        origin: Vector3<f32>,
+        normal: Vector3<f32>,
+    ) -> Option<Vector3<f32>> {
+        let denom = normal.dot(self.direction);
+        if denom.abs() < f32::EPSILON * normal.magnitude() * self.direction.magnitude() {
             return None;
         }
-        let t = -self.origin.y / self.direction.y;
-        if t.is_sign_negative() {
+        let t = normal.dot(origin - self.origin.to_vec()) / denom;
+        if !t.is_finite() || t.is_sign_negative() {
             return None;
         }
-        let intersection_point = self.origin + self.direction * t;
-        Some(Point2::new(intersection_point.x, intersection_point.z))
+        Some(self.origin.to_vec() + self.direction * t)
+    }
+
+    /**
+     * Calculates the distance along the ray to the first intersection with `aabb` (slab method).
+     *
+     * The distance is measured in multiples of `direction`. Rays starting inside the box hit
+     * at 0.0, boxes behind the ray return None. Boxes without extent on some axes (planes,
+     * lines, points) are hit when the ray passes through them exactly.
+     */
+    pub fn intersect_aabb(&self, aabb: &Aabb) -> Option<f32> {
+        let mut t_min = 0.0_f32;
+        let mut t_max = f32::INFINITY;
+        for axis in 0..3 {
+            let (origin, direction) = (self.origin[axis], self.direction[axis]);
+            let (min, max) = (aabb.min[axis], aabb.max[axis]);
+            if direction == 0.0 {
+                // Parallel to the slab, only hits if it already lies within
+                if origin < min || origin > max {
+                    return None;
+                }
+                continue;
+            }
+            let t1 = (min - origin) / direction;
+            let t2 = (max - origin) / direction;
+            t_min = t_min.max(t1.min(t2));
+            t_max = t_max.min(t1.max(t2));
+            if t_min > t_max {
+                return None;
+            }
+        }
+        Some(t_min)
     }
 }
 
@@ -55,6 +132,8 @@ impl Ray {
 pub struct CameraUniform {
     view_position: [f32; 4],
     view_proj: [[f32; 4]; 4],
+    // x: seconds since start, used for shader-side animation. yzw are padding.
+    time: [f32; 4],
 }
 
 impl CameraUniform {
@@ -62,13 +141,51 @@ impl CameraUniform {
         Self {
             view_position: [0.0; 4],
             view_proj: cgmath::Matrix4::identity().into(),
+            time: [0.0; 4],
         }
     }
 
+    /// Sets the global time shaders use for animations such as `MaterialAnimator`.
+    pub fn set_time(&mut self, seconds: f32) {
+        self.time[0] = seconds;
+    }
+
     pub fn update_view_proj(&mut self, camera: &Camera, projection: &Projection) {
         self.view_position = camera.position.to_homogeneous().into();
         self.view_proj = (projection.calc_matrix() * camera.calc_matrix()).into();
     }
+
+    /// Frustum of the view-projection matrix last written by [`CameraUniform::update_view_proj`].
+    pub fn frustum(&self) -> Frustum {
+        Frustum::from_matrix(self.view_proj.into())
+    }
+
+    /// The view-projection matrix last written by [`CameraUniform::update_view_proj`].
+    pub(crate) fn view_proj(&self) -> Matrix4<f32> {
+        self.view_proj.into()
+    }
+}
+
+/// Entries of [`mk_camera_bind_group_layout`].
+pub const CAMERA_ENTRIES: [wgpu::BindGroupLayoutEntry; 1] = [
+    wgpu::BindGroupLayoutEntry {
+        binding: 0,
+        visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    },
+];
+
+/// Layout of the [`CameraUniform`] bind group shared by all 3D pipelines.
+pub fn mk_camera_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        entries: &CAMERA_ENTRIES,
+        label: Some("camera_bind_group_layout"),
+    })
 }
 
 #[derive(Debug, Clone)]
@@ -78,6 +195,41 @@ pub struct Camera {
     pitch: Rad<f32>,
 }
 
+/// Where a [`Camera`] is and where it looks, e.g. to save the view of an editor.
+///
+/// Enable the `serde` feature to serialize it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CameraPose {
+    pub position: Point3<f32>,
+    pub yaw: Rad<f32>,
+    pub pitch: Rad<f32>,
+}
+
+impl CameraPose {
+    /// Recovers the pose from a view matrix as built by [`Camera::calc_matrix`].
+    ///
+    /// Looking straight up or down the yaw is undefined and comes out as 0.
+    pub fn from_view_matrix(view: &Matrix4<f32>) -> Self {
+        // The rows of the rotation are the right, up and backward axes
+        let right = Vector3::new(view.x.x, view.y.x, view.z.x);
+        let up = Vector3::new(view.x.y, view.y.y, view.z.y);
+        let back = Vector3::new(view.x.z, view.y.z, view.z.z);
+        let position = -(right * view.w.x + up * view.w.y + back * view.w.z);
+        let forward = -back;
+        let yaw = if forward.x == 0.0 && forward.z == 0.0 {
+            0.0
+        } else {
+            forward.z.atan2(forward.x)
+        };
+        Self {
+            position: Point3::from_vec(position),
+            yaw: Rad(yaw),
+            pitch: Rad(forward.y.clamp(-1.0, 1.0).asin()),
+        }
+    }
+}
+
 impl Camera {
     pub fn new<V: Into<Point3<f32>>, Y: Into<Rad<f32>>, P: Into<Rad<f32>>>(
         position: V,
@@ -91,15 +243,47 @@ impl Camera {
         }
     }
 
+    pub fn pose(&self) -> CameraPose {
+        CameraPose {
+            position: self.position,
+            yaw: self.yaw,
+            pitch: self.pitch,
+        }
+    }
+
+    /// Moves the camera to `pose`. Use [`CameraResources::set_pose`] for the main camera so
+    /// its controller drops input collected for the old pose.
+    pub fn set_pose(&mut self, pose: &CameraPose) {
+        self.position = pose.position;
+        self.yaw = pose.yaw;
+        self.pitch = pose.pitch;
+    }
+
     pub fn calc_matrix(&self) -> Matrix4<f32> {
+        Matrix4::look_to_rh(self.position, self.forward(), Vector3::unit_y())
+    }
+
+    /// Unit vector the camera looks along.
+    fn forward(&self) -> Vector3<f32> {
         let (sin_pitch, cos_pitch) = self.pitch.0.sin_cos();
         let (sin_yaw, cos_yaw) = self.yaw.0.sin_cos();
+        Vector3::new(cos_pitch * cos_yaw, sin_pitch, cos_pitch * sin_yaw).normalize()
+    }
 
-        Matrix4::look_to_rh(
-            self.position,
-            Vector3::new(cos_pitch * cos_yaw, sin_pitch, cos_pitch * sin_yaw).normalize(),
-            Vector3::unit_y(),
-        )
+    /// Copy of the camera moved along its own right (x) and up (y) axes.
+    pub(crate) fn displaced(&self, offset: Vector2<f32>) -> Camera {
+        if offset.is_zero() {
+            return self.clone();
+        }
+        let (sin_pitch, cos_pitch) = self.pitch.0.sin_cos();
+        let (sin_yaw, cos_yaw) = self.yaw.0.sin_cos();
+        let forward = Vector3::new(cos_pitch * cos_yaw, sin_pitch, cos_pitch * sin_yaw);
+        let right = Vector3::new(-sin_yaw, 0.0, cos_yaw);
+        let up = right.cross(forward);
+        Camera {
+            position: self.position + right * offset.x + up * offset.y,
+            ..self.clone()
+        }
     }
 
     /**
@@ -121,6 +305,28 @@ impl Camera {
 
         ray_from_ndc(ndc.x, ndc.y, inv_proj_view, self.position)
     }
+
+    /**
+     * The inverse of [`Camera::cast_ray_from_mouse`]: maps a world position to window pixels,
+     * e.g. to place a health bar above a unit. The origin is the top left corner.
+     *
+     * Returns `None` for points behind the near plane.
+     */
+    pub fn world_to_screen(
+        &self,
+        world: Vector3<f32>,
+        width: f32,
+        height: f32,
+        projection: &Projection,
+    ) -> Option<PhysicalPosition<f64>> {
+        project_to_screen(
+            projection.calc_matrix() * self.calc_matrix(),
+            Point3::from_vec(world),
+            width,
+            height,
+            projection.reversed_z,
+        )
+    }
 }
 
 pub(crate) fn ray_from_ndc(
@@ -141,12 +347,53 @@ pub(crate) fn ray_from_ndc(
     }
 }
 
+/// Maps a world position to window pixels. Returns `None` for points behind the near plane.
+///
+/// `reversed_z` has to match the projection of `view_proj`, see [`Projection::reversed_z`].
+pub(crate) fn project_to_screen(
+    view_proj: Matrix4<f32>,
+    world: Point3<f32>,
+    width: f32,
+    height: f32,
+    reversed_z: bool,
+) -> Option<PhysicalPosition<f64>> {
+    let clip = view_proj * world.to_homogeneous();
+    if clip.w <= 0.0 {
+        return None;
+    }
+    let ndc = clip.truncate() / clip.w;
+    // wgpu's depth range is [0, 1], anything beyond the near plane's end is in front of it
+    let in_front_of_near = if reversed_z { ndc.z > 1.0 } else { ndc.z < 0.0 };
+    if in_front_of_near || !ndc.x.is_finite() || !ndc.y.is_finite() {
+        return None;
+    }
+    let x = (ndc.x + 1.0) * 0.5 * width;
+    let y = (1.0 - ndc.y) * 0.5 * height;
+    Some(PhysicalPosition::new(x as f64, y as f64))
+}
+
+/// World position of the window pixel `position` at `depth` in wgpu's `[0, 1]` depth range,
+/// the inverse of [`project_to_screen`]. `None` if `view_proj` can't be inverted.
+pub(crate) fn unproject_from_screen(
+    view_proj: Matrix4<f32>,
+    position: PhysicalPosition<f64>,
+    depth: f32,
+    width: f32,
+    height: f32,
+) -> Option<Vector3<f32>> {
+    let ndc = screen_to_ndc(position.x as f32, position.y as f32, width, height);
+    let world = view_proj.invert()? * ndc.truncate().extend(depth).extend(1.0);
+    (world.w != 0.0).then(|| world.truncate() / world.w)
+}
+
 #[derive(Debug)]
 pub struct Projection {
     aspect: f32,
     fovy: Rad<f32>,
     pub znear: f32,
     pub zfar: f32,
+    // Set by `Context::configure_depth`
+    reversed_z: bool,
 }
 
 impl Projection {
@@ -168,14 +415,46 @@ impl Projection {
             )
         )?;
         let aspect = width / height;
+        let fovy = fovy.into();
+        validate_projection(fovy, znear, zfar)?;
         Ok(Self {
             aspect,
-            fovy: fovy.into(),
+            fovy,
             znear,
             zfar,
+            reversed_z: false,
         })
     }
 
+    pub fn fovy(&self) -> Rad<f32> {
+        self.fovy
+    }
+
+    /**
+     * Changes the vertical field of view, e.g. narrowing it to zoom while aiming.
+     *
+     * Like the other setters it leaves the projection untouched and returns an error if the
+     * result would be degenerate. The camera uniform picks the change up next frame.
+     */
+    pub fn set_fovy<F: Into<Rad<f32>>>(&mut self, fovy: F) -> anyhow::Result<()> {
+        let fovy = fovy.into();
+        validate_projection(fovy, self.znear, self.zfar)?;
+        self.fovy = fovy;
+        Ok(())
+    }
+
+    pub fn set_znear(&mut self, znear: f32) -> anyhow::Result<()> {
+        validate_projection(self.fovy, znear, self.zfar)?;
+        self.znear = znear;
+        Ok(())
+    }
+
+    pub fn set_zfar(&mut self, zfar: f32) -> anyhow::Result<()> {
+        validate_projection(self.fovy, self.znear, zfar)?;
+        self.zfar = zfar;
+        Ok(())
+    }
+
     pub fn resize(&mut self, width: u32, height: u32) {
         let width = width.to_f32().unwrap_or(f32::MAX);
         let height = height.to_f32().unwrap_or(f32::MAX);
@@ -185,8 +464,87 @@ impl Projection {
         self.aspect = width / height;
     }
 
+    /// Whether [`Projection::calc_matrix`] maps the near plane to a depth of `1` and the far
+    /// plane to `0`. Follows the [`DepthConfig`](crate::data_structures::texture::DepthConfig)
+    /// of the context the projection is used with.
+    pub fn reversed_z(&self) -> bool {
+        self.reversed_z
+    }
+
+    pub(crate) fn set_reversed_z(&mut self, reversed_z: bool) {
+        self.reversed_z = reversed_z;
+    }
+
     pub fn calc_matrix(&self) -> Matrix4<f32> {
-        OPENGL_TO_WGPU_MATRIX * perspective(self.fovy, self.aspect, self.znear, self.zfar)
+        let matrix =
+            OPENGL_TO_WGPU_MATRIX * perspective(self.fovy, self.aspect, self.znear, self.zfar);
+        if self.reversed_z {
+            REVERSE_Z_MATRIX * matrix
+        } else {
+            matrix
+        }
+    }
+}
+
+fn validate_projection(fovy: Rad<f32>, znear: f32, zfar: f32) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        fovy.0 > 0.0 && fovy.0 < std::f32::consts::PI,
+        "Field of view must be between 0° and 180°, got {:?}",
+        Deg::from(fovy)
+    );
+    anyhow::ensure!(znear > 0.0, "Near plane must be positive, got {}", znear);
+    anyhow::ensure!(
+        zfar > znear,
+        "Far plane {} must lie beyond the near plane {}",
+        zfar,
+        znear
+    );
+    Ok(())
+}
+
+/// Input handling interface the engine drives every frame.
+///
+/// `handle_window_events` sees every window event, `handle_mouse` receives the
+/// mouse delta while the right button is held, `handle_scroll` receives wheel
+/// input and `update` applies the accumulated input to the [`Camera`] once per
+/// frame. Install a different implementation through `Out::Configure` by
+/// replacing `ctx.camera.controller`.
+///
+/// Touch gestures are translated into the same calls: dragging one finger into
+/// `handle_mouse`, pinching into `handle_scroll` and dragging two fingers into `handle_pan`.
+pub trait Controller: std::fmt::Debug + std::any::Any {
+    fn handle_window_events(&mut self, event: &WindowEvent) -> bool;
+    fn handle_mouse(&mut self, mouse_dx: f64, mouse_dy: f64);
+    fn handle_scroll(&mut self, delta: &MouseScrollDelta);
+    fn update(&mut self, camera: &mut Camera, dt: Duration);
+
+    /// Moves the view along with a two finger drag of `dx`, `dy` pixels. Ignored by default.
+    fn handle_pan(&mut self, _dx: f64, _dy: f64) {}
+
+    /// Moves like the movement keys held as far as an analog stick is deflected, `x` to the
+    /// right and `y` forward in `[-1, 1]`. Ignored by default.
+    fn handle_move(&mut self, _x: f32, _y: f32) {}
+
+    /// Handles a key press or release, replayed ones included, whose `KeyboardInput` events
+    /// winit doesn't let the engine recreate. Returns whether the key was used. Ignored by
+    /// default.
+    fn handle_key(&mut self, _key: KeyCode, _state: ElementState) -> bool {
+        false
+    }
+
+    /// Drops input collected since the last `update` and adopts the pose of `camera`. Called
+    /// after the camera was placed programmatically so the next frame doesn't jump.
+    fn reset(&mut self, _camera: &Camera) {}
+
+    /// Whether raw mouse motion should reach `handle_mouse` even when no button is held.
+    fn wants_raw_mouse(&self) -> bool {
+        false
+    }
+
+    /// Point the camera looks at from a distance, if any. Camera collision casts from it,
+    /// see [`CameraResources::set_collision`].
+    fn pivot(&self) -> Option<Point3<f32>> {
+        None
     }
 }
 
@@ -201,8 +559,17 @@ pub struct CameraController {
     rotate_horizontal: f32,
     rotate_vertical: f32,
     scroll: f32,
+    pan_horizontal: f32,
+    pan_vertical: f32,
     speed: f32,
     sensitivity: f32,
+    zoom_speed: f32,
+    zoom_inverted: bool,
+    min_pitch: Rad<f32>,
+    max_pitch: Rad<f32>,
+    zoom_limits: Option<(f32, f32)>,
+    // pitch after the last update, used to drop mouse input pushing past a limit
+    pitch: Option<Rad<f32>>,
 }
 
 impl CameraController {
@@ -217,12 +584,103 @@ impl CameraController {
             rotate_horizontal: 0.0,
             rotate_vertical: 0.0,
             scroll: 0.0,
+            pan_horizontal: 0.0,
+            pan_vertical: 0.0,
             speed,
             sensitivity,
+            zoom_speed: 1.0,
+            zoom_inverted: false,
+            min_pitch: Rad(-SAFE_FRAC_PI_2),
+            max_pitch: Rad(SAFE_FRAC_PI_2),
+            zoom_limits: None,
+            pitch: None,
+        }
+    }
+
+    pub fn min_pitch(&self) -> Deg<f32> {
+        self.min_pitch.into()
+    }
+
+    pub fn max_pitch(&self) -> Deg<f32> {
+        self.max_pitch.into()
+    }
+
+    /// Limits how far the camera can look down (`min`) and up (`max`).
+    ///
+    /// Both are kept just short of ±90° so the view never flips. Swapped arguments are reordered.
+    pub fn set_pitch_limits(&mut self, min: Deg<f32>, max: Deg<f32>) {
+        let min = Rad::from(min).0.clamp(-SAFE_FRAC_PI_2, SAFE_FRAC_PI_2);
+        let max = Rad::from(max).0.clamp(-SAFE_FRAC_PI_2, SAFE_FRAC_PI_2);
+        self.min_pitch = Rad(min.min(max));
+        self.max_pitch = Rad(min.max(max));
+    }
+
+    pub fn min_zoom_distance(&self) -> Option<f32> {
+        self.zoom_limits.map(|(min, _)| min)
+    }
+
+    pub fn max_zoom_distance(&self) -> Option<f32> {
+        self.zoom_limits.map(|(_, max)| max)
+    }
+
+    /// Limits scrolling to keep the distance between the camera and the floor point it
+    /// looks at (along the view ray to `y = 0`) within `[min, max]`.
+    ///
+    /// The limits only apply while the camera looks down at the floor from above. A camera
+    /// already outside the range can't scroll further out but isn't moved back.
+    pub fn set_zoom_limits(&mut self, min: f32, max: f32) {
+        let min = min.max(0.0);
+        self.zoom_limits = Some((min, max.max(min)));
+    }
+
+    /// Removes the zoom limits, which is the default.
+    pub fn clear_zoom_limits(&mut self) {
+        self.zoom_limits = None;
+    }
+
+    pub fn zoom_speed(&self) -> f32 {
+        self.zoom_speed
+    }
+
+    /// Scales how far one scroll notch moves the camera, on top of its speed. Defaults to 1.
+    /// Negative values are treated as 0, which disables scroll zoom.
+    pub fn set_zoom_speed(&mut self, zoom_speed: f32) {
+        if zoom_speed.is_finite() {
+            self.zoom_speed = zoom_speed.max(0.0);
+        }
+    }
+
+    pub fn zoom_inverted(&self) -> bool {
+        self.zoom_inverted
+    }
+
+    /// Flips the scroll direction for "natural" scrolling, where scrolling up moves the camera
+    /// towards the scene.
+    pub fn set_zoom_inverted(&mut self, inverted: bool) {
+        self.zoom_inverted = inverted;
+    }
+
+    /// Restricts a step of `step` units along the view direction `forward` to the zoom limits.
+    fn limit_zoom_step(&self, position: Point3<f32>, forward: Vector3<f32>, step: f32) -> f32 {
+        let Some((min, max)) = self.zoom_limits else {
+            return step;
+        };
+        if forward.y >= -f32::EPSILON || position.y <= 0.0 {
+            return step;
         }
+        let distance = position.y / -forward.y;
+        let target = distance - step;
+        let target = if step > 0.0 {
+            target.max(min.min(distance))
+        } else {
+            target.min(max.max(distance))
+        };
+        distance - target
     }
+}
 
-    pub fn handle_window_events(&mut self, event: &WindowEvent) -> bool {
+impl Controller for CameraController {
+    fn handle_window_events(&mut self, event: &WindowEvent) -> bool {
         if let WindowEvent::KeyboardInput {
             event:
                 KeyEvent {
@@ -233,46 +691,61 @@ impl CameraController {
             ..
         } = event
         {
-            let amount = if key_state.is_pressed() { 1.0 } else { 0.0 };
-            match key {
-                KeyCode::KeyW | KeyCode::ArrowUp => {
-                    self.amount_forward = amount;
-                    true
-                }
-                KeyCode::KeyS | KeyCode::ArrowDown => {
-                    self.amount_backward = amount;
-                    true
-                }
-                KeyCode::KeyA | KeyCode::ArrowLeft => {
-                    self.amount_left = amount;
-                    true
-                }
-                KeyCode::KeyD | KeyCode::ArrowRight => {
-                    self.amount_right = amount;
-                    true
-                }
-                KeyCode::Space => {
-                    self.amount_up = amount;
-                    true
-                }
-                KeyCode::ShiftLeft => {
-                    self.amount_down = amount;
-                    true
-                }
-                _ => false,
-            }
+            self.handle_key(*key, *key_state)
         } else {
             false
         }
     }
 
-    pub fn handle_mouse(&mut self, mouse_dx: f64, mouse_dy: f64) {
+    fn handle_key(&mut self, key: KeyCode, state: ElementState) -> bool {
+        let amount = if state.is_pressed() { 1.0 } else { 0.0 };
+        match key {
+            KeyCode::KeyW | KeyCode::ArrowUp => {
+                self.amount_forward = amount;
+                true
+            }
+            KeyCode::KeyS | KeyCode::ArrowDown => {
+                self.amount_backward = amount;
+                true
+            }
+            KeyCode::KeyA | KeyCode::ArrowLeft => {
+                self.amount_left = amount;
+                true
+            }
+            KeyCode::KeyD | KeyCode::ArrowRight => {
+                self.amount_right = amount;
+                true
+            }
+            KeyCode::Space => {
+                self.amount_up = amount;
+                true
+            }
+            KeyCode::ShiftLeft => {
+                self.amount_down = amount;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn handle_move(&mut self, x: f32, y: f32) {
+        self.amount_right = x.max(0.0);
+        self.amount_left = (-x).max(0.0);
+        self.amount_forward = y.max(0.0);
+        self.amount_backward = (-y).max(0.0);
+    }
+
+    fn handle_mouse(&mut self, mouse_dx: f64, mouse_dy: f64) {
         let dx = mouse_dx as f32;
         let dy = mouse_dy as f32;
         // handle f32 to f64 conversion without panicing:
         if dx.is_finite() && dy.is_finite() {
             self.rotate_horizontal = dx;
-            self.rotate_vertical = dy;
+            // Moving the mouse up (negative dy) raises the pitch
+            let at_limit = self.pitch.is_some_and(|pitch| {
+                (dy < 0.0 && pitch >= self.max_pitch) || (dy > 0.0 && pitch <= self.min_pitch)
+            });
+            self.rotate_vertical = if at_limit { 0.0 } else { dy };
         } else {
             log::warn!(
                 "Mouse coordinates of ({}, {}) are out of bounds and are not updated. The maximum supported coordinate value is {}.",
@@ -283,14 +756,33 @@ impl CameraController {
         }
     }
 
-    pub fn handle_scroll(&mut self, delta: &MouseScrollDelta) {
-        self.scroll = match delta {
-            MouseScrollDelta::LineDelta(_, scroll) => -scroll * 0.5,
-            MouseScrollDelta::PixelDelta(PhysicalPosition { y: scroll, .. }) => -*scroll as f32,
-        };
+    fn handle_scroll(&mut self, delta: &MouseScrollDelta) {
+        let direction = if self.zoom_inverted { 0.5 } else { -0.5 };
+        let scroll = scroll_lines(delta) * direction;
+        // Several wheel events can arrive per frame. Bound the sum so a stalled frame
+        // doesn't release a huge zoom at once.
+        if scroll.is_finite() {
+            self.scroll = (self.scroll + scroll).clamp(-MAX_PENDING_SCROLL, MAX_PENDING_SCROLL);
+        }
+    }
+
+    fn handle_pan(&mut self, dx: f64, dy: f64) {
+        if dx.is_finite() && dy.is_finite() {
+            self.pan_horizontal += dx as f32;
+            self.pan_vertical += dy as f32;
+        }
     }
 
-    pub fn update(&mut self, camera: &mut Camera, dt: Duration) {
+    fn reset(&mut self, camera: &Camera) {
+        self.rotate_horizontal = 0.0;
+        self.rotate_vertical = 0.0;
+        self.scroll = 0.0;
+        self.pan_horizontal = 0.0;
+        self.pan_vertical = 0.0;
+        self.pitch = Some(camera.pitch);
+    }
+
+    fn update(&mut self, camera: &mut Camera, dt: Duration) {
         let dt = dt.as_secs_f32();
 
         let (yaw_sin, yaw_cos) = camera.yaw.0.sin_cos();
@@ -299,6 +791,13 @@ impl CameraController {
         camera.position += forward * (self.amount_forward - self.amount_backward) * self.speed * dt;
         camera.position += right * (self.amount_right - self.amount_left) * self.speed * dt;
 
+        // Panning drags the ground along with the fingers
+        camera.position += (forward * self.pan_vertical - right * self.pan_horizontal)
+            * self.speed
+            * PAN_PER_PIXEL;
+        self.pan_horizontal = 0.0;
+        self.pan_vertical = 0.0;
+
         // Move in/out (aka. "zoom")
         // Note: this isn't an actual zoom. The camera's position
         // changes when zooming. I've added this to make it easier
@@ -306,7 +805,8 @@ impl CameraController {
         let (pitch_sin, pitch_cos) = camera.pitch.0.sin_cos();
         let scrollward =
             Vector3::new(pitch_cos * yaw_cos, pitch_sin, pitch_cos * yaw_sin).normalize();
-        camera.position += scrollward * self.scroll * self.speed * self.sensitivity * dt;
+        let step = self.scroll * self.zoom_speed * self.speed * self.sensitivity * dt;
+        camera.position += scrollward * self.limit_zoom_step(camera.position, scrollward, step);
         self.scroll = 0.0;
 
         // Move up/down. Since we don't use roll, we can just
@@ -324,102 +824,1224 @@ impl CameraController {
         self.rotate_vertical = 0.0;
 
         // Keep the camera's angle from going too high/low.
-        if camera.pitch < -Rad(SAFE_FRAC_PI_2) {
-            camera.pitch = -Rad(SAFE_FRAC_PI_2);
-        } else if camera.pitch > Rad(SAFE_FRAC_PI_2) {
-            camera.pitch = Rad(SAFE_FRAC_PI_2);
+        if camera.pitch < self.min_pitch {
+            camera.pitch = self.min_pitch;
+        } else if camera.pitch > self.max_pitch {
+            camera.pitch = self.max_pitch;
         }
+        self.pitch = Some(camera.pitch);
     }
 }
 
-#[derive(Debug)]
-pub struct CameraResources {
-    pub camera: Camera,
-    pub controller: CameraController,
-    pub uniform: CameraUniform,
-    pub buffer: wgpu::Buffer,
-    pub bind_group: wgpu::BindGroup,
-    pub bind_group_layout: wgpu::BindGroupLayout,
+/// Orbit (arcball) style controller rotating the camera around a target point.
+///
+/// Right-drag orbits around the target, middle-drag pans the target in the view
+/// plane and the scroll wheel dollies towards or away from it. The distance to the
+/// target is kept within `[min_radius, max_radius]` and the pitch stays short of
+/// the poles so the view never flips.
+#[derive(Debug, Clone)]
+pub struct OrbitController {
+    target: Point3<f32>,
+    radius: f32,
+    min_radius: f32,
+    max_radius: f32,
+    rotate_horizontal: f32,
+    rotate_vertical: f32,
+    pan_horizontal: f32,
+    pan_vertical: f32,
+    scroll: f32,
+    middle_pressed: bool,
+    last_cursor: Option<PhysicalPosition<f64>>,
+    sensitivity: f32,
 }
 
-#[cfg(kani)]
-mod kani_proofs {
-    use super::*;
+impl OrbitController {
+    pub fn new<P: Into<Point3<f32>>>(target: P, radius: f32, sensitivity: f32) -> Self {
+        Self {
+            target: target.into(),
+            radius: radius.max(f32::EPSILON),
+            min_radius: 0.5,
+            max_radius: 500.0,
+            rotate_horizontal: 0.0,
+            rotate_vertical: 0.0,
+            pan_horizontal: 0.0,
+            pan_vertical: 0.0,
+            scroll: 0.0,
+            middle_pressed: false,
+            last_cursor: None,
+            sensitivity,
+        }
+    }
 
-    #[kani::proof]
-    #[kani::unwind(1)]
-    fn verify_screen_to_ndc_x_range() {
-        let width: f32 = kani::any();
-        let height: f32 = kani::any();
-        kani::assume(width > 0.0 && width < 1e6 && width.is_finite());
-        kani::assume(height > 0.0 && height < 1e6 && height.is_finite());
-        let mouse_x: f32 = kani::any();
-        kani::assume(mouse_x >= 0.0 && mouse_x <= width);
-        let mouse_y: f32 = kani::any();
-        kani::assume(mouse_y >= 0.0 && mouse_y <= height);
-        let ndc = screen_to_ndc(mouse_x, mouse_y, width, height);
-        kani::assert(ndc.x >= -1.0 && ndc.x <= 1.0, "NDC x in [-1, 1]");
-        kani::assert(ndc.y >= -1.0 && ndc.y <= 1.0, "NDC y in [-1, 1]");
+    pub fn target(&self) -> Point3<f32> {
+        self.target
     }
 
-    #[kani::proof]
-    #[kani::unwind(1)]
-    fn verify_intersect_no_panic() {
-        let ox: f32 = kani::any();
-        let oy: f32 = kani::any();
-        let oz: f32 = kani::any();
-        let dx: f32 = kani::any();
-        let dy: f32 = kani::any();
-        let dz: f32 = kani::any();
-        kani::assume(ox.is_finite() && oy.is_finite() && oz.is_finite());
-        kani::assume(dx.is_finite() && dy.is_finite() && dz.is_finite());
-        let ray = Ray {
-            origin: cgmath::Point3::new(ox, oy, oz),
-            direction: cgmath::Vector3::new(dx, dy, dz),
-        };
-        let _ = ray.intersect_with_floor();
+    pub fn set_target<P: Into<Point3<f32>>>(&mut self, target: P) {
+        self.target = target.into();
     }
 
-    #[kani::proof]
-    #[kani::unwind(1)]
-    fn verify_intersect_upward_none() {
-        // An upward ray (dy > 0) from strictly above the floor (oy > 0) returns None.
-        let ox: f32 = kani::any();
-        let oy: f32 = kani::any();
-        let oz: f32 = kani::any();
-        let dx: f32 = kani::any();
-        let dy: f32 = kani::any();
-        let dz: f32 = kani::any();
-        kani::assume(ox.is_finite() && oz.is_finite());
-        kani::assume(dx.is_finite() && dz.is_finite());
-        kani::assume(oy > 0.0 && oy.is_finite());
-        kani::assume(dy > f32::EPSILON && dy.is_finite());
-        let ray = Ray {
-            origin: cgmath::Point3::new(ox, oy, oz),
-            direction: cgmath::Vector3::new(dx, dy, dz),
-        };
-        kani::assert(
-            ray.intersect_with_floor().is_none(),
-            "upward ray from above floor returns None",
-        );
+    pub fn radius(&self) -> f32 {
+        self.radius
     }
 
-    /// Verify that Projection::resize produces a finite aspect ratio
-    /// for any valid (non-zero) dimensions. This proof SHOULD FAIL:
-    /// resize(_, 0) divides by zero, producing infinity.
-    #[kani::proof]
-    #[kani::unwind(1)]
-    fn verify_resize_aspect_finite() {
-        let w: u32 = kani::any();
-        let h: u32 = kani::any();
-        kani::assume(w > 0 && w < 10000);
-        kani::assume(h > 0 && h < 10000);
-        let mut proj = Projection {
-            aspect: 1.0,
-            fovy: cgmath::Rad(std::f32::consts::FRAC_PI_4),
-            znear: 0.1,
-            zfar: 100.0,
-        };
+    /**
+     * Sets the allowed distance between camera and target.
+     *
+     * `min` is raised to a small positive value so the camera can never reach or pass the
+     * target and the current radius is clamped into the new range.
+     */
+    pub fn set_radius_limits(&mut self, min: f32, max: f32) {
+        self.min_radius = min.max(f32::EPSILON);
+        self.max_radius = max.max(self.min_radius);
+        self.radius = self.radius.clamp(self.min_radius, self.max_radius);
+    }
+}
+
+impl Controller for OrbitController {
+    fn handle_window_events(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::MouseInput {
+                button: MouseButton::Middle,
+                state,
+                ..
+            } => {
+                self.middle_pressed = state.is_pressed();
+                true
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                if let (true, Some(last)) = (self.middle_pressed, self.last_cursor) {
+                    self.pan_horizontal += (position.x - last.x) as f32;
+                    self.pan_vertical += (position.y - last.y) as f32;
+                }
+                self.last_cursor = Some(*position);
+                self.middle_pressed
+            }
+            _ => false,
+        }
+    }
+
+    fn handle_mouse(&mut self, mouse_dx: f64, mouse_dy: f64) {
+        let dx = mouse_dx as f32;
+        let dy = mouse_dy as f32;
+        if dx.is_finite() && dy.is_finite() {
+            self.rotate_horizontal = dx;
+            self.rotate_vertical = dy;
+        } else {
+            log::warn!(
+                "Mouse coordinates of ({}, {}) are out of bounds and are not updated. The maximum supported coordinate value is {}.",
+                mouse_dx,
+                mouse_dy,
+                f32::MAX
+            );
+        }
+    }
+
+    fn handle_scroll(&mut self, delta: &MouseScrollDelta) {
+        self.scroll = -scroll_lines(delta) * 0.5;
+    }
+
+    fn handle_pan(&mut self, dx: f64, dy: f64) {
+        if dx.is_finite() && dy.is_finite() {
+            self.pan_horizontal += dx as f32;
+            self.pan_vertical += dy as f32;
+        }
+    }
+
+    fn reset(&mut self, camera: &Camera) {
+        self.rotate_horizontal = 0.0;
+        self.rotate_vertical = 0.0;
+        self.pan_horizontal = 0.0;
+        self.pan_vertical = 0.0;
+        self.scroll = 0.0;
+        // keep orbiting around the point in front of the new pose
+        self.target = camera.position + camera.forward() * self.radius;
+    }
+
+    fn update(&mut self, camera: &mut Camera, dt: Duration) {
+        let dt = dt.as_secs_f32();
+
+        camera.yaw += Rad(self.rotate_horizontal * self.sensitivity * dt);
+        camera.pitch += Rad(-self.rotate_vertical * self.sensitivity * dt);
+        camera.pitch = Rad(camera.pitch.0.clamp(-SAFE_FRAC_PI_2, SAFE_FRAC_PI_2));
+        self.rotate_horizontal = 0.0;
+        self.rotate_vertical = 0.0;
+
+        // Scaling exponentially keeps the radius positive no matter how far we scroll.
+        self.radius = (self.radius * (self.scroll * self.sensitivity).exp())
+            .clamp(self.min_radius, self.max_radius);
+        self.scroll = 0.0;
+
+        let (pitch_sin, pitch_cos) = camera.pitch.0.sin_cos();
+        let (yaw_sin, yaw_cos) = camera.yaw.0.sin_cos();
+        let forward = Vector3::new(pitch_cos * yaw_cos, pitch_sin, pitch_cos * yaw_sin).normalize();
+        let right = forward.cross(Vector3::unit_y()).normalize();
+        let up = right.cross(forward);
+
+        // Pan proportionally to the distance so the target follows the cursor.
+        let pan_scale = self.radius * 0.002;
+        self.target += (-right * self.pan_horizontal + up * self.pan_vertical) * pan_scale;
+        self.pan_horizontal = 0.0;
+        self.pan_vertical = 0.0;
+
+        camera.position = self.target - forward * self.radius;
+    }
+
+    fn pivot(&self) -> Option<Point3<f32>> {
+        Some(self.target)
+    }
+}
+
+/// First-person controller walking on the ground plane.
+///
+/// WASD/arrow keys move along the horizontal view direction, the mouse looks around
+/// without holding a button (see [`Controller::wants_raw_mouse`]) and the camera is kept at
+/// `eye_height` above y = 0. Combine with `Context::set_cursor_grab` and
+/// `Context::set_cursor_visible` to keep the cursor out of the way.
+#[derive(Debug, Clone)]
+pub struct FpsController {
+    amount_left: f32,
+    amount_right: f32,
+    amount_forward: f32,
+    amount_backward: f32,
+    rotate_horizontal: f32,
+    rotate_vertical: f32,
+    speed: f32,
+    sensitivity: f32,
+    eye_height: f32,
+}
+
+impl FpsController {
+    /// Maximum pitch of ±89° so the view never reaches the poles.
+    pub const MAX_PITCH: Deg<f32> = Deg(89.0);
+
+    /// `speed` is in units per second, `sensitivity` in radians per unit of mouse motion.
+    pub fn new(speed: f32, sensitivity: f32, eye_height: f32) -> Self {
+        Self {
+            amount_left: 0.0,
+            amount_right: 0.0,
+            amount_forward: 0.0,
+            amount_backward: 0.0,
+            rotate_horizontal: 0.0,
+            rotate_vertical: 0.0,
+            speed,
+            sensitivity,
+            eye_height,
+        }
+    }
+
+    pub fn eye_height(&self) -> f32 {
+        self.eye_height
+    }
+
+    pub fn set_eye_height(&mut self, eye_height: f32) {
+        self.eye_height = eye_height;
+    }
+}
+
+impl Controller for FpsController {
+    fn handle_window_events(&mut self, event: &WindowEvent) -> bool {
+        if let WindowEvent::KeyboardInput {
+            event:
+                KeyEvent {
+                    physical_key: PhysicalKey::Code(key),
+                    state: key_state,
+                    ..
+                },
+            ..
+        } = event
+        {
+            self.handle_key(*key, *key_state)
+        } else {
+            false
+        }
+    }
+
+    fn handle_key(&mut self, key: KeyCode, state: ElementState) -> bool {
+        let amount = if state.is_pressed() { 1.0 } else { 0.0 };
+        match key {
+            KeyCode::KeyW | KeyCode::ArrowUp => self.amount_forward = amount,
+            KeyCode::KeyS | KeyCode::ArrowDown => self.amount_backward = amount,
+            KeyCode::KeyA | KeyCode::ArrowLeft => self.amount_left = amount,
+            KeyCode::KeyD | KeyCode::ArrowRight => self.amount_right = amount,
+            _ => return false,
+        }
+        true
+    }
+
+    fn handle_move(&mut self, x: f32, y: f32) {
+        self.amount_right = x.max(0.0);
+        self.amount_left = (-x).max(0.0);
+        self.amount_forward = y.max(0.0);
+        self.amount_backward = (-y).max(0.0);
+    }
+
+    fn handle_mouse(&mut self, mouse_dx: f64, mouse_dy: f64) {
+        let dx = mouse_dx as f32;
+        let dy = mouse_dy as f32;
+        // Raw motion arrives several times per frame so it is accumulated until `update`
+        if dx.is_finite() && dy.is_finite() {
+            self.rotate_horizontal += dx;
+            self.rotate_vertical += dy;
+        } else {
+            log::warn!(
+                "Mouse coordinates of ({}, {}) are out of bounds and are not updated. The maximum supported coordinate value is {}.",
+                mouse_dx,
+                mouse_dy,
+                f32::MAX
+            );
+        }
+    }
+
+    fn handle_scroll(&mut self, _delta: &MouseScrollDelta) {}
+
+    fn update(&mut self, camera: &mut Camera, dt: Duration) {
+        let dt = dt.as_secs_f32();
+
+        // Mouse deltas are distances already, so they are not scaled by dt
+        camera.yaw += Rad(self.rotate_horizontal * self.sensitivity);
+        camera.pitch += Rad(-self.rotate_vertical * self.sensitivity);
+        let max_pitch: Rad<f32> = Self::MAX_PITCH.into();
+        camera.pitch = Rad(camera.pitch.0.clamp(-max_pitch.0, max_pitch.0));
+        self.rotate_horizontal = 0.0;
+        self.rotate_vertical = 0.0;
+
+        let (yaw_sin, yaw_cos) = camera.yaw.0.sin_cos();
+        let forward = Vector3::new(yaw_cos, 0.0, yaw_sin);
+        let right = Vector3::new(-yaw_sin, 0.0, yaw_cos);
+        let mut movement = forward * (self.amount_forward - self.amount_backward)
+            + right * (self.amount_right - self.amount_left);
+        // Walking diagonally shouldn't be faster
+        if movement.magnitude2() > 1.0 {
+            movement = movement.normalize();
+        }
+        camera.position += movement * self.speed * dt;
+        camera.position.y = self.eye_height;
+    }
+
+    fn reset(&mut self, _camera: &Camera) {
+        self.rotate_horizontal = 0.0;
+        self.rotate_vertical = 0.0;
+    }
+
+    fn wants_raw_mouse(&self) -> bool {
+        true
+    }
+}
+
+/// What the camera follows, see [`CameraResources::set_follow`].
+pub enum FollowTarget {
+    Point(Point3<f32>),
+    /// Queried every frame, e.g. reading a position shared with the flow's state.
+    Provider(Box<dyn Fn() -> Point3<f32>>),
+}
+
+impl FollowTarget {
+    pub fn provider(f: impl Fn() -> Point3<f32> + 'static) -> Self {
+        FollowTarget::Provider(Box::new(f))
+    }
+
+    fn position(&self) -> Point3<f32> {
+        match self {
+            FollowTarget::Point(p) => *p,
+            FollowTarget::Provider(f) => f(),
+        }
+    }
+}
+
+impl From<Point3<f32>> for FollowTarget {
+    fn from(p: Point3<f32>) -> Self {
+        FollowTarget::Point(p)
+    }
+}
+
+impl std::fmt::Debug for FollowTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FollowTarget::Point(p) => f.debug_tuple("Point").field(p).finish(),
+            FollowTarget::Provider(_) => f.write_str("Provider"),
+        }
+    }
+}
+
+/// Critically damped spring towards `target` (see "Game Programming Gems 4", 1.10).
+///
+/// Unlike lerping by `dt` this converges the same way regardless of the frame rate and
+/// never overshoots. `smooth_time` is roughly the time to reach the target.
+pub(crate) fn smooth_damp(
+    current: Vector3<f32>,
+    target: Vector3<f32>,
+    velocity: &mut Vector3<f32>,
+    smooth_time: f32,
+    dt: f32,
+) -> Vector3<f32> {
+    if smooth_time <= 0.0 {
+        *velocity = Vector3::zero();
+        return target;
+    }
+    let omega = 2.0 / smooth_time;
+    let x = omega * dt;
+    let exp = 1.0 / (1.0 + x + 0.48 * x * x + 0.235 * x * x * x);
+    let change = current - target;
+    let temp = (*velocity + change * omega) * dt;
+    *velocity = (*velocity - temp * omega) * exp;
+    target + (change + temp) * exp
+}
+
+/// State of an active follow, see [`CameraResources::set_follow`].
+#[derive(Debug)]
+pub struct Follow {
+    target: FollowTarget,
+    offset: Vector3<f32>,
+    smoothing: f32,
+    velocity: Vector3<f32>,
+    /// Orientation written last frame, used to detect rotation input from the controller.
+    last_yaw: Option<Rad<f32>>,
+    last_pitch: Option<Rad<f32>>,
+}
+
+impl Follow {
+    pub fn new(target: impl Into<FollowTarget>, offset: Vector3<f32>, smoothing: f32) -> Self {
+        Self {
+            target: target.into(),
+            offset,
+            smoothing,
+            velocity: Vector3::zero(),
+            last_yaw: None,
+            last_pitch: None,
+        }
+    }
+
+    pub fn offset(&self) -> Vector3<f32> {
+        self.offset
+    }
+
+    pub(crate) fn target_position(&self) -> Point3<f32> {
+        self.target.position()
+    }
+
+    /**
+     * Eases the camera towards `target + offset` and points it at the target.
+     *
+     * Rotation the controller applied since the last frame rotates the offset around the
+     * target, so user input orbits the followed object instead of the world origin.
+     */
+    pub fn apply(&mut self, camera: &mut Camera, dt: Duration) {
+        let distance = self.offset.magnitude();
+        if let (Some(last_yaw), Some(last_pitch), true) =
+            (self.last_yaw, self.last_pitch, distance > f32::EPSILON)
+        {
+            let direction = -self.offset / distance;
+            let yaw = Rad(direction.z.atan2(direction.x)) + (camera.yaw - last_yaw);
+            let pitch = Rad(direction.y.asin()) + (camera.pitch - last_pitch);
+            let pitch = pitch.0.clamp(-SAFE_FRAC_PI_2, SAFE_FRAC_PI_2);
+            let (pitch_sin, pitch_cos) = pitch.sin_cos();
+            let (yaw_sin, yaw_cos) = yaw.0.sin_cos();
+            self.offset = -Vector3::new(pitch_cos * yaw_cos, pitch_sin, pitch_cos * yaw_sin) * distance;
+        }
+
+        let target = self.target.position();
+        let desired = target.to_vec() + self.offset;
+        camera.position = Point3::from_vec(smooth_damp(
+            camera.position.to_vec(),
+            desired,
+            &mut self.velocity,
+            self.smoothing,
+            dt.as_secs_f32(),
+        ));
+
+        let look = target - camera.position;
+        if look.magnitude2() > f32::EPSILON {
+            let look = look.normalize();
+            camera.yaw = Rad(look.z.atan2(look.x));
+            camera.pitch = Rad(look.y.clamp(-1.0, 1.0).asin().clamp(-SAFE_FRAC_PI_2, SAFE_FRAC_PI_2));
+        }
+        self.last_yaw = Some(camera.yaw);
+        self.last_pitch = Some(camera.pitch);
+    }
+}
+
+/// Geometry the camera can't move through, see [`CameraResources::set_collision`].
+pub enum Occluder {
+    /// A world space box, e.g. [`crate::data_structures::model::Model::bounds`] moved by
+    /// its instance.
+    Box(Aabb),
+    /// Terrain height at a world x and z.
+    Ground(Box<dyn Fn(f32, f32) -> f32>),
+}
+
+impl Occluder {
+    pub fn ground(height: impl Fn(f32, f32) -> f32 + 'static) -> Self {
+        Occluder::Ground(Box::new(height))
+    }
+
+    /// Distance along `ray` to the first hit within `max`. Occluders the ray starts in are
+    /// ignored so the bounds of the followed object itself can be part of the set.
+    fn intersect(&self, ray: &Ray, max: f32) -> Option<f32> {
+        match self {
+            Occluder::Box(aabb) => ray
+                .intersect_aabb(aabb)
+                .filter(|&distance| distance > 0.0 && distance <= max),
+            Occluder::Ground(height) => {
+                let below = |distance: f32| {
+                    let p = ray.origin + ray.direction * distance;
+                    p.y < height(p.x, p.z)
+                };
+                if below(0.0) {
+                    return None;
+                }
+                // March to the first sample below the ground, then bisect the step
+                let step = max / GROUND_SAMPLES as f32;
+                let end = (1..=GROUND_SAMPLES)
+                    .map(|i| i as f32 * step)
+                    .find(|&distance| below(distance))?;
+                let (mut above, mut under) = (end - step, end);
+                for _ in 0..GROUND_REFINEMENTS {
+                    let mid = (above + under) / 2.0;
+                    if below(mid) {
+                        under = mid;
+                    } else {
+                        above = mid;
+                    }
+                }
+                Some(above)
+            }
+        }
+    }
+}
+
+const GROUND_SAMPLES: u32 = 32;
+const GROUND_REFINEMENTS: u32 = 8;
+
+impl From<Aabb> for Occluder {
+    fn from(aabb: Aabb) -> Self {
+        Occluder::Box(aabb)
+    }
+}
+
+impl std::fmt::Debug for Occluder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Occluder::Box(aabb) => f.debug_tuple("Box").field(aabb).finish(),
+            Occluder::Ground(_) => f.write_str("Ground"),
+        }
+    }
+}
+
+/// Keeps the camera in front of occluders, see [`CameraResources::set_collision`].
+#[derive(Debug)]
+pub struct CollisionConfig {
+    pub occluders: Vec<Occluder>,
+    /// Distance kept between the camera and the first hit.
+    pub min_distance: f32,
+    /// Roughly the seconds the camera takes to move back out once nothing is in the way.
+    /// Moving in is immediate so the occluder never covers the view.
+    pub smoothing: f32,
+}
+
+impl Default for CollisionConfig {
+    fn default() -> Self {
+        Self {
+            occluders: Vec::new(),
+            min_distance: 0.2,
+            smoothing: 0.3,
+        }
+    }
+}
+
+/// State of an active camera collision, see [`CameraResources::set_collision`].
+#[derive(Debug)]
+pub(crate) struct CameraCollision {
+    pub(crate) config: CollisionConfig,
+    // Distance from the pivot after the last frame, `None` while unobstructed
+    distance: Option<f32>,
+    // Positions before and after the last constraint, restored so the controller and
+    // follow keep easing towards where the camera wants to be
+    unconstrained: Option<(Point3<f32>, Point3<f32>)>,
+}
+
+impl CameraCollision {
+    pub(crate) fn new(config: CollisionConfig) -> Self {
+        Self {
+            config,
+            distance: None,
+            unconstrained: None,
+        }
+    }
+
+    /// Puts the camera back where the controller left it, unless it was moved since.
+    fn restore(&mut self, camera: &mut Camera) {
+        if let Some((unconstrained, constrained)) = self.unconstrained.take()
+            && camera.position == constrained
+        {
+            camera.position = unconstrained;
+        }
+    }
+
+    /// Pulls the camera towards `pivot` in front of the first occluder in between.
+    fn constrain(&mut self, camera: &mut Camera, pivot: Point3<f32>, dt: Duration) {
+        let offset = camera.position - pivot;
+        let desired = offset.magnitude();
+        if self.config.occluders.is_empty() || desired <= f32::EPSILON {
+            self.distance = None;
+            return;
+        }
+        let ray = Ray {
+            origin: pivot,
+            direction: offset / desired,
+        };
+        let allowed = self
+            .config
+            .occluders
+            .iter()
+            .filter_map(|occluder| occluder.intersect(&ray, desired))
+            .min_by(f32::total_cmp)
+            .map_or(desired, |hit| (hit - self.config.min_distance).max(0.0));
+        let distance = match self.distance {
+            Some(current) if current < allowed && self.config.smoothing > 0.0 => {
+                let t = 1.0 - (-dt.as_secs_f32() / self.config.smoothing).exp();
+                current + (allowed - current) * t
+            }
+            _ => allowed,
+        };
+        // Easing only approaches the desired distance, snap the last bit
+        if distance >= desired * 0.999 {
+            self.distance = None;
+            return;
+        }
+        self.distance = Some(distance);
+        let constrained = pivot + ray.direction * distance;
+        self.unconstrained = Some((camera.position, constrained));
+        camera.position = constrained;
+    }
+}
+
+/// Easing curve of a camera animation, see [`CameraResources::animate_to`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Easing {
+    Linear,
+    /// Starts and ends slowly.
+    #[default]
+    Smoothstep,
+}
+
+impl Easing {
+    /// Maps the linear progress `t` in `[0, 1]` onto the curve.
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::Smoothstep => t * t * (3.0 - 2.0 * t),
+        }
+    }
+}
+
+/// An in-flight camera animation, see [`CameraResources::animate_to`].
+#[derive(Debug, Clone)]
+pub struct CameraTween {
+    from_position: Point3<f32>,
+    from_yaw: Rad<f32>,
+    from_pitch: Rad<f32>,
+    to_position: Point3<f32>,
+    // Relative to `from_yaw` along the shorter way around
+    yaw_delta: Rad<f32>,
+    to_pitch: Rad<f32>,
+    duration: f32,
+    elapsed: f32,
+    easing: Easing,
+}
+
+impl CameraTween {
+    pub fn new(
+        camera: &Camera,
+        position: Point3<f32>,
+        yaw: Rad<f32>,
+        pitch: Rad<f32>,
+        duration: Duration,
+        easing: Easing,
+    ) -> Self {
+        Self {
+            from_position: camera.position,
+            from_yaw: camera.yaw,
+            from_pitch: camera.pitch,
+            to_position: position,
+            yaw_delta: (yaw - camera.yaw).normalize_signed(),
+            to_pitch: Rad(pitch.0.clamp(-SAFE_FRAC_PI_2, SAFE_FRAC_PI_2)),
+            duration: duration.as_secs_f32(),
+            elapsed: 0.0,
+            easing,
+        }
+    }
+
+    /// Advances the animation by `dt` and moves `camera` accordingly. Returns `true` once the
+    /// target pose is reached.
+    pub fn apply(&mut self, camera: &mut Camera, dt: Duration) -> bool {
+        self.elapsed += dt.as_secs_f32();
+        let progress = if self.duration > 0.0 {
+            self.elapsed / self.duration
+        } else {
+            1.0
+        };
+        let t = self.easing.apply(progress);
+        camera.position = self.from_position + (self.to_position - self.from_position) * t;
+        camera.yaw = self.from_yaw + self.yaw_delta * t;
+        camera.pitch = self.from_pitch + (self.to_pitch - self.from_pitch) * t;
+        progress >= 1.0
+    }
+}
+
+/// A camera pose at a point in time of a [`CameraPath`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CameraKeyframe {
+    /// Time since the start of the path.
+    pub time: Duration,
+    pub position: Point3<f32>,
+    pub yaw: Rad<f32>,
+    pub pitch: Rad<f32>,
+}
+
+impl CameraKeyframe {
+    pub fn new(
+        time: Duration,
+        position: impl Into<Point3<f32>>,
+        yaw: impl Into<Rad<f32>>,
+        pitch: impl Into<Rad<f32>>,
+    ) -> Self {
+        Self {
+            time,
+            position: position.into(),
+            yaw: yaw.into(),
+            pitch: pitch.into(),
+        }
+    }
+
+    fn forward(&self) -> Vector3<f32> {
+        let (sin_pitch, cos_pitch) = self.pitch.0.sin_cos();
+        let (sin_yaw, cos_yaw) = self.yaw.0.sin_cos();
+        Vector3::new(cos_pitch * cos_yaw, sin_pitch, cos_pitch * sin_yaw)
+    }
+}
+
+/**
+ * A fly-through for [`CameraResources::play`], e.g. for cinematics.
+ *
+ * Positions follow a Catmull-Rom spline through all keyframes, the view direction turns at
+ * a constant rate between two keyframes.
+ */
+#[derive(Debug, Clone)]
+pub struct CameraPath {
+    keyframes: Vec<CameraKeyframe>,
+}
+
+impl CameraPath {
+    /// Fails for an empty path or keyframes not ordered by strictly increasing time.
+    pub fn new(keyframes: Vec<CameraKeyframe>) -> anyhow::Result<Self> {
+        anyhow::ensure!(!keyframes.is_empty(), "A camera path needs at least one keyframe");
+        if let Some(i) = keyframes.windows(2).position(|k| k[1].time <= k[0].time) {
+            anyhow::bail!(
+                "Camera keyframe {} at {:?} doesn't come after keyframe {} at {:?}",
+                i + 1,
+                keyframes[i + 1].time,
+                i,
+                keyframes[i].time
+            );
+        }
+        Ok(Self { keyframes })
+    }
+
+    pub fn keyframes(&self) -> &[CameraKeyframe] {
+        &self.keyframes
+    }
+
+    /// Time of the last keyframe.
+    pub fn duration(&self) -> Duration {
+        self.keyframes[self.keyframes.len() - 1].time
+    }
+
+    /// Pose `time` seconds into the path as position, yaw and pitch, clamped to its ends.
+    pub fn sample(&self, time: f32) -> (Point3<f32>, Rad<f32>, Rad<f32>) {
+        let keys = &self.keyframes;
+        let next = keys.partition_point(|k| k.time.as_secs_f32() <= time);
+        if next == 0 || next == keys.len() {
+            let k = if next == 0 { &keys[0] } else { &keys[keys.len() - 1] };
+            return (k.position, k.yaw, k.pitch);
+        }
+        let (k1, k2) = (&keys[next - 1], &keys[next]);
+        let k0 = &keys[next.saturating_sub(2)];
+        let k3 = &keys[(next + 1).min(keys.len() - 1)];
+        let (t1, t2) = (k1.time.as_secs_f32(), k2.time.as_secs_f32());
+        let t = (time - t1) / (t2 - t1);
+
+        let position = catmull_rom(k0.position, k1.position, k2.position, k3.position, t);
+        let forward = slerp_direction(k1.forward(), k2.forward(), t);
+        let (yaw, pitch) = if forward.magnitude2() > 0.0 {
+            (
+                Rad(forward.z.atan2(forward.x)),
+                Rad(forward.y.clamp(-1.0, 1.0).asin()),
+            )
+        } else {
+            // Opposite directions, turn around the vertical axis instead
+            (
+                k1.yaw + (k2.yaw - k1.yaw).normalize_signed() * t,
+                k1.pitch + (k2.pitch - k1.pitch) * t,
+            )
+        };
+        (
+            position,
+            yaw,
+            Rad(pitch.0.clamp(-SAFE_FRAC_PI_2, SAFE_FRAC_PI_2)),
+        )
+    }
+}
+
+/// Uniform Catmull-Rom spline through `p1` (t = 0) and `p2` (t = 1).
+fn catmull_rom(
+    p0: Point3<f32>,
+    p1: Point3<f32>,
+    p2: Point3<f32>,
+    p3: Point3<f32>,
+    t: f32,
+) -> Point3<f32> {
+    let (p0, p1, p2, p3) = (p0.to_vec(), p1.to_vec(), p2.to_vec(), p3.to_vec());
+    let (t2, t3) = (t * t, t * t * t);
+    let v = (p1 * 2.0
+        + (p2 - p0) * t
+        + (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * t2
+        + (p1 * 3.0 - p0 - p2 * 3.0 + p3) * t3)
+        * 0.5;
+    Point3::from_vec(v)
+}
+
+/// Spherical interpolation of unit vectors, zero if they point in opposite directions.
+fn slerp_direction(from: Vector3<f32>, to: Vector3<f32>, t: f32) -> Vector3<f32> {
+    let cos = from.dot(to).clamp(-1.0, 1.0);
+    if cos > 0.9995 {
+        return from.lerp(to, t).normalize();
+    }
+    if cos < -0.9995 {
+        return Vector3::zero();
+    }
+    let angle = cos.acos();
+    (from * ((1.0 - t) * angle).sin() + to * (t * angle).sin()) / angle.sin()
+}
+
+/// A [`CameraPath`] being played, see [`CameraResources::play`].
+#[derive(Debug, Clone)]
+pub(crate) struct PathPlayback {
+    path: CameraPath,
+    elapsed: f32,
+    looped: bool,
+}
+
+impl PathPlayback {
+    /// Advances playback by `dt` and moves `camera` accordingly. Returns `true` once a path
+    /// that doesn't loop reached its last keyframe.
+    fn apply(&mut self, camera: &mut Camera, dt: Duration) -> bool {
+        self.elapsed += dt.as_secs_f32();
+        // A single keyframe has nothing to move along or loop over
+        let single = self.path.keyframes.len() == 1;
+        let duration = self.path.duration().as_secs_f32();
+        if self.looped && !single {
+            self.elapsed %= duration;
+        }
+        let (position, yaw, pitch) = self.path.sample(self.elapsed);
+        camera.position = position;
+        camera.yaw = yaw;
+        camera.pitch = pitch;
+        single || (!self.looped && self.elapsed >= duration)
+    }
+}
+
+/// A decaying camera shake, see [`CameraResources::shake`].
+#[derive(Debug, Clone)]
+pub struct CameraShake {
+    amplitude: f32,
+    frequency: f32,
+    duration: f32,
+    elapsed: f32,
+    // Phase offset so overlapping shakes don't move in lockstep
+    seed: f32,
+}
+
+impl CameraShake {
+    pub fn new(amplitude: f32, frequency: f32, duration: Duration, seed: u32) -> Self {
+        Self {
+            amplitude,
+            frequency,
+            duration: duration.as_secs_f32(),
+            elapsed: 0.0,
+            seed: seed as f32 * 7.31,
+        }
+    }
+
+    /// Advances the shake by `dt`. Returns `true` once it has fully decayed.
+    pub fn advance(&mut self, dt: Duration) -> bool {
+        self.elapsed += dt.as_secs_f32();
+        self.elapsed >= self.duration
+    }
+
+    /// Current displacement along the camera's right (x) and up (y) axes.
+    pub fn offset(&self) -> Vector2<f32> {
+        if self.elapsed >= self.duration {
+            return Vector2::zero();
+        }
+        let decay = (1.0 - self.elapsed / self.duration).powi(2);
+        let t = std::f32::consts::TAU * self.frequency * self.elapsed;
+        Vector2::new(shake_noise(t, self.seed), shake_noise(t, self.seed + 3.7))
+            * (self.amplitude * decay)
+    }
+}
+
+/// Smooth noise in `[-1, 1]`. Incommensurate frequencies keep it from looking periodic.
+fn shake_noise(t: f32, seed: f32) -> f32 {
+    0.5 * (t + seed).sin() + 0.3 * (1.7 * t + 2.3 * seed).sin() + 0.2 * (2.9 * t + 4.1 * seed).sin()
+}
+
+#[derive(Debug)]
+pub struct CameraResources {
+    pub camera: Camera,
+    pub controller: Box<dyn Controller>,
+    pub follow: Option<Follow>,
+    pub(crate) collision: Option<CameraCollision>,
+    pub(crate) tween: Option<CameraTween>,
+    pub(crate) tween_finished: bool,
+    pub(crate) path: Option<PathPlayback>,
+    pub(crate) shakes: Vec<CameraShake>,
+    // Number of shakes started so far, seeds the next one
+    pub(crate) shake_count: u32,
+    pub uniform: CameraUniform,
+    pub buffer: wgpu::Buffer,
+    pub bind_group: wgpu::BindGroup,
+    pub bind_group_layout: wgpu::BindGroupLayout,
+}
+
+impl CameraResources {
+    /**
+     * Makes the camera follow `target` at `offset`, easing with a critically damped spring
+     * that takes roughly `smoothing` seconds to catch up (0 snaps). Rotation input of the
+     * controller orbits around the target while following.
+     */
+    pub fn set_follow(
+        &mut self,
+        target: impl Into<FollowTarget>,
+        offset: Vector3<f32>,
+        smoothing: f32,
+    ) {
+        self.follow = Some(Follow::new(target, offset, smoothing));
+    }
+
+    /// Stops following and returns full control to the controller.
+    pub fn clear_follow(&mut self) {
+        self.follow = None;
+    }
+
+    /**
+     * Keeps the camera from passing through `config.occluders`, e.g. walls behind a third
+     * person camera.
+     *
+     * Every frame a ray is cast from the followed target, or the point the controller orbits,
+     * towards the camera and the camera is pulled in front of the first hit. Without such a
+     * point or occluders the camera moves freely.
+     */
+    pub fn set_collision(&mut self, config: CollisionConfig) {
+        self.collision = Some(CameraCollision::new(config));
+    }
+
+    pub fn clear_collision(&mut self) {
+        if let Some(mut collision) = self.collision.take() {
+            collision.restore(&mut self.camera);
+        }
+    }
+
+    /// Occluders of the active collision, e.g. to add buildings as they're placed.
+    pub fn occluders_mut(&mut self) -> Option<&mut Vec<Occluder>> {
+        self.collision
+            .as_mut()
+            .map(|collision| &mut collision.config.occluders)
+    }
+
+    /**
+     * Flies the camera to `position` looking along `yaw` and `pitch` over `duration`.
+     *
+     * The engine advances the animation every frame, ignoring controller input and any
+     * follow until it's done. Calling it again replaces the animation or path in flight,
+     * starting from wherever the camera currently is.
+     */
+    pub fn animate_to(
+        &mut self,
+        position: impl Into<Point3<f32>>,
+        yaw: impl Into<Rad<f32>>,
+        pitch: impl Into<Rad<f32>>,
+        duration: Duration,
+        easing: Easing,
+    ) {
+        self.path = None;
+        self.tween = Some(CameraTween::new(
+            &self.camera,
+            position.into(),
+            yaw.into(),
+            pitch.into(),
+            duration,
+            easing,
+        ));
+    }
+
+    /**
+     * Flies the camera along `path`, starting at its first keyframe right away.
+     *
+     * Like [`CameraResources::animate_to`] the engine advances it with the frame time and
+     * controller input is ignored until it's done. A `looped` path jumps back to its start
+     * and plays until cancelled, the others finish at their last keyframe. Playing replaces
+     * the animation or path in flight.
+     */
+    pub fn play(&mut self, path: CameraPath, looped: bool) {
+        self.tween = None;
+        self.path = Some(PathPlayback {
+            path,
+            elapsed: 0.0,
+            looped,
+        });
+    }
+
+    /// Places the camera at `pose`, e.g. when restoring a saved view. Stops any animation or
+    /// path in flight and input the controller collected for the old pose.
+    pub fn set_pose(&mut self, pose: &CameraPose) {
+        self.cancel_animation();
+        self.camera.set_pose(pose);
+        self.controller.reset(&self.camera);
+    }
+
+    /// Stops the animation or path in flight, leaving the camera where it currently is.
+    pub fn cancel_animation(&mut self) {
+        self.tween = None;
+        self.path = None;
+    }
+
+    /// Whether an animation or path is in flight.
+    pub fn is_animating(&self) -> bool {
+        self.tween.is_some() || self.path.is_some()
+    }
+
+    /// `true` during the frame an animation reached its target or a path its end, e.g. to
+    /// open the info panel of the building the camera flew to.
+    pub fn animation_finished(&self) -> bool {
+        self.tween_finished
+    }
+
+    /**
+     * Shakes the rendered view by up to `amplitude` world units along the camera's right and
+     * up axes, `frequency` times per second, fading out over `duration`, e.g. for explosions.
+     *
+     * Only the view matrix is displaced, `camera` keeps its pose so the view returns exactly
+     * to it and [`Camera::cast_ray_from_mouse`] stays accurate. Overlapping shakes add up.
+     */
+    pub fn shake(&mut self, amplitude: f32, frequency: f32, duration: Duration) {
+        self.shakes.push(CameraShake::new(
+            amplitude,
+            frequency,
+            duration,
+            self.shake_count,
+        ));
+        self.shake_count = self.shake_count.wrapping_add(1);
+    }
+
+    pub fn is_shaking(&self) -> bool {
+        !self.shakes.is_empty()
+    }
+
+    /// The camera the scene is rendered from: [`CameraResources::camera`] displaced by all
+    /// active shakes.
+    pub fn view_camera(&self) -> Camera {
+        let offset = self
+            .shakes
+            .iter()
+            .fold(Vector2::zero(), |sum, shake| sum + shake.offset());
+        self.camera.displaced(offset)
+    }
+
+    /// Applies controller input (and an active follow) to the camera for this frame.
+    pub fn update(&mut self, dt: Duration) {
+        self.advance(dt, true);
+    }
+
+    /// Like [`CameraResources::update`], leaving out the controller unless `controller`, see
+    /// [`crate::context::Context::camera_controller_enabled`].
+    pub(crate) fn advance(&mut self, dt: Duration, controller: bool) {
+        self.shakes.retain_mut(|shake| !shake.advance(dt));
+        self.tween_finished = false;
+        if let Some(tween) = &mut self.tween {
+            if tween.apply(&mut self.camera, dt) {
+                self.tween = None;
+                self.tween_finished = true;
+            }
+            return;
+        }
+        if let Some(path) = &mut self.path {
+            if path.apply(&mut self.camera, dt) {
+                self.path = None;
+                self.tween_finished = true;
+            }
+            return;
+        }
+        if let Some(collision) = &mut self.collision {
+            collision.restore(&mut self.camera);
+        }
+        if controller {
+            self.controller.update(&mut self.camera, dt);
+        }
+        if let Some(follow) = &mut self.follow {
+            follow.apply(&mut self.camera, dt);
+        }
+        let pivot = match &self.follow {
+            Some(follow) => Some(follow.target_position()),
+            None => self.controller.pivot(),
+        };
+        if let (Some(collision), Some(pivot)) = (&mut self.collision, pivot) {
+            collision.constrain(&mut self.camera, pivot, dt);
+        }
+    }
+
+    /// Returns the installed controller if it is of type `C`.
+    pub fn controller_mut<C: Controller>(&mut self) -> Option<&mut C> {
+        (self.controller.as_mut() as &mut dyn std::any::Any).downcast_mut::<C>()
+    }
+}
+
+/// Name of the controller-driven camera in [`crate::context::Context::camera`].
+pub const MAIN_CAMERA: &str = "main";
+
+/**
+ * An additional camera registered through [`crate::context::Context::add_camera`], e.g. for a
+ * minimap or a cinematic cut-in.
+ *
+ * Unlike the main camera it has no controller and its projection isn't resized with the
+ * window. Move it by changing `camera` directly, the uniform is written every frame.
+ */
+#[derive(Debug)]
+pub struct CameraView {
+    pub camera: Camera,
+    pub projection: Projection,
+    pub uniform: CameraUniform,
+    pub buffer: wgpu::Buffer,
+    pub bind_group: wgpu::BindGroup,
+}
+
+impl CameraView {
+    pub fn new(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        camera: Camera,
+        projection: Projection,
+    ) -> Self {
+        let mut uniform = CameraUniform::new();
+        uniform.update_view_proj(&camera, &projection);
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Camera View Buffer"),
+            contents: bytemuck::cast_slice(&[uniform]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+            label: Some("camera_view_bind_group"),
+        });
+        Self {
+            camera,
+            projection,
+            uniform,
+            buffer,
+            bind_group,
+        }
+    }
+
+    pub(crate) fn write_uniform(&mut self, queue: &wgpu::Queue) {
+        self.uniform.update_view_proj(&self.camera, &self.projection);
+        profiling::write_buffer(queue, &self.buffer, 0, bytemuck::cast_slice(&[self.uniform]));
+    }
+}
+
+#[cfg(kani)]
+mod kani_proofs {
+    use super::*;
+
+    #[kani::proof]
+    #[kani::unwind(1)]
+    fn verify_screen_to_ndc_x_range() {
+        let width: f32 = kani::any();
+        let height: f32 = kani::any();
+        kani::assume(width > 0.0 && width < 1e6 && width.is_finite());
+        kani::assume(height > 0.0 && height < 1e6 && height.is_finite());
+        let mouse_x: f32 = kani::any();
+        kani::assume(mouse_x >= 0.0 && mouse_x <= width);
+        let mouse_y: f32 = kani::any();
+        kani::assume(mouse_y >= 0.0 && mouse_y <= height);
+        let ndc = screen_to_ndc(mouse_x, mouse_y, width, height);
+        kani::assert(ndc.x >= -1.0 && ndc.x <= 1.0, "NDC x in [-1, 1]");
+        kani::assert(ndc.y >= -1.0 && ndc.y <= 1.0, "NDC y in [-1, 1]");
+    }
+
+    #[kani::proof]
+    #[kani::unwind(1)]
+    fn verify_intersect_no_panic() {
+        let ox: f32 = kani::any();
+        let oy: f32 = kani::any();
+        let oz: f32 = kani::any();
+        let dx: f32 = kani::any();
+        let dy: f32 = kani::any();
+        let dz: f32 = kani::any();
+        kani::assume(ox.is_finite() && oy.is_finite() && oz.is_finite());
+        kani::assume(dx.is_finite() && dy.is_finite() && dz.is_finite());
+        let ray = Ray {
+            origin: cgmath::Point3::new(ox, oy, oz),
+            direction: cgmath::Vector3::new(dx, dy, dz),
+        };
+        let _ = ray.intersect_with_floor();
+    }
+
+    #[kani::proof]
+    #[kani::unwind(1)]
+    fn verify_intersect_upward_none() {
+        // An upward ray (dy > 0) from strictly above the floor (oy > 0) returns None.
+        let ox: f32 = kani::any();
+        let oy: f32 = kani::any();
+        let oz: f32 = kani::any();
+        let dx: f32 = kani::any();
+        let dy: f32 = kani::any();
+        let dz: f32 = kani::any();
+        kani::assume(ox.is_finite() && oz.is_finite());
+        kani::assume(dx.is_finite() && dz.is_finite());
+        kani::assume(oy > 0.0 && oy.is_finite());
+        kani::assume(dy > f32::EPSILON && dy.is_finite());
+        let ray = Ray {
+            origin: cgmath::Point3::new(ox, oy, oz),
+            direction: cgmath::Vector3::new(dx, dy, dz),
+        };
+        kani::assert(
+            ray.intersect_with_floor().is_none(),
+            "upward ray from above floor returns None",
+        );
+    }
+
+    /// Verify that Projection::resize produces a finite aspect ratio
+    /// for any valid (non-zero) dimensions. This proof SHOULD FAIL:
+    /// resize(_, 0) divides by zero, producing infinity.
+    #[kani::proof]
+    #[kani::unwind(1)]
+    fn verify_resize_aspect_finite() {
+        let w: u32 = kani::any();
+        let h: u32 = kani::any();
+        kani::assume(w > 0 && w < 10000);
+        kani::assume(h > 0 && h < 10000);
+        let mut proj = Projection {
+            aspect: 1.0,
+            fovy: cgmath::Rad(std::f32::consts::FRAC_PI_4),
+            znear: 0.1,
+            zfar: 100.0,
+            reversed_z: false,
+        };
         proj.resize(w, h);
         kani::assert(proj.aspect.is_finite(), "aspect ratio must be finite for non-zero dimensions");
     }
@@ -450,33 +2072,194 @@ mod tests {
     }
 
     #[test]
-    fn bottom_right_maps_to_one_minus_one() {
-        let (w, h) = (800.0f32, 600.0f32);
-        let ndc = screen_to_ndc(w, h, w, h);
-        assert_relative_eq!(ndc.x, 1.0, epsilon = 1e-6);
-        assert_relative_eq!(ndc.y, -1.0, epsilon = 1e-6);
+    fn bottom_right_maps_to_one_minus_one() {
+        let (w, h) = (800.0f32, 600.0f32);
+        let ndc = screen_to_ndc(w, h, w, h);
+        assert_relative_eq!(ndc.x, 1.0, epsilon = 1e-6);
+        assert_relative_eq!(ndc.y, -1.0, epsilon = 1e-6);
+    }
+
+    // Projection::resize should guard against zero height to avoid infinite aspect ratio.
+    #[test]
+    fn resize_with_zero_height_keeps_previous_aspect() {
+        let mut proj = Projection::new(800, 600, Deg(45.0), 0.1, 100.0).unwrap();
+        let aspect_before = proj.aspect;
+        proj.resize(800, 0);
+        assert!(proj.aspect.is_finite(), "resize with zero height must not produce infinite aspect");
+        assert_relative_eq!(proj.aspect, aspect_before, epsilon = 1e-6);
+    }
+
+    #[test]
+    fn projection_setters_reject_degenerate_values() {
+        let mut proj = Projection::new(800, 600, Deg(45.0), 0.1, 100.0).unwrap();
+        assert!(proj.set_fovy(Deg(0.0)).is_err());
+        assert!(proj.set_fovy(Deg(180.0)).is_err());
+        assert!(proj.set_znear(0.0).is_err());
+        assert!(proj.set_znear(200.0).is_err());
+        assert!(proj.set_zfar(0.05).is_err());
+        assert!(proj.set_zfar(f32::NAN).is_err());
+        // failed calls leave the projection as it was
+        assert_relative_eq!(proj.fovy(), Rad::from(Deg(45.0)));
+        assert_relative_eq!(proj.znear, 0.1);
+        assert_relative_eq!(proj.zfar, 100.0);
+        assert!(Projection::new(800, 600, Deg(45.0), 1.0, 1.0).is_err());
+    }
+
+    #[test]
+    fn narrower_fovy_zooms_in() {
+        let mut proj = Projection::new(800, 600, Deg(60.0), 0.1, 100.0).unwrap();
+        let wide = proj.calc_matrix();
+        proj.set_fovy(Deg(20.0)).unwrap();
+        proj.set_zfar(1000.0).unwrap();
+        let narrow = proj.calc_matrix();
+        assert!(narrow.y.y > wide.y.y);
+    }
+
+    // screen_to_ndc must return finite values even for degenerate zero dimensions.
+    #[test]
+    fn screen_to_ndc_zero_dimensions_returns_finite() {
+        let ndc = screen_to_ndc(0.0, 0.0, 0.0, 600.0);
+        assert!(ndc.x.is_finite(), "zero width must not produce non-finite NDC x");
+        let ndc = screen_to_ndc(0.0, 0.0, 800.0, 0.0);
+        assert!(ndc.y.is_finite(), "zero height must not produce non-finite NDC y");
+    }
+
+    // --- Ray::intersect_plane ---
+
+    #[test]
+    fn ray_hits_raised_floor() {
+        let ray = Ray {
+            origin: Point3::new(1.0, 5.0, -2.0),
+            direction: Vector3::new(0.0, -1.0, 0.0),
+        };
+        let hit = ray
+            .intersect_plane(Vector3::new(0.0, 3.0, 0.0), Vector3::unit_y())
+            .expect("should hit plane");
+        assert_relative_eq!(hit, Vector3::new(1.0, 3.0, -2.0), epsilon = 1e-6);
+    }
+
+    #[test]
+    fn angled_ray_hits_wall() {
+        // Wall at x = 4 facing the ray, normal not normalized
+        let ray = Ray {
+            origin: Point3::new(0.0, 1.0, 0.0),
+            direction: Vector3::new(1.0, 0.5, 1.0).normalize(),
+        };
+        let hit = ray
+            .intersect_plane(Vector3::new(4.0, 0.0, 0.0), Vector3::new(-3.0, 0.0, 0.0))
+            .expect("should hit wall");
+        assert_relative_eq!(hit, Vector3::new(4.0, 3.0, 4.0), epsilon = 1e-5);
+    }
+
+    #[test]
+    fn ray_parallel_to_plane_returns_none() {
+        let ray = Ray {
+            origin: Point3::new(0.0, 1.0, 0.0),
+            direction: Vector3::new(0.0, 0.0, 1.0),
+        };
+        assert!(
+            ray.intersect_plane(Vector3::new(2.0, 0.0, 0.0), Vector3::unit_x())
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn plane_behind_ray_returns_none() {
+        let ray = Ray {
+            origin: Point3::new(0.0, 1.0, 0.0),
+            direction: Vector3::new(0.0, 1.0, 0.0),
+        };
+        assert!(
+            ray.intersect_plane(Vector3::new(0.0, -2.0, 0.0), Vector3::unit_y())
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn floor_matches_plane_at_zero() {
+        let ray = Ray {
+            origin: Point3::new(-1.0, 4.0, 2.0),
+            direction: Vector3::new(0.5, -1.0, 0.25),
+        };
+        let floor = ray.intersect_with_floor().unwrap();
+        let plane = ray
+            .intersect_plane(Vector3::zero(), Vector3::unit_y())
+            .unwrap();
+        assert_relative_eq!(floor.x, plane.x);
+        assert_relative_eq!(floor.y, plane.z);
+        assert_relative_eq!(plane.y, 0.0);
+    }
+
+    // --- Ray::intersect_with_floor ---
+
+    fn unit_aabb() -> Aabb {
+        Aabb::new(Point3::new(-1.0, -1.0, -1.0), Point3::new(1.0, 1.0, 1.0))
+    }
+
+    #[test]
+    fn ray_hits_aabb_front_face() {
+        let ray = Ray {
+            origin: Point3::new(-5.0, 0.0, 0.0),
+            direction: Vector3::new(1.0, 0.0, 0.0),
+        };
+        assert_relative_eq!(ray.intersect_aabb(&unit_aabb()).unwrap(), 4.0);
+    }
+
+    #[test]
+    fn angled_ray_hits_aabb() {
+        let ray = Ray {
+            origin: Point3::new(-3.0, 3.0, 0.5),
+            direction: Vector3::new(1.0, -1.0, 0.0).normalize(),
+        };
+        let t = ray.intersect_aabb(&unit_aabb()).unwrap();
+        let hit = ray.origin + ray.direction * t;
+        assert_relative_eq!(hit.x, -1.0, epsilon = 1e-5);
+        assert_relative_eq!(hit.y, 1.0, epsilon = 1e-5);
+    }
+
+    #[test]
+    fn ray_missing_aabb_returns_none() {
+        let ray = Ray {
+            origin: Point3::new(-5.0, 2.0, 0.0),
+            direction: Vector3::new(1.0, 0.0, 0.0),
+        };
+        assert!(ray.intersect_aabb(&unit_aabb()).is_none());
+    }
+
+    #[test]
+    fn aabb_behind_ray_returns_none() {
+        let ray = Ray {
+            origin: Point3::new(5.0, 0.0, 0.0),
+            direction: Vector3::new(1.0, 0.0, 0.0),
+        };
+        assert!(ray.intersect_aabb(&unit_aabb()).is_none());
     }
 
-    // Projection::resize should guard against zero height to avoid infinite aspect ratio.
     #[test]
-    fn resize_with_zero_height_keeps_previous_aspect() {
-        let mut proj = Projection::new(800, 600, Deg(45.0), 0.1, 100.0).unwrap();
-        let aspect_before = proj.aspect;
-        proj.resize(800, 0);
-        assert!(proj.aspect.is_finite(), "resize with zero height must not produce infinite aspect");
-        assert_relative_eq!(proj.aspect, aspect_before, epsilon = 1e-6);
+    fn ray_inside_aabb_hits_at_zero() {
+        let ray = Ray {
+            origin: Point3::new(0.2, 0.3, -0.4),
+            direction: Vector3::new(0.0, 1.0, 0.0),
+        };
+        assert_eq!(ray.intersect_aabb(&unit_aabb()), Some(0.0));
     }
 
-    // screen_to_ndc must return finite values even for degenerate zero dimensions.
     #[test]
-    fn screen_to_ndc_zero_dimensions_returns_finite() {
-        let ndc = screen_to_ndc(0.0, 0.0, 0.0, 600.0);
-        assert!(ndc.x.is_finite(), "zero width must not produce non-finite NDC x");
-        let ndc = screen_to_ndc(0.0, 0.0, 800.0, 0.0);
-        assert!(ndc.y.is_finite(), "zero height must not produce non-finite NDC y");
-    }
+    fn degenerate_aabb_is_hittable() {
+        let plane = Aabb::new(Point3::new(-1.0, 0.0, -1.0), Point3::new(1.0, 0.0, 1.0));
+        let ray = Ray {
+            origin: Point3::new(0.0, 3.0, 0.0),
+            direction: Vector3::new(0.0, -1.0, 0.0),
+        };
+        assert_relative_eq!(ray.intersect_aabb(&plane).unwrap(), 3.0);
 
-    // --- Ray::intersect_with_floor ---
+        let point = Aabb::new(Point3::new(2.0, 0.0, 0.0), Point3::new(2.0, 0.0, 0.0));
+        let ray = Ray {
+            origin: Point3::new(0.0, 0.0, 0.0),
+            direction: Vector3::new(1.0, 0.0, 0.0),
+        };
+        assert_relative_eq!(ray.intersect_aabb(&point).unwrap(), 2.0);
+    }
 
     #[test]
     fn downward_ray_hits_floor() {
@@ -541,6 +2324,256 @@ mod tests {
         assert_relative_eq!(hit.y, 0.0, epsilon = 1e-6);
     }
 
+    // --- project_to_screen ---
+
+    #[test]
+    fn project_point_in_front_of_camera_to_centre() {
+        let camera = Camera::new(Point3::new(0.0, 0.0, 5.0), Deg(-90.0), Deg(0.0));
+        let proj = Projection::new(800, 600, Deg(45.0), 0.1, 100.0).unwrap();
+        let vp = proj.calc_matrix() * camera.calc_matrix();
+        let p = project_to_screen(vp, Point3::new(0.0, 0.0, 0.0), 800.0, 600.0, false).unwrap();
+        assert_relative_eq!(p.x, 400.0, epsilon = 1e-3);
+        assert_relative_eq!(p.y, 300.0, epsilon = 1e-3);
+    }
+
+    #[test]
+    fn project_point_behind_camera_is_none() {
+        let camera = Camera::new(Point3::new(0.0, 0.0, 5.0), Deg(-90.0), Deg(0.0));
+        let proj = Projection::new(800, 600, Deg(45.0), 0.1, 100.0).unwrap();
+        let vp = proj.calc_matrix() * camera.calc_matrix();
+        assert!(project_to_screen(vp, Point3::new(0.0, 0.0, 10.0), 800.0, 600.0, false).is_none());
+    }
+
+    #[test]
+    fn reversed_z_maps_near_to_one_and_far_to_zero() {
+        let camera = Camera::new(Point3::new(0.0, 0.0, 0.0), Deg(-90.0), Deg(0.0));
+        let mut proj = Projection::new(800, 600, Deg(45.0), 0.1, 100.0).unwrap();
+        let depth = |proj: &Projection, distance: f32| {
+            let clip = proj.calc_matrix()
+                * camera.calc_matrix()
+                * Point3::new(0.0, 0.0, -distance).to_homogeneous();
+            clip.z / clip.w
+        };
+        assert_relative_eq!(depth(&proj, 0.1), 0.0, epsilon = 1e-5);
+        assert_relative_eq!(depth(&proj, 100.0), 1.0, epsilon = 1e-5);
+        let standard = depth(&proj, 50.0);
+        proj.set_reversed_z(true);
+        assert_relative_eq!(depth(&proj, 0.1), 1.0, epsilon = 1e-5);
+        assert_relative_eq!(depth(&proj, 100.0), 0.0, epsilon = 1e-5);
+        assert_relative_eq!(depth(&proj, 50.0), 1.0 - standard, epsilon = 1e-5);
+
+        // Points in front of the near plane are still rejected
+        let vp = proj.calc_matrix() * camera.calc_matrix();
+        assert!(project_to_screen(vp, Point3::new(0.0, 0.0, -0.05), 800.0, 600.0, true).is_none());
+        let p = project_to_screen(vp, Point3::new(0.0, 0.0, -5.0), 800.0, 600.0, true).unwrap();
+        assert_relative_eq!(p.x, 400.0, epsilon = 1e-3);
+    }
+
+    #[test]
+    fn unprojecting_a_projected_point_finds_it_again() {
+        let camera = Camera::new(Point3::new(2.0, 6.0, 9.0), Deg(-110.0), Deg(-30.0));
+        let mut proj = Projection::new(800, 600, Deg(45.0), 0.1, 100.0).unwrap();
+        let world = Point3::new(1.0, 0.5, -3.0);
+        for reversed_z in [false, true] {
+            proj.set_reversed_z(reversed_z);
+            let vp = proj.calc_matrix() * camera.calc_matrix();
+            let clip = vp * world.to_homogeneous();
+            let screen = project_to_screen(vp, world, 800.0, 600.0, reversed_z).unwrap();
+            let found = unproject_from_screen(vp, screen, clip.z / clip.w, 800.0, 600.0).unwrap();
+            assert_relative_eq!(found, world.to_vec(), epsilon = 1e-3);
+        }
+    }
+
+    #[test]
+    fn world_to_screen_round_trips_through_mouse_ray() {
+        let camera = Camera::new(Point3::new(2.0, 6.0, 9.0), Deg(-110.0), Deg(-30.0));
+        let proj = Projection::new(800, 600, Deg(45.0), 0.1, 100.0).unwrap();
+        let world = Vector3::new(1.0, 0.5, -2.0);
+        let screen = camera.world_to_screen(world, 800.0, 600.0, &proj).unwrap();
+        assert!((0.0..800.0).contains(&screen.x) && (0.0..600.0).contains(&screen.y));
+
+        let ray = camera.cast_ray_from_mouse(screen, 800.0, 600.0, &proj);
+        let to_point = Point3::from_vec(world) - ray.origin;
+        let off_ray = to_point - ray.direction * to_point.dot(ray.direction);
+        assert!(off_ray.magnitude() < 1e-3, "ray misses by {}", off_ray.magnitude());
+    }
+
+    #[test]
+    fn easing_curves_hit_endpoints() {
+        for easing in [Easing::Linear, Easing::Smoothstep] {
+            assert_eq!(easing.apply(0.0), 0.0);
+            assert_eq!(easing.apply(1.0), 1.0);
+            assert_eq!(easing.apply(2.0), 1.0);
+        }
+        assert_relative_eq!(Easing::Smoothstep.apply(0.5), 0.5);
+        assert!(Easing::Smoothstep.apply(0.1) < Easing::Linear.apply(0.1));
+    }
+
+    #[test]
+    fn tween_reaches_target_pose() {
+        let mut camera = Camera::new((0.0, 10.0, 0.0), Deg(0.0), Deg(-45.0));
+        let mut tween = CameraTween::new(
+            &camera,
+            Point3::new(4.0, 2.0, -6.0),
+            Deg(90.0).into(),
+            Deg(-10.0).into(),
+            Duration::from_millis(700),
+            Easing::Smoothstep,
+        );
+        assert!(!tween.apply(&mut camera, Duration::from_millis(350)));
+        assert_relative_eq!(camera.position, Point3::new(2.0, 6.0, -3.0), epsilon = 1e-4);
+        assert!(tween.apply(&mut camera, Duration::from_millis(400)));
+        assert_relative_eq!(camera.position, Point3::new(4.0, 2.0, -6.0), epsilon = 1e-4);
+        assert_relative_eq!(camera.yaw, Rad::from(Deg(90.0)), epsilon = 1e-4);
+        assert_relative_eq!(camera.pitch, Rad::from(Deg(-10.0)), epsilon = 1e-4);
+    }
+
+    #[test]
+    fn tween_turns_the_short_way_around() {
+        let mut camera = Camera::new((0.0, 0.0, 0.0), Deg(170.0), Deg(0.0));
+        let mut tween = CameraTween::new(
+            &camera,
+            camera.position,
+            Deg(-170.0).into(),
+            Deg(0.0).into(),
+            Duration::from_secs(1),
+            Easing::Linear,
+        );
+        tween.apply(&mut camera, Duration::from_millis(500));
+        assert_relative_eq!(camera.yaw, Rad::from(Deg(180.0)), epsilon = 1e-4);
+    }
+
+    #[test]
+    fn zero_duration_tween_snaps() {
+        let mut camera = Camera::new((0.0, 0.0, 0.0), Deg(0.0), Deg(0.0));
+        let mut tween = CameraTween::new(
+            &camera,
+            Point3::new(1.0, 2.0, 3.0),
+            Deg(0.0).into(),
+            Deg(0.0).into(),
+            Duration::ZERO,
+            Easing::Linear,
+        );
+        assert!(tween.apply(&mut camera, Duration::ZERO));
+        assert_eq!(camera.position, Point3::new(1.0, 2.0, 3.0));
+    }
+
+    fn fly_through() -> CameraPath {
+        CameraPath::new(vec![
+            CameraKeyframe::new(Duration::ZERO, (0.0, 0.0, 0.0), Deg(0.0), Deg(0.0)),
+            CameraKeyframe::new(Duration::from_secs(1), (10.0, 0.0, 0.0), Deg(90.0), Deg(0.0)),
+            CameraKeyframe::new(
+                Duration::from_secs(3),
+                (10.0, 0.0, 10.0),
+                Deg(90.0),
+                Deg(-30.0),
+            ),
+        ])
+        .unwrap()
+    }
+
+    #[test]
+    fn path_passes_through_keyframes() {
+        let path = fly_through();
+        for key in path.keyframes() {
+            let (position, yaw, pitch) = path.sample(key.time.as_secs_f32());
+            assert_relative_eq!(position, key.position, epsilon = 1e-4);
+            assert_relative_eq!(yaw, key.yaw, epsilon = 1e-4);
+            assert_relative_eq!(pitch, key.pitch, epsilon = 1e-4);
+        }
+        let (_, yaw, pitch) = path.sample(0.5);
+        assert_relative_eq!(yaw, Rad::from(Deg(45.0)), epsilon = 1e-4);
+        assert_relative_eq!(pitch, Rad(0.0), epsilon = 1e-4);
+        // clamped past the end
+        assert_eq!(path.sample(5.0).0, Point3::new(10.0, 0.0, 10.0));
+    }
+
+    #[test]
+    fn path_needs_ordered_keyframes() {
+        assert!(CameraPath::new(Vec::new()).is_err());
+        let key = |secs| {
+            CameraKeyframe::new(Duration::from_secs(secs), (0.0, 0.0, 0.0), Deg(0.0), Deg(0.0))
+        };
+        assert!(CameraPath::new(vec![key(1), key(1)]).is_err());
+        assert!(CameraPath::new(vec![key(2), key(1)]).is_err());
+    }
+
+    #[test]
+    fn single_keyframe_path_snaps_and_completes() {
+        let path = CameraPath::new(vec![CameraKeyframe::new(
+            Duration::from_secs(2),
+            (1.0, 2.0, 3.0),
+            Deg(30.0),
+            Deg(10.0),
+        )])
+        .unwrap();
+        let mut camera = Camera::new((0.0, 0.0, 0.0), Deg(0.0), Deg(0.0));
+        let mut playback = PathPlayback {
+            path,
+            elapsed: 0.0,
+            looped: true,
+        };
+        assert!(playback.apply(&mut camera, Duration::ZERO));
+        assert_eq!(camera.position, Point3::new(1.0, 2.0, 3.0));
+        assert_relative_eq!(camera.yaw, Rad::from(Deg(30.0)), epsilon = 1e-5);
+    }
+
+    #[test]
+    fn looped_path_wraps_around() {
+        let mut camera = Camera::new((0.0, 0.0, 0.0), Deg(0.0), Deg(0.0));
+        let mut playback = PathPlayback {
+            path: fly_through(),
+            elapsed: 0.0,
+            looped: true,
+        };
+        assert!(!playback.apply(&mut camera, Duration::from_secs(4)));
+        assert_relative_eq!(camera.position, Point3::new(10.0, 0.0, 0.0), epsilon = 1e-4);
+        playback.looped = false;
+        assert!(!playback.apply(&mut camera, Duration::from_secs(1)));
+        assert!(playback.apply(&mut camera, Duration::from_secs(1)));
+        assert_relative_eq!(camera.position, Point3::new(10.0, 0.0, 10.0), epsilon = 1e-4);
+    }
+
+    #[test]
+    fn shake_decays_to_zero() {
+        let mut shake = CameraShake::new(0.5, 20.0, Duration::from_millis(300), 0);
+        assert!(!shake.advance(Duration::from_millis(10)));
+        let offset = shake.offset();
+        assert!(offset.x.abs() <= 0.5 && offset.y.abs() <= 0.5);
+        assert!(offset.magnitude() > 0.0);
+        assert!(!shake.advance(Duration::from_millis(200)));
+        assert!(shake.advance(Duration::from_millis(100)));
+        assert_eq!(shake.offset(), Vector2::zero());
+    }
+
+    #[test]
+    fn overlapping_shakes_differ() {
+        let mut a = CameraShake::new(1.0, 10.0, Duration::from_secs(1), 0);
+        let mut b = CameraShake::new(1.0, 10.0, Duration::from_secs(1), 1);
+        a.advance(Duration::from_millis(50));
+        b.advance(Duration::from_millis(50));
+        assert_ne!(a.offset(), b.offset());
+    }
+
+    #[test]
+    fn displaced_keeps_orientation() {
+        let camera = Camera::new((0.0, 0.0, 5.0), Deg(-90.0), Deg(0.0));
+        // looking down -z, right is +x and up is +y
+        let moved = camera.displaced(Vector2::new(1.0, 2.0));
+        assert_relative_eq!(moved.position, Point3::new(1.0, 2.0, 5.0), epsilon = 1e-5);
+        assert_eq!(moved.yaw, camera.yaw);
+        assert_eq!(moved.pitch, camera.pitch);
+        assert_eq!(camera.displaced(Vector2::zero()).position, camera.position);
+    }
+
+    #[test]
+    fn world_to_screen_behind_camera_is_none() {
+        let camera = Camera::new(Point3::new(0.0, 0.0, 5.0), Deg(-90.0), Deg(0.0));
+        let proj = Projection::new(800, 600, Deg(45.0), 0.1, 100.0).unwrap();
+        let behind = Vector3::new(0.0, 0.0, 10.0);
+        assert!(camera.world_to_screen(behind, 800.0, 600.0, &proj).is_none());
+    }
+
     // --- Camera::calc_matrix ---
 
     #[test]
@@ -587,6 +2620,450 @@ mod tests {
         assert!(camera.pitch.0 >= -(SAFE_FRAC_PI_2 + 1e-5));
     }
 
+    #[test]
+    fn pitch_limits_clamp_update() {
+        let mut camera = Camera::new(Point3::new(0.0, 0.0, 0.0), Deg(0.0), Deg(0.0));
+        let mut ctrl = CameraController::new(1.0, 1.0);
+        ctrl.set_pitch_limits(Deg(-60.0), Deg(10.0));
+        ctrl.rotate_vertical = -1e6;
+        ctrl.update(&mut camera, Duration::from_secs_f32(1.0));
+        assert_relative_eq!(camera.pitch.0, Rad::from(Deg(10.0)).0);
+        ctrl.rotate_vertical = 1e6;
+        ctrl.update(&mut camera, Duration::from_secs_f32(1.0));
+        assert_relative_eq!(camera.pitch.0, Rad::from(Deg(-60.0)).0);
+    }
+
+    #[test]
+    fn pitch_limits_stay_short_of_poles_and_are_ordered() {
+        let mut ctrl = CameraController::new(1.0, 1.0);
+        ctrl.set_pitch_limits(Deg(120.0), Deg(-120.0));
+        assert_relative_eq!(Rad::from(ctrl.min_pitch()).0, -SAFE_FRAC_PI_2);
+        assert_relative_eq!(Rad::from(ctrl.max_pitch()).0, SAFE_FRAC_PI_2);
+    }
+
+    #[test]
+    fn mouse_input_past_pitch_limit_is_dropped() {
+        let mut camera = Camera::new(Point3::new(0.0, 0.0, 0.0), Deg(0.0), Deg(0.0));
+        let mut ctrl = CameraController::new(1.0, 1.0);
+        ctrl.set_pitch_limits(Deg(-30.0), Deg(30.0));
+        ctrl.rotate_vertical = -1e6;
+        ctrl.update(&mut camera, Duration::from_secs_f32(1.0));
+
+        ctrl.handle_mouse(0.0, -5.0);
+        assert_eq!(ctrl.rotate_vertical, 0.0);
+        // Looking back down is still possible
+        ctrl.handle_mouse(0.0, 5.0);
+        assert_eq!(ctrl.rotate_vertical, 5.0);
+    }
+
+    fn looking_down() -> Camera {
+        Camera::new(Point3::new(0.0, 10.0, 0.0), Deg(0.0), Deg(-90.0))
+    }
+
+    #[test]
+    fn default_zoom_is_unlimited() {
+        let mut camera = looking_down();
+        let mut ctrl = CameraController::new(1.0, 1.0);
+        ctrl.scroll = 20.0;
+        ctrl.update(&mut camera, Duration::from_secs_f32(1.0));
+        assert!(camera.position.y < 0.0);
+    }
+
+    #[test]
+    fn zoom_stops_at_min_distance() {
+        let mut camera = looking_down();
+        let mut ctrl = CameraController::new(1.0, 1.0);
+        ctrl.set_zoom_limits(2.0, 50.0);
+        ctrl.scroll = 20.0;
+        ctrl.update(&mut camera, Duration::from_secs_f32(1.0));
+        assert_relative_eq!(camera.position.y, 2.0, epsilon = 1e-3);
+    }
+
+    #[test]
+    fn zoom_stops_at_max_distance() {
+        let mut camera = looking_down();
+        let mut ctrl = CameraController::new(1.0, 1.0);
+        ctrl.set_zoom_limits(2.0, 15.0);
+        ctrl.scroll = -20.0;
+        ctrl.update(&mut camera, Duration::from_secs_f32(1.0));
+        assert_relative_eq!(camera.position.y, 15.0, epsilon = 1e-3);
+    }
+
+    #[test]
+    fn zoom_outside_limits_can_move_back_in() {
+        let mut camera = looking_down();
+        let mut ctrl = CameraController::new(1.0, 1.0);
+        ctrl.set_zoom_limits(20.0, 50.0);
+        ctrl.scroll = 1.0;
+        ctrl.update(&mut camera, Duration::from_secs_f32(1.0));
+        assert_relative_eq!(camera.position.y, 10.0, epsilon = 1e-3);
+        ctrl.scroll = -5.0;
+        ctrl.update(&mut camera, Duration::from_secs_f32(1.0));
+        assert_relative_eq!(camera.position.y, 15.0, epsilon = 1e-3);
+    }
+
+    #[test]
+    fn pending_scroll_is_accumulated_and_clamped() {
+        let mut ctrl = CameraController::new(1.0, 1.0);
+        ctrl.handle_scroll(&MouseScrollDelta::LineDelta(0.0, -1.0));
+        ctrl.handle_scroll(&MouseScrollDelta::LineDelta(0.0, -1.0));
+        assert_relative_eq!(ctrl.scroll, 1.0);
+        for _ in 0..1000 {
+            ctrl.handle_scroll(&MouseScrollDelta::LineDelta(0.0, -1.0));
+        }
+        assert_relative_eq!(ctrl.scroll, MAX_PENDING_SCROLL);
+    }
+
+    #[test]
+    fn trackpad_swipe_scrolls_like_a_wheel_notch() {
+        let mut wheel = CameraController::new(1.0, 1.0);
+        wheel.handle_scroll(&MouseScrollDelta::LineDelta(0.0, 1.0));
+        let mut trackpad = CameraController::new(1.0, 1.0);
+        for _ in 0..10 {
+            let delta = MouseScrollDelta::PixelDelta(PhysicalPosition::new(0.0, 5.0));
+            trackpad.handle_scroll(&delta);
+        }
+        assert_relative_eq!(wheel.scroll, trackpad.scroll, epsilon = 1e-6);
+    }
+
+    #[test]
+    fn zoom_speed_and_inversion_scale_the_step() {
+        let mut camera = looking_down();
+        let mut ctrl = CameraController::new(1.0, 1.0);
+        ctrl.set_zoom_speed(2.0);
+        ctrl.handle_scroll(&MouseScrollDelta::LineDelta(0.0, -2.0));
+        ctrl.update(&mut camera, Duration::from_secs_f32(1.0));
+        // two notches towards the user give one unit of scroll, doubled it moves down by 2
+        assert_relative_eq!(camera.position.y, 8.0, epsilon = 1e-3);
+
+        ctrl.set_zoom_inverted(true);
+        ctrl.handle_scroll(&MouseScrollDelta::LineDelta(0.0, -2.0));
+        ctrl.update(&mut camera, Duration::from_secs_f32(1.0));
+        assert_relative_eq!(camera.position.y, 10.0, epsilon = 1e-3);
+
+        ctrl.set_zoom_speed(-1.0);
+        assert_eq!(ctrl.zoom_speed(), 0.0);
+        ctrl.set_zoom_speed(f32::NAN);
+        assert_eq!(ctrl.zoom_speed(), 0.0);
+    }
+
+    // --- OrbitController ---
+
+    fn orbit_camera() -> Camera {
+        Camera::new(Point3::new(0.0, 0.0, 10.0), Deg(-90.0), Deg(0.0))
+    }
+
+    #[test]
+    fn orbit_keeps_distance_to_target() {
+        let mut camera = orbit_camera();
+        let mut ctrl = OrbitController::new(Point3::new(1.0, 2.0, 3.0), 10.0, 0.4);
+        ctrl.handle_mouse(50.0, 20.0);
+        ctrl.update(&mut camera, std::time::Duration::from_secs_f32(0.1));
+        assert_relative_eq!((camera.position - ctrl.target()).magnitude(), 10.0, epsilon = 1e-4);
+    }
+
+    #[test]
+    fn orbit_does_not_flip_over_poles() {
+        let mut camera = orbit_camera();
+        let mut ctrl = OrbitController::new(Point3::new(0.0, 0.0, 0.0), 10.0, 1.0);
+        ctrl.handle_mouse(0.0, -1e6);
+        ctrl.update(&mut camera, std::time::Duration::from_secs_f32(1.0));
+        assert!(camera.pitch.0 <= SAFE_FRAC_PI_2);
+        ctrl.handle_mouse(0.0, 1e6);
+        ctrl.update(&mut camera, std::time::Duration::from_secs_f32(1.0));
+        assert!(camera.pitch.0 >= -SAFE_FRAC_PI_2);
+    }
+
+    #[test]
+    fn orbit_zoom_is_clamped_and_never_passes_target() {
+        let mut camera = orbit_camera();
+        let mut ctrl = OrbitController::new(Point3::new(0.0, 0.0, 0.0), 10.0, 1.0);
+        ctrl.set_radius_limits(2.0, 20.0);
+        ctrl.handle_scroll(&MouseScrollDelta::LineDelta(0.0, 1e4));
+        ctrl.update(&mut camera, std::time::Duration::from_secs_f32(0.1));
+        assert_relative_eq!(ctrl.radius(), 2.0, epsilon = 1e-6);
+        ctrl.handle_scroll(&MouseScrollDelta::LineDelta(0.0, -1e4));
+        ctrl.update(&mut camera, std::time::Duration::from_secs_f32(0.1));
+        assert_relative_eq!(ctrl.radius(), 20.0, epsilon = 1e-6);
+    }
+
+    #[test]
+    fn orbit_radius_limits_reject_zero() {
+        let mut ctrl = OrbitController::new(Point3::new(0.0, 0.0, 0.0), 10.0, 1.0);
+        ctrl.set_radius_limits(0.0, 0.0);
+        assert!(ctrl.radius() > 0.0);
+    }
+
+    #[test]
+    fn orbit_set_target_moves_camera() {
+        let mut camera = orbit_camera();
+        let mut ctrl = OrbitController::new(Point3::new(0.0, 0.0, 0.0), 5.0, 1.0);
+        ctrl.set_target(Point3::new(10.0, 0.0, 0.0));
+        ctrl.update(&mut camera, std::time::Duration::from_secs_f32(0.1));
+        // yaw -90° looks down -z, so the camera sits behind the target on +z
+        assert_relative_eq!(camera.position.x, 10.0, epsilon = 1e-4);
+        assert_relative_eq!(camera.position.z, 5.0, epsilon = 1e-4);
+    }
+
+    #[test]
+    fn orbit_ignores_keyboard() {
+        let mut ctrl = OrbitController::new(Point3::new(0.0, 0.0, 0.0), 5.0, 1.0);
+        assert!(!ctrl.handle_window_events(&WindowEvent::Focused(true)));
+    }
+
+    #[test]
+    fn orbit_reset_keeps_the_new_pose() {
+        let mut camera = orbit_camera();
+        let mut ctrl = OrbitController::new(Point3::new(0.0, 0.0, 0.0), 5.0, 1.0);
+        ctrl.handle_mouse(30.0, 10.0);
+        camera.set_pose(&CameraPose {
+            position: Point3::new(3.0, 4.0, 5.0),
+            yaw: Rad(0.3),
+            pitch: Rad(-0.2),
+        });
+        ctrl.reset(&camera);
+        ctrl.update(&mut camera, Duration::from_secs_f32(0.1));
+        assert_relative_eq!(camera.position, Point3::new(3.0, 4.0, 5.0), epsilon = 1e-4);
+        assert_relative_eq!(camera.yaw, Rad(0.3));
+    }
+
+    // --- CameraPose ---
+
+    #[test]
+    fn pose_survives_a_view_matrix_round_trip() {
+        for (yaw, pitch) in [(0.0, 0.0), (-90.0, 30.0), (135.0, -60.0), (-170.0, 85.0)] {
+            let camera = Camera::new((1.0, -2.0, 3.5), Deg(yaw), Deg(pitch));
+            let pose = CameraPose::from_view_matrix(&camera.calc_matrix());
+            assert_relative_eq!(pose.position, camera.position, epsilon = 1e-4);
+            assert_relative_eq!(pose.yaw, camera.yaw, epsilon = 1e-4);
+            assert_relative_eq!(pose.pitch, camera.pitch, epsilon = 1e-4);
+            let expected = CameraPose {
+                position: camera.position,
+                yaw: Deg(yaw).into(),
+                pitch: Deg(pitch).into(),
+            };
+            assert_eq!(camera.pose(), expected);
+        }
+    }
+
+    #[test]
+    fn reset_drops_pending_input() {
+        let mut camera = looking_down();
+        let mut ctrl = CameraController::new(1.0, 1.0);
+        ctrl.handle_mouse(40.0, 40.0);
+        ctrl.handle_scroll(&MouseScrollDelta::LineDelta(0.0, -4.0));
+        ctrl.handle_pan(10.0, 10.0);
+        let saved = CameraPose {
+            position: Point3::new(5.0, 5.0, 5.0),
+            yaw: Rad(1.0),
+            pitch: Rad(-0.5),
+        };
+        camera.set_pose(&saved);
+        ctrl.reset(&camera);
+        ctrl.update(&mut camera, Duration::from_secs_f32(1.0));
+        assert_eq!(camera.pose(), saved);
+    }
+
+    // --- FpsController ---
+
+    #[test]
+    fn fps_wants_raw_mouse() {
+        assert!(FpsController::new(1.0, 0.01, 1.8).wants_raw_mouse());
+        assert!(!CameraController::new(1.0, 1.0).wants_raw_mouse());
+    }
+
+    #[test]
+    fn fps_pitch_clamped_to_89_degrees() {
+        let mut camera = Camera::new(Point3::new(0.0, 0.0, 0.0), Deg(0.0), Deg(0.0));
+        let mut ctrl = FpsController::new(1.0, 0.01, 1.8);
+        ctrl.handle_mouse(0.0, -1e6);
+        ctrl.update(&mut camera, std::time::Duration::from_secs_f32(0.016));
+        assert_relative_eq!(camera.pitch.0, Rad::from(Deg(89.0f32)).0, epsilon = 1e-5);
+        ctrl.handle_mouse(0.0, 1e6);
+        ctrl.update(&mut camera, std::time::Duration::from_secs_f32(0.016));
+        assert_relative_eq!(camera.pitch.0, Rad::from(Deg(-89.0f32)).0, epsilon = 1e-5);
+    }
+
+    #[test]
+    fn fps_walks_on_ground_plane_at_eye_height() {
+        // Looking steeply down must not move the camera into the floor
+        let mut camera = Camera::new(Point3::new(0.0, 5.0, 0.0), Deg(0.0), Deg(-80.0));
+        let mut ctrl = FpsController::new(2.0, 0.01, 1.8);
+        ctrl.amount_forward = 1.0;
+        ctrl.update(&mut camera, std::time::Duration::from_secs_f32(1.0));
+        assert_relative_eq!(camera.position.x, 2.0, epsilon = 1e-5);
+        assert_relative_eq!(camera.position.y, 1.8, epsilon = 1e-5);
+        assert_relative_eq!(camera.position.z, 0.0, epsilon = 1e-5);
+    }
+
+    #[test]
+    fn fps_accumulates_mouse_until_update() {
+        let mut camera = Camera::new(Point3::new(0.0, 0.0, 0.0), Deg(0.0), Deg(0.0));
+        let mut ctrl = FpsController::new(1.0, 0.01, 1.8);
+        ctrl.handle_mouse(10.0, 0.0);
+        ctrl.handle_mouse(10.0, 0.0);
+        ctrl.update(&mut camera, std::time::Duration::from_secs_f32(0.016));
+        assert_relative_eq!(camera.yaw.0, 0.2, epsilon = 1e-5);
+    }
+
+    // --- Follow ---
+
+    #[test]
+    fn smooth_damp_converges_without_overshoot() {
+        let mut velocity = Vector3::zero();
+        let mut current = Vector3::new(0.0f32, 0.0, 0.0);
+        let target = Vector3::new(10.0, 0.0, 0.0);
+        for _ in 0..200 {
+            current = smooth_damp(current, target, &mut velocity, 0.3, 1.0 / 60.0);
+            assert!(current.x <= 10.0 + 1e-4);
+        }
+        assert_relative_eq!(current.x, 10.0, epsilon = 1e-2);
+    }
+
+    #[test]
+    fn smooth_damp_is_frame_rate_independent() {
+        let target = Vector3::new(10.0f32, 0.0, 0.0);
+        let run = |steps: u32| {
+            let mut velocity = Vector3::zero();
+            let mut current = Vector3::zero();
+            for _ in 0..steps {
+                current = smooth_damp(current, target, &mut velocity, 0.5, 1.0 / steps as f32);
+            }
+            current.x
+        };
+        assert_relative_eq!(run(30), run(144), epsilon = 0.1);
+    }
+
+    #[test]
+    fn follow_without_smoothing_snaps_and_looks_at_target() {
+        let mut camera = Camera::new(Point3::new(0.0, 0.0, 0.0), Deg(0.0), Deg(0.0));
+        let mut follow = Follow::new(Point3::new(5.0, 0.0, 5.0), Vector3::new(0.0, 0.0, 10.0), 0.0);
+        follow.apply(&mut camera, Duration::from_secs_f32(0.016));
+        assert_relative_eq!(camera.position.z, 15.0, epsilon = 1e-4);
+        // Looking down -z
+        assert_relative_eq!(camera.yaw.0, -FRAC_PI_2, epsilon = 1e-4);
+        assert_relative_eq!(camera.pitch.0, 0.0, epsilon = 1e-4);
+    }
+
+    #[test]
+    fn follow_rotation_input_orbits_target() {
+        let target = Point3::new(5.0, 0.0, 5.0);
+        let mut camera = Camera::new(Point3::new(0.0, 0.0, 0.0), Deg(0.0), Deg(0.0));
+        let mut follow = Follow::new(target, Vector3::new(0.0, 0.0, 10.0), 0.0);
+        follow.apply(&mut camera, Duration::from_secs_f32(0.016));
+        // The controller turns the camera by 90°
+        camera.yaw += Rad(FRAC_PI_2);
+        follow.apply(&mut camera, Duration::from_secs_f32(0.016));
+        assert_relative_eq!((camera.position - target).magnitude(), 10.0, epsilon = 1e-4);
+        // Looking down +x now, so the camera sits on the -x side of the target
+        assert_relative_eq!(camera.position.x, -5.0, epsilon = 1e-3);
+        assert_relative_eq!(camera.position.z, 5.0, epsilon = 1e-3);
+        assert_relative_eq!(camera.yaw.0, 0.0, epsilon = 1e-3);
+    }
+
+    #[test]
+    fn follow_provider_is_queried_every_frame() {
+        let shared = std::rc::Rc::new(std::cell::Cell::new(Point3::new(0.0f32, 0.0, 0.0)));
+        let reader = shared.clone();
+        let mut camera = Camera::new(Point3::new(0.0, 0.0, 0.0), Deg(0.0), Deg(0.0));
+        let mut follow = Follow::new(
+            FollowTarget::provider(move || reader.get()),
+            Vector3::new(0.0, 2.0, 0.0),
+            0.0,
+        );
+        shared.set(Point3::new(3.0, 0.0, 0.0));
+        follow.apply(&mut camera, Duration::from_secs_f32(0.016));
+        assert_relative_eq!(camera.position.x, 3.0, epsilon = 1e-4);
+        assert_relative_eq!(camera.position.y, 2.0, epsilon = 1e-4);
+    }
+
+    // --- CameraCollision ---
+
+    const FRAME: Duration = Duration::from_millis(16);
+
+    fn wall_collision(smoothing: f32) -> CameraCollision {
+        // between the pivot at the origin and a camera at z = 10
+        let wall = Aabb::new(Point3::new(-5.0, -5.0, 4.0), Point3::new(5.0, 5.0, 5.0));
+        CameraCollision::new(CollisionConfig {
+            occluders: vec![wall.into()],
+            min_distance: 0.5,
+            smoothing,
+        })
+    }
+
+    fn behind_pivot() -> Camera {
+        Camera::new(Point3::new(0.0, 0.0, 10.0), Deg(-90.0), Deg(0.0))
+    }
+
+    #[test]
+    fn collision_without_occluders_leaves_camera() {
+        let mut collision = CameraCollision::new(CollisionConfig::default());
+        let mut camera = behind_pivot();
+        collision.constrain(&mut camera, Point3::origin(), FRAME);
+        assert_eq!(camera.position, Point3::new(0.0, 0.0, 10.0));
+    }
+
+    #[test]
+    fn camera_is_pulled_in_front_of_occluder() {
+        let mut collision = wall_collision(0.0);
+        let mut camera = behind_pivot();
+        collision.constrain(&mut camera, Point3::origin(), FRAME);
+        assert_relative_eq!(camera.position.z, 3.5, epsilon = 1e-4);
+
+        // the controller's position comes back before its next update
+        collision.restore(&mut camera);
+        assert_eq!(camera.position, Point3::new(0.0, 0.0, 10.0));
+    }
+
+    #[test]
+    fn occluder_around_the_pivot_is_ignored() {
+        let own_bounds = Aabb::new(Point3::new(-1.0, -1.0, -1.0), Point3::new(1.0, 1.0, 1.0));
+        let mut collision = CameraCollision::new(CollisionConfig {
+            occluders: vec![own_bounds.into()],
+            ..Default::default()
+        });
+        let mut camera = behind_pivot();
+        collision.constrain(&mut camera, Point3::origin(), FRAME);
+        assert_eq!(camera.position, Point3::new(0.0, 0.0, 10.0));
+    }
+
+    #[test]
+    fn camera_stays_above_ground() {
+        let mut collision = CameraCollision::new(CollisionConfig {
+            occluders: vec![Occluder::ground(|_, _| 0.0)],
+            min_distance: 0.0,
+            smoothing: 0.0,
+        });
+        let mut camera = Camera::new(Point3::new(0.0, -4.0, 4.0), Deg(-90.0), Deg(45.0));
+        collision.constrain(&mut camera, Point3::new(0.0, 4.0, -4.0), FRAME);
+        assert!(camera.position.y >= 0.0);
+        assert_relative_eq!(camera.position.y, 0.0, epsilon = 0.05);
+        assert_relative_eq!(camera.position.z, 0.0, epsilon = 0.05);
+    }
+
+    #[test]
+    fn camera_eases_back_out_once_unobstructed() {
+        let mut collision = wall_collision(0.3);
+        let mut camera = behind_pivot();
+        collision.constrain(&mut camera, Point3::origin(), FRAME);
+        assert_relative_eq!(camera.position.z, 3.5, epsilon = 1e-4);
+
+        collision.config.occluders.clear();
+        collision.config.occluders.push(Occluder::ground(|_, _| -100.0));
+        let mut previous = camera.position.z;
+        for _ in 0..10 {
+            collision.restore(&mut camera);
+            collision.constrain(&mut camera, Point3::origin(), FRAME);
+            assert!(camera.position.z > previous && camera.position.z < 10.0);
+            previous = camera.position.z;
+        }
+        for _ in 0..200 {
+            collision.restore(&mut camera);
+            collision.constrain(&mut camera, Point3::origin(), FRAME);
+        }
+        assert_eq!(camera.position, Point3::new(0.0, 0.0, 10.0));
+    }
+
     // --- Projection::calc_matrix ---
 
     #[test]
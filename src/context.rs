@@ -1,23 +1,73 @@
-use std::sync::Arc;
+use std::{
+    any::Any,
+    cell::{Cell, RefCell},
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
 
-use cgmath::num_traits::ToPrimitive;
+use cgmath::{Matrix4, num_traits::ToPrimitive};
+use image::RgbaImage;
 use wgpu::{ExperimentalFeatures, util::DeviceExt};
-use winit::{dpi::PhysicalPosition, window::Window};
+use winit::{
+    dpi::{LogicalPosition, PhysicalPosition, PhysicalSize},
+    error::ExternalError,
+    event::{Ime, MouseButton, WindowEvent},
+    keyboard::{Key, KeyCode, NamedKey},
+    window::{CursorGrabMode, CursorIcon, Fullscreen, Window, WindowAttributes},
+};
 
 use crate::{
-    camera::{self, CameraResources, CameraUniform, Projection},
-    data_structures::{instance::Instance, texture},
-    pick::PickId,
+    camera::{
+        self, CameraResources, CameraUniform, CameraView, MAIN_CAMERA, Projection,
+        project_to_screen,
+    },
+    data_structures::{
+        instance::Instance,
+        texture::{self, DepthConfig},
+        visibility::VisibilitySet,
+    },
+    error::Error,
+    flow::{
+        ConfigureFuture, ConfigureQueue, EventSender, FlowActivity, FlowId, FrameTarget,
+        GraphicsFlow, draw_frame,
+    },
+    input::{ActionMap, InputState},
+    loading::{LoadProgress, ProgressTracker},
+    persist::EngineSnapshot,
+    pick::{HoverPicking, PICK_DEPTH_FORMAT, PickId, PickMaterials, PickTargets},
     pipelines::{
         basic::mk_basic_pipeline,
-        gui::{mk_gui_pipeline, mk_screen_size_bind_group, mk_screen_size_bind_group_layout},
-        light::{LightResources, LightUniform, mk_light_pipeline},
+        compat::{self, EngineGroups},
+        gui::{self, mk_gui_pipeline, mk_screen_size_bind_group, mk_screen_size_bind_group_layout},
+        highlight::{Highlight, HighlightResources},
+        light::{self, LightResources, LightUniform, mk_light_pipeline},
+        overdraw::{OverdrawResources, OverdrawStats},
+        overlay::{
+            OverlayResources, mk_overlay_bind_group_layout, mk_overlay_blur_pipeline,
+            mk_overlay_pipeline,
+        },
         pick::mk_pick_pipeline,
         pick_gui::mk_gui_pick_pipeline,
+        scene_depth::SceneDepthResources,
         terrain::mk_terrain_pipeline,
-        transparent::mk_transparent_pipeline,
+        tonemap::{HDR_FORMAT, HdrResources, Tonemapping},
+        transparent::{mk_transparency_bind_group_layout, mk_transparent_pipeline},
     },
-    render::Render,
+    profiling::{self, FrameProfiler, SpikeReport, Timing},
+    render::{Instanced, Render},
+    resources::{
+        assets::AssetCache,
+        memory::{self, MemoryReport},
+        pick::pick_layout,
+        texture::diffuse_normal_layout,
+        thumbnail::padded_bytes_per_row,
+    },
+    stream::{self, LoadTicket, Streams, Uploads},
+    window::{PendingWindow, SecondaryWindow, WindowId},
 };
 
 pub trait GPUResource<'a, 'pass> {
@@ -38,6 +88,15 @@ impl<'a, 'pass> GPUResource<'a, 'pass> for Box<dyn GPUResource<'a, 'pass> + Send
         (**self).write_to_buffer(queue, device);
     }
 
+    fn write_to_buffer_offset(
+        &mut self,
+        queue: &wgpu::Queue,
+        device: &wgpu::Device,
+        offset: &Instance,
+    ) {
+        (**self).write_to_buffer_offset(queue, device, offset);
+    }
+
     fn get_render(&'a self) -> Render<'a, 'pass> {
         (**self).get_render()
     }
@@ -54,6 +113,15 @@ impl<'a, 'pass> GPUResource<'a, 'pass> for Box<dyn GPUResource<'a, 'pass>> {
         (**self).write_to_buffer(queue, device);
     }
 
+    fn write_to_buffer_offset(
+        &mut self,
+        queue: &wgpu::Queue,
+        device: &wgpu::Device,
+        offset: &Instance,
+    ) {
+        (**self).write_to_buffer_offset(queue, device, offset);
+    }
+
     fn get_render(&'a self) -> Render<'a, 'pass> {
         (**self).get_render()
     }
@@ -75,13 +143,139 @@ impl AntiAliasing {
     }
 }
 
-#[derive(Debug)]
+/// When the event loop renders frames, see [`Context::render_strategy`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RenderStrategy {
+    /// Renders frame after frame, e.g. for games.
+    #[default]
+    Continuous,
+    /// Renders only after input, a resize, camera movement, a playing animation or
+    /// [`Context::request_frame`], e.g. for editors that shouldn't keep the GPU busy while
+    /// nothing changes. In between the event loop sleeps until the next event or tick.
+    ///
+    /// `on_tick` keeps its rate, each tick rendering a frame. The `dt` passed to `on_update`
+    /// is the time since the previous frame capped at [`MAX_ON_DEMAND_DT`], so the first frame
+    /// after an idle period doesn't jump. Shaders animating with the time uniform, such as
+    /// [`MaterialAnimator`](crate::data_structures::model::MaterialAnimator), only advance
+    /// while frames are requested.
+    OnDemand,
+}
+
+/// Default step of `on_fixed_update`, see [`Context::set_fixed_step`].
+pub const DEFAULT_FIXED_STEP: Duration = Duration::from_nanos(16_666_667);
+
+/// Largest `dt` passed to `on_update` in [`RenderStrategy::OnDemand`].
+pub const MAX_ON_DEMAND_DT: Duration = Duration::from_millis(100);
+
+// Set through `request_frame`, which animations call without a context at hand
+static FRAME_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// Renders another frame in [`RenderStrategy::OnDemand`], see [`Context::request_frame`].
+pub(crate) fn request_frame() {
+    FRAME_REQUESTED.store(true, Ordering::Relaxed);
+}
+
+/// Whether a frame was requested since the last call, resetting the request.
+pub(crate) fn take_frame_request() -> bool {
+    FRAME_REQUESTED.swap(false, Ordering::Relaxed)
+}
+
+/// Debug visualisation replacing the regular 3D rendering, see [`Context::set_debug_render`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DebugRender {
+    #[default]
+    None,
+    /// Colours every pixel by how many fragments of opaque, transparent and terrain batches were
+    /// shaded for it: blue for one, then green, yellow, red and white at 2, 4, 8 and 16+.
+    Overdraw,
+}
+
+/// Numbers collected while rendering, see [`Context::stats`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RenderStats {
+    /// Only available in [`DebugRender::Overdraw`].
+    pub overdraw: Option<OverdrawStats>,
+    /// Breakdown of the latest frame exceeding [`Context::spike_threshold`].
+    pub last_spike: Option<SpikeReport>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum MouseButtonState {
     Right,
     Left,
+    Middle,
     None,
 }
 
+/// Fullscreen kinds of [`Context::set_fullscreen`], both on the monitor showing the window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FullscreenMode {
+    /// Borderless window covering the monitor, switches instantly.
+    Borderless,
+    /// Exclusive fullscreen in the monitor's largest video mode, falling back to borderless
+    /// where video modes can't be changed, e.g. on the web.
+    Exclusive,
+}
+
+/// How mouse input drives the camera, see [`Context::input_settings`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InputSettings {
+    /// Factor applied to mouse motion before it reaches the camera controller.
+    pub mouse_sensitivity: f64,
+    /// Button rotating the camera while held, [`MouseButtonState::None`] disables it.
+    /// Controllers with raw mouse input rotate regardless.
+    pub rotate_button: MouseButtonState,
+    /// Whether pressing the rotate button on a pickable object leaves the camera alone, e.g.
+    /// so right clicks on units issue commands. Requires the button in
+    /// [`Context::pick_buttons`].
+    pub rotate_off_objects_only: bool,
+    /// Whether the left stick of a gamepad moves the camera and its south button clicks,
+    /// with the `gamepad` feature.
+    pub gamepad_camera: bool,
+    /// Longest time between two clicks on an object for [`PointerEvent::DoubleClicked`].
+    ///
+    /// [`PointerEvent::DoubleClicked`]: crate::flow::PointerEvent::DoubleClicked
+    pub double_click_time: Duration,
+    /// Pixels the cursor may move between the clicks of a double click.
+    pub double_click_distance: f64,
+    /// Pixels the cursor moves with a button held on an object before it is dragged.
+    pub drag_threshold: f64,
+}
+
+impl Default for InputSettings {
+    fn default() -> Self {
+        Self {
+            mouse_sensitivity: 5.0,
+            rotate_button: MouseButtonState::Right,
+            rotate_off_objects_only: false,
+            gamepad_camera: true,
+            double_click_time: Duration::from_millis(400),
+            double_click_distance: 4.0,
+            drag_threshold: 4.0,
+        }
+    }
+}
+
+impl InputSettings {
+    /// Whether holding `pressed` rotates the camera.
+    pub(crate) fn rotates(&self, pressed: MouseButtonState) -> bool {
+        pressed != MouseButtonState::None && pressed == self.rotate_button
+    }
+}
+
+/// What keeps running while the window is unfocused, see [`Context::unfocused`].
+///
+/// Both default to off, e.g. a game pauses itself from `GraphicsFlow::on_focus` instead.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct UnfocusedSettings {
+    /// Skips `on_update`, `on_fixed_update` and `on_tick`, game time stands still until the
+    /// window is focused again. Frames are still drawn.
+    pub stop_updates: bool,
+    /// Frame rate cap in addition to [`Context::set_max_fps`], e.g. `Some(10)` to keep a
+    /// visible but unfocused window from using a whole core. Zero is ignored.
+    pub max_fps: Option<u32>,
+}
+
 #[derive(Debug)]
 pub struct MouseState {
     pub coords: PhysicalPosition<f64>,
@@ -98,16 +292,327 @@ impl MouseState {
     }
 }
 
+/// Keyboard input for the flow holding keyboard focus, see [`Context::request_keyboard_focus`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum TextEvent {
+    /// Typed text or text committed by an IME.
+    Insert(String),
+    Backspace,
+    Delete,
+    /// Enter was pressed.
+    Submit,
+    /// Escape was pressed.
+    Cancel,
+    /// Any other named key, e.g. arrows, `Home` or `End`.
+    Key(NamedKey),
+    /// IME composition in progress. An empty `text` ends the composition, `cursor` is the
+    /// byte range of the IME cursor within `text`.
+    Preedit {
+        text: String,
+        cursor: Option<(usize, usize)>,
+    },
+}
+
+impl TextEvent {
+    /// Translates key presses and IME events, other events yield `None`.
+    pub(crate) fn from_window_event(event: &WindowEvent) -> Option<Self> {
+        match event {
+            WindowEvent::KeyboardInput { event, .. } if event.state.is_pressed() => {
+                let text = event
+                    .text
+                    .as_ref()
+                    .filter(|text| !text.chars().all(char::is_control))
+                    .map(|text| TextEvent::Insert(text.to_string()));
+                match &event.logical_key {
+                    Key::Named(NamedKey::Backspace) => Some(TextEvent::Backspace),
+                    Key::Named(NamedKey::Delete) => Some(TextEvent::Delete),
+                    Key::Named(NamedKey::Enter) => Some(TextEvent::Submit),
+                    Key::Named(NamedKey::Escape) => Some(TextEvent::Cancel),
+                    // Named keys with text, e.g. space, insert it
+                    Key::Named(named) => text.or(Some(TextEvent::Key(*named))),
+                    _ => text,
+                }
+            }
+            WindowEvent::Ime(Ime::Commit(text)) => Some(TextEvent::Insert(text.clone())),
+            WindowEvent::Ime(Ime::Preedit(text, cursor)) => Some(TextEvent::Preedit {
+                text: text.clone(),
+                cursor: *cursor,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Representative point of a hovered object used to anchor tooltips.
+#[derive(Debug, Clone, Copy)]
+pub enum HoverTarget {
+    /// A 3D object, e.g. the position of the hovered instance.
+    World(cgmath::Point3<f32>),
+    /// A 2D element, e.g. the center of a `Flat` rect in window pixels.
+    Screen(PhysicalPosition<f64>),
+}
+
+/// Where a tooltip for the hovered object should be drawn this frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TooltipAnchor {
+    pub id: PickId,
+    /// Window pixels, clamped to stay on screen.
+    pub position: PhysicalPosition<f64>,
+    /// 1.0 while hovered, decreasing to 0.0 over `TooltipState::fade` once the anchor is
+    /// fading, see [`TooltipState::fading`].
+    pub opacity: f32,
+}
+
+#[derive(Debug)]
+pub struct TooltipState {
+    pub hovered: Option<(PickId, HoverTarget)>,
+    /// Time the last anchor lingers after nothing is hovered anymore.
+    pub fade: Duration,
+    /// Distance in pixels kept between the anchor and the window border.
+    pub margin: f64,
+    anchor: Option<TooltipAnchor>,
+    // Whether `anchor` follows a hovered target rather than fading out
+    current: bool,
+}
+
+impl Default for TooltipState {
+    fn default() -> Self {
+        Self {
+            hovered: None,
+            fade: Duration::from_millis(150),
+            margin: 8.0,
+            anchor: None,
+            current: false,
+        }
+    }
+}
+
+impl TooltipState {
+    /// Anchor of the hovered target, `None` as soon as nothing is hovered.
+    pub fn anchor(&self) -> Option<TooltipAnchor> {
+        self.anchor.filter(|_| self.current)
+    }
+
+    /// The last anchor while it fades out after the hover ended, with its decreasing
+    /// `opacity`.
+    pub fn fading(&self) -> Option<TooltipAnchor> {
+        self.anchor.filter(|_| !self.current)
+    }
+
+    /**
+     * Advances the anchor by one frame.
+     *
+     * `projected` is the screen position of the hovered target (if any is hovered and
+     * visible). Without one the previous anchor stays in place as [`TooltipState::fading`]
+     * so tooltips don't flicker while the cursor crosses gaps between objects.
+     */
+    pub(crate) fn update(
+        &mut self,
+        projected: Option<PhysicalPosition<f64>>,
+        width: f64,
+        height: f64,
+        dt: Duration,
+    ) {
+        match (self.hovered, projected) {
+            (Some((id, _)), Some(position)) => {
+                let clamp = |v: f64, max: f64| {
+                    v.clamp(
+                        self.margin.min(max / 2.0),
+                        (max - self.margin).max(max / 2.0),
+                    )
+                };
+                self.anchor = Some(TooltipAnchor {
+                    id,
+                    position: PhysicalPosition::new(
+                        clamp(position.x, width),
+                        clamp(position.y, height),
+                    ),
+                    opacity: 1.0,
+                });
+                self.current = true;
+            }
+            _ => {
+                self.current = false;
+                self.anchor = self.anchor.and_then(|mut anchor| {
+                    let step = if self.fade.is_zero() {
+                        1.0
+                    } else {
+                        dt.as_secs_f32() / self.fade.as_secs_f32()
+                    };
+                    anchor.opacity -= step;
+                    (anchor.opacity > 0.0).then_some(anchor)
+                });
+            }
+        }
+    }
+}
+
+/// Bind group layouts shared by the pipelines and the resources bound to them.
+///
+/// Created once per device, so loading models or picking doesn't create a layout per call.
+#[derive(Debug, Clone)]
+pub struct Layouts {
+    /// Diffuse and normal textures of model materials.
+    pub diffuse_normal: wgpu::BindGroupLayout,
+    pub camera: wgpu::BindGroupLayout,
+    pub light: wgpu::BindGroupLayout,
+    /// Id uniform of pick materials and flat pick draws.
+    pub pick: wgpu::BindGroupLayout,
+    /// Texture and sampler of GUI elements.
+    pub gui: wgpu::BindGroupLayout,
+    /// Colour uniform of the pause overlay.
+    pub overlay: wgpu::BindGroupLayout,
+    /// Transparency uniform and scene depth of transparent draws.
+    pub transparency: wgpu::BindGroupLayout,
+}
+
+impl Layouts {
+    pub fn new(device: &wgpu::Device) -> Self {
+        Self {
+            diffuse_normal: diffuse_normal_layout(device),
+            camera: camera::mk_camera_bind_group_layout(device),
+            light: light::mk_bind_group_layout(device),
+            pick: pick_layout(device),
+            gui: gui::mk_bind_group_layout(device),
+            overlay: mk_overlay_bind_group_layout(device),
+            transparency: mk_transparency_bind_group_layout(device),
+        }
+    }
+}
+
+/// Render pipelines shared by all flows.
+///
+/// Instanced pipelines come in three cull variants: `*` culls clockwise triangles (the back
+/// faces of counter-clockwise meshes), `*_cw` counter-clockwise ones and `*_no_cull` none.
+/// Draws pick theirs with [`Instanced::culled_winding`].
 #[derive(Debug)]
 pub struct Pipelines {
     pub light: wgpu::RenderPipeline,
     pub basic: wgpu::RenderPipeline,
     pub basic_cw: wgpu::RenderPipeline,
+    pub basic_no_cull: wgpu::RenderPipeline,
     pub pick: wgpu::RenderPipeline,
+    pub pick_cw: wgpu::RenderPipeline,
+    pub pick_no_cull: wgpu::RenderPipeline,
     pub gui: wgpu::RenderPipeline,
     pub transparent: wgpu::RenderPipeline,
+    pub transparent_cw: wgpu::RenderPipeline,
+    pub transparent_no_cull: wgpu::RenderPipeline,
     pub terrain: wgpu::RenderPipeline,
     pub flat_pick: wgpu::RenderPipeline,
+    pub overlay: wgpu::RenderPipeline,
+    pub overlay_blur: wgpu::RenderPipeline,
+}
+
+impl Pipelines {
+    fn new(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        color_format: wgpu::TextureFormat,
+        layouts: &Layouts,
+        screen_size_layout: &wgpu::BindGroupLayout,
+        sample_count: u32,
+        depth: DepthConfig,
+    ) -> Self {
+        use wgpu::{Face, FrontFace};
+        // Everything in the scene pass draws into the same target
+        let config = &wgpu::SurfaceConfiguration {
+            format: color_format,
+            ..config.clone()
+        };
+        #[cfg(debug_assertions)]
+        if let Err(error) = compat::check_engine_shaders() {
+            panic!("{error}");
+        }
+        let basic = |direction, cull| {
+            mk_basic_pipeline(
+                device,
+                color_format,
+                direction,
+                cull,
+                layouts,
+                sample_count,
+                depth,
+            )
+        };
+        let transparent = |direction, cull| {
+            mk_transparent_pipeline(
+                device,
+                color_format,
+                direction,
+                cull,
+                layouts,
+                sample_count,
+                depth,
+            )
+        };
+        // Picks read their depth back, which not every depth format can be copied for
+        let pick_depth = depth.with_format(PICK_DEPTH_FORMAT);
+        let pick = |direction, cull| mk_pick_pipeline(device, direction, cull, layouts, pick_depth);
+        Self {
+            light: mk_light_pipeline(
+                device,
+                config,
+                &layouts.light,
+                &layouts.camera,
+                sample_count,
+                depth,
+            ),
+            basic: basic(FrontFace::Ccw, Some(Face::Back)),
+            basic_cw: basic(FrontFace::Cw, Some(Face::Back)),
+            basic_no_cull: basic(FrontFace::Ccw, None),
+            pick: pick(FrontFace::Ccw, Some(Face::Back)),
+            pick_cw: pick(FrontFace::Cw, Some(Face::Back)),
+            pick_no_cull: pick(FrontFace::Ccw, None),
+            gui: mk_gui_pipeline(
+                device,
+                config,
+                layouts,
+                screen_size_layout,
+                sample_count,
+                depth,
+            ),
+            transparent: transparent(FrontFace::Ccw, Some(Face::Back)),
+            transparent_cw: transparent(FrontFace::Cw, Some(Face::Back)),
+            transparent_no_cull: transparent(FrontFace::Ccw, None),
+            terrain: mk_terrain_pipeline(
+                device,
+                config,
+                &layouts.camera,
+                &layouts.light,
+                sample_count,
+                8,
+                depth,
+            ),
+            flat_pick: mk_gui_pick_pipeline(device, layouts, screen_size_layout, pick_depth),
+            overlay: mk_overlay_pipeline(device, config, sample_count, depth),
+            overlay_blur: mk_overlay_blur_pipeline(device, config, sample_count, depth),
+        }
+    }
+
+    pub(crate) fn basic_for(&self, instanced: &Instanced) -> &wgpu::RenderPipeline {
+        match instanced.culled_winding() {
+            Some(wgpu::FrontFace::Cw) => &self.basic,
+            Some(wgpu::FrontFace::Ccw) => &self.basic_cw,
+            None => &self.basic_no_cull,
+        }
+    }
+
+    pub(crate) fn transparent_for(&self, instanced: &Instanced) -> &wgpu::RenderPipeline {
+        match instanced.culled_winding() {
+            Some(wgpu::FrontFace::Cw) => &self.transparent,
+            Some(wgpu::FrontFace::Ccw) => &self.transparent_cw,
+            None => &self.transparent_no_cull,
+        }
+    }
+
+    pub(crate) fn pick_for(&self, instanced: &Instanced) -> &wgpu::RenderPipeline {
+        match instanced.culled_winding() {
+            Some(wgpu::FrontFace::Cw) => &self.pick,
+            Some(wgpu::FrontFace::Ccw) => &self.pick_cw,
+            None => &self.pick_no_cull,
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -119,67 +624,434 @@ pub struct ScreenSizeResources {
 
 #[derive(Debug)]
 pub struct Context {
-    pub window: Arc<Window>,
+    /// `None` for a context created through [`Context::new_headless`], see
+    /// [`Context::window`].
+    pub window: Option<Arc<Window>>,
     pub(crate) depth_texture: texture::Texture,
     pub(crate) msaa_view: Option<wgpu::TextureView>,
     pub anti_aliasing: AntiAliasing,
     pub tick_duration_millis: u64,
+    /// Whether frames are rendered continuously or only when something changed.
+    pub render_strategy: RenderStrategy,
     pub clear_colour: wgpu::Color,
-    pub surface: wgpu::Surface<'static>,
+    /// `None` for a context created through [`Context::new_headless`], see
+    /// [`Context::surface`].
+    pub surface: Option<wgpu::Surface<'static>>,
+    // Colour target of a headless context
+    pub(crate) offscreen: Option<wgpu::Texture>,
     pub device: wgpu::Device,
     pub queue: wgpu::Queue,
     pub mouse: MouseState,
+    /// Keys and mouse buttons held down, pressed or released this frame.
+    pub input: InputState,
+    /// Surface configuration, change the present mode and frame latency through
+    /// [`Context::set_present_mode`] and [`Context::set_frame_latency`].
     pub config: wgpu::SurfaceConfiguration,
+    // Present modes of the surface capabilities
+    present_modes: Vec<wgpu::PresentMode>,
     pub camera: CameraResources,
     pub projection: Projection,
     pub light: LightResources,
+    /// Bind group layouts of the pipelines, e.g. to create materials or pick models.
+    pub layouts: Layouts,
     pub pipelines: Pipelines,
     pub screen_size: ScreenSizeResources,
+    pub tooltip: TooltipState,
+    /// Tint of the draw in [`MouseState::selection`], `None` disables it.
+    pub selection_highlight: Option<[f32; 4]>,
+    /// Tint of the draw marked through [`Context::set_hovered`], `None` disables it.
+    pub hover_highlight: Option<[f32; 4]>,
+    highlights: Vec<Highlight>,
+    pub(crate) hover: HoverPicking,
+    pub(crate) highlight: HighlightResources,
+    /// Loaded textures and models, e.g. to unload the previous level's assets.
+    pub assets: AssetCache,
+    /// Keys bound to actions, [`ActionMap::PAUSE`] toggles [`Context::set_paused`] and is bound
+    /// to Escape by default.
+    pub actions: ActionMap,
+    /// Tint blended over the 3D scene while paused. An alpha of `0.0` disables it.
+    pub pause_overlay: wgpu::Color,
+    /// Radius in pixels the 3D scene is blurred by while paused, `0.0`, the default, disables
+    /// it. Needs a target that can be copied, otherwise the scene is only tinted.
+    pub pause_blur: f32,
+    pub(crate) overlay: OverlayResources,
+    pub input_settings: InputSettings,
+    /// Updates and frame rate while the window is unfocused.
+    pub unfocused: UnfocusedSettings,
+    /// Mouse buttons picking the object under the cursor, passed to `on_click_with`. Only
+    /// the left button by default.
+    pub pick_buttons: HashSet<MouseButton>,
+    /// Frames taking longer than this multiple of the rolling average frame time are logged
+    /// and kept in [`RenderStats::last_spike`]. `f32::INFINITY` disables the reports.
+    pub spike_threshold: f32,
+    /// Whether the camera's controller moves [`CameraResources::camera`] and receives input.
+    /// Disable it to move the camera from a flow alone, e.g. during a cutscene. Tweens, shakes
+    /// and follows still apply.
+    pub camera_controller_enabled: bool,
+    /// Time each frame spends on the GPU uploads of [`Context::spawn_load`], at least one
+    /// step runs per frame. Defaults to 2 ms.
+    pub stream_budget: Duration,
+    /// Whether the light circles around the scene's vertical axis over game time.
+    pub auto_light_rotation_enabled: bool,
+    keyboard_focus: Option<FlowId>,
+    paused: bool,
+    window_focused: bool,
+    suspended: bool,
+    recovering_surface: bool,
+    // Flows not `FlowActivity::ACTIVE`, see `set_flow_active`
+    flow_activity: HashMap<FlowId, FlowActivity>,
+    // Set through `request_exit`, which hooks only get `&Context` for
+    exit_requested: Cell<bool>,
+    // Set through `capture_input` while a window or device event is dispatched
+    input_captured: Cell<bool>,
+    // Recording or replaying the session, see `crate::record`
+    #[cfg(feature = "record")]
+    pub(crate) session: Option<crate::record::Session>,
+    debug_render: DebugRender,
+    cameras: HashMap<String, CameraView>,
+    // `None` renders through the main camera
+    active_camera: Option<String>,
+    pub(crate) stats: RenderStats,
+    pub(crate) profiler: FrameProfiler,
+    pub(crate) timing: Timing,
+    // Progress of the flow constructors, `None` once they resolved
+    pub(crate) loading: Option<ProgressTracker>,
+    pub(crate) overdraw: Option<OverdrawResources>,
+    // Pipelines of `register_pipeline` by label
+    custom_pipelines: HashMap<String, wgpu::RenderPipeline>,
+    // Per window, as they may differ in size
+    pub(crate) scene_depth: BTreeMap<WindowId, SceneDepthResources>,
+    tonemapping: Option<Tonemapping>,
+    pub(crate) hdr: BTreeMap<WindowId, HdrResources>,
+    depth: DepthConfig,
+    pub(crate) ticks: u64,
+    pub(crate) tick_paused: bool,
+    pub(crate) tick_alpha: f32,
+    time_scale: f32,
+    fixed_step: Duration,
+    pub(crate) fixed_alpha: f32,
+    max_fps: Option<u32>,
+    // Size applied by `set_window_size` without a `Resized` event following
+    pub(crate) requested_size: Option<PhysicalSize<u32>>,
+    // Set by `set_ui_scale` to re-apply the current size through `on_resize`
+    pub(crate) ui_relayout: bool,
+    ui_scale: f32,
+    // Of the main window, updated on `ScaleFactorChanged`
+    scale_factor: f64,
+    /// Smallest size in physical pixels text is drawn at, whatever its font size and the
+    /// [UI scale](Context::set_ui_scale). `0.0` disables the clamp.
+    pub min_text_size: f32,
+    cursor_grab: CursorGrabMode,
+    pub(crate) visibility: HashMap<PickId, VisibilitySet>,
+    pub(crate) configures: ConfigureQueue,
+    // Futures of `Out::ConfigureFut`, polled by the app
+    pub(crate) configure_futures: Vec<ConfigureFuture>,
+    // Constructors of `Out::SpawnFlow`, erased as the context isn't generic over the flows
+    pub(crate) spawned_flows: Vec<Box<dyn Any>>,
+    // Closures of `Out::Snapshot`, erased like the constructors
+    pub(crate) snapshots: Vec<Box<dyn Any>>,
+    removed_flows: RefCell<Vec<FlowId>>,
+    // Loads of `spawn_load`, which hooks only get `&Context` for
+    pub(crate) streams: RefCell<Streams>,
+    flow_order: RefCell<Option<Vec<FlowId>>>,
+    // Reused by every pick, created on the first one
+    pub(crate) pick_targets: RefCell<Option<PickTargets>>,
+    pub(crate) pick_materials: RefCell<PickMaterials>,
+    // Events of `defer_event`, erased like the constructors
+    deferred_events: RefCell<Vec<Box<dyn Any>>>,
+    // Set through `broadcast_events`, `None` passes events on until a flow consumes them
+    pub(crate) event_cloner: Option<EventCloner>,
+    dead_letters: Option<DeadLetterHandler>,
+    // Instance and adapter creating the surfaces of secondary windows, `None` when headless
+    gpu: Option<(wgpu::Instance, wgpu::Adapter)>,
+    // Raised by the device callbacks of `watch_device`
+    gpu_lost: Arc<AtomicBool>,
+    pub(crate) windows: BTreeMap<WindowId, SecondaryWindow>,
+    // Windows of `create_window` the event loop opens once it can
+    pub(crate) pending_windows: Vec<PendingWindow>,
+    next_window: u32,
+    #[cfg(feature = "integration-tests")]
+    pub(crate) scripted_clicks: Vec<PhysicalPosition<f64>>,
+    // Set through `force_surface_loss`, the next frame treats the surface as lost
+    #[cfg(feature = "integration-tests")]
+    pub(crate) forced_surface_loss: bool,
+}
+/// Smallest and largest factor accepted by [`Context::set_ui_scale`].
+pub const UI_SCALE_RANGE: (f32, f32) = (0.5, 4.0);
+
+fn clamp_ui_scale(scale: f32) -> f32 {
+    if scale.is_nan() {
+        return 1.0;
+    }
+    scale.clamp(UI_SCALE_RANGE.0, UI_SCALE_RANGE.1)
+}
+
+fn text_scale(ui_scale: f32, font_size: f32, min_text_size: f32) -> f32 {
+    if font_size <= 0.0 {
+        return ui_scale;
+    }
+    ui_scale.max(min_text_size / font_size)
+}
+
+/// Format of the colour target of headless contexts and of [`Context::render_once`] images.
+const HEADLESS_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+/// Backends and adapter a windowed context is created on, see
+/// [`RunConfig::with_adapter`](crate::flow::RunConfig::with_adapter).
+#[derive(Debug, Clone, PartialEq)]
+pub struct AdapterOptions {
+    /// Defaults to [`AdapterOptions::DEFAULT_BACKENDS`].
+    pub backends: wgpu::Backends,
+    pub power_preference: wgpu::PowerPreference,
+    /// Part of the adapter name to look for ignoring case, e.g. `"nvidia"`. Without a match
+    /// the adapter is picked by `power_preference`.
+    pub name: Option<String>,
 }
+
+impl AdapterOptions {
+    /// Vulkan, Metal, DX12 and WebGPU natively, WebGL on the web.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub const DEFAULT_BACKENDS: wgpu::Backends = wgpu::Backends::PRIMARY;
+    #[cfg(target_arch = "wasm32")]
+    pub const DEFAULT_BACKENDS: wgpu::Backends = wgpu::Backends::GL;
+}
+
+impl Default for AdapterOptions {
+    fn default() -> Self {
+        Self {
+            backends: Self::DEFAULT_BACKENDS,
+            power_preference: wgpu::PowerPreference::default(),
+            name: None,
+        }
+    }
+}
+
+fn name_matches(adapter_name: &str, name: &str) -> bool {
+    adapter_name.to_lowercase().contains(&name.to_lowercase())
+}
+
+/// Handle to the GPU on `backends`.
+fn mk_instance(backends: wgpu::Backends) -> wgpu::Instance {
+    wgpu::Instance::new(wgpu::InstanceDescriptor {
+        backends,
+        flags: wgpu::InstanceFlags::default(),
+        memory_budget_thresholds: wgpu::MemoryBudgetThresholds::default(),
+        backend_options: wgpu::BackendOptions::default(),
+        display: None,
+    })
+}
+
+async fn request_adapter(
+    instance: &wgpu::Instance,
+    surface: Option<&wgpu::Surface<'static>>,
+    options: &AdapterOptions,
+) -> Result<wgpu::Adapter, Error> {
+    if let Some(name) = &options.name {
+        let named = instance
+            .enumerate_adapters(options.backends)
+            .await
+            .into_iter()
+            .find(|adapter| {
+                surface.is_none_or(|surface| adapter.is_surface_supported(surface))
+                    && name_matches(&adapter.get_info().name, name)
+            });
+        match named {
+            Some(adapter) => return Ok(adapter),
+            None => log::warn!("No adapter matches {name:?}, falling back to the default"),
+        }
+    }
+    instance
+        .request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: options.power_preference,
+            compatible_surface: surface,
+            force_fallback_adapter: false,
+        })
+        .await
+        .map_err(|e| Error::Gpu(e.into()))
+}
+
+// Instance, surface (for a window), adapter, device and queue of a context
+type Connection = (
+    wgpu::Instance,
+    Option<wgpu::Surface<'static>>,
+    wgpu::Adapter,
+    wgpu::Device,
+    wgpu::Queue,
+);
+
+/// Creates the surface of `window` (if any) and the device of `request` on the adapter picked
+/// by `options`.
+async fn connect(
+    window: Option<&Arc<Window>>,
+    options: &AdapterOptions,
+    request: &DeviceRequest,
+) -> Result<Connection, Error> {
+    let instance = mk_instance(options.backends);
+    let surface = window
+        .map(|window| instance.create_surface(window.clone()))
+        .transpose()
+        .map_err(|e| Error::Gpu(e.into()))?;
+    let adapter = request_adapter(&instance, surface.as_ref(), options).await?;
+    let info = adapter.get_info();
+    log::info!(
+        "Using adapter {} ({:?}, {:?})",
+        info.name,
+        info.backend,
+        info.device_type
+    );
+    let (device, queue) = request_device(&adapter, request).await?;
+    Ok((instance, surface, adapter, device, queue))
+}
+
+/// Like [`connect`], retrying on the default backends if `options` names others that
+/// don't have a suitable adapter.
+async fn connect_or_default(
+    window: Option<&Arc<Window>>,
+    options: &AdapterOptions,
+    request: &DeviceRequest,
+) -> Result<Connection, Error> {
+    match connect(window, options, request).await {
+        Err(e) if options.backends != AdapterOptions::DEFAULT_BACKENDS => {
+            log::warn!(
+                "No adapter on {:?} ({e}), falling back to the default backends",
+                options.backends
+            );
+            let options = AdapterOptions {
+                backends: AdapterOptions::DEFAULT_BACKENDS,
+                ..options.clone()
+            };
+            connect(window, &options, request).await
+        }
+        connected => connected,
+    }
+}
+
+/// Features and limits the device is requested with, see
+/// [`RunConfig::with_device_request`](crate::flow::RunConfig::with_device_request). Check
+/// what the device ended up with through `ctx.device.features()` and `ctx.device.limits()`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceRequest {
+    pub features: wgpu::Features,
+    /// Defaults to `Limits::default()` natively and the WebGL2 limits on the web.
+    pub limits: wgpu::Limits,
+}
+
+impl Default for DeviceRequest {
+    fn default() -> Self {
+        Self {
+            features: wgpu::Features::empty(),
+            // WebGL doesn't support all of wgpu's features, so if
+            // we're building for the web we'll have to disable some.
+            limits: if cfg!(target_arch = "wasm32") {
+                wgpu::Limits::downlevel_webgl2_defaults()
+            } else {
+                wgpu::Limits::default()
+            },
+        }
+    }
+}
+
+impl DeviceRequest {
+    /// Features and limits of the request beyond what `features` and `limits` of an adapter
+    /// offer, e.g. `feature POLYGON_MODE_LINE`.
+    fn missing(&self, features: wgpu::Features, limits: &wgpu::Limits) -> Vec<String> {
+        let mut missing: Vec<_> = self
+            .features
+            .difference(features)
+            .iter_names()
+            .map(|(name, _)| format!("feature {name}"))
+            .collect();
+        self.limits
+            .check_limits_with_fail_fn(limits, false, |name, requested, allowed| {
+                missing.push(format!("{name} of {requested} (adapter allows {allowed})"))
+            });
+        missing
+    }
+}
+
+async fn request_device(
+    adapter: &wgpu::Adapter,
+    request: &DeviceRequest,
+) -> Result<(wgpu::Device, wgpu::Queue), Error> {
+    let missing = request.missing(adapter.features(), &adapter.limits());
+    if !missing.is_empty() {
+        return Err(Error::MissingCapabilities { missing });
+    }
+    log::warn!("device and queue");
+    let (device, queue) = adapter
+        .request_device(&wgpu::DeviceDescriptor {
+            label: None,
+            required_features: request.features,
+            required_limits: request.limits.clone(),
+            memory_hints: Default::default(),
+            trace: wgpu::Trace::Off,
+            experimental_features: ExperimentalFeatures::disabled(),
+        })
+        .await
+        .map_err(|e| Error::Gpu(e.into()))?;
+    Ok((device, queue))
+}
+
+/// Logs errors outside of error scopes instead of panicking, repeated ones once, and raises
+/// the returned flag once the device is lost or out of memory, see [`Context::is_gpu_lost`].
+fn watch_device(device: &wgpu::Device) -> Arc<AtomicBool> {
+    let lost = Arc::new(AtomicBool::new(false));
+    let flag = lost.clone();
+    device.set_device_lost_callback(move |reason, message| {
+        // Dropping the device, e.g. the old one of `reset_gpu`, isn't a loss
+        if reason != wgpu::DeviceLostReason::Destroyed {
+            log::error!("GPU device lost ({reason:?}): {message}");
+            flag.store(true, Ordering::Relaxed);
+        }
+    });
+    let flag = lost.clone();
+    let last = std::sync::Mutex::new(String::new());
+    device.on_uncaptured_error(Arc::new(move |error| {
+        if matches!(error, wgpu::Error::OutOfMemory { .. }) {
+            flag.store(true, Ordering::Relaxed);
+        }
+        let message = error.to_string();
+        let mut last = last.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if *last != message {
+            log::error!("Uncaptured GPU error: {message}");
+            *last = message;
+        }
+    }));
+    lost
+}
+
+fn mk_offscreen_target(
+    device: &wgpu::Device,
+    config: &wgpu::SurfaceConfiguration,
+) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Headless Color Target"),
+        size: wgpu::Extent3d {
+            width: config.width,
+            height: config.height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: config.format,
+        usage: config.usage,
+        view_formats: &[],
+    })
+}
+
 impl Context {
-    pub(crate) async fn new(window: Arc<Window>) -> Result<Self, anyhow::Error> {
+    pub(crate) async fn new(
+        window: Arc<Window>,
+        adapter: &AdapterOptions,
+        request: &DeviceRequest,
+    ) -> Result<Self, Error> {
         let size = window.inner_size();
 
-        // The instance is a handle to our GPU
-        // BackendBit::PRIMARY => Vulkan + Metal + DX12 + Browser WebGPU
         log::warn!("WGPU setup");
-        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
-            #[cfg(not(target_arch = "wasm32"))]
-            backends: wgpu::Backends::PRIMARY,
-            #[cfg(target_arch = "wasm32")]
-            backends: wgpu::Backends::GL,
-            flags: wgpu::InstanceFlags::default(),
-            memory_budget_thresholds: wgpu::MemoryBudgetThresholds::default(),
-            backend_options: wgpu::BackendOptions::default(),
-            display: None,
-        });
-
-        let surface = instance.create_surface(window.clone())?;
-
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::default(),
-                compatible_surface: Some(&surface),
-                force_fallback_adapter: false,
-            })
-            .await?;
-        log::warn!("device and queue");
-        let (device, queue) = adapter
-            .request_device(&wgpu::DeviceDescriptor {
-                label: None,
-                required_features: wgpu::Features::empty(),
-                // WebGL doesn't support all of wgpu's features, so if
-                // we're building for the web we'll have to disable some.
-                required_limits: if cfg!(target_arch = "wasm32") {
-                    wgpu::Limits::downlevel_webgl2_defaults()
-                } else {
-                    wgpu::Limits::default()
-                },
-                memory_hints: Default::default(),
-                trace: wgpu::Trace::Off,
-                experimental_features: ExperimentalFeatures::disabled(),
-            })
-            .await?;
+        let (instance, surface, adapter, device, queue) =
+            connect_or_default(Some(&window), adapter, request).await?;
+        let surface = surface.expect("surface created for the window");
 
         log::warn!("Surface");
         let surface_caps = surface.get_capabilities(&adapter);
@@ -195,20 +1067,298 @@ impl Context {
             .or(surface_caps.formats.iter().copied().find(|f| f.is_srgb()))
             .unwrap_or(surface_caps.formats[0]);
         let config = wgpu::SurfaceConfiguration {
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            // Copied for the blurred pause overlay where supported
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | (surface_caps.usages & wgpu::TextureUsages::COPY_SRC),
             format: surface_format,
             width: size.width,
             height: size.height,
-            present_mode: surface_caps.present_modes[0],
+            present_mode: supported_present_mode(
+                wgpu::PresentMode::Fifo,
+                &surface_caps.present_modes,
+            ),
             alpha_mode: surface_caps.alpha_modes[0],
             view_formats: vec![],
             desired_maximum_frame_latency: 2,
         };
 
+        let mut ctx = Self::with_device(device, queue, config);
+        ctx.present_modes = surface_caps.present_modes;
+        ctx.surface = Some(surface);
+        ctx.scale_factor = window.scale_factor();
+        ctx.write_screen_size();
+        ctx.window = Some(window);
+        ctx.gpu = Some((instance, adapter));
+        Ok(ctx)
+    }
+
+    /**
+     * Creates a context without a window, e.g. to render thumbnails on a server or in
+     * tools and tests.
+     *
+     * Frames are rendered into an owned `width` x `height` texture through
+     * [`Context::render_once`] instead of being presented, the event loop of
+     * [`run`](crate::flow::run) isn't involved. Apart from that the context behaves like
+     * the one passed to flows, with the same depth texture, camera, light and pipelines.
+     * A size of zero is raised to one pixel.
+     */
+    pub async fn new_headless(width: u32, height: u32) -> Result<Self, Error> {
+        let (_, _, _, device, queue) =
+            connect(None, &AdapterOptions::default(), &DeviceRequest::default()).await?;
+        let max = u64::from(device.limits().max_texture_dimension_2d);
+        Error::check_limit("width of the headless target", u64::from(width), max)?;
+        Error::check_limit("height of the headless target", u64::from(height), max)?;
+        let config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            format: HEADLESS_FORMAT,
+            width: width.max(1),
+            height: height.max(1),
+            present_mode: wgpu::PresentMode::Fifo,
+            alpha_mode: wgpu::CompositeAlphaMode::Opaque,
+            view_formats: vec![],
+            desired_maximum_frame_latency: 2,
+        };
+        let mut ctx = Self::with_device(device, queue, config);
+        ctx.offscreen = Some(mk_offscreen_target(&ctx.device, &ctx.config));
+        Ok(ctx)
+    }
+
+    /// Whether the device was lost or ran out of memory. The event loop then rebuilds the
+    /// GPU state through [`Context::reset_gpu`] before the next frame.
+    pub fn is_gpu_lost(&self) -> bool {
+        self.gpu_lost.load(Ordering::Relaxed)
+    }
+
+    /// Whether the event loop waits for a lost or outdated surface to come back, holding
+    /// updates, ticks and picks meanwhile. Flows learn it's back through
+    /// [`GraphicsFlow::on_gpu_recovered`](crate::flow::GraphicsFlow::on_gpu_recovered).
+    pub fn is_recovering_surface(&self) -> bool {
+        self.recovering_surface
+    }
+
+    pub(crate) fn set_recovering_surface(&mut self, recovering: bool) {
+        self.recovering_surface = recovering;
+    }
+
+    /**
+     * Connects to the GPU again and recreates everything the context holds on it: device,
+     * queue, surfaces, pipelines, depth and MSAA targets and the camera and light buffers.
+     * Settings like the camera, anti-aliasing, depth config or HDR carry over.
+     *
+     * Buffers and textures created on the old device are unusable afterwards. The event
+     * loop calls [`GraphicsFlow::on_gpu_reset`] so flows can upload them again, including
+     * assets of [`Context::assets`], pipelines of [`Context::register_pipeline`] and the
+     * light model.
+     */
+    pub async fn reset_gpu(
+        &mut self,
+        adapter: &AdapterOptions,
+        request: &DeviceRequest,
+    ) -> Result<(), Error> {
+        // A window can only have one surface at a time, so the old ones have to go before
+        // the new instance creates its own
+        self.surface = None;
+        let windows: Vec<_> = std::mem::take(&mut self.windows)
+            .into_iter()
+            .map(|(id, window)| (id, window.window, window.camera))
+            .collect();
+        let fresh = match &self.window {
+            Some(window) => match Self::new(window.clone(), adapter, request).await {
+                // The adapter may be gone with the device, e.g. an unplugged eGPU
+                Err(e) if *adapter != AdapterOptions::default() => {
+                    log::warn!("Unable to reconnect to the adapter ({e}), using the default");
+                    Self::new(window.clone(), &AdapterOptions::default(), request).await?
+                }
+                fresh => fresh?,
+            },
+            None => Self::new_headless(self.config.width, self.config.height).await?,
+        };
+        let Self {
+            device,
+            queue,
+            surface,
+            offscreen,
+            present_modes,
+            config,
+            camera,
+            layouts,
+            screen_size,
+            highlight,
+            overlay,
+            assets,
+            gpu,
+            gpu_lost,
+            ..
+        } = fresh;
+        self.device = device;
+        self.queue = queue;
+        self.offscreen = offscreen;
+        self.layouts = layouts;
+        self.screen_size = screen_size;
+        self.highlight = highlight;
+        self.overlay = overlay;
+        self.assets = assets;
+        self.gpu = gpu;
+        self.gpu_lost = gpu_lost;
+        self.camera.buffer = camera.buffer;
+        self.camera.bind_group = camera.bind_group;
+        self.camera.bind_group_layout = camera.bind_group_layout;
+        self.light = LightResources::with_layout(
+            self.light.uniform,
+            None,
+            &self.device,
+            &self.layouts.light,
+        );
+
+        // Keep the size, present mode and latency of the old surface where possible
+        self.config.format = config.format;
+        if !present_modes.contains(&self.config.present_mode) {
+            self.config.present_mode = config.present_mode;
+        }
+        self.present_modes = present_modes;
+        if let Some(surface) = &surface {
+            surface.configure(&self.device, &self.config);
+        }
+        self.surface = surface;
+
+        self.overdraw = None;
+        self.scene_depth.clear();
+        self.hdr.clear();
+        self.custom_pipelines.clear();
+        self.cameras = std::mem::take(&mut self.cameras)
+            .into_iter()
+            .map(|(name, view)| {
+                let view = CameraView::new(
+                    &self.device,
+                    &self.camera.bind_group_layout,
+                    view.camera,
+                    view.projection,
+                );
+                (name, view)
+            })
+            .collect();
+        for (id, window, camera) in windows {
+            let Some((instance, adapter)) = &self.gpu else {
+                break;
+            };
+            match SecondaryWindow::new(
+                window,
+                camera,
+                (instance, adapter),
+                &self.device,
+                self.config.format,
+                self.anti_aliasing.sample_count(),
+                self.depth.format,
+            ) {
+                Ok(window) => {
+                    self.windows.insert(id, window);
+                }
+                Err(e) => log::error!("Unable to restore window {id:?}: {e}"),
+            }
+        }
+        // Rebuilds the pipelines and targets for the current settings
+        self.configure_anti_aliasing(self.anti_aliasing);
+        self.write_screen_size();
+        Ok(())
+    }
+
+    /**
+     * Renders `flows` into the target of a headless context and reads the image back.
+     *
+     * The renders are batched and drawn like a frame of the event loop, after the camera
+     * uniforms are brought up to date. No hooks are called, so write instance buffers and
+     * update flows beforehand. Fails with [`Error::Unsupported`] on contexts with a window.
+     */
+    pub async fn render_once<S, E: Send>(
+        &mut self,
+        flows: &[Box<dyn GraphicsFlow<S, E>>],
+    ) -> Result<RgbaImage, Error> {
+        let Some(target) = self.offscreen.clone() else {
+            return Err(Error::Unsupported {
+                feature: "render_once on a context with a window".to_string(),
+            });
+        };
+        let view = target.create_view(&wgpu::TextureViewDescriptor::default());
+        self.update_camera_uniforms(0.0);
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Headless Render Encoder"),
+            });
+        let overdraw_readback = draw_frame(self, flows, &mut encoder, FrameTarget::Context(&view));
+
+        let (width, height) = (target.width(), target.height());
+        let padded_bytes_per_row = padded_bytes_per_row(width);
+        let readback = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Headless Readback Buffer"),
+            size: u64::from(padded_bytes_per_row) * u64::from(height),
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        encoder.copy_texture_to_buffer(
+            wgpu::TexelCopyTextureInfo {
+                aspect: wgpu::TextureAspect::All,
+                texture: &target,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            wgpu::TexelCopyBufferInfo {
+                buffer: &readback,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_bytes_per_row),
+                    rows_per_image: Some(height),
+                },
+            },
+            target.size(),
+        );
+        self.queue.submit(std::iter::once(encoder.finish()));
+        if overdraw_readback && let Some(resources) = &self.overdraw {
+            resources.map_readback();
+        }
+
+        let buffer_slice = readback.slice(..);
+        // NOTE: map first, then poll, then await. Otherwise the future never resolves.
+        let (tx, rx) = futures_intrusive::channel::shared::oneshot_channel();
+        buffer_slice.map_async(wgpu::MapMode::Read, move |result| {
+            tx.send(result).unwrap();
+        });
+        #[cfg(target_arch = "wasm32")]
+        let polled = self.device.poll(wgpu::PollType::Poll);
+        #[cfg(not(target_arch = "wasm32"))]
+        let polled = self.device.poll(wgpu::PollType::Wait {
+            submission_index: None,
+            timeout: None,
+        });
+        polled.map_err(|e| Error::Gpu(e.into()))?;
+        match rx.receive().await {
+            Some(result) => result.map_err(|e| Error::Gpu(e.into()))?,
+            None => return Err(Error::Gpu("headless readback was cancelled".into())),
+        }
+
+        let row_bytes = width as usize * 4;
+        let pixels = buffer_slice
+            .get_mapped_range()
+            .chunks(padded_bytes_per_row as usize)
+            .take(height as usize)
+            .flat_map(|row| &row[..row_bytes])
+            .copied()
+            .collect();
+        Ok(RgbaImage::from_raw(width, height, pixels).expect("readback matches the target size"))
+    }
+
+    /// Everything but the window, surface and offscreen target, which are left empty.
+    fn with_device(
+        device: wgpu::Device,
+        queue: wgpu::Queue,
+        config: wgpu::SurfaceConfiguration,
+    ) -> Self {
         // right/left, height, forward/backward - y axis rotation (turn head left/right) - x axis rotation (head up/down)
         let camera = camera::Camera::new((0.0, 30.0, 20.0), cgmath::Deg(-90.0), cgmath::Deg(-60.0));
         let projection =
-            camera::Projection::new(config.width, config.height, cgmath::Deg(45.0), 0.1, 500.0)?;
+            camera::Projection::new(config.width, config.height, cgmath::Deg(45.0), 0.1, 500.0)
+                .expect("default projection is valid");
         let camera_controller = camera::CameraController::new(10.0, 0.4);
 
         let mut camera_uniform = CameraUniform::new();
@@ -221,25 +1371,12 @@ impl Context {
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
         });
 
-        let camera_bind_group_layout =
-            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                entries: &[wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                }],
-                label: Some("camera_bind_group_layout"),
-            });
+        let layouts = Layouts::new(&device);
 
-        let bind_group_layout = camera_bind_group_layout.clone();
+        let bind_group_layout = layouts.camera.clone();
 
         let camera_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &camera_bind_group_layout,
+            layout: &layouts.camera,
             entries: &[wgpu::BindGroupEntry {
                 binding: 0,
                 resource: camera_buffer.as_entire_binding(),
@@ -249,7 +1386,14 @@ impl Context {
 
         let camera = CameraResources {
             camera,
-            controller: camera_controller,
+            controller: Box::new(camera_controller),
+            follow: None,
+            collision: None,
+            tween: None,
+            tween_finished: false,
+            path: None,
+            shakes: Vec::new(),
+            shake_count: 0,
             uniform: camera_uniform,
             buffer: camera_buffer,
             bind_group: camera_bind_group,
@@ -258,12 +1402,14 @@ impl Context {
 
         let anti_aliasing = AntiAliasing::None;
         let sample_count = anti_aliasing.sample_count();
+        let depth = DepthConfig::default();
 
         let depth_texture = texture::Texture::create_depth_texture(
             &device,
             [config.width, config.height],
             "depth_texture",
             sample_count,
+            depth.format,
         );
 
         let msaa_view = if sample_count > 1 {
@@ -284,7 +1430,7 @@ impl Context {
             _padding2: 0,
         };
 
-        let light = LightResources::new(light_uniform, None, &device);
+        let light = LightResources::with_layout(light_uniform, None, &device, &layouts.light);
 
         let clear_colour = wgpu::Color {
             r: 0.1,
@@ -310,62 +1456,18 @@ impl Context {
         };
 
         // Generate pipelines once so they can be reused without being initialized every frame
-        let light_pipeline = mk_light_pipeline(
-            &device,
-            &config,
-            &light.bind_group_layout,
-            &camera.bind_group_layout,
-            sample_count,
-        );
-        let basic_pipeline = mk_basic_pipeline(
-            &device,
-            &config,
-            wgpu::FrontFace::Ccw,
-            &light.bind_group_layout,
-            &camera.bind_group_layout,
-            sample_count,
-        );
-        let basic_cw_pipeline = mk_basic_pipeline(
-            &device,
-            &config,
-            wgpu::FrontFace::Cw,
-            &light.bind_group_layout,
-            &camera.bind_group_layout,
-            sample_count,
-        );
-        let pick_pipeline = mk_pick_pipeline(&device, &camera.bind_group_layout);
-        let gui_pipeline = mk_gui_pipeline(
+        let pipelines = Pipelines::new(
             &device,
             &config,
+            config.format,
+            &layouts,
             &screen_size.bind_group_layout,
             sample_count,
+            depth,
         );
-        let gui_pick_pipeline = mk_gui_pick_pipeline(&device, &screen_size.bind_group_layout);
-        let transparent_pipeline = mk_transparent_pipeline(
-            &device,
-            &config,
-            &light.bind_group_layout,
-            &camera.bind_group_layout,
-            sample_count,
-        );
-        let terrain_pipeline = mk_terrain_pipeline(
-            &device,
-            &config,
-            &camera.bind_group_layout,
-            &light.bind_group_layout,
-            sample_count,
-            8
-        );
-        let pipelines = Pipelines {
-            basic: basic_pipeline,
-            basic_cw: basic_cw_pipeline,
-            gui: gui_pipeline,
-            flat_pick: gui_pick_pipeline,
-            light: light_pipeline,
-            pick: pick_pipeline,
-            transparent: transparent_pipeline,
-            terrain: terrain_pipeline,
-        };
+        let highlight = HighlightResources::new(&device);
+        let overlay = OverlayResources::new(&device, &layouts.overlay);
+        let assets = AssetCache::new(device.clone(), queue.clone(), layouts.clone());
         let mouse = MouseState {
             coords: (0.0, 0.0).into(),
             prev_coords: (0.0, 0.0).into(),
@@ -373,25 +1475,116 @@ impl Context {
             selection: None,
         };
         let tick_duration_millis = 500;
+        let gpu_lost = watch_device(&device);
 
-        Ok(Self {
+        Self {
             anti_aliasing,
             camera,
             clear_colour,
             config,
+            present_modes: Vec::new(),
             depth_texture,
             device,
+            layouts,
             light,
             mouse,
+            input: InputState::default(),
             msaa_view,
             pipelines,
             projection,
             queue,
             screen_size,
-            surface,
+            surface: None,
+            offscreen: None,
             tick_duration_millis,
-            window,
-        })
+            render_strategy: RenderStrategy::default(),
+            tooltip: TooltipState::default(),
+            selection_highlight: None,
+            hover_highlight: None,
+            hover: HoverPicking::default(),
+            highlights: Vec::new(),
+            highlight,
+            assets,
+            actions: {
+                let mut actions = ActionMap::default();
+                actions.bind(ActionMap::PAUSE, KeyCode::Escape);
+                actions
+            },
+            pause_overlay: wgpu::Color {
+                r: 0.0,
+                g: 0.0,
+                b: 0.0,
+                a: 0.5,
+            },
+            pause_blur: 0.0,
+            overlay,
+            input_settings: InputSettings::default(),
+            unfocused: UnfocusedSettings::default(),
+            pick_buttons: HashSet::from([MouseButton::Left]),
+            spike_threshold: 3.0,
+            camera_controller_enabled: true,
+            stream_budget: Duration::from_millis(2),
+            auto_light_rotation_enabled: true,
+            keyboard_focus: None,
+            paused: false,
+            window_focused: true,
+            suspended: false,
+            recovering_surface: false,
+            flow_activity: HashMap::new(),
+            exit_requested: Cell::new(false),
+            input_captured: Cell::new(false),
+            #[cfg(feature = "record")]
+            session: None,
+            debug_render: DebugRender::None,
+            cameras: HashMap::new(),
+            active_camera: None,
+            stats: RenderStats::default(),
+            profiler: FrameProfiler::default(),
+            timing: Timing::default(),
+            loading: None,
+            overdraw: None,
+            custom_pipelines: HashMap::new(),
+            scene_depth: BTreeMap::new(),
+            tonemapping: None,
+            hdr: BTreeMap::new(),
+            depth,
+            ticks: 0,
+            tick_paused: false,
+            tick_alpha: 0.0,
+            time_scale: 1.0,
+            fixed_step: DEFAULT_FIXED_STEP,
+            fixed_alpha: 0.0,
+            max_fps: None,
+            requested_size: None,
+            ui_relayout: false,
+            ui_scale: 1.0,
+            scale_factor: 1.0,
+            min_text_size: 0.0,
+            cursor_grab: CursorGrabMode::None,
+            visibility: HashMap::new(),
+            configures: ConfigureQueue::default(),
+            configure_futures: Vec::new(),
+            spawned_flows: Vec::new(),
+            snapshots: Vec::new(),
+            removed_flows: RefCell::new(Vec::new()),
+            streams: RefCell::new(Streams::default()),
+            flow_order: RefCell::new(None),
+            pick_targets: RefCell::new(None),
+            pick_materials: RefCell::new(PickMaterials::default()),
+            deferred_events: RefCell::new(Vec::new()),
+            event_cloner: None,
+            dead_letters: None,
+            gpu: None,
+            gpu_lost,
+            windows: BTreeMap::new(),
+            pending_windows: Vec::new(),
+            next_window: WindowId::MAIN.0,
+            #[cfg(feature = "integration-tests")]
+            scripted_clicks: Vec::new(),
+            #[cfg(feature = "integration-tests")]
+            forced_surface_loss: false,
+            window: None,
+        }
     }
 
     /// Switch anti-aliasing mode at runtime, rebuilding all affected GPU state.
@@ -404,6 +1597,7 @@ impl Context {
             [self.config.width, self.config.height],
             "depth_texture",
             sample_count,
+            self.depth.format,
         );
 
         self.msaa_view = if sample_count > 1 {
@@ -416,54 +1610,1346 @@ impl Context {
             None
         };
 
-        self.pipelines = Pipelines {
-            light: mk_light_pipeline(
-                &self.device,
-                &self.config,
-                &self.light.bind_group_layout,
-                &self.camera.bind_group_layout,
-                sample_count,
-            ),
-            basic: mk_basic_pipeline(
-                &self.device,
-                &self.config,
-                wgpu::FrontFace::Ccw,
-                &self.light.bind_group_layout,
-                &self.camera.bind_group_layout,
-                sample_count,
-            ),
-            basic_cw: mk_basic_pipeline(
-                &self.device,
-                &self.config,
-                wgpu::FrontFace::Cw,
-                &self.light.bind_group_layout,
-                &self.camera.bind_group_layout,
-                sample_count,
-            ),
-            pick: mk_pick_pipeline(&self.device, &self.camera.bind_group_layout),
-            gui: mk_gui_pipeline(
-                &self.device,
-                &self.config,
-                &self.screen_size.bind_group_layout,
-                sample_count,
-            ),
-            transparent: mk_transparent_pipeline(
+        self.pipelines = Pipelines::new(
+            &self.device,
+            &self.config,
+            self.color_format(),
+            &self.layouts,
+            &self.screen_size.bind_group_layout,
+            sample_count,
+            self.depth,
+        );
+        for window in self.windows.values_mut() {
+            window.rebuild_targets(&self.device, sample_count, self.depth.format);
+        }
+    }
+
+    /**
+     * Renders the scene into an HDR target tonemapped with `tonemapping` to the surface, or
+     * directly to the surface with `None`, the default.
+     *
+     * The GUI is drawn in the same passes and tonemapped as well, text labels pick the format
+     * up when they're created. Like
+     * [`Context::configure_anti_aliasing`] this rebuilds the pipelines, so pipelines of
+     * `Render::Custom` draws have to be registered again for [`Context::color_format`].
+     */
+    pub fn set_hdr(&mut self, tonemapping: Option<Tonemapping>) {
+        if self.tonemapping.is_some() == tonemapping.is_some() {
+            self.tonemapping = tonemapping;
+            return;
+        }
+        self.tonemapping = tonemapping;
+        self.hdr.clear();
+        self.overdraw = None;
+        self.configure_anti_aliasing(self.anti_aliasing);
+    }
+
+    pub fn tonemapping(&self) -> Option<Tonemapping> {
+        self.tonemapping
+    }
+
+    /// Colour format the scene pipelines draw into: [`HDR_FORMAT`] with
+    /// [HDR](Context::set_hdr), otherwise the surface format.
+    pub fn color_format(&self) -> wgpu::TextureFormat {
+        match self.tonemapping {
+            Some(_) => HDR_FORMAT,
+            None => self.config.format,
+        }
+    }
+
+    pub fn depth(&self) -> DepthConfig {
+        self.depth
+    }
+
+    /**
+     * Switches the depth buffers of all passes to `depth`, e.g. reversed Z against
+     * z-fighting of distant terrain. Like [`Context::configure_anti_aliasing`] this rebuilds
+     * the pipelines, so `Render::Custom` pipelines have to be registered again with a depth
+     * stencil state matching [`Context::depth`].
+     *
+     * Fails with [`Error::Unsupported`] for formats without a depth aspect or with a stencil
+     * aspect, the scene depth copy samples the depth directly.
+     */
+    pub fn configure_depth(&mut self, depth: DepthConfig) -> Result<(), Error> {
+        if !depth.format.has_depth_aspect() || depth.format.has_stencil_aspect() {
+            return Err(Error::Unsupported {
+                feature: format!("{:?} as the depth format", depth.format),
+            });
+        }
+        if self.depth == depth {
+            return Ok(());
+        }
+        self.depth = depth;
+        self.sync_reversed_z();
+        // The heatmap pipeline tests against the depth attachment
+        self.overdraw = None;
+        self.configure_anti_aliasing(self.anti_aliasing);
+        Ok(())
+    }
+
+    fn sync_reversed_z(&mut self) {
+        let reversed_z = self.depth.reversed_z;
+        self.projection.set_reversed_z(reversed_z);
+        for view in self.cameras.values_mut() {
+            view.projection.set_reversed_z(reversed_z);
+        }
+    }
+
+    /// Creates a pipeline for `Render::Custom` draws under `label`, see [`Context::pipeline`].
+    ///
+    /// `wgsl` is checked against the bind groups the engine sets for `groups` first, so a
+    /// shader expecting other resources fails here with [`Error::IncompatiblePipeline`]
+    /// instead of with a validation error while drawing. `mk` receives the shader module and a
+    /// pipeline layout of those groups. The pipeline has to be registered again after the
+    /// sample count changes, e.g. through [`Context::configure_anti_aliasing`].
+    pub fn register_pipeline(
+        &mut self,
+        label: &str,
+        wgsl: &str,
+        groups: EngineGroups,
+        mk: impl FnOnce(&Context, &wgpu::ShaderModule, &wgpu::PipelineLayout) -> wgpu::RenderPipeline,
+    ) -> Result<&wgpu::RenderPipeline, Error> {
+        compat::check_shader(label, wgsl, groups)?;
+        let shader = self
+            .device
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some(label),
+                source: wgpu::ShaderSource::Wgsl(wgsl.into()),
+            });
+        let bind_group_layouts = groups
+            .groups()
+            .iter()
+            .map(|group| {
+                self.device
+                    .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                        label: Some(group.name),
+                        entries: group.entries,
+                    })
+            })
+            .collect::<Vec<_>>();
+        let layout = self
+            .device
+            .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some(label),
+                bind_group_layouts: &bind_group_layouts.iter().map(Some).collect::<Vec<_>>(),
+                ..Default::default()
+            });
+        let pipeline = mk(self, &shader, &layout);
+        self.custom_pipelines.insert(label.to_string(), pipeline);
+        Ok(&self.custom_pipelines[label])
+    }
+
+    /// The pipeline registered under `label` through [`Context::register_pipeline`].
+    pub fn pipeline(&self, label: &str) -> Option<&wgpu::RenderPipeline> {
+        self.custom_pipelines.get(label)
+    }
+
+    /// Presents frames with `mode` from the next frame on, e.g. `Mailbox` or `Immediate` to
+    /// render faster than the display refreshes. Modes the surface doesn't support fall back
+    /// to `Fifo`, which is always available. Returns the mode in use.
+    pub fn set_present_mode(&mut self, mode: wgpu::PresentMode) -> wgpu::PresentMode {
+        self.config.present_mode = supported_present_mode(mode, &self.present_modes);
+        self.reconfigure_surface();
+        self.config.present_mode
+    }
+
+    /// Frames the GPU may queue up ahead of the display, see
+    /// [`wgpu::SurfaceConfiguration::desired_maximum_frame_latency`]. Lower values reduce input
+    /// latency at the cost of throughput. Defaults to 2.
+    pub fn set_frame_latency(&mut self, frames: u32) {
+        self.config.desired_maximum_frame_latency = frames;
+        self.reconfigure_surface();
+    }
+
+    fn reconfigure_surface(&self) {
+        // Before the first resize (or while minimized) the surface is configured on resize
+        if let Some(surface) = &self.surface
+            && self.config.width > 0
+            && self.config.height > 0
+        {
+            surface.configure(&self.device, &self.config);
+        }
+    }
+
+    /// Switches the window to fullscreen, or back to windowed for `None`. The surface,
+    /// depth texture and projection follow through the regular resize once the window
+    /// reports its new size.
+    pub fn set_fullscreen(&self, mode: Option<FullscreenMode>) {
+        let Some(window) = &self.window else {
+            return;
+        };
+        let fullscreen = mode.map(|mode| {
+            let monitor = window.current_monitor();
+            let video_mode = monitor
+                .as_ref()
+                .filter(|_| mode == FullscreenMode::Exclusive)
+                .and_then(|monitor| {
+                    monitor.video_modes().max_by_key(|video_mode| {
+                        let size = video_mode.size();
+                        (
+                            u64::from(size.width) * u64::from(size.height),
+                            video_mode.refresh_rate_millihertz(),
+                        )
+                    })
+                });
+            match video_mode {
+                Some(video_mode) => Fullscreen::Exclusive(video_mode),
+                None => Fullscreen::Borderless(monitor),
+            }
+        });
+        window.set_fullscreen(fullscreen);
+    }
+
+    pub fn is_fullscreen(&self) -> bool {
+        self.window
+            .as_ref()
+            .is_some_and(|window| window.fullscreen().is_some())
+    }
+
+    /// Requests an inner size of `width` x `height` physical pixels. The platform may
+    /// ignore it or pick another size, e.g. for a maximized or tiled window. Headless
+    /// contexts resize their target right away.
+    pub fn set_window_size(&mut self, width: u32, height: u32) {
+        match &self.window {
+            // Platforms resizing synchronously don't always send `Resized`
+            Some(window) => {
+                self.requested_size = window.request_inner_size(PhysicalSize::new(width, height))
+            }
+            None => self.resize_targets(width.max(1), height.max(1)),
+        }
+    }
+
+    pub fn set_resizable(&self, resizable: bool) {
+        if let Some(window) = &self.window {
+            window.set_resizable(resizable);
+        }
+    }
+
+    /// Resizes the surface (or offscreen target), depth and MSAA textures, projection and
+    /// screen size uniform to `width` x `height`, both non-zero.
+    pub(crate) fn resize_targets(&mut self, width: u32, height: u32) {
+        self.config.width = width;
+        self.config.height = height;
+        self.projection.resize(width, height);
+        if let Some(surface) = &self.surface {
+            surface.configure(&self.device, &self.config);
+        }
+        if self.offscreen.is_some() {
+            self.offscreen = Some(mk_offscreen_target(&self.device, &self.config));
+        }
+        let sample_count = self.anti_aliasing.sample_count();
+        self.depth_texture = texture::Texture::create_depth_texture(
+            &self.device,
+            [width, height],
+            "depth_texture",
+            sample_count,
+            self.depth.format,
+        );
+        self.msaa_view = if sample_count > 1 {
+            Some(texture::Texture::create_msaa_texture(
                 &self.device,
                 &self.config,
-                &self.light.bind_group_layout,
-                &self.camera.bind_group_layout,
                 sample_count,
+            ))
+        } else {
+            None
+        };
+        self.write_screen_size();
+    }
+
+    // GUI pipelines map UI pixels to NDC, so a larger UI scale shrinks the screen they see
+    fn write_screen_size(&self) {
+        let scale = self.ui_pixel_scale();
+        let screen_size_data = [
+            self.config.width as f32 / scale,
+            self.config.height as f32 / scale,
+            0.0f32,
+            0.0f32,
+        ];
+        profiling::write_buffer(
+            &self.queue,
+            &self.screen_size.buffer,
+            0,
+            bytemuck::cast_slice(&screen_size_data),
+        );
+    }
+
+    /// Scales all GUI drawn in pixels, including text and picking, by `scale` on top of the
+    /// [OS scale factor](Context::scale_factor), e.g. `1.5` for larger menus. The scale is
+    /// clamped to
+    /// [`UI_SCALE_RANGE`], which is returned. UI elements lay out again through `on_resize`
+    /// before the next frame.
+    pub fn set_ui_scale(&mut self, scale: f32) -> f32 {
+        self.ui_scale = clamp_ui_scale(scale);
+        self.write_screen_size();
+        self.ui_relayout = true;
+        self.ui_scale
+    }
+
+    pub fn ui_scale(&self) -> f32 {
+        self.ui_scale
+    }
+
+    /// Physical pixels of the main window per logical pixel, e.g. `2.0` on a display scaled to
+    /// 200%. Always `1.0` for headless contexts.
+    pub fn scale_factor(&self) -> f64 {
+        self.scale_factor
+    }
+
+    /// Applies the scale factor of a `ScaleFactorChanged` event, the GUI lays out again
+    /// through `on_resize` before the next frame.
+    pub(crate) fn set_scale_factor(&mut self, scale_factor: f64) {
+        if scale_factor == self.scale_factor || !scale_factor.is_finite() || scale_factor <= 0.0 {
+            return;
+        }
+        self.scale_factor = scale_factor;
+        self.write_screen_size();
+        self.ui_relayout = true;
+    }
+
+    /// Physical pixels per UI pixel, the [scale factor](Context::scale_factor) times the
+    /// [UI scale](Context::set_ui_scale). GUI vertices are given in UI pixels.
+    pub fn ui_pixel_scale(&self) -> f32 {
+        self.scale_factor as f32 * self.ui_scale
+    }
+
+    /// Window pixels, like mouse coordinates and clicks, of a `logical` position.
+    pub fn to_physical(&self, logical: LogicalPosition<f64>) -> PhysicalPosition<f64> {
+        logical.to_physical(self.scale_factor)
+    }
+
+    /// Logical position of `physical` window pixels, e.g. of the mouse.
+    pub fn to_logical(&self, physical: PhysicalPosition<f64>) -> LogicalPosition<f64> {
+        physical.to_logical(self.scale_factor)
+    }
+
+    /// Size of the screen in UI pixels, the root bounds to lay out UI elements in.
+    pub fn ui_size(&self) -> (u32, u32) {
+        let scale = self.ui_pixel_scale();
+        (
+            (self.config.width as f32 / scale).round() as u32,
+            (self.config.height as f32 / scale).round() as u32,
+        )
+    }
+
+    /// Mouse position in UI pixels, to hit-test rects laid out in [`Context::ui_size`].
+    pub fn ui_mouse(&self) -> PhysicalPosition<f64> {
+        let scale = f64::from(self.ui_pixel_scale());
+        PhysicalPosition::new(self.mouse.coords.x / scale, self.mouse.coords.y / scale)
+    }
+
+    /// Factor to draw text of `font_size` UI pixels with, the UI pixel scale raised as far
+    /// as needed to reach [`Context::min_text_size`].
+    pub fn ui_text_scale(&self, font_size: f32) -> f32 {
+        text_scale(self.ui_pixel_scale(), font_size, self.min_text_size)
+    }
+
+    /// Locks (falling back to confining where locking is unsupported) or confines the
+    /// cursor to the window, e.g. for first-person controls. [`CursorGrabMode::None`]
+    /// releases it again. The grab is released while the window is unfocused and
+    /// re-applied when it regains focus.
+    pub fn set_cursor_grab(&mut self, mode: CursorGrabMode) -> Result<(), Error> {
+        self.cursor_grab = self.grab_cursor(mode)?;
+        Ok(())
+    }
+
+    /// Grabs the cursor without remembering the mode, returning the applied one.
+    fn grab_cursor(&self, mode: CursorGrabMode) -> Result<CursorGrabMode, Error> {
+        let Some(window) = &self.window else {
+            return Err(Error::Unsupported {
+                feature: "cursor grab without a window".to_string(),
+            });
+        };
+        let result = match mode {
+            CursorGrabMode::Locked => window
+                .set_cursor_grab(CursorGrabMode::Locked)
+                .map(|()| CursorGrabMode::Locked)
+                .or_else(|_| {
+                    window
+                        .set_cursor_grab(CursorGrabMode::Confined)
+                        .map(|()| CursorGrabMode::Confined)
+                }),
+            mode => window.set_cursor_grab(mode).map(|()| mode),
+        };
+        result.map_err(|e| match e {
+            ExternalError::NotSupported(_) => Error::Unsupported {
+                feature: format!("cursor grab mode {mode:?}"),
+            },
+            e => Error::Window(e.into()),
+        })
+    }
+
+    /// Grab mode requested through [`Context::set_cursor_grab`], even while the window is
+    /// unfocused.
+    pub fn cursor_grab(&self) -> CursorGrabMode {
+        self.cursor_grab
+    }
+
+    /// Releases the grab while the window is unfocused and re-applies it on focus.
+    pub(crate) fn focus_changed(&self, focused: bool) {
+        if self.cursor_grab == CursorGrabMode::None {
+            return;
+        }
+        let mode = if focused {
+            self.cursor_grab
+        } else {
+            CursorGrabMode::None
+        };
+        if let Err(e) = self.grab_cursor(mode) {
+            log::warn!("Unable to update the cursor grab on focus change: {e}");
+        }
+    }
+
+    /// Hides the cursor while it is over the window.
+    pub fn set_cursor_visible(&self, visible: bool) {
+        if let Some(window) = &self.window {
+            window.set_cursor_visible(visible);
+        }
+    }
+
+    pub fn set_cursor_icon(&self, icon: CursorIcon) {
+        if let Some(window) = &self.window {
+            window.set_cursor(icon);
+        }
+    }
+
+    /// Marks `id` as hovered so [`Context::tooltip_anchor`] follows `target`. Typically set
+    /// from `on_window_events` through `Out::Configure`.
+    pub fn set_hovered(&mut self, id: PickId, target: HoverTarget) {
+        self.tooltip.hovered = Some((id, target));
+    }
+
+    pub fn clear_hovered(&mut self) {
+        self.tooltip.hovered = None;
+    }
+
+    /// Picks the object under the cursor at most every `interval` while the cursor moves and
+    /// calls `on_hover` of the flows owning it. Clicks reuse the latest pick while the cursor
+    /// rests, so they don't render the pick buffer again. Off by default.
+    pub fn enable_hover_picking(&mut self, interval: Duration) {
+        self.hover.enable(interval);
+    }
+
+    /// Stops hover picking, the owners of the hovered object get `on_hover(None)` once more.
+    pub fn disable_hover_picking(&mut self) {
+        self.hover.disable();
+    }
+
+    pub fn hover_picking(&self) -> bool {
+        self.hover.is_enabled()
+    }
+
+    /// Tints instances of an opaque draw until removed again, e.g. the instance under the
+    /// cursor. Works with static instance buffers as the tint is applied in the shader.
+    pub fn highlight(&mut self, highlight: Highlight) {
+        if !self.highlights.contains(&highlight) {
+            self.highlights.push(highlight);
+        }
+    }
+
+    /// Removes the highlights of `instance` of the draw `id`, or of the whole draw for `None`.
+    pub fn unhighlight(&mut self, id: PickId, instance: Option<u32>) {
+        self.highlights.retain(|highlight| {
+            highlight.id != id || instance.is_some_and(|i| highlight.instance != Some(i))
+        });
+    }
+
+    pub fn clear_highlights(&mut self) {
+        self.highlights.clear();
+    }
+
+    /// Highlights drawn this frame: those added through [`Context::highlight`] followed by
+    /// the selection and hover tints.
+    pub fn active_highlights(&self) -> Vec<Highlight> {
+        let selection = self
+            .mouse
+            .selection
+            .zip(self.selection_highlight)
+            .map(|(id, color)| Highlight::group(id, color));
+        let hover = self
+            .tooltip
+            .hovered
+            .zip(self.hover_highlight)
+            .map(|((id, _), color)| Highlight::group(id, color));
+        self.highlights
+            .iter()
+            .copied()
+            .chain(selection)
+            .chain(hover)
+            .collect()
+    }
+
+    /// Screen position and id of the hovered object for placing a tooltip, `None` once
+    /// nothing is hovered.
+    pub fn tooltip_anchor(&self) -> Option<TooltipAnchor> {
+        self.tooltip.anchor()
+    }
+
+    /// The previous tooltip anchor while it fades out after the hover ended, e.g. to draw
+    /// the tooltip with its decreasing `opacity`. `None` while something is hovered.
+    pub fn fading_tooltip(&self) -> Option<TooltipAnchor> {
+        self.tooltip.fading()
+    }
+
+    pub(crate) fn update_tooltip_anchor(&mut self, dt: Duration) {
+        let (width, height) = (self.config.width as f32, self.config.height as f32);
+        let projected = self.tooltip.hovered.and_then(|(_, target)| match target {
+            HoverTarget::World(point) => project_to_screen(
+                self.projection.calc_matrix() * self.camera.camera.calc_matrix(),
+                point,
+                width,
+                height,
+                self.projection.reversed_z(),
             ),
-            terrain: mk_terrain_pipeline(
+            HoverTarget::Screen(position) => Some(position),
+        });
+        self.tooltip
+            .update(projected, width as f64, height as f64, dt);
+    }
+
+    /**
+     * Gives the flow `flow_id` exclusive keyboard input, e.g. while a text box is open.
+     *
+     * While focused, `WindowEvent::KeyboardInput` and `WindowEvent::Ime` only reach that
+     * flow's `on_window_events` and `on_text_input`, and the camera controller ignores key
+     * presses. Flows learn their id from [`InitContext::flow_id`] and are notified through
+     * `on_focus_changed`.
+     */
+    pub fn request_keyboard_focus(&mut self, flow_id: FlowId) {
+        self.keyboard_focus = Some(flow_id);
+    }
+
+    /// Returns keyboard input to all flows and the camera controller.
+    pub fn release_focus(&mut self) {
+        self.keyboard_focus = None;
+    }
+
+    pub fn keyboard_focus(&self) -> Option<FlowId> {
+        self.keyboard_focus
+    }
+
+    /// Whether a key of `action` in [`Context::actions`] went down since the previous frame.
+    pub fn was_action_pressed(&self, action: &str) -> bool {
+        self.actions
+            .keys(action)
+            .iter()
+            .any(|&key| self.input.was_key_pressed(key))
+    }
+
+    /// Whether a key of `action` in [`Context::actions`] is held down.
+    pub fn is_action_down(&self, action: &str) -> bool {
+        self.actions
+            .keys(action)
+            .iter()
+            .any(|&key| self.input.is_key_down(key))
+    }
+
+    pub fn has_keyboard_focus(&self, flow_id: FlowId) -> bool {
+        self.keyboard_focus == Some(flow_id)
+    }
+
+    /**
+     * Pauses or resumes the app.
+     *
+     * While paused, flows that don't return `true` from `runs_while_paused` receive no input,
+     * clicks or ticks and get `on_update` with a zero `dt`. The camera controller ignores
+     * input and [`Context::pause_overlay`] and [`Context::pause_blur`] cover the 3D scene below
+     * the GUI. Keys of [`ActionMap::PAUSE`] in [`Context::actions`] toggle it. All flows are
+     * notified through `on_pause_changed`, e.g. to show or hide a pause menu.
+     */
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    /// Decides which hooks the flow `id` receives without removing it, e.g.
+    /// [`FlowActivity::FROZEN`] keeps drawing the paused world behind a pause menu flow while
+    /// the world's `on_update`, ticks and input stop. Unlike [`Context::set_paused`] this
+    /// applies to single flows.
+    pub fn set_flow_active(&mut self, id: FlowId, activity: FlowActivity) {
+        if activity == FlowActivity::ACTIVE {
+            self.flow_activity.remove(&id);
+        } else {
+            self.flow_activity.insert(id, activity);
+        }
+    }
+
+    pub fn flow_activity(&self, id: FlowId) -> FlowActivity {
+        self.flow_activity.get(&id).copied().unwrap_or_default()
+    }
+
+    /// The camera pose, clear colour and selection, e.g. to save them with the state in
+    /// `on_exit`. See [`crate::persist`].
+    pub fn engine_snapshot(&self) -> EngineSnapshot {
+        EngineSnapshot {
+            camera: self.camera.camera.pose(),
+            clear_colour: self.clear_colour,
+            selection: self.mouse.selection,
+        }
+    }
+
+    /// Shows the view of `snapshot` again, the camera's controller drops its input.
+    pub fn restore(&mut self, snapshot: &EngineSnapshot) {
+        self.camera.set_pose(&snapshot.camera);
+        self.clear_colour = snapshot.clear_colour;
+        self.mouse.selection = snapshot.selection;
+    }
+
+    /// Closes the app once the current events and frame are handled, e.g. for a "Quit" menu
+    /// item. Every flow gets `on_exit` first to persist its state. Works from any hook, flows
+    /// returning an [`Out`](crate::flow::Out) can return `Out::Exit` instead.
+    ///
+    /// On the web the page keeps running: the app stops rendering and releases the GPU.
+    pub fn request_exit(&self) {
+        self.exit_requested.set(true);
+    }
+
+    pub fn exit_requested(&self) -> bool {
+        self.exit_requested.get()
+    }
+
+    /// Keeps the event passed to `on_window_events` or `on_device_events` from the flows after
+    /// this one, the camera controller and picking, e.g. for a click on a button drawn over the
+    /// scene. Flows returning an [`Out`](crate::flow::Out) can return `Out::Capture` instead.
+    /// Ignored by other hooks. Key releases still reach the camera controller so keys don't get
+    /// stuck.
+    pub fn capture_input(&self) {
+        self.input_captured.set(true);
+    }
+
+    pub(crate) fn input_captured(&self) -> bool {
+        self.input_captured.get()
+    }
+
+    pub(crate) fn take_input_captured(&self) -> bool {
+        self.input_captured.take()
+    }
+
+    /// Removes the flow `id` once the current event is handled, e.g. a main menu removing
+    /// itself after starting the game. The flow gets `on_exit` first. Flows learn their id
+    /// from [`InitContext::flow_id`], those returning an [`Out`](crate::flow::Out) can return
+    /// `Out::RemoveFlow` instead.
+    pub fn remove_flow(&self, id: FlowId) {
+        self.removed_flows.borrow_mut().push(id);
+    }
+
+    pub(crate) fn take_removed_flows(&self) -> Vec<FlowId> {
+        self.removed_flows.take()
+    }
+
+    /// Reorders the flows in `order` once the current event is handled, e.g. `[world, gui]`
+    /// so the GUI flow is batched for rendering after the world. The listed flows swap the
+    /// places they take in the dispatch order, each taking the priority of its new place,
+    /// others and unknown ids stay as they are. Affects every hook and the order flows sharing
+    /// a clicked [`PickId`] get `on_click`.
+    pub fn set_flow_order(&self, order: &[FlowId]) {
+        *self.flow_order.borrow_mut() = Some(order.to_vec());
+    }
+
+    pub(crate) fn take_flow_order(&self) -> Option<Vec<FlowId>> {
+        self.flow_order.take()
+    }
+
+    /**
+     * Loads an asset during gameplay without stalling frames, see [`crate::stream`].
+     *
+     * `load` reads and decodes the files in the background, e.g. [`stream::load_model_obj`],
+     * and its [`Uploads`] run across frames within [`Context::stream_budget`]. Once they're
+     * done, or `load` failed, the event `finish` returns reaches the flows'
+     * `on_custom_events`. `E` has to be the app's event type, other events are dropped with
+     * an error.
+     */
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn spawn_load<T: Send + 'static, E: Send + 'static>(
+        &self,
+        load: impl Future<Output = anyhow::Result<Uploads<T>>> + Send + 'static,
+        finish: impl FnOnce(anyhow::Result<T>) -> E + Send + 'static,
+    ) -> LoadTicket {
+        self.streams.borrow_mut().spawn(stream::job(load, finish))
+    }
+
+    /// Loads an asset during gameplay without stalling frames, see [`crate::stream`].
+    #[cfg(target_arch = "wasm32")]
+    pub fn spawn_load<T: 'static, E: Send + 'static>(
+        &self,
+        load: impl Future<Output = anyhow::Result<Uploads<T>>> + 'static,
+        finish: impl FnOnce(anyhow::Result<T>) -> E + 'static,
+    ) -> LoadTicket {
+        self.streams.borrow_mut().spawn(stream::job(load, finish))
+    }
+
+    /// Stops the load `ticket`: its remaining uploads don't run and no event is sent. What
+    /// was uploaded so far is dropped.
+    pub fn cancel_load(&self, ticket: LoadTicket) {
+        self.streams.borrow_mut().cancel(ticket);
+    }
+
+    /// Whether the load `ticket` is still reading files or uploading.
+    pub fn is_loading(&self, ticket: LoadTicket) -> bool {
+        self.streams.borrow().is_loading(ticket)
+    }
+
+    /// Runs upload steps of streamed loads until [`Context::stream_budget`] is used up.
+    /// Returns the events of the loads that finished.
+    pub(crate) fn upload_streamed(&self) -> Vec<Box<dyn Any + Send>> {
+        let start = instant::Instant::now();
+        let mut finished = Vec::new();
+        // Steps may spawn or cancel loads, so the streams aren't borrowed while they run
+        loop {
+            let next = self.streams.borrow_mut().next_upload();
+            let Some((ticket, mut job)) = next else {
+                break;
+            };
+            if job.upload(self) {
+                finished.push(job.finish());
+            } else {
+                self.streams.borrow_mut().requeue(ticket, job);
+            }
+            if start.elapsed() >= self.stream_budget {
+                break;
+            }
+        }
+        finished
+    }
+
+    /// Passes `event` to the flows' `on_custom_events` once the current event is handled, like
+    /// `Out::Emit` for hooks that already return another [`Out`](crate::flow::Out). `E` has to
+    /// be the app's event type, other events are dropped with an error.
+    pub fn defer_event<E: Send + 'static>(&self, event: E) {
+        self.deferred_events.borrow_mut().push(Box::new(event));
+    }
+
+    pub(crate) fn take_deferred_events(&self) -> Vec<Box<dyn Any>> {
+        self.deferred_events.take()
+    }
+
+    /// Passes every custom event to all flows, each getting its own clone, instead of only
+    /// until a flow consumes it. `E` has to be the app's event type.
+    pub fn broadcast_events<E: Clone + 'static>(&mut self) {
+        self.event_cloner = Some(EventCloner::new::<E>());
+    }
+
+    /// Passes custom events on until a flow consumes them, the default.
+    pub fn consume_events(&mut self) {
+        self.event_cloner = None;
+    }
+
+    pub fn broadcasts_events(&self) -> bool {
+        self.event_cloner.is_some()
+    }
+
+    /// Passes custom events no flow consumed to `handler` instead of logging a warning, e.g.
+    /// to send them back to a background task through a channel. This includes `Out::EmitTo`
+    /// events whose flow was removed. Broadcast events are never unconsumed. `E` has to be
+    /// the app's event type.
+    pub fn set_dead_letter_handler<E: 'static>(&mut self, handler: impl FnMut(E) + 'static) {
+        self.dead_letters = Some(DeadLetterHandler::new(handler));
+    }
+
+    /// Logs unconsumed custom events again, the default.
+    pub fn clear_dead_letter_handler(&mut self) {
+        self.dead_letters = None;
+    }
+
+    /// Hands an unconsumed custom event to the dead letter handler.
+    pub(crate) fn dead_letter<E: 'static>(&mut self, event: E) {
+        let Some(DeadLetterHandler(handler)) = &mut self.dead_letters else {
+            log::warn!("Warning! Custom event was not consumed this cycle");
+            return;
+        };
+        if handler(Box::new(event)).is_some() {
+            log::error!("Dropped an unconsumed event that isn't of the dead letter handler's type");
+        }
+    }
+
+    /// Renders another frame in [`RenderStrategy::OnDemand`], e.g. after a flow changed its
+    /// state outside of input handling. Input, resizes and camera movement already do.
+    /// Continuous rendering ignores it. The frame waits for [`Context::set_max_fps`].
+    pub fn request_frame(&self) {
+        request_frame();
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Stops or resumes `on_tick` without pausing the app, e.g. while a cutscene plays. Time
+    /// doesn't accumulate while paused, so no ticks are made up on resume.
+    pub fn set_tick_paused(&mut self, paused: bool) {
+        self.tick_paused = paused;
+    }
+
+    pub fn is_tick_paused(&self) -> bool {
+        self.tick_paused
+    }
+
+    /// Runs `on_tick` every `rate`, in whole milliseconds like `tick_duration_millis`. Fails
+    /// for rates below a millisecond.
+    pub fn set_tick_rate(&mut self, rate: Duration) -> anyhow::Result<()> {
+        let millis = u64::try_from(rate.as_millis())?;
+        if millis == 0 {
+            anyhow::bail!("tick rate {rate:?} is below the minimum of 1ms");
+        }
+        self.tick_duration_millis = millis;
+        Ok(())
+    }
+
+    pub fn tick_rate(&self) -> Duration {
+        Duration::from_millis(self.tick_duration_millis)
+    }
+
+    /// Caps the frame rate of both render strategies, e.g. to save power on a static menu
+    /// screen or on battery. The event loop sleeps until the next frame is due instead of
+    /// rendering as fast as the present mode allows. `None` removes the cap, zero fails.
+    ///
+    /// Ticks keep their rate, a frame runs every tick that became due since the previous one.
+    /// On the web frames still follow `requestAnimationFrame`, early ones are skipped.
+    pub fn set_max_fps(&mut self, fps: Option<u32>) -> anyhow::Result<()> {
+        if fps == Some(0) {
+            anyhow::bail!("the frame rate cap must be at least 1 FPS");
+        }
+        self.max_fps = fps;
+        Ok(())
+    }
+
+    pub fn max_fps(&self) -> Option<u32> {
+        self.max_fps
+    }
+
+    /// Shortest time between two frames under [`Context::set_max_fps`], or under
+    /// [`UnfocusedSettings::max_fps`] while the window is unfocused if that's lower.
+    pub fn frame_interval(&self) -> Option<Duration> {
+        let unfocused = self.unfocused.max_fps.filter(|_| !self.window_focused);
+        [self.max_fps, unfocused]
+            .into_iter()
+            .flatten()
+            .filter(|&fps| fps > 0)
+            .min()
+            .map(|fps| Duration::from_secs(1) / fps)
+    }
+
+    /// Whether the main window has keyboard focus, flows learn about changes through
+    /// `GraphicsFlow::on_focus`.
+    pub fn is_window_focused(&self) -> bool {
+        self.window_focused
+    }
+
+    pub(crate) fn set_window_focused(&mut self, focused: bool) {
+        self.window_focused = focused;
+        self.focus_changed(focused);
+    }
+
+    /// Whether `on_update`, `on_fixed_update` and `on_tick` are skipped, see
+    /// [`UnfocusedSettings::stop_updates`].
+    pub fn updates_stopped(&self) -> bool {
+        self.unfocused.stop_updates && !self.window_focused
+    }
+
+    /// Whether the app is suspended, e.g. sent to the background on mobile or in a hidden
+    /// browser tab. Nothing is updated or drawn meanwhile, see `GraphicsFlow::on_suspend`.
+    pub fn is_suspended(&self) -> bool {
+        self.suspended
+    }
+
+    pub(crate) fn set_suspended(&mut self, suspended: bool) {
+        self.suspended = suspended;
+    }
+
+    /// Number of `on_tick` rounds run so far.
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    /// Progress from the last tick towards the next one in `[0, 1]`, e.g. to draw state
+    /// simulated in `on_tick` between two steps. See `BuildingBlocks::set_interpolated`
+    /// and `SceneNode::write_to_buffers_interpolated`.
+    pub fn tick_alpha(&self) -> f32 {
+        self.tick_alpha
+    }
+
+    /// Speeds up or slows down the game, e.g. `0.2` for slow motion. The frame time passed
+    /// to `on_update`, `on_fixed_update`, the tick rate and the light rotation are scaled by
+    /// it, `0.0` freezes them like a pause without blocking input. The camera controller and
+    /// flows that run while paused, like the GUI, keep real time. Fails for negative or
+    /// non-finite scales.
+    pub fn set_time_scale(&mut self, scale: f32) -> anyhow::Result<()> {
+        if !scale.is_finite() || scale < 0.0 {
+            anyhow::bail!("time scale {scale} isn't a finite non-negative number");
+        }
+        self.time_scale = scale;
+        Ok(())
+    }
+
+    pub fn time_scale(&self) -> f32 {
+        self.time_scale
+    }
+
+    /// Game time simulated by each `on_fixed_update`, [`DEFAULT_FIXED_STEP`] unless changed.
+    /// Fails for a zero step.
+    pub fn set_fixed_step(&mut self, step: Duration) -> anyhow::Result<()> {
+        if step.is_zero() {
+            anyhow::bail!("the fixed step must be longer than zero");
+        }
+        self.fixed_step = step;
+        Ok(())
+    }
+
+    pub fn fixed_step(&self) -> Duration {
+        self.fixed_step
+    }
+
+    /// Progress from the last `on_fixed_update` towards the next one in `[0, 1]`, e.g. to
+    /// draw a body between its previous and current simulated position.
+    pub fn fixed_alpha(&self) -> f32 {
+        self.fixed_alpha
+    }
+
+    /// Queues a left click at `position` in window coordinates. It is picked and dispatched
+    /// to `on_click` like a real click before the next frame renders.
+    #[cfg(feature = "integration-tests")]
+    pub fn click_at(&mut self, position: PhysicalPosition<f64>) {
+        self.scripted_clicks.push(position);
+    }
+
+    /// Treats the surface as lost on the next frame, so tests go through the recovery of
+    /// [`GraphicsFlow::on_gpu_recovered`](crate::flow::GraphicsFlow::on_gpu_recovered)
+    /// without losing it.
+    #[cfg(feature = "integration-tests")]
+    pub fn force_surface_loss(&mut self) {
+        self.forced_surface_loss = true;
+    }
+
+    /**
+     * Registers an additional camera under `name` with its own uniform buffer and bind group,
+     * replacing a previous one of the same name. Activate it with [`Context::set_active_camera`].
+     *
+     * [`MAIN_CAMERA`] is reserved for [`Context::camera`].
+     */
+    pub fn add_camera(
+        &mut self,
+        name: impl Into<String>,
+        camera: camera::Camera,
+        projection: Projection,
+    ) -> Result<(), anyhow::Error> {
+        let name = name.into();
+        if name == MAIN_CAMERA {
+            anyhow::bail!(
+                "The camera name '{}' is reserved for the main camera",
+                MAIN_CAMERA
+            );
+        }
+        let view = CameraView::new(
+            &self.device,
+            &self.camera.bind_group_layout,
+            camera,
+            projection,
+        );
+        self.cameras.insert(name, view);
+        Ok(())
+    }
+
+    /// Unregisters the camera `name`. Rendering falls back to the main camera if it was active.
+    pub fn remove_camera(&mut self, name: &str) -> Option<CameraView> {
+        if self.active_camera.as_deref() == Some(name) {
+            self.active_camera = None;
+        }
+        self.cameras.remove(name)
+    }
+
+    pub fn camera_view(&self, name: &str) -> Option<&CameraView> {
+        self.cameras.get(name)
+    }
+
+    pub fn camera_view_mut(&mut self, name: &str) -> Option<&mut CameraView> {
+        self.cameras.get_mut(name)
+    }
+
+    /**
+     * Renders and picks through the camera `name` from the next frame on, [`MAIN_CAMERA`]
+     * switches back to [`Context::camera`]. Pipelines are shared, so switching is cheap.
+     *
+     * The controller keeps driving the main camera while another one is active.
+     */
+    pub fn set_active_camera(&mut self, name: &str) -> Result<(), anyhow::Error> {
+        if name == MAIN_CAMERA {
+            self.active_camera = None;
+        } else if self.cameras.contains_key(name) {
+            self.active_camera = Some(name.to_string());
+        } else {
+            anyhow::bail!("No camera named '{}' was registered", name);
+        }
+        Ok(())
+    }
+
+    pub fn active_camera(&self) -> &str {
+        self.active_camera.as_deref().unwrap_or(MAIN_CAMERA)
+    }
+
+    /// Bind group of the active camera, use it when drawing `Render::Custom` renders.
+    pub fn active_camera_bind_group(&self) -> &wgpu::BindGroup {
+        self.active_camera
+            .as_ref()
+            .and_then(|name| self.cameras.get(name))
+            .map_or(&self.camera.bind_group, |view| &view.bind_group)
+    }
+
+    /// View-projection matrix of the active camera as last uploaded, which picks draw with.
+    pub(crate) fn active_view_proj(&self) -> Matrix4<f32> {
+        self.active_camera
+            .as_ref()
+            .and_then(|name| self.cameras.get(name))
+            .map_or(&self.camera.uniform, |view| &view.uniform)
+            .view_proj()
+    }
+
+    /// Projection of the active camera.
+    pub(crate) fn active_projection(&self) -> &Projection {
+        self.active_camera
+            .as_ref()
+            .and_then(|name| self.cameras.get(name))
+            .map_or(&self.projection, |view| &view.projection)
+    }
+
+    /// Writes the uniforms of the main and all registered cameras.
+    pub(crate) fn update_camera_uniforms(&mut self, seconds: f32) {
+        self.camera.uniform.set_time(seconds);
+        for view in self.cameras.values_mut() {
+            view.uniform.set_time(seconds);
+        }
+        self.upload_camera_uniforms();
+    }
+
+    /// Writes the current camera poses to the uniforms, keeping the shaders' time.
+    pub(crate) fn upload_camera_uniforms(&mut self) {
+        // Projections replaced through the public fields follow the depth config as well
+        self.sync_reversed_z();
+        self.camera
+            .uniform
+            .update_view_proj(&self.camera.view_camera(), &self.projection);
+        profiling::write_buffer(
+            &self.queue,
+            &self.camera.buffer,
+            0,
+            bytemuck::cast_slice(&[self.camera.uniform]),
+        );
+        for view in self.cameras.values_mut() {
+            view.write_uniform(&self.queue);
+        }
+    }
+
+    /**
+     * Opens another OS window drawing the flows whose
+     * [`GraphicsFlow::window`](crate::flow::GraphicsFlow::window) returns the new id through
+     * the registered camera `camera`, e.g. an asset preview next to the scene.
+     *
+     * The window opens once the current events are handled and the camera's projection
+     * follows its size. Input of the window doesn't reach flows and picking, GUI layout and
+     * debug renders stay with the main window. Closing it releases its surface, the app
+     * keeps running. Not supported on the web.
+     */
+    pub fn create_window(
+        &mut self,
+        attributes: WindowAttributes,
+        camera: &str,
+    ) -> Result<WindowId, anyhow::Error> {
+        if cfg!(target_arch = "wasm32") {
+            anyhow::bail!("Secondary windows aren't supported on the web");
+        }
+        if camera != MAIN_CAMERA && !self.cameras.contains_key(camera) {
+            anyhow::bail!("No camera named '{}' was registered", camera);
+        }
+        self.next_window += 1;
+        let id = WindowId(self.next_window);
+        self.pending_windows.push(PendingWindow {
+            id,
+            attributes,
+            camera: camera.to_string(),
+        });
+        Ok(id)
+    }
+
+    /// Closes a window of [`Context::create_window`]. The main window closes the app through
+    /// [`Context::request_exit`] instead.
+    pub fn close_window(&mut self, id: WindowId) {
+        self.windows.remove(&id);
+        self.hdr.remove(&id);
+        self.scene_depth.remove(&id);
+        self.overlay.remove_window(id);
+        self.pending_windows.retain(|pending| pending.id != id);
+    }
+
+    /// Whether the window `id` is open or about to open.
+    pub fn is_window_open(&self, id: WindowId) -> bool {
+        match id {
+            WindowId::MAIN => self.window.is_some(),
+            id => {
+                self.windows.contains_key(&id)
+                    || self.pending_windows.iter().any(|pending| pending.id == id)
+            }
+        }
+    }
+
+    /// The main window, `None` for a context created through [`Context::new_headless`].
+    ///
+    /// Contexts passed to flows by [`run`](crate::flow::run) always have one, flows that are
+    /// also drawn through [`Context::render_once`] must handle `None`. See
+    /// `docs/adr/0006-headless-context.md` for migrating from the former `window` field.
+    pub fn window(&self) -> Option<&Arc<Window>> {
+        self.window.as_ref()
+    }
+
+    /// Surface of the main window, `None` for a context created through
+    /// [`Context::new_headless`].
+    pub fn surface(&self) -> Option<&wgpu::Surface<'static>> {
+        self.surface.as_ref()
+    }
+
+    /// Whether the context was created through [`Context::new_headless`] and renders into an
+    /// offscreen target instead of a window.
+    pub fn is_headless(&self) -> bool {
+        self.window.is_none()
+    }
+
+    /// OS window of `id`, e.g. to change its title.
+    pub fn os_window(&self, id: WindowId) -> Option<&Arc<Window>> {
+        match id {
+            WindowId::MAIN => self.window.as_ref(),
+            id => self.windows.get(&id).map(|window| &window.window),
+        }
+    }
+
+    /// The secondary window winit knows as `window_id`.
+    pub(crate) fn secondary_window_id(
+        &self,
+        window_id: winit::window::WindowId,
+    ) -> Option<WindowId> {
+        self.windows
+            .iter()
+            .find(|(_, window)| window.window.id() == window_id)
+            .map(|(&id, _)| id)
+    }
+
+    /// Creates the surface of a window the event loop opened for `pending`.
+    pub(crate) fn open_window(
+        &mut self,
+        pending: PendingWindow,
+        window: Arc<Window>,
+    ) -> Result<(), Error> {
+        let Some((instance, adapter)) = &self.gpu else {
+            return Err(Error::Unsupported {
+                feature: "a secondary window of a headless context".to_string(),
+            });
+        };
+        let window = SecondaryWindow::new(
+            window,
+            pending.camera,
+            (instance, adapter),
+            &self.device,
+            self.config.format,
+            self.anti_aliasing.sample_count(),
+            self.depth.format,
+        )?;
+        let [width, height] = window.size();
+        if let Some(view) = self.cameras.get_mut(&window.camera) {
+            view.projection.resize(width, height);
+        }
+        window.window.request_redraw();
+        self.windows.insert(pending.id, window);
+        Ok(())
+    }
+
+    pub(crate) fn resize_window(&mut self, id: WindowId, width: u32, height: u32) {
+        let sample_count = self.anti_aliasing.sample_count();
+        let Some(window) = self.windows.get_mut(&id) else {
+            return;
+        };
+        window.resize(&self.device, width, height, sample_count, self.depth.format);
+        if width > 0
+            && height > 0
+            && let Some(view) = self.cameras.get_mut(&window.camera)
+        {
+            view.projection.resize(width, height);
+        }
+        window.window.request_redraw();
+    }
+
+    /// Requests a redraw of the main and all secondary windows.
+    pub(crate) fn request_redraws(&self) {
+        for window in self
+            .window
+            .iter()
+            .chain(self.windows.values().map(|w| &w.window))
+        {
+            window.request_redraw();
+        }
+    }
+
+    /// Draws `flows` into the secondary window `id` and presents it.
+    pub(crate) fn render_window<'f, S: 'f, E: Send + 'f>(
+        &mut self,
+        id: WindowId,
+        flows: impl IntoIterator<Item = &'f Box<dyn GraphicsFlow<S, E>>>,
+    ) -> Result<(), Error> {
+        // Taken out so its targets can be borrowed while drawing changes the context
+        let Some(window) = self.windows.remove(&id) else {
+            return Ok(());
+        };
+        let rendered = self.draw_window(id, &window, flows);
+        self.windows.insert(id, window);
+        rendered
+    }
+
+    fn draw_window<'f, S: 'f, E: Send + 'f>(
+        &mut self,
+        id: WindowId,
+        window: &SecondaryWindow,
+        flows: impl IntoIterator<Item = &'f Box<dyn GraphicsFlow<S, E>>>,
+    ) -> Result<(), Error> {
+        let Some(output) = window.current_texture(&self.device) else {
+            return Ok(());
+        };
+        let view = output
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        let (color, resolve, depth) = window.attachments(&view);
+
+        // Drawn through the window's camera, without touching what the main window recorded
+        let camera = (window.camera != MAIN_CAMERA).then(|| window.camera.clone());
+        let active_camera = std::mem::replace(&mut self.active_camera, camera);
+        let debug_render = std::mem::take(&mut self.debug_render);
+        let visibility = std::mem::take(&mut self.visibility);
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Secondary Window Encoder"),
+            });
+        draw_frame(
+            self,
+            flows,
+            &mut encoder,
+            FrameTarget::Attachments {
+                window: id,
+                color,
+                resolve,
+                depth,
+                size: window.size(),
+            },
+        );
+        self.active_camera = active_camera;
+        self.debug_render = debug_render;
+        self.visibility = visibility;
+
+        self.queue.submit(std::iter::once(encoder.finish()));
+        output.present();
+        Ok(())
+    }
+
+    /// Switches to a debug visualisation, [`DebugRender::None`] returns to regular rendering.
+    pub fn set_debug_render(&mut self, mode: DebugRender) {
+        self.debug_render = mode;
+        if mode != DebugRender::Overdraw {
+            self.overdraw = None;
+            self.stats.overdraw = None;
+        }
+    }
+
+    pub fn debug_render(&self) -> DebugRender {
+        self.debug_render
+    }
+
+    /**
+     * Instances of the opaque and transparent draws with pick id `id` that were rendered
+     * in the last frame, `None` if nothing was drawn with that id.
+     *
+     * Only [`BuildingBlocks::write_to_buffer_culled`] culls instances, other draws report
+     * all their instances visible. The sets are recorded while rendering, so during
+     * `on_update` they lag one frame behind the instances.
+     *
+     * [`BuildingBlocks::write_to_buffer_culled`]: crate::data_structures::block::BuildingBlocks::write_to_buffer_culled
+     */
+    pub fn visibility(&self, id: impl Into<PickId>) -> Option<&VisibilitySet> {
+        self.visibility.get(&id.into())
+    }
+
+    /// Latest render statistics. GPU readbacks finish asynchronously so values may lag a
+    /// frame or two behind.
+    pub fn stats(&self) -> &RenderStats {
+        &self.stats
+    }
+
+    /// Frame time, smoothed frame time, FPS, time since start and index of the current frame.
+    pub fn timing(&self) -> &Timing {
+        &self.timing
+    }
+
+    /// Assets the flow constructors reported through [`InitContext::report_progress`], `None`
+    /// once the flows are initialized. Read by the [loading screen](crate::loading).
+    pub fn loading_progress(&self) -> Option<LoadProgress> {
+        self.loading.as_ref().map(ProgressTracker::progress)
+    }
+
+    /// GPU memory held by textures, meshes, instance buffers and pick readbacks the engine
+    /// created, with the `top` largest allocations listed. Buffers created directly through
+    /// `wgpu` aren't included.
+    pub fn memory_report(&self, top: usize) -> MemoryReport {
+        memory::report(top)
+    }
+
+    /// Creates the overdraw target on first use and recreates it after resizes.
+    pub(crate) fn prepare_overdraw(&mut self) {
+        let size = [self.config.width.max(1), self.config.height.max(1)];
+        let sample_count = self.anti_aliasing.sample_count();
+        let outdated = self
+            .overdraw
+            .as_ref()
+            .is_none_or(|overdraw| overdraw.size != size || overdraw.sample_count != sample_count);
+        if outdated {
+            self.overdraw = Some(OverdrawResources::new(
                 &self.device,
-                &self.config,
-                &self.camera.bind_group_layout,
-                &self.light.bind_group_layout,
+                &wgpu::SurfaceConfiguration {
+                    format: self.color_format(),
+                    ..self.config.clone()
+                },
+                &self.layouts,
                 sample_count,
-                8
-            ),
-            flat_pick: mk_gui_pick_pipeline(&self.device, &self.screen_size.bind_group_layout),
+                self.depth,
+            ));
+        }
+    }
+
+    /// Creates the HDR target of `window` on first use and recreates it once its frame of
+    /// `size` changes. Does nothing without HDR.
+    pub(crate) fn prepare_hdr(&mut self, window: WindowId, size: [u32; 2]) {
+        let Some(tonemapping) = self.tonemapping else {
+            return;
         };
+        let sample_count = self.anti_aliasing.sample_count();
+        // Every frame target has the surface format
+        let format = self.config.format;
+        let outdated = self.hdr.get(&window).is_none_or(|hdr| {
+            hdr.size != size
+                || hdr.sample_count != sample_count
+                || hdr.format != format
+                || hdr.tonemapping != tonemapping
+        });
+        if outdated {
+            let hdr = HdrResources::new(&self.device, size, sample_count, format, tonemapping);
+            self.hdr.insert(window, hdr);
+        }
+    }
+
+    /// Creates the scene depth copy of `window` on first use and recreates it once its
+    /// depth attachment of `size` changes.
+    pub(crate) fn prepare_scene_depth(&mut self, window: WindowId, size: [u32; 2]) {
+        let sample_count = self.anti_aliasing.sample_count();
+        let outdated = self
+            .scene_depth
+            .get(&window)
+            .is_none_or(|depth| depth.size != size || depth.sample_count != sample_count);
+        if outdated {
+            let depth = SceneDepthResources::new(&self.device, size, sample_count);
+            self.scene_depth.insert(window, depth);
+        }
     }
 
     pub fn ray_to_floor(&self) -> Option<cgmath::Point2<f32>> {
@@ -483,6 +2969,30 @@ impl Context {
 pub struct InitContext {
     pub queue: wgpu::Queue,
     pub device: wgpu::Device,
+    /// Stable id of the constructed flow, e.g. for [`Context::request_keyboard_focus`].
+    /// `None` for the loading screen and contexts made with `From<&Context>`, which construct
+    /// no flow.
+    pub flow_id: Option<FlowId>,
+    /// The same cache as [`Context::assets`].
+    pub assets: AssetCache,
+    /// The same layouts as [`Context::layouts`].
+    pub layouts: Layouts,
+    /// Copy of [`Context::config`] at startup, e.g. for the surface format of custom
+    /// pipelines.
+    pub config: wgpu::SurfaceConfiguration,
+    /// Physical size of the window at startup, later sizes arrive through `on_resize`.
+    pub window_size: PhysicalSize<u32>,
+    /// See [`Context::scale_factor`].
+    pub scale_factor: f64,
+    /// Depth attachment and sample count pipelines drawn in the scene pass must match.
+    pub depth: DepthConfig,
+    /// See [`Context::color_format`], pipelines drawn in the scene pass must target it.
+    pub color_format: wgpu::TextureFormat,
+    pub anti_aliasing: AntiAliasing,
+    // `EventSender` of the running app, erased as the context isn't generic over its types
+    pub(crate) events: Option<AnySender>,
+    // Set while the loading screen is shown
+    pub(crate) progress: Option<ProgressTracker>,
 }
 impl From<&Context> for InitContext {
     fn from(ctx: &Context) -> Self {
@@ -490,6 +3000,308 @@ impl From<&Context> for InitContext {
             // Queue and Device can be cloned as they're internally handled as Arc
             queue: ctx.queue.clone(),
             device: ctx.device.clone(),
+            flow_id: None,
+            assets: ctx.assets.clone(),
+            layouts: ctx.layouts.clone(),
+            config: ctx.config.clone(),
+            window_size: PhysicalSize::new(ctx.config.width, ctx.config.height),
+            scale_factor: ctx.scale_factor,
+            depth: ctx.depth,
+            color_format: ctx.color_format(),
+            anti_aliasing: ctx.anti_aliasing,
+            events: None,
+            progress: ctx.loading.clone(),
+        }
+    }
+}
+
+impl InitContext {
+    /// Posts events and state mutations onto the event loop, e.g. from a thread reading a
+    /// websocket. `None` outside of [`run`](crate::flow::run) or if `State` and `Event` aren't
+    /// the types the app runs with.
+    ///
+    /// ```ignore
+    /// let sender = ctx.event_sender::<State, Event>().unwrap();
+    /// std::thread::spawn(move || {
+    ///     for message in socket {
+    ///         if sender.send(Event::Message(message)).is_err() {
+    ///             break;
+    ///         }
+    ///     }
+    /// });
+    /// ```
+    pub fn event_sender<State: 'static, Event: 'static>(
+        &self,
+    ) -> Option<EventSender<State, Event>> {
+        self.events
+            .as_ref()?
+            .downcast_ref::<EventSender<State, Event>>()
+            .cloned()
+    }
+
+    /// Reports that the constructor loaded `loaded` of its `total` assets, replacing its
+    /// earlier report. The [loading screen](crate::loading) shows the sum over all
+    /// constructors, flows spawned later have none and ignore this.
+    ///
+    /// ```ignore
+    /// for (i, file) in files.iter().enumerate() {
+    ///     models.push(BuildingBlocks::new(/* ... */, file).await?);
+    ///     ctx.report_progress(i as u32 + 1, files.len() as u32);
+    /// }
+    /// ```
+    pub fn report_progress(&self, loaded: u32, total: u32) {
+        if let (Some(progress), Some(flow_id)) = (&self.progress, self.flow_id) {
+            progress.report(flow_id, LoadProgress { loaded, total });
+        }
+    }
+}
+
+/// Clones the custom events of [`Context::broadcast_events`], which the context only knows as
+/// `Any`.
+pub(crate) struct EventCloner(Box<CloneFn>);
+
+type CloneFn = dyn Fn(&dyn Any) -> Option<Box<dyn Any>>;
+
+impl EventCloner {
+    fn new<E: Clone + 'static>() -> Self {
+        Self(Box::new(|event| {
+            let event = event.downcast_ref::<E>()?;
+            Some(Box::new(event.clone()) as Box<dyn Any>)
+        }))
+    }
+
+    /// `None` if `E` isn't the type the cloner was made for.
+    pub(crate) fn clone_event<E: 'static>(&self, event: &E) -> Option<E> {
+        (self.0)(event)?.downcast().ok().map(|event| *event)
+    }
+}
+
+impl std::fmt::Debug for EventCloner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("EventCloner")
+    }
+}
+
+/// Handler of [`Context::set_dead_letter_handler`], erased like the [`EventCloner`].
+struct DeadLetterHandler(Box<DeadLetterFn>);
+
+// Gives the event back if it isn't of the handler's type
+type DeadLetterFn = dyn FnMut(Box<dyn Any>) -> Option<Box<dyn Any>>;
+
+impl DeadLetterHandler {
+    fn new<E: 'static>(mut handler: impl FnMut(E) + 'static) -> Self {
+        Self(Box::new(move |event| match event.downcast::<E>() {
+            Ok(event) => {
+                handler(*event);
+                None
+            }
+            Err(event) => Some(event),
+        }))
+    }
+}
+
+impl std::fmt::Debug for DeadLetterHandler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("DeadLetterHandler")
+    }
+}
+
+// Shared rather than locked, the sender is only read to clone it
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) type AnySender = Arc<dyn Any + Send + Sync>;
+#[cfg(target_arch = "wasm32")]
+pub(crate) type AnySender = Arc<dyn Any>;
+
+/// `requested` if the surface supports it, `Fifo` otherwise.
+fn supported_present_mode(
+    requested: wgpu::PresentMode,
+    available: &[wgpu::PresentMode],
+) -> wgpu::PresentMode {
+    // The automatic modes pick a supported mode themselves
+    let automatic = matches!(
+        requested,
+        wgpu::PresentMode::AutoVsync | wgpu::PresentMode::AutoNoVsync
+    );
+    if automatic || available.contains(&requested) {
+        requested
+    } else {
+        log::warn!("Present mode {requested:?} isn't supported by the surface, using Fifo.");
+        wgpu::PresentMode::Fifo
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn event_cloner_only_clones_its_type() {
+        let cloner = EventCloner::new::<String>();
+        assert_eq!(
+            cloner.clone_event(&"ping".to_string()).as_deref(),
+            Some("ping")
+        );
+        assert_eq!(cloner.clone_event(&7u32), None);
+    }
+
+    fn hovered_state() -> TooltipState {
+        TooltipState {
+            hovered: Some((
+                PickId(3),
+                HoverTarget::Screen(PhysicalPosition::new(0.0, 0.0)),
+            )),
+            fade: Duration::from_millis(100),
+            ..Default::default()
         }
     }
+
+    #[test]
+    fn only_the_rotate_button_rotates() {
+        let mut settings = InputSettings::default();
+        assert!(settings.rotates(MouseButtonState::Right));
+        assert!(!settings.rotates(MouseButtonState::Middle));
+        assert!(!settings.rotates(MouseButtonState::None));
+
+        settings.rotate_button = MouseButtonState::None;
+        assert!(!settings.rotates(MouseButtonState::None));
+    }
+
+    #[test]
+    fn unsupported_present_modes_fall_back_to_fifo() {
+        use wgpu::PresentMode;
+        let available = [PresentMode::Fifo, PresentMode::Mailbox];
+        assert_eq!(
+            supported_present_mode(PresentMode::Mailbox, &available),
+            PresentMode::Mailbox
+        );
+        assert_eq!(
+            supported_present_mode(PresentMode::Immediate, &available),
+            PresentMode::Fifo
+        );
+        assert_eq!(
+            supported_present_mode(PresentMode::AutoNoVsync, &available),
+            PresentMode::AutoNoVsync
+        );
+    }
+
+    #[test]
+    fn ime_commit_inserts_text() {
+        let event = WindowEvent::Ime(Ime::Commit("日本".to_string()));
+        assert_eq!(
+            TextEvent::from_window_event(&event),
+            Some(TextEvent::Insert("日本".to_string()))
+        );
+    }
+
+    #[test]
+    fn ime_preedit_keeps_cursor() {
+        let event = WindowEvent::Ime(Ime::Preedit("にほ".to_string(), Some((3, 6))));
+        assert_eq!(
+            TextEvent::from_window_event(&event),
+            Some(TextEvent::Preedit {
+                text: "にほ".to_string(),
+                cursor: Some((3, 6)),
+            })
+        );
+    }
+
+    #[test]
+    fn non_text_events_are_ignored() {
+        assert_eq!(
+            TextEvent::from_window_event(&WindowEvent::Ime(Ime::Enabled)),
+            None
+        );
+        assert_eq!(
+            TextEvent::from_window_event(&WindowEvent::Focused(true)),
+            None
+        );
+    }
+
+    #[test]
+    fn tooltip_anchor_is_clamped_to_screen() {
+        let mut tooltip = hovered_state();
+        tooltip.update(
+            Some(PhysicalPosition::new(-50.0, 900.0)),
+            800.0,
+            600.0,
+            Duration::ZERO,
+        );
+        let anchor = tooltip.anchor().unwrap();
+        assert_eq!(anchor.id, PickId(3));
+        assert_eq!(anchor.position, PhysicalPosition::new(8.0, 592.0));
+        assert_eq!(anchor.opacity, 1.0);
+    }
+
+    #[test]
+    fn tooltip_anchor_fades_out_after_hover_ends() {
+        let mut tooltip = hovered_state();
+        tooltip.update(
+            Some(PhysicalPosition::new(10.0, 10.0)),
+            800.0,
+            600.0,
+            Duration::ZERO,
+        );
+        assert!(tooltip.fading().is_none());
+        tooltip.hovered = None;
+        tooltip.update(None, 800.0, 600.0, Duration::from_millis(50));
+        assert!(tooltip.anchor().is_none(), "the hover ended");
+        let fading = tooltip.fading().unwrap();
+        assert!((fading.opacity - 0.5).abs() < 1e-4);
+        assert_eq!(fading.position, PhysicalPosition::new(10.0, 10.0));
+        tooltip.update(None, 800.0, 600.0, Duration::from_millis(60));
+        assert!(tooltip.fading().is_none());
+    }
+
+    #[test]
+    fn tooltip_anchor_none_without_hover() {
+        let mut tooltip = TooltipState::default();
+        tooltip.update(None, 800.0, 600.0, Duration::from_millis(16));
+        assert!(tooltip.anchor().is_none());
+    }
+
+    #[test]
+    fn missing_features_and_limits_are_listed() {
+        let request = DeviceRequest {
+            features: wgpu::Features::POLYGON_MODE_LINE | wgpu::Features::DEPTH_CLIP_CONTROL,
+            limits: wgpu::Limits {
+                max_texture_dimension_2d: 16384,
+                ..wgpu::Limits::default()
+            },
+        };
+        assert_eq!(
+            request.missing(wgpu::Features::DEPTH_CLIP_CONTROL, &wgpu::Limits::default()),
+            [
+                "feature POLYGON_MODE_LINE".to_string(),
+                "max_texture_dimension_2d of 16384 (adapter allows 8192)".to_string(),
+            ]
+        );
+        assert!(
+            request
+                .missing(wgpu::Features::all(), &request.limits)
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn adapter_names_match_ignoring_case() {
+        assert!(name_matches("NVIDIA GeForce RTX 3060 Laptop GPU", "nvidia"));
+        assert!(name_matches("AMD Radeon(TM) Graphics", "Radeon"));
+        assert!(!name_matches("Intel(R) Iris(R) Xe Graphics", "nvidia"));
+    }
+
+    #[test]
+    fn ui_scale_is_clamped_to_its_range() {
+        assert_eq!(clamp_ui_scale(1.5), 1.5);
+        assert_eq!(clamp_ui_scale(0.1), UI_SCALE_RANGE.0);
+        assert_eq!(clamp_ui_scale(f32::INFINITY), UI_SCALE_RANGE.1);
+        assert_eq!(clamp_ui_scale(f32::NAN), 1.0);
+    }
+
+    #[test]
+    fn small_text_is_raised_to_the_minimum_size() {
+        assert_eq!(text_scale(1.0, 12.0, 0.0), 1.0);
+        assert_eq!(text_scale(1.0, 12.0, 18.0), 1.5);
+        assert_eq!(text_scale(2.0, 12.0, 18.0), 2.0);
+        assert_eq!(text_scale(1.0, 0.0, 18.0), 1.0);
+    }
 }
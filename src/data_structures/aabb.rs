@@ -0,0 +1,155 @@
+//! Axis-aligned bounding boxes.
+//!
+//! [`Aabb`] is computed from mesh vertices when models are loaded (see
+//! [`Mesh::bounds`](crate::data_structures::model::Mesh), [`Model::bounds`](crate::data_structures::model::Model::bounds))
+//! and can be moved into world space with an [`Instance`] transform, e.g. to test mouse rays
+//! against objects on the CPU with [`Ray::intersect_aabb`](crate::camera::Ray::intersect_aabb).
+
+use cgmath::{EuclideanSpace, InnerSpace, Point3, Transform, Vector3};
+
+use crate::data_structures::instance::Instance;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: Point3<f32>,
+    pub max: Point3<f32>,
+}
+
+impl Aabb {
+    pub fn new(min: Point3<f32>, max: Point3<f32>) -> Self {
+        Self {
+            min: Point3::new(min.x.min(max.x), min.y.min(max.y), min.z.min(max.z)),
+            max: Point3::new(min.x.max(max.x), min.y.max(max.y), min.z.max(max.z)),
+        }
+    }
+
+    /// Returns the smallest box containing all `points`, `None` if there are none.
+    pub fn from_points(points: impl IntoIterator<Item = Point3<f32>>) -> Option<Self> {
+        points.into_iter().fold(None, |acc: Option<Aabb>, p| {
+            Some(match acc {
+                Some(aabb) => aabb.extend(p),
+                None => Aabb { min: p, max: p },
+            })
+        })
+    }
+
+    pub fn extend(&self, p: Point3<f32>) -> Self {
+        Aabb {
+            min: Point3::new(
+                self.min.x.min(p.x),
+                self.min.y.min(p.y),
+                self.min.z.min(p.z),
+            ),
+            max: Point3::new(
+                self.max.x.max(p.x),
+                self.max.y.max(p.y),
+                self.max.z.max(p.z),
+            ),
+        }
+    }
+
+    pub fn union(&self, other: &Aabb) -> Self {
+        self.extend(other.min).extend(other.max)
+    }
+
+    pub fn center(&self) -> Point3<f32> {
+        self.min.midpoint(self.max)
+    }
+
+    pub fn size(&self) -> Vector3<f32> {
+        self.max - self.min
+    }
+
+    /// Radius of the sphere around [`Aabb::center`] enclosing the box.
+    pub fn radius(&self) -> f32 {
+        self.size().magnitude() * 0.5
+    }
+
+    pub fn corners(&self) -> [Point3<f32>; 8] {
+        let (a, b) = (self.min, self.max);
+        [
+            Point3::new(a.x, a.y, a.z),
+            Point3::new(b.x, a.y, a.z),
+            Point3::new(a.x, b.y, a.z),
+            Point3::new(b.x, b.y, a.z),
+            Point3::new(a.x, a.y, b.z),
+            Point3::new(b.x, a.y, b.z),
+            Point3::new(a.x, b.y, b.z),
+            Point3::new(b.x, b.y, b.z),
+        ]
+    }
+
+    /// Returns the box enclosing this box after applying `instance`.
+    ///
+    /// Rotated boxes grow to stay axis-aligned, so the result may be larger than the
+    /// transformed geometry.
+    pub fn transform(&self, instance: &Instance) -> Self {
+        let matrix = instance.to_matrix();
+        Aabb::from_points(self.corners().map(|c| matrix.transform_point(c))).unwrap_or(*self)
+    }
+
+    pub fn contains(&self, p: Point3<f32>) -> bool {
+        (self.min.x..=self.max.x).contains(&p.x)
+            && (self.min.y..=self.max.y).contains(&p.y)
+            && (self.min.z..=self.max.z).contains(&p.z)
+    }
+
+    pub(crate) fn from_vertex_positions(
+        positions: impl IntoIterator<Item = [f32; 3]>,
+    ) -> Option<Self> {
+        Aabb::from_points(positions.into_iter().map(Point3::from))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::{Deg, Quaternion, Rotation3, assert_relative_eq};
+
+    #[test]
+    fn from_points_spans_all_points() {
+        let aabb = Aabb::from_points([
+            Point3::new(1.0, -2.0, 3.0),
+            Point3::new(-1.0, 2.0, 0.0),
+            Point3::new(0.0, 0.0, 5.0),
+        ])
+        .unwrap();
+        assert_eq!(aabb.min, Point3::new(-1.0, -2.0, 0.0));
+        assert_eq!(aabb.max, Point3::new(1.0, 2.0, 5.0));
+    }
+
+    #[test]
+    fn from_no_points_is_none() {
+        assert!(Aabb::from_points([]).is_none());
+    }
+
+    #[test]
+    fn new_orders_corners() {
+        let aabb = Aabb::new(Point3::new(1.0, 1.0, 1.0), Point3::new(0.0, 0.0, 0.0));
+        assert_eq!(aabb.min, Point3::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn union_and_center() {
+        let a = Aabb::new(Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 1.0, 1.0));
+        let b = Aabb::new(Point3::new(2.0, 0.0, 0.0), Point3::new(3.0, 1.0, 1.0));
+        let u = a.union(&b);
+        assert_eq!(u.center(), Point3::new(1.5, 0.5, 0.5));
+        assert_relative_eq!(u.size().x, 3.0);
+    }
+
+    #[test]
+    fn transform_translates_and_rotates() {
+        let aabb = Aabb::new(Point3::new(0.0, 0.0, 0.0), Point3::new(2.0, 1.0, 1.0));
+        let instance = Instance {
+            position: Vector3::new(10.0, 0.0, 0.0),
+            rotation: Quaternion::from_axis_angle(Vector3::unit_y(), Deg(90.0)),
+            scale: Vector3::new(1.0, 1.0, 1.0),
+        };
+        let moved = aabb.transform(&instance);
+        // x extent of 2 becomes a z extent of 2 after a quarter turn around y
+        assert_relative_eq!(moved.size().z, 2.0, epsilon = 1e-5);
+        assert_relative_eq!(moved.size().x, 1.0, epsilon = 1e-5);
+        assert!(moved.contains(Point3::new(10.5, 0.5, -1.0)));
+    }
+}
@@ -1,27 +1,43 @@
 //! Building blocks implemented via GPU instancing.
 //!
 //! Provides [`BuildingBlocks`], a collection of identically-shaped objects
-//! (e.g., construction blocks or crowds) rendered efficiently using GPU instancing. Note that
-//! hidden blocks are not culled, so this may not be optimal for large voxel worlds.
+//! (e.g., construction blocks or crowds) rendered efficiently using GPU instancing. Blocks
+//! outside the camera can be skipped with [`BuildingBlocks::write_to_buffer_culled`], but
+//! hidden blocks are not, so this may not be optimal for large voxel worlds. Use
+//! [`voxel`](crate::data_structures::voxel) for those instead.
 
 use crate::{
+    camera::Ray,
     context::{Context, GPUResource},
     data_structures::{
-        instance::Instance,
-        model::{self},
+        aabb::Aabb,
+        frustum::Frustum,
+        instance::{Instance, InstanceRaw},
+        model::{self, Material},
+        snapshot::{DEFAULT_MAX_DELTA_LEN, InstanceDelta},
+        visibility::VisibilitySet,
     },
+    error::{Error, Result},
     pick::PickId,
+    profiling,
     render::{Instanced, Render},
-    resources::{self, pick::load_pick_model},
+    resources::{
+        self,
+        assets::AssetCache,
+        memory::{MemoryCategory, TrackedBuffer},
+        pick::load_pick_model,
+    },
 };
-use cgmath::{One, Rotation3, Zero};
-use wgpu::{Device, util::DeviceExt};
+use cgmath::{One, Rotation3, SquareMatrix, Transform, Zero};
+use std::ops::Range;
+use wgpu::Device;
 
 /// A collection of identically-shaped building blocks.
 ///
 /// Uses GPU instancing to efficiently render many copies of the same model
-/// with different transformations. Currently does not perform frustum culling
-/// or occlusion culling, so performance may degrade with very large numbers of blocks.
+/// with different transformations. Frustum culling is opt-in via
+/// [`BuildingBlocks::write_to_buffer_culled`], there is no occlusion culling, so performance
+/// may degrade with very large numbers of blocks.
 pub struct BuildingBlocks {
     // TODO: create apis and make fields private
     pub id: PickId,
@@ -30,8 +46,200 @@ pub struct BuildingBlocks {
     #[allow(dead_code)]
     obj_file: String,
     instances: Vec<Instance>,
-    instance_buffer: wgpu::Buffer,
+    instance_buffer: TrackedBuffer,
     buffer_size_needs_change: bool,
+    // what the instance buffer holds apart from `dirty`
+    contents: BufferContents,
+    // instances changed since the last upload
+    dirty: Option<Range<usize>>,
+    // number of instances at the start of the buffer that survived the last culled write
+    visible: Option<usize>,
+    // instances that survived the last culled write
+    culled: Option<VisibilitySet>,
+    interpolation: Option<Interpolation>,
+    // sequence of the last applied `InstanceDelta`
+    delta_sequence: u64,
+    max_delta_len: u32,
+    pick_instances: bool,
+}
+
+/// What the instance buffer of a [`BuildingBlocks`] was last written with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BufferContents {
+    /// The instances as they are.
+    Plain,
+    /// Culled or offset instances, rewritten in full every time.
+    Other,
+    /// Instances blended between two ticks, see [`Interpolation`].
+    Blended,
+}
+
+/// Instances as of the last two ticks, see [`BuildingBlocks::set_interpolated`].
+#[derive(Default)]
+pub(crate) struct Interpolation {
+    previous: Vec<Instance>,
+    current: Vec<Instance>,
+    // tick `current` was taken at, `None` until the first write
+    tick: Option<u64>,
+    // instances differing between `previous` and `current`, their blend changes every frame
+    moving: Range<usize>,
+}
+
+impl Interpolation {
+    /**
+     * Takes `instances` as the state of `tick` if it is a new one and returns the range of
+     * instances whose blend may differ from the last frame.
+     *
+     * Instances added or removed since the tick are followed right away, new ones unblended,
+     * so there are always as many blended instances as `instances`.
+     */
+    pub(crate) fn advance(&mut self, instances: &[Instance], tick: u64) -> Range<usize> {
+        let len = instances.len();
+        if self.tick.is_none() {
+            self.previous = instances.to_vec();
+            self.current = instances.to_vec();
+            self.tick = Some(tick);
+            self.moving = 0..0;
+            return 0..len;
+        }
+        if self.tick != Some(tick) {
+            self.previous = std::mem::replace(&mut self.current, instances.to_vec());
+            self.tick = Some(tick);
+            let moved = changed_range(&self.previous, &self.current);
+            // instances that stopped moving still need their final blend written
+            return union(std::mem::replace(&mut self.moving, moved.clone()), moved);
+        }
+        if len != self.current.len() {
+            let start = len.min(self.current.len());
+            self.previous.truncate(len);
+            self.current.truncate(len);
+            self.current.extend_from_slice(&instances[start..]);
+            return union(self.moving.clone(), start..len);
+        }
+        self.moving.clone()
+    }
+
+    /// Number of blended instances.
+    pub(crate) fn len(&self) -> usize {
+        self.current.len()
+    }
+
+    /// The instances in `range` blended by `alpha`, see [`interpolated_instances`].
+    pub(crate) fn raws(&self, range: Range<usize>, alpha: f32) -> Vec<InstanceRaw> {
+        let previous = self.previous.get(range.start..).unwrap_or_default();
+        interpolated_instances(previous, &self.current[range], alpha)
+            .iter()
+            .map(Instance::to_raw)
+            .collect()
+    }
+}
+
+/// Range of the instances of `current` that differ from the ones at the same index in
+/// `previous`, or have none.
+fn changed_range(previous: &[Instance], current: &[Instance]) -> Range<usize> {
+    let changed = |idx: &usize| previous.get(*idx) != current.get(*idx);
+    let start = (0..current.len()).find(changed);
+    let end = (0..current.len()).rfind(changed);
+    match (start, end) {
+        (Some(start), Some(end)) => start..end + 1,
+        _ => 0..0,
+    }
+}
+
+/// Smallest range covering both `a` and `b`, empty ranges are ignored.
+fn union(a: Range<usize>, b: Range<usize>) -> Range<usize> {
+    if a.is_empty() {
+        b
+    } else if b.is_empty() {
+        a
+    } else {
+        a.start.min(b.start)..a.end.max(b.end)
+    }
+}
+
+/**
+ * Prepares `buffer` for `len` instances and returns the range of them to write: all if it
+ * had to be replaced to fit them or `rewrite` is set, else `dirty`.
+ */
+pub(crate) fn upload_range(
+    device: &wgpu::Device,
+    buffer: &mut TrackedBuffer,
+    size_needs_change: &mut bool,
+    len: usize,
+    rewrite: bool,
+    dirty: Option<Range<usize>>,
+) -> Range<usize> {
+    let size = (len * std::mem::size_of::<InstanceRaw>()) as u64;
+    if *size_needs_change || size > buffer.size() {
+        *buffer = TrackedBuffer::new(
+            device,
+            &wgpu::BufferDescriptor {
+                label: Some("Instance Buffer"),
+                size,
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            },
+            MemoryCategory::Instance,
+        );
+        *size_needs_change = false;
+        0..len
+    } else if rewrite {
+        0..len
+    } else {
+        dirty.map_or(0..0, |dirty| dirty.start.min(len)..dirty.end.min(len))
+    }
+}
+
+/// Writes `raws` to the instance `buffer` starting at instance `start`.
+pub(crate) fn write_raws(
+    queue: &wgpu::Queue,
+    buffer: &TrackedBuffer,
+    start: usize,
+    raws: &[InstanceRaw],
+) {
+    if !raws.is_empty() {
+        let offset = (start * std::mem::size_of::<InstanceRaw>()) as u64;
+        profiling::write_buffer(queue, buffer, offset, bytemuck::cast_slice(raws));
+    }
+}
+
+/// Blends every instance of `current` with the one at the same index in `previous`.
+/// Instances without a predecessor are taken as they are.
+pub(crate) fn interpolated_instances(
+    previous: &[Instance],
+    current: &[Instance],
+    alpha: f32,
+) -> Vec<Instance> {
+    current
+        .iter()
+        .enumerate()
+        .map(|(idx, instance)| match previous.get(idx) {
+            Some(prev) => prev.lerp(instance, alpha),
+            None => instance.clone(),
+        })
+        .collect()
+}
+
+/// Nearest of `instances` whose `bounds` are hit by `ray`, see [`BuildingBlocks::pick_instance`].
+pub(crate) fn pick_instance(
+    instances: &[Instance],
+    bounds: &Aabb,
+    ray: &Ray,
+) -> Option<(usize, f32)> {
+    instances
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, instance)| {
+            // Instances scaled to zero can't be inverted and aren't visible anyway
+            let to_local = instance.to_matrix().invert()?;
+            // An affine map keeps distances along the ray, so hits compare across instances
+            let local = Ray {
+                origin: to_local.transform_point(ray.origin),
+                direction: to_local.transform_vector(ray.direction),
+            };
+            local.intersect_aabb(bounds).map(|distance| (idx, distance))
+        })
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
 }
 
 pub(crate) fn uniform_instances(
@@ -68,30 +276,97 @@ impl BuildingBlocks {
         start_rotation: cgmath::Quaternion<f32>,
         amount: usize,
         obj_file: &str,
-    ) -> Self {
-        let obj_model = resources::load_model_obj(obj_file, &device, &queue).await;
-        if let Err(e) = obj_model {
-            panic!("Error failed to load model {}: {}", obj_file, e);
-        }
-        let obj_model = obj_model.unwrap();
+    ) -> Result<Self> {
+        let instance_bytes = amount * std::mem::size_of::<InstanceRaw>();
+        Error::check_limit(
+            format_args!("instance buffer size of {obj_file}"),
+            instance_bytes as u64,
+            device.limits().max_buffer_size,
+        )?;
+        let obj_model = resources::load_model_obj(obj_file, &device, &queue).await?;
+
+        let mut blocks = Self::from_model(
+            id,
+            device,
+            obj_model,
+            start_position,
+            start_rotation,
+            amount,
+        );
+        blocks.obj_file = obj_file.to_string();
+        Ok(blocks)
+    }
+
+    /// Like [`BuildingBlocks::new`], loading `obj_file` through `assets` so its materials use
+    /// the cached [`crate::context::Layouts`] and share textures with other models.
+    pub async fn from_assets(
+        id: impl Into<PickId>,
+        assets: &AssetCache,
+        start_position: cgmath::Vector3<f32>,
+        start_rotation: cgmath::Quaternion<f32>,
+        amount: usize,
+        obj_file: &str,
+    ) -> Result<Self> {
+        let device = assets.device();
+        let instance_bytes = amount * std::mem::size_of::<InstanceRaw>();
+        Error::check_limit(
+            format_args!("instance buffer size of {obj_file}"),
+            instance_bytes as u64,
+            device.limits().max_buffer_size,
+        )?;
+        let obj_model = assets.load_model_obj(obj_file, None).await?;
+
+        let mut blocks = Self::from_model(
+            id,
+            device,
+            obj_model,
+            start_position,
+            start_rotation,
+            amount,
+        );
+        blocks.obj_file = obj_file.to_string();
+        Ok(blocks)
+    }
 
+    /// Creates `amount` instances of an already loaded or built model, e.g. the output of
+    /// [`crate::data_structures::builder::ModelBuilder`].
+    pub fn from_model(
+        id: impl Into<PickId>,
+        device: &wgpu::Device,
+        obj_model: model::Model,
+        start_position: cgmath::Vector3<f32>,
+        start_rotation: cgmath::Quaternion<f32>,
+        amount: usize,
+    ) -> Self {
         let instances = uniform_instances(amount, start_position, start_rotation);
 
         let instance_data = instances.iter().map(Instance::to_raw).collect::<Vec<_>>();
-        let instance_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Instance Buffer"),
-            contents: bytemuck::cast_slice(&instance_data),
-            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-        });
+        let instance_buffer = TrackedBuffer::init(
+            device,
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("Instance Buffer"),
+                contents: bytemuck::cast_slice(&instance_data),
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            },
+            MemoryCategory::Instance,
+        );
 
         Self {
             obj_model,
             instances,
-            obj_file: obj_file.to_string(),
+            obj_file: String::new(),
             instance_buffer,
             // Ids may be used later for picking, hitboxes, etc.
             id: id.into(),
             buffer_size_needs_change: false,
+            contents: BufferContents::Plain,
+            dirty: None,
+            visible: None,
+            culled: None,
+            interpolation: None,
+            delta_sequence: 0,
+            max_delta_len: DEFAULT_MAX_DELTA_LEN,
+            pick_instances: false,
         }
     }
 
@@ -108,6 +383,7 @@ impl BuildingBlocks {
     }
 
     pub fn instances_mut_size_unchanged(&mut self) -> &mut [Instance] {
+        self.mark_dirty(0..self.instances.len());
         self.instances.as_mut_slice()
     }
 
@@ -118,6 +394,18 @@ impl BuildingBlocks {
 
     pub fn set_instance(&mut self, idx: usize, instance: Instance) {
         self.instances[idx] = instance;
+        self.mark_dirty(idx..idx + 1);
+    }
+
+    /// Widens the range the next `write_to_buffer` uploads by `range`.
+    fn mark_dirty(&mut self, range: Range<usize>) {
+        if range.is_empty() {
+            return;
+        }
+        self.dirty = Some(match self.dirty.take() {
+            Some(dirty) => dirty.start.min(range.start)..dirty.end.max(range.end),
+            None => range,
+        });
     }
 
     pub fn add_instance(&mut self, instance: Instance) {
@@ -130,6 +418,31 @@ impl BuildingBlocks {
         self.buffer_size_needs_change = true;
     }
 
+    /// Applies a delta received from another [`BuildingBlocks`], see
+    /// [`crate::data_structures::snapshot`]. Only the changed instances are uploaded by the
+    /// next `write_to_buffer`.
+    ///
+    /// Fails with [`Error::DeltaOutOfOrder`] unless `delta` follows the last applied one,
+    /// starting from the empty snapshot, and with [`Error::InvalidDelta`] if it has more
+    /// instances than [`BuildingBlocks::set_max_delta_len`] allows, leaving the instances
+    /// unchanged.
+    pub fn apply_delta(&mut self, delta: &InstanceDelta) -> Result<()> {
+        let changed = delta.apply(
+            &mut self.delta_sequence,
+            &mut self.instances,
+            self.max_delta_len,
+        )?;
+        self.mark_dirty(changed);
+        Ok(())
+    }
+
+    /// Number of instances a delta of [`BuildingBlocks::apply_delta`] may resize the blocks
+    /// to, [`DEFAULT_MAX_DELTA_LEN`] by default. Guards against deltas from untrusted peers
+    /// allocating unbounded memory.
+    pub fn set_max_delta_len(&mut self, max_len: u32) {
+        self.max_delta_len = max_len;
+    }
+
     /**
      * This constructor creates `amount` instances all located at (0.0, 0.0, 0.0).
      *
@@ -140,7 +453,7 @@ impl BuildingBlocks {
         device: &wgpu::Device,
         amount: usize,
         descr: &[(PickId, &'static str)],
-    ) -> Vec<BuildingBlocks> {
+    ) -> Result<Vec<BuildingBlocks>> {
         let futures = descr.into_iter().map(|(id, file_name)| {
             BuildingBlocks::new(
                 *id,
@@ -152,7 +465,7 @@ impl BuildingBlocks {
                 file_name,
             )
         });
-        futures::future::join_all(futures).await
+        futures::future::try_join_all(futures).await
     }
 
     /**
@@ -173,11 +486,15 @@ impl BuildingBlocks {
             .iter()
             .map(Instance::to_raw)
             .collect::<Vec<_>>();
-        let instance_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Instance Buffer for Picking"),
-            contents: bytemuck::cast_slice(&instance_data),
-            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-        });
+        let instance_buffer = TrackedBuffer::init(
+            device,
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("Instance Buffer for Picking"),
+                contents: bytemuck::cast_slice(&instance_data),
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            },
+            MemoryCategory::Instance,
+        );
 
         Self {
             obj_model: obj_model,
@@ -186,6 +503,14 @@ impl BuildingBlocks {
             instance_buffer,
             id,
             buffer_size_needs_change: false,
+            contents: BufferContents::Plain,
+            dirty: None,
+            visible: None,
+            culled: None,
+            interpolation: None,
+            delta_sequence: self.delta_sequence,
+            max_delta_len: self.max_delta_len,
+            pick_instances: self.pick_instances,
         }
     }
 
@@ -199,35 +524,169 @@ impl BuildingBlocks {
         self.instances.drain(from..to);
     }
 
+    /// Indices of the instances whose transformed model bounds intersect `frustum`.
+    ///
+    /// Models without bounds (e.g. without vertices) are treated as always visible.
+    pub fn visible_range(&self, frustum: &Frustum) -> Vec<usize> {
+        let Some(bounds) = self.obj_model.bounds() else {
+            return (0..self.instances.len()).collect();
+        };
+        self.instances
+            .iter()
+            .enumerate()
+            .filter(|(_, instance)| frustum.contains_aabb(&bounds.transform(instance)))
+            .map(|(idx, _)| idx)
+            .collect()
+    }
+
+    /// Index of the nearest instance under `ray` and the distance to it in multiples of
+    /// `ray.direction`, e.g. of [`Camera::cast_ray_from_mouse`].
+    ///
+    /// A CPU alternative to the pick pass for hovering and dragging, as it doesn't wait for
+    /// a buffer readback. The ray is tested against the model bounds in the local space of
+    /// every instance, so rotated and scaled instances are hit where their box actually is.
+    ///
+    /// [`Camera::cast_ray_from_mouse`]: crate::camera::Camera::cast_ray_from_mouse
+    pub fn pick_instance(&self, ray: &Ray) -> Option<(usize, f32)> {
+        // mesh bounds are computed once on load
+        let bounds = self.obj_model.bounds()?;
+        pick_instance(&self.instances, &bounds, ray)
+    }
+
+    /// Like [`GPUResource::write_to_buffer`] but only uploads and draws the instances inside
+    /// `frustum`, e.g. `ctx.camera.uniform.frustum()`.
+    ///
+    /// The culled set is kept until the next write, so call this every frame the camera or
+    /// the instances move.
+    pub fn write_to_buffer_culled(
+        &mut self,
+        queue: &wgpu::Queue,
+        device: &wgpu::Device,
+        frustum: &Frustum,
+    ) {
+        self.obj_model.write_materials(queue);
+        let visible = self.visible_range(frustum);
+        let raws = visible
+            .iter()
+            .map(|&idx| self.instances[idx].to_raw())
+            .collect::<Vec<_>>();
+        self.culled = Some(VisibilitySet::from_visible(self.instances.len(), visible));
+        // keep room for all instances so unculled writes still fit
+        self.upload_range(device, BufferContents::Other, self.instances.len(), None);
+        self.write_raws(queue, 0, &raws);
+        self.visible = Some(raws.len());
+    }
+
+    /**
+     * Prepares the instance buffer for `len` instances of `contents` and returns the range
+     * of them to write: all if the buffer had to grow or last held other contents, else
+     * `dirty`.
+     */
+    fn upload_range(
+        &mut self,
+        device: &wgpu::Device,
+        contents: BufferContents,
+        len: usize,
+        dirty: Option<Range<usize>>,
+    ) -> Range<usize> {
+        let rewrite = self.contents != contents;
+        self.contents = contents;
+        upload_range(
+            device,
+            &mut self.instance_buffer,
+            &mut self.buffer_size_needs_change,
+            len,
+            rewrite,
+            dirty,
+        )
+    }
+
+    /// Writes `raws` to the instance buffer starting at instance `start`.
+    fn write_raws(&self, queue: &wgpu::Queue, start: usize, raws: &[InstanceRaw]) {
+        write_raws(queue, &self.instance_buffer, start, raws);
+    }
+
+    /// Picks every instance on its own, `id` plus the instance's index, so clicks tell which
+    /// one was hit, see [`Instanced::pick_instances`].
+    pub fn set_instance_picking(&mut self, pick_instances: bool) {
+        self.pick_instances = pick_instances;
+    }
+
+    /**
+     * Smooths instances moved in `on_tick` when frames render faster than the tick rate.
+     *
+     * [`BuildingBlocks::write_interpolated`] then keeps the instances of the last two ticks
+     * and uploads them blended by [`Context::tick_alpha`], drawing one tick behind the
+     * simulation.
+     */
+    pub fn set_interpolated(&mut self, interpolated: bool) {
+        if interpolated != self.interpolation.is_some() {
+            self.interpolation = interpolated.then(Interpolation::default);
+        }
+    }
+
+    pub fn is_interpolated(&self) -> bool {
+        self.interpolation.is_some()
+    }
+
+    /// Uploads the instances, blended between the last two ticks if
+    /// [`BuildingBlocks::set_interpolated`] is on. Call it every frame, e.g. in `on_update`,
+    /// so new ticks are noticed.
+    pub fn write_interpolated(&mut self, ctx: &Context) {
+        let Some(interpolation) = &mut self.interpolation else {
+            return self.write_to_buffer(&ctx.queue, &ctx.device);
+        };
+        let changed = interpolation.advance(&self.instances, ctx.ticks());
+        let len = interpolation.len();
+        self.obj_model.write_materials(&ctx.queue);
+        let range = self.upload_range(&ctx.device, BufferContents::Blended, len, Some(changed));
+        if let Some(interpolation) = &self.interpolation {
+            let raws = interpolation.raws(range.clone(), ctx.tick_alpha());
+            self.write_raws(&ctx.queue, range.start, &raws);
+        }
+        self.visible = Some(len);
+        self.culled = None;
+    }
+
     /// Returns the inner instanced of the `Default` render for possible optimizations with `Defaults`
     pub fn to_instanced(&self) -> Instanced<'_> {
         Instanced {
             instance: &self.instance_buffer,
             model: &self.obj_model,
-            amount: self.instances.len(),
+            amount: self.visible.unwrap_or(self.instances.len()),
             front_face: wgpu::FrontFace::Ccw,
             id: self.id,
+            cull_override: None,
+            visibility: self.culled.as_ref(),
+            material_override: None,
+            pick_instances: self.pick_instances,
         }
     }
+
+    /// Renders the blocks with every mesh in `material` instead of its own, e.g. as a ghost
+    /// blueprint. `material` must use the diffuse and normal layout of
+    /// [`Context::layouts`](crate::context::Context::layouts), see
+    /// [`Instanced::material_override`].
+    pub fn render_with_material<'a, 'pass>(
+        &'a self,
+        material: &'a Material,
+    ) -> Render<'a, 'pass> {
+        Render::Default(self.to_instanced().with_material(material))
+    }
 }
 
 impl<'a, 'pass> GPUResource<'a, 'pass> for BuildingBlocks {
     fn write_to_buffer(&mut self, queue: &wgpu::Queue, device: &wgpu::Device) {
-        let raws = self
-            .instances
+        self.obj_model.write_materials(queue);
+        self.visible = None;
+        self.culled = None;
+        let dirty = self.dirty.take();
+        let range = self.upload_range(device, BufferContents::Plain, self.instances.len(), dirty);
+        let raws = self.instances[range.clone()]
             .iter()
             .map(Instance::to_raw)
             .collect::<Vec<_>>();
-        if self.buffer_size_needs_change {
-            self.instance_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some("Instance Buffer"),
-                contents: bytemuck::cast_slice(&raws),
-                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-            });
-            self.buffer_size_needs_change = false;
-        } else {
-            queue.write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(&raws));
-        }
+        self.write_raws(queue, range.start, &raws);
     }
 
     fn get_render(&'a self) -> Render<'a, 'pass> {
@@ -240,21 +699,18 @@ impl<'a, 'pass> GPUResource<'a, 'pass> for BuildingBlocks {
         device: &wgpu::Device,
         offset: &Instance,
     ) {
+        self.obj_model.write_materials(queue);
+        self.visible = None;
+        self.culled = None;
+        // The offset may have moved, so everything is rewritten
+        let len = self.instances.len();
+        self.upload_range(device, BufferContents::Other, len, None);
         let raws = self
             .instances
             .iter()
             .map(|local| (offset * local).to_raw())
             .collect::<Vec<_>>();
-        if self.buffer_size_needs_change {
-            self.instance_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some("Offset Instance Buffer"),
-                contents: bytemuck::cast_slice(&raws),
-                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-            });
-            self.buffer_size_needs_change = false;
-        } else {
-            queue.write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(&raws));
-        }
+        self.write_raws(queue, 0, &raws);
     }
 }
 
@@ -263,6 +719,97 @@ mod tests {
     use super::*;
     use cgmath::{Deg, Quaternion, Rotation3, Vector3, assert_relative_eq};
 
+    #[test]
+    fn interpolation_at_half_alpha_is_midpoint() {
+        let previous = vec![Instance::new()];
+        let mut moved = Instance::new();
+        moved.position = Vector3::new(4.0, 2.0, 0.0);
+        let current = vec![moved, Instance::new()];
+        let blended = interpolated_instances(&previous, &current, 0.5);
+        assert_eq!(blended.len(), 2);
+        assert_relative_eq!(
+            blended[0].to_matrix(),
+            cgmath::Matrix4::from_translation(Vector3::new(2.0, 1.0, 0.0)),
+            epsilon = 1e-6
+        );
+        // instances added since the last tick appear without blending
+        assert_eq!(blended[1].position, Vector3::zero());
+    }
+
+    fn at(x: f32) -> Instance {
+        let mut instance = Instance::new();
+        instance.position = Vector3::new(x, 0.0, 0.0);
+        instance
+    }
+
+    // Applies the writes of one frame to `buffer` like `write_raws` does on the GPU
+    fn upload(
+        buffer: &mut Vec<InstanceRaw>,
+        interpolation: &mut Interpolation,
+        instances: &[Instance],
+        tick: u64,
+        alpha: f32,
+    ) -> Range<usize> {
+        let changed = interpolation.advance(instances, tick);
+        buffer.resize(interpolation.len(), Instance::new().to_raw());
+        let range = changed.start.min(buffer.len())..changed.end.min(buffer.len());
+        let raws = interpolation.raws(range.clone(), alpha);
+        buffer[range.clone()].copy_from_slice(&raws);
+        range
+    }
+
+    fn assert_positions(buffer: &[InstanceRaw], expected: &[f32]) {
+        let expected = expected.iter().map(|&x| at(x).to_raw()).collect::<Vec<_>>();
+        assert_eq!(
+            bytemuck::cast_slice::<_, f32>(buffer),
+            bytemuck::cast_slice::<_, f32>(&expected)
+        );
+    }
+
+    #[test]
+    fn interpolated_buffer_only_rewrites_moving_instances() {
+        let mut interpolation = Interpolation::default();
+        let mut buffer = Vec::new();
+        let first = [at(0.0), at(1.0), at(2.0)];
+        assert_eq!(
+            upload(&mut buffer, &mut interpolation, &first, 0, 0.0),
+            0..3
+        );
+        assert_positions(&buffer, &[0.0, 1.0, 2.0]);
+
+        // tick 1 moves the middle instance by 4
+        let second = [at(0.0), at(5.0), at(2.0)];
+        assert_eq!(
+            upload(&mut buffer, &mut interpolation, &second, 1, 0.25),
+            1..2
+        );
+        assert_positions(&buffer, &[0.0, 2.0, 2.0]);
+        assert_eq!(
+            upload(&mut buffer, &mut interpolation, &second, 1, 0.5),
+            1..2
+        );
+        assert_positions(&buffer, &[0.0, 3.0, 2.0]);
+
+        // tick 2 stops it, its final position is written once more
+        assert_eq!(
+            upload(&mut buffer, &mut interpolation, &second, 2, 0.0),
+            1..2
+        );
+        assert_positions(&buffer, &[0.0, 5.0, 2.0]);
+        assert!(upload(&mut buffer, &mut interpolation, &second, 2, 0.5).is_empty());
+        assert_positions(&buffer, &[0.0, 5.0, 2.0]);
+    }
+
+    #[test]
+    fn interpolation_follows_instances_added_between_ticks() {
+        let mut interpolation = Interpolation::default();
+        let mut buffer = Vec::new();
+        upload(&mut buffer, &mut interpolation, &[at(0.0)], 0, 0.0);
+        let range = upload(&mut buffer, &mut interpolation, &[at(0.0), at(7.0)], 0, 0.5);
+        assert_eq!(range, 1..2);
+        assert_positions(&buffer, &[0.0, 7.0]);
+    }
+
     #[test]
     fn uniform_instances_correct_count() {
         let instances = uniform_instances(5, Vector3::new(1.0, 2.0, 3.0), Quaternion::one());
@@ -319,4 +866,60 @@ mod tests {
         // near_zero.is_zero() returns false, so rotation should be preserved
         assert_relative_eq!(instances[0].rotation.s, rot.s, epsilon = 1e-6);
     }
+
+    fn unit_box() -> Aabb {
+        Aabb::new(
+            cgmath::Point3::new(-0.5, -0.5, -0.5),
+            cgmath::Point3::new(0.5, 0.5, 0.5),
+        )
+    }
+
+    fn ray_down_at(x: f32, z: f32) -> Ray {
+        Ray {
+            origin: cgmath::Point3::new(x, 10.0, z),
+            direction: -Vector3::unit_y(),
+        }
+    }
+
+    fn instance_at(position: Vector3<f32>) -> Instance {
+        let mut instance = Instance::new();
+        instance.position = position;
+        instance
+    }
+
+    #[test]
+    fn pick_instance_returns_nearest_hit() {
+        let instances = vec![
+            instance_at(Vector3::new(0.0, 0.0, 0.0)),
+            instance_at(Vector3::new(0.0, 2.0, 0.0)),
+            instance_at(Vector3::new(5.0, 4.0, 0.0)),
+        ];
+        let (idx, distance) =
+            pick_instance(&instances, &unit_box(), &ray_down_at(0.0, 0.0)).unwrap();
+        assert_eq!(idx, 1);
+        assert_relative_eq!(distance, 7.5, epsilon = 1e-5);
+        assert!(pick_instance(&instances, &unit_box(), &ray_down_at(2.5, 0.0)).is_none());
+    }
+
+    #[test]
+    fn pick_instance_respects_scale() {
+        let mut wide = Instance::new();
+        wide.scale = Vector3::new(6.0, 1.0, 1.0);
+        let (_, distance) = pick_instance(&[wide], &unit_box(), &ray_down_at(2.5, 0.0)).unwrap();
+        assert_relative_eq!(distance, 9.5, epsilon = 1e-5);
+
+        let mut flat = Instance::new();
+        flat.scale = Vector3::zero();
+        assert!(pick_instance(&[flat], &unit_box(), &ray_down_at(0.0, 0.0)).is_none());
+    }
+
+    #[test]
+    fn pick_instance_misses_corners_of_rotated_hull() {
+        let mut rotated = Instance::new();
+        rotated.rotation = Quaternion::from_angle_y(Deg(45.0));
+        // inside the axis-aligned hull of the rotated box but outside the box itself
+        let rotated = [rotated];
+        assert!(pick_instance(&rotated, &unit_box(), &ray_down_at(0.6, 0.6)).is_none());
+        assert!(pick_instance(&rotated, &unit_box(), &ray_down_at(0.65, 0.0)).is_some());
+    }
 }
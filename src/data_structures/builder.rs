@@ -0,0 +1,279 @@
+//! Validated construction of [`Mesh`] and [`Model`] from custom geometry.
+//!
+//! Filling the public fields of a `Mesh` by hand makes it easy to break a draw call, e.g.
+//! with a `num_elements` that doesn't match the index buffer. The builders check the
+//! geometry, create the buffers with the usages the pipelines expect and compute bounds.
+//! Their output can be passed to `ModelNode::from_model` and `BuildingBlocks::from_model`.
+
+use anyhow::{Context as _, bail, ensure};
+use wgpu::util::DeviceExt;
+
+use crate::{
+    data_structures::{
+        aabb::Aabb,
+        model::{Material, Mesh, Model, ModelVertex},
+    },
+    resources::mesh::compute_tangents,
+};
+
+/// Builds a single indexed triangle mesh.
+#[derive(Debug, Clone, Default)]
+pub struct MeshBuilder {
+    name: String,
+    vertices: Vec<ModelVertex>,
+    indices: Vec<u32>,
+    material: usize,
+    generate_tangents: bool,
+}
+
+impl MeshBuilder {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn vertices(mut self, vertices: Vec<ModelVertex>) -> Self {
+        self.vertices = vertices;
+        self
+    }
+
+    pub fn indices(mut self, indices: Vec<u32>) -> Self {
+        self.indices = indices;
+        self
+    }
+
+    /// Index into the materials of the model this mesh ends up in, `0` by default.
+    pub fn material(mut self, material: usize) -> Self {
+        self.material = material;
+        self
+    }
+
+    /// Computes tangents and bitangents from positions and texture coordinates on build,
+    /// overwriting the ones set on the vertices. Needed for normal mapping.
+    pub fn with_tangents(mut self) -> Self {
+        self.generate_tangents = true;
+        self
+    }
+
+    /// Appends a vertex with tangents left to [`MeshBuilder::with_tangents`] and returns
+    /// its index.
+    pub fn push_vertex(
+        &mut self,
+        position: [f32; 3],
+        tex_coords: [f32; 2],
+        normal: [f32; 3],
+    ) -> u32 {
+        self.vertices.push(ModelVertex {
+            position,
+            tex_coords,
+            normal,
+            tangent: [0.0; 3],
+            bitangent: [0.0; 3],
+        });
+        (self.vertices.len() - 1) as u32
+    }
+
+    /// Appends a triangle, counter-clockwise when seen from the front.
+    pub fn push_triangle(&mut self, a: u32, b: u32, c: u32) {
+        self.indices.extend([a, b, c]);
+    }
+
+    pub fn material_index(&self) -> usize {
+        self.material
+    }
+
+    /// Checks the geometry without touching the GPU. [`MeshBuilder::build`] does this too.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        ensure!(
+            !self.vertices.is_empty(),
+            "Mesh '{}' has no vertices",
+            self.name
+        );
+        ensure!(
+            !self.indices.is_empty(),
+            "Mesh '{}' has no indices",
+            self.name
+        );
+        ensure!(
+            self.indices.len().is_multiple_of(3),
+            "Mesh '{}' has {} indices, which is not a whole number of triangles",
+            self.name,
+            self.indices.len()
+        );
+        ensure!(
+            u32::try_from(self.indices.len()).is_ok(),
+            "Mesh '{}' has more than {} indices",
+            self.name,
+            u32::MAX
+        );
+        if let Some((position, index)) = self
+            .indices
+            .iter()
+            .enumerate()
+            .find(|(_, index)| **index as usize >= self.vertices.len())
+        {
+            bail!(
+                "Index {} at position {} of mesh '{}' is out of bounds for {} vertices",
+                index,
+                position,
+                self.name,
+                self.vertices.len()
+            );
+        }
+        if let Some(vertex) = self
+            .vertices
+            .iter()
+            .position(|v| v.position.iter().any(|c| !c.is_finite()))
+        {
+            bail!(
+                "Vertex {} of mesh '{}' has a non-finite position",
+                vertex,
+                self.name
+            );
+        }
+        Ok(())
+    }
+
+    /// Validates the geometry and uploads it.
+    pub fn build(mut self, device: &wgpu::Device) -> anyhow::Result<Mesh> {
+        self.validate()?;
+        if self.generate_tangents {
+            compute_tangents(&mut self.vertices, &self.indices);
+        }
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(&format!("{} Vertex Buffer", self.name)),
+            contents: bytemuck::cast_slice(&self.vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(&format!("{} Index Buffer", self.name)),
+            contents: bytemuck::cast_slice(&self.indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+        Ok(Mesh {
+            memory: Mesh::track_buffers(&vertex_buffer, &index_buffer, &self.name),
+            bounds: Aabb::from_vertex_positions(self.vertices.iter().map(|v| v.position)),
+            num_elements: self.indices.len() as u32,
+            name: self.name,
+            vertex_buffer,
+            index_buffer,
+            material: self.material,
+        })
+    }
+}
+
+/// Builds a [`Model`] from meshes and the materials they refer to.
+#[derive(Debug, Default)]
+pub struct ModelBuilder {
+    meshes: Vec<MeshBuilder>,
+    materials: Vec<Material>,
+}
+
+impl ModelBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn mesh(mut self, mesh: MeshBuilder) -> Self {
+        self.meshes.push(mesh);
+        self
+    }
+
+    /// Adds a material, meshes refer to it by the order materials were added in.
+    pub fn material(mut self, material: Material) -> Self {
+        self.materials.push(material);
+        self
+    }
+
+    /// Checks all meshes and their material indices without touching the GPU.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        validate_model(&self.meshes, self.materials.len())
+    }
+
+    pub fn build(self, device: &wgpu::Device) -> anyhow::Result<Model> {
+        self.validate()?;
+        let meshes = self
+            .meshes
+            .into_iter()
+            .map(|mesh| {
+                let name = mesh.name.clone();
+                mesh.build(device)
+                    .with_context(|| format!("Failed to build mesh '{}'", name))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(Model {
+            meshes,
+            materials: self.materials,
+        })
+    }
+}
+
+fn validate_model(meshes: &[MeshBuilder], material_count: usize) -> anyhow::Result<()> {
+    ensure!(!meshes.is_empty(), "A model needs at least one mesh");
+    for mesh in meshes {
+        mesh.validate()?;
+        ensure!(
+            mesh.material < material_count,
+            "Mesh '{}' uses material {} but the model only has {} materials",
+            mesh.name,
+            mesh.material,
+            material_count
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn triangle() -> MeshBuilder {
+        let mut mesh = MeshBuilder::new("triangle");
+        let a = mesh.push_vertex([0.0, 0.0, 0.0], [0.0, 0.0], [0.0, 0.0, 1.0]);
+        let b = mesh.push_vertex([1.0, 0.0, 0.0], [1.0, 0.0], [0.0, 0.0, 1.0]);
+        let c = mesh.push_vertex([0.0, 1.0, 0.0], [0.0, 1.0], [0.0, 0.0, 1.0]);
+        mesh.push_triangle(a, b, c);
+        mesh
+    }
+
+    #[test]
+    fn valid_triangle_passes() {
+        assert!(triangle().validate().is_ok());
+        assert!(validate_model(&[triangle()], 1).is_ok());
+    }
+
+    #[test]
+    fn empty_geometry_is_rejected() {
+        assert!(MeshBuilder::new("empty").validate().is_err());
+        let no_indices = triangle().indices(Vec::new());
+        assert!(no_indices.validate().is_err());
+        assert!(validate_model(&[], 1).is_err());
+    }
+
+    #[test]
+    fn out_of_bounds_index_is_rejected() {
+        let mesh = triangle().indices(vec![0, 1, 3]);
+        let err = mesh.validate().unwrap_err().to_string();
+        assert!(err.contains("out of bounds"), "{}", err);
+    }
+
+    #[test]
+    fn partial_triangle_is_rejected() {
+        assert!(triangle().indices(vec![0, 1, 2, 0]).validate().is_err());
+    }
+
+    #[test]
+    fn non_finite_position_is_rejected() {
+        let mut mesh = triangle();
+        mesh.push_vertex([f32::NAN, 0.0, 0.0], [0.0, 0.0], [0.0, 0.0, 1.0]);
+        assert!(mesh.validate().is_err());
+    }
+
+    #[test]
+    fn material_index_must_exist() {
+        assert!(validate_model(&[triangle().material(1)], 1).is_err());
+        assert!(validate_model(&[triangle()], 0).is_err());
+    }
+}
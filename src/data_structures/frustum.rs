@@ -0,0 +1,174 @@
+//! View frustum for culling objects on the CPU before they are drawn.
+//!
+//! A [`Frustum`] is extracted from a view-projection matrix, usually via
+//! [`CameraUniform::frustum`](crate::camera::CameraUniform::frustum) or
+//! [`Frustum::from_camera`]. It assumes wgpu's clip space where depth ranges from `0` to `1`.
+
+use cgmath::{InnerSpace, Matrix, Matrix4, Point3, Vector3, Vector4};
+
+use crate::{
+    camera::{Camera, Projection},
+    data_structures::aabb::Aabb,
+};
+
+/// A plane `normal · p + distance = 0` whose normal points into the frustum.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Plane {
+    pub normal: Vector3<f32>,
+    pub distance: f32,
+}
+
+impl Plane {
+    fn from_row(row: Vector4<f32>) -> Self {
+        let normal = row.truncate();
+        let length = normal.magnitude();
+        Self {
+            normal: normal / length,
+            distance: row.w / length,
+        }
+    }
+
+    /// Signed distance of `p`, positive on the inner side.
+    pub fn signed_distance(&self, p: Point3<f32>) -> f32 {
+        self.normal.dot(Vector3::new(p.x, p.y, p.z)) + self.distance
+    }
+}
+
+/// The six planes bounding everything a camera can see.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Frustum {
+    /// Left, right, bottom, top, near and far, in that order.
+    pub planes: [Plane; 6],
+}
+
+impl Frustum {
+    /// Extracts the planes from a view-projection matrix (Gribb/Hartmann).
+    pub fn from_matrix(view_proj: Matrix4<f32>) -> Self {
+        let (r0, r1, r2, r3) = (
+            view_proj.row(0),
+            view_proj.row(1),
+            view_proj.row(2),
+            view_proj.row(3),
+        );
+        Self {
+            planes: [
+                Plane::from_row(r3 + r0),
+                Plane::from_row(r3 - r0),
+                Plane::from_row(r3 + r1),
+                Plane::from_row(r3 - r1),
+                // wgpu clips at z >= 0 instead of OpenGL's z >= -w
+                Plane::from_row(r2),
+                Plane::from_row(r3 - r2),
+            ],
+        }
+    }
+
+    pub fn from_camera(camera: &Camera, projection: &Projection) -> Self {
+        Self::from_matrix(projection.calc_matrix() * camera.calc_matrix())
+    }
+
+    /// Whether any part of the sphere may be visible.
+    pub fn contains_sphere(&self, center: Point3<f32>, radius: f32) -> bool {
+        self.planes
+            .iter()
+            .all(|plane| plane.signed_distance(center) >= -radius)
+    }
+
+    /// Whether any part of the box may be visible.
+    ///
+    /// Conservative: boxes near a frustum corner can pass although they are outside.
+    pub fn contains_aabb(&self, aabb: &Aabb) -> bool {
+        self.planes.iter().all(|plane| {
+            // the corner furthest along the normal decides whether the box is fully outside
+            let corner = Point3::new(
+                if plane.normal.x >= 0.0 {
+                    aabb.max.x
+                } else {
+                    aabb.min.x
+                },
+                if plane.normal.y >= 0.0 {
+                    aabb.max.y
+                } else {
+                    aabb.min.y
+                },
+                if plane.normal.z >= 0.0 {
+                    aabb.max.z
+                } else {
+                    aabb.min.z
+                },
+            );
+            plane.signed_distance(corner) >= 0.0
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::OPENGL_TO_WGPU_MATRIX;
+    use cgmath::{Deg, SquareMatrix, assert_relative_eq, ortho};
+
+    fn camera() -> (Camera, Projection) {
+        // looks down -z from the origin
+        let camera = Camera::new((0.0, 0.0, 0.0), Deg(-90.0), Deg(0.0));
+        let projection = Projection::new(100, 100, Deg(90.0), 1.0, 100.0).unwrap();
+        (camera, projection)
+    }
+
+    #[test]
+    fn planes_point_inwards() {
+        let frustum =
+            Frustum::from_matrix(OPENGL_TO_WGPU_MATRIX * ortho(-1.0, 1.0, -1.0, 1.0, 0.0, 1.0));
+        let [left, right, bottom, top, near, far] = frustum.planes;
+        assert_relative_eq!(left.normal, Vector3::unit_x());
+        assert_relative_eq!(right.normal, -Vector3::unit_x());
+        assert_relative_eq!(bottom.normal, Vector3::unit_y());
+        assert_relative_eq!(top.normal, -Vector3::unit_y());
+        // cgmath's ortho looks down -z, the near plane sits at z = 0
+        assert_relative_eq!(near.normal, -Vector3::unit_z());
+        assert_relative_eq!(far.normal, Vector3::unit_z());
+        assert_relative_eq!(near.distance, 0.0);
+        assert_relative_eq!(far.distance, 1.0);
+    }
+
+    #[test]
+    fn identity_is_wgpu_clip_volume() {
+        let frustum = Frustum::from_matrix(Matrix4::identity());
+        assert!(frustum.contains_sphere(Point3::new(0.0, 0.0, 0.5), 0.0));
+        // depth below zero is clipped in wgpu, unlike OpenGL
+        assert!(!frustum.contains_sphere(Point3::new(0.0, 0.0, -0.5), 0.0));
+        assert!(!frustum.contains_sphere(Point3::new(0.0, 0.0, 1.5), 0.0));
+        assert!(!frustum.contains_sphere(Point3::new(1.5, 0.0, 0.5), 0.0));
+        assert!(frustum.contains_sphere(Point3::new(1.5, 0.0, 0.5), 0.6));
+    }
+
+    #[test]
+    fn spheres_against_camera() {
+        let (camera, projection) = camera();
+        let frustum = Frustum::from_camera(&camera, &projection);
+        assert!(frustum.contains_sphere(Point3::new(0.0, 0.0, -10.0), 1.0));
+        assert!(!frustum.contains_sphere(Point3::new(0.0, 0.0, 10.0), 1.0));
+        assert!(!frustum.contains_sphere(Point3::new(0.0, 0.0, -0.5), 0.1));
+        assert!(!frustum.contains_sphere(Point3::new(0.0, 0.0, -150.0), 1.0));
+        assert!(!frustum.contains_sphere(Point3::new(30.0, 0.0, -10.0), 1.0));
+        // intersecting the near plane still counts
+        assert!(frustum.contains_sphere(Point3::new(0.0, 0.0, -0.5), 1.0));
+    }
+
+    #[test]
+    fn boxes_against_camera() {
+        let (camera, projection) = camera();
+        let frustum = Frustum::from_camera(&camera, &projection);
+        let at = |x: f32, z: f32| {
+            Aabb::new(
+                Point3::new(x - 1.0, -1.0, z - 1.0),
+                Point3::new(x + 1.0, 1.0, z + 1.0),
+            )
+        };
+        assert!(frustum.contains_aabb(&at(0.0, -10.0)));
+        assert!(!frustum.contains_aabb(&at(0.0, 10.0)));
+        assert!(!frustum.contains_aabb(&at(-30.0, -10.0)));
+        // straddling the right plane
+        assert!(frustum.contains_aabb(&at(10.5, -10.0)));
+    }
+}
@@ -14,7 +14,7 @@ use crate::data_structures::model;
 /// Used for GPU instancing: multiple copies of the same model can be rendered
 /// with different transforms in a single draw call. The instance data is packed
 /// into a GPU buffer and accessible to vertex shaders.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Instance {
     pub position: cgmath::Vector3<f32>,
     pub rotation: cgmath::Quaternion<f32>,
@@ -48,6 +48,15 @@ impl Instance {
             handedness: handedness,
         }
     }
+
+    /// Blends towards `other`: positions and scales linearly, rotations along the shortest arc.
+    pub fn lerp(&self, other: &Instance, t: f32) -> Instance {
+        Instance {
+            position: self.position + (other.position - self.position) * t,
+            rotation: self.rotation.slerp(other.rotation, t),
+            scale: self.scale + (other.scale - self.scale) * t,
+        }
+    }
 }
 
 impl Mul<Instance> for Instance {
@@ -187,6 +196,23 @@ mod tests {
         assert_relative_eq!(a.scale.z, b.scale.z, epsilon = 1e-5);
     }
 
+    #[test]
+    fn lerp_blends_halfway() {
+        let a = Instance::new();
+        let b = Instance {
+            position: Vector3::new(2.0, 0.0, -4.0),
+            rotation: Quaternion::from_angle_y(Deg(90.0)),
+            scale: Vector3::new(3.0, 1.0, 1.0),
+        };
+        let expected = Instance {
+            position: Vector3::new(1.0, 0.0, -2.0),
+            rotation: Quaternion::from_angle_y(Deg(45.0)),
+            scale: Vector3::new(2.0, 1.0, 1.0),
+        };
+        approx_eq_instance(&a.lerp(&b, 0.5), &expected);
+        approx_eq_instance(&a.lerp(&b, 1.0), &b);
+    }
+
     #[test]
     fn identity_matrix() {
         let m = Instance::new().to_matrix();
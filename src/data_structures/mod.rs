@@ -4,15 +4,30 @@
 //!
 //! - `model` contains mesh and material definitions, GPU resources for 3D models
 //! - `texture` contains GPU texture wrapper and creation utilities
+//! - `builder` validates and uploads custom geometry into meshes and models
+//! - `aabb` provides axis-aligned bounding boxes for meshes and scene nodes
+//! - `frustum` extracts camera frustums to cull instances on the CPU
 //! - `block` is an instanced building blocks (pre-configured model + instance data)
 //! - `instance` holds per-instance transformation and attribute data
+//! - `nav` finds paths over terrain and walks instances along them (feature `nav`)
 //! - `scene_graph` enables hierarchical scene organization
+//! - `snapshot` encodes compact instance deltas, e.g. to sync blocks over the network
 //! - `terrain` will be used for terrain mesh and management
+//! - `visibility` records which instances of a draw were rendered last frame
+//! - `voxel` holds chunked voxel worlds meshed with hidden-face culling
 
+pub mod aabb;
 pub mod block;
+pub mod builder;
 pub mod collision;
+pub mod frustum;
 pub mod instance;
 pub mod model;
+#[cfg(feature = "nav")]
+pub mod nav;
 pub mod scene_graph;
+pub mod snapshot;
 pub mod texture;
 pub mod terrain;
+pub mod visibility;
+pub mod voxel;
@@ -4,12 +4,25 @@
 //!
 //! - [`ModelVertex`] holds position, normals, tangents, and texture coordinates
 //! - [`Material`] is the material with diffuse and normal textures and samplers
+//! - [`MaterialAnimator`] holds UV scrolling and tint animation parameters of a material
 //! - [`Mesh`] is a single mesh (vertices, indices, material)
 //! - [`Model`] is a collection of meshes with shared materials
 
 use std::ops::Range;
 
-use crate::{data_structures::texture::{self, create_default_sampler}, resources::pick::pick_layout};
+use wgpu::util::DeviceExt;
+
+use crate::{
+    data_structures::{
+        aabb::Aabb,
+        texture::{self, create_default_sampler},
+    },
+    profiling,
+    resources::{
+        memory::{MemoryCategory, TrackedMemory},
+        pick::pick_layout,
+    },
+};
 
 /// Trait for types that describe their GPU vertex layout.
 pub trait Vertex {
@@ -69,10 +82,90 @@ impl Vertex for ModelVertex {
     }
 }
 
+/// GPU layout of [`MaterialAnimator`], bound next to the material textures.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable, PartialEq)]
+pub struct MaterialUniform {
+    uv_offset: [f32; 2],
+    uv_scale: [f32; 2],
+    uv_scroll: [f32; 2],
+    tint_period: f32,
+    _padding: f32,
+    tint_a: [f32; 4],
+    tint_b: [f32; 4],
+}
+
+/// Animated material parameters, evaluated in the shader from the global time.
+///
+/// Texture coordinates become `uv * uv_scale + uv_offset + uv_scroll * time` and the
+/// sampled colour is multiplied by a tint oscillating between `tint_a` and `tint_b`
+/// every `tint_period` seconds (a period of 0 keeps `tint_a`). Since the motion is
+/// computed on the GPU the uniform only needs to be written when parameters change.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MaterialAnimator {
+    pub uv_offset: cgmath::Vector2<f32>,
+    pub uv_scale: cgmath::Vector2<f32>,
+    pub uv_scroll: cgmath::Vector2<f32>,
+    pub tint_a: [f32; 4],
+    pub tint_b: [f32; 4],
+    pub tint_period: f32,
+}
+
+impl Default for MaterialAnimator {
+    fn default() -> Self {
+        Self {
+            uv_offset: cgmath::Vector2::new(0.0, 0.0),
+            uv_scale: cgmath::Vector2::new(1.0, 1.0),
+            uv_scroll: cgmath::Vector2::new(0.0, 0.0),
+            tint_a: [1.0; 4],
+            tint_b: [1.0; 4],
+            tint_period: 0.0,
+        }
+    }
+}
+
+impl MaterialAnimator {
+    /// Pulses the tint between `color_a` and `color_b` once per `period` seconds.
+    pub fn tint_pulse(color_a: [f32; 4], color_b: [f32; 4], period: f32) -> Self {
+        Self {
+            tint_a: color_a,
+            tint_b: color_b,
+            tint_period: period.max(0.0),
+            ..Default::default()
+        }
+    }
+
+    /// Scrolls the texture by `rate` UV units per second, e.g. for conveyor belts.
+    pub fn uv_scroll(rate: cgmath::Vector2<f32>) -> Self {
+        Self {
+            uv_scroll: rate,
+            ..Default::default()
+        }
+    }
+
+    pub fn to_raw(&self) -> MaterialUniform {
+        MaterialUniform {
+            uv_offset: self.uv_offset.into(),
+            uv_scale: self.uv_scale.into(),
+            uv_scroll: self.uv_scroll.into(),
+            tint_period: self.tint_period,
+            _padding: 0.0,
+            tint_a: self.tint_a,
+            tint_b: self.tint_b,
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Material {
     pub name: String,
     pub bind_group: wgpu::BindGroup,
+    animator: MaterialAnimator,
+    // Pick materials don't sample textures and have no animation buffer
+    animation_buffer: Option<wgpu::Buffer>,
+    animation_dirty: bool,
+    // The bind group keeps the textures and buffers alive, so their accounting stays here
+    pub(crate) memory: Vec<TrackedMemory>,
 }
 
 impl Material {
@@ -89,6 +182,12 @@ impl Material {
         let normal_texture_sampler = normal_texture
             .sampler
             .unwrap_or(create_default_sampler(device));
+        let animator = MaterialAnimator::default();
+        let animation_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Material Animation Buffer"),
+            contents: bytemuck::cast_slice(&[animator.to_raw()]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
         let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
             layout,
             entries: &[
@@ -109,18 +208,73 @@ impl Material {
                     binding: 3,
                     resource: wgpu::BindingResource::Sampler(&normal_texture_sampler),
                 },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: animation_buffer.as_entire_binding(),
+                },
             ],
             label: Some(name),
         });
+        let memory = vec![
+            diffuse_texture.memory,
+            normal_texture.memory,
+            TrackedMemory::buffer(&animation_buffer, name, MemoryCategory::Uniform),
+        ];
         Ok(Self {
             name: String::from(name),
             bind_group,
+            animator,
+            animation_buffer: Some(animation_buffer),
+            animation_dirty: false,
+            memory,
         })
     }
 
+    pub fn animator(&self) -> &MaterialAnimator {
+        &self.animator
+    }
+
+    /// Replaces all animation parameters. Takes effect after the next `write_to_buffer`.
+    pub fn set_animator(&mut self, animator: MaterialAnimator) {
+        self.animation_dirty |= self.animator != animator;
+        self.animator = animator;
+    }
+
+    /// Scrolls the texture by `rate` UV units per second.
+    pub fn set_uv_scroll(&mut self, rate: cgmath::Vector2<f32>) {
+        self.set_animator(MaterialAnimator {
+            uv_scroll: rate,
+            ..self.animator
+        });
+    }
+
+    /// Writes the animation uniform if any parameter changed since the last write.
+    pub fn write_to_buffer(&mut self, queue: &wgpu::Queue) {
+        if let (true, Some(buffer)) = (self.animation_dirty, &self.animation_buffer) {
+            profiling::write_buffer(
+                queue,
+                buffer,
+                0,
+                bytemuck::cast_slice(&[self.animator.to_raw()]),
+            );
+        }
+        self.animation_dirty = false;
+    }
+
     pub fn new_pick_material(device: &wgpu::Device, name: &str, buffer: wgpu::Buffer) -> Self {
+        Self::new_pick_material_with_layout(device, name, buffer, &pick_layout(device))
+    }
+
+    /// Like [`Material::new_pick_material`] bound to `layout`, usually
+    /// [`crate::context::Layouts::pick`].
+    pub fn new_pick_material_with_layout(
+        device: &wgpu::Device,
+        name: &str,
+        buffer: wgpu::Buffer,
+        layout: &wgpu::BindGroupLayout,
+    ) -> Self {
         let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &pick_layout(device),
+            layout,
             entries: &[
                 // Must match amount of bind groups in texture_bind_group_layout (hmmm not surprising...)
                 wgpu::BindGroupEntry {
@@ -138,6 +292,10 @@ impl Material {
         Self {
             name: String::from(name),
             bind_group,
+            animator: MaterialAnimator::default(),
+            animation_buffer: None,
+            animation_dirty: false,
+            memory: Vec::new(),
         }
     }
 }
@@ -149,14 +307,50 @@ pub struct Mesh {
     pub index_buffer: wgpu::Buffer,
     pub num_elements: u32,
     pub material: usize,
+    /// Object-space bounds of the vertices, `None` for empty meshes.
+    pub bounds: Option<Aabb>,
+    // only held so the buffers stay counted while the mesh is alive
+    #[allow(dead_code)]
+    pub(crate) memory: Vec<TrackedMemory>,
 }
 
-#[derive(Debug)]
+impl Mesh {
+    /// Accounting for a freshly uploaded vertex and index buffer pair.
+    pub(crate) fn track_buffers(
+        vertex_buffer: &wgpu::Buffer,
+        index_buffer: &wgpu::Buffer,
+        name: &str,
+    ) -> Vec<TrackedMemory> {
+        vec![
+            TrackedMemory::buffer(vertex_buffer, name, MemoryCategory::Vertex),
+            TrackedMemory::buffer(index_buffer, name, MemoryCategory::Index),
+        ]
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct Model {
     pub meshes: Vec<Mesh>,
     pub materials: Vec<Material>,
 }
 
+impl Model {
+    /// Uploads changed material animation parameters, see [`Material::write_to_buffer`].
+    pub fn write_materials(&mut self, queue: &wgpu::Queue) {
+        self.materials
+            .iter_mut()
+            .for_each(|material| material.write_to_buffer(queue));
+    }
+
+    /// Object-space bounds over all meshes, `None` if every mesh is empty.
+    pub fn bounds(&self) -> Option<Aabb> {
+        self.meshes
+            .iter()
+            .filter_map(|mesh| mesh.bounds)
+            .reduce(|a, b| a.union(&b))
+    }
+}
+
 pub trait DrawModel<'a> {
     fn draw_mesh(
         &mut self,
@@ -187,6 +381,15 @@ pub trait DrawModel<'a> {
         camera_bind_group: &'a wgpu::BindGroup,
         light_bind_group: &'a wgpu::BindGroup,
     );
+    /// Like `draw_model_instanced`, drawing every mesh in `material_override` if it's set.
+    fn draw_model_instanced_with(
+        &mut self,
+        model: &'a Model,
+        material_override: Option<&'a Material>,
+        instances: Range<u32>,
+        camera_bind_group: &'a wgpu::BindGroup,
+        light_bind_group: &'a wgpu::BindGroup,
+    );
 }
 
 impl<'a, 'b> DrawModel<'b> for wgpu::RenderPass<'a>
@@ -234,9 +437,20 @@ where
         instances: Range<u32>,
         camera_bind_group: &'b wgpu::BindGroup,
         light_bind_group: &'b wgpu::BindGroup,
+    ) {
+        self.draw_model_instanced_with(model, None, instances, camera_bind_group, light_bind_group);
+    }
+
+    fn draw_model_instanced_with(
+        &mut self,
+        model: &'b Model,
+        material_override: Option<&'b Material>,
+        instances: Range<u32>,
+        camera_bind_group: &'b wgpu::BindGroup,
+        light_bind_group: &'b wgpu::BindGroup,
     ) {
         for mesh in &model.meshes {
-            let material = &model.materials[mesh.material];
+            let material = material_override.unwrap_or(&model.materials[mesh.material]);
             self.draw_mesh_instanced(
                 mesh,
                 material,
@@ -331,3 +545,32 @@ where
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn material_uniform_matches_wgsl_layout() {
+        // vec2 offset, vec2 scale, vec2 scroll, f32 period (+pad), vec4 tint_a, vec4 tint_b
+        assert_eq!(std::mem::size_of::<MaterialUniform>(), 64);
+    }
+
+    #[test]
+    fn default_animator_is_identity() {
+        let raw = MaterialAnimator::default().to_raw();
+        assert_eq!(raw.uv_scale, [1.0, 1.0]);
+        assert_eq!(raw.uv_offset, [0.0, 0.0]);
+        assert_eq!(raw.uv_scroll, [0.0, 0.0]);
+        assert_eq!(raw.tint_a, [1.0; 4]);
+        assert_eq!(raw.tint_period, 0.0);
+    }
+
+    #[test]
+    fn tint_pulse_rejects_negative_period() {
+        let anim = MaterialAnimator::tint_pulse([1.0, 0.0, 0.0, 1.0], [0.0, 0.0, 1.0, 1.0], -2.0);
+        assert_eq!(anim.tint_period, 0.0);
+        assert_eq!(anim.tint_a, [1.0, 0.0, 0.0, 1.0]);
+        assert_eq!(anim.tint_b, [0.0, 0.0, 1.0, 1.0]);
+    }
+}
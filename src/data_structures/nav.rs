@@ -0,0 +1,717 @@
+//! Grid based navigation over a heightfield, e.g. to walk units to a clicked point.
+//!
+//! A [`NavGrid`] rasterizes the terrain into square cells on the xz plane. Cells steeper than
+//! the slope limit are never walkable, cells covered by a blocker (usually the world space
+//! [`Aabb`] of an instance) only until the blocker is removed again. [`NavGrid::find_path`]
+//! runs A* over the 8-neighbourhood without cutting corners of blocked cells and returns
+//! world space waypoints, which a [`Mover`] walks an [`Instance`] along in `on_update`.
+//!
+//! Everything runs on the CPU, enable the `nav` feature to use it.
+
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashMap, VecDeque},
+    fmt,
+    time::Duration,
+};
+
+use cgmath::{EuclideanSpace, InnerSpace, Point2, Point3, Rad, Vector3};
+
+use crate::{
+    camera::Ray,
+    data_structures::{aabb::Aabb, instance::Instance},
+    pick::PickId,
+};
+
+/// Column (x) and row (z) of a cell.
+pub type Cell = (usize, usize);
+
+// Bisection steps refining where a ray enters the heightfield
+const RAYCAST_REFINEMENTS: usize = 8;
+
+#[derive(Debug, Clone)]
+pub struct NavGrid {
+    /// Corner of the cell `(0, 0)` on the xz plane.
+    origin: Point2<f32>,
+    cell_size: f32,
+    columns: usize,
+    rows: usize,
+    // Terrain height at the cell centres
+    heights: Vec<f32>,
+    // Cells too steep to walk
+    steep: Vec<bool>,
+    blockers: HashMap<PickId, Aabb>,
+    // Blockers covering each cell
+    blocked: Vec<u32>,
+}
+
+impl NavGrid {
+    /// A flat grid of `columns` x `rows` cells at height 0.0 starting at `origin` (x, z).
+    pub fn flat(origin: Point2<f32>, cell_size: f32, columns: usize, rows: usize) -> Self {
+        Self::from_heights(origin, cell_size, columns, rows, |_, _| 0.0, Rad(0.0))
+    }
+
+    /**
+     * Samples the terrain `height` at (x, z) for every cell. Cells whose slope exceeds
+     * `max_slope` aren't walkable.
+     *
+     * The slope of a cell is taken from the heights at its corners, so a cliff between two
+     * cell centres blocks the cells on both sides.
+     */
+    pub fn from_heights(
+        origin: Point2<f32>,
+        cell_size: f32,
+        columns: usize,
+        rows: usize,
+        height: impl Fn(f32, f32) -> f32,
+        max_slope: impl Into<Rad<f32>>,
+    ) -> Self {
+        let max_gradient = max_slope.into().0.tan();
+        let corner = |column: usize, row: usize| {
+            height(
+                origin.x + column as f32 * cell_size,
+                origin.y + row as f32 * cell_size,
+            )
+        };
+        let mut heights = Vec::with_capacity(columns * rows);
+        let mut steep = Vec::with_capacity(columns * rows);
+        for row in 0..rows {
+            for column in 0..columns {
+                let x = origin.x + (column as f32 + 0.5) * cell_size;
+                let z = origin.y + (row as f32 + 0.5) * cell_size;
+                heights.push(height(x, z));
+                let [h00, h10, h01, h11] = [
+                    corner(column, row),
+                    corner(column + 1, row),
+                    corner(column, row + 1),
+                    corner(column + 1, row + 1),
+                ];
+                let dx = (h10 - h00 + h11 - h01) / (2.0 * cell_size);
+                let dz = (h01 - h00 + h11 - h10) / (2.0 * cell_size);
+                // Tolerance so a zero slope limit still allows flat ground
+                steep.push((dx * dx + dz * dz).sqrt() > max_gradient + 1e-6);
+            }
+        }
+        Self {
+            origin,
+            cell_size,
+            columns,
+            rows,
+            heights,
+            steep,
+            blockers: HashMap::new(),
+            blocked: vec![0; columns * rows],
+        }
+    }
+
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cell_size(&self) -> f32 {
+        self.cell_size
+    }
+
+    fn index(&self, (column, row): Cell) -> usize {
+        row * self.columns + column
+    }
+
+    /// The cell containing `point`, ignoring its height. `None` outside the grid.
+    pub fn cell_at(&self, point: Point3<f32>) -> Option<Cell> {
+        let column = ((point.x - self.origin.x) / self.cell_size).floor();
+        let row = ((point.z - self.origin.y) / self.cell_size).floor();
+        let inside = column >= 0.0
+            && row >= 0.0
+            && (column as usize) < self.columns
+            && (row as usize) < self.rows;
+        inside.then_some((column as usize, row as usize))
+    }
+
+    /// Centre of `cell` on the terrain.
+    pub fn cell_center(&self, cell: Cell) -> Point3<f32> {
+        Point3::new(
+            self.origin.x + (cell.0 as f32 + 0.5) * self.cell_size,
+            self.heights[self.index(cell)],
+            self.origin.y + (cell.1 as f32 + 0.5) * self.cell_size,
+        )
+    }
+
+    /// `point` moved onto the terrain of its cell, e.g. a floor hit from
+    /// [`crate::context::Context::ray_to_floor`]. `None` outside the grid.
+    pub fn on_terrain(&self, x: f32, z: f32) -> Option<Point3<f32>> {
+        let cell = self.cell_at(Point3::new(x, 0.0, z))?;
+        Some(Point3::new(x, self.heights[self.index(cell)], z))
+    }
+
+    pub fn is_walkable(&self, cell: Cell) -> bool {
+        let index = self.index(cell);
+        !self.steep[index] && self.blocked[index] == 0
+    }
+
+    /// Whether `point` is on the grid in a walkable cell.
+    pub fn is_walkable_at(&self, point: Point3<f32>) -> bool {
+        self.cell_at(point)
+            .is_some_and(|cell| self.is_walkable(cell))
+    }
+
+    /// Cells whose centre lies within the xz footprint of `aabb`.
+    fn covered_cells(&self, aabb: &Aabb) -> impl Iterator<Item = usize> + '_ {
+        let range = |min: f32, max: f32, origin: f32, count: usize| {
+            let first = ((min - origin) / self.cell_size - 0.5).ceil().max(0.0) as usize;
+            let last = ((max - origin) / self.cell_size - 0.5).floor();
+            let end = if last < 0.0 {
+                0
+            } else {
+                (last as usize + 1).min(count)
+            };
+            first..end
+        };
+        let columns = range(aabb.min.x, aabb.max.x, self.origin.x, self.columns);
+        let rows = range(aabb.min.z, aabb.max.z, self.origin.y, self.rows);
+        rows.flat_map(move |row| {
+            columns
+                .clone()
+                .map(move |column| row * self.columns + column)
+        })
+    }
+
+    /**
+     * Blocks the cells under `aabb` for the object `id`, replacing its previous blocker.
+     * Typically the world space bounds of an instance, see [`Aabb::transform`].
+     */
+    pub fn set_blocker(&mut self, id: impl Into<PickId>, aabb: Aabb) {
+        let id = id.into();
+        self.remove_blocker(id);
+        let cells: Vec<_> = self.covered_cells(&aabb).collect();
+        for index in cells {
+            self.blocked[index] += 1;
+        }
+        self.blockers.insert(id, aabb);
+    }
+
+    /// Unblocks the cells of `id`'s blocker. Returns whether there was one.
+    pub fn remove_blocker(&mut self, id: impl Into<PickId>) -> bool {
+        let Some(aabb) = self.blockers.remove(&id.into()) else {
+            return false;
+        };
+        let cells: Vec<_> = self.covered_cells(&aabb).collect();
+        for index in cells {
+            self.blocked[index] -= 1;
+        }
+        true
+    }
+
+    pub fn clear_blockers(&mut self) {
+        self.blockers.clear();
+        self.blocked.fill(0);
+    }
+
+    fn neighbours(&self, (column, row): Cell) -> impl Iterator<Item = (Cell, f32)> + '_ {
+        const DIRECTIONS: [(isize, isize); 8] = [
+            (1, 0),
+            (-1, 0),
+            (0, 1),
+            (0, -1),
+            (1, 1),
+            (1, -1),
+            (-1, 1),
+            (-1, -1),
+        ];
+        let offset = move |dx: isize, dz: isize| {
+            let column = column.checked_add_signed(dx)?;
+            let row = row.checked_add_signed(dz)?;
+            (column < self.columns && row < self.rows).then_some((column, row))
+        };
+        let walkable = move |dx, dz| offset(dx, dz).is_some_and(|cell| self.is_walkable(cell));
+        DIRECTIONS.into_iter().filter_map(move |(dx, dz)| {
+            let cell = offset(dx, dz).filter(|&cell| self.is_walkable(cell))?;
+            if dx != 0 && dz != 0 {
+                // No squeezing diagonally between two blocked cells
+                if !walkable(dx, 0) || !walkable(0, dz) {
+                    return None;
+                }
+                return Some((cell, std::f32::consts::SQRT_2));
+            }
+            Some((cell, 1.0))
+        })
+    }
+
+    /**
+     * Shortest path from `start` to `goal` through walkable cells, as waypoints in world
+     * space ending exactly at `goal` on the terrain. Waypoints along straight lines are
+     * merged.
+     *
+     * The start cell may be blocked, e.g. by the unit standing in it. Returns `None` if
+     * either point is off the grid, the goal isn't walkable or can't be reached.
+     */
+    pub fn find_path(&self, start: Point3<f32>, goal: Point3<f32>) -> Option<Vec<Point3<f32>>> {
+        let start_cell = self.cell_at(start)?;
+        let goal_cell = self.cell_at(goal)?;
+        if !self.is_walkable(goal_cell) {
+            return None;
+        }
+        let cells = self.search(start_cell, goal_cell)?;
+        let mut waypoints: Vec<_> = simplify(&cells)
+            .into_iter()
+            .skip(1)
+            .map(|cell| self.cell_center(cell))
+            .collect();
+        waypoints.pop();
+        waypoints.extend(self.on_terrain(goal.x, goal.z));
+        Some(waypoints)
+    }
+
+    /// A* from `start` to `goal`, both included.
+    fn search(&self, start: Cell, goal: Cell) -> Option<Vec<Cell>> {
+        // Octile distance, exact on an empty grid
+        let heuristic = |(column, row): Cell| {
+            let dx = column.abs_diff(goal.0) as f32;
+            let dz = row.abs_diff(goal.1) as f32;
+            dx.max(dz) + (std::f32::consts::SQRT_2 - 1.0) * dx.min(dz)
+        };
+        let mut open = BinaryHeap::from([Candidate {
+            estimate: heuristic(start),
+            cell: start,
+        }]);
+        let mut cost = HashMap::from([(start, 0.0)]);
+        let mut came_from = HashMap::new();
+        while let Some(Candidate { cell, estimate }) = open.pop() {
+            if cell == goal {
+                let mut path = vec![goal];
+                let mut cell = goal;
+                while let Some(&previous) = came_from.get(&cell) {
+                    path.push(previous);
+                    cell = previous;
+                }
+                path.reverse();
+                return Some(path);
+            }
+            let current = cost[&cell];
+            // Stale entry of a cell reached more cheaply since
+            if estimate > current + heuristic(cell) + 1e-4 {
+                continue;
+            }
+            for (next, step) in self.neighbours(cell) {
+                let next_cost = current + step;
+                if cost.get(&next).is_none_or(|&known| next_cost < known) {
+                    cost.insert(next, next_cost);
+                    came_from.insert(next, cell);
+                    open.push(Candidate {
+                        estimate: next_cost + heuristic(next),
+                        cell: next,
+                    });
+                }
+            }
+        }
+        None
+    }
+
+    /// Whether the straight line from `from` to `to` only crosses walkable cells, not
+    /// counting the cell of `from`.
+    pub fn is_path_clear(&self, from: Point3<f32>, to: Point3<f32>) -> bool {
+        let start = self.cell_at(from);
+        let offset = to - from;
+        // Quarter cell steps, so lines can't skip a cell
+        let steps = (4.0 * offset.magnitude() / self.cell_size).ceil().max(1.0) as usize;
+        (1..=steps).all(|step| {
+            let point = from + offset * (step as f32 / steps as f32);
+            let cell = self.cell_at(point);
+            cell.is_some_and(|cell| Some(cell) == start || self.is_walkable(cell))
+        })
+    }
+
+    /// Where `ray` first hits the terrain of the grid, e.g. the mouse ray from
+    /// [`crate::camera::Camera::cast_ray_from_mouse`].
+    pub fn raycast(&self, ray: &Ray) -> Option<Point3<f32>> {
+        let (low, high) = self
+            .heights
+            .iter()
+            .fold((f32::INFINITY, f32::NEG_INFINITY), |(low, high), &h| {
+                (low.min(h), high.max(h))
+            });
+        let bounds = Aabb::new(
+            Point3::new(self.origin.x, low, self.origin.y),
+            Point3::new(
+                self.origin.x + self.columns as f32 * self.cell_size,
+                high,
+                self.origin.y + self.rows as f32 * self.cell_size,
+            ),
+        );
+        let enter = ray.intersect_aabb(&bounds)?;
+        let below = |distance: f32| {
+            let p = ray.origin + ray.direction * distance;
+            self.cell_at(p)
+                .map(|cell| p.y <= self.heights[self.index(cell)])
+        };
+        // March half a cell at a time until below the terrain, then bisect the step
+        let step = 0.5 * self.cell_size / ray.direction.magnitude();
+        let mut distance = enter;
+        loop {
+            match below(distance)? {
+                true => break,
+                false => distance += step,
+            }
+        }
+        if distance == enter {
+            return Some(ray.origin + ray.direction * enter);
+        }
+        let (mut above, mut under) = (distance - step, distance);
+        for _ in 0..RAYCAST_REFINEMENTS {
+            let mid = (above + under) / 2.0;
+            if below(mid).unwrap_or(false) {
+                under = mid;
+            } else {
+                above = mid;
+            }
+        }
+        Some(ray.origin + ray.direction * under)
+    }
+}
+
+/// Open cell of the A* search, ordered so the heap pops the lowest estimate first.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Candidate {
+    estimate: f32,
+    cell: Cell,
+}
+
+impl Eq for Candidate {}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .estimate
+            .total_cmp(&self.estimate)
+            .then_with(|| self.cell.cmp(&other.cell))
+    }
+}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// `cells` without those continuing a straight line.
+fn simplify(cells: &[Cell]) -> Vec<Cell> {
+    let direction = |a: Cell, b: Cell| (b.0 as isize - a.0 as isize, b.1 as isize - a.1 as isize);
+    let mut simplified: Vec<Cell> = cells.iter().copied().take(1).collect();
+    for window in cells.windows(3) {
+        if direction(window[0], window[1]) != direction(window[1], window[2]) {
+            simplified.push(window[1]);
+        }
+    }
+    if cells.len() > 1 {
+        simplified.extend(cells.last());
+    }
+    simplified
+}
+
+/// Result of advancing a [`Mover`] for a frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoveStatus {
+    /// No path to follow.
+    Idle,
+    Moving,
+    /// Reached the goal this frame.
+    Arrived,
+    /// The path became blocked and no other way to the goal was found.
+    Blocked,
+}
+
+type MoveCallback = Box<dyn FnMut(&Instance)>;
+
+/**
+ * Walks an [`Instance`] along a path of a [`NavGrid`] at a constant speed.
+ *
+ * ```ignore
+ * // on_window_events: walk to the clicked point
+ * let goal = ctx.ray_to_floor().and_then(|p| self.grid.on_terrain(p.x, p.y));
+ * if let Some(goal) = goal {
+ *     self.mover.move_to(&self.grid, &self.unit, goal);
+ * }
+ * // on_update
+ * self.mover.advance(&self.grid, &mut self.unit, dt);
+ * ```
+ *
+ * Blockers added while moving are noticed on the next [`Mover::advance`], which then looks
+ * for another way to the goal.
+ */
+pub struct Mover {
+    /// World units per second.
+    pub speed: f32,
+    path: VecDeque<Point3<f32>>,
+    goal: Option<Point3<f32>>,
+    on_arrival: Option<MoveCallback>,
+    on_blocked: Option<MoveCallback>,
+}
+
+impl fmt::Debug for Mover {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Mover")
+            .field("speed", &self.speed)
+            .field("path", &self.path)
+            .field("goal", &self.goal)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Mover {
+    pub fn new(speed: f32) -> Self {
+        Self {
+            speed,
+            path: VecDeque::new(),
+            goal: None,
+            on_arrival: None,
+            on_blocked: None,
+        }
+    }
+
+    /// Called with the instance once it reached the goal.
+    pub fn on_arrival(mut self, callback: impl FnMut(&Instance) + 'static) -> Self {
+        self.on_arrival = Some(Box::new(callback));
+        self
+    }
+
+    /// Called with the instance where it stopped because the goal became unreachable.
+    pub fn on_blocked(mut self, callback: impl FnMut(&Instance) + 'static) -> Self {
+        self.on_blocked = Some(Box::new(callback));
+        self
+    }
+
+    /// Starts walking `instance` to `goal`. Returns `false` and stops if there's no path.
+    pub fn move_to(&mut self, grid: &NavGrid, instance: &Instance, goal: Point3<f32>) -> bool {
+        let start = Point3::from_vec(instance.position);
+        match grid.find_path(start, goal) {
+            Some(path) => {
+                self.path = path.into();
+                self.goal = Some(goal);
+                true
+            }
+            None => {
+                self.stop();
+                false
+            }
+        }
+    }
+
+    pub fn stop(&mut self) {
+        self.path.clear();
+        self.goal = None;
+    }
+
+    pub fn is_moving(&self) -> bool {
+        !self.path.is_empty()
+    }
+
+    /// Waypoints left to walk, the last one being the goal.
+    pub fn path(&self) -> impl Iterator<Item = &Point3<f32>> {
+        self.path.iter()
+    }
+
+    /// Moves `instance` by `speed * dt` along the path.
+    pub fn advance(&mut self, grid: &NavGrid, instance: &mut Instance, dt: Duration) -> MoveStatus {
+        let Some(goal) = self.goal else {
+            return MoveStatus::Idle;
+        };
+        let position = Point3::from_vec(instance.position);
+        let clear = std::iter::once(&position)
+            .chain(&self.path)
+            .zip(&self.path)
+            .all(|(&from, &to)| grid.is_path_clear(from, to));
+        if !clear && !self.move_to(grid, instance, goal) {
+            if let Some(callback) = &mut self.on_blocked {
+                callback(instance);
+            }
+            return MoveStatus::Blocked;
+        }
+        let mut budget = self.speed * dt.as_secs_f32();
+        while let Some(&waypoint) = self.path.front() {
+            let to_waypoint: Vector3<f32> = waypoint - Point3::from_vec(instance.position);
+            let distance = to_waypoint.magnitude();
+            if distance > budget {
+                instance.position += to_waypoint * (budget / distance);
+                return MoveStatus::Moving;
+            }
+            instance.position = waypoint.to_vec();
+            budget -= distance;
+            self.path.pop_front();
+        }
+        self.goal = None;
+        if let Some(callback) = &mut self.on_arrival {
+            callback(instance);
+        }
+        MoveStatus::Arrived
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell as Counter, rc::Rc};
+
+    use cgmath::Deg;
+
+    use super::*;
+
+    /// 5 x 5 grid of unit cells with a wall at column 2 leaving row 4 open.
+    fn walled_grid() -> NavGrid {
+        let mut grid = NavGrid::flat(Point2::new(0.0, 0.0), 1.0, 5, 5);
+        grid.set_blocker(
+            1,
+            Aabb::new(Point3::new(2.1, 0.0, 0.0), Point3::new(2.9, 1.0, 3.9)),
+        );
+        grid
+    }
+
+    fn cells(grid: &NavGrid, path: &[Point3<f32>]) -> Vec<Cell> {
+        path.iter().map(|&p| grid.cell_at(p).unwrap()).collect()
+    }
+
+    fn length(start: Point3<f32>, path: &[Point3<f32>]) -> f32 {
+        std::iter::once(&start)
+            .chain(path)
+            .zip(path)
+            .map(|(a, b)| (b - a).magnitude())
+            .sum()
+    }
+
+    #[test]
+    fn path_goes_around_the_wall() {
+        let grid = walled_grid();
+        let start = grid.cell_center((0, 0));
+        let goal = Point3::new(4.2, 0.0, 0.3);
+        let path = grid.find_path(start, goal).unwrap();
+        assert_eq!(path.last(), Some(&goal));
+        assert!(
+            std::iter::once(&start)
+                .chain(&path)
+                .zip(&path)
+                .all(|(&from, &to)| grid.is_path_clear(from, to))
+        );
+
+        // Through the gap in row 4 without cutting the wall's corners
+        let path = grid.find_path(start, grid.cell_center((4, 0))).unwrap();
+        let shortest = 2.0 * (std::f32::consts::SQRT_2 + 3.0) + 2.0;
+        assert!((length(start, &path) - shortest).abs() < 1e-4);
+        // Straight stretches are merged into single waypoints
+        assert!(path.len() < 8, "{:?}", cells(&grid, &path));
+    }
+
+    #[test]
+    fn enclosed_or_blocked_goals_have_no_path() {
+        let mut grid = walled_grid();
+        let start = grid.cell_center((0, 0));
+        assert!(grid.find_path(start, grid.cell_center((2, 1))).is_none());
+
+        grid.set_blocker(
+            2,
+            Aabb::new(Point3::new(2.1, 0.0, 4.1), Point3::new(2.9, 1.0, 4.9)),
+        );
+        assert!(grid.find_path(start, grid.cell_center((4, 0))).is_none());
+        assert!(grid.find_path(start, Point3::new(9.0, 0.0, 0.0)).is_none());
+
+        assert!(grid.remove_blocker(2));
+        assert!(grid.find_path(start, grid.cell_center((4, 0))).is_some());
+        assert!(!grid.remove_blocker(2));
+    }
+
+    #[test]
+    fn moving_a_blocker_frees_its_old_cells() {
+        let mut grid = walled_grid();
+        grid.set_blocker(
+            1,
+            Aabb::new(Point3::new(0.2, 0.0, 0.2), Point3::new(0.8, 1.0, 0.8)),
+        );
+        assert!(!grid.is_walkable((0, 0)));
+        assert!(grid.is_walkable((2, 0)));
+        let path = grid
+            .find_path(grid.cell_center((1, 0)), grid.cell_center((4, 0)))
+            .unwrap();
+        assert_eq!(cells(&grid, &path), [(4, 0)]);
+    }
+
+    #[test]
+    fn steep_cells_are_not_walkable() {
+        // A ramp rising 2 units per unit along x from column 2 on
+        let ramp = |x: f32, _z: f32| (x - 2.0).max(0.0) * 2.0;
+        let grid = NavGrid::from_heights(Point2::new(0.0, 0.0), 1.0, 4, 2, ramp, Deg(45.0));
+        assert!(grid.is_walkable((0, 0)));
+        assert!(grid.is_walkable((1, 1)));
+        assert!(!grid.is_walkable((2, 0)));
+        assert!(!grid.is_walkable((3, 1)));
+        assert_eq!(grid.cell_center((3, 0)).y, 3.0);
+
+        let gentle = NavGrid::from_heights(Point2::new(0.0, 0.0), 1.0, 4, 2, ramp, Deg(70.0));
+        assert!(gentle.is_walkable((3, 1)));
+    }
+
+    #[test]
+    fn raycast_hits_the_terrain() {
+        let step = |x: f32, _z: f32| if x >= 2.0 { 1.0 } else { 0.0 };
+        let grid = NavGrid::from_heights(Point2::new(0.0, 0.0), 1.0, 4, 4, step, Deg(90.0));
+        let down = |x, z| Ray {
+            origin: Point3::new(x, 10.0, z),
+            direction: Vector3::new(0.0, -1.0, 0.0),
+        };
+        let low = grid.raycast(&down(0.5, 0.5)).unwrap();
+        assert!(low.y.abs() < 1e-3, "{low:?}");
+        let high = grid.raycast(&down(3.5, 0.5)).unwrap();
+        assert!((high.y - 1.0).abs() < 1e-3, "{high:?}");
+        assert!(grid.raycast(&down(5.0, 0.5)).is_none());
+
+        let slanted = Ray {
+            origin: Point3::new(-1.0, 2.0, 0.5),
+            direction: Vector3::new(1.0, -1.0, 0.0),
+        };
+        let hit = grid.raycast(&slanted).unwrap();
+        assert!((hit.x - 1.0).abs() < 0.01 && hit.y.abs() < 0.01, "{hit:?}");
+    }
+
+    #[test]
+    fn mover_arrives_at_the_goal() {
+        let grid = walled_grid();
+        let arrivals = Rc::new(Counter::new(0));
+        let counter = arrivals.clone();
+        let mut mover = Mover::new(2.0).on_arrival(move |_| counter.set(counter.get() + 1));
+        let mut unit = Instance::new();
+        unit.position = Vector3::new(0.5, 0.0, 0.5);
+        let goal = grid.cell_center((4, 0));
+        assert!(mover.move_to(&grid, &unit, goal));
+
+        let frame = Duration::from_millis(100);
+        let mut frames = 0;
+        while mover.advance(&grid, &mut unit, frame) == MoveStatus::Moving {
+            frames += 1;
+            assert!(grid.is_walkable_at(Point3::from_vec(unit.position)));
+        }
+        assert!(frames > 0);
+        assert_eq!(Point3::from_vec(unit.position), goal);
+        assert_eq!(arrivals.get(), 1);
+        assert_eq!(mover.advance(&grid, &mut unit, frame), MoveStatus::Idle);
+    }
+
+    #[test]
+    fn mover_reports_paths_blocked_on_the_way() {
+        let mut grid = walled_grid();
+        let blocked = Rc::new(Counter::new(false));
+        let flag = blocked.clone();
+        let mut mover = Mover::new(1.0).on_blocked(move |_| flag.set(true));
+        let mut unit = Instance::new();
+        unit.position = Vector3::new(0.5, 0.0, 0.5);
+        assert!(mover.move_to(&grid, &unit, grid.cell_center((4, 0))));
+        let frame = Duration::from_millis(100);
+        assert_eq!(mover.advance(&grid, &mut unit, frame), MoveStatus::Moving);
+
+        grid.set_blocker(
+            2,
+            Aabb::new(Point3::new(2.1, 0.0, 4.1), Point3::new(2.9, 1.0, 4.9)),
+        );
+        assert_eq!(mover.advance(&grid, &mut unit, frame), MoveStatus::Blocked);
+        assert!(blocked.get());
+        assert!(!mover.is_moving());
+    }
+}
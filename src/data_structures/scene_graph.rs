@@ -11,15 +11,22 @@ use log::warn;
 use wgpu::{Device, Queue, util::DeviceExt};
 
 use crate::{
-    context::GPUResource,
+    context::{Context, GPUResource},
     data_structures::{
+        aabb::Aabb,
+        block::{self, Interpolation},
         instance::{Instance, InstanceRaw},
         model::{self, DrawModel},
     },
     pick::PickId,
+    profiling,
     render::{Instanced, Render},
     resources::{
-        animation::Keyframes, load_model_obj, mesh::compute_tangents, pick::load_pick_model,
+        animation::Keyframes,
+        load_model_obj,
+        memory::{MemoryCategory, TrackedBuffer},
+        mesh::compute_tangents,
+        pick::load_pick_model,
     },
 };
 
@@ -158,12 +165,15 @@ pub fn to_scene_node(
                 });
                 let mat_idx = primitive.material().index().unwrap_or(0);
 
+                let name = mesh.name().unwrap_or("unknown_mesh").to_string();
                 meshes.push(model::Mesh {
-                    name: mesh.name().unwrap_or("unknown_mesh").to_string(),
+                    memory: model::Mesh::track_buffers(&vertex_buffer, &index_buffer, &name),
+                    name,
                     vertex_buffer,
                     index_buffer,
                     num_elements: indices.len() as u32,
                     material: mat_idx,
+                    bounds: Aabb::from_vertex_positions(vertices.iter().map(|v| v.position)),
                 });
             });
             /* TOOD: don't store all materials in one place (insert Walter White meme here)
@@ -338,6 +348,14 @@ pub trait SceneNode: Send {
 
     fn write_to_buffers(&mut self, queue: &wgpu::Queue, device: &wgpu::Device);
 
+    /// Like [`SceneNode::write_to_buffers`] but uploads the world transforms blended between
+    /// the last two ticks by [`Context::tick_alpha`], see
+    /// [`BuildingBlocks::set_interpolated`](crate::data_structures::block::BuildingBlocks::set_interpolated).
+    /// Call it every frame, e.g. in `on_update`, so new ticks are noticed.
+    fn write_to_buffers_interpolated(&mut self, ctx: &Context) {
+        self.write_to_buffers(&ctx.queue, &ctx.device);
+    }
+
     fn write_to_buffers_offset(
         &mut self,
         queue: &wgpu::Queue,
@@ -371,6 +389,16 @@ pub trait SceneNode: Send {
 
     fn get_renders(&self) -> Vec<Instanced<'_>>;
 
+    /// World-space bounds of this node and its children over all instances.
+    ///
+    /// Relies on the world transforms, so call [`SceneNode::update_world_transform_all`] first.
+    fn bounds(&self) -> Option<Aabb> {
+        self.get_children()
+            .iter()
+            .filter_map(|child| child.bounds())
+            .reduce(|a, b| a.union(&b))
+    }
+
     fn get_render_dir(&self) -> wgpu::FrontFace {
         wgpu::FrontFace::Ccw
     }
@@ -607,6 +635,12 @@ impl SceneNode for ContainerNode {
             .for_each(|child| child.write_to_buffers(queue, device));
     }
 
+    fn write_to_buffers_interpolated(&mut self, ctx: &Context) {
+        self.get_children_mut()
+            .iter_mut()
+            .for_each(|child| child.write_to_buffers_interpolated(ctx));
+    }
+
     fn draw<'a, 'pass>(
         &self,
         camera_bind_group: &'a wgpu::BindGroup,
@@ -732,10 +766,12 @@ impl SceneNode for ContainerNode {
 pub struct ModelNode {
     children: Vec<Box<dyn SceneNode>>,
     front_face: wgpu::FrontFace,
-    instance_buffer: wgpu::Buffer,
+    instance_buffer: TrackedBuffer,
     instances: Vec<(Instance, Instance)>,
     animations: Vec<ModelAnimation>,
     buffer_size_needs_change: bool,
+    // world transforms of the last two ticks while written interpolated
+    interpolation: Option<Interpolation>,
     hidden: bool,
     model: model::Model,
     id: PickId,
@@ -775,11 +811,15 @@ impl ModelNode {
             .map(Instance::to_raw)
             .collect::<Vec<_>>();
 
-        let instance_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Instance Buffer"),
-            contents: bytemuck::cast_slice(&instance_data),
-            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-        });
+        let instance_buffer = TrackedBuffer::init(
+            device,
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("Instance Buffer"),
+                contents: bytemuck::cast_slice(&instance_data),
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            },
+            MemoryCategory::Instance,
+        );
 
         let size_changed = false;
         let direction = wgpu::FrontFace::Ccw;
@@ -792,6 +832,7 @@ impl ModelNode {
             hidden: false,
             model: obj_model,
             buffer_size_needs_change: size_changed,
+            interpolation: None,
             animations,
             id: id.into(),
         }
@@ -873,6 +914,8 @@ impl SceneNode for ModelNode {
     }
 
     fn write_to_buffers(&mut self, queue: &wgpu::Queue, device: &wgpu::Device) {
+        self.model.write_materials(queue);
+        self.interpolation = None;
         if let Some((_, world)) = self.instances.first() {
             let det = world.to_matrix().determinant().signum();
             if det < 0.0 {
@@ -887,14 +930,19 @@ impl SceneNode for ModelNode {
             .map(|(_, world)| world.to_raw())
             .collect();
         if self.buffer_size_needs_change {
-            self.instance_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some("Instance Buffer"),
-                contents: bytemuck::cast_slice(&raw_instances),
-                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-            });
+            self.instance_buffer = TrackedBuffer::init(
+                device,
+                &wgpu::util::BufferInitDescriptor {
+                    label: Some("Instance Buffer"),
+                    contents: bytemuck::cast_slice(&raw_instances),
+                    usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                },
+                MemoryCategory::Instance,
+            );
             self.buffer_size_needs_change = false;
         } else {
-            queue.write_buffer(
+            profiling::write_buffer(
+                queue,
                 &self.instance_buffer,
                 0,
                 bytemuck::cast_slice(&raw_instances),
@@ -905,6 +953,36 @@ impl SceneNode for ModelNode {
             .for_each(|child| child.write_to_buffers(queue, device));
     }
 
+    fn write_to_buffers_interpolated(&mut self, ctx: &Context) {
+        self.model.write_materials(&ctx.queue);
+        let worlds = self.get_world_transforms();
+        if let Some(world) = worlds.first() {
+            let det = world.to_matrix().determinant().signum();
+            if det < 0.0 {
+                self.front_face = wgpu::FrontFace::Cw;
+            } else {
+                self.front_face = wgpu::FrontFace::Ccw;
+            }
+        }
+        let interpolation = self
+            .interpolation
+            .get_or_insert_with(Interpolation::default);
+        let changed = interpolation.advance(&worlds, ctx.ticks());
+        let range = block::upload_range(
+            &ctx.device,
+            &mut self.instance_buffer,
+            &mut self.buffer_size_needs_change,
+            interpolation.len(),
+            false,
+            Some(changed),
+        );
+        let raws = interpolation.raws(range.clone(), ctx.tick_alpha());
+        block::write_raws(&ctx.queue, &self.instance_buffer, range.start, &raws);
+        self.get_children_mut()
+            .iter_mut()
+            .for_each(|child| child.write_to_buffers_interpolated(ctx));
+    }
+
     fn draw<'a, 'b>(
         &self,
         camera_bind_group: &'a wgpu::BindGroup,
@@ -948,6 +1026,7 @@ impl SceneNode for ModelNode {
             instances: self.instances.clone(),
             hidden: self.hidden,
             buffer_size_needs_change: false,
+            interpolation: None,
             model: obj_model,
             animations: Vec::new(),
             id: id.into(),
@@ -987,10 +1066,30 @@ impl SceneNode for ModelNode {
                 amount: self.instances.len(),
                 front_face: self.front_face,
                 id: self.id,
+                cull_override: None,
+                visibility: None,
+                material_override: None,
+                pick_instances: false,
             }])
             .collect()
     }
 
+    fn bounds(&self) -> Option<Aabb> {
+        if self.hidden {
+            return None;
+        }
+        let own = self.model.bounds().into_iter().flat_map(|local| {
+            self.instances
+                .iter()
+                .map(move |(_, world)| local.transform(world))
+        });
+        self.children
+            .iter()
+            .filter_map(|child| child.bounds())
+            .chain(own)
+            .reduce(|a, b| a.union(&b))
+    }
+
     fn remove_instance(&mut self, idx: usize) -> (Instance, Instance) {
         if self.instances.len() == 1 {
             // If last one is removed keep children unchanged to make sure GLTF proportions stay intact
@@ -1073,6 +1172,8 @@ impl SceneNode for ModelNode {
         device: &wgpu::Device,
         offset: &Instance,
     ) {
+        self.model.write_materials(queue);
+        self.interpolation = None;
         if let Some((_, world)) = self.instances.first() {
             let det = (offset * world).to_matrix().determinant().signum();
             if det < 0.0 {
@@ -1087,14 +1188,19 @@ impl SceneNode for ModelNode {
             .map(|(_, world)| (offset * world).to_raw())
             .collect();
         if self.buffer_size_needs_change {
-            self.instance_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some("Instance Buffer"),
-                contents: bytemuck::cast_slice(&raw_instances),
-                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-            });
+            self.instance_buffer = TrackedBuffer::init(
+                device,
+                &wgpu::util::BufferInitDescriptor {
+                    label: Some("Instance Buffer"),
+                    contents: bytemuck::cast_slice(&raw_instances),
+                    usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                },
+                MemoryCategory::Instance,
+            );
             self.buffer_size_needs_change = false;
         } else {
-            queue.write_buffer(
+            profiling::write_buffer(
+                queue,
                 &self.instance_buffer,
                 0,
                 bytemuck::cast_slice(&raw_instances),
@@ -0,0 +1,517 @@
+//! Compact instance deltas, e.g. to sync [`BuildingBlocks`] over a socket.
+//!
+//! The sender captures an [`InstanceSnapshot`] every tick and sends the [`InstanceDelta`] to
+//! the previously sent one, holding only the instances that changed with positions, rotations
+//! and scales quantized to a [`Precision`]. The receiver applies deltas in order through
+//! [`BuildingBlocks::apply_delta`], starting from [`InstanceSnapshot::default`].
+//!
+//! ```ignore
+//! let mut sent = InstanceSnapshot::default();
+//! // every tick
+//! let mut snapshot = InstanceSnapshot::capture(&blocks);
+//! socket.send(&snapshot.diff(&sent).encode())?;
+//! sent = snapshot;
+//!
+//! // on the other end
+//! remote_blocks.apply_delta(&InstanceDelta::decode(&bytes)?)?;
+//! ```
+//!
+//! [`BuildingBlocks`]: crate::data_structures::block::BuildingBlocks
+//! [`BuildingBlocks::apply_delta`]: crate::data_structures::block::BuildingBlocks::apply_delta
+
+use std::ops::Range;
+
+use crate::{
+    data_structures::{block::BuildingBlocks, instance::Instance},
+    error::{Error, Result},
+};
+
+/// Version byte leading every encoded [`InstanceDelta`].
+pub const DELTA_VERSION: u8 = 1;
+
+/// Default for the number of instances a delta may resize the receiver to, see
+/// [`BuildingBlocks::set_max_delta_len`].
+///
+/// [`BuildingBlocks::set_max_delta_len`]: crate::data_structures::block::BuildingBlocks::set_max_delta_len
+pub const DEFAULT_MAX_DELTA_LEN: u32 = 1 << 20;
+
+const POSITION: u8 = 1;
+const ROTATION: u8 = 1 << 1;
+const SCALE: u8 = 1 << 2;
+
+/// Quantization steps of an [`InstanceDelta`]. Changes smaller than a step aren't sent.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Precision {
+    /// World units, a millimetre by default.
+    pub position: f32,
+    /// Of each quaternion component.
+    pub rotation: f32,
+    pub scale: f32,
+}
+
+impl Default for Precision {
+    fn default() -> Self {
+        Self {
+            position: 0.001,
+            rotation: 0.0001,
+            scale: 0.001,
+        }
+    }
+}
+
+/// Instances of a [`BuildingBlocks`] at one point in time, see the [module docs](self).
+#[derive(Debug, Clone, Default)]
+pub struct InstanceSnapshot {
+    /// Number of this snapshot, `0` for the empty snapshot deltas start from.
+    pub sequence: u64,
+    pub precision: Precision,
+    instances: Vec<Instance>,
+}
+
+/// Quantized components of one instance, `None` where they didn't change.
+#[derive(Debug, Clone, PartialEq)]
+struct Change {
+    index: u32,
+    position: Option<[i32; 3]>,
+    rotation: Option<[i32; 4]>,
+    scale: Option<[i32; 3]>,
+}
+
+/// Changes between two [`InstanceSnapshot`]s, see the [module docs](self).
+#[derive(Debug, Clone, PartialEq)]
+pub struct InstanceDelta {
+    /// Sequence of the snapshot the delta applies to.
+    pub base: u64,
+    /// Sequence of the snapshot the delta leads to.
+    pub sequence: u64,
+    /// Number of instances after applying the delta.
+    pub len: u32,
+    pub precision: Precision,
+    changes: Vec<Change>,
+}
+
+fn quantize<const N: usize>(values: [f32; N], step: f32) -> [i32; N] {
+    // `as` saturates values too large for the precision
+    values.map(|value| (value / step).round() as i32)
+}
+
+fn dequantize<const N: usize>(values: [i32; N], step: f32) -> [f32; N] {
+    values.map(|value| value as f32 * step)
+}
+
+/// `now` unless it equals `before`.
+fn changed<T: PartialEq>(now: T, before: Option<T>) -> Option<T> {
+    (before.as_ref() != Some(&now)).then_some(now)
+}
+
+/// Quantized position, rotation and scale of `instance`.
+fn quantized(instance: &Instance, precision: &Precision) -> ([i32; 3], [i32; 4], [i32; 3]) {
+    let rotation = instance.rotation;
+    (
+        quantize(instance.position.into(), precision.position),
+        quantize(
+            [rotation.s, rotation.v.x, rotation.v.y, rotation.v.z],
+            precision.rotation,
+        ),
+        quantize(instance.scale.into(), precision.scale),
+    )
+}
+
+impl InstanceSnapshot {
+    pub fn capture(blocks: &BuildingBlocks) -> Self {
+        Self::from_instances(blocks.instances().clone())
+    }
+
+    pub fn from_instances(instances: Vec<Instance>) -> Self {
+        Self {
+            instances,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_precision(mut self, precision: Precision) -> Self {
+        self.precision = precision;
+        self
+    }
+
+    pub fn instances(&self) -> &[Instance] {
+        &self.instances
+    }
+
+    /// The changes since `previous`, numbering `self` as the snapshot following it.
+    ///
+    /// Instances are compared after quantizing with `self.precision`, instances `previous`
+    /// doesn't have are sent in full.
+    pub fn diff(&mut self, previous: &InstanceSnapshot) -> InstanceDelta {
+        self.sequence = previous.sequence + 1;
+        let precision = self.precision;
+        let changes = self
+            .instances
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, instance)| {
+                let (position, rotation, scale) = quantized(instance, &precision);
+                let before = previous
+                    .instances
+                    .get(idx)
+                    .map(|instance| quantized(instance, &precision));
+                let change = Change {
+                    index: idx as u32,
+                    position: changed(position, before.map(|b| b.0)),
+                    rotation: changed(rotation, before.map(|b| b.1)),
+                    scale: changed(scale, before.map(|b| b.2)),
+                };
+                let unchanged = change.position.is_none()
+                    && change.rotation.is_none()
+                    && change.scale.is_none();
+                (!unchanged).then_some(change)
+            })
+            .collect();
+        InstanceDelta {
+            base: previous.sequence,
+            sequence: self.sequence,
+            len: self.instances.len() as u32,
+            precision,
+            changes,
+        }
+    }
+}
+
+impl InstanceDelta {
+    /// Number of instances that changed.
+    pub fn changed(&self) -> usize {
+        self.changes.len()
+    }
+
+    /// Writes the changes to `instances`, which are at snapshot `applied`, and moves
+    /// `applied` on. Returns the range of instances that changed or were added.
+    ///
+    /// Fails with [`Error::InvalidDelta`] if the delta has more than `max_len` instances,
+    /// as `len` comes from the wire.
+    pub(crate) fn apply(
+        &self,
+        applied: &mut u64,
+        instances: &mut Vec<Instance>,
+        max_len: u32,
+    ) -> Result<Range<usize>> {
+        if self.base != *applied {
+            return Err(Error::DeltaOutOfOrder {
+                applied: *applied,
+                base: self.base,
+            });
+        }
+        if self.len > max_len {
+            return Err(Error::InvalidDelta {
+                reason: format!("{} instances exceed the limit of {max_len}", self.len),
+            });
+        }
+        let len = self.len as usize;
+        let mut changed = match instances.len() < len {
+            true => instances.len()..len,
+            false => len..len,
+        };
+        instances.resize_with(len, Instance::new);
+        for change in &self.changes {
+            let idx = change.index as usize;
+            changed = changed.start.min(idx)..changed.end.max(idx + 1);
+            let instance = &mut instances[idx];
+            if let Some(position) = change.position {
+                instance.position = dequantize(position, self.precision.position).into();
+            }
+            if let Some(rotation) = change.rotation {
+                let [s, x, y, z] = dequantize(rotation, self.precision.rotation);
+                instance.rotation = cgmath::Quaternion::new(s, x, y, z);
+            }
+            if let Some(scale) = change.scale {
+                instance.scale = dequantize(scale, self.precision.scale).into();
+            }
+        }
+        *applied = self.sequence;
+        Ok(changed)
+    }
+
+    /// Little endian bytes led by [`DELTA_VERSION`], integers as variable length.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = vec![DELTA_VERSION];
+        write_varint(&mut bytes, self.base);
+        write_varint(&mut bytes, self.sequence);
+        write_varint(&mut bytes, self.len.into());
+        for step in [
+            self.precision.position,
+            self.precision.rotation,
+            self.precision.scale,
+        ] {
+            bytes.extend_from_slice(&step.to_le_bytes());
+        }
+        write_varint(&mut bytes, self.changes.len() as u64);
+        for change in &self.changes {
+            write_varint(&mut bytes, change.index.into());
+            let mask = change.position.map_or(0, |_| POSITION)
+                | change.rotation.map_or(0, |_| ROTATION)
+                | change.scale.map_or(0, |_| SCALE);
+            bytes.push(mask);
+            let components = change.position.iter().flatten();
+            let components = components.chain(change.rotation.iter().flatten());
+            for &value in components.chain(change.scale.iter().flatten()) {
+                write_varint(&mut bytes, zigzag(value));
+            }
+        }
+        bytes
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Reader { bytes };
+        let version = reader.byte()?;
+        if version != DELTA_VERSION {
+            return Err(Error::InvalidDelta {
+                reason: format!("unsupported version {version}"),
+            });
+        }
+        let base = reader.varint()?;
+        let sequence = reader.varint()?;
+        let len = reader.u32()?;
+        let precision = Precision {
+            position: reader.f32()?,
+            rotation: reader.f32()?,
+            scale: reader.f32()?,
+        };
+        let count = reader.varint()?;
+        let mut changes = Vec::new();
+        for _ in 0..count {
+            let index = reader.u32()?;
+            if index >= len {
+                return Err(Error::InvalidDelta {
+                    reason: format!("instance {index} is out of range of {len} instances"),
+                });
+            }
+            let mask = reader.byte()?;
+            changes.push(Change {
+                index,
+                position: (mask & POSITION != 0)
+                    .then(|| reader.components())
+                    .transpose()?,
+                rotation: (mask & ROTATION != 0)
+                    .then(|| reader.components())
+                    .transpose()?,
+                scale: (mask & SCALE != 0)
+                    .then(|| reader.components())
+                    .transpose()?,
+            });
+        }
+        if !reader.bytes.is_empty() {
+            return Err(Error::InvalidDelta {
+                reason: format!("{} trailing bytes", reader.bytes.len()),
+            });
+        }
+        Ok(Self {
+            base,
+            sequence,
+            len,
+            precision,
+            changes,
+        })
+    }
+}
+
+fn zigzag(value: i32) -> u64 {
+    ((value << 1) ^ (value >> 31)) as u32 as u64
+}
+
+fn unzigzag(value: u32) -> i32 {
+    (value >> 1) as i32 ^ -((value & 1) as i32)
+}
+
+fn write_varint(bytes: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        bytes.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl Reader<'_> {
+    fn truncated() -> Error {
+        Error::InvalidDelta {
+            reason: "truncated".to_string(),
+        }
+    }
+
+    fn byte(&mut self) -> Result<u8> {
+        let (&byte, rest) = self.bytes.split_first().ok_or_else(Self::truncated)?;
+        self.bytes = rest;
+        Ok(byte)
+    }
+
+    fn varint(&mut self) -> Result<u64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(Error::InvalidDelta {
+            reason: "varint too long".to_string(),
+        })
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        u32::try_from(self.varint()?).map_err(|_| Error::InvalidDelta {
+            reason: "integer out of range".to_string(),
+        })
+    }
+
+    fn f32(&mut self) -> Result<f32> {
+        let (bytes, rest) = self
+            .bytes
+            .split_first_chunk::<4>()
+            .ok_or_else(Self::truncated)?;
+        self.bytes = rest;
+        Ok(f32::from_le_bytes(*bytes))
+    }
+
+    fn components<const N: usize>(&mut self) -> Result<[i32; N]> {
+        let mut components = [0; N];
+        for component in &mut components {
+            *component = unzigzag(self.u32()?);
+        }
+        Ok(components)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::{Deg, Quaternion, Rotation3, Vector3, assert_relative_eq};
+
+    fn instance_at(x: f32) -> Instance {
+        let mut instance = Instance::new();
+        instance.position = Vector3::new(x, 0.5, -2.0);
+        instance
+    }
+
+    #[test]
+    fn only_changed_instances_are_sent() {
+        let mut first =
+            InstanceSnapshot::from_instances((0..100).map(|i| instance_at(i as f32)).collect());
+        let full = first.diff(&InstanceSnapshot::default());
+        assert_eq!((full.base, full.sequence, full.changed()), (0, 1, 100));
+
+        let mut instances = first.instances().to_vec();
+        instances[42].rotation = Quaternion::from_angle_y(Deg(90.0));
+        // below the precision
+        instances[7].position.x += 0.0001;
+        let mut second = InstanceSnapshot::from_instances(instances);
+        let delta = second.diff(&first);
+        assert_eq!((delta.base, delta.sequence, delta.changed()), (1, 2, 1));
+        assert_eq!(delta.changes[0].index, 42);
+        assert!(delta.changes[0].position.is_none());
+        assert!(delta.encode().len() < full.encode().len() / 50);
+    }
+
+    #[test]
+    fn deltas_round_trip_through_bytes() {
+        let mut moved = instance_at(-1234.5678);
+        moved.rotation = Quaternion::from_angle_z(Deg(-30.0));
+        moved.scale = Vector3::new(2.0, 0.25, 1.0);
+        let mut snapshot = InstanceSnapshot::from_instances(vec![instance_at(3.0), moved.clone()]);
+        let delta = snapshot.diff(&InstanceSnapshot::default());
+        let decoded = InstanceDelta::decode(&delta.encode()).unwrap();
+        assert_eq!(decoded, delta);
+
+        let mut applied = 0;
+        let mut instances = Vec::new();
+        let changed = decoded.apply(&mut applied, &mut instances, DEFAULT_MAX_DELTA_LEN);
+        assert_eq!(changed.unwrap(), 0..2);
+        assert_eq!(applied, 1);
+        assert_relative_eq!(instances[1].position, moved.position, epsilon = 0.001);
+        assert_relative_eq!(instances[1].rotation, moved.rotation, epsilon = 0.0001);
+        assert_relative_eq!(instances[1].scale, moved.scale, epsilon = 0.001);
+    }
+
+    #[test]
+    fn removed_instances_shrink_the_receiver() {
+        let mut first = InstanceSnapshot::from_instances(vec![instance_at(0.0), instance_at(1.0)]);
+        let full = first.diff(&InstanceSnapshot::default());
+        let mut second = InstanceSnapshot::from_instances(vec![instance_at(0.0)]);
+        let shrink = second.diff(&first);
+        assert_eq!((shrink.len, shrink.changed()), (1, 0));
+
+        let (mut applied, mut instances) = (0, Vec::new());
+        full.apply(&mut applied, &mut instances, DEFAULT_MAX_DELTA_LEN)
+            .unwrap();
+        let changed = shrink.apply(&mut applied, &mut instances, DEFAULT_MAX_DELTA_LEN);
+        assert!(changed.unwrap().is_empty());
+        assert_eq!(instances.len(), 1);
+    }
+
+    #[test]
+    fn out_of_order_deltas_are_rejected() {
+        let mut first = InstanceSnapshot::from_instances(vec![instance_at(0.0)]);
+        let full = first.diff(&InstanceSnapshot::default());
+        let mut second = InstanceSnapshot::from_instances(vec![instance_at(1.0)]);
+        let next = second.diff(&first);
+
+        let (mut applied, mut instances) = (0, Vec::new());
+        assert!(matches!(
+            next.apply(&mut applied, &mut instances, DEFAULT_MAX_DELTA_LEN),
+            Err(Error::DeltaOutOfOrder {
+                applied: 0,
+                base: 1
+            })
+        ));
+        assert!(instances.is_empty());
+        full.apply(&mut applied, &mut instances, DEFAULT_MAX_DELTA_LEN)
+            .unwrap();
+        let changed = next.apply(&mut applied, &mut instances, DEFAULT_MAX_DELTA_LEN);
+        assert_eq!(changed.unwrap(), 0..1);
+        // replaying a delta fails as well
+        assert!(
+            next.apply(&mut applied, &mut instances, DEFAULT_MAX_DELTA_LEN)
+                .is_err()
+        );
+        assert_relative_eq!(instances[0].position.x, 1.0);
+    }
+
+    #[test]
+    fn malformed_bytes_are_invalid_deltas() {
+        let mut snapshot = InstanceSnapshot::from_instances(vec![instance_at(0.0)]);
+        let bytes = snapshot.diff(&InstanceSnapshot::default()).encode();
+        for bytes in [&bytes[..bytes.len() - 1], &[DELTA_VERSION + 1], &[]] {
+            assert!(matches!(
+                InstanceDelta::decode(bytes),
+                Err(Error::InvalidDelta { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn oversized_deltas_are_rejected_before_allocating() {
+        // a handful of bytes claiming four billion instances
+        let mut bytes = vec![DELTA_VERSION, 0, 1];
+        write_varint(&mut bytes, u32::MAX.into());
+        bytes.extend_from_slice(&[0; 12]);
+        bytes.push(0);
+        let delta = InstanceDelta::decode(&bytes).unwrap();
+
+        let (mut applied, mut instances) = (0, Vec::new());
+        assert!(matches!(
+            delta.apply(&mut applied, &mut instances, DEFAULT_MAX_DELTA_LEN),
+            Err(Error::InvalidDelta { .. })
+        ));
+        assert_eq!((applied, instances.len()), (0, 0));
+    }
+
+    #[test]
+    fn zigzag_keeps_small_negatives_short() {
+        for value in [0, -1, 1, i32::MIN, i32::MAX, -64] {
+            assert_eq!(unzigzag(zigzag(value) as u32), value);
+        }
+        assert_eq!(zigzag(-1), 1);
+    }
+}
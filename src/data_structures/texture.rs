@@ -4,10 +4,67 @@
 //! and helper methods for creating depth textures, normal maps, and loading textures
 //! from image data.
 
-use anyhow::*;
 use image::{GenericImageView, ImageFormat, load_from_memory_with_format};
 
-use crate::pipelines::mipmapper::Mipmapper;
+use crate::{
+    error::{Error, Result},
+    pipelines::mipmapper::Mipmapper,
+    resources::memory::TrackedMemory,
+};
+
+/// Format and direction of the depth buffers of all passes, see
+/// [`Context::configure_depth`](crate::context::Context::configure_depth).
+///
+/// With `reversed_z` the projection maps the near plane to a depth of `1` and the far plane
+/// to `0`, which spreads the float precision of [`wgpu::TextureFormat::Depth32Float`] evenly
+/// over the distance and removes z-fighting far from the camera.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DepthConfig {
+    pub format: wgpu::TextureFormat,
+    pub reversed_z: bool,
+}
+
+impl Default for DepthConfig {
+    fn default() -> Self {
+        Self {
+            format: Texture::DEPTH_FORMAT,
+            reversed_z: false,
+        }
+    }
+}
+
+impl DepthConfig {
+    pub fn with_format(mut self, format: wgpu::TextureFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    pub fn with_reversed_z(mut self, reversed_z: bool) -> Self {
+        self.reversed_z = reversed_z;
+        self
+    }
+
+    /// `compare` as written for a standard depth range, flipped with reversed Z, e.g.
+    /// `LessEqual` becomes `GreaterEqual`.
+    pub fn compare(self, compare: wgpu::CompareFunction) -> wgpu::CompareFunction {
+        use wgpu::CompareFunction::*;
+        if !self.reversed_z {
+            return compare;
+        }
+        match compare {
+            Less => Greater,
+            LessEqual => GreaterEqual,
+            Greater => Less,
+            GreaterEqual => LessEqual,
+            other => other,
+        }
+    }
+
+    /// Depth of the far plane, which the depth buffers are cleared to.
+    pub fn far(self) -> f32 {
+        if self.reversed_z { 0.0 } else { 1.0 }
+    }
+}
 
 /// A GPU texture with a view and optional sampler.
 ///
@@ -21,6 +78,7 @@ pub struct Texture {
     pub texture: wgpu::Texture,
     pub view: wgpu::TextureView,
     pub sampler: Option<wgpu::Sampler>,
+    pub(crate) memory: TrackedMemory,
 }
 
 impl Texture {
@@ -59,7 +117,14 @@ impl Texture {
         texture.create_view(&wgpu::TextureViewDescriptor::default())
     }
 
-    pub fn create_depth_texture(device: &wgpu::Device, size: [u32; 2], label: &str, sample_count: u32) -> Self {
+    /// Depth texture of `format`, usually the [`DepthConfig::format`] of the context.
+    pub fn create_depth_texture(
+        device: &wgpu::Device,
+        size: [u32; 2],
+        label: &str,
+        sample_count: u32,
+        format: wgpu::TextureFormat,
+    ) -> Self {
         let size = wgpu::Extent3d {
             width: size[0].max(1),
             height: size[1].max(1),
@@ -71,9 +136,9 @@ impl Texture {
             mip_level_count: 1,
             sample_count,
             dimension: wgpu::TextureDimension::D2,
-            format: Self::DEPTH_FORMAT,
+            format,
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
-            view_formats: &[Self::DEPTH_FORMAT],
+            view_formats: &[format],
         };
         let texture = device.create_texture(&desc);
         let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
@@ -91,6 +156,7 @@ impl Texture {
         }));
 
         Self {
+            memory: TrackedMemory::texture(&texture, label),
             texture,
             view,
             sampler,
@@ -121,8 +187,9 @@ impl Texture {
             .map(|&u| u)
             .collect();
 
+        const LABEL: &str = "default normal map";
         let texture = device.create_texture(&wgpu::TextureDescriptor {
-            label: Some("default normal map"),
+            label: Some(LABEL),
             size,
             mip_level_count: 1,
             sample_count: 1,
@@ -151,6 +218,7 @@ impl Texture {
         let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
         let sampler = Some(create_default_sampler(device));
         Texture {
+            memory: TrackedMemory::texture(&texture, LABEL),
             texture,
             view,
             sampler,
@@ -174,11 +242,15 @@ impl Texture {
         is_normal_map: bool,
     ) -> Result<Self> {
         let img = match format {
-            None => image::load_from_memory(bytes)?,
+            None => image::load_from_memory(bytes),
             Some(fmt) => {
-                load_from_memory_with_format(bytes, ImageFormat::from_extension(fmt).unwrap())?
+                let format = ImageFormat::from_extension(fmt).ok_or_else(|| Error::Unsupported {
+                    feature: format!("image format {fmt} of {label}"),
+                })?;
+                load_from_memory_with_format(bytes, format)
             }
-        };
+        }
+        .map_err(|e| Error::decode(label, e))?;
         Self::from_image(device, queue, &img, Some(label), is_normal_map)
     }
 
@@ -190,8 +262,9 @@ impl Texture {
             depth_or_array_layers: 1,
         };
 
+        const LABEL: &str = "solid color texture";
         let texture = device.create_texture(&wgpu::TextureDescriptor {
-            label: Some("solid color texture"),
+            label: Some(LABEL),
             size,
             mip_level_count: 1,
             sample_count: 1,
@@ -220,6 +293,7 @@ impl Texture {
         let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
         let sampler = Some(create_default_sampler(device));
         Texture {
+            memory: TrackedMemory::texture(&texture, LABEL),
             texture,
             view,
             sampler,
@@ -234,6 +308,12 @@ impl Texture {
         is_normal_map: bool,
     ) -> Result<Self> {
         let dimensions = img.dimensions();
+        let max = device.limits().max_texture_dimension_2d;
+        Error::check_limit(
+            format_args!("size of texture {}", label.unwrap_or("image")),
+            dimensions.0.max(dimensions.1).into(),
+            max.into(),
+        )?;
         let rgba = img.to_rgba8();
 
         let mip_level_count = dimensions.0.min(dimensions.1).max(1).ilog2() + 1;
@@ -292,6 +372,7 @@ impl Texture {
         }));
 
         Ok(Self {
+            memory: TrackedMemory::texture(&texture, label.unwrap_or("image texture")),
             texture,
             view,
             sampler,
@@ -310,3 +391,25 @@ pub fn create_default_sampler(device: &wgpu::Device) -> wgpu::Sampler {
         ..Default::default()
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wgpu::CompareFunction;
+
+    #[test]
+    fn reversed_z_flips_comparisons_and_the_far_plane() {
+        let standard = DepthConfig::default();
+        assert_eq!(standard.compare(CompareFunction::Less), CompareFunction::Less);
+        assert_eq!(standard.far(), 1.0);
+
+        let reversed = standard.with_reversed_z(true);
+        assert_eq!(reversed.compare(CompareFunction::Less), CompareFunction::Greater);
+        assert_eq!(
+            reversed.compare(CompareFunction::LessEqual),
+            CompareFunction::GreaterEqual
+        );
+        assert_eq!(reversed.compare(CompareFunction::Always), CompareFunction::Always);
+        assert_eq!(reversed.far(), 0.0);
+    }
+}
@@ -0,0 +1,148 @@
+//! Which instances of a draw were rendered, see [`Context::visibility`].
+//!
+//! Flows can skip expensive logic such as AI, particle emitters or audio for instances the
+//! camera doesn't see. The sets are recorded while rendering, so during `on_update` they
+//! describe the previous frame.
+//!
+//! [`Context::visibility`]: crate::context::Context::visibility
+
+/// Bitset of the visible instances of a draw, indexed like its instances.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VisibilitySet {
+    words: Vec<u64>,
+    len: usize,
+    visible: usize,
+}
+
+impl VisibilitySet {
+    /// `len` instances that are all visible, e.g. of a draw without culling.
+    pub fn all(len: usize) -> Self {
+        Self::from_visible(len, 0..len)
+    }
+
+    /// `len` instances of which only `visible` are, indices out of range are ignored.
+    pub fn from_visible(len: usize, visible: impl IntoIterator<Item = usize>) -> Self {
+        let mut set = Self {
+            words: vec![0; len.div_ceil(64)],
+            len,
+            visible: 0,
+        };
+        for idx in visible {
+            set.insert(idx);
+        }
+        set
+    }
+
+    fn insert(&mut self, idx: usize) {
+        if idx >= self.len {
+            return;
+        }
+        let word = &mut self.words[idx / 64];
+        let bit = 1 << (idx % 64);
+        if *word & bit == 0 {
+            *word |= bit;
+            self.visible += 1;
+        }
+    }
+
+    /// Number of instances, visible or not.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn visible_count(&self) -> usize {
+        self.visible
+    }
+
+    pub fn hidden_count(&self) -> usize {
+        self.len - self.visible
+    }
+
+    /// Whether instance `idx` was rendered, `false` for instances the draw didn't have.
+    pub fn is_visible(&self, idx: usize) -> bool {
+        idx < self.len && self.words[idx / 64] & (1 << (idx % 64)) != 0
+    }
+
+    /// Indices of the visible instances in ascending order.
+    pub fn iter_visible(&self) -> impl Iterator<Item = usize> + '_ {
+        self.words.iter().enumerate().flat_map(|(word_idx, &word)| {
+            let mut bits = word;
+            std::iter::from_fn(move || {
+                (bits != 0).then(|| {
+                    let bit = bits.trailing_zeros() as usize;
+                    bits &= bits - 1;
+                    word_idx * 64 + bit
+                })
+            })
+        })
+    }
+
+    /// Position of instance `idx` among the visible ones, i.e. its index in a culled
+    /// instance buffer. `None` if it wasn't rendered.
+    pub(crate) fn drawn_index(&self, idx: usize) -> Option<usize> {
+        if !self.is_visible(idx) {
+            return None;
+        }
+        let (word, bit) = (idx / 64, idx % 64);
+        let before: u32 = self.words[..word].iter().map(|w| w.count_ones()).sum();
+        let below = self.words[word] & ((1 << bit) - 1);
+        Some((before + below.count_ones()) as usize)
+    }
+
+    /// Appends the instances of `other`, for several draws sharing a pick id.
+    pub(crate) fn append(&mut self, other: &VisibilitySet) {
+        let offset = self.len;
+        self.len += other.len;
+        self.words.resize(self.len.div_ceil(64), 0);
+        for idx in other.iter_visible() {
+            self.insert(offset + idx);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_and_iterates_visible_instances() {
+        let set = VisibilitySet::from_visible(130, [0, 3, 64, 129, 3, 500]);
+        assert_eq!(set.len(), 130);
+        assert_eq!(set.visible_count(), 4);
+        assert_eq!(set.hidden_count(), 126);
+        assert_eq!(set.iter_visible().collect::<Vec<_>>(), [0, 3, 64, 129]);
+        assert!(set.is_visible(64));
+        assert!(!set.is_visible(65));
+        assert!(!set.is_visible(500));
+    }
+
+    #[test]
+    fn all_marks_every_instance_visible() {
+        let set = VisibilitySet::all(70);
+        assert_eq!(set.visible_count(), 70);
+        assert_eq!(set.iter_visible().count(), 70);
+        assert!(!set.is_visible(70));
+        assert!(VisibilitySet::all(0).is_empty());
+    }
+
+    #[test]
+    fn drawn_index_counts_the_visible_instances_before() {
+        let set = VisibilitySet::from_visible(130, [0, 3, 64, 129]);
+        let drawn: Vec<_> = [0, 3, 64, 129].map(|idx| set.drawn_index(idx)).into();
+        assert_eq!(drawn, [Some(0), Some(1), Some(2), Some(3)]);
+        assert_eq!(set.drawn_index(1), None);
+        assert_eq!(set.drawn_index(130), None);
+    }
+
+    #[test]
+    fn appended_sets_continue_the_indices() {
+        let mut set = VisibilitySet::from_visible(3, [1]);
+        set.append(&VisibilitySet::from_visible(64, [0, 63]));
+        assert_eq!(set.len(), 67);
+        assert_eq!(set.iter_visible().collect::<Vec<_>>(), [1, 3, 66]);
+    }
+}
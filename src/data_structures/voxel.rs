@@ -0,0 +1,862 @@
+//! Chunked voxel worlds with hidden-face culling.
+//!
+//! Unlike [`BuildingBlocks`](crate::data_structures::block::BuildingBlocks), which draws a
+//! full model per block, voxels are stored per [`VoxelChunk`] and meshed so that only faces
+//! bordering air are emitted. Only chunks whose voxels changed are remeshed.
+//!
+//! - [`VoxelGrid`] is the CPU side: chunk storage, `set_voxel`, streaming and ray casts
+//! - [`VoxelWorld`] owns the per-chunk GPU meshes and resolves picks to voxels
+//! - [`VoxelAtlas`] selects the texture atlas tile for each material and face
+
+use std::collections::{HashMap, HashSet};
+
+use cgmath::{EuclideanSpace, Point3, Vector3};
+
+use crate::{
+    camera::Ray,
+    context::GPUResource,
+    data_structures::{
+        builder::{MeshBuilder, ModelBuilder},
+        instance::Instance,
+        model::{self, ModelVertex},
+    },
+    pick::PickId,
+    profiling,
+    render::{Instanced, Render},
+    resources::{
+        memory::{MemoryCategory, TrackedBuffer},
+        mesh::compute_tangents,
+    },
+};
+
+/// Edge length of a chunk in voxels.
+pub const CHUNK_SIZE: i32 = 16;
+const CHUNK_VOLUME: usize = (CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE) as usize;
+
+/// Material id of empty space. Every other id is solid.
+pub const AIR: u16 = 0;
+
+/// Chunk coordinates, i.e. world voxel coordinates divided by [`CHUNK_SIZE`].
+pub type ChunkCoord = Point3<i32>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Face {
+    PosX,
+    NegX,
+    PosY,
+    NegY,
+    PosZ,
+    NegZ,
+}
+
+impl Face {
+    pub const ALL: [Face; 6] = [
+        Face::PosX,
+        Face::NegX,
+        Face::PosY,
+        Face::NegY,
+        Face::PosZ,
+        Face::NegZ,
+    ];
+
+    pub fn normal(self) -> Vector3<i32> {
+        match self {
+            Face::PosX => Vector3::new(1, 0, 0),
+            Face::NegX => Vector3::new(-1, 0, 0),
+            Face::PosY => Vector3::new(0, 1, 0),
+            Face::NegY => Vector3::new(0, -1, 0),
+            Face::PosZ => Vector3::new(0, 0, 1),
+            Face::NegZ => Vector3::new(0, 0, -1),
+        }
+    }
+
+    /// Corner of the quad plus the two edge vectors. `u × v` points along the normal so the
+    /// quad is counter-clockwise when seen from outside. On the sides `v` points up, so
+    /// textures stand upright.
+    fn quad(self) -> ([f32; 3], [f32; 3], [f32; 3]) {
+        match self {
+            Face::PosX => ([1.0, 0.0, 1.0], [0.0, 0.0, -1.0], [0.0, 1.0, 0.0]),
+            Face::NegX => ([0.0, 0.0, 0.0], [0.0, 0.0, 1.0], [0.0, 1.0, 0.0]),
+            Face::PosY => ([0.0, 1.0, 0.0], [0.0, 0.0, 1.0], [1.0, 0.0, 0.0]),
+            Face::NegY => ([0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]),
+            Face::PosZ => ([0.0, 0.0, 1.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+            Face::NegZ => ([1.0, 0.0, 0.0], [-1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+        }
+    }
+}
+
+/// Maps (material, face) pairs onto tiles of a texture atlas laid out as a grid.
+///
+/// Tiles are numbered row by row starting at the top left. Without an explicit mapping
+/// material `m` uses tile `m - 1` on every face.
+#[derive(Clone, Debug)]
+pub struct VoxelAtlas {
+    columns: u32,
+    rows: u32,
+    tiles: HashMap<(u16, Option<Face>), u32>,
+}
+
+impl VoxelAtlas {
+    pub fn new(columns: u32, rows: u32) -> Self {
+        Self {
+            columns: columns.max(1),
+            rows: rows.max(1),
+            tiles: HashMap::new(),
+        }
+    }
+
+    /// Uses `tile` for every face of `material`.
+    pub fn set_tile(&mut self, material: u16, tile: u32) {
+        self.tiles.insert((material, None), tile);
+    }
+
+    /// Uses `tile` for one face of `material`, e.g. grass on top and dirt on the sides.
+    pub fn set_face_tile(&mut self, material: u16, face: Face, tile: u32) {
+        self.tiles.insert((material, Some(face)), tile);
+    }
+
+    /// Returns the `[u_min, v_min, u_max, v_max]` rectangle of the tile used by `material` on `face`.
+    pub fn uv_rect(&self, material: u16, face: Face) -> [f32; 4] {
+        let tile = self
+            .tiles
+            .get(&(material, Some(face)))
+            .or(self.tiles.get(&(material, None)))
+            .copied()
+            .unwrap_or(material.saturating_sub(1) as u32)
+            % (self.columns * self.rows);
+        let (w, h) = (1.0 / self.columns as f32, 1.0 / self.rows as f32);
+        let (col, row) = ((tile % self.columns) as f32, (tile / self.columns) as f32);
+        [col * w, row * h, (col + 1.0) * w, (row + 1.0) * h]
+    }
+}
+
+/// A cube of `CHUNK_SIZE³` voxels, each storing a material id.
+#[derive(Clone, Debug)]
+pub struct VoxelChunk {
+    voxels: Vec<u16>,
+}
+
+impl Default for VoxelChunk {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl VoxelChunk {
+    /// Creates a chunk filled with [`AIR`].
+    pub fn new() -> Self {
+        Self {
+            voxels: vec![AIR; CHUNK_VOLUME],
+        }
+    }
+
+    fn index(local: Point3<i32>) -> Option<usize> {
+        let in_bounds = |c: i32| (0..CHUNK_SIZE).contains(&c);
+        (in_bounds(local.x) && in_bounds(local.y) && in_bounds(local.z))
+            .then(|| (local.x + local.y * CHUNK_SIZE + local.z * CHUNK_SIZE * CHUNK_SIZE) as usize)
+    }
+
+    /// Returns the material at chunk-local coordinates, or [`AIR`] outside of the chunk.
+    pub fn get(&self, local: Point3<i32>) -> u16 {
+        Self::index(local).map_or(AIR, |i| self.voxels[i])
+    }
+
+    /// Sets the material at chunk-local coordinates. Returns whether anything changed.
+    pub fn set(&mut self, local: Point3<i32>, material: u16) -> bool {
+        match Self::index(local) {
+            Some(i) if self.voxels[i] != material => {
+                self.voxels[i] = material;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.voxels.iter().all(|&v| v == AIR)
+    }
+
+    /**
+     * Emits one quad per voxel face that borders air.
+     *
+     * `solid_outside` is queried for neighbours outside of this chunk (in chunk-local
+     * coordinates) so faces between two loaded chunks are culled as well. Vertices are
+     * chunk-local; the chunk's instance places them in the world.
+     */
+    pub fn mesh(
+        &self,
+        atlas: &VoxelAtlas,
+        solid_outside: impl Fn(Point3<i32>) -> bool,
+    ) -> (Vec<ModelVertex>, Vec<u32>) {
+        let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+        let solid = |p: Point3<i32>| match Self::index(p) {
+            Some(i) => self.voxels[i] != AIR,
+            None => solid_outside(p),
+        };
+        for z in 0..CHUNK_SIZE {
+            for y in 0..CHUNK_SIZE {
+                for x in 0..CHUNK_SIZE {
+                    let p = Point3::new(x, y, z);
+                    let material = self.get(p);
+                    if material == AIR {
+                        continue;
+                    }
+                    for face in Face::ALL {
+                        if solid(p + face.normal()) {
+                            continue;
+                        }
+                        push_quad(
+                            &mut vertices,
+                            &mut indices,
+                            p,
+                            face,
+                            atlas.uv_rect(material, face),
+                        );
+                    }
+                }
+            }
+        }
+        compute_tangents(&mut vertices, &indices);
+        (vertices, indices)
+    }
+}
+
+fn push_quad(
+    vertices: &mut Vec<ModelVertex>,
+    indices: &mut Vec<u32>,
+    p: Point3<i32>,
+    face: Face,
+    [u0, v0, u1, v1]: [f32; 4],
+) {
+    let (corner, u, v) = face.quad();
+    let n = face.normal();
+    let base = vertices.len() as u32;
+    let origin = [
+        p.x as f32 + corner[0],
+        p.y as f32 + corner[1],
+        p.z as f32 + corner[2],
+    ];
+    let corners = [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)];
+    let tex = [[u0, v1], [u1, v1], [u1, v0], [u0, v0]];
+    for ((a, b), tex_coords) in corners.into_iter().zip(tex) {
+        vertices.push(ModelVertex {
+            position: [
+                origin[0] + u[0] * a + v[0] * b,
+                origin[1] + u[1] * a + v[1] * b,
+                origin[2] + u[2] * a + v[2] * b,
+            ],
+            tex_coords,
+            normal: [n.x as f32, n.y as f32, n.z as f32],
+            tangent: [0.0; 3],
+            bitangent: [0.0; 3],
+        });
+    }
+    indices.extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+}
+
+/// Splits world voxel coordinates into chunk coordinates and chunk-local coordinates.
+pub fn split_world_pos(world: Point3<i32>) -> (ChunkCoord, Point3<i32>) {
+    (
+        Point3::new(
+            world.x.div_euclid(CHUNK_SIZE),
+            world.y.div_euclid(CHUNK_SIZE),
+            world.z.div_euclid(CHUNK_SIZE),
+        ),
+        Point3::new(
+            world.x.rem_euclid(CHUNK_SIZE),
+            world.y.rem_euclid(CHUNK_SIZE),
+            world.z.rem_euclid(CHUNK_SIZE),
+        ),
+    )
+}
+
+/// Result of a voxel ray cast or a resolved pick.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VoxelHit {
+    pub chunk: ChunkCoord,
+    /// Chunk-local coordinates of the hit voxel.
+    pub voxel: Point3<i32>,
+    /// World coordinates of the hit voxel.
+    pub world: Point3<i32>,
+    /// The face the ray entered through, `None` if the ray started inside the voxel.
+    pub face: Option<Face>,
+    pub distance: f32,
+}
+
+/// CPU side of a voxel world: the loaded chunks and which of them need remeshing.
+#[derive(Clone, Debug, Default)]
+pub struct VoxelGrid {
+    chunks: HashMap<ChunkCoord, VoxelChunk>,
+    dirty: HashSet<ChunkCoord>,
+}
+
+impl VoxelGrid {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn chunk(&self, coord: ChunkCoord) -> Option<&VoxelChunk> {
+        self.chunks.get(&coord)
+    }
+
+    pub fn chunks(&self) -> impl Iterator<Item = (&ChunkCoord, &VoxelChunk)> {
+        self.chunks.iter()
+    }
+
+    /// Returns the material at world voxel coordinates, [`AIR`] for unloaded chunks.
+    pub fn voxel(&self, world: Point3<i32>) -> u16 {
+        let (chunk, local) = split_world_pos(world);
+        self.chunks.get(&chunk).map_or(AIR, |c| c.get(local))
+    }
+
+    /**
+     * Sets the material at world voxel coordinates, creating the chunk if needed.
+     *
+     * Marks the chunk dirty and, when the voxel lies on a chunk border, the neighbouring
+     * chunk as well since its culled faces may change.
+     */
+    pub fn set_voxel(&mut self, world: Point3<i32>, material: u16) {
+        let (coord, local) = split_world_pos(world);
+        let chunk = self.chunks.entry(coord).or_default();
+        if !chunk.set(local, material) {
+            return;
+        }
+        self.dirty.insert(coord);
+        for face in Face::ALL {
+            let (neighbour, _) = split_world_pos(world + face.normal());
+            if neighbour != coord && self.chunks.contains_key(&neighbour) {
+                self.dirty.insert(neighbour);
+            }
+        }
+    }
+
+    /// Inserts (or replaces) a whole chunk and marks it and its loaded neighbours dirty.
+    pub fn insert_chunk(&mut self, coord: ChunkCoord, chunk: VoxelChunk) {
+        self.chunks.insert(coord, chunk);
+        self.dirty.insert(coord);
+        self.mark_neighbours_dirty(coord);
+    }
+
+    pub fn remove_chunk(&mut self, coord: ChunkCoord) -> Option<VoxelChunk> {
+        let removed = self.chunks.remove(&coord);
+        self.dirty.remove(&coord);
+        if removed.is_some() {
+            self.mark_neighbours_dirty(coord);
+        }
+        removed
+    }
+
+    fn mark_neighbours_dirty(&mut self, coord: ChunkCoord) {
+        for face in Face::ALL {
+            let neighbour = coord + face.normal();
+            if self.chunks.contains_key(&neighbour) {
+                self.dirty.insert(neighbour);
+            }
+        }
+    }
+
+    /**
+     * Loads every chunk within `radius` chunks of `position` using `generate` and unloads
+     * chunks further away than `radius + 1` chunks. The one chunk of slack avoids loading
+     * and unloading the same chunks when the camera moves back and forth across a border.
+     *
+     * Unloaded chunks are dropped, so persist edits before they leave the radius.
+     */
+    pub fn stream_around(
+        &mut self,
+        position: Point3<f32>,
+        radius: i32,
+        mut generate: impl FnMut(ChunkCoord) -> VoxelChunk,
+    ) {
+        let (centre, _) = split_world_pos(Point3::new(
+            position.x.floor() as i32,
+            position.y.floor() as i32,
+            position.z.floor() as i32,
+        ));
+        let radius = radius.max(0);
+        let far: Vec<_> = self
+            .chunks
+            .keys()
+            .filter(|c| {
+                let d = *c - centre;
+                d.x.abs().max(d.y.abs()).max(d.z.abs()) > radius + 1
+            })
+            .copied()
+            .collect();
+        for coord in far {
+            self.remove_chunk(coord);
+        }
+        for z in -radius..=radius {
+            for y in -radius..=radius {
+                for x in -radius..=radius {
+                    let coord = centre + Vector3::new(x, y, z);
+                    if !self.chunks.contains_key(&coord) {
+                        self.insert_chunk(coord, generate(coord));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Meshes a single chunk, culling faces against its loaded neighbours.
+    pub fn mesh_chunk(
+        &self,
+        coord: ChunkCoord,
+        atlas: &VoxelAtlas,
+    ) -> Option<(Vec<ModelVertex>, Vec<u32>)> {
+        let origin = coord * CHUNK_SIZE;
+        self.chunks.get(&coord).map(|chunk| {
+            chunk.mesh(atlas, |local| {
+                self.voxel(Point3::from_vec(origin.to_vec() + local.to_vec())) != AIR
+            })
+        })
+    }
+
+    /// Takes the set of chunks changed since the last call.
+    pub fn take_dirty(&mut self) -> HashSet<ChunkCoord> {
+        std::mem::take(&mut self.dirty)
+    }
+
+    /// Returns the first solid voxel hit by `ray` within `max_distance`.
+    pub fn raycast(&self, ray: &Ray, max_distance: f32) -> Option<VoxelHit> {
+        self.raycast_filtered(ray, max_distance, |_| true)
+    }
+
+    /**
+     * Walks the voxels along `ray` (Amanatides & Woo) and returns the first solid voxel
+     * accepted by `accept`.
+     */
+    pub fn raycast_filtered(
+        &self,
+        ray: &Ray,
+        max_distance: f32,
+        accept: impl Fn(&VoxelHit) -> bool,
+    ) -> Option<VoxelHit> {
+        let o = ray.origin;
+        let d = ray.direction;
+        if !(d.x.is_finite() && d.y.is_finite() && d.z.is_finite())
+            || d == Vector3::new(0.0, 0.0, 0.0)
+        {
+            return None;
+        }
+        let mut cell = Point3::new(o.x.floor() as i32, o.y.floor() as i32, o.z.floor() as i32);
+        let axis = |origin: f32, dir: f32, cell: i32| -> (i32, f32, f32) {
+            if dir > 0.0 {
+                (1, (cell as f32 + 1.0 - origin) / dir, 1.0 / dir)
+            } else if dir < 0.0 {
+                (-1, (origin - cell as f32) / -dir, -1.0 / dir)
+            } else {
+                (0, f32::INFINITY, f32::INFINITY)
+            }
+        };
+        let (step_x, mut t_max_x, delta_x) = axis(o.x, d.x, cell.x);
+        let (step_y, mut t_max_y, delta_y) = axis(o.y, d.y, cell.y);
+        let (step_z, mut t_max_z, delta_z) = axis(o.z, d.z, cell.z);
+        let mut t = 0.0;
+        let mut face = None;
+        while t <= max_distance {
+            if self.voxel(cell) != AIR {
+                let (chunk, voxel) = split_world_pos(cell);
+                let hit = VoxelHit {
+                    chunk,
+                    voxel,
+                    world: cell,
+                    face,
+                    distance: t,
+                };
+                if accept(&hit) {
+                    return Some(hit);
+                }
+            }
+            if t_max_x <= t_max_y && t_max_x <= t_max_z {
+                cell.x += step_x;
+                t = t_max_x;
+                t_max_x += delta_x;
+                face = Some(if step_x > 0 { Face::NegX } else { Face::PosX });
+            } else if t_max_y <= t_max_z {
+                cell.y += step_y;
+                t = t_max_y;
+                t_max_y += delta_y;
+                face = Some(if step_y > 0 { Face::NegY } else { Face::PosY });
+            } else {
+                cell.z += step_z;
+                t = t_max_z;
+                t_max_z += delta_z;
+                face = Some(if step_z > 0 { Face::NegZ } else { Face::PosZ });
+            }
+        }
+        None
+    }
+}
+
+struct ChunkMesh {
+    model: model::Model,
+    instance: Instance,
+    instance_buffer: TrackedBuffer,
+    id: PickId,
+}
+
+/// A streamed voxel world rendered through the basic pipeline.
+///
+/// Each non-empty chunk becomes one mesh with its own [`PickId`] so GPU picking identifies
+/// the chunk, [`VoxelWorld::resolve_pick`] then finds the voxel with a CPU ray cast.
+/// Call [`GPUResource::write_to_buffer`] once per frame (e.g. in `on_update`) to remesh
+/// the chunks that changed.
+pub struct VoxelWorld {
+    pub grid: VoxelGrid,
+    atlas: VoxelAtlas,
+    material: model::Material,
+    meshes: HashMap<ChunkCoord, ChunkMesh>,
+    pick_ids: HashMap<PickId, ChunkCoord>,
+    next_id: u32,
+}
+
+impl VoxelWorld {
+    /**
+     * Creates an empty voxel world.
+     *
+     * `material` should hold the atlas texture described by `atlas`. Chunks get pick ids
+     * counting up from `first_id`, so reserve a range that doesn't collide with other flows.
+     */
+    pub fn new(first_id: impl Into<PickId>, material: model::Material, atlas: VoxelAtlas) -> Self {
+        Self {
+            grid: VoxelGrid::new(),
+            atlas,
+            material,
+            meshes: HashMap::new(),
+            pick_ids: HashMap::new(),
+            next_id: first_id.into().0,
+        }
+    }
+
+    pub fn set_voxel(&mut self, world: Point3<i32>, material: u16) {
+        self.grid.set_voxel(world, material);
+    }
+
+    pub fn voxel(&self, world: Point3<i32>) -> u16 {
+        self.grid.voxel(world)
+    }
+
+    /// See [`VoxelGrid::stream_around`], typically called with `ctx.camera.camera.position`.
+    pub fn stream_around(
+        &mut self,
+        position: Point3<f32>,
+        radius: i32,
+        generate: impl FnMut(ChunkCoord) -> VoxelChunk,
+    ) {
+        self.grid.stream_around(position, radius, generate);
+    }
+
+    /// Returns the chunk drawn with `id`.
+    pub fn chunk_for_pick(&self, id: PickId) -> Option<ChunkCoord> {
+        self.pick_ids.get(&id).copied()
+    }
+
+    /**
+     * Resolves a pick id from `on_click` to the clicked voxel.
+     *
+     * The GPU pick pass only knows the chunk, so the mouse ray (see
+     * `Camera::cast_ray_from_mouse`) is walked through that chunk to find the voxel.
+     */
+    pub fn resolve_pick(&self, id: PickId, ray: &Ray, max_distance: f32) -> Option<VoxelHit> {
+        let chunk = self.chunk_for_pick(id)?;
+        self.grid
+            .raycast_filtered(ray, max_distance, |hit| hit.chunk == chunk)
+    }
+
+    fn remesh(&mut self, device: &wgpu::Device) {
+        for coord in self.grid.take_dirty() {
+            let mesh = self
+                .grid
+                .mesh_chunk(coord, &self.atlas)
+                .filter(|(_, indices)| !indices.is_empty());
+            let Some((vertices, indices)) = mesh else {
+                if let Some(old) = self.meshes.remove(&coord) {
+                    self.pick_ids.remove(&old.id);
+                }
+                continue;
+            };
+            let label = format!("Voxel chunk {:?}", (coord.x, coord.y, coord.z));
+            // Tangents were computed while meshing
+            let model = ModelBuilder::new()
+                .mesh(MeshBuilder::new(label).vertices(vertices).indices(indices))
+                .material(self.material.clone())
+                .build(device);
+            let model = match model {
+                Ok(model) => model,
+                Err(e) => {
+                    log::warn!("Skipping voxel chunk {:?}: {}", coord, e);
+                    continue;
+                }
+            };
+            match self.meshes.get_mut(&coord) {
+                Some(existing) => existing.model = model,
+                None => {
+                    let id = PickId(self.next_id);
+                    self.next_id = self.next_id.wrapping_add(1);
+                    let instance = Instance::from(cgmath::Vector3::new(
+                        (coord.x * CHUNK_SIZE) as f32,
+                        (coord.y * CHUNK_SIZE) as f32,
+                        (coord.z * CHUNK_SIZE) as f32,
+                    ));
+                    let instance_buffer = TrackedBuffer::init(
+                        device,
+                        &wgpu::util::BufferInitDescriptor {
+                            label: Some("Voxel Chunk Instance Buffer"),
+                            contents: bytemuck::cast_slice(&[instance.to_raw()]),
+                            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                        },
+                        MemoryCategory::Instance,
+                    );
+                    self.pick_ids.insert(id, coord);
+                    self.meshes.insert(
+                        coord,
+                        ChunkMesh {
+                            model,
+                            instance,
+                            instance_buffer,
+                            id,
+                        },
+                    );
+                }
+            }
+        }
+        // Chunks unloaded without ever being dirty again still have meshes
+        let grid = &self.grid;
+        let pick_ids = &mut self.pick_ids;
+        self.meshes.retain(|coord, mesh| {
+            let keep = grid.chunk(*coord).is_some();
+            if !keep {
+                pick_ids.remove(&mesh.id);
+            }
+            keep
+        });
+    }
+}
+
+impl<'a, 'pass> GPUResource<'a, 'pass> for VoxelWorld {
+    fn write_to_buffer(&mut self, _queue: &wgpu::Queue, device: &wgpu::Device) {
+        self.remesh(device);
+    }
+
+    fn write_to_buffer_offset(
+        &mut self,
+        queue: &wgpu::Queue,
+        device: &wgpu::Device,
+        offset: &Instance,
+    ) {
+        self.remesh(device);
+        for mesh in self.meshes.values() {
+            let raw = (offset * &mesh.instance).to_raw();
+            profiling::write_buffer(queue, &mesh.instance_buffer, 0, bytemuck::cast_slice(&[raw]));
+        }
+    }
+
+    fn get_render(&'a self) -> Render<'a, 'pass> {
+        Render::Defaults(
+            self.meshes
+                .values()
+                .map(|mesh| Instanced {
+                    instance: &mesh.instance_buffer,
+                    model: &mesh.model,
+                    front_face: wgpu::FrontFace::Ccw,
+                    amount: 1,
+                    id: mesh.id,
+                    cull_override: None,
+                    visibility: None,
+                    material_override: None,
+                    pick_instances: false,
+                })
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::InnerSpace;
+
+    fn solid_chunk_with(voxels: &[(i32, i32, i32)]) -> VoxelChunk {
+        let mut chunk = VoxelChunk::new();
+        for &(x, y, z) in voxels {
+            chunk.set(Point3::new(x, y, z), 1);
+        }
+        chunk
+    }
+
+    // --- meshing ---
+
+    #[test]
+    fn single_voxel_has_six_faces() {
+        let chunk = solid_chunk_with(&[(0, 0, 0)]);
+        let (vertices, indices) = chunk.mesh(&VoxelAtlas::new(1, 1), |_| false);
+        assert_eq!(vertices.len(), 6 * 4);
+        assert_eq!(indices.len(), 6 * 6);
+    }
+
+    #[test]
+    fn adjacent_voxels_cull_shared_faces() {
+        let chunk = solid_chunk_with(&[(0, 0, 0), (1, 0, 0)]);
+        let (_, indices) = chunk.mesh(&VoxelAtlas::new(1, 1), |_| false);
+        assert_eq!(indices.len() / 6, 10);
+    }
+
+    #[test]
+    fn solid_neighbour_chunk_culls_border_face() {
+        let chunk = solid_chunk_with(&[(0, 0, 0)]);
+        let (_, indices) = chunk.mesh(&VoxelAtlas::new(1, 1), |p| p == Point3::new(-1, 0, 0));
+        assert_eq!(indices.len() / 6, 5);
+    }
+
+    #[test]
+    fn quads_wind_counter_clockwise_seen_from_outside() {
+        let chunk = solid_chunk_with(&[(0, 0, 0)]);
+        let (vertices, indices) = chunk.mesh(&VoxelAtlas::new(1, 1), |_| false);
+        for tri in indices.chunks(3) {
+            let [a, b, c] =
+                [tri[0], tri[1], tri[2]].map(|i| Vector3::from(vertices[i as usize].position));
+            let n = Vector3::from(vertices[tri[0] as usize].normal);
+            assert!((b - a).cross(c - a).dot(n) > 0.0);
+        }
+    }
+
+    #[test]
+    fn tex_coords_stand_upright_on_every_side() {
+        let chunk = solid_chunk_with(&[(0, 0, 0)]);
+        let (vertices, _) = chunk.mesh(&VoxelAtlas::new(1, 1), |_| false);
+        for quad in vertices.chunks(4) {
+            let at = |tex: [f32; 2]| {
+                let vertex = quad.iter().find(|v| v.tex_coords == tex).unwrap();
+                Vector3::from(vertex.position)
+            };
+            // texture right and up in world space, from the bottom left of the tile
+            let right = at([1.0, 1.0]) - at([0.0, 1.0]);
+            let up = at([0.0, 0.0]) - at([0.0, 1.0]);
+            let n = Vector3::from(quad[0].normal);
+            // not mirrored when seen from outside
+            assert_eq!(right.cross(up), n);
+            if n.y == 0.0 {
+                assert_eq!(up, Vector3::unit_y(), "face with normal {n:?} is rotated");
+            }
+        }
+    }
+
+    #[test]
+    fn atlas_selects_per_face_tiles() {
+        let mut atlas = VoxelAtlas::new(2, 2);
+        atlas.set_tile(1, 1);
+        atlas.set_face_tile(1, Face::PosY, 2);
+        assert_eq!(atlas.uv_rect(1, Face::PosX), [0.5, 0.0, 1.0, 0.5]);
+        assert_eq!(atlas.uv_rect(1, Face::PosY), [0.0, 0.5, 0.5, 1.0]);
+        // default mapping is material - 1
+        assert_eq!(atlas.uv_rect(4, Face::NegZ), [0.5, 0.5, 1.0, 1.0]);
+    }
+
+    // --- grid ---
+
+    #[test]
+    fn split_world_pos_handles_negative_coordinates() {
+        let (chunk, local) = split_world_pos(Point3::new(-1, 16, -17));
+        assert_eq!(chunk, Point3::new(-1, 1, -2));
+        assert_eq!(local, Point3::new(15, 0, 15));
+    }
+
+    #[test]
+    fn set_voxel_marks_only_changed_chunks_dirty() {
+        let mut grid = VoxelGrid::new();
+        grid.set_voxel(Point3::new(3, 3, 3), 1);
+        grid.set_voxel(Point3::new(40, 3, 3), 1);
+        grid.take_dirty();
+        grid.set_voxel(Point3::new(3, 3, 3), 2);
+        assert_eq!(grid.take_dirty(), HashSet::from([Point3::new(0, 0, 0)]));
+        // Unchanged material doesn't dirty anything
+        grid.set_voxel(Point3::new(3, 3, 3), 2);
+        assert!(grid.take_dirty().is_empty());
+    }
+
+    #[test]
+    fn set_voxel_on_border_dirties_neighbour() {
+        let mut grid = VoxelGrid::new();
+        grid.set_voxel(Point3::new(0, 0, 0), 1);
+        grid.set_voxel(Point3::new(-1, 0, 0), 1);
+        grid.take_dirty();
+        grid.set_voxel(Point3::new(0, 1, 0), 1);
+        assert_eq!(
+            grid.take_dirty(),
+            HashSet::from([Point3::new(0, 0, 0), Point3::new(-1, 0, 0)])
+        );
+    }
+
+    #[test]
+    fn mesh_chunk_culls_across_chunk_borders() {
+        let mut grid = VoxelGrid::new();
+        grid.set_voxel(Point3::new(0, 0, 0), 1);
+        grid.set_voxel(Point3::new(-1, 0, 0), 1);
+        let (_, indices) = grid
+            .mesh_chunk(Point3::new(0, 0, 0), &VoxelAtlas::new(1, 1))
+            .unwrap();
+        assert_eq!(indices.len() / 6, 5);
+    }
+
+    #[test]
+    fn stream_around_loads_and_unloads() {
+        let mut grid = VoxelGrid::new();
+        grid.stream_around(Point3::new(0.0, 0.0, 0.0), 1, |_| VoxelChunk::new());
+        assert_eq!(grid.chunks().count(), 27);
+        // Moving one chunk keeps the old ones within the slack
+        grid.stream_around(Point3::new(16.0, 0.0, 0.0), 1, |_| VoxelChunk::new());
+        assert_eq!(grid.chunks().count(), 36);
+        grid.stream_around(Point3::new(160.0, 0.0, 0.0), 1, |_| VoxelChunk::new());
+        assert_eq!(grid.chunks().count(), 27);
+        assert!(grid.chunk(Point3::new(0, 0, 0)).is_none());
+    }
+
+    // --- raycast ---
+
+    #[test]
+    fn raycast_hits_first_voxel_and_face() {
+        let mut grid = VoxelGrid::new();
+        grid.set_voxel(Point3::new(5, 0, 0), 1);
+        grid.set_voxel(Point3::new(8, 0, 0), 1);
+        let ray = Ray {
+            origin: Point3::new(0.5, 0.5, 0.5),
+            direction: Vector3::new(1.0, 0.0, 0.0),
+        };
+        let hit = grid.raycast(&ray, 100.0).unwrap();
+        assert_eq!(hit.world, Point3::new(5, 0, 0));
+        assert_eq!(hit.face, Some(Face::NegX));
+        assert!((hit.distance - 4.5).abs() < 1e-5);
+    }
+
+    #[test]
+    fn raycast_respects_max_distance() {
+        let mut grid = VoxelGrid::new();
+        grid.set_voxel(Point3::new(0, -10, 0), 1);
+        let ray = Ray {
+            origin: Point3::new(0.5, 0.5, 0.5),
+            direction: Vector3::new(0.0, -1.0, 0.0),
+        };
+        assert!(grid.raycast(&ray, 5.0).is_none());
+        let hit = grid.raycast(&ray, 20.0).unwrap();
+        assert_eq!(hit.face, Some(Face::PosY));
+        assert_eq!(hit.chunk, Point3::new(0, -1, 0));
+    }
+
+    #[test]
+    fn raycast_filtered_skips_other_chunks() {
+        let mut grid = VoxelGrid::new();
+        grid.set_voxel(Point3::new(10, 0, 0), 1);
+        grid.set_voxel(Point3::new(20, 0, 0), 1);
+        let ray = Ray {
+            origin: Point3::new(0.5, 0.5, 0.5),
+            direction: Vector3::new(1.0, 0.0, 0.0).normalize(),
+        };
+        let hit = grid
+            .raycast_filtered(&ray, 100.0, |h| h.chunk == Point3::new(1, 0, 0))
+            .unwrap();
+        assert_eq!(hit.world, Point3::new(20, 0, 0));
+        assert_eq!(hit.voxel, Point3::new(4, 0, 0));
+    }
+}
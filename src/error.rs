@@ -0,0 +1,163 @@
+//! Error type of the crate's public API.
+//!
+//! [`Error`] implements [`std::error::Error`], so `?` still converts it into an
+//! `anyhow::Error` where flows use anyhow.
+
+use std::fmt;
+
+/// Boxed cause of an [`Error`], e.g. an image or gltf decoding error.
+pub type Source = Box<dyn std::error::Error + Send + Sync + 'static>;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum Error {
+    /// The asset doesn't exist, relative to the `assets` directory (or URL on the web).
+    #[error("asset {path} not found")]
+    AssetNotFound { path: String },
+    /// The asset exists but couldn't be read, or a file like a recording couldn't be written.
+    #[error("failed to read asset {path}")]
+    Io {
+        path: String,
+        #[source]
+        source: Source,
+    },
+    /// The asset was read but its contents are invalid. For assets embedded in another file
+    /// the path names the embedding file and the material, e.g. `tree.gltf#bark`.
+    #[error("failed to decode asset {path}")]
+    Decode {
+        path: String,
+        #[source]
+        source: Source,
+    },
+    /// E.g. a texture larger than the device supports, `what` names the asset if there is
+    /// one.
+    #[error("{what} of {requested} exceeds the GPU limit of {max}")]
+    GpuLimit {
+        what: String,
+        requested: u64,
+        max: u64,
+    },
+    #[error("the window surface was lost")]
+    SurfaceLost,
+    #[error("{feature} is not supported")]
+    Unsupported { feature: String },
+    /// The adapter lacks features or limits of the
+    /// [`DeviceRequest`](crate::context::DeviceRequest), e.g. `feature POLYGON_MODE_LINE`.
+    #[error("the GPU adapter doesn't support {}", missing.join(", "))]
+    MissingCapabilities { missing: Vec<String> },
+    /// A WGSL shader failed to parse or validate.
+    #[error("shader {label} is invalid")]
+    Shader {
+        label: String,
+        #[source]
+        source: Source,
+    },
+    /// A shader expects other resources than the bind groups the engine sets for its draws,
+    /// see [`crate::pipelines::compat`]. Every mismatch is listed on its own line.
+    #[error(
+        "pipeline {label} doesn't match the bind groups set for {drawn_for}:\n{}",
+        mismatches.join("\n")
+    )]
+    IncompatiblePipeline {
+        label: String,
+        drawn_for: &'static str,
+        mismatches: Vec<String>,
+    },
+    /// Bytes that aren't an encoded [`InstanceDelta`](crate::data_structures::snapshot::InstanceDelta).
+    #[error("invalid instance delta: {reason}")]
+    InvalidDelta { reason: String },
+    /// An instance delta was applied out of order, e.g. after one was lost.
+    #[error("instance delta from snapshot {base} can't be applied to snapshot {applied}")]
+    DeltaOutOfOrder { applied: u64, base: u64 },
+    /// Assets of [`RunConfig::validate_assets`](crate::flow::RunConfig::validate_assets) are
+    /// missing or invalid, the report lists every one of them.
+    #[error("{} asset(s) failed to validate:\n{report}", report.problems.len())]
+    InvalidAssets {
+        report: crate::resources::validate::AssetReport,
+    },
+    /// Creating the surface, adapter or device failed.
+    #[error("failed to set up the GPU")]
+    Gpu(#[source] Source),
+    /// The platform rejected a window request, e.g. grabbing the cursor.
+    #[error("window request failed")]
+    Window(#[source] Source),
+    #[error("event loop failed")]
+    EventLoop(#[from] winit::error::EventLoopError),
+    /// An [`EventSender`](crate::flow::EventSender) posted after the event loop exited.
+    #[error("the event loop has exited")]
+    EventLoopClosed,
+}
+
+impl Error {
+    pub(crate) fn decode(path: impl fmt::Display, source: impl Into<Source>) -> Self {
+        Self::Decode {
+            path: path.to_string(),
+            source: source.into(),
+        }
+    }
+
+    /// Reading `path` failed with `source`, telling missing files apart.
+    pub(crate) fn read(path: &str, source: std::io::Error) -> Self {
+        match source.kind() {
+            std::io::ErrorKind::NotFound => Self::AssetNotFound {
+                path: path.to_string(),
+            },
+            _ => Self::Io {
+                path: path.to_string(),
+                source: source.into(),
+            },
+        }
+    }
+
+    /// Fails with [`Error::GpuLimit`] if `requested` exceeds `max`.
+    pub(crate) fn check_limit(what: impl fmt::Display, requested: u64, max: u64) -> Result<()> {
+        if requested > max {
+            return Err(Self::GpuLimit {
+                what: what.to_string(),
+                requested,
+                max,
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_files_are_not_found_errors() {
+        let missing = std::io::Error::from(std::io::ErrorKind::NotFound);
+        assert!(matches!(
+            Error::read("cube.obj", missing),
+            Error::AssetNotFound { path } if path == "cube.obj"
+        ));
+        let denied = std::io::Error::from(std::io::ErrorKind::PermissionDenied);
+        assert!(matches!(Error::read("cube.obj", denied), Error::Io { .. }));
+    }
+
+    #[test]
+    fn errors_keep_their_path_through_anyhow() {
+        let error = Error::decode("tree.gltf#bark", "invalid png");
+        let error = anyhow::Error::from(error);
+        assert_eq!(error.to_string(), "failed to decode asset tree.gltf#bark");
+        assert_eq!(error.root_cause().to_string(), "invalid png");
+        assert!(matches!(
+            error.downcast_ref::<Error>(),
+            Some(Error::Decode { .. })
+        ));
+    }
+
+    #[test]
+    fn limits_are_inclusive() {
+        assert!(Error::check_limit("texture width", 8192, 8192).is_ok());
+        let error = Error::check_limit("texture width", 8193, 8192).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "texture width of 8193 exceeds the GPU limit of 8192"
+        );
+    }
+}
@@ -9,6 +9,8 @@
 //!
 //! - [`GraphicsFlow<S, E>`] is the trait for scenes/states that handle events and rendering
 //! - [`Out<S, E>`] is the output type for async event handling and context configuration
+//! - [`RunConfig`] configures the window passed to [`run_with`]
+//! - [`run_simple`] runs flows without shared state or custom events, see [`NoEvent`]
 //!
 //! # Lifecycle Flow
 //!
@@ -21,38 +23,68 @@
 //! 6. Render to frame buffer using batched pipelines
 //! 7. Present frame
 
-use std::{collections::HashSet, fmt::Debug, iter, pin::Pin, sync::Arc};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Debug,
+    iter,
+    pin::Pin,
+    sync::Arc,
+    task::Poll,
+};
 
 use instant::{Duration, Instant};
 
-use cgmath::Rotation3;
+use cgmath::{Rotation3, Vector3};
 #[cfg(feature = "integration-tests")]
 use tokio::runtime::Runtime;
+
+#[cfg(feature = "integration-tests")]
+use crate::pick::{PickImage, PickRegion, read_pick_image, render_pick_buffer};
 use winit::{
     application::ApplicationHandler,
-    event::{DeviceEvent, DeviceId, MouseButton, WindowEvent},
-    event_loop::{ActiveEventLoop, EventLoop},
-    window::Window,
+    dpi::{PhysicalPosition, PhysicalSize, Size},
+    error::EventLoopError,
+    event::{
+        DeviceEvent, DeviceId, ElementState, MouseButton, MouseScrollDelta, TouchPhase, WindowEvent,
+    },
+    event_loop::{ActiveEventLoop, ControlFlow, EventLoop, EventLoopProxy},
+    keyboard::{KeyCode, PhysicalKey},
+    window::{Icon, Window, WindowAttributes},
 };
 
+#[cfg(all(feature = "gamepad", not(target_arch = "wasm32")))]
+use crate::gamepad::{GamepadAxis, GamepadButton, GamepadEvent, Gamepads};
+#[cfg(feature = "catch-flow-panics")]
+use crate::panic_guard::PanicGuard;
+#[cfg(target_arch = "wasm32")]
+use crate::pick::PickPurpose;
+#[cfg(feature = "record")]
+use crate::record::{RecordedInput, Replay, ReplayedInput, Session};
+#[cfg(feature = "record")]
+pub use crate::record::{Recorder, Recording};
+use crate::stream::{Job, LoadTicket};
 use crate::{
-    context::{Context, InitContext, MouseButtonState},
-    data_structures::{
-        model::{DrawLight, DrawModel},
-        texture::Texture,
-    },
-    pick::{PickId, draw_to_pick_buffer},
-    pipelines::transparent::{
-        mk_transparency_bind_group, mk_transparency_bind_group_layout, TransparencyUniform,
+    context::{
+        self, AdapterOptions, AnySender, Context, DebugRender, DeviceRequest, InitContext,
+        InputSettings, MAX_ON_DEMAND_DT, MouseButtonState, RenderStrategy, TextEvent,
     },
-    render::{Flat, Geometry, Instanced, Render},
+    data_structures::model::{DrawLight, DrawModel},
+    error::Error,
+    input::ActionMap,
+    loading::{LoadingScreen, ProgressTracker},
+    persist::{EngineSnapshot, Snapshot, SnapshotFn, SnapshotWriter},
+    pick::{HoverChange, PickHit, PickId, Picked, draw_to_pick_buffer},
+    pipelines::{overlay::OverlayUniform, transparent::mk_transparency_bind_group},
+    profiling::Hook,
+    render::{Batches, Layer, Layers, Render, collect_visibility},
+    resources::validate::validate_assets,
+    window::WindowId,
 };
 use wgpu::util::DeviceExt;
 
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
 
-
 ///
 /// This is the Output Type for every lifecycle hook where the user can pass async events that are
 /// handled according to the platform you're running on.
@@ -62,11 +94,43 @@ use wasm_bindgen::prelude::*;
 /// side effects unless handled.
 ///
 /// `Out::FutFn` can be used to directly modify the state and the mutation is handled internally with
-/// no further action required by the callee but is blocking on non-wasn environments.
+/// no further action required by the callee. The futures resolve in the background, on the tokio
+/// runtime on non-wasm environments, and the mutations are applied on the main thread in the
+/// order they were returned once all of them resolved. Frames keep rendering meanwhile.
+///
+/// `Out::FutFnLocal` is the same for futures or mutations that aren't `Send`, e.g. holding an
+/// `Rc`. It blocks on non-wasm environments until the futures resolved.
 ///
 /// `Out::Configure` can be used to modify the Context during runtime for instance to change the tick
 /// speed or the clear colour.
 ///
+/// `Out::ConfigureFut` resolves a future of such a modification first, e.g. to load a new
+/// skybox. The event loop polls it while frames keep rendering, as it needn't be `Send`.
+/// Modifications are applied between frames in the order they were returned, even if their
+/// futures resolve in another order. A failed future leaves the Context untouched, its error
+/// is logged and passed to [`RunConfig::with_error_handler`].
+///
+/// `Out::Emit` passes an event that is already available to the flows' `on_custom_events` once
+/// the current event is handled, without wrapping it in a future. Hooks returning another
+/// `Out` can use [`Context::defer_event`] instead. [`Context::broadcast_events`] delivers
+/// events to every flow instead of only until one consumes it. `Out::EmitTo` passes the event
+/// only to the flow of the given id, also while broadcasting. Events no flow consumed go to
+/// [`Context::set_dead_letter_handler`].
+///
+/// `Out::SpawnFlow` constructs another flow and adds it once the current event is handled,
+/// e.g. the gameplay flow after "Start Game" was clicked. It gets `on_init` like the flows of
+/// `run`. The constructor is polled like the future of `Out::ConfigureFut`, so the other flows
+/// keep rendering while it loads its assets, and its error goes to the same handler.
+/// `Out::RemoveFlow` removes a flow after calling its `on_exit`, see [`Context::remove_flow`].
+///
+/// `Out::Snapshot` passes a [`SnapshotWriter`] to its closure once the current event is
+/// handled, between frames, e.g. to write a save game. See [`crate::persist`].
+///
+/// `Out::Composed` returns several outputs from one hook, e.g. reconfiguring the Context and
+/// starting a `FutFn` in the same frame. Its `Configure` modifications are applied first, the
+/// other outputs follow in the order they were returned. Nested compositions are flattened,
+/// an empty one does nothing like `Empty`. Collecting outputs into an `Out` composes them.
+///
 /// `Empty` is the default output used when no eventing/futures need to be handled.
 ///
 pub enum Out<S, E>
@@ -77,18 +141,78 @@ where
     FutEvent(Vec<Box<dyn Future<Output = E> + Send>>),
     #[cfg(target_arch = "wasm32")]
     FutEvent(Vec<Box<dyn Future<Output = E>>>),
-    FutFn(Vec<Box<dyn Future<Output = Box<dyn FnOnce(&mut S)>>>>),
+    #[cfg(not(target_arch = "wasm32"))]
+    FutFn(Vec<Box<dyn Future<Output = StateFn<S>> + Send>>),
+    #[cfg(target_arch = "wasm32")]
+    FutFn(Vec<Box<dyn Future<Output = LocalStateFn<S>>>>),
+    FutFnLocal(Vec<Box<dyn Future<Output = LocalStateFn<S>>>>),
     Configure(Box<dyn FnOnce(&mut Context)>),
+    ConfigureFut(Box<dyn Future<Output = anyhow::Result<ConfigureFn>>>),
+    Emit(E),
+    EmitTo(FlowId, E),
+    /// Closes the app after the current frame, see [`Context::request_exit`].
+    Exit,
+    /// Keeps the current window or device event from later flows, the camera and picking, see
+    /// [`Context::capture_input`].
+    Capture,
+    SpawnFlow(FlowConstructor<S, E>),
+    RemoveFlow(FlowId),
+    Snapshot(SnapshotFn<S>),
     Composed(Vec<Out<S, E>>),
     Empty,
 }
 
+/// Modification of the Context resolved by `Out::ConfigureFut`.
+pub type ConfigureFn = Box<dyn FnOnce(&mut Context)>;
+
+/// Modification of the state resolved by `Out::FutFn`.
+pub type StateFn<S> = Box<dyn FnOnce(&mut S) + Send>;
+
+/// Modification of the state resolved by `Out::FutFnLocal`.
+pub type LocalStateFn<S> = Box<dyn FnOnce(&mut S)>;
+
 impl<S, E: Send> Default for Out<S, E> {
     fn default() -> Self {
         Self::Empty
     }
 }
 
+impl<S, E: Send> FromIterator<Out<S, E>> for Out<S, E> {
+    /// `Empty` for no outputs, the output itself for a single one.
+    fn from_iter<I: IntoIterator<Item = Out<S, E>>>(outs: I) -> Self {
+        let mut flat = Vec::new();
+        outs.into_iter().for_each(|out| out.flatten_into(&mut flat));
+        match flat.len() {
+            0 => Out::Empty,
+            1 => flat.remove(0),
+            _ => Out::Composed(flat),
+        }
+    }
+}
+
+impl<S, E: Send> Out<S, E> {
+    /// Moves the outputs of nested compositions into `flat`, leaving out `Empty`.
+    fn flatten_into(self, flat: &mut Vec<Self>) {
+        match self {
+            Out::Composed(outs) => outs.into_iter().for_each(|out| out.flatten_into(flat)),
+            Out::Empty => (),
+            out => flat.push(out),
+        }
+    }
+
+    /// The outputs in the order they're handled, `Configure` before the rest.
+    fn into_steps(self) -> Vec<Self> {
+        let mut flat = Vec::new();
+        self.flatten_into(&mut flat);
+        // Futures of the same output see the configured Context
+        let (mut steps, rest): (Vec<_>, Vec<_>) = flat
+            .into_iter()
+            .partition(|out| matches!(out, Out::Configure(_)));
+        steps.extend(rest);
+        steps
+    }
+}
+
 #[cfg(feature = "integration-tests")]
 pub enum ImageTestResult {
     Passed,
@@ -105,12 +229,34 @@ pub enum ImageTestResult {
 /// # Lifecycle
 ///
 /// 1. `on_init()` is called once when the flow is created; configure context (camera, clear color, etc.)
+///    followed by `on_restore()` when the app started from a [`Snapshot`]
 /// 2. `on_window_events()` and `on_device_events()` are called for each winit input event
-/// 3. `on_update()` is called every frame
+/// 3. `on_fixed_update()` is called every fixed step and `on_update()` every frame
 /// 4. `on_tick()` is called every `tick_duration_millis`
-/// 5. `on_click()` is called when an object with this flow's ID is clicked
+/// 5. `on_click()` is called when an object with this flow's ID is clicked, `on_hover()` when
+///    the cursor moves onto or off it while [`Context::enable_hover_picking`] is on and
+///    `on_pointer()` for clicks, double clicks and drags on it
 /// 6. `on_custom_events()` is called for custom application events
 /// 7. `on_render()` is called each frame and specifies how to render `self`
+/// 8. `on_gpu_reset()` is called after the context recovered from a lost GPU device and
+///    `on_gpu_recovered()` once a lost or outdated surface draws again
+/// 9. `on_flow_panic()` is called after another flow panicked, see the `catch-flow-panics`
+///    feature
+///
+/// While a flow holds keyboard focus (see [`Context::request_keyboard_focus`]) keyboard and
+/// IME events only reach that flow, additionally translated by `on_text_input()`.
+///
+/// While the app is paused (see [`Context::set_paused`]) only flows returning `true` from
+/// `runs_while_paused()` receive input, clicks and ticks; all others are updated with a zero `dt`.
+/// [`Context::set_flow_active`] stops updates, ticks, input or rendering of single flows.
+///
+/// # Ordering
+///
+/// Every hook visits flows in the same order: descending `priority()`, then the order flows
+/// were added in. This decides which flow consumes a custom event first, the order renders
+/// are batched in and the order flows sharing a clicked [`PickId`] get `on_click`. Each flow
+/// is identified by a [`FlowId`] that stays the same for its whole lifetime, it learns its
+/// own from [`InitContext::flow_id`]. [`Context::set_flow_order`] reorders flows later on.
 ///
 pub trait GraphicsFlow<S, E: Send> {
     /// Initialize the flow and configure the context.
@@ -121,6 +267,18 @@ pub trait GraphicsFlow<S, E: Send> {
         Out::Empty
     }
 
+    /// Called on every flow after `on_init` when the app started from a [`Snapshot`] through
+    /// [`run_with_restore`]. The state and view were already restored, flows rebuild what
+    /// depends on them, e.g. the instances of saved units.
+    fn on_restore(
+        &mut self,
+        _ctx: &Context,
+        _state: &mut S,
+        _snapshot: &EngineSnapshot,
+    ) -> Out<S, E> {
+        Out::Empty
+    }
+
     /// Handle a click on an object rendered by this flow.
     ///
     ///
@@ -137,14 +295,103 @@ pub trait GraphicsFlow<S, E: Send> {
         Out::Empty
     }
 
+    /// Handle a left click on an object rendered by this flow, `at` the world position of the
+    /// clicked surface. `at` is `None` for UI elements and flows that don't write the pick
+    /// depth. By default the click is passed to `on_click`.
+    fn on_click_at(
+        &mut self,
+        ctx: &Context,
+        state: &mut S,
+        id: PickId,
+        _at: Option<Vector3<f32>>,
+    ) -> Out<S, E> {
+        self.on_click(ctx, state, id)
+    }
+
+    /// Handle a left click on the instance with index `instance` of a draw with
+    /// [`Instanced::pick_instances`](crate::render::Instanced::pick_instances), `id` being the
+    /// draw's id and `at` as in `on_click_at`. By default the click is passed to
+    /// `on_click_at`.
+    fn on_click_instance(
+        &mut self,
+        ctx: &Context,
+        state: &mut S,
+        id: PickId,
+        _instance: u32,
+        at: Option<Vector3<f32>>,
+    ) -> Out<S, E> {
+        self.on_click_at(ctx, state, id, at)
+    }
+
+    /// Handle a click with any of the [`Context::pick_buttons`] on an object rendered by this
+    /// flow, `hit` telling where it was clicked. By default left clicks are passed to
+    /// `on_click_instance` for instances picked on their own or `on_click_at` otherwise, and
+    /// other buttons are ignored.
+    fn on_click_with(
+        &mut self,
+        ctx: &Context,
+        state: &mut S,
+        id: PickId,
+        hit: PickHit,
+        button: MouseButton,
+    ) -> Out<S, E> {
+        match (button, hit.instance) {
+            (MouseButton::Left, Some(instance)) => {
+                self.on_click_instance(ctx, state, id, instance, hit.position)
+            }
+            (MouseButton::Left, None) => self.on_click_at(ctx, state, id, hit.position),
+            _ => Out::Empty,
+        }
+    }
+
+    /// Handle the cursor moving onto an object rendered by this flow, or off it with `None`.
+    ///
+    /// Only called while [`Context::enable_hover_picking`] is on. `None` is delivered once
+    /// when the cursor moves to empty space, an object of another flow or out of the window.
+    fn on_hover(&mut self, _ctx: &Context, _state: &mut S, _id: Option<PickId>) -> Out<S, E> {
+        Out::Empty
+    }
+
+    /// Handle clicks, double clicks and drags that started on an object rendered by this flow.
+    ///
+    /// Unlike `on_click`, which fires as soon as the button goes down, `Clicked` waits for the
+    /// release and is left out when the object was dragged. Drags keep reaching the flow that
+    /// owns the pressed object until the button is released, wherever the cursor moves.
+    fn on_pointer(&mut self, _ctx: &Context, _state: &mut S, _event: PointerEvent) -> Out<S, E> {
+        Out::Empty
+    }
+
     /// Update state every frame.
     ///
     /// Called every frame with the elapsed time `dt`. Use for animations,
     /// physics updates, and other per-frame logic.
+    ///
+    /// With [`RenderStrategy::OnDemand`](crate::context::RenderStrategy::OnDemand) frames can
+    /// be seconds apart, so `dt` is capped at
+    /// [`MAX_ON_DEMAND_DT`](crate::context::MAX_ON_DEMAND_DT). Flows animating something
+    /// themselves call [`Context::request_frame`] to get the next frame.
+    ///
+    /// `dt` is game time scaled by [`Context::time_scale`], except for flows that run while
+    /// paused which get real time. [`Context::timing`] has both.
     fn on_update(&mut self, _ctx: &Context, _state: &mut S, _dt: Duration) -> Out<S, E> {
         Out::Empty
     }
 
+    /// Advance a simulation by the fixed step `fixed_dt`, e.g. physics.
+    ///
+    /// Called zero or more times per frame before `on_update`, every
+    /// [`Context::fixed_step`] of game time, so the simulation is deterministic regardless
+    /// of the frame rate. [`Context::fixed_alpha`] blends between the previous and current
+    /// step when drawing. Stops while the app is paused.
+    fn on_fixed_update(
+        &mut self,
+        _ctx: &Context,
+        _state: &mut S,
+        _fixed_dt: Duration,
+    ) -> Out<S, E> {
+        Out::Empty
+    }
+
     /// Update state periodically.
     ///
     /// Called every `tick_duration_millis` milliseconds (configurable via context).
@@ -154,6 +401,8 @@ pub trait GraphicsFlow<S, E: Send> {
     }
 
     /// Handle raw device events (keyboard, mouse hardware input).
+    ///
+    /// Return `Out::Capture` to keep the event from the flows after this one and the camera.
     fn on_device_events(
         &mut self,
         _ctx: &Context,
@@ -164,6 +413,10 @@ pub trait GraphicsFlow<S, E: Send> {
     }
 
     /// Handle window events (keyboard, mouse, window resizing, etc.).
+    ///
+    /// Flows get the event in dispatch order before the camera controller does. Return
+    /// `Out::Capture` to keep it from the flows after this one, the camera and, for a mouse
+    /// press, picking, e.g. when the cursor is over a panel of the flow.
     fn on_window_events(
         &mut self,
         _ctx: &Context,
@@ -173,14 +426,149 @@ pub trait GraphicsFlow<S, E: Send> {
         Out::Empty
     }
 
+    /// Handle a key press or release, right after `on_window_events` got its `KeyboardInput`.
+    ///
+    /// Replays of a recorded session can't recreate `KeyboardInput` events and only call this
+    /// one, so flows reacting to keys here replay exactly. Return `Out::Capture` like in
+    /// `on_window_events`.
+    fn on_key(
+        &mut self,
+        _ctx: &Context,
+        _state: &mut S,
+        _key: KeyCode,
+        _key_state: ElementState,
+    ) -> Out<S, E> {
+        Out::Empty
+    }
+
+    /// Handle gamepad input since the previous frame, including gamepads being plugged in
+    /// and out. Only with the `gamepad` feature on native targets.
+    #[cfg(all(feature = "gamepad", not(target_arch = "wasm32")))]
+    fn on_gamepad_events(
+        &mut self,
+        _ctx: &Context,
+        _state: &mut S,
+        _events: &[GamepadEvent],
+    ) -> Out<S, E> {
+        Out::Empty
+    }
+
+    /// Handle text input while this flow holds keyboard focus.
+    ///
+    /// Receives character input, editing keys and IME composition. The raw events are
+    /// passed to `on_window_events` as well.
+    fn on_text_input(&mut self, _ctx: &Context, _state: &mut S, _event: TextEvent) -> Out<S, E> {
+        Out::Empty
+    }
+
+    /// Called when this flow gains (`true`) or loses (`false`) keyboard focus, e.g. to
+    /// highlight the focused widget.
+    fn on_focus_changed(&mut self, _ctx: &Context, _state: &mut S, _focused: bool) -> Out<S, E> {
+        Out::Empty
+    }
+
+    /// Called after the surface was resized to `size`.
+    ///
+    /// Bursts of `WindowEvent::Resized` (e.g. while dragging the window edge) are collapsed
+    /// into one call per frame and minimizing the window doesn't trigger it.
+    fn on_resize(&mut self, _ctx: &Context, _state: &mut S, _size: PhysicalSize<u32>) -> Out<S, E> {
+        Out::Empty
+    }
+
+    /// Called after the GPU device was lost and [`Context::reset_gpu`] rebuilt the context.
+    ///
+    /// Buffers, textures and pipelines created on the old device can't be drawn anymore, so
+    /// models are loaded and written to their buffers again here, and custom pipelines are
+    /// registered again through `Out::Configure`.
+    fn on_gpu_reset(&mut self, _ctx: &Context, _state: &mut S) -> Out<S, E> {
+        Out::Empty
+    }
+
+    /// Called once the surface draws again after it was lost or outdated.
+    ///
+    /// Meanwhile updates, ticks and picks are held for up to a second worth of frames, see
+    /// [`Context::is_recovering_surface`]. Unlike after `on_gpu_reset` the device and what
+    /// was uploaded to it are still there.
+    fn on_gpu_recovered(&mut self, _ctx: &Context, _state: &mut S) -> Out<S, E> {
+        Out::Empty
+    }
+
+    /// Whether this flow keeps running while the app is paused, e.g. a pause menu or HUD.
+    /// These flows also get real time in `on_update` regardless of [`Context::time_scale`].
+    fn runs_while_paused(&self) -> bool {
+        false
+    }
+
+    /// Position of this flow in the dispatch order, read once when the flow is added.
+    ///
+    /// Hooks reach flows with a higher priority first, flows of equal priority in the order
+    /// they were added. E.g. a menu that consumes custom events before the game sees them
+    /// returns a positive value.
+    fn priority(&self) -> i32 {
+        0
+    }
+
+    /// Called on every flow when the app is paused (`true`) or resumed (`false`), e.g. to
+    /// show or hide a pause menu.
+    fn on_pause_changed(&mut self, _ctx: &Context, _state: &mut S, _paused: bool) -> Out<S, E> {
+        Out::Empty
+    }
+
+    /// Called on every flow when the window gains (`true`) or loses (`false`) focus, e.g. to
+    /// pause the game while the player is in another window. Unlike `on_focus_changed` this
+    /// is about the OS window, see [`Context::is_window_focused`].
+    fn on_focus(&mut self, _ctx: &Context, _state: &mut S, _focused: bool) -> Out<S, E> {
+        Out::Empty
+    }
+
+    /// Called on every flow when the app is suspended, e.g. sent to the background on mobile
+    /// or in a hidden browser tab. No frames, updates or ticks run until `on_resume`.
+    fn on_suspend(&mut self, _ctx: &Context, _state: &mut S) -> Out<S, E> {
+        Out::Empty
+    }
+
+    /// Called on every flow when the app continues after `on_suspend`. The time spent
+    /// suspended doesn't count towards `dt` or ticks.
+    fn on_resume(&mut self, _ctx: &Context, _state: &mut S) -> Out<S, E> {
+        Out::Empty
+    }
+
+    /// Called on every flow once before the app closes, through [`Context::request_exit`],
+    /// `Out::Exit` or the window's close button, e.g. to save the game. A flow removed
+    /// through [`Context::remove_flow`] gets it when it's removed.
+    fn on_exit(&mut self, _ctx: &Context, _state: &mut S) {}
+
+    /// Called on every other flow after a flow panicked, with the `catch-flow-panics` feature.
+    ///
+    /// The panicking flow is poisoned: it gets no more hooks, not even `on_exit`, and isn't
+    /// drawn anymore. A supervisor flow can show an error screen and keep the app running by
+    /// returning anything but `Out::Empty`, e.g. `Out::Configure`. If every flow returns
+    /// `Out::Empty`, the default, the app exits and the others get `on_exit` to save.
+    fn on_flow_panic(&mut self, _ctx: &Context, _state: &mut S, _panic: &FlowPanic) -> Out<S, E> {
+        Out::Empty
+    }
+
     /// Handle custom application events.
     ///
     /// Returns the event if it was not consumed, allowing it to be passed to
-    /// the next flow. Returning `None` means the event was consumed.
+    /// the next flow. Returning `None` means the event was consumed. Events no flow consumed
+    /// go to [`Context::set_dead_letter_handler`].
     fn on_custom_events(&mut self, _ctx: &Context, _state: &mut S, event: E) -> Option<E> {
         Some(event)
     }
 
+    /// Window `on_render` draws into, see [`Context::create_window`]. Flows of a closed
+    /// window aren't drawn.
+    fn window(&self) -> WindowId {
+        WindowId::MAIN
+    }
+
+    /// Layer of everything `on_render` returns that doesn't pick its own through
+    /// [`Render::on_layer`], e.g. [`Layer::OVERLAY`] to draw a GUI over the world.
+    fn layer(&self) -> Layer {
+        Layer::WORLD
+    }
+
     /// Return renderable objects for this flow.
     ///
     /// Called each frame. Collect your objects into a [`Render`] and return it.
@@ -198,6 +586,18 @@ pub trait GraphicsFlow<S, E: Send> {
     ) -> Result<ImageTestResult, anyhow::Error> {
         Ok(ImageTestResult::Passed)
     }
+
+    /// Like `render_to_texture` but receives the pick id of every pixel, rendered the same
+    /// way clicks are resolved.
+    #[cfg(feature = "integration-tests")]
+    fn pick_to_texture(
+        &self,
+        _ctx: &Context,
+        _state: &mut S,
+        _ids: &PickImage,
+    ) -> Result<ImageTestResult, anyhow::Error> {
+        Ok(ImageTestResult::Passed)
+    }
 }
 
 // Dummy impl to make wasm work
@@ -211,89 +611,410 @@ impl<State, Event> Debug for dyn GraphicsFlow<State, Event> + 'static {
 ///
 /// A flow constructor takes an `InitContext` and asynchronously returns a
 /// boxed `GraphicsFlow`. This allows lazy initialization and resource loading.
+///
+/// The constructors of `run` resolve while the window shows the
+/// [loading screen](crate::loading). If one fails, e.g. because an asset is missing, the app
+/// stops with its error before the first frame of the flows. Those of `Out::SpawnFlow` go to
+/// [`RunConfig::with_error_handler`] and are skipped.
 pub type FlowConstructor<S, E> =
-    Box<dyn FnOnce(InitContext) -> Pin<Box<dyn Future<Output = Box<dyn GraphicsFlow<S, E>>>>>>;
+    Box<dyn FnOnce(InitContext) -> Pin<Box<dyn Future<Output = FlowResult<S, E>>>>>;
+
+/// Flow built by a [`FlowConstructor`].
+pub type FlowResult<S, E> = Result<Box<dyn GraphicsFlow<S, E>>, Error>;
+
+/// Boxes an async constructor such as `async fn new(ctx: InitContext) -> MyFlow` into a
+/// [`FlowConstructor`] without spelling out the state and event types.
+///
+/// ```ignore
+/// flow_ngin::flow::run_simple(vec![Spinner::new.into_flow()])
+/// ```
+pub trait IntoFlowConstructor<S, E: Send> {
+    fn into_flow(self) -> FlowConstructor<S, E>;
+}
+
+impl<S, E, F, Fut, G> IntoFlowConstructor<S, E> for F
+where
+    E: Send,
+    F: FnOnce(InitContext) -> Fut + 'static,
+    Fut: Future<Output = G> + 'static,
+    G: GraphicsFlow<S, E> + 'static,
+{
+    fn into_flow(self) -> FlowConstructor<S, E> {
+        Box::new(|ctx| {
+            Box::pin(async move { Ok(Box::new(self(ctx).await) as Box<dyn GraphicsFlow<S, E>>) })
+        })
+    }
+}
+
+/// Like [`IntoFlowConstructor`] for constructors that can fail, such as
+/// `async fn new(ctx: InitContext) -> Result<MyFlow, Error>`.
+///
+/// ```ignore
+/// flow_ngin::flow::run_simple(vec![Spinner::new.try_into_flow()])
+/// ```
+pub trait TryIntoFlowConstructor<S, E: Send> {
+    fn try_into_flow(self) -> FlowConstructor<S, E>;
+}
+
+impl<S, E, F, Fut, G> TryIntoFlowConstructor<S, E> for F
+where
+    E: Send,
+    F: FnOnce(InitContext) -> Fut + 'static,
+    Fut: Future<Output = Result<G, Error>> + 'static,
+    G: GraphicsFlow<S, E> + 'static,
+{
+    fn try_into_flow(self) -> FlowConstructor<S, E> {
+        Box::new(|ctx| {
+            Box::pin(async move { Ok(Box::new(self(ctx).await?) as Box<dyn GraphicsFlow<S, E>>) })
+        })
+    }
+}
+
+/// Event type of apps that don't send custom events, it has no values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoEvent {}
+
+/// Flow constructor of [`run_simple`], for flows without shared state or custom events.
+pub type SimpleFlowConstructor = FlowConstructor<(), NoEvent>;
+
+/// Stable id of a flow, see [`InitContext::flow_id`].
+///
+/// Ids are handed out in the order flows are added and never reused, so unlike a position
+/// in the list of flows they stay valid when other flows come and go.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FlowId(pub usize);
+
+/// A panic caught in a hook of a flow, see [`GraphicsFlow::on_flow_panic`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FlowPanic {
+    pub flow: FlowId,
+    /// Name of the hook that panicked, e.g. `"on_update"`.
+    pub hook: &'static str,
+    /// The panic message, if it was a string.
+    pub message: String,
+}
+
+/// Which hooks a flow receives, see [`Context::set_flow_active`]. Other hooks such as
+/// `on_custom_events`, `on_resize` and `on_exit` reach every flow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FlowActivity {
+    /// `on_update`.
+    pub update: bool,
+    /// `on_tick`.
+    pub tick: bool,
+    /// Window and device events, text input and clicks. Objects of a flow without input
+    /// aren't picked, so clicks reach what's drawn behind them.
+    pub input: bool,
+    /// `on_render`, drawing the flow at all.
+    pub render: bool,
+}
+
+impl FlowActivity {
+    pub const ACTIVE: Self = Self {
+        update: true,
+        tick: true,
+        input: true,
+        render: true,
+    };
+    /// Drawn but not updated, e.g. the world behind a translucent pause menu.
+    pub const FROZEN: Self = Self {
+        update: false,
+        tick: false,
+        input: false,
+        render: true,
+    };
+    /// Kept alive with its state and GPU buffers without running or being drawn.
+    pub const SUSPENDED: Self = Self {
+        update: false,
+        tick: false,
+        input: false,
+        render: false,
+    };
+}
+
+impl Default for FlowActivity {
+    fn default() -> Self {
+        Self::ACTIVE
+    }
+}
+
+struct FlowEntry<F> {
+    id: FlowId,
+    priority: i32,
+    flow: F,
+}
+
+/// Flows kept in dispatch order, see [`GraphicsFlow`]'s ordering guarantees.
+pub(crate) struct Flows<F> {
+    // Sorted by descending priority, then ascending id
+    entries: Vec<FlowEntry<F>>,
+    next_id: usize,
+}
+
+pub(crate) type FlowList<S, E> = Flows<Box<dyn GraphicsFlow<S, E>>>;
+
+impl<F> Flows<F> {
+    pub(crate) fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+            next_id: 0,
+        }
+    }
+
+    /// Id the next inserted flow will get.
+    pub(crate) fn next_id(&self) -> FlowId {
+        FlowId(self.next_id)
+    }
+
+    /// Hands out the id of a flow that is inserted later through [`Flows::insert_as`], e.g.
+    /// once its constructor resolved.
+    pub(crate) fn reserve_id(&mut self) -> FlowId {
+        self.next_id += 1;
+        FlowId(self.next_id - 1)
+    }
+
+    pub(crate) fn insert(&mut self, flow: F, priority: i32) -> FlowId {
+        let id = self.reserve_id();
+        self.insert_as(id, flow, priority);
+        id
+    }
+
+    pub(crate) fn insert_as(&mut self, id: FlowId, flow: F, priority: i32) {
+        // behind all flows of higher priority and those of equal priority added earlier
+        let at = self.entries.partition_point(|entry| {
+            entry.priority > priority || (entry.priority == priority && entry.id < id)
+        });
+        self.entries.insert(at, FlowEntry { id, priority, flow });
+    }
+
+    /// Puts the flows `order` into that order within the places they take up, see
+    /// [`Context::set_flow_order`].
+    pub(crate) fn reorder(&mut self, order: &[FlowId]) {
+        let mut ids = Vec::new();
+        for &id in order {
+            if self.contains(id) && !ids.contains(&id) {
+                ids.push(id);
+            }
+        }
+        // Taking over the priorities keeps the entries sorted for `insert_as`
+        let slots: Vec<(usize, i32)> = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| ids.contains(&entry.id))
+            .map(|(at, entry)| (at, entry.priority))
+            .collect();
+        let mut entries: Vec<_> = self.entries.drain(..).map(Some).collect();
+        let moved: Vec<_> = ids
+            .iter()
+            .filter_map(|id| {
+                entries
+                    .iter_mut()
+                    .find(|entry| entry.as_ref().is_some_and(|entry| entry.id == *id))?
+                    .take()
+            })
+            .collect();
+        for ((at, priority), mut entry) in slots.into_iter().zip(moved) {
+            entry.priority = priority;
+            entries[at] = Some(entry);
+        }
+        self.entries = entries.into_iter().flatten().collect();
+    }
+
+    pub(crate) fn remove(&mut self, id: FlowId) -> Option<F> {
+        let at = self.entries.iter().position(|entry| entry.id == id)?;
+        Some(self.entries.remove(at).flow)
+    }
+
+    pub(crate) fn contains(&self, id: FlowId) -> bool {
+        self.entries.iter().any(|entry| entry.id == id)
+    }
+
+    pub(crate) fn get(&self, id: FlowId) -> Option<&F> {
+        self.entries
+            .iter()
+            .find(|entry| entry.id == id)
+            .map(|entry| &entry.flow)
+    }
+
+    pub(crate) fn get_mut(&mut self, id: FlowId) -> Option<&mut F> {
+        self.entries
+            .iter_mut()
+            .find(|entry| entry.id == id)
+            .map(|entry| &mut entry.flow)
+    }
+
+    /// Flows in dispatch order.
+    #[allow(dead_code)]
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &F> {
+        self.entries.iter().map(|entry| &entry.flow)
+    }
+
+    /// Flows in dispatch order.
+    pub(crate) fn iter_mut(&mut self) -> impl Iterator<Item = &mut F> {
+        self.entries.iter_mut().map(|entry| &mut entry.flow)
+    }
+
+    /// Flows with their ids in dispatch order.
+    pub(crate) fn iter_with_ids(&self) -> impl Iterator<Item = (FlowId, &F)> {
+        self.entries.iter().map(|entry| (entry.id, &entry.flow))
+    }
+
+    /// Flows with their ids in dispatch order.
+    pub(crate) fn iter_mut_with_ids(&mut self) -> impl Iterator<Item = (FlowId, &mut F)> {
+        self.entries
+            .iter_mut()
+            .map(|entry| (entry.id, &mut entry.flow))
+    }
+
+    /// Passes `event` to the flow `target`, or along all flows in dispatch order, until
+    /// `deliver` consumes it. Returns the event if no flow did.
+    pub(crate) fn deliver<E>(
+        &mut self,
+        target: Option<FlowId>,
+        event: E,
+        mut deliver: impl FnMut(&mut F, E) -> Option<E>,
+    ) -> Option<E> {
+        match target {
+            Some(id) => match self.get_mut(id) {
+                Some(flow) => deliver(flow, event),
+                None => Some(event),
+            },
+            None => self
+                .iter_mut()
+                .try_fold(event, |event, flow| deliver(flow, event)),
+        }
+    }
+
+    /// The flows among `owners` that still exist, in dispatch order.
+    pub(crate) fn dispatch_order(&self, owners: &BTreeSet<FlowId>) -> Vec<FlowId> {
+        self.entries
+            .iter()
+            .map(|entry| entry.id)
+            .filter(|id| owners.contains(id))
+            .collect()
+    }
+}
 
 /// Application state bundle: GPU context, app state, and surface status.
 #[derive(Debug)]
 pub struct AppState<State: 'static> {
     pub(crate) ctx: Context,
+    // Same as `ctx.window`, which is always set for the event loop
+    window: Arc<Window>,
     state: State,
     is_surface_configured: bool,
+    // Outcome of the last `get_surface_texture`, taken after each frame
+    acquired: Option<Acquire>,
 }
-impl<'a, State: Default> AppState<State> {
-    async fn new(window: Arc<Window>) -> Self {
-        let ctx = Context::new(window).await;
-        let ctx = match ctx {
-            Ok(ctx) => ctx,
-            Err(e) => panic!(
-                "App initialization failed. Cannot create the main context: {}",
-                e
-            ),
-        };
+
+/// Outcome of getting the texture of the next frame from the surface.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Acquire {
+    Acquired,
+    // Lost or outdated, it's reconfigured before the next attempt
+    Lost,
+    // Timed out or occluded, the next frame tries again
+    Skipped,
+}
+impl<'a, State> AppState<State> {
+    async fn new(
+        window: Arc<Window>,
+        adapter: &AdapterOptions,
+        request: &DeviceRequest,
+    ) -> Result<Self, Error>
+    where
+        State: Default,
+    {
+        let ctx = Context::new(window.clone(), adapter, request).await?;
         let state = State::default();
         let is_surface_configured = false;
-        Self {
+        Ok(Self {
             ctx,
+            window,
             state,
             is_surface_configured,
-        }
+            acquired: None,
+        })
     }
 
     fn resize(&mut self, width: u32, height: u32) {
         if width > 0 && height > 0 {
-            self.ctx.config.width = width;
-            self.ctx.config.height = height;
             self.is_surface_configured = true;
-            self.ctx.projection.resize(width, height);
-            self.ctx
-                .surface
-                .configure(&self.ctx.device, &self.ctx.config);
-            let sample_count = self.ctx.anti_aliasing.sample_count();
-            self.ctx.depth_texture = Texture::create_depth_texture(
-                &self.ctx.device,
-                [self.ctx.config.width, self.ctx.config.height],
-                "depth_texture",
-                sample_count,
-            );
-            self.ctx.msaa_view = if sample_count > 1 {
-                Some(Texture::create_msaa_texture(
-                    &self.ctx.device,
-                    &self.ctx.config,
-                    sample_count,
-                ))
-            } else {
-                None
-            };
-            let screen_size_data = [width as f32, height as f32, 0.0f32, 0.0f32];
-            self.ctx.queue.write_buffer(
-                &self.ctx.screen_size.buffer,
-                0,
-                bytemuck::cast_slice(&screen_size_data),
-            );
+            self.ctx.resize_targets(width, height);
         }
     }
 
-    fn get_surface_texture(&self) -> Option<wgpu::SurfaceTexture> {
-        match self.ctx.surface.get_current_texture() {
+    /// Stops rendering until the next resize, e.g. while the window is minimized.
+    fn suspend(&mut self) {
+        self.is_surface_configured = false;
+    }
+
+    /// The texture to draw the next frame into, `None` skips the frame. A lost or outdated
+    /// surface is left for [`AppState::reconfigure_surface`].
+    fn get_surface_texture(&mut self) -> Option<wgpu::SurfaceTexture> {
+        let surface = self.ctx.surface.as_ref()?;
+        #[cfg(feature = "integration-tests")]
+        if std::mem::take(&mut self.ctx.forced_surface_loss) {
+            self.acquired = Some(Acquire::Lost);
+            return None;
+        }
+        let (texture, acquired) = match surface.get_current_texture() {
             wgpu::CurrentSurfaceTexture::Success(tex)
-            | wgpu::CurrentSurfaceTexture::Suboptimal(tex) => Some(tex),
-            wgpu::CurrentSurfaceTexture::Timeout
-            | wgpu::CurrentSurfaceTexture::Occluded => None,
-            wgpu::CurrentSurfaceTexture::Outdated
-            | wgpu::CurrentSurfaceTexture::Lost => {
-                let size = self.ctx.window.inner_size();
-                log::warn!("Surface lost/outdated, needs reconfigure ({}x{})", size.width, size.height);
-                None
+            | wgpu::CurrentSurfaceTexture::Suboptimal(tex) => (Some(tex), Acquire::Acquired),
+            wgpu::CurrentSurfaceTexture::Timeout | wgpu::CurrentSurfaceTexture::Occluded => {
+                (None, Acquire::Skipped)
             }
-            wgpu::CurrentSurfaceTexture::Validation => {
-                log::error!("Surface validation error");
-                None
+            wgpu::CurrentSurfaceTexture::Outdated | wgpu::CurrentSurfaceTexture::Lost => {
+                (None, Acquire::Lost)
             }
+            // Reported through the uncaptured error handler of the device
+            wgpu::CurrentSurfaceTexture::Validation => (None, Acquire::Skipped),
+        };
+        self.acquired = Some(acquired);
+        texture
+    }
+
+    fn reconfigure_surface(&self) {
+        if let Some(surface) = &self.ctx.surface {
+            log::debug!("Reconfiguring the surface");
+            surface.configure(&self.ctx.device, &self.ctx.config);
         }
     }
 
-    #[cfg(feature = "integration-tests")]
+    /// Draws a frame of the loading screen, without a `screen` it's only cleared.
+    fn render_loading(&mut self, screen: &Option<Box<dyn GraphicsFlow<(), NoEvent>>>) {
+        if !self.is_surface_configured {
+            return;
+        }
+        let Some(output) = self.get_surface_texture() else {
+            // No flows wait for the surface yet
+            if self.acquired.take() == Some(Acquire::Lost) {
+                self.reconfigure_surface();
+            }
+            return;
+        };
+        let view = output
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder = self
+            .ctx
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Loading Screen Encoder"),
+            });
+        draw_frame(
+            &mut self.ctx,
+            screen,
+            &mut encoder,
+            FrameTarget::Context(&view),
+        );
+        self.ctx.queue.submit(iter::once(encoder.finish()));
+        output.present();
+    }
+
+    #[cfg(feature = "integration-tests")]
     fn get_test_texture(&self, extent3d: wgpu::Extent3d) -> wgpu::Texture {
         self.ctx.device.create_texture(&wgpu::TextureDescriptor {
             label: Some("Golden Image Test Output Texture"),
@@ -315,8 +1036,10 @@ impl<'a, State: Default> AppState<State> {
             mip_level_count: 1,
             sample_count,
             dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Depth32Float,
-            usage: wgpu::TextureUsages::COPY_SRC | wgpu::TextureUsages::RENDER_ATTACHMENT,
+            format: self.ctx.depth().format,
+            usage: wgpu::TextureUsages::COPY_SRC
+                | wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::TEXTURE_BINDING,
             view_formats: &[],
         })
     }
@@ -345,23 +1068,17 @@ impl<'a, State: Default> AppState<State> {
 
     fn render<Event: Send>(
         &'a mut self,
-        graphics_flows: &mut Vec<Box<dyn GraphicsFlow<State, Event>>>,
+        graphics_flows: &mut FlowList<State, Event>,
         #[cfg(feature = "integration-tests")] async_runtime: &Runtime,
-        #[cfg(feature = "integration-tests")] event_loop: &winit::event_loop::EventLoopProxy<
-            FlowEvent<State, Event>,
-        >,
+        #[cfg(feature = "integration-tests")] event_loop: &EngineProxy<State, Event>,
     ) -> Result<(), anyhow::Error> {
-        // invoke main render loop
-        self.ctx.window.request_redraw();
-
         // Rendering requires the surface to be configured
         if !self.is_surface_configured {
             return Ok(());
         }
 
-        let output = match self.get_surface_texture() {
-            Some(tex) => tex,
-            None => return Ok(()),
+        let Some(output) = self.get_surface_texture() else {
+            return Ok(());
         };
         // TODO: different view for golden img testing
         #[cfg(not(feature = "integration-tests"))]
@@ -408,170 +1125,28 @@ impl<'a, State: Default> AppState<State> {
                 .create_command_encoder(&wgpu::CommandEncoderDescriptor {
                     label: Some("Render Encoder"),
                 });
-        {
-            let mut render_pass: wgpu::RenderPass<'_> =
-                encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                    label: Some("Render Pass"),
-                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                        #[cfg(feature = "integration-tests")]
-                        view: msaa_tex_view.as_ref().unwrap_or(&tex_view),
-                        #[cfg(not(feature = "integration-tests"))]
-                        view: self.ctx.msaa_view.as_ref().unwrap_or(&view),
-                        #[cfg(feature = "integration-tests")]
-                        resolve_target: if msaa_tex_view.is_some() {
-                            Some(&tex_view)
-                        } else {
-                            None
-                        },
-                        #[cfg(not(feature = "integration-tests"))]
-                        resolve_target: if self.ctx.msaa_view.is_some() {
-                            Some(&view)
-                        } else {
-                            None
-                        },
-                        ops: wgpu::Operations {
-                            load: wgpu::LoadOp::Clear(self.ctx.clear_colour),
-                            store: wgpu::StoreOp::Store,
-                        },
-                        depth_slice: None,
-                    })],
-                    depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                        #[cfg(feature = "integration-tests")]
-                        view: &depth_view,
-                        #[cfg(not(feature = "integration-tests"))]
-                        view: &self.ctx.depth_texture.view,
-                        depth_ops: Some(wgpu::Operations {
-                            load: wgpu::LoadOp::Clear(1.0),
-                            store: wgpu::StoreOp::Store,
-                        }),
-                        stencil_ops: None,
-                    }),
-                    occlusion_query_set: None,
-                    timestamp_writes: None,
-                    ..Default::default()
-                });
-
-            // Actual rendering:
-            if self.ctx.light.model.is_some() {
-                render_pass.set_pipeline(&self.ctx.pipelines.light);
-                render_pass.draw_light_model(
-                    self.ctx.light.model.as_ref().unwrap(),
-                    &self.ctx.camera.bind_group,
-                    &self.ctx.light.bind_group,
-                );
-            }
-            let mut basics: Vec<Instanced> = Vec::new();
-            let mut trans: Vec<(Instanced, TransparencyUniform)> = Vec::new();
-            let mut guis: Vec<Flat> = Vec::new();
-            let mut terrain: Vec<Geometry> = Vec::new();
-            let mut customs = Vec::new();
-            graphics_flows.iter_mut().for_each(|flow| {
-                let render = flow.on_render();
-                render.set_pipelines(
-                    &self.ctx,
-                    &mut render_pass,
-                    &mut basics,
-                    &mut trans,
-                    &mut guis,
-                    &mut terrain,
-                    &mut customs,
-                );
-            });
-
-            render_pass.set_pipeline(&self.ctx.pipelines.basic);
-            for instanced in basics {
-                if instanced.amount == 0 {
-                    log::debug!("you attemted to render instances, nothing drawn to screen.");
-                    continue;
-                }
-                if instanced.instance.size() == 0 {
-                    log::debug!(
-                        "you attemted to draw an empty buffer, remember to call `write_to_buffer()` on your models."
-                    );
-                    continue;
-                }
-                // TODO: introduce as new render category
-                if let wgpu::FrontFace::Cw = instanced.front_face {
-                    render_pass.set_pipeline(&self.ctx.pipelines.basic_cw);
-                    render_pass.set_vertex_buffer(1, instanced.instance.slice(..));
-                    render_pass.draw_model_instanced(
-                        &instanced.model,
-                        0..instanced.amount as u32,
-                        &self.ctx.camera.bind_group,
-                        &self.ctx.light.bind_group,
-                    );
-                    render_pass.set_pipeline(&self.ctx.pipelines.basic);
-                    continue;
-                }
-                render_pass.set_vertex_buffer(1, instanced.instance.slice(..));
-                render_pass.draw_model_instanced(
-                    &instanced.model,
-                    0..instanced.amount as u32,
-                    &self.ctx.camera.bind_group,
-                    &self.ctx.light.bind_group,
-                );
-            }
-
-            render_pass.set_pipeline(&self.ctx.pipelines.terrain);
-            for button in terrain {
-                render_pass.set_vertex_buffer(1, button.instance.slice(..));
-                render_pass.set_bind_group(0, button.group, &[]);
-                render_pass.set_bind_group(1, &self.ctx.camera.bind_group, &[]);
-                render_pass.set_bind_group(2, &self.ctx.light.bind_group, &[]);
-                render_pass.set_vertex_buffer(0, button.vertex.slice(..));
-                render_pass.set_index_buffer(button.index.slice(..), wgpu::IndexFormat::Uint16);
-                render_pass.draw_indexed(0..button.amount as u32, 0, 0..1);
-            }
-
-            render_pass.set_pipeline(&self.ctx.pipelines.transparent);
-            let transparency_layout = mk_transparency_bind_group_layout(&self.ctx.device);
-            for (instanced, transparency) in trans {
-                if instanced.amount == 0 {
-                    log::debug!("you attemted to render instances, nothing drawn to screen.");
-                    continue;
-                }
-                if instanced.instance.size() == 0 {
-                    log::debug!(
-                        "you attemted to draw an empty buffer, remember to call `write_to_buffer()` on your models."
-                    );
-                    continue;
-                }
-                let transparency_buffer =
-                    self.ctx
-                        .device
-                        .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                            label: Some("Transparency Buffer"),
-                            contents: bytemuck::bytes_of(&transparency),
-                            usage: wgpu::BufferUsages::UNIFORM,
-                        });
-                let transparency_bind_group = mk_transparency_bind_group(
-                    &self.ctx.device,
-                    &transparency_buffer,
-                    &transparency_layout,
-                );
-                render_pass.set_bind_group(3, &transparency_bind_group, &[]);
-                render_pass.set_vertex_buffer(1, instanced.instance.slice(..));
-                render_pass.draw_model_instanced(
-                    &instanced.model,
-                    0..instanced.amount as u32,
-                    &self.ctx.camera.bind_group,
-                    &self.ctx.light.bind_group,
-                );
-            }
-
-            render_pass.set_pipeline(&self.ctx.pipelines.gui);
-            render_pass.set_bind_group(1, &self.ctx.screen_size.bind_group, &[]);
-            for button in guis {
-                render_pass.set_bind_group(0, button.group, &[]);
-                render_pass.set_vertex_buffer(0, button.vertex.slice(..));
-                render_pass.set_index_buffer(button.index.slice(..), wgpu::IndexFormat::Uint16);
-                render_pass.draw_indexed(0..button.amount as u32, 0, 0..1);
-            }
 
-            for custom in customs {
-                custom(&self.ctx, &mut render_pass);
+        #[cfg(feature = "integration-tests")]
+        let target = {
+            let extent3d = self.get_test_3d_extent();
+            FrameTarget::Attachments {
+                window: WindowId::MAIN,
+                color: msaa_tex_view.as_ref().unwrap_or(&tex_view),
+                resolve: msaa_tex_view.as_ref().map(|_| &tex_view),
+                depth: &depth_view,
+                size: [extent3d.width, extent3d.height],
             }
-        }
+        };
+        #[cfg(not(feature = "integration-tests"))]
+        let target = FrameTarget::Context(&view);
+        let flows: Vec<_> = graphics_flows
+            .iter_with_ids()
+            .filter(|(flow_id, flow)| {
+                flow.window() == WindowId::MAIN && self.ctx.flow_activity(*flow_id).render
+            })
+            .map(|(_, flow)| flow)
+            .collect();
+        let overdraw_readback = draw_frame(&mut self.ctx, flows, &mut encoder, target);
 
         #[cfg(feature = "integration-tests")]
         let output_buffer = {
@@ -606,6 +1181,9 @@ impl<'a, State: Default> AppState<State> {
         };
 
         self.ctx.queue.submit(iter::once(encoder.finish()));
+        if overdraw_readback && let Some(resources) = &self.ctx.overdraw {
+            resources.map_readback();
+        }
 
         #[cfg(feature = "integration-tests")]
         let fut_img = async {
@@ -634,15 +1212,22 @@ impl<'a, State: Default> AppState<State> {
 
             let mut img: image::ImageBuffer<image::Rgba<u8>, wgpu::BufferView> =
                 async_runtime.block_on(fut_img);
+            let (width, height) = (self.ctx.config.width, self.ctx.config.height);
+            let pick =
+                render_pick_buffer(graphics_flows, &self.ctx, PickRegion::whole(width, height));
+            let ids = async_runtime.block_on(read_pick_image(&pick, &self.ctx));
             let state = &mut self.state;
+            let passed = |res| match res {
+                Err(e) => panic!("{}", e),
+                Ok(ImageTestResult::Passed) => true,
+                Ok(ImageTestResult::Failed) => panic!("Assertion failed"),
+                Ok(ImageTestResult::Waiting) => false,
+            };
             let all_passed = graphics_flows
                 .iter_mut()
-                .map(|flow| flow.render_to_texture(&self.ctx, state, &mut img))
-                .map(|res| match res {
-                    Err(e) => panic!("{}", e),
-                    Ok(ImageTestResult::Passed) => true,
-                    Ok(ImageTestResult::Failed) => panic!("Assertion failed"),
-                    Ok(ImageTestResult::Waiting) => false,
+                .map(|flow| {
+                    passed(flow.render_to_texture(&self.ctx, state, &mut img))
+                        & passed(flow.pick_to_texture(&self.ctx, state, &ids))
                 })
                 .all(identity);
             if all_passed {
@@ -657,317 +1242,3194 @@ impl<'a, State: Default> AppState<State> {
     }
 }
 
+// How often startup is polled while the window waits. Futures on the web can't wake the event
+// loop, and those waiting for the GPU only progress as the device is polled
+const LOADING_POLL_INTERVAL: Duration = Duration::from_millis(16);
+
+// How often connected gamepads are polled while on demand rendering waits for input
+#[cfg(all(feature = "gamepad", not(target_arch = "wasm32")))]
+const GAMEPAD_POLL_INTERVAL: Duration = Duration::from_millis(16);
+
+type FlowsFuture<State, Event> =
+    Pin<Box<dyn Future<Output = Result<Vec<Box<dyn GraphicsFlow<State, Event>>>, Error>>>>;
+
+/// Startup of the app, polled by the event loop so the window stays responsive.
+enum Loading<State: 'static, Event: 'static> {
+    // Validating the assets and requesting the GPU device
+    Starting(Pin<Box<dyn Future<Output = Result<AppState<State>, Error>>>>),
+    // Drawing the loading screen while the flow constructors run
+    Constructing {
+        state: Box<AppState<State>>,
+        flows: FlowsFuture<State, Event>,
+        screen: Option<Box<dyn GraphicsFlow<(), NoEvent>>>,
+    },
+}
+
+/// The flows and window of an app, driven by an event loop.
+///
+/// [`run`] creates it and runs it in an event loop of its own. Hosts owning the event loop,
+/// e.g. an editor shell showing the app as one of its windows, create it through
+/// [`App::new_with_proxy`] and forward the loop's callbacks to it instead.
 pub struct App<State: 'static, Event: 'static> {
     #[cfg(not(target_arch = "wasm32"))]
     async_runtime: tokio::runtime::Runtime,
-    proxy: winit::event_loop::EventLoopProxy<FlowEvent<State, Event>>,
+    proxy: EngineProxy<State, Event>,
+    // `EventSender` handed to flow constructors through `InitContext::event_sender`
+    events: AnySender,
+    config: RunConfig,
     state: Option<AppState<State>>,
     // This will hold the fully initialized flows once they are ready.
-    graphics_flows: Vec<Box<dyn GraphicsFlow<State, Event>>>,
+    graphics_flows: FlowList<State, Event>,
     // This holds the constructors at the star.
     // We use Option to `take()` it after use.
     constructors: Option<Vec<FlowConstructor<State, Event>>>,
+    // Startup until the flows are initialized, `state` is `None` meanwhile
+    loading: Option<Loading<State, Event>>,
+    // Events posted by constructors, delivered once the flows exist
+    queued_events: Vec<FlowEvent<State, Event>>,
+    // Futures that aren't `Send`, see `App::spawn_local`
+    #[cfg(not(target_arch = "wasm32"))]
+    pending: Vec<LocalFuture<State, Event>>,
     last_time: Instant,
-    time_since_tick: Duration,
+    start_time: Instant,
+    ticks: TickAccumulator,
+    #[cfg(all(feature = "gamepad", not(target_arch = "wasm32")))]
+    gamepads: Gamepads,
+    // Game time towards the next `on_fixed_update`
+    fixed_steps: TickAccumulator,
+    // Keyboard focus the flows were last notified about
+    notified_focus: Option<FlowId>,
+    // Pause state the flows were last notified about
+    notified_paused: bool,
+    resize: ResizeDebouncer,
+    recovery: SurfaceRecovery,
+    touch: TouchGestures,
+    pointer: PointerGestures,
+    // Why the event loop stopped early, returned by `run`
+    error: Option<Error>,
+    // Whether the flows already got `on_exit`
+    exiting: bool,
+    // Set by `run`, a host's event loop keeps running once the app exited
+    owns_event_loop: bool,
+    // Of the main window once it was created
+    window_id: Option<winit::window::WindowId>,
+    // Restored once the flows are constructed, see `run_with_restore`
+    restore: Option<Snapshot<State>>,
+    // Moved into the context once the flows are constructed, see `crate::record`
+    #[cfg(feature = "record")]
+    session: Option<Session>,
 }
 
 impl<'a, State, Event> App<State, Event>
 where
     State: 'static,
-    Event: 'static,
+    Event: Send + 'static,
 {
     fn new(
-        event_loop: &EventLoop<FlowEvent<State, Event>>,
+        event_loop: &EventLoop<EngineEvent<State, Event>>,
+        config: RunConfig,
+        constructors: Vec<FlowConstructor<State, Event>>,
+    ) -> Self {
+        let proxy = EngineProxy::new(event_loop.create_proxy());
+        let mut app = Self::with_proxy(proxy, config, constructors);
+        app.owns_event_loop = true;
+        app
+    }
+
+    fn with_proxy(
+        proxy: EngineProxy<State, Event>,
+        config: RunConfig,
         constructors: Vec<FlowConstructor<State, Event>>,
     ) -> Self {
-        let proxy = event_loop.create_proxy();
+        let sender = EventSender {
+            proxy: proxy.clone(),
+        };
         #[cfg(not(target_arch = "wasm32"))]
         let async_runtime = tokio::runtime::Runtime::new().unwrap();
+        #[cfg(feature = "record")]
+        let session = config.recorder.clone().map(Session::Record);
         Self {
             #[cfg(not(target_arch = "wasm32"))]
             async_runtime,
             proxy,
+            events: Arc::new(sender),
+            config,
             state: None,
-            graphics_flows: Vec::new(),
+            graphics_flows: Flows::new(),
             constructors: Some(constructors),
+            loading: None,
+            queued_events: Vec::new(),
+            #[cfg(not(target_arch = "wasm32"))]
+            pending: Vec::new(),
             last_time: Instant::now(),
-            time_since_tick: Duration::from_millis(0),
+            start_time: Instant::now(),
+            ticks: TickAccumulator::default(),
+            #[cfg(all(feature = "gamepad", not(target_arch = "wasm32")))]
+            gamepads: Gamepads::new(),
+            fixed_steps: TickAccumulator::default(),
+            notified_focus: None,
+            notified_paused: false,
+            resize: ResizeDebouncer::default(),
+            recovery: SurfaceRecovery::default(),
+            touch: TouchGestures::default(),
+            pointer: PointerGestures::default(),
+            error: None,
+            exiting: false,
+            owns_event_loop: false,
+            window_id: None,
+            restore: None,
+            #[cfg(feature = "record")]
+            session,
         }
     }
-}
 
-pub(crate) enum FlowEvent<State: 'static, Event: 'static> {
-    #[cfg(target_arch = "wasm32")]
-    Initialized {
-        state: AppState<State>,
-        flows: Vec<Box<dyn GraphicsFlow<State, Event>>>,
-    },
-    #[allow(dead_code)]
-    Id((u32, HashSet<usize>)),
-    #[allow(dead_code)]
-    #[cfg(not(target_arch = "wasm32"))]
-    Mut(Box<dyn FnOnce(&mut State) + Send>),
-    #[cfg(target_arch = "wasm32")]
-    Mut(Box<dyn FnOnce(&mut State)>),
-    #[allow(dead_code)]
-    Custom(Event),
-    #[allow(dead_code)]
-    Exit,
-}
-impl<State, Event> Debug for FlowEvent<State, Event> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            #[cfg(target_arch = "wasm32")]
-            Self::Initialized { state: _, flows } => {
-                f.debug_struct("Initialized").field("flows", flows).finish()
-            }
-            Self::Id(arg0) => f.debug_tuple("Id").field(arg0).finish(),
-            Self::Mut(_) => f.write_str("Mut(|&mut State| -> {...})"),
-            Self::Custom(_) => f.write_str("Custom(E)"),
-            Self::Exit => f.write_str("Exit"),
+    /// Logs an error the app recovers from and passes it to the handler of
+    /// [`RunConfig::with_error_handler`].
+    fn report_error(&self, error: anyhow::Error) {
+        log::error!("{error:#}");
+        if let Some(handler) = &self.config.on_error {
+            (handler.0)(&error);
+        }
+    }
+
+    /// Stops the event loop because the app couldn't start or recover from a lost GPU,
+    /// `run` returns `error`.
+    fn fail(&mut self, event_loop: &ActiveEventLoop, error: Error) {
+        log::error!("App failed: {error}");
+        #[cfg(feature = "record")]
+        self.save_recording();
+        if let Some(handler) = &self.config.on_fatal_error {
+            (handler.0)(&error);
+        }
+        self.error = Some(error);
+        if self.owns_event_loop {
+            event_loop.exit();
+        } else {
+            self.exit(event_loop);
         }
     }
 }
 
-impl<State: 'static + Default, Event: Send + 'static> ApplicationHandler<FlowEvent<State, Event>>
-    for App<State, Event>
+impl<State, Event> App<State, Event>
+where
+    State: 'static,
+    Event: Send + 'static,
 {
-    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
-        #[allow(unused_mut)]
-        let mut window_attributes = Window::default_attributes();
+    /// Adds constructed flows in order, each id matching the one its constructor was given.
+    fn insert_flows(&mut self, flows: Vec<Box<dyn GraphicsFlow<State, Event>>>) {
+        for flow in flows {
+            let flow = self.guard_flow(self.graphics_flows.next_id(), flow);
+            let priority = flow.priority();
+            self.graphics_flows.insert(flow, priority);
+        }
+    }
 
-        #[cfg(target_arch = "wasm32")]
-        {
-            use wasm_bindgen::JsCast;
-            use winit::platform::web::WindowAttributesExtWebSys;
+    /// Poisons the flow once it panics and tells the others through `on_flow_panic`.
+    #[cfg(feature = "catch-flow-panics")]
+    fn guard_flow(
+        &self,
+        flow_id: FlowId,
+        flow: Box<dyn GraphicsFlow<State, Event>>,
+    ) -> Box<dyn GraphicsFlow<State, Event>> {
+        let proxy = self.proxy.clone();
+        Box::new(PanicGuard::new(flow, flow_id, move |panic| {
+            if proxy.send_event(FlowEvent::Panicked(panic)).is_err() {
+                log::warn!("The event loop closed before a flow panic was reported");
+            }
+        }))
+    }
 
-            const CANVAS_ID: &str = "canvas";
+    #[cfg(not(feature = "catch-flow-panics"))]
+    fn guard_flow(
+        &self,
+        _flow_id: FlowId,
+        flow: Box<dyn GraphicsFlow<State, Event>>,
+    ) -> Box<dyn GraphicsFlow<State, Event>> {
+        flow
+    }
 
-            let window = wgpu::web_sys::window().unwrap_throw();
-            let document = window.document().unwrap_throw();
-            let canvas = document.get_element_by_id(CANVAS_ID).unwrap_throw();
-            let html_canvas_element = canvas.unchecked_into();
-            window_attributes = window_attributes.with_canvas(Some(html_canvas_element));
+    /// Calls `on_exit` on every flow and stops the event loop.
+    fn exit(&mut self, event_loop: &ActiveEventLoop) {
+        if std::mem::replace(&mut self.exiting, true) {
+            return;
         }
+        if let Some(state) = &mut self.state {
+            for flow in self.graphics_flows.iter_mut() {
+                flow.on_exit(&state.ctx, &mut state.state);
+            }
+        }
+        #[cfg(feature = "record")]
+        self.save_recording();
+        // Constructors that are still running never finish
+        self.loading = None;
+        if self.owns_event_loop {
+            event_loop.exit();
+        }
+        // The page outlives the event loop on the web and a host's loop keeps running,
+        // dropping the state closes the window, stops the redraws and releases the surface
+        if cfg!(target_arch = "wasm32") || !self.owns_event_loop {
+            self.state = None;
+        }
+    }
 
-        let window = Arc::new(event_loop.create_window(window_attributes).unwrap());
-
-        let constructors = self.constructors.take().unwrap();
-
-        let init_future = async move {
-            let app_state = AppState::new(window).await;
+    /// Writes the recording of a [`Recorder`] once the app exits.
+    #[cfg(feature = "record")]
+    fn save_recording(&mut self) {
+        let session = self
+            .state
+            .as_mut()
+            .and_then(|state| state.ctx.session.take());
+        if let Some(Session::Record(recorder)) = session.or(self.session.take())
+            && let Err(e) = recorder.save()
+        {
+            log::error!(
+                "Unable to save the recording to {}: {e}",
+                recorder.path().display()
+            );
+        }
+    }
 
-            let flow_futures: Vec<_> = constructors
-                .into_iter()
-                // The clone in into() leverages the internal Arcs of Device and Queue and thus only clones the ref
-                .map(|constructor| constructor((&app_state.ctx).into()))
-                .collect();
-            let flows: Vec<_> = futures::future::join_all(flow_futures).await;
-            (app_state, flows)
+    /// Notifies the flows once the app was suspended or resumed. Frames stop meanwhile and
+    /// the suspended time is skipped.
+    fn set_suspended(&mut self, suspended: bool) {
+        let Some(state) = &mut self.state else {
+            return;
         };
+        if state.ctx.is_suspended() == suspended {
+            return;
+        }
+        state.ctx.set_suspended(suspended);
+        if suspended {
+            state.suspend();
+        } else {
+            // Reconfigures the surface, which may have been dropped by the OS
+            let size = state.window.inner_size();
+            self.resize.request(size.width, size.height);
+            self.last_time = Instant::now();
+            state.window.request_redraw();
+        }
+        notify_flows(
+            #[cfg(not(target_arch = "wasm32"))]
+            &self.async_runtime,
+            &self.proxy,
+            &mut self.graphics_flows,
+            state,
+            |flow, ctx, state| {
+                if suspended {
+                    flow.on_suspend(ctx, state)
+                } else {
+                    flow.on_resume(ctx, state)
+                }
+            },
+        );
+        self.sync_flows();
+    }
 
+    /// Polls `future` once without blocking, within the runtime on native. Native futures
+    /// wake the event loop through [`ProxyWaker`], on the web they're polled periodically.
+    fn poll_now<F: Future + ?Sized>(&self, future: Pin<&mut F>) -> Poll<F::Output> {
         #[cfg(not(target_arch = "wasm32"))]
-        {
-            let (mut app_state, flows) = self.async_runtime.block_on(init_future);
-            self.graphics_flows = flows;
-            self.graphics_flows.iter_mut().for_each(|flow| {
-                let events = flow.on_init(&mut app_state.ctx, &mut app_state.state);
-                let proxy = self.proxy.clone();
-                handle_flow_output(
-                    &self.async_runtime,
-                    &mut app_state.state,
-                    &mut app_state.ctx,
-                    proxy,
-                    events,
-                );
-            });
-            self.state = Some(app_state);
-        }
+        let _runtime = self.async_runtime.enter();
+        #[cfg(not(target_arch = "wasm32"))]
+        let waker = std::task::Waker::from(Arc::new(ProxyWaker(self.proxy.clone())));
+        #[cfg(target_arch = "wasm32")]
+        let waker = std::task::Waker::noop().clone();
+        future.poll(&mut std::task::Context::from_waker(&waker))
+    }
+
+    /// Resolves `future` while the frames keep rendering and handles its result. Native futures
+    /// are polled by the event loop like startup as they aren't `Send`, on the web they're
+    /// spawned locally.
+    fn spawn_local(&mut self, future: impl Future<Output = Resolved<State, Event>> + 'static) {
+        #[cfg(not(target_arch = "wasm32"))]
+        self.pending.push(Box::pin(future));
 
         #[cfg(target_arch = "wasm32")]
         {
             let proxy = self.proxy.clone();
             wasm_bindgen_futures::spawn_local(async move {
-                let (app_state, flows) = init_future.await;
-                assert!(
-                    proxy
-                        .send_event(FlowEvent::Initialized {
-                            state: app_state,
-                            flows,
-                        })
-                        .is_ok()
-                );
+                if proxy.send_event(FlowEvent::Resolved(future.await)).is_err() {
+                    log::warn!("The event loop closed before a future resolved");
+                }
             });
         }
     }
 
-    #[allow(unused_mut)]
-    fn user_event(&mut self, event_loop: &ActiveEventLoop, mut event: FlowEvent<State, Event>) {
-        match event {
-            #[cfg(target_arch = "wasm32")]
-            FlowEvent::Initialized { state, flows } => {
-                // This is the message from our wasm `spawn_local`
-                self.state = Some(state);
-                self.graphics_flows = flows;
-
-                // Important: Trigger a resize and redraw now that we are initialized
-                let app_state = self.state.as_mut().unwrap();
-                let size = app_state.ctx.window.inner_size();
-                app_state.resize(size.width, size.height);
-                self.graphics_flows.iter_mut().for_each(|flow| {
-                    let events = flow.on_init(&mut app_state.ctx, &mut app_state.state);
-                    let proxy = self.proxy.clone();
-                    handle_flow_output(
-                        #[cfg(not(target_arch = "wasm32"))]
-                        &self.async_runtime,
-                        &mut app_state.state,
-                        &mut app_state.ctx,
-                        proxy,
-                        events,
-                    );
-                });
-                app_state.ctx.window.request_redraw();
-            }
-            FlowEvent::Id((pick_id, flow_ids)) => {
-                if let Some(state) = &mut self.state {
-                    state.ctx.mouse.toggle(PickId(pick_id));
-                    flow_ids.into_iter().for_each(|flow_id| {
-                        self.graphics_flows
-                            .get_mut(flow_id)
-                            .map(|flow| flow.on_click(&state.ctx, &mut state.state, PickId(pick_id)));
-                    });
-                }
-            }
-            FlowEvent::Custom(custom_event) => {
-                if let Some(state) = &mut self.state {
-                    let result = self
-                        .graphics_flows
-                        .iter_mut()
-                        .fold(Some(custom_event), |event, flow| {
-                            flow.on_custom_events(&state.ctx, &mut state.state, event?)
-                        });
-                    if result.is_some() {
-                        log::warn!("Warning! Custom event was not consumed this cycle");
-                    }
-                }
+    /// Polls the futures of [`App::spawn_local`] once and handles those that resolved.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn poll_pending(&mut self) {
+        let mut resolved = Vec::new();
+        for mut future in std::mem::take(&mut self.pending) {
+            match self.poll_now(future.as_mut()) {
+                Poll::Pending => self.pending.push(future),
+                Poll::Ready(result) => resolved.push(result),
             }
-            FlowEvent::Mut(fn_once) => {
+        }
+        if resolved.is_empty() {
+            return;
+        }
+        for result in resolved {
+            self.handle_resolved(result);
+        }
+        context::request_frame();
+        self.sync_flows();
+    }
+
+    /// Handles the result of a future of [`App::spawn_local`].
+    fn handle_resolved(&mut self, resolved: Resolved<State, Event>) {
+        match resolved {
+            Resolved::Configure(ticket, configure) => {
+                let configure = configure
+                    .map_err(|e| self.report_error(e.context("Configuring the context failed")))
+                    .ok();
                 if let Some(state) = &mut self.state {
-                    fn_once(&mut state.state);
+                    apply_configure(&mut state.ctx, ticket, configure);
                 }
             }
-            FlowEvent::Exit => {
-                event_loop.exit();
-            }
+            Resolved::Spawned(flow_id, Ok(flow)) => self.add_flow(flow_id, flow),
+            Resolved::Spawned(flow_id, Err(e)) => self.report_error(
+                anyhow::Error::new(e).context(format!("Failed to spawn flow {}", flow_id.0)),
+            ),
         }
     }
 
-    fn device_event(
-        &mut self,
-        _event_loop: &ActiveEventLoop,
-        _device_id: DeviceId,
-        event: DeviceEvent,
-    ) {
-        let state = match &mut self.state {
-            Some(state) => state,
+    /// Advances startup: creates the loading screen once the device is ready and initializes
+    /// the flows once every constructor resolved.
+    fn poll_loading(&mut self, event_loop: &ActiveEventLoop) {
+        let loading = match self.loading.take() {
+            Some(Loading::Starting(mut future)) => match self.poll_now(future.as_mut()) {
+                Poll::Pending => Loading::Starting(future),
+                Poll::Ready(Ok(state)) => self.start_constructing(state),
+                Poll::Ready(Err(e)) => return self.fail(event_loop, e),
+            },
+            Some(loading) => loading,
             None => return,
         };
-        if let DeviceEvent::MouseMotion { delta: (dx, dy) } = event {
-            // TODO: make the below pattern/factor configurable
-            let speed_factor = 5.0;
-            if let MouseButtonState::Right = state.ctx.mouse.pressed {
-                state
-                    .ctx
-                    .camera
-                    .controller
-                    .handle_mouse(dx * speed_factor, dy * speed_factor);
-            }
-        }
-        self.graphics_flows.iter_mut().for_each(|f| {
-            let events = f.on_device_events(&state.ctx, &mut state.state, &event);
-            let proxy = self.proxy.clone();
-            handle_flow_output(
-                #[cfg(not(target_arch = "wasm32"))]
-                &self.async_runtime,
-                &mut state.state,
-                &mut state.ctx,
-                proxy,
-                events,
-            );
+        self.loading = match loading {
+            Loading::Constructing {
+                state,
+                mut flows,
+                screen,
+            } => match self.poll_now(flows.as_mut()) {
+                Poll::Pending => Some(Loading::Constructing {
+                    state,
+                    flows,
+                    screen,
+                }),
+                Poll::Ready(Ok(flows)) => return self.finish_loading(*state, flows),
+                Poll::Ready(Err(e)) => return self.fail(event_loop, e),
+            },
+            starting => Some(starting),
+        };
+    }
+
+    /// Calls the flow constructors and the loading screen's constructor.
+    fn start_constructing(&mut self, mut state: AppState<State>) -> Loading<State, Event> {
+        // Resizes before the device was ready were dropped
+        let size = state.window.inner_size();
+        state.resize(size.width, size.height);
+        state.ctx.loading = Some(ProgressTracker::default());
+        let screen = self.config.loading_screen.construct(InitContext {
+            progress: None,
+            ..(&state.ctx).into()
+        });
+
+        // Flows are inserted in constructor order once resolved, so they get consecutive ids
+        let first_id = self.graphics_flows.next_id();
+        let flow_futures: Vec<_> = self
+            .constructors
+            .take()
+            .unwrap_or_default()
+            .into_iter()
+            .enumerate()
+            // The clone in into() leverages the internal Arcs of Device and Queue and thus
+            // only clones the ref
+            .map(|(i, constructor)| {
+                constructor(InitContext {
+                    flow_id: Some(FlowId(first_id.0 + i)),
+                    events: Some(self.events.clone()),
+                    ..(&state.ctx).into()
+                })
+            })
+            .collect();
+        let flows = Box::pin(async move {
+            let flows = futures::future::join_all(flow_futures).await;
+            flows.into_iter().collect::<Result<Vec<_>, Error>>()
         });
+        state.window.request_redraw();
+        Loading::Constructing {
+            state: Box::new(state),
+            flows,
+            screen,
+        }
     }
 
-    fn window_event(
+    /// Initializes the constructed flows and starts the app's frames.
+    fn finish_loading(
         &mut self,
-        event_loop: &ActiveEventLoop,
-        _window_id: winit::window::WindowId,
-        event: WindowEvent,
+        mut app_state: AppState<State>,
+        flows: Vec<Box<dyn GraphicsFlow<State, Event>>>,
     ) {
-        let state = match &mut self.state {
-            Some(state) => state,
-            None => return,
-        };
-
-        // general stuff
-        state.ctx.camera.controller.handle_window_events(&event);
-
-        if let WindowEvent::CursorMoved {
-            device_id: _,
-            position,
-        } = event
+        app_state.ctx.loading = None;
+        #[cfg(feature = "record")]
         {
-            let dx = position.x - state.ctx.mouse.coords.x;
-            let dy = position.y - state.ctx.mouse.coords.y;
-            state.ctx.mouse.prev_coords = state.ctx.mouse.coords;
-            state.ctx.mouse.coords = position;
-            if let MouseButtonState::Right = state.ctx.mouse.pressed {
-                let speed_factor = 5.0;
-                state
-                    .ctx
-                    .camera
-                    .controller
-                    .handle_mouse(dx * speed_factor, dy * speed_factor);
-            }
-        };
-
-        // Update config before dispatching so components see current dimensions.
-        if let WindowEvent::Resized(size) = event {
-            state.resize(size.width, size.height);
+            app_state.ctx.session = self.session.take();
         }
-
-        self.graphics_flows.iter_mut().for_each(|f| {
-            let events = f.on_window_events(&state.ctx, &mut state.state, &event);
-            let proxy = self.proxy.clone();
-            handle_flow_output(
-                #[cfg(not(target_arch = "wasm32"))]
-                &self.async_runtime,
-                &mut state.state,
-                &mut state.ctx,
-                proxy,
-                events,
+        let restored = self.restore.take().map(|snapshot| {
+            app_state.state = snapshot.state;
+            snapshot.engine
+        });
+        self.insert_flows(flows);
+        self.graphics_flows
+            .iter_mut_with_ids()
+            .for_each(|(flow_id, flow)| {
+                let start = Instant::now();
+                let events = flow.on_init(&mut app_state.ctx, &mut app_state.state);
+                let proxy = self.proxy.clone();
+                handle_flow_output(
+                    #[cfg(not(target_arch = "wasm32"))]
+                    &self.async_runtime,
+                    &mut app_state.state,
+                    &mut app_state.ctx,
+                    proxy,
+                    events,
+                );
+                app_state
+                    .ctx
+                    .profiler
+                    .record(flow_id, Hook::Init, start.elapsed());
+            });
+        // The saved view wins over the one the flows configured
+        if let Some(snapshot) = restored {
+            app_state.ctx.restore(&snapshot);
+            notify_flows(
+                #[cfg(not(target_arch = "wasm32"))]
+                &self.async_runtime,
+                &self.proxy,
+                &mut self.graphics_flows,
+                &mut app_state,
+                |flow, ctx, state| flow.on_restore(ctx, state, &snapshot),
+            );
+        }
+        // Delivers the current size through `on_resize` with the first frame
+        let size = app_state.window.inner_size();
+        self.resize.request(size.width, size.height);
+        app_state.window.request_redraw();
+        self.state = Some(app_state);
+        for event in std::mem::take(&mut self.queued_events) {
+            if self.proxy.send_event(event).is_err() {
+                log::warn!("The event loop closed before an event of a constructor was delivered");
+            }
+        }
+        self.sync_flows();
+        context::request_frame();
+    }
+
+    /// Keeps the window responsive and draws the loading screen until the flows exist.
+    fn loading_window_event(&mut self, event_loop: &ActiveEventLoop, event: WindowEvent) {
+        if let WindowEvent::CloseRequested = event {
+            return self.exit(event_loop);
+        }
+        let Some(Loading::Constructing { state, screen, .. }) = &mut self.loading else {
+            return;
+        };
+        match event {
+            WindowEvent::Resized(size) => state.resize(size.width, size.height),
+            WindowEvent::RedrawRequested => {
+                let dt = self.last_time.elapsed();
+                self.last_time = Instant::now();
+                // There's no state or event loop for the loading screen's output yet
+                if let Some(screen) = screen {
+                    let _ = screen.on_update(&state.ctx, &mut (), dt);
+                }
+                state.render_loading(screen);
+            }
+            _ => (),
+        }
+    }
+
+    /// Adds a flow of `Out::SpawnFlow` and initializes it.
+    fn add_flow(&mut self, flow_id: FlowId, flow: Box<dyn GraphicsFlow<State, Event>>) {
+        let mut flow = self.guard_flow(flow_id, flow);
+        let Some(state) = &mut self.state else {
+            return;
+        };
+        let start = Instant::now();
+        let events = flow.on_init(&mut state.ctx, &mut state.state);
+        handle_flow_output(
+            #[cfg(not(target_arch = "wasm32"))]
+            &self.async_runtime,
+            &mut state.state,
+            &mut state.ctx,
+            self.proxy.clone(),
+            events,
+        );
+        state
+            .ctx
+            .profiler
+            .record(flow_id, Hook::Init, start.elapsed());
+        let priority = flow.priority();
+        self.graphics_flows.insert_as(flow_id, flow, priority);
+        context::request_frame();
+    }
+
+    /// Starts constructing the flows of `Out::SpawnFlow`, removes those of [`Context::remove_flow`]
+    /// after calling their `on_exit` and applies [`Context::set_flow_order`].
+    fn sync_flow_list(&mut self) {
+        let Some(state) = &mut self.state else {
+            return;
+        };
+        let mut spawned = Vec::new();
+        for constructor in std::mem::take(&mut state.ctx.spawned_flows) {
+            // Only `handle_flow_output` of this app pushes constructors
+            let Ok(constructor) = constructor.downcast::<FlowConstructor<State, Event>>() else {
+                continue;
+            };
+            let flow_id = self.graphics_flows.reserve_id();
+            let future = constructor(InitContext {
+                flow_id: Some(flow_id),
+                events: Some(self.events.clone()),
+                ..(&state.ctx).into()
+            });
+            spawned.push(async move { Resolved::Spawned(flow_id, future.await) });
+        }
+        for flow_id in state.ctx.take_removed_flows() {
+            if let Some(mut flow) = self.graphics_flows.remove(flow_id) {
+                state.ctx.set_flow_active(flow_id, FlowActivity::ACTIVE);
+                flow.on_exit(&state.ctx, &mut state.state);
+                context::request_frame();
+            }
+        }
+        for future in spawned {
+            self.spawn_local(future);
+        }
+        if let Some(state) = &self.state
+            && let Some(order) = state.ctx.take_flow_order()
+        {
+            self.graphics_flows.reorder(&order);
+            context::request_frame();
+        }
+    }
+
+    /// Notifies flows about focus and pause changes made since the last event and applies
+    /// flows added or removed meanwhile.
+    fn sync_flows(&mut self) {
+        self.sync_configures();
+        self.sync_loads();
+        self.sync_snapshots();
+        self.sync_deferred_events();
+        self.sync_flow_list();
+        self.sync_keyboard_focus();
+        self.sync_paused();
+    }
+
+    /// Runs the closures of `Out::Snapshot` now that no hook is halfway through its changes.
+    fn sync_snapshots(&mut self) {
+        let Some(state) = &mut self.state else {
+            return;
+        };
+        for write in std::mem::take(&mut state.ctx.snapshots) {
+            // Only `handle_flow_output` of this app pushes them
+            let Ok(write) = write.downcast::<SnapshotFn<State>>() else {
+                continue;
+            };
+            write(SnapshotWriter::new(&state.ctx, &state.state));
+        }
+    }
+
+    /// Resolves the futures of `Out::ConfigureFut` through [`App::spawn_local`].
+    fn sync_configures(&mut self) {
+        let Some(state) = &mut self.state else {
+            return;
+        };
+        for ConfigureFuture { ticket, future } in std::mem::take(&mut state.ctx.configure_futures) {
+            self.spawn_local(async move { Resolved::Configure(ticket, future.await) });
+        }
+    }
+
+    /// Runs the futures of [`Context::spawn_load`] in the background. Their uploads start
+    /// once they're handed back through `FlowEvent::Loaded`.
+    fn sync_loads(&mut self) {
+        let Some(state) = &self.state else {
+            return;
+        };
+        for (ticket, future) in state.ctx.streams.borrow_mut().take_spawned() {
+            let proxy = self.proxy.clone();
+            let load = async move {
+                let job = future.await;
+                if proxy.send_event(FlowEvent::Loaded(ticket, job)).is_err() {
+                    log::warn!("The event loop closed before a streamed load resolved");
+                }
+            };
+            #[cfg(not(target_arch = "wasm32"))]
+            self.async_runtime.spawn(load);
+            #[cfg(target_arch = "wasm32")]
+            wasm_bindgen_futures::spawn_local(load);
+        }
+    }
+
+    /// Posts the events of `Context::defer_event` so they are handled like `Out::Emit`.
+    fn sync_deferred_events(&mut self) {
+        let Some(state) = &self.state else {
+            return;
+        };
+        for event in state.ctx.take_deferred_events() {
+            let Ok(event) = event.downcast::<Event>() else {
+                log::error!("Dropped a deferred event that isn't of the app's event type");
+                continue;
+            };
+            if self.proxy.send_event(FlowEvent::Custom(*event)).is_err() {
+                log::warn!("The event loop closed before a deferred event was delivered");
+            }
+        }
+    }
+
+    /// Drops focus held by a flow that no longer exists and notifies flows whose focus changed.
+    fn sync_keyboard_focus(&mut self) {
+        let Some(state) = &mut self.state else {
+            return;
+        };
+        if state
+            .ctx
+            .keyboard_focus()
+            .is_some_and(|flow_id| !self.graphics_flows.contains(flow_id))
+        {
+            state.ctx.release_focus();
+        }
+        let focus = state.ctx.keyboard_focus();
+        if focus == self.notified_focus {
+            return;
+        }
+        let previous = std::mem::replace(&mut self.notified_focus, focus);
+        state.window.set_ime_allowed(focus.is_some());
+        let changes = previous
+            .map(|flow_id| (flow_id, false))
+            .into_iter()
+            .chain(focus.map(|flow_id| (flow_id, true)));
+        for (flow_id, focused) in changes {
+            if let Some(flow) = self.graphics_flows.get_mut(flow_id) {
+                let events = flow.on_focus_changed(&state.ctx, &mut state.state, focused);
+                handle_flow_output(
+                    #[cfg(not(target_arch = "wasm32"))]
+                    &self.async_runtime,
+                    &mut state.state,
+                    &mut state.ctx,
+                    self.proxy.clone(),
+                    events,
+                );
+            }
+        }
+    }
+
+    fn sync_paused(&mut self) {
+        let Some(state) = &mut self.state else {
+            return;
+        };
+        let paused = state.ctx.is_paused();
+        if paused == self.notified_paused {
+            return;
+        }
+        self.notified_paused = paused;
+        self.graphics_flows.iter_mut().for_each(|flow| {
+            let events = flow.on_pause_changed(&state.ctx, &mut state.state, paused);
+            handle_flow_output(
+                #[cfg(not(target_arch = "wasm32"))]
+                &self.async_runtime,
+                &mut state.state,
+                &mut state.ctx,
+                self.proxy.clone(),
+                events,
+            );
+        });
+    }
+}
+
+/// Surface change to apply before the next frame, see [`ResizeDebouncer`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ResizeAction {
+    Apply(u32, u32),
+    Suspend,
+}
+
+/// Frames updates, ticks and picks are held for at most while the surface recovers.
+const MAX_RECOVERY_FRAMES: u32 = 60;
+/// Longest wait between two attempts to get a lost surface back, in frames.
+const MAX_RECOVERY_BACKOFF: u32 = 16;
+
+/// Whether frames run normally or wait for a lost or outdated surface to come back.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum SurfaceRecovery {
+    #[default]
+    Running,
+    Recovering {
+        // Frames since the surface was lost
+        frames: u32,
+        // Attempts to reconfigure it so far
+        attempts: u32,
+        // Frame of the next attempt
+        retry_at: u32,
+    },
+}
+
+impl SurfaceRecovery {
+    /// The surface was lost or outdated. The first attempt follows with the next frame, failed
+    /// attempts keep backing off.
+    fn lost(&mut self) {
+        if *self == Self::Running {
+            *self = Self::Recovering {
+                frames: 0,
+                attempts: 0,
+                retry_at: 1,
+            };
+        }
+    }
+
+    /// Advances a frame, `true` if it draws. Recovering frames only draw to reconfigure the
+    /// surface and try again, waiting twice as long after each attempt.
+    fn frame(&mut self) -> bool {
+        let Self::Recovering {
+            frames,
+            attempts,
+            retry_at,
+        } = self
+        else {
+            return true;
+        };
+        *frames += 1;
+        if *frames < *retry_at {
+            return false;
+        }
+        *attempts += 1;
+        *retry_at = *frames + 2u32.saturating_pow(*attempts).min(MAX_RECOVERY_BACKOFF);
+        true
+    }
+
+    fn is_recovering(&self) -> bool {
+        *self != Self::Running
+    }
+
+    /// Whether updates, ticks and picks wait for the surface.
+    fn holds_updates(&self) -> bool {
+        matches!(self, Self::Recovering { frames, .. } if *frames < MAX_RECOVERY_FRAMES)
+    }
+
+    /// A frame got its surface texture, `true` if that ended a recovery.
+    fn acquired(&mut self) -> bool {
+        std::mem::take(self).is_recovering()
+    }
+}
+
+/// Collapses bursts of `Resized` events into at most one surface reconfiguration per frame.
+#[derive(Debug, Default)]
+struct ResizeDebouncer {
+    // Latest size requested since the last frame
+    requested: Option<(u32, u32)>,
+    // Last non-zero size that was applied
+    applied: Option<(u32, u32)>,
+    // Set while the window has a zero dimension, e.g. minimized on Windows
+    suspended: bool,
+}
+
+impl ResizeDebouncer {
+    fn request(&mut self, width: u32, height: u32) {
+        self.requested = Some((width, height));
+    }
+
+    /// Takes the latest requested size, to be called once per frame before rendering.
+    fn take(&mut self) -> Option<ResizeAction> {
+        let (width, height) = self.requested.take()?;
+        if width == 0 || height == 0 {
+            if self.suspended {
+                return None;
+            }
+            self.suspended = true;
+            return Some(ResizeAction::Suspend);
+        }
+        if !self.suspended && self.applied == Some((width, height)) {
+            return None;
+        }
+        self.suspended = false;
+        self.applied = Some((width, height));
+        Some(ResizeAction::Apply(width, height))
+    }
+
+    /// Applies the current size again on the next `take`, e.g. to lay out the UI after a
+    /// scale change. A suspended window lays out once it is restored.
+    fn reapply(&mut self) {
+        if self.suspended {
+            return;
+        }
+        if let Some(size) = self.applied.take() {
+            self.requested.get_or_insert(size);
+        }
+    }
+
+    /// Re-applies the last valid size when a window is restored without reporting its size.
+    fn restore(&mut self) -> Option<ResizeAction> {
+        if !self.suspended || self.requested.is_some() {
+            return None;
+        }
+        let (width, height) = self.applied?;
+        self.suspended = false;
+        Some(ResizeAction::Apply(width, height))
+    }
+}
+
+/// Context modifications of `Out::ConfigureFut` waiting for the ones returned before them.
+pub(crate) type ConfigureQueue = OrderedQueue<Option<ConfigureFn>>;
+
+/// Future of `Out::ConfigureFut` with its ticket in the [`ConfigureQueue`], polled by the app
+/// once the hook returned.
+pub(crate) struct ConfigureFuture {
+    pub(crate) ticket: u64,
+    pub(crate) future: Pin<Box<dyn Future<Output = anyhow::Result<ConfigureFn>>>>,
+}
+
+impl Debug for ConfigureFuture {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ConfigureFuture")
+            .field("ticket", &self.ticket)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Future the app polls between events without blocking the frames.
+#[cfg(not(target_arch = "wasm32"))]
+type LocalFuture<State, Event> = Pin<Box<dyn Future<Output = Resolved<State, Event>>>>;
+
+/// Result of a future of [`App::spawn_local`].
+pub(crate) enum Resolved<State: 'static, Event: 'static> {
+    // `Out::ConfigureFut` with its place in the `ConfigureQueue`
+    Configure(u64, anyhow::Result<ConfigureFn>),
+    // Flow of `Out::SpawnFlow` with the id reserved for it
+    Spawned(FlowId, FlowResult<State, Event>),
+}
+
+impl<State, Event> Debug for Resolved<State, Event> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Configure(ticket, _) => {
+                write!(f, "Configure({ticket}, |&mut Context| -> {{...}})")
+            }
+            Self::Spawned(id, flow) => f.debug_tuple("Spawned").field(id).field(flow).finish(),
+        }
+    }
+}
+
+/// Releases values in the order their tickets were handed out, although they may be resolved
+/// in any order.
+pub(crate) struct OrderedQueue<T> {
+    next_ticket: u64,
+    // Ticket of the next value to release
+    next_release: u64,
+    resolved: BTreeMap<u64, T>,
+}
+
+impl<T> Default for OrderedQueue<T> {
+    fn default() -> Self {
+        Self {
+            next_ticket: 0,
+            next_release: 0,
+            resolved: BTreeMap::new(),
+        }
+    }
+}
+
+impl<T> Debug for OrderedQueue<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OrderedQueue")
+            .field("pending", &(self.next_ticket - self.next_release))
+            .finish()
+    }
+}
+
+impl<T> OrderedQueue<T> {
+    pub(crate) fn ticket(&mut self) -> u64 {
+        self.next_ticket += 1;
+        self.next_ticket - 1
+    }
+
+    pub(crate) fn resolve(&mut self, ticket: u64, value: T) {
+        self.resolved.insert(ticket, value);
+    }
+
+    /// Values of consecutive resolved tickets from the oldest unreleased one on.
+    pub(crate) fn take_ready(&mut self) -> Vec<T> {
+        iter::from_fn(|| {
+            let value = self.resolved.remove(&self.next_release)?;
+            self.next_release += 1;
+            Some(value)
+        })
+        .collect()
+    }
+}
+
+/// Distance in pixels a finger may move before a touch stops counting as a tap.
+const TAP_SLOP: f64 = 10.0;
+
+/// Camera and pointer input derived from touch events, see [`TouchGestures`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Gesture {
+    /// A single finger touched down, acting like a pressed left mouse button.
+    Press(PhysicalPosition<f64>),
+    /// A single finger dragged by `dx`, `dy` pixels to `position`.
+    Rotate {
+        position: PhysicalPosition<f64>,
+        dx: f64,
+        dy: f64,
+    },
+    /// Two fingers moved apart by this many pixels, negative when moving together.
+    Pinch(f64),
+    /// The midpoint between two fingers moved by `dx`, `dy` pixels.
+    Pan(f64, f64),
+    /// The press ended, either because the finger lifted or a second one joined.
+    Release,
+    /// A single finger lifted without moving further than [`TAP_SLOP`].
+    Tap(PhysicalPosition<f64>),
+}
+
+/// Tracks touch points across `Started`/`Moved`/`Ended` events by their id and turns them into
+/// [`Gesture`]s: one finger rotates, two fingers pinch to zoom and drag to pan, a tap clicks.
+#[derive(Debug, Default)]
+struct TouchGestures {
+    touches: BTreeMap<u64, PhysicalPosition<f64>>,
+    // Where the only finger touched down, while it still counts as a tap
+    tap: Option<PhysicalPosition<f64>>,
+}
+
+impl TouchGestures {
+    fn handle(
+        &mut self,
+        id: u64,
+        phase: TouchPhase,
+        position: PhysicalPosition<f64>,
+    ) -> Vec<Gesture> {
+        match phase {
+            TouchPhase::Started => {
+                self.touches.insert(id, position);
+                match self.touches.len() {
+                    1 => {
+                        self.tap = Some(position);
+                        vec![Gesture::Press(position)]
+                    }
+                    2 => {
+                        self.tap = None;
+                        vec![Gesture::Release]
+                    }
+                    _ => Vec::new(),
+                }
+            }
+            TouchPhase::Moved => {
+                let Some(tracked) = self.touches.get_mut(&id) else {
+                    return Vec::new();
+                };
+                let previous = std::mem::replace(tracked, position);
+                match self.touches.len() {
+                    1 => {
+                        if self
+                            .tap
+                            .is_some_and(|start| distance(start, position) > TAP_SLOP)
+                        {
+                            self.tap = None;
+                        }
+                        vec![Gesture::Rotate {
+                            position,
+                            dx: position.x - previous.x,
+                            dy: position.y - previous.y,
+                        }]
+                    }
+                    2 => {
+                        let other = *self
+                            .touches
+                            .iter()
+                            .find(|(other, _)| **other != id)
+                            .map(|(_, other)| other)
+                            .expect("two touches are tracked");
+                        let pinch = distance(position, other) - distance(previous, other);
+                        // the midpoint moves by half of what one finger moved
+                        let pan = (
+                            (position.x - previous.x) / 2.0,
+                            (position.y - previous.y) / 2.0,
+                        );
+                        let mut gestures = Vec::new();
+                        if pinch != 0.0 {
+                            gestures.push(Gesture::Pinch(pinch));
+                        }
+                        if pan != (0.0, 0.0) {
+                            gestures.push(Gesture::Pan(pan.0, pan.1));
+                        }
+                        gestures
+                    }
+                    _ => Vec::new(),
+                }
+            }
+            TouchPhase::Ended | TouchPhase::Cancelled => {
+                if self.touches.remove(&id).is_none() || !self.touches.is_empty() {
+                    return Vec::new();
+                }
+                let tap = self.tap.take().filter(|_| phase == TouchPhase::Ended);
+                iter::once(Gesture::Release)
+                    .chain(tap.map(|_| Gesture::Tap(position)))
+                    .collect()
+            }
+        }
+    }
+}
+
+/// Mouse gesture on a pickable object, passed to [`GraphicsFlow::on_pointer`] of the flows
+/// owning the object the button went down on. Thresholds are part of [`InputSettings`].
+///
+/// [`InputSettings`]: crate::context::InputSettings
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PointerEvent {
+    /// `button` was pressed and released on `id` without dragging it.
+    Clicked { id: PickId, button: MouseButton },
+    /// A second left click on `id` shortly after the first, following its `Clicked`.
+    DoubleClicked { id: PickId },
+    /// The cursor moved past the drag threshold with a button held on `id`.
+    DragStart {
+        id: PickId,
+        start: PhysicalPosition<f64>,
+    },
+    /// The cursor moved `delta` pixels to `current` while dragging `id`.
+    Dragging {
+        id: PickId,
+        current: PhysicalPosition<f64>,
+        delta: (f64, f64),
+    },
+    /// The button was released or the window lost focus while dragging `id`.
+    DragEnd { id: PickId },
+}
+
+/// Owners of a pressed object and the gestures to pass them.
+type PointerGestureEvents = (BTreeSet<FlowId>, Vec<PointerEvent>);
+
+/// A button held down on a picked object.
+#[derive(Debug)]
+struct PointerPress {
+    button: MouseButton,
+    id: PickId,
+    owners: BTreeSet<FlowId>,
+    start: PhysicalPosition<f64>,
+    last: PhysicalPosition<f64>,
+    dragging: bool,
+    // On an element of a GUI rather than an object of the scene
+    gui: bool,
+}
+
+/// Turns presses on picked objects, cursor motion and releases into [`PointerEvent`]s.
+#[derive(Debug, Default)]
+struct PointerGestures {
+    press: Option<PointerPress>,
+    // Time, position and id of a left click that may become a double click
+    last_click: Option<(Instant, PhysicalPosition<f64>, PickId)>,
+}
+
+impl PointerGestures {
+    /// Whether a button is held down on a picked object.
+    fn holds_object(&self) -> bool {
+        self.press.is_some()
+    }
+
+    /// Whether a button is held down on an element of a GUI, which keeps the camera still.
+    fn holds_gui(&self) -> bool {
+        self.press.as_ref().is_some_and(|press| press.gui)
+    }
+
+    /// `button` went down at `position` on `picked`, an element of a GUI if `gui`. Presses on
+    /// empty space aren't tracked.
+    fn press(
+        &mut self,
+        button: MouseButton,
+        picked: Picked,
+        position: PhysicalPosition<f64>,
+        gui: bool,
+    ) {
+        self.press = picked.map(|(id, owners)| PointerPress {
+            button,
+            id: PickId(id),
+            owners,
+            start: position,
+            last: position,
+            dragging: false,
+            gui,
+        });
+    }
+
+    fn moved(
+        &mut self,
+        position: PhysicalPosition<f64>,
+        settings: &InputSettings,
+    ) -> Option<PointerGestureEvents> {
+        let press = self.press.as_mut()?;
+        let mut events = Vec::new();
+        if !press.dragging {
+            if distance(press.start, position) <= settings.drag_threshold {
+                return None;
+            }
+            press.dragging = true;
+            events.push(PointerEvent::DragStart {
+                id: press.id,
+                start: press.start,
+            });
+        }
+        let delta = (position.x - press.last.x, position.y - press.last.y);
+        press.last = position;
+        events.push(PointerEvent::Dragging {
+            id: press.id,
+            current: position,
+            delta,
+        });
+        Some((press.owners.clone(), events))
+    }
+
+    fn release(
+        &mut self,
+        button: MouseButton,
+        position: PhysicalPosition<f64>,
+        now: Instant,
+        settings: &InputSettings,
+    ) -> Option<PointerGestureEvents> {
+        let press = self.press.take_if(|press| press.button == button)?;
+        if press.dragging {
+            return Some((press.owners, vec![PointerEvent::DragEnd { id: press.id }]));
+        }
+        let mut events = vec![PointerEvent::Clicked {
+            id: press.id,
+            button,
+        }];
+        if button == MouseButton::Left {
+            let double = self.last_click.take().is_some_and(|(time, at, id)| {
+                id == press.id
+                    && now.saturating_duration_since(time) <= settings.double_click_time
+                    && distance(at, position) <= settings.double_click_distance
+            });
+            if double {
+                events.push(PointerEvent::DoubleClicked { id: press.id });
+            } else {
+                self.last_click = Some((now, position, press.id));
+            }
+        }
+        Some((press.owners, events))
+    }
+
+    /// Ends a drag that won't see its release, e.g. because the window lost focus.
+    fn cancel(&mut self) -> Option<PointerGestureEvents> {
+        let press = self.press.take().filter(|press| press.dragging)?;
+        Some((press.owners, vec![PointerEvent::DragEnd { id: press.id }]))
+    }
+}
+
+/// Most `on_tick` rounds run in one frame. A longer backlog, e.g. after the window was
+/// minimized, is dropped rather than stalling the following frames to catch up.
+pub const MAX_TICKS_PER_FRAME: u32 = 8;
+
+/// Most `on_fixed_update` steps run in one frame, so a simulation slower than real time
+/// falls behind instead of taking longer every frame.
+pub const MAX_FIXED_STEPS_PER_FRAME: u32 = 8;
+
+/// Turns frame times into fixed ticks. The time past a tick carries over to the next one, so
+/// the tick rate holds while frames are slower or irregular.
+#[derive(Debug, Default)]
+struct TickAccumulator {
+    // Time since the last tick
+    since_tick: Duration,
+}
+
+impl TickAccumulator {
+    /// Adds the frame time `elapsed` and returns the number of ticks due, at most
+    /// [`MAX_TICKS_PER_FRAME`]. Paused ticking accumulates nothing.
+    fn advance(&mut self, elapsed: Duration, tick: Duration, paused: bool) -> u32 {
+        self.advance_max(elapsed, tick, paused, MAX_TICKS_PER_FRAME)
+    }
+
+    /// Like [`TickAccumulator::advance`] with at most `max` ticks.
+    fn advance_max(&mut self, elapsed: Duration, tick: Duration, paused: bool, max: u32) -> u32 {
+        if paused {
+            return 0;
+        }
+        self.since_tick += elapsed;
+        if tick.is_zero() {
+            // Ticks every frame
+            self.since_tick = Duration::ZERO;
+            return 1;
+        }
+        let due = self.since_tick.as_nanos() / tick.as_nanos();
+        let remainder = self.since_tick.as_nanos() % tick.as_nanos();
+        if due > u128::from(max) {
+            log::warn!(
+                "Dropped {} steps the frame rate couldn't keep up with",
+                due - u128::from(max)
+            );
+        }
+        self.since_tick = Duration::from_nanos(remainder as u64);
+        due.min(u128::from(max)) as u32
+    }
+
+    /// Progress towards the next tick in `[0, 1]`.
+    fn alpha(&self, tick: Duration) -> f32 {
+        if tick.is_zero() {
+            return 1.0;
+        }
+        (self.since_tick.as_secs_f32() / tick.as_secs_f32()).min(1.0)
+    }
+
+    /// Time until the next tick given the `pending` frame time not yet added, `None` while
+    /// ticking is paused.
+    fn until_tick(&self, tick: Duration, pending: Duration, paused: bool) -> Option<Duration> {
+        (!paused).then(|| tick.saturating_sub(self.since_tick + pending))
+    }
+}
+
+// Tolerated `requestAnimationFrame` jitter, so a 30 FPS cap on a 60Hz display isn't 20 FPS
+#[cfg(target_arch = "wasm32")]
+const FRAME_CAP_SLACK: Duration = Duration::from_millis(2);
+
+/// Time until the frame cap `interval` allows the next frame, `since_frame` after the last.
+fn until_frame(interval: Option<Duration>, since_frame: Duration) -> Duration {
+    interval.map_or(Duration::ZERO, |interval| {
+        interval.saturating_sub(since_frame)
+    })
+}
+
+/// Colour and depth attachments of a frame drawn by [`draw_frame`].
+#[derive(Clone, Copy)]
+pub(crate) enum FrameTarget<'t> {
+    /// Draws to `view` through the context's MSAA and depth textures.
+    Context(&'t wgpu::TextureView),
+    /// Attachments owned by the caller, e.g. of a secondary window or the padded golden
+    /// image textures. Offscreen targets of `window` are kept for the next frame.
+    Attachments {
+        window: WindowId,
+        color: &'t wgpu::TextureView,
+        resolve: Option<&'t wgpu::TextureView>,
+        depth: &'t wgpu::TextureView,
+        size: [u32; 2],
+    },
+}
+
+/**
+ * Batches the renders of `flows` by layer and pipeline and records the scene pass (or the overdraw heatmap) into
+ * `encoder`. Shared by the event loop and [`Context::render_once`].
+ *
+ * Returns whether the overdraw counters were copied for readback, which is mapped once
+ * `encoder` is submitted.
+ */
+pub(crate) fn draw_frame<'f, S: 'f, E: Send + 'f>(
+    ctx: &mut Context,
+    flows: impl IntoIterator<Item = &'f Box<dyn GraphicsFlow<S, E>>>,
+    encoder: &mut wgpu::CommandEncoder,
+    target: FrameTarget<'_>,
+) -> bool {
+    let overdraw = ctx.debug_render() == DebugRender::Overdraw;
+    if overdraw {
+        ctx.prepare_overdraw();
+        if let Some(stats) = ctx.overdraw.as_mut().and_then(|o| o.take_stats()) {
+            ctx.stats.overdraw = Some(stats);
+        }
+    }
+
+    let mut layers = Layers::new();
+    for flow in flows {
+        flow.on_render().set_pipelines(flow.layer(), &mut layers);
+    }
+    // The clear and the pause overlay need a layer even if nothing is drawn
+    layers.entry(Layer::WORLD.index).or_default();
+    ctx.visibility = collect_visibility(layers.values().flat_map(Batches::instanced));
+
+    if let Some(resources) = ctx.overdraw.as_ref().filter(|_| overdraw) {
+        let mut overdraw_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Overdraw Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &resources.view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    store: wgpu::StoreOp::Store,
+                },
+                depth_slice: None,
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+            ..Default::default()
+        });
+        for instanced in layers.values().flat_map(Batches::instanced) {
+            if instanced.amount == 0 || instanced.instance.size() == 0 {
+                continue;
+            }
+            // the heatmap has no double sided variant, those count front faces only
+            match instanced.culled_winding() {
+                Some(wgpu::FrontFace::Ccw) => overdraw_pass.set_pipeline(&resources.pipeline_cw),
+                _ => overdraw_pass.set_pipeline(&resources.pipeline),
+            }
+            overdraw_pass.set_vertex_buffer(1, instanced.instance.slice(..));
+            overdraw_pass.draw_model_instanced(
+                instanced.model,
+                0..instanced.amount as u32,
+                ctx.active_camera_bind_group(),
+                &ctx.light.bind_group,
+            );
+        }
+        overdraw_pass.set_pipeline(&resources.pipeline_terrain);
+        for terrain in layers.values().flat_map(|batches| &batches.terrain) {
+            overdraw_pass.set_vertex_buffer(1, terrain.instance.slice(..));
+            overdraw_pass.set_bind_group(0, terrain.group, &[]);
+            overdraw_pass.set_bind_group(1, ctx.active_camera_bind_group(), &[]);
+            overdraw_pass.set_bind_group(2, &ctx.light.bind_group, &[]);
+            overdraw_pass.set_vertex_buffer(0, terrain.vertex.slice(..));
+            overdraw_pass.set_index_buffer(terrain.index.slice(..), wgpu::IndexFormat::Uint16);
+            overdraw_pass.draw_indexed(0..terrain.amount as u32, 0, 0..1);
+        }
+    }
+
+    let (window, size) = match target {
+        FrameTarget::Context(_) => (
+            WindowId::MAIN,
+            [ctx.config.width.max(1), ctx.config.height.max(1)],
+        ),
+        FrameTarget::Attachments { window, size, .. } => (window, size),
+    };
+    // Transparents read a copy of the depth, even if none of them fades
+    if !overdraw && layers.values().any(|batches| !batches.trans.is_empty()) {
+        ctx.prepare_scene_depth(window, size);
+    }
+    ctx.prepare_hdr(window, size);
+
+    let (color_view, resolve_target, depth_view) = match target {
+        FrameTarget::Context(view) => (
+            ctx.msaa_view.as_ref().unwrap_or(view),
+            ctx.msaa_view.as_ref().map(|_| view),
+            &ctx.depth_texture.view,
+        ),
+        FrameTarget::Attachments {
+            color,
+            resolve,
+            depth,
+            ..
+        } => (color, resolve, depth),
+    };
+    // With HDR the scene is drawn offscreen and tonemapped into the target at the end
+    let output = resolve_target.unwrap_or(color_view);
+    let hdr = ctx.tonemapping().and(ctx.hdr.get(&window));
+    let (color_view, resolve_target) = match hdr {
+        Some(hdr) => hdr.attachments(),
+        None => (color_view, resolve_target),
+    };
+
+    // The pause overlay dims the 3D scene, so it goes above the last layer drawing any
+    let overlay_layer = layers
+        .iter()
+        .filter(|(_, batches)| batches.has_world())
+        .map(|(&index, _)| index)
+        .next_back()
+        .unwrap_or(Layer::WORLD.index);
+
+    let far = ctx.depth().far();
+    {
+        let mut render_pass = begin_scene_pass(
+            encoder,
+            color_view,
+            resolve_target,
+            depth_view,
+            Some(ctx.clear_colour),
+            Some(far),
+        );
+
+        // Actual rendering:
+        if let Some(resources) = ctx.overdraw.as_ref().filter(|_| overdraw) {
+            render_pass.set_pipeline(&resources.heatmap);
+            render_pass.set_bind_group(0, &resources.heatmap_bind_group, &[]);
+            render_pass.draw(0..3, 0..1);
+        } else if ctx.light.model.is_some() {
+            render_pass.set_pipeline(&ctx.pipelines.light);
+            render_pass.draw_light_model(
+                ctx.light.model.as_ref().unwrap(),
+                ctx.active_camera_bind_group(),
+                &ctx.light.bind_group,
+            );
+        }
+        let highlights = ctx.active_highlights();
+        ctx.highlight.begin_frame();
+        for (position, (index, batches)) in layers.into_iter().enumerate() {
+            // The first layer's depth is cleared along with the colour
+            if position > 0 && batches.clear_depth {
+                drop(render_pass);
+                render_pass = begin_scene_pass(
+                    encoder,
+                    color_view,
+                    resolve_target,
+                    depth_view,
+                    None,
+                    Some(far),
+                );
+            }
+
+            // The heatmap replaces the 3D scene of all layers
+            if !overdraw {
+                for instanced in batches.basics {
+                    if instanced.amount == 0 {
+                        log::debug!("you attemted to render instances, nothing drawn to screen.");
+                        continue;
+                    }
+                    if instanced.instance.size() == 0 {
+                        log::debug!(
+                            "you attemted to draw an empty buffer, remember to call `write_to_buffer()` on your models."
+                        );
+                        continue;
+                    }
+                    let highlight = ctx.highlight.bind_group(
+                        &ctx.device,
+                        &ctx.queue,
+                        &highlights,
+                        instanced.id,
+                        instanced.visibility,
+                    );
+                    render_pass.set_pipeline(ctx.pipelines.basic_for(&instanced));
+                    render_pass.set_bind_group(
+                        3,
+                        highlight.as_ref().unwrap_or(ctx.highlight.none()),
+                        &[],
+                    );
+                    render_pass.set_vertex_buffer(1, instanced.instance.slice(..));
+                    render_pass.draw_model_instanced_with(
+                        &instanced.model,
+                        instanced.material_override,
+                        0..instanced.amount as u32,
+                        ctx.active_camera_bind_group(),
+                        &ctx.light.bind_group,
+                    );
+                }
+
+                render_pass.set_pipeline(&ctx.pipelines.terrain);
+                for button in batches.terrain {
+                    render_pass.set_vertex_buffer(1, button.instance.slice(..));
+                    render_pass.set_bind_group(0, button.group, &[]);
+                    render_pass.set_bind_group(1, ctx.active_camera_bind_group(), &[]);
+                    render_pass.set_bind_group(2, &ctx.light.bind_group, &[]);
+                    render_pass.set_vertex_buffer(0, button.vertex.slice(..));
+                    render_pass.set_index_buffer(button.index.slice(..), wgpu::IndexFormat::Uint16);
+                    render_pass.draw_indexed(0..button.amount as u32, 0, 0..1);
+                }
+            }
+
+            // Prepared above whenever there are transparents
+            let trans = batches.trans;
+            if let Some(scene_depth) = ctx
+                .scene_depth
+                .get(&window)
+                .filter(|_| !overdraw && !trans.is_empty())
+            {
+                let fade = trans
+                    .iter()
+                    .any(|(_, transparency)| transparency.fade_distance > 0.0);
+                // The depth can't be read while it's attached, so the pass is split to copy it
+                if fade {
+                    drop(render_pass);
+                    scene_depth.copy(&ctx.device, encoder, depth_view, far);
+                    render_pass = begin_scene_pass(
+                        encoder,
+                        color_view,
+                        resolve_target,
+                        depth_view,
+                        None,
+                        None,
+                    );
+                }
+
+                let projection = ctx.active_projection();
+                for (instanced, transparency) in trans {
+                    if instanced.amount == 0 {
+                        log::debug!("you attemted to render instances, nothing drawn to screen.");
+                        continue;
+                    }
+                    if instanced.instance.size() == 0 {
+                        log::debug!(
+                            "you attemted to draw an empty buffer, remember to call `write_to_buffer()` on your models."
+                        );
+                        continue;
+                    }
+                    let transparency_buffer =
+                        ctx.device
+                            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                                label: Some("Transparency Buffer"),
+                                contents: bytemuck::bytes_of(&transparency.to_raw(
+                                    projection.znear,
+                                    projection.zfar,
+                                    projection.reversed_z(),
+                                )),
+                                usage: wgpu::BufferUsages::UNIFORM,
+                            });
+                    let transparency_bind_group = mk_transparency_bind_group(
+                        &ctx.device,
+                        &transparency_buffer,
+                        &scene_depth.view,
+                        &ctx.layouts.transparency,
+                    );
+                    render_pass.set_pipeline(ctx.pipelines.transparent_for(&instanced));
+                    render_pass.set_bind_group(3, &transparency_bind_group, &[]);
+                    render_pass.set_vertex_buffer(1, instanced.instance.slice(..));
+                    render_pass.draw_model_instanced_with(
+                        &instanced.model,
+                        instanced.material_override,
+                        0..instanced.amount as u32,
+                        ctx.active_camera_bind_group(),
+                        &ctx.light.bind_group,
+                    );
+                }
+            }
+
+            // Dim and blur the 3D scene below the pause menu
+            let blur = ctx.pause_blur > 0.0;
+            if index == overlay_layer && ctx.is_paused() && (blur || ctx.pause_overlay.a > 0.0) {
+                let uniform = OverlayUniform::new(ctx.pause_overlay, ctx.pause_blur);
+                ctx.overlay.write(&ctx.queue, uniform);
+                let scene = resolve_target.unwrap_or(color_view).texture();
+                if blur && scene.usage().contains(wgpu::TextureUsages::COPY_SRC) {
+                    // The scene can't be sampled while it's attached, so the pass is split to
+                    // copy it
+                    drop(render_pass);
+                    let source = ctx.overlay.copy_source(&ctx.device, encoder, window, scene);
+                    render_pass = begin_scene_pass(
+                        encoder,
+                        color_view,
+                        resolve_target,
+                        depth_view,
+                        None,
+                        None,
+                    );
+                    render_pass.set_pipeline(&ctx.pipelines.overlay_blur);
+                    render_pass.set_bind_group(1, &source, &[]);
+                } else {
+                    render_pass.set_pipeline(&ctx.pipelines.overlay);
+                }
+                render_pass.set_bind_group(0, &ctx.overlay.bind_group, &[]);
+                render_pass.draw(0..3, 0..1);
+            }
+
+            render_pass.set_pipeline(&ctx.pipelines.gui);
+            render_pass.set_bind_group(1, &ctx.screen_size.bind_group, &[]);
+            for button in batches.guis {
+                render_pass.set_bind_group(0, button.group, &[]);
+                render_pass.set_vertex_buffer(0, button.vertex.slice(..));
+                render_pass.set_index_buffer(button.index.slice(..), wgpu::IndexFormat::Uint16);
+                render_pass.draw_indexed(0..button.amount as u32, 0, 0..1);
+            }
+
+            for custom in batches.customs {
+                custom(ctx, &mut render_pass);
+            }
+        }
+    }
+    if let Some(hdr) = hdr {
+        hdr.tonemap(encoder, output);
+    }
+
+    overdraw
+        && ctx
+            .overdraw
+            .as_mut()
+            .is_some_and(|resources| resources.copy_to_readback(encoder))
+}
+
+/// Main pass drawing the scene, `clear` is `None` when continuing an earlier pass, which
+/// still clears the depth to `clear_depth`, the far plane of the [`Context::depth`].
+///
+/// The pass is split for the scene depth copy and layers while `Render::Custom` closures
+/// expect a single pass type, so it doesn't borrow `encoder`. Drop it before recording
+/// anything else.
+fn begin_scene_pass(
+    encoder: &mut wgpu::CommandEncoder,
+    view: &wgpu::TextureView,
+    resolve_target: Option<&wgpu::TextureView>,
+    depth_view: &wgpu::TextureView,
+    clear: Option<wgpu::Color>,
+    clear_depth: Option<f32>,
+) -> wgpu::RenderPass<'static> {
+    let depth_load = clear_depth.map_or(wgpu::LoadOp::Load, wgpu::LoadOp::Clear);
+    encoder
+        .begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Render Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target,
+                ops: wgpu::Operations {
+                    load: clear.map_or(wgpu::LoadOp::Load, wgpu::LoadOp::Clear),
+                    store: wgpu::StoreOp::Store,
+                },
+                depth_slice: None,
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: depth_view,
+                depth_ops: Some(wgpu::Operations {
+                    load: depth_load,
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            occlusion_query_set: None,
+            timestamp_writes: None,
+            ..Default::default()
+        })
+        .forget_lifetime()
+}
+
+fn distance(a: PhysicalPosition<f64>, b: PhysicalPosition<f64>) -> f64 {
+    (a.x - b.x).hypot(a.y - b.y)
+}
+
+/// Whether `flow` should receive input, clicks and ticks in the current pause state.
+fn is_running<S, E: Send>(flow: &dyn GraphicsFlow<S, E>, ctx: &Context) -> bool {
+    !ctx.is_paused() || flow.runs_while_paused()
+}
+
+/// Whether the running flow `flow_id` should receive input and clicks.
+fn takes_input<S, E: Send>(flow_id: FlowId, flow: &dyn GraphicsFlow<S, E>, ctx: &Context) -> bool {
+    is_running(flow, ctx) && ctx.flow_activity(flow_id).input
+}
+
+/// Whether `picked` is an element of a GUI drawn by one of its owners rather than an object
+/// of the scene.
+fn picks_gui<S, E: Send>(graphics_flows: &FlowList<S, E>, picked: &Picked) -> bool {
+    picked.as_ref().is_some_and(|(id, owners)| {
+        owners.iter().any(|&flow_id| {
+            graphics_flows
+                .get(flow_id)
+                .is_some_and(|flow| flow.on_render().draws_gui(PickId(*id)))
+        })
+    })
+}
+
+/// Picks the object under the mouse and calls `on_click_with` of the running flows owning it.
+/// Returns what was picked, always `None` on WASM where the pick is read back later.
+fn dispatch_click<State, Event: Send>(
+    #[cfg(not(target_arch = "wasm32"))] async_runtime: &tokio::runtime::Runtime,
+    proxy: &EngineProxy<State, Event>,
+    graphics_flows: &mut FlowList<State, Event>,
+    state: &mut AppState<State>,
+    button: MouseButton,
+) -> Picked {
+    if state.ctx.is_recovering_surface() {
+        return None;
+    }
+    // Replays take the recorded pick
+    #[cfg(feature = "record")]
+    let replayed = state.ctx.session.as_mut().and_then(Session::replayed_click);
+    #[cfg(not(feature = "record"))]
+    let replayed = None;
+    // The latest hover pick is still valid while the cursor rests
+    let (picked, hit) = replayed
+        .or_else(|| state.ctx.hover.cached(state.ctx.mouse.coords))
+        .unwrap_or_else(|| {
+            // Picks what flows moved the camera to since the last frame
+            state.ctx.upload_camera_uniforms();
+            draw_to_pick_buffer::<State, Event>(
+                #[cfg(not(target_arch = "wasm32"))]
+                async_runtime,
+                graphics_flows,
+                &state.ctx,
+                &state.ctx.mouse,
+                #[cfg(target_arch = "wasm32")]
+                proxy.clone(),
+                #[cfg(target_arch = "wasm32")]
+                PickPurpose::Click(button),
+            )
+        });
+    #[cfg(feature = "record")]
+    if let Some(session) = &mut state.ctx.session {
+        session.record_click(&picked, hit);
+    }
+    let (pick_id, flow_ids) = picked.clone()?;
+    // The world behind a GUI wasn't hit
+    let hit = PickHit {
+        position: hit.position.filter(|_| !picks_gui(graphics_flows, &picked)),
+        ..hit
+    };
+    click_flows(
+        #[cfg(not(target_arch = "wasm32"))]
+        async_runtime,
+        proxy,
+        graphics_flows,
+        state,
+        (pick_id, &flow_ids),
+        hit,
+        button,
+    );
+    // Clicks on a GUI don't select what's behind it
+    if !picks_gui(graphics_flows, &picked) {
+        state.ctx.mouse.toggle(PickId(pick_id));
+    }
+    if flow_ids.len() > 1 && pick_id != PickId::default().0 {
+        log::warn!(
+            "Multiple flows (ids {:?}) want to react to the render ID {}.",
+            flow_ids,
+            pick_id
+        );
+    }
+    picked
+}
+
+/// Passes a click on a pick id to those of its flows that take input, in dispatch order.
+fn click_flows<State, Event: Send>(
+    #[cfg(not(target_arch = "wasm32"))] async_runtime: &tokio::runtime::Runtime,
+    proxy: &EngineProxy<State, Event>,
+    graphics_flows: &mut FlowList<State, Event>,
+    state: &mut AppState<State>,
+    (pick_id, flow_ids): (u32, &BTreeSet<FlowId>),
+    hit: PickHit,
+    button: MouseButton,
+) {
+    for flow_id in graphics_flows.dispatch_order(flow_ids) {
+        let Some(flow) = graphics_flows
+            .get_mut(flow_id)
+            .filter(|flow| takes_input(flow_id, flow.as_ref(), &state.ctx))
+        else {
+            continue;
+        };
+        let start = Instant::now();
+        let id = PickId(pick_id);
+        let events = flow.on_click_with(&state.ctx, &mut state.state, id, hit, button);
+        handle_flow_output(
+            #[cfg(not(target_arch = "wasm32"))]
+            async_runtime,
+            &mut state.state,
+            &mut state.ctx,
+            proxy.clone(),
+            events,
+        );
+        state
+            .ctx
+            .profiler
+            .record(flow_id, Hook::Click, start.elapsed());
+    }
+}
+
+/// Calls `on_pointer` of the running `owners` of a pressed object with each of `events`.
+fn deliver_pointer<State, Event: Send>(
+    #[cfg(not(target_arch = "wasm32"))] async_runtime: &tokio::runtime::Runtime,
+    proxy: &EngineProxy<State, Event>,
+    graphics_flows: &mut FlowList<State, Event>,
+    state: &mut AppState<State>,
+    (owners, events): PointerGestureEvents,
+) {
+    let owners = graphics_flows.dispatch_order(&owners);
+    for event in events {
+        for &flow_id in &owners {
+            let Some(flow) = graphics_flows
+                .get_mut(flow_id)
+                .filter(|flow| takes_input(flow_id, flow.as_ref(), &state.ctx))
+            else {
+                continue;
+            };
+            let start = Instant::now();
+            let out = flow.on_pointer(&state.ctx, &mut state.state, event);
+            handle_flow_output(
+                #[cfg(not(target_arch = "wasm32"))]
+                async_runtime,
+                &mut state.state,
+                &mut state.ctx,
+                proxy.clone(),
+                out,
+            );
+            state
+                .ctx
+                .profiler
+                .record(flow_id, Hook::Pointer, start.elapsed());
+        }
+    }
+}
+
+/// Suspends the app while the page is hidden, e.g. in a background tab where its timers
+/// would keep waking the event loop.
+#[cfg(target_arch = "wasm32")]
+fn watch_visibility<State: 'static, Event: 'static>(
+    document: &wgpu::web_sys::Document,
+    proxy: EngineProxy<State, Event>,
+) {
+    use wasm_bindgen::JsCast;
+
+    let page = document.clone();
+    let on_change = Closure::<dyn FnMut()>::new(move || {
+        let _ = proxy.send_event(FlowEvent::Visible(!page.hidden()));
+    });
+    let listener = on_change.as_ref().unchecked_ref();
+    if let Err(e) = document.add_event_listener_with_callback("visibilitychange", listener) {
+        log::warn!("Unable to watch the page visibility: {e:?}");
+    }
+    // Listens for as long as the page lives
+    on_change.forget();
+}
+
+/// Calls `hook` on every flow, paused or not, e.g. `on_focus` once the window's focus changed.
+fn notify_flows<State, Event: Send>(
+    #[cfg(not(target_arch = "wasm32"))] async_runtime: &tokio::runtime::Runtime,
+    proxy: &EngineProxy<State, Event>,
+    graphics_flows: &mut FlowList<State, Event>,
+    state: &mut AppState<State>,
+    hook: impl Fn(&mut dyn GraphicsFlow<State, Event>, &Context, &mut State) -> Out<State, Event>,
+) {
+    for flow in graphics_flows.iter_mut() {
+        let out = hook(flow.as_mut(), &state.ctx, &mut state.state);
+        handle_flow_output(
+            #[cfg(not(target_arch = "wasm32"))]
+            async_runtime,
+            &mut state.state,
+            &mut state.ctx,
+            proxy.clone(),
+            out,
+        );
+    }
+}
+
+/// Passes `event` to the flow `target` or along the flows' `on_custom_events` until one
+/// consumes it, or a clone to every flow after [`Context::broadcast_events`]. Unconsumed
+/// events go to the dead letter handler.
+fn dispatch_custom_event<State, Event: Send + 'static>(
+    graphics_flows: &mut FlowList<State, Event>,
+    state: &mut AppState<State>,
+    target: Option<FlowId>,
+    event: Event,
+) {
+    let Some(cloner) = state.ctx.event_cloner.as_ref().filter(|_| target.is_none()) else {
+        let unconsumed = graphics_flows.deliver(target, event, |flow, event| {
+            flow.on_custom_events(&state.ctx, &mut state.state, event)
+        });
+        if let Some(event) = unconsumed {
+            state.ctx.dead_letter(event);
+        }
+        return;
+    };
+    let mut flows = graphics_flows.iter_mut().peekable();
+    let mut event = Some(event);
+    while let Some(flow) = flows.next() {
+        // The last flow gets the original
+        let copy = match (&event, flows.peek()) {
+            (Some(original), Some(_)) => cloner.clone_event(original),
+            _ => event.take(),
+        };
+        let Some(copy) = copy else {
+            log::error!("Custom events aren't of the type passed to `Context::broadcast_events`");
+            return;
+        };
+        flow.on_custom_events(&state.ctx, &mut state.state, copy);
+    }
+}
+
+/// Picks the object under the resting cursor if hover picking is on and due, or hovers
+/// nothing once the cursor left the window.
+fn dispatch_hover<State, Event: Send>(
+    #[cfg(not(target_arch = "wasm32"))] async_runtime: &tokio::runtime::Runtime,
+    proxy: &EngineProxy<State, Event>,
+    graphics_flows: &mut FlowList<State, Event>,
+    state: &mut AppState<State>,
+) {
+    if state.ctx.is_recovering_surface() {
+        return;
+    }
+    let cursor = state.ctx.mouse.coords;
+    // Replays take the recorded pick, if the recording picked this frame
+    #[cfg(feature = "record")]
+    let replayed = state.ctx.session.as_mut().and_then(Session::replayed_hover);
+    #[cfg(not(feature = "record"))]
+    let replayed: Option<Option<(Picked, PickHit)>> = None;
+    let change = if !state.ctx.hover.is_enabled() || state.ctx.input.cursor_position().is_none() {
+        state.ctx.hover.hover(None)
+    } else if let Some(picked) = replayed {
+        picked.and_then(|(picked, hit)| state.ctx.hover.finish_pick(cursor, picked, hit))
+    } else if !state.ctx.hover.moved(cursor) {
+        None
+    } else if state.ctx.hover.throttled(Instant::now()) {
+        // Nothing else may wake an on demand loop once the cursor rests
+        context::request_frame();
+        None
+    } else {
+        state.ctx.hover.start_pick(Instant::now());
+        state.ctx.upload_camera_uniforms();
+        let (picked, hit) = draw_to_pick_buffer::<State, Event>(
+            #[cfg(not(target_arch = "wasm32"))]
+            async_runtime,
+            graphics_flows,
+            &state.ctx,
+            &state.ctx.mouse,
+            #[cfg(target_arch = "wasm32")]
+            proxy.clone(),
+            #[cfg(target_arch = "wasm32")]
+            PickPurpose::Hover,
+        );
+        #[cfg(feature = "record")]
+        if let Some(session) = &mut state.ctx.session {
+            session.record_hover(&picked, hit);
+        }
+        // WASM reads the pick back asynchronously and finishes it through `FlowEvent::Hover`
+        cfg!(not(target_arch = "wasm32"))
+            .then(|| state.ctx.hover.finish_pick(cursor, picked, hit))
+            .flatten()
+    };
+    if let Some(change) = change {
+        deliver_hover(
+            #[cfg(not(target_arch = "wasm32"))]
+            async_runtime,
+            proxy,
+            graphics_flows,
+            state,
+            change,
+        );
+    }
+}
+
+/// Calls `on_hover(None)` of the flows the cursor left and `on_hover(Some(id))` of the owners
+/// of the newly hovered id.
+fn deliver_hover<State, Event: Send>(
+    #[cfg(not(target_arch = "wasm32"))] async_runtime: &tokio::runtime::Runtime,
+    proxy: &EngineProxy<State, Event>,
+    graphics_flows: &mut FlowList<State, Event>,
+    state: &mut AppState<State>,
+    change: HoverChange,
+) {
+    let left = graphics_flows
+        .dispatch_order(&change.left)
+        .into_iter()
+        .map(|id| (id, None));
+    let entered = change.entered.iter().flat_map(|(pick_id, flow_ids)| {
+        let dispatch_order = graphics_flows.dispatch_order(flow_ids);
+        dispatch_order
+            .into_iter()
+            .map(|id| (id, Some(PickId(*pick_id))))
+    });
+    for (flow_id, hovered) in left.chain(entered).collect::<Vec<_>>() {
+        let Some(flow) = graphics_flows
+            .get_mut(flow_id)
+            .filter(|flow| takes_input(flow_id, flow.as_ref(), &state.ctx))
+        else {
+            continue;
+        };
+        let start = Instant::now();
+        let events = flow.on_hover(&state.ctx, &mut state.state, hovered);
+        handle_flow_output(
+            #[cfg(not(target_arch = "wasm32"))]
+            async_runtime,
+            &mut state.state,
+            &mut state.ctx,
+            proxy.clone(),
+            events,
+        );
+        state
+            .ctx
+            .profiler
+            .record(flow_id, Hook::Hover, start.elapsed());
+    }
+}
+
+/// Toggles pausing on a press of a key bound to [`ActionMap::PAUSE`]. Text input keeps Escape,
+/// e.g. to cancel editing, so pausing needs the keyboard free.
+fn toggle_pause(
+    ctx: &mut Context,
+    focus: Option<FlowId>,
+    key: KeyCode,
+    key_state: ElementState,
+    repeat: bool,
+) {
+    if focus.is_none()
+        && key_state.is_pressed()
+        && !repeat
+        && ctx.actions.triggers(ActionMap::PAUSE, key)
+    {
+        let paused = ctx.is_paused();
+        ctx.set_paused(!paused);
+    }
+}
+
+/// Passes gamepad input since the previous poll to the flows taking input, moves the camera
+/// with the left stick and clicks the object under the cursor with the south button.
+#[cfg(all(feature = "gamepad", not(target_arch = "wasm32")))]
+fn dispatch_gamepads<State, Event: Send>(
+    async_runtime: &tokio::runtime::Runtime,
+    proxy: &EngineProxy<State, Event>,
+    gamepads: &mut Gamepads,
+    graphics_flows: &mut FlowList<State, Event>,
+    state: &mut AppState<State>,
+) {
+    let events = gamepads.poll();
+    if !events.is_empty() {
+        context::request_frame();
+        graphics_flows.iter_mut_with_ids().for_each(|(flow_id, f)| {
+            if !takes_input(flow_id, f.as_ref(), &state.ctx) {
+                return;
+            }
+            let out = f.on_gamepad_events(&state.ctx, &mut state.state, &events);
+            handle_flow_output(
+                async_runtime,
+                &mut state.state,
+                &mut state.ctx,
+                proxy.clone(),
+                out,
+            );
+        });
+    }
+
+    let camera_input = state.ctx.input_settings.gamepad_camera
+        && !state.ctx.is_paused()
+        && !state.ctx.camera.is_animating()
+        && state.ctx.camera_controller_enabled;
+    let drove_camera = gamepads.set_drives_camera(camera_input);
+    // Only changes are applied, so a stick at rest doesn't stop keyboard movement
+    let stick_moved = events.iter().any(|event| {
+        matches!(
+            event,
+            GamepadEvent::AxisChanged(_, GamepadAxis::LeftStickX | GamepadAxis::LeftStickY, _)
+                | GamepadEvent::Disconnected(_)
+        )
+    });
+    let (x, y) = gamepads.left_stick();
+    if camera_input && (stick_moved || (!drove_camera && (x, y) != (0.0, 0.0))) {
+        // Also picks up a stick deflected while the camera ignored it
+        state.ctx.camera.controller.handle_move(x, y);
+    } else if !camera_input && drove_camera {
+        // Changes while paused are dropped, the movement from before mustn't outlast the pause
+        state.ctx.camera.controller.handle_move(0.0, 0.0);
+    }
+    let clicked = events
+        .iter()
+        .any(|event| matches!(event, GamepadEvent::ButtonPressed(_, GamepadButton::South)));
+    if clicked {
+        dispatch_click(
+            async_runtime,
+            proxy,
+            graphics_flows,
+            state,
+            MouseButton::Left,
+        );
+    }
+}
+
+/// Rebuilds the GPU state of `state` after the device was lost and lets the flows upload
+/// theirs again through `on_gpu_reset`.
+#[cfg(not(target_arch = "wasm32"))]
+fn recover_gpu<State, Event: Send>(
+    async_runtime: &tokio::runtime::Runtime,
+    proxy: &EngineProxy<State, Event>,
+    graphics_flows: &mut FlowList<State, Event>,
+    state: &mut AppState<State>,
+    config: &RunConfig,
+) -> Result<(), Error> {
+    log::warn!("Rebuilding the GPU state after the device was lost");
+    async_runtime.block_on(state.ctx.reset_gpu(&config.adapter, &config.device))?;
+    graphics_flows
+        .iter_mut_with_ids()
+        .for_each(|(flow_id, flow)| {
+            let start = Instant::now();
+            let events = flow.on_gpu_reset(&state.ctx, &mut state.state);
+            handle_flow_output(
+                async_runtime,
+                &mut state.state,
+                &mut state.ctx,
+                proxy.clone(),
+                events,
+            );
+            state
+                .ctx
+                .profiler
+                .record(flow_id, Hook::GpuReset, start.elapsed());
+        });
+    Ok(())
+}
+
+/// Posts events onto the event loop from other threads, e.g. messages of a websocket. Get one
+/// through [`InitContext::event_sender`], sending fails with [`Error::EventLoopClosed`] once
+/// the event loop exited.
+pub struct EventSender<State: 'static, Event: 'static> {
+    proxy: EngineProxy<State, Event>,
+}
+
+impl<State, Event> Clone for EventSender<State, Event> {
+    fn clone(&self) -> Self {
+        Self {
+            proxy: self.proxy.clone(),
+        }
+    }
+}
+
+impl<State, Event> Debug for EventSender<State, Event> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("EventSender")
+    }
+}
+
+impl<State, Event> EventSender<State, Event> {
+    /// Passes `event` through the flows' `on_custom_events` on the main thread.
+    pub fn send(&self, event: Event) -> Result<(), Error> {
+        self.post(FlowEvent::Custom(event))
+    }
+
+    /// Passes `event` only to the `on_custom_events` of the flow `id`, like `Out::EmitTo`.
+    pub fn send_to(&self, id: FlowId, event: Event) -> Result<(), Error> {
+        self.post(FlowEvent::CustomTo(id, event))
+    }
+
+    /// Runs `mutation` on the shared state on the main thread.
+    pub fn send_mut(&self, mutation: Box<dyn FnOnce(&mut State) + Send>) -> Result<(), Error> {
+        self.post(FlowEvent::Mut(mutation))
+    }
+
+    fn post(&self, event: FlowEvent<State, Event>) -> Result<(), Error> {
+        self.proxy.send_event(event)
+    }
+}
+
+/// Posts the engine's events onto the event loop running the app, which is a host's loop of
+/// another user event type after [`App::new_with_proxy`].
+pub(crate) struct EngineProxy<State: 'static, Event: 'static>(Arc<PostFn<State, Event>>);
+
+#[cfg(not(target_arch = "wasm32"))]
+type PostFn<State, Event> = dyn Fn(FlowEvent<State, Event>) -> Result<(), Error> + Send + Sync;
+#[cfg(target_arch = "wasm32")]
+type PostFn<State, Event> = dyn Fn(FlowEvent<State, Event>) -> Result<(), Error>;
+
+impl<State, Event> EngineProxy<State, Event> {
+    #[cfg(not(target_arch = "wasm32"))]
+    fn new<T>(proxy: EventLoopProxy<T>) -> Self
+    where
+        T: From<EngineEvent<State, Event>> + Send,
+    {
+        Self(Arc::new(move |event| {
+            let event = T::from(EngineEvent(event));
+            proxy.send_event(event).map_err(|_| Error::EventLoopClosed)
+        }))
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn new<T>(proxy: EventLoopProxy<T>) -> Self
+    where
+        T: From<EngineEvent<State, Event>>,
+    {
+        Self(Arc::new(move |event| {
+            let event = T::from(EngineEvent(event));
+            proxy.send_event(event).map_err(|_| Error::EventLoopClosed)
+        }))
+    }
+
+    /// Fails with [`Error::EventLoopClosed`] once the event loop exited.
+    pub(crate) fn send_event(&self, event: FlowEvent<State, Event>) -> Result<(), Error> {
+        (self.0)(event)
+    }
+}
+
+impl<State, Event> Clone for EngineProxy<State, Event> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+/// Wakes the event loop through [`FlowEvent::Wake`] once a startup future or one of
+/// [`App::spawn_local`] can make progress.
+#[cfg(not(target_arch = "wasm32"))]
+struct ProxyWaker<State: 'static, Event: 'static>(EngineProxy<State, Event>);
+
+#[cfg(not(target_arch = "wasm32"))]
+impl<State, Event> std::task::Wake for ProxyWaker<State, Event> {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        // Nothing is left to start once the event loop exited
+        let _ = self.0.send_event(FlowEvent::Wake);
+    }
+}
+
+/// Event the engine posts to its event loop, e.g. a resolved future or a pick. Hosts running
+/// the app in their own event loop wrap it in their user event type and pass it back through
+/// [`App::handle_user_event`], see [`App::new_with_proxy`].
+pub struct EngineEvent<State: 'static, Event: 'static>(FlowEvent<State, Event>);
+
+impl<State, Event> Debug for EngineEvent<State, Event> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("EngineEvent").field(&self.0).finish()
+    }
+}
+
+pub(crate) enum FlowEvent<State: 'static, Event: 'static> {
+    #[allow(dead_code)]
+    Id((u32, BTreeSet<FlowId>), PickHit, MouseButton),
+    // Hover pick taken at the cursor position
+    #[cfg(target_arch = "wasm32")]
+    Hover(PhysicalPosition<f64>, Picked, PickHit),
+    // The page was hidden (`false`) or shown again
+    #[cfg(target_arch = "wasm32")]
+    Visible(bool),
+    #[cfg(not(target_arch = "wasm32"))]
+    Mut(Box<dyn FnOnce(&mut State) + Send>),
+    #[cfg(target_arch = "wasm32")]
+    Mut(Box<dyn FnOnce(&mut State)>),
+    // Resolved future of `App::spawn_local`
+    #[cfg(target_arch = "wasm32")]
+    Resolved(Resolved<State, Event>),
+    Custom(Event),
+    // Event of `Out::EmitTo` for a single flow
+    CustomTo(FlowId, Event),
+    // Resolved future of `Context::spawn_load`
+    Loaded(LoadTicket, Box<dyn Job>),
+    // A flow panicked and was poisoned
+    #[cfg(feature = "catch-flow-panics")]
+    Panicked(FlowPanic),
+    // A startup future or one of `App::spawn_local` can make progress, `about_to_wait` polls
+    // it next
+    #[cfg(not(target_arch = "wasm32"))]
+    Wake,
+    #[allow(dead_code)]
+    Exit,
+}
+impl<State, Event> Debug for FlowEvent<State, Event> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Id(arg0, hit, button) => f
+                .debug_tuple("Id")
+                .field(arg0)
+                .field(hit)
+                .field(button)
+                .finish(),
+            #[cfg(target_arch = "wasm32")]
+            Self::Hover(position, picked, hit) => f
+                .debug_tuple("Hover")
+                .field(position)
+                .field(picked)
+                .field(hit)
+                .finish(),
+            #[cfg(target_arch = "wasm32")]
+            Self::Visible(visible) => f.debug_tuple("Visible").field(visible).finish(),
+            Self::Mut(_) => f.write_str("Mut(|&mut State| -> {...})"),
+            #[cfg(target_arch = "wasm32")]
+            Self::Resolved(resolved) => f.debug_tuple("Resolved").field(resolved).finish(),
+            Self::Custom(_) => f.write_str("Custom(E)"),
+            Self::CustomTo(id, _) => write!(f, "CustomTo({id:?}, E)"),
+            Self::Loaded(ticket, _) => write!(f, "Loaded({ticket:?}, Uploads)"),
+            #[cfg(feature = "catch-flow-panics")]
+            Self::Panicked(panic) => f.debug_tuple("Panicked").field(panic).finish(),
+            #[cfg(not(target_arch = "wasm32"))]
+            Self::Wake => f.write_str("Wake"),
+            Self::Exit => f.write_str("Exit"),
+        }
+    }
+}
+
+impl<State: 'static + Default, Event: Send + 'static> ApplicationHandler<EngineEvent<State, Event>>
+    for App<State, Event>
+{
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        // Only the first resume starts the app, later ones follow `suspended`
+        if self.state.is_some() || self.loading.is_some() {
+            return self.set_suspended(false);
+        }
+        // A host's event loop keeps resuming after the app exited
+        if self.exiting {
+            return;
+        }
+        #[allow(unused_mut)]
+        let mut window_attributes = match self.config.window_attributes() {
+            Ok(attributes) => attributes,
+            Err(e) => return self.fail(event_loop, e),
+        };
+
+        #[cfg(target_arch = "wasm32")]
+        {
+            use wasm_bindgen::JsCast;
+            use winit::platform::web::WindowAttributesExtWebSys;
+
+            let window = wgpu::web_sys::window().unwrap_throw();
+            let document = window.document().unwrap_throw();
+            let canvas = document
+                .get_element_by_id(&self.config.canvas_id)
+                .unwrap_throw();
+            let html_canvas_element = canvas.unchecked_into();
+            window_attributes = window_attributes.with_canvas(Some(html_canvas_element));
+            watch_visibility(&document, self.proxy.clone());
+        }
+
+        let window = match event_loop.create_window(window_attributes) {
+            Ok(window) => Arc::new(window),
+            Err(e) => return self.fail(event_loop, EventLoopError::from(e).into()),
+        };
+        self.window_id = Some(window.id());
+
+        let adapter = self.config.adapter.clone();
+        let request = self.config.device.clone();
+        let manifest = self.config.asset_manifest.clone();
+        // Polled from `about_to_wait` so the window handles events while the app starts
+        let starting = async move {
+            validate_assets(&manifest).await.into_result()?;
+            AppState::new(window, &adapter, &request).await
+        };
+        self.loading = Some(Loading::Starting(Box::pin(starting)));
+        self.poll_loading(event_loop);
+    }
+
+    #[allow(unused_mut)]
+    fn user_event(&mut self, event_loop: &ActiveEventLoop, event: EngineEvent<State, Event>) {
+        let EngineEvent(mut event) = event;
+        // Constructors can send events before there are flows to receive them
+        if self.loading.is_some() && !matches!(event, FlowEvent::Exit) {
+            // Only wakes the event loop, `about_to_wait` polls startup after the events
+            #[cfg(not(target_arch = "wasm32"))]
+            if let FlowEvent::Wake = event {
+                return;
+            }
+            self.queued_events.push(event);
+            return;
+        }
+        match event {
+            FlowEvent::Id((pick_id, flow_ids), hit, button) => {
+                if let Some(state) = &mut self.state {
+                    let picked = Some((pick_id, flow_ids.clone()));
+                    let gui = picks_gui(&self.graphics_flows, &picked);
+                    if !gui {
+                        state.ctx.mouse.toggle(PickId(pick_id));
+                    }
+                    let hit = PickHit {
+                        position: hit.position.filter(|_| !gui),
+                        ..hit
+                    };
+                    // Gestures only start if the button is still held once the pick arrived
+                    #[cfg(target_arch = "wasm32")]
+                    if state.ctx.input.is_button_down(button) {
+                        self.pointer
+                            .press(button, picked, state.ctx.mouse.coords, gui);
+                    }
+                    click_flows(
+                        #[cfg(not(target_arch = "wasm32"))]
+                        &self.async_runtime,
+                        &self.proxy,
+                        &mut self.graphics_flows,
+                        state,
+                        (pick_id, &flow_ids),
+                        hit,
+                        button,
+                    );
+                }
+            }
+            #[cfg(target_arch = "wasm32")]
+            FlowEvent::Hover(cursor, picked, hit) => {
+                if let Some(state) = &mut self.state
+                    && let Some(change) = state.ctx.hover.finish_pick(cursor, picked, hit)
+                {
+                    deliver_hover(&self.proxy, &mut self.graphics_flows, state, change);
+                }
+            }
+            FlowEvent::Custom(custom_event) => {
+                if let Some(state) = &mut self.state {
+                    dispatch_custom_event(&mut self.graphics_flows, state, None, custom_event);
+                }
+            }
+            FlowEvent::CustomTo(flow_id, custom_event) => {
+                if let Some(state) = &mut self.state {
+                    dispatch_custom_event(
+                        &mut self.graphics_flows,
+                        state,
+                        Some(flow_id),
+                        custom_event,
+                    );
+                }
+            }
+            FlowEvent::Mut(fn_once) => {
+                if let Some(state) = &mut self.state {
+                    fn_once(&mut state.state);
+                }
+            }
+            #[cfg(target_arch = "wasm32")]
+            FlowEvent::Resolved(resolved) => self.handle_resolved(resolved),
+            #[cfg(target_arch = "wasm32")]
+            FlowEvent::Visible(visible) => self.set_suspended(!visible),
+            FlowEvent::Loaded(ticket, job) => {
+                if let Some(state) = &self.state {
+                    state.ctx.streams.borrow_mut().resolve(ticket, job);
+                }
+            }
+            #[cfg(feature = "catch-flow-panics")]
+            FlowEvent::Panicked(panic) => {
+                // The poisoned flow itself ignores it
+                let mut handled = false;
+                for flow in self.graphics_flows.iter_mut() {
+                    let Some(state) = &mut self.state else {
+                        break;
+                    };
+                    let out = flow.on_flow_panic(&state.ctx, &mut state.state, &panic);
+                    handled |= !matches!(out, Out::Empty);
+                    handle_flow_output(
+                        #[cfg(not(target_arch = "wasm32"))]
+                        &self.async_runtime,
+                        &mut state.state,
+                        &mut state.ctx,
+                        self.proxy.clone(),
+                        out,
+                    );
+                }
+                // Nobody recovers, so the others get `on_exit` to save instead of running on
+                if !handled && self.state.is_some() {
+                    log::error!(
+                        "No flow handled the panic of flow {:?}, exiting",
+                        panic.flow
+                    );
+                    self.exit(event_loop);
+                }
+            }
+            // Of a future `about_to_wait` polls next
+            #[cfg(not(target_arch = "wasm32"))]
+            FlowEvent::Wake => return,
+            FlowEvent::Exit => self.exit(event_loop),
+        }
+        // Flows may have changed their state, e.g. once a loaded asset arrived
+        context::request_frame();
+        self.sync_flows();
+    }
+
+    fn suspended(&mut self, _event_loop: &ActiveEventLoop) {
+        self.set_suspended(true);
+    }
+
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        if self.loading.is_some() {
+            self.poll_loading(event_loop);
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        self.poll_pending();
+        if let Some(loading) = &self.loading {
+            // Redraws keep the loading screen animated, the timeout polls a minimized window
+            if let Loading::Constructing { state, .. } = loading {
+                state.window.request_redraw();
+            }
+            event_loop.set_control_flow(ControlFlow::wait_duration(LOADING_POLL_INTERVAL));
+            return;
+        }
+        let Some(state) = &mut self.state else {
+            return;
+        };
+        if state.ctx.exit_requested() {
+            return self.exit(event_loop);
+        }
+        // Nothing runs until the app is resumed
+        if state.ctx.is_suspended() {
+            event_loop.set_control_flow(ControlFlow::Wait);
+            return;
+        }
+        for pending in std::mem::take(&mut state.ctx.pending_windows) {
+            let id = pending.id;
+            let opened = event_loop
+                .create_window(pending.attributes.clone())
+                .map_err(|e| EventLoopError::from(e).into())
+                .and_then(|window| state.ctx.open_window(pending, Arc::new(window)));
+            if let Err(e) = opened {
+                log::error!("Failed to open window {id:?}: {e}");
+            }
+        }
+        // Polled here rather than per frame, on demand rendering has no frames while idle
+        #[cfg(all(feature = "gamepad", not(target_arch = "wasm32")))]
+        dispatch_gamepads(
+            &self.async_runtime,
+            &self.proxy,
+            &mut self.gamepads,
+            &mut self.graphics_flows,
+            state,
+        );
+        let requested = context::take_frame_request();
+        let until_frame = until_frame(state.ctx.frame_interval(), self.last_time.elapsed());
+        match state.ctx.render_strategy {
+            RenderStrategy::Continuous if until_frame.is_zero() => {
+                event_loop.set_control_flow(ControlFlow::Wait);
+                state.ctx.request_redraws();
+            }
+            // Sleeps until the frame cap allows the next frame
+            RenderStrategy::Continuous => {
+                event_loop.set_control_flow(ControlFlow::wait_duration(until_frame));
+            }
+            RenderStrategy::OnDemand => {
+                // Ticks run while rendering, so a due tick renders a frame
+                // Ticks come slower in slow motion and never at a time scale of zero
+                let scale = state.ctx.time_scale();
+                let until_tick = self
+                    .ticks
+                    .until_tick(
+                        state.ctx.tick_rate(),
+                        self.last_time.elapsed().mul_f32(scale),
+                        state.ctx.is_tick_paused() || state.ctx.updates_stopped() || scale == 0.0,
+                    )
+                    .map(|until| {
+                        Duration::try_from_secs_f32(until.as_secs_f32() / scale)
+                            .unwrap_or(Duration::MAX)
+                    });
+                let due = requested || until_tick.is_some_and(|until| until.is_zero());
+                if due && until_frame.is_zero() {
+                    state.ctx.request_redraws();
+                } else if due {
+                    // Kept for once the frame cap allows it
+                    context::request_frame();
+                }
+                // A pending frame runs any tick that becomes due until then
+                let wake = if due { Some(until_frame) } else { until_tick };
+                // Gamepads can't wake the event loop, so it wakes up to poll them
+                #[cfg(all(feature = "gamepad", not(target_arch = "wasm32")))]
+                let wake = match wake {
+                    _ if !self.gamepads.is_connected() => wake,
+                    Some(wake) => Some(wake.min(GAMEPAD_POLL_INTERVAL)),
+                    None => Some(GAMEPAD_POLL_INTERVAL),
+                };
+                event_loop
+                    .set_control_flow(wake.map_or(ControlFlow::Wait, ControlFlow::wait_duration));
+            }
+        }
+    }
+
+    fn device_event(
+        &mut self,
+        _event_loop: &ActiveEventLoop,
+        _device_id: DeviceId,
+        event: DeviceEvent,
+    ) {
+        let state = match &mut self.state {
+            Some(state) => state,
+            None => return,
+        };
+        // Recorded, or dropped while replaying for the recorded input
+        #[cfg(feature = "record")]
+        if let Some(session) = &mut state.ctx.session
+            && let Some(input) = RecordedInput::from_device_event(&event)
+            && !session.input(self.start_time.elapsed(), input)
+        {
+            return;
+        }
+        // Flows see the event first and may capture it, captures of other hooks are ignored
+        state.ctx.take_input_captured();
+        for (flow_id, f) in self.graphics_flows.iter_mut_with_ids() {
+            if !takes_input(flow_id, f.as_ref(), &state.ctx) {
+                continue;
+            }
+            let start = Instant::now();
+            let events = f.on_device_events(&state.ctx, &mut state.state, &event);
+            let proxy = self.proxy.clone();
+            handle_flow_output(
+                #[cfg(not(target_arch = "wasm32"))]
+                &self.async_runtime,
+                &mut state.state,
+                &mut state.ctx,
+                proxy,
+                events,
             );
+            state
+                .ctx
+                .profiler
+                .record(flow_id, Hook::DeviceEvents, start.elapsed());
+            if state.ctx.input_captured() {
+                break;
+            }
+        }
+        let captured = state.ctx.take_input_captured();
+        if let DeviceEvent::MouseMotion { delta: (dx, dy) } = event
+            && !captured
+            && !state.ctx.is_paused()
+            && !state.ctx.camera.is_animating()
+            && state.ctx.camera_controller_enabled
+        {
+            let settings = state.ctx.input_settings;
+            let speed_factor = settings.mouse_sensitivity;
+            if state.ctx.camera.controller.wants_raw_mouse()
+                || (settings.rotates(state.ctx.mouse.pressed) && !self.pointer.holds_gui())
+            {
+                context::request_frame();
+                state
+                    .ctx
+                    .camera
+                    .controller
+                    .handle_mouse(dx * speed_factor, dy * speed_factor);
+            }
+        }
+        self.sync_flows();
+    }
+
+    fn window_event(
+        &mut self,
+        event_loop: &ActiveEventLoop,
+        window_id: winit::window::WindowId,
+        event: WindowEvent,
+    ) {
+        // Windows of a host running the app in its event loop
+        let secondary = |state: &AppState<State>| state.ctx.secondary_window_id(window_id);
+        if self.window_id != Some(window_id) && self.state.as_ref().and_then(secondary).is_none() {
+            return;
+        }
+        // Replays feed the recorded inputs ahead of the frame they arrived for
+        #[cfg(feature = "record")]
+        if matches!(event, WindowEvent::RedrawRequested) && self.window_id == Some(window_id) {
+            self.feed_replay(event_loop, window_id);
+        }
+        let state = match &mut self.state {
+            Some(state) => state,
+            None => return self.loading_window_event(event_loop, event),
+        };
+        // Secondary windows are only resized, drawn and closed, their input isn't dispatched
+        if let Some(id) = state.ctx.secondary_window_id(window_id) {
+            match event {
+                WindowEvent::Resized(size) => state.ctx.resize_window(id, size.width, size.height),
+                WindowEvent::CloseRequested => state.ctx.close_window(id),
+                WindowEvent::RedrawRequested => {
+                    let ctx = &state.ctx;
+                    let flows: Vec<_> = self
+                        .graphics_flows
+                        .iter_with_ids()
+                        .filter(|(flow_id, flow)| {
+                            flow.window() == id && ctx.flow_activity(*flow_id).render
+                        })
+                        .map(|(_, flow)| flow)
+                        .collect();
+                    if let Err(e) = state.ctx.render_window(id, flows) {
+                        log::error!("Unable to render window {id:?}: {e}");
+                    }
+                }
+                _ => (),
+            }
+            return;
+        }
+        // Recorded, or dropped while replaying for the recorded input
+        #[cfg(feature = "record")]
+        if let Some(session) = &mut state.ctx.session
+            && let Some(input) = RecordedInput::from_window_event(&event)
+            && !session.input(self.start_time.elapsed(), input)
+        {
+            return;
+        }
+        // Input, resizes and focus changes may all change what's drawn
+        if !matches!(event, WindowEvent::RedrawRequested) {
+            context::request_frame();
+        }
+        state.ctx.input.handle_window_event(&event);
+
+        let focus = state
+            .ctx
+            .keyboard_focus()
+            .filter(|&flow_id| self.graphics_flows.contains(flow_id));
+        let keyboard_event = matches!(
+            event,
+            WindowEvent::KeyboardInput { .. } | WindowEvent::Ime(_)
+        );
+        let key_released = matches!(
+            &event,
+            WindowEvent::KeyboardInput { event, .. } if !event.state.is_pressed()
+        );
+
+        let key = match &event {
+            WindowEvent::KeyboardInput { event: key, .. } => match key.physical_key {
+                PhysicalKey::Code(code) => Some((code, key.state, key.repeat)),
+                PhysicalKey::Unidentified(_) => None,
+            },
+            _ => None,
+        };
+        if let Some((code, key_state, repeat)) = key {
+            toggle_pause(&mut state.ctx, focus, code, key_state, repeat);
+        }
+        // Flows read the new position while handling the motion
+        let cursor_delta = match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                let delta = (
+                    position.x - state.ctx.mouse.coords.x,
+                    position.y - state.ctx.mouse.coords.y,
+                );
+                state.ctx.mouse.prev_coords = state.ctx.mouse.coords;
+                state.ctx.mouse.coords = position;
+                Some(delta)
+            }
+            _ => None,
+        };
+
+        // GUI sizes change with the scale factor even if the physical size doesn't
+        if let WindowEvent::ScaleFactorChanged { scale_factor, .. } = event {
+            state.ctx.set_scale_factor(scale_factor);
+            state.window.request_redraw();
+        }
+
+        // Flows see the event before the camera and picking and may capture it, captures of
+        // other hooks are ignored
+        state.ctx.take_input_captured();
+        let focused_flow = focus.filter(|_| keyboard_event).and_then(|flow_id| {
+            self.graphics_flows
+                .get_mut(flow_id)
+                .map(|flow| (flow_id, flow))
         });
+        match focused_flow {
+            // Keyboard input of a focused flow without input is dropped, not broadcast
+            Some((flow_id, _)) if !state.ctx.flow_activity(flow_id).input => (),
+            Some((flow_id, flow)) => {
+                let start = Instant::now();
+                let mut events = vec![flow.on_window_events(&state.ctx, &mut state.state, &event)];
+                if let Some((code, key_state, _)) = key {
+                    events.push(flow.on_key(&state.ctx, &mut state.state, code, key_state));
+                }
+                if let Some(text_event) = TextEvent::from_window_event(&event) {
+                    events.push(flow.on_text_input(&state.ctx, &mut state.state, text_event));
+                }
+                handle_flow_output(
+                    #[cfg(not(target_arch = "wasm32"))]
+                    &self.async_runtime,
+                    &mut state.state,
+                    &mut state.ctx,
+                    self.proxy.clone(),
+                    Out::Composed(events),
+                );
+                state
+                    .ctx
+                    .profiler
+                    .record(flow_id, Hook::WindowEvents, start.elapsed());
+            }
+            None => {
+                for (flow_id, f) in self.graphics_flows.iter_mut_with_ids() {
+                    if !takes_input(flow_id, f.as_ref(), &state.ctx) {
+                        continue;
+                    }
+                    let start = Instant::now();
+                    let mut events = f.on_window_events(&state.ctx, &mut state.state, &event);
+                    if let Some((code, key_state, _)) = key {
+                        let key_events = f.on_key(&state.ctx, &mut state.state, code, key_state);
+                        events = Out::Composed(vec![events, key_events]);
+                    }
+                    let proxy = self.proxy.clone();
+                    handle_flow_output(
+                        #[cfg(not(target_arch = "wasm32"))]
+                        &self.async_runtime,
+                        &mut state.state,
+                        &mut state.ctx,
+                        proxy,
+                        events,
+                    );
+                    state
+                        .ctx
+                        .profiler
+                        .record(flow_id, Hook::WindowEvents, start.elapsed());
+                    if state.ctx.input_captured() {
+                        break;
+                    }
+                }
+            }
+        }
+        let captured = state.ctx.take_input_captured();
+
+        // Pausing and camera animations keep the controller from accumulating input
+        let camera_input = !captured
+            && !state.ctx.is_paused()
+            && !state.ctx.camera.is_animating()
+            && state.ctx.camera_controller_enabled;
+
+        // general stuff
+        // Releases still reach the controller so keys held while focus is taken don't get stuck
+        if ((focus.is_none() || !keyboard_event) && camera_input) || key_released {
+            state.ctx.camera.controller.handle_window_events(&event);
+        }
+        if let WindowEvent::MouseWheel { delta, .. } = &event
+            && camera_input
+        {
+            state.ctx.camera.controller.handle_scroll(delta);
+        }
+
+        if let Some((dx, dy)) = cursor_delta {
+            let position = state.ctx.mouse.coords;
+            // Controllers with raw mouse input already receive the motion as device events
+            let settings = state.ctx.input_settings;
+            let on_object = (settings.rotate_off_objects_only && self.pointer.holds_object())
+                || self.pointer.holds_gui();
+            if settings.rotates(state.ctx.mouse.pressed)
+                && !on_object
+                && !state.ctx.camera.controller.wants_raw_mouse()
+                && camera_input
+            {
+                let speed_factor = settings.mouse_sensitivity;
+                state
+                    .ctx
+                    .camera
+                    .controller
+                    .handle_mouse(dx * speed_factor, dy * speed_factor);
+            }
+            if let Some(gesture) = self.pointer.moved(position, &settings) {
+                deliver_pointer(
+                    #[cfg(not(target_arch = "wasm32"))]
+                    &self.async_runtime,
+                    &self.proxy,
+                    &mut self.graphics_flows,
+                    state,
+                    gesture,
+                );
+            }
+        };
+
+        // Touch screens drive the pointer and the camera through gestures
+        if let WindowEvent::Touch(touch) = &event {
+            for gesture in self.touch.handle(touch.id, touch.phase, touch.location) {
+                match gesture {
+                    Gesture::Press(position) => {
+                        state.ctx.mouse.prev_coords = state.ctx.mouse.coords;
+                        state.ctx.mouse.coords = position;
+                        state.ctx.mouse.pressed = MouseButtonState::Left;
+                    }
+                    Gesture::Rotate { position, dx, dy } => {
+                        state.ctx.mouse.prev_coords = state.ctx.mouse.coords;
+                        state.ctx.mouse.coords = position;
+                        if camera_input {
+                            let speed_factor = state.ctx.input_settings.mouse_sensitivity;
+                            state
+                                .ctx
+                                .camera
+                                .controller
+                                .handle_mouse(dx * speed_factor, dy * speed_factor);
+                        }
+                    }
+                    // Spreading the fingers moves `CameraController` towards the scene
+                    Gesture::Pinch(change) if camera_input => {
+                        let delta =
+                            MouseScrollDelta::PixelDelta(PhysicalPosition::new(0.0, -change));
+                        state.ctx.camera.controller.handle_scroll(&delta);
+                    }
+                    Gesture::Pan(dx, dy) if camera_input => {
+                        state.ctx.camera.controller.handle_pan(dx, dy);
+                    }
+                    Gesture::Pinch(_) | Gesture::Pan(..) => (),
+                    Gesture::Release => state.ctx.mouse.pressed = MouseButtonState::None,
+                    Gesture::Tap(position) => {
+                        state.ctx.mouse.coords = position;
+                        if captured {
+                            continue;
+                        }
+                        dispatch_click(
+                            #[cfg(not(target_arch = "wasm32"))]
+                            &self.async_runtime,
+                            &self.proxy,
+                            &mut self.graphics_flows,
+                            state,
+                            MouseButton::Left,
+                        );
+                    }
+                }
+            }
+        }
+
+        // Applied once per frame before rendering, flows are notified through `on_resize`
+        if let WindowEvent::Resized(size) = event {
+            self.resize.request(size.width, size.height);
+        }
 
         match event {
-            WindowEvent::CloseRequested => event_loop.exit(),
+            WindowEvent::CloseRequested => self.exit(event_loop),
+            WindowEvent::Focused(focused) => {
+                state.ctx.set_window_focused(focused);
+                notify_flows(
+                    #[cfg(not(target_arch = "wasm32"))]
+                    &self.async_runtime,
+                    &self.proxy,
+                    &mut self.graphics_flows,
+                    state,
+                    |flow, ctx, state| flow.on_focus(ctx, state, focused),
+                );
+                // The release of a drag won't arrive while another window has focus
+                if let Some(gesture) = self.pointer.cancel().filter(|_| !focused) {
+                    deliver_pointer(
+                        #[cfg(not(target_arch = "wasm32"))]
+                        &self.async_runtime,
+                        &self.proxy,
+                        &mut self.graphics_flows,
+                        state,
+                        gesture,
+                    );
+                }
+            }
+            // Browsers and mobile OSes may still ask for frames of a suspended app
+            WindowEvent::RedrawRequested if state.ctx.is_suspended() => (),
             WindowEvent::RedrawRequested => {
-                let dt = self.last_time.elapsed();
-                self.last_time = Instant::now();
-                self.time_since_tick += dt;
+                if state.ctx.is_gpu_lost() {
+                    #[cfg(not(target_arch = "wasm32"))]
+                    let recovered = recover_gpu(
+                        &self.async_runtime,
+                        &self.proxy,
+                        &mut self.graphics_flows,
+                        state,
+                        &self.config,
+                    );
+                    // The event loop can't wait for a new device on the web
+                    #[cfg(target_arch = "wasm32")]
+                    let recovered = Err(Error::Unsupported {
+                        feature: "recovering from a lost GPU device on the web".to_string(),
+                    });
+                    if let Err(e) = recovered {
+                        return self.fail(event_loop, e);
+                    }
+                }
+                // Browsers redraw at the display rate, frames ahead of the cap are skipped
+                #[cfg(target_arch = "wasm32")]
+                {
+                    let since_frame = self.last_time.elapsed() + FRAME_CAP_SLACK;
+                    if !until_frame(state.ctx.frame_interval(), since_frame).is_zero() {
+                        context::request_frame();
+                        return;
+                    }
+                }
+                let visible = state.window.is_minimized() != Some(true) && {
+                    let size = state.window.inner_size();
+                    size.width > 0 && size.height > 0
+                };
+                if let Some(size) = state.ctx.requested_size.take() {
+                    self.resize.request(size.width, size.height);
+                }
+                if std::mem::take(&mut state.ctx.ui_relayout) {
+                    self.resize.reapply();
+                }
+                let resize = self
+                    .resize
+                    .take()
+                    .or_else(|| visible.then(|| self.resize.restore()).flatten());
+                match resize {
+                    Some(ResizeAction::Apply(width, height)) => {
+                        state.resize(width, height);
+                        self.graphics_flows
+                            .iter_mut_with_ids()
+                            .for_each(|(flow_id, f)| {
+                                let start = Instant::now();
+                                let events = f.on_resize(
+                                    &state.ctx,
+                                    &mut state.state,
+                                    PhysicalSize::new(width, height),
+                                );
+                                handle_flow_output(
+                                    #[cfg(not(target_arch = "wasm32"))]
+                                    &self.async_runtime,
+                                    &mut state.state,
+                                    &mut state.ctx,
+                                    self.proxy.clone(),
+                                    events,
+                                );
+                                state
+                                    .ctx
+                                    .profiler
+                                    .record(flow_id, Hook::Resize, start.elapsed());
+                            });
+                    }
+                    Some(ResizeAction::Suspend) => state.suspend(),
+                    None => (),
+                }
 
-                match state.render(
-                    &mut self.graphics_flows,
-                    #[cfg(feature = "integration-tests")]
+                // Clicks scripted by tests through `Context::click_at`
+                #[cfg(feature = "integration-tests")]
+                for position in std::mem::take(&mut state.ctx.scripted_clicks) {
+                    state.ctx.mouse.prev_coords = state.ctx.mouse.coords;
+                    state.ctx.mouse.coords = position;
+                    dispatch_click(
+                        &self.async_runtime,
+                        &self.proxy,
+                        &mut self.graphics_flows,
+                        state,
+                        MouseButton::Left,
+                    );
+                }
+
+                let elapsed = self.last_time.elapsed();
+                self.last_time = Instant::now();
+                let since_start = self.start_time.elapsed();
+                // Replays advance by the recorded times
+                #[cfg(feature = "record")]
+                let (elapsed, since_start) = match &mut state.ctx.session {
+                    Some(session) => session.frame(elapsed, since_start),
+                    None => (elapsed, since_start),
+                };
+                let on_demand = state.ctx.render_strategy == RenderStrategy::OnDemand;
+                // On demand frames may follow an idle period, which isn't frame time
+                let (dt, spike_threshold) = if on_demand {
+                    (elapsed.min(MAX_ON_DEMAND_DT), f32::INFINITY)
+                } else {
+                    (elapsed, state.ctx.spike_threshold)
+                };
+                state.ctx.timing.advance(dt, since_start);
+                dispatch_hover(
+                    #[cfg(not(target_arch = "wasm32"))]
                     &self.async_runtime,
-                    #[cfg(feature = "integration-tests")]
                     &self.proxy,
-                ) {
+                    &mut self.graphics_flows,
+                    state,
+                );
+                // Everything since the previous redraw, including its render and updates
+                if let Some(report) = state.ctx.profiler.finish_frame(dt, spike_threshold) {
+                    log::warn!("Frame time spike: {}", report);
+                    state.ctx.stats.last_spike = Some(report);
+                }
+                // Paused gameplay runs at a time scale of zero, GUI and tooltips keep real time
+                let frozen = state.ctx.is_paused() || state.ctx.updates_stopped();
+                let game_dt = if frozen {
+                    Duration::ZERO
+                } else {
+                    dt.mul_f32(state.ctx.time_scale())
+                };
+                state.ctx.timing.set_scaled_dt(game_dt);
+
+                // A lost surface is reconfigured on the frames that try to draw again
+                let draws = self.recovery.frame();
+                if draws && self.recovery.is_recovering() {
+                    state.reconfigure_surface();
+                }
+                let held = self.recovery.holds_updates();
+                // Streamed loads upload a bit each frame
+                let loaded = if held {
+                    Vec::new()
+                } else {
+                    state.ctx.upload_streamed()
+                };
+                for event in loaded {
+                    let Ok(event) = event.downcast::<Event>() else {
+                        log::error!(
+                            "Dropped the event of a streamed load that isn't of the app's event type"
+                        );
+                        continue;
+                    };
+                    if self.proxy.send_event(FlowEvent::Custom(*event)).is_err() {
+                        log::warn!("The event loop closed before a streamed load finished");
+                    }
+                }
+                if state.ctx.streams.borrow().is_uploading() {
+                    context::request_frame();
+                }
+                // Renders the camera as events and the last updates left it
+                state.ctx.update_camera_uniforms(since_start.as_secs_f32());
+                let render_start = Instant::now();
+                let rendered = if draws {
+                    state.render(
+                        &mut self.graphics_flows,
+                        #[cfg(feature = "integration-tests")]
+                        &self.async_runtime,
+                        #[cfg(feature = "integration-tests")]
+                        &self.proxy,
+                    )
+                } else {
+                    Ok(())
+                };
+                state.ctx.profiler.record_render(render_start.elapsed());
+                state.ctx.assets.collect();
+                match state.acquired.take() {
+                    Some(Acquire::Lost) => self.recovery.lost(),
+                    Some(Acquire::Acquired) if self.recovery.acquired() => notify_flows(
+                        #[cfg(not(target_arch = "wasm32"))]
+                        &self.async_runtime,
+                        &self.proxy,
+                        &mut self.graphics_flows,
+                        state,
+                        |flow, ctx, state| flow.on_gpu_recovered(ctx, state),
+                    ),
+                    _ => (),
+                }
+                let held = held && self.recovery.holds_updates();
+                state.ctx.set_recovering_surface(held);
+                if self.recovery.is_recovering() {
+                    context::request_frame();
+                }
+                match rendered {
+                    // Flows may write to buffers the lost surface's frames never drew
+                    Ok(_) if held => (),
                     Ok(_) => {
-                        if self.time_since_tick
-                            >= Duration::from_millis(state.ctx.tick_duration_millis)
+                        let tick = state.ctx.tick_rate();
+                        let stopped = state.ctx.updates_stopped();
+                        let tick_paused = state.ctx.is_tick_paused() || stopped;
+                        let due = self.ticks.advance(
+                            elapsed.mul_f32(state.ctx.time_scale()),
+                            tick,
+                            tick_paused,
+                        );
+                        for _ in 0..due {
+                            self.graphics_flows
+                                .iter_mut_with_ids()
+                                .for_each(|(flow_id, f)| {
+                                    if !is_running(f.as_ref(), &state.ctx)
+                                        || !state.ctx.flow_activity(flow_id).tick
+                                    {
+                                        return;
+                                    }
+                                    let start = Instant::now();
+                                    let events = f.on_tick(&state.ctx, &mut state.state);
+                                    let proxy = self.proxy.clone();
+                                    handle_flow_output(
+                                        #[cfg(not(target_arch = "wasm32"))]
+                                        &self.async_runtime,
+                                        &mut state.state,
+                                        &mut state.ctx,
+                                        proxy,
+                                        events,
+                                    );
+                                    state
+                                        .ctx
+                                        .profiler
+                                        .record(flow_id, Hook::Tick, start.elapsed());
+                                });
+                            state.ctx.ticks += 1;
+                        }
+                        state.ctx.tick_alpha = self.ticks.alpha(tick);
+                        // Update the camera
+                        let pose = state.ctx.camera.camera.pose();
+                        // Slow motion doesn't slow down the camera
+                        let controller = state.ctx.camera_controller_enabled;
+                        let camera_dt = if frozen { Duration::ZERO } else { dt };
+                        state.ctx.camera.advance(camera_dt, controller);
+                        // Keeps on demand frames coming while the camera moves, e.g. with a
+                        // movement key held
+                        let camera = &state.ctx.camera;
+                        if camera.camera.pose() != pose
+                            || camera.is_animating()
+                            || camera.is_shaking()
                         {
-                            self.graphics_flows.iter_mut().for_each(|f| {
-                                let events = f.on_tick(&state.ctx, &mut state.state);
+                            context::request_frame();
+                        }
+                        state.ctx.update_tooltip_anchor(dt);
+                        // Update the light
+                        if state.ctx.auto_light_rotation_enabled {
+                            let old_position: cgmath::Vector3<_> =
+                                state.ctx.light.uniform.position.into();
+                            state.ctx.light.uniform.position =
+                                (cgmath::Quaternion::from_axis_angle(
+                                    (0.0, 1.0, 0.0).into(),
+                                    cgmath::Deg(2.0 * game_dt.as_secs_f32()),
+                                ) * old_position)
+                                    .into();
+                        }
+                        let step = state.ctx.fixed_step();
+                        let due = self.fixed_steps.advance_max(
+                            game_dt,
+                            step,
+                            false,
+                            MAX_FIXED_STEPS_PER_FRAME,
+                        );
+                        for _ in 0..due {
+                            self.graphics_flows
+                                .iter_mut_with_ids()
+                                .for_each(|(flow_id, f)| {
+                                    if !state.ctx.flow_activity(flow_id).update {
+                                        return;
+                                    }
+                                    let start = Instant::now();
+                                    let events =
+                                        f.on_fixed_update(&state.ctx, &mut state.state, step);
+                                    let proxy = self.proxy.clone();
+                                    handle_flow_output(
+                                        #[cfg(not(target_arch = "wasm32"))]
+                                        &self.async_runtime,
+                                        &mut state.state,
+                                        &mut state.ctx,
+                                        proxy,
+                                        events,
+                                    );
+                                    state.ctx.profiler.record(
+                                        flow_id,
+                                        Hook::FixedUpdate,
+                                        start.elapsed(),
+                                    );
+                                });
+                        }
+                        state.ctx.fixed_alpha = self.fixed_steps.alpha(step);
+                        // Update custom stuff
+                        self.graphics_flows
+                            .iter_mut_with_ids()
+                            .for_each(|(flow_id, f)| {
+                                if stopped || !state.ctx.flow_activity(flow_id).update {
+                                    return;
+                                }
+                                let dt = if f.runs_while_paused() { dt } else { game_dt };
+                                let start = Instant::now();
+                                let events = f.on_update(&state.ctx, &mut state.state, dt);
                                 let proxy = self.proxy.clone();
                                 handle_flow_output(
                                     #[cfg(not(target_arch = "wasm32"))]
@@ -977,46 +4439,13 @@ impl<State: 'static + Default, Event: Send + 'static> ApplicationHandler<FlowEve
                                     proxy,
                                     events,
                                 );
+                                state
+                                    .ctx
+                                    .profiler
+                                    .record(flow_id, Hook::Update, start.elapsed());
                             });
-                            self.time_since_tick = Duration::from_millis(0);
-                        }
-                        // Update the camera
-                        state
-                            .ctx
-                            .camera
-                            .controller
-                            .update(&mut state.ctx.camera.camera, dt);
-                        state
-                            .ctx
-                            .camera
-                            .uniform
-                            .update_view_proj(&state.ctx.camera.camera, &state.ctx.projection);
-                        state.ctx.queue.write_buffer(
-                            &state.ctx.camera.buffer,
-                            0,
-                            bytemuck::cast_slice(&[state.ctx.camera.uniform]),
-                        );
-                        // Update the light
-                        let old_position: cgmath::Vector3<_> =
-                            state.ctx.light.uniform.position.into();
-                        state.ctx.light.uniform.position = (cgmath::Quaternion::from_axis_angle(
-                            (0.0, 1.0, 0.0).into(),
-                            cgmath::Deg(2.0 * dt.as_secs_f32()),
-                        ) * old_position)
-                            .into();
-                        // Update custom stuff
-                        self.graphics_flows.iter_mut().for_each(|f| {
-                            let events = f.on_update(&state.ctx, &mut state.state, dt);
-                            let proxy = self.proxy.clone();
-                            handle_flow_output(
-                                #[cfg(not(target_arch = "wasm32"))]
-                                &self.async_runtime,
-                                &mut state.state,
-                                &mut state.ctx,
-                                proxy,
-                                events,
-                            );
-                        });
+                        // Held and failed frames keep the presses for the next `on_update`
+                        state.ctx.input.end_frame();
                     }
                     Err(e) => {
                         log::error!("Unable to render: {}", e);
@@ -1029,61 +4458,287 @@ impl<State: 'static + Default, Event: Send + 'static> ApplicationHandler<FlowEve
                 ..
             } => {
                 if let Some(state) = &mut self.state {
+                    // Presses a flow captured neither move the camera nor pick
+                    let pressed = button_state.is_pressed() && !captured;
                     match (button, button_state.is_pressed()) {
+                        (_, true) if captured => (),
                         (MouseButton::Left, true) => {
                             state.ctx.mouse.pressed = MouseButtonState::Left;
-                            if let Some((pick_id, flow_ids)) = draw_to_pick_buffer::<State, Event>(
-                                #[cfg(not(target_arch = "wasm32"))]
-                                &self.async_runtime,
-                                &mut self.graphics_flows,
-                                &state.ctx,
-                                &state.ctx.mouse,
-                                #[cfg(target_arch = "wasm32")]
-                                self.proxy.clone(),
-                            ) {
-                                flow_ids.clone().into_iter().for_each(|flow_id| {
-                                    self.graphics_flows.get_mut(flow_id).map(|flow| {
-                                        let events =
-                                            flow.on_click(&state.ctx, &mut state.state, PickId(pick_id));
-                                        let proxy = self.proxy.clone();
-                                        handle_flow_output(
-                                            #[cfg(not(target_arch = "wasm32"))]
-                                            &self.async_runtime,
-                                            &mut state.state,
-                                            &mut state.ctx,
-                                            proxy,
-                                            events,
-                                        );
-                                    });
-                                });
-                                state.ctx.mouse.toggle(PickId(pick_id));
-                                if flow_ids.len() > 1 && pick_id != PickId::default().0 {
-                                    log::warn!(
-                                        "Multiple flows (incides {:?}) want to react to the render ID {}.",
-                                        flow_ids,
-                                        pick_id
-                                    );
-                                }
-                            }
                         }
                         (MouseButton::Right, true) => {
                             state.ctx.mouse.pressed = MouseButtonState::Right;
                         }
+                        (MouseButton::Middle, true) => {
+                            state.ctx.mouse.pressed = MouseButtonState::Middle;
+                        }
                         (_, false) => state.ctx.mouse.pressed = MouseButtonState::None,
                         _ => (),
                     }
+                    if pressed && state.ctx.pick_buttons.contains(&button) {
+                        let picked = dispatch_click(
+                            #[cfg(not(target_arch = "wasm32"))]
+                            &self.async_runtime,
+                            &self.proxy,
+                            &mut self.graphics_flows,
+                            state,
+                            button,
+                        );
+                        let gui = picks_gui(&self.graphics_flows, &picked);
+                        // Picks read back later on WASM press through `FlowEvent::Id`
+                        self.pointer
+                            .press(button, picked, state.ctx.mouse.coords, gui);
+                    }
+                    // Double clicks of a replay are timed by the recording
+                    #[cfg(feature = "record")]
+                    let now = state
+                        .ctx
+                        .session
+                        .as_ref()
+                        .and_then(Session::replay_clock)
+                        .map_or_else(Instant::now, |at| self.start_time + at);
+                    #[cfg(not(feature = "record"))]
+                    let now = Instant::now();
+                    if !button_state.is_pressed()
+                        && let Some(gesture) = self.pointer.release(
+                            button,
+                            state.ctx.mouse.coords,
+                            now,
+                            &state.ctx.input_settings,
+                        )
+                    {
+                        deliver_pointer(
+                            #[cfg(not(target_arch = "wasm32"))]
+                            &self.async_runtime,
+                            &self.proxy,
+                            &mut self.graphics_flows,
+                            state,
+                            gesture,
+                        );
+                    }
+                }
+            }
+            _ => {}
+        }
+        self.sync_flows();
+    }
+}
+
+impl<State, Event> App<State, Event>
+where
+    State: 'static + Default,
+    Event: Send + 'static,
+{
+    /**
+     * Creates the app to run in an event loop the host owns, posting its events through
+     * `proxy`. [`run`] does the same within an event loop of its own.
+     *
+     * The host's user event type wraps [`EngineEvent`] through `From` and the host forwards
+     * the loop's callbacks: `resumed`, `suspended` and `about_to_wait` through the app's
+     * [`ApplicationHandler`] implementation, window and device events through
+     * [`App::handle_window_event`] and [`App::handle_device_event`] and the wrapped events
+     * through [`App::handle_user_event`]. The app opens its window on the first `resumed`
+     * and ignores events of the host's windows. It sets the loop's `ControlFlow` in
+     * `about_to_wait` and never stops the loop, once it exited the host drops it, see
+     * [`App::has_exited`].
+     */
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn new_with_proxy<T>(
+        config: RunConfig,
+        constructors: Vec<FlowConstructor<State, Event>>,
+        proxy: EventLoopProxy<T>,
+    ) -> Self
+    where
+        T: From<EngineEvent<State, Event>> + Send,
+    {
+        Self::with_proxy(EngineProxy::new(proxy), config, constructors)
+    }
+
+    /// Like the native `new_with_proxy`, on the web the host's events don't have to be `Send`.
+    #[cfg(target_arch = "wasm32")]
+    pub fn new_with_proxy<T>(
+        config: RunConfig,
+        constructors: Vec<FlowConstructor<State, Event>>,
+        proxy: EventLoopProxy<T>,
+    ) -> Self
+    where
+        T: From<EngineEvent<State, Event>>,
+    {
+        Self::with_proxy(EngineProxy::new(proxy), config, constructors)
+    }
+
+    /// Starts the app from `snapshot` instead of `State::default()`, like [`run_with_restore`].
+    pub fn with_snapshot(mut self, snapshot: Snapshot<State>) -> Self {
+        self.restore = Some(snapshot);
+        self
+    }
+
+    /// Feeds the inputs of the next recorded frame while replaying, once the previous one was
+    /// drawn. Live input resumes once the recording ends.
+    #[cfg(feature = "record")]
+    fn feed_replay(&mut self, event_loop: &ActiveEventLoop, window_id: winit::window::WindowId) {
+        let Some(state) = &mut self.state else {
+            return;
+        };
+        let Some(Session::Replay(replay)) = &mut state.ctx.session else {
+            return;
+        };
+        if replay.is_finished() {
+            log::info!("The replay finished, taking live input again");
+            state.ctx.session = None;
+            return;
+        }
+        let Some(inputs) = replay.start_frame() else {
+            return;
+        };
+        for recorded in inputs {
+            let Some(state) = &mut self.state else {
+                return;
+            };
+            let Some(Session::Replay(replay)) = &mut state.ctx.session else {
+                return;
+            };
+            replay.feed(Some(recorded.at));
+            match recorded.input.into_replayed() {
+                ReplayedInput::Window(event) => self.window_event(event_loop, window_id, event),
+                ReplayedInput::Device(event) => {
+                    self.device_event(event_loop, DeviceId::dummy(), event)
                 }
+                ReplayedInput::Key {
+                    key: PhysicalKey::Code(key),
+                    state: key_state,
+                    repeat,
+                } => self.replay_key(key, key_state, repeat),
+                ReplayedInput::Key { .. } => (),
             }
-            _ => {}
         }
+        if let Some(state) = &mut self.state
+            && let Some(Session::Replay(replay)) = &mut state.ctx.session
+        {
+            replay.feed(None);
+        }
+    }
+
+    /// Dispatches a replayed key like a live `KeyboardInput`, but to `on_key` and
+    /// [`Controller::handle_key`] as winit's key events can't be recreated.
+    ///
+    /// [`Controller::handle_key`]: crate::camera::Controller::handle_key
+    #[cfg(feature = "record")]
+    fn replay_key(&mut self, key: KeyCode, key_state: ElementState, repeat: bool) {
+        let Some(state) = &mut self.state else {
+            return;
+        };
+        context::request_frame();
+        state.ctx.input.key(key, key_state, repeat);
+        let focus = state
+            .ctx
+            .keyboard_focus()
+            .filter(|&flow_id| self.graphics_flows.contains(flow_id));
+        toggle_pause(&mut state.ctx, focus, key, key_state, repeat);
+
+        state.ctx.take_input_captured();
+        let focused_flow = focus.and_then(|flow_id| {
+            self.graphics_flows
+                .get_mut(flow_id)
+                .map(|flow| (flow_id, flow))
+        });
+        let flows: Vec<_> = match focused_flow {
+            Some((flow_id, _)) if !state.ctx.flow_activity(flow_id).input => Vec::new(),
+            Some((flow_id, flow)) => vec![(flow_id, flow)],
+            None => self.graphics_flows.iter_mut_with_ids().collect(),
+        };
+        for (flow_id, f) in flows {
+            if focus.is_none() && !takes_input(flow_id, f.as_ref(), &state.ctx) {
+                continue;
+            }
+            let start = Instant::now();
+            let events = f.on_key(&state.ctx, &mut state.state, key, key_state);
+            handle_flow_output(
+                #[cfg(not(target_arch = "wasm32"))]
+                &self.async_runtime,
+                &mut state.state,
+                &mut state.ctx,
+                self.proxy.clone(),
+                events,
+            );
+            state
+                .ctx
+                .profiler
+                .record(flow_id, Hook::WindowEvents, start.elapsed());
+            if state.ctx.input_captured() {
+                break;
+            }
+        }
+        let captured = state.ctx.take_input_captured();
+        let camera_input = !captured
+            && !state.ctx.is_paused()
+            && !state.ctx.camera.is_animating()
+            && state.ctx.camera_controller_enabled;
+        // Releases still reach the controller so keys held while focus is taken don't get stuck
+        if (focus.is_none() && camera_input) || !key_state.is_pressed() {
+            state.ctx.camera.controller.handle_key(key, key_state);
+        }
+    }
+
+    /// Handles an event of the app's windows, others are ignored.
+    pub fn handle_window_event(
+        &mut self,
+        event_loop: &ActiveEventLoop,
+        window_id: winit::window::WindowId,
+        event: WindowEvent,
+    ) {
+        self.window_event(event_loop, window_id, event);
+    }
+
+    pub fn handle_device_event(
+        &mut self,
+        event_loop: &ActiveEventLoop,
+        device_id: DeviceId,
+        event: DeviceEvent,
+    ) {
+        self.device_event(event_loop, device_id, event);
+    }
+
+    /// Handles an event the app posted to the host's event loop.
+    pub fn handle_user_event(
+        &mut self,
+        event_loop: &ActiveEventLoop,
+        event: EngineEvent<State, Event>,
+    ) {
+        self.user_event(event_loop, event);
+    }
+
+    /// Updates the flows and draws a frame to the main window right away, like a
+    /// `RedrawRequested` of it. Does nothing before the window was created.
+    pub fn render_frame(&mut self, event_loop: &ActiveEventLoop) {
+        if let Some(window_id) = self.window_id {
+            self.window_event(event_loop, window_id, WindowEvent::RedrawRequested);
+        }
+    }
+
+    /// Posts custom events and state mutations to the flows, e.g. from the host's menus.
+    pub fn event_sender(&self) -> EventSender<State, Event> {
+        EventSender {
+            proxy: self.proxy.clone(),
+        }
+    }
+
+    /// Whether the app exited, through [`Context::request_exit`], the close button of its
+    /// window or an error it couldn't recover from, see [`App::take_error`].
+    pub fn has_exited(&self) -> bool {
+        self.exiting
+    }
+
+    /// The error that stopped the app, which [`run`] returns.
+    pub fn take_error(&mut self) -> Option<Error> {
+        self.error.take()
     }
 }
 
-fn handle_flow_output<State, Event: Send>(
+fn handle_flow_output<State: 'static, Event: Send + 'static>(
     #[cfg(not(target_arch = "wasm32"))] async_runtime: &tokio::runtime::Runtime,
     state: &mut State,
     ctx: &mut Context,
-    proxy: winit::event_loop::EventLoopProxy<FlowEvent<State, Event>>,
+    proxy: EngineProxy<State, Event>,
     out: Out<State, Event>,
 ) {
     match out {
@@ -1095,13 +4750,12 @@ fn handle_flow_output<State, Event: Send>(
             {
                 async_runtime.spawn(async move {
                     let resolved = fut.await;
-                    resolved.into_iter().for_each(|event| {
-                        let err = proxy.send_event(FlowEvent::Custom(event));
-                        if let Err(err) = err {
-                            log::error!("{}", err);
-                            panic!("Event loop was cloesed before all events could be processed.")
+                    for event in resolved {
+                        if proxy.send_event(FlowEvent::Custom(event)).is_err() {
+                            log::warn!("The event loop closed before all events were delivered");
+                            return;
                         }
-                    });
+                    }
                 });
             }
 
@@ -1110,13 +4764,32 @@ fn handle_flow_output<State, Event: Send>(
                 wasm_bindgen_futures::spawn_local(async move {
                     let resolved = fut.await;
                     for event in resolved {
-                        assert!(proxy.send_event(FlowEvent::Custom(event)).is_ok());
+                        if proxy.send_event(FlowEvent::Custom(event)).is_err() {
+                            log::warn!("The event loop closed before all events were delivered");
+                            return;
+                        }
                     }
                 });
             }
         }
-        // Mutate the state if the arch supports async, create an event otherwise
+        // Resolved in the background, the mutations come back as events in their order
+        #[cfg(not(target_arch = "wasm32"))]
         Out::FutFn(futures) => {
+            let fut =
+                async move { futures::future::join_all(futures.into_iter().map(Pin::from)).await };
+            async_runtime.spawn(async move {
+                for mutation in fut.await {
+                    if proxy.send_event(FlowEvent::Mut(mutation)).is_err() {
+                        log::warn!("The event loop closed before a state mutation was applied");
+                        return;
+                    }
+                }
+            });
+        }
+        #[cfg(target_arch = "wasm32")]
+        Out::FutFn(futures) => handle_flow_output(state, ctx, proxy, Out::FutFnLocal(futures)),
+        // Mutate the state if the arch supports async, create an event otherwise
+        Out::FutFnLocal(futures) => {
             let events: Vec<Pin<Box<dyn Future<Output = Box<dyn FnOnce(&mut State)>>>>> =
                 futures.into_iter().map(Pin::from).collect();
             let fut = async move { futures::future::join_all(events.into_iter()).await };
@@ -1133,14 +4806,23 @@ fn handle_flow_output<State, Event: Send>(
                 wasm_bindgen_futures::spawn_local(async move {
                     let resolved = fut.await;
                     for mutation in resolved {
-                        assert!(proxy.send_event(FlowEvent::Mut(mutation)).is_ok());
+                        if proxy.send_event(FlowEvent::Mut(mutation)).is_err() {
+                            log::warn!("The event loop closed before a state mutation was applied");
+                            return;
+                        }
                     }
                 });
             }
         }
         Out::Configure(f) => f(ctx),
-        Out::Composed(outs) => {
-            for out in outs {
+        Out::ConfigureFut(future) => {
+            ctx.configure_futures.push(ConfigureFuture {
+                ticket: ctx.configures.ticket(),
+                future: Pin::from(future),
+            });
+        }
+        out @ Out::Composed(_) => {
+            for out in out.into_steps() {
                 handle_flow_output(
                     #[cfg(not(target_arch = "wasm32"))]
                     async_runtime,
@@ -1151,13 +4833,310 @@ fn handle_flow_output<State, Event: Send>(
                 );
             }
         }
+        Out::Emit(event) => {
+            if proxy.send_event(FlowEvent::Custom(event)).is_err() {
+                log::warn!("The event loop closed before an emitted event was delivered");
+            }
+        }
+        Out::EmitTo(id, event) => {
+            if proxy.send_event(FlowEvent::CustomTo(id, event)).is_err() {
+                log::warn!("The event loop closed before an emitted event was delivered");
+            }
+        }
+        Out::Exit => ctx.request_exit(),
+        Out::Capture => ctx.capture_input(),
+        Out::SpawnFlow(constructor) => ctx.spawned_flows.push(Box::new(constructor)),
+        Out::RemoveFlow(id) => ctx.remove_flow(id),
+        Out::Snapshot(write) => ctx.snapshots.push(Box::new(write)),
         Out::Empty => (),
     }
 }
 
+/// Applies the resolved `Out::ConfigureFut` of `ticket` and the later ones that waited for it.
+/// A failed one is `None` and only releases the later ones.
+fn apply_configure(ctx: &mut Context, ticket: u64, configure: Option<ConfigureFn>) {
+    ctx.configures.resolve(ticket, configure);
+    for configure in ctx.configures.take_ready().into_iter().flatten() {
+        configure(ctx);
+    }
+}
+
+/// Attributes of the window [`run_with`] creates and the GPU adapter it renders with. The
+/// default matches `Window::default_attributes()` and [`AdapterOptions::default`].
+///
+/// ```ignore
+/// let config = RunConfig::default()
+///     .with_backends(Backends::VULKAN)
+///     .with_power_preference(PowerPreference::HighPerformance);
+/// flow_ngin::flow::run_with(config, constructors)?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct RunConfig {
+    pub title: Option<String>,
+    pub inner_size: Option<Size>,
+    pub min_inner_size: Option<Size>,
+    pub max_inner_size: Option<Size>,
+    /// Encoded image, e.g. the contents of a png, in any format the `image` crate decodes.
+    pub icon: Option<Vec<u8>>,
+    pub resizable: bool,
+    /// Id of the canvas element the app renders into on the web.
+    pub canvas_id: String,
+    /// GPU backends and adapter to render with.
+    pub adapter: AdapterOptions,
+    pub device: DeviceRequest,
+    /// Assets checked before the flows are constructed, see [`RunConfig::validate_assets`].
+    pub asset_manifest: Vec<String>,
+    pub on_fatal_error: Option<FatalErrorHandler>,
+    pub on_error: Option<ErrorHandler>,
+    /// Drawn while the flow constructors run, see [`crate::loading`].
+    pub loading_screen: LoadingScreen,
+    /// Records the session to replay it with [`run_replay`], see [`crate::record`].
+    #[cfg(feature = "record")]
+    pub recorder: Option<Recorder>,
+}
+
+/// Called with the error the app couldn't start with, e.g. to show it on the page where the
+/// web has no `run` to return it to.
+#[derive(Clone)]
+pub struct FatalErrorHandler(Arc<dyn Fn(&Error)>);
+
+impl Debug for FatalErrorHandler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("FatalErrorHandler")
+    }
+}
+
+/// Called with an error the app recovered from, see [`RunConfig::with_error_handler`].
+#[derive(Clone)]
+pub struct ErrorHandler(Arc<dyn Fn(&anyhow::Error)>);
+
+impl Debug for ErrorHandler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ErrorHandler")
+    }
+}
+
+impl Default for RunConfig {
+    fn default() -> Self {
+        Self {
+            title: None,
+            inner_size: None,
+            min_inner_size: None,
+            max_inner_size: None,
+            icon: None,
+            resizable: true,
+            canvas_id: "canvas".to_string(),
+            adapter: AdapterOptions::default(),
+            device: DeviceRequest::default(),
+            asset_manifest: Vec::new(),
+            on_fatal_error: None,
+            on_error: None,
+            loading_screen: LoadingScreen::default(),
+            #[cfg(feature = "record")]
+            recorder: None,
+        }
+    }
+}
+
+impl RunConfig {
+    pub fn with_title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    pub fn with_inner_size(mut self, size: impl Into<Size>) -> Self {
+        self.inner_size = Some(size.into());
+        self
+    }
+
+    pub fn with_min_inner_size(mut self, size: impl Into<Size>) -> Self {
+        self.min_inner_size = Some(size.into());
+        self
+    }
+
+    pub fn with_max_inner_size(mut self, size: impl Into<Size>) -> Self {
+        self.max_inner_size = Some(size.into());
+        self
+    }
+
+    pub fn with_icon(mut self, bytes: impl Into<Vec<u8>>) -> Self {
+        self.icon = Some(bytes.into());
+        self
+    }
+
+    pub fn with_resizable(mut self, resizable: bool) -> Self {
+        self.resizable = resizable;
+        self
+    }
+
+    pub fn with_canvas_id(mut self, id: impl Into<String>) -> Self {
+        self.canvas_id = id.into();
+        self
+    }
+
+    pub fn with_adapter(mut self, adapter: AdapterOptions) -> Self {
+        self.adapter = adapter;
+        self
+    }
+
+    /// Restricts the adapters to `backends`, e.g. `Backends::VULKAN`. The default backends
+    /// are used if none of them has an adapter.
+    pub fn with_backends(mut self, backends: wgpu::Backends) -> Self {
+        self.adapter.backends = backends;
+        self
+    }
+
+    /// Prefers e.g. the discrete GPU of a laptop with [`wgpu::PowerPreference::HighPerformance`].
+    pub fn with_power_preference(mut self, preference: wgpu::PowerPreference) -> Self {
+        self.adapter.power_preference = preference;
+        self
+    }
+
+    /// Requests the device with `request`, [`run_with`] fails with
+    /// [`Error::MissingCapabilities`] if the adapter doesn't support all of it.
+    pub fn with_device_request(mut self, request: DeviceRequest) -> Self {
+        self.device = request;
+        self
+    }
+
+    /// Requests `features` in addition to those already requested, e.g.
+    /// [`wgpu::Features::POLYGON_MODE_LINE`] for wireframe pipelines.
+    pub fn with_features(mut self, features: wgpu::Features) -> Self {
+        self.device.features |= features;
+        self
+    }
+
+    pub fn with_limits(mut self, limits: wgpu::Limits) -> Self {
+        self.device.limits = limits;
+        self
+    }
+
+    /// Picks the adapter whose name contains `name` ignoring case, see [`AdapterOptions::name`].
+    pub fn with_adapter_name(mut self, name: impl Into<String>) -> Self {
+        self.adapter.name = Some(name.into());
+        self
+    }
+
+    /// Checks that `manifest` and the files its models reference load before any flow is
+    /// constructed. Startup fails with one [`Error::InvalidAssets`] listing every problem
+    /// instead of the first missing texture a constructor runs into.
+    pub fn validate_assets(
+        mut self,
+        manifest: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        self.asset_manifest
+            .extend(manifest.into_iter().map(Into::into));
+        self
+    }
+
+    /// Calls `handler` with the error the app couldn't start with. `run` also returns it, but
+    /// never returns on the web.
+    pub fn with_fatal_error_handler(mut self, handler: impl Fn(&Error) + 'static) -> Self {
+        self.on_fatal_error = Some(FatalErrorHandler(Arc::new(handler)));
+        self
+    }
+
+    /// Calls `handler` with errors the app recovers from, e.g. of a failed
+    /// `Out::ConfigureFut` or `Out::SpawnFlow`. They're logged either way.
+    pub fn with_error_handler(mut self, handler: impl Fn(&anyhow::Error) + 'static) -> Self {
+        self.on_error = Some(ErrorHandler(Arc::new(handler)));
+        self
+    }
+
+    /// Draws `screen` until every flow constructor resolved, e.g. [`LoadingScreen::Spinner`]
+    /// for scenes downloading their assets on the web.
+    pub fn with_loading_screen(mut self, screen: LoadingScreen) -> Self {
+        self.loading_screen = screen;
+        self
+    }
+
+    /// Records the session with `recorder`, e.g. `Recorder::new("session.json")`, to replay
+    /// it with [`run_replay`].
+    #[cfg(feature = "record")]
+    pub fn with_recorder(mut self, recorder: Recorder) -> Self {
+        self.recorder = Some(recorder);
+        self
+    }
+
+    fn window_attributes(&self) -> Result<WindowAttributes, Error> {
+        let mut attributes = Window::default_attributes().with_resizable(self.resizable);
+        if let Some(title) = &self.title {
+            attributes = attributes.with_title(title);
+        }
+        if let Some(size) = self.inner_size {
+            attributes = attributes.with_inner_size(size);
+        }
+        if let Some(size) = self.min_inner_size {
+            attributes = attributes.with_min_inner_size(size);
+        }
+        if let Some(size) = self.max_inner_size {
+            attributes = attributes.with_max_inner_size(size);
+        }
+        if let Some(bytes) = &self.icon {
+            attributes = attributes.with_window_icon(Some(decode_icon(bytes)?));
+        }
+        Ok(attributes)
+    }
+}
+
+fn decode_icon(bytes: &[u8]) -> Result<Icon, Error> {
+    let image = image::load_from_memory(bytes)
+        .map_err(|e| Error::decode("window icon", e))?
+        .into_rgba8();
+    let (width, height) = image.dimensions();
+    Icon::from_rgba(image.into_raw(), width, height).map_err(|e| Error::decode("window icon", e))
+}
+
+/// Runs the flows in a window with the default [`RunConfig`].
 pub fn run<State: 'static + Default, Event: Send + 'static>(
     constructors: Vec<FlowConstructor<State, Event>>,
-) -> anyhow::Result<()> {
+) -> Result<(), Error> {
+    run_with(RunConfig::default(), constructors)
+}
+
+/// Runs flows of `GraphicsFlow<(), NoEvent>` in a window with the default [`RunConfig`], for
+/// apps that don't need shared state or custom events.
+pub fn run_simple(constructors: Vec<SimpleFlowConstructor>) -> Result<(), Error> {
+    run(constructors)
+}
+
+/// Runs the flows in a window configured by `config`.
+pub fn run_with<State: 'static + Default, Event: Send + 'static>(
+    config: RunConfig,
+    constructors: Vec<FlowConstructor<State, Event>>,
+) -> Result<(), Error> {
+    run_with_restore(config, constructors, None)
+}
+
+/// Runs the flows like [`run_with`], starting from `snapshot` if there is one, e.g. a loaded
+/// save game. Its state replaces `State::default()` before `on_init`, its view is restored
+/// after and every flow gets `on_restore`. See [`crate::persist`].
+pub fn run_with_restore<State: 'static + Default, Event: Send + 'static>(
+    config: RunConfig,
+    constructors: Vec<FlowConstructor<State, Event>>,
+    snapshot: Option<Snapshot<State>>,
+) -> Result<(), Error> {
+    launch(config, constructors, |app| app.restore = snapshot)
+}
+
+/// Replays `recording` from the start with the same flows it was recorded with, see
+/// [`crate::record`].
+#[cfg(feature = "record")]
+pub fn run_replay<State: 'static + Default, Event: Send + 'static>(
+    constructors: Vec<FlowConstructor<State, Event>>,
+    recording: Recording,
+) -> Result<(), Error> {
+    launch(RunConfig::default(), constructors, |app| {
+        app.session = Some(Session::Replay(Replay::new(recording)));
+    })
+}
+
+/// Runs the app `prepare`d before the event loop starts.
+fn launch<State: 'static + Default, Event: Send + 'static>(
+    config: RunConfig,
+    constructors: Vec<FlowConstructor<State, Event>>,
+    prepare: impl FnOnce(&mut App<State, Event>),
+) -> Result<(), Error> {
     #[cfg(not(target_arch = "wasm32"))]
     {
         if let Err(e) = env_logger::try_init() {
@@ -1171,31 +5150,633 @@ pub fn run<State: 'static + Default, Event: Send + 'static>(
     }
 
     #[cfg(all(feature = "integration-tests", target_os = "linux"))]
-    let event_loop: EventLoop<FlowEvent<State, Event>> = {
+    let event_loop: EventLoop<EngineEvent<State, Event>> = {
         use winit::platform::wayland::EventLoopBuilderExtWayland;
 
         winit::event_loop::EventLoop::with_user_event()
             .with_any_thread(true)
-            .build()
-            .expect("Failed to create an event loop")
+            .build()?
     };
 
     #[cfg(all(feature = "integration-tests", target_os = "windows"))]
-    let event_loop: EventLoop<FlowEvent<State, Event>> = {
+    let event_loop: EventLoop<EngineEvent<State, Event>> = {
         use winit::platform::windows::EventLoopBuilderExtWindows;
 
         winit::event_loop::EventLoop::with_user_event()
             .with_any_thread(true)
-            .build()
-            .expect("Failed to create an event loop")
+            .build()?
     };
 
     #[cfg(not(feature = "integration-tests"))]
-    let event_loop: EventLoop<FlowEvent<State, Event>> = EventLoop::with_user_event().build()?;
+    let event_loop: EventLoop<EngineEvent<State, Event>> = EventLoop::with_user_event().build()?;
 
-    let mut app: App<State, Event> = App::new(&event_loop, constructors);
+    let mut app: App<State, Event> = App::new(&event_loop, config, constructors);
+    prepare(&mut app);
 
     event_loop.run_app(&mut app)?;
 
-    Ok(())
+    app.take_error().map_or(Ok(()), Err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::map_id_list;
+    use std::collections::HashMap;
+
+    #[test]
+    fn async_constructors_box_into_simple_flows() {
+        struct Noop;
+        impl GraphicsFlow<(), NoEvent> for Noop {}
+        async fn new(_: InitContext) -> Noop {
+            Noop
+        }
+        let constructors: Vec<SimpleFlowConstructor> = vec![new.into_flow()];
+        assert_eq!(constructors.len(), 1);
+    }
+
+    #[test]
+    fn composed_outputs_flatten_and_configure_first() {
+        type TestOut = Out<Vec<&'static str>, ()>;
+        let mutation = || -> TestOut { Out::FutFnLocal(Vec::new()) };
+        let configure = || -> TestOut { Out::Configure(Box::new(|_| ())) };
+        let composed: TestOut = Out::Composed(vec![
+            mutation(),
+            Out::Composed(vec![Out::Emit(()), configure(), Out::Empty]),
+            Out::Composed(Vec::new()),
+            configure(),
+        ]);
+        let steps = composed.into_steps();
+        assert_eq!(steps.len(), 4);
+        assert!(matches!(steps[0], Out::Configure(_)));
+        assert!(matches!(steps[1], Out::Configure(_)));
+        assert!(matches!(steps[2], Out::FutFnLocal(_)));
+        assert!(matches!(steps[3], Out::Emit(())));
+
+        assert!(Out::Composed(Vec::<TestOut>::new()).into_steps().is_empty());
+        let collected: TestOut = [Out::Empty, Out::Composed(vec![Out::Exit])]
+            .into_iter()
+            .collect();
+        assert!(matches!(collected, Out::Exit));
+        assert!(matches!(TestOut::from_iter([Out::Empty]), Out::Empty));
+    }
+
+    #[test]
+    fn icons_are_decoded_from_image_bytes() {
+        let mut png = Vec::new();
+        image::RgbaImage::new(16, 16)
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        assert!(decode_icon(&png).is_ok());
+        assert!(matches!(
+            decode_icon(b"not an image"),
+            Err(Error::Decode { path, .. }) if path == "window icon"
+        ));
+        let config = RunConfig::default().with_icon(b"not an image".as_slice());
+        assert!(config.window_attributes().is_err());
+    }
+
+    #[test]
+    fn resize_burst_applies_latest_size_once() {
+        let mut resize = ResizeDebouncer::default();
+        for width in 800..840 {
+            resize.request(width, 600);
+        }
+        assert_eq!(resize.take(), Some(ResizeAction::Apply(839, 600)));
+        assert_eq!(resize.take(), None);
+    }
+
+    #[test]
+    fn unchanged_size_is_not_reapplied() {
+        let mut resize = ResizeDebouncer::default();
+        resize.request(800, 600);
+        assert_eq!(resize.take(), Some(ResizeAction::Apply(800, 600)));
+        resize.request(900, 600);
+        resize.request(800, 600);
+        assert_eq!(resize.take(), None);
+    }
+
+    #[test]
+    fn minimize_suspends_once_and_restore_reapplies_last_size() {
+        let mut resize = ResizeDebouncer::default();
+        resize.request(800, 600);
+        resize.take();
+        resize.request(0, 0);
+        assert_eq!(resize.take(), Some(ResizeAction::Suspend));
+        resize.request(800, 0);
+        assert_eq!(resize.take(), None);
+        assert_eq!(resize.restore(), Some(ResizeAction::Apply(800, 600)));
+        assert_eq!(resize.restore(), None);
+    }
+
+    #[test]
+    fn restore_reports_same_size_again() {
+        let mut resize = ResizeDebouncer::default();
+        resize.request(800, 600);
+        resize.take();
+        resize.request(0, 0);
+        resize.take();
+        resize.request(800, 600);
+        assert_eq!(resize.take(), Some(ResizeAction::Apply(800, 600)));
+    }
+
+    #[test]
+    fn reapply_repeats_the_current_size_once() {
+        let mut resize = ResizeDebouncer::default();
+        resize.reapply();
+        assert_eq!(resize.take(), None);
+        resize.request(800, 600);
+        resize.take();
+        resize.reapply();
+        assert_eq!(resize.take(), Some(ResizeAction::Apply(800, 600)));
+        assert_eq!(resize.take(), None);
+        resize.request(900, 600);
+        resize.reapply();
+        assert_eq!(resize.take(), Some(ResizeAction::Apply(900, 600)));
+    }
+
+    #[test]
+    fn ticks_carry_the_remainder_across_frames() {
+        let tick = Duration::from_millis(100);
+        let mut ticks = TickAccumulator::default();
+        // Frames slower than the tick rate still tick 10 times per second
+        let due: u32 = (0..8)
+            .map(|_| ticks.advance(Duration::from_millis(125), tick, false))
+            .sum();
+        assert_eq!(due, 10);
+        assert_eq!(ticks.alpha(tick), 0.0);
+        assert_eq!(ticks.advance(Duration::from_millis(60), tick, false), 0);
+        assert!((ticks.alpha(tick) - 0.6).abs() < 1e-6);
+        assert_eq!(
+            ticks.until_tick(tick, Duration::from_millis(10), false),
+            Some(Duration::from_millis(30))
+        );
+    }
+
+    #[test]
+    fn fixed_steps_interpolate_and_cap() {
+        let step = Duration::from_millis(10);
+        let mut steps = TickAccumulator::default();
+        let max = MAX_FIXED_STEPS_PER_FRAME;
+        assert_eq!(
+            steps.advance_max(Duration::from_millis(25), step, false, max),
+            2
+        );
+        assert!((steps.alpha(step) - 0.5).abs() < 1e-6);
+        // A hitch is simulated in at most `max` steps, the rest of the backlog is dropped
+        assert_eq!(
+            steps.advance_max(Duration::from_secs(1), step, false, max),
+            max
+        );
+        assert_eq!(
+            steps.advance_max(Duration::from_millis(5), step, false, max),
+            1
+        );
+    }
+
+    #[test]
+    fn event_senders_move_to_other_threads() {
+        fn assert_send<T: Send + Clone>() {}
+        assert_send::<EventSender<Vec<u8>, String>>();
+    }
+
+    #[test]
+    fn frame_cap_waits_for_the_interval() {
+        let interval = Some(Duration::from_millis(33));
+        assert_eq!(until_frame(None, Duration::ZERO), Duration::ZERO);
+        assert_eq!(
+            until_frame(interval, Duration::from_millis(13)),
+            Duration::from_millis(20)
+        );
+        assert_eq!(
+            until_frame(interval, Duration::from_millis(40)),
+            Duration::ZERO
+        );
+    }
+
+    #[test]
+    fn ticks_are_capped_and_pausable() {
+        let tick = Duration::from_millis(10);
+        let mut ticks = TickAccumulator::default();
+        assert_eq!(ticks.advance(Duration::from_secs(5), tick, true), 0);
+        assert_eq!(ticks.until_tick(tick, Duration::ZERO, true), None);
+        assert_eq!(
+            ticks.advance(Duration::from_millis(1005), tick, false),
+            MAX_TICKS_PER_FRAME
+        );
+        // The dropped backlog doesn't tick on later frames
+        assert_eq!(ticks.advance(Duration::from_millis(4), tick, false), 0);
+        assert_eq!(ticks.advance(Duration::from_millis(1), tick, false), 1);
+    }
+
+    #[test]
+    fn ordered_queue_releases_in_ticket_order() {
+        let mut queue = OrderedQueue::default();
+        let (first, second, third) = (queue.ticket(), queue.ticket(), queue.ticket());
+        queue.resolve(third, "third");
+        queue.resolve(second, "second");
+        assert!(queue.take_ready().is_empty());
+        queue.resolve(first, "first");
+        assert_eq!(queue.take_ready(), ["first", "second", "third"]);
+        let fourth = queue.ticket();
+        queue.resolve(fourth, "fourth");
+        assert_eq!(queue.take_ready(), ["fourth"]);
+    }
+
+    #[test]
+    fn restore_without_valid_size_does_nothing() {
+        let mut resize = ResizeDebouncer::default();
+        resize.request(0, 0);
+        assert_eq!(resize.take(), Some(ResizeAction::Suspend));
+        assert_eq!(resize.restore(), None);
+    }
+
+    #[test]
+    fn lost_surfaces_retry_with_backoff() {
+        let mut recovery = SurfaceRecovery::default();
+        assert!(recovery.frame() && !recovery.holds_updates());
+        recovery.lost();
+        let draws: Vec<_> = (1..=40).filter(|_| recovery.frame()).collect();
+        // failed attempts don't restart the backoff
+        recovery.lost();
+        assert_eq!(draws, [1, 3, 7, 15, 31]);
+        assert!(recovery.holds_updates());
+        (41..=MAX_RECOVERY_FRAMES).for_each(|_| {
+            recovery.frame();
+        });
+        assert!(
+            !recovery.holds_updates(),
+            "held for at most MAX_RECOVERY_FRAMES"
+        );
+        assert!(recovery.is_recovering());
+
+        assert!(recovery.acquired(), "ends the recovery");
+        assert!(!recovery.acquired());
+        assert!(recovery.frame() && !recovery.is_recovering());
+    }
+
+    /// Routes a click on `pick_id` the way `dispatch_click` does, returning the names of the
+    /// flows that get `on_click`.
+    fn route(
+        flows: &mut Flows<&'static str>,
+        translation: &HashMap<PickId, BTreeSet<FlowId>>,
+        pick_id: u32,
+    ) -> Vec<&'static str> {
+        let owners = translation
+            .get(&PickId(pick_id))
+            .cloned()
+            .unwrap_or_default();
+        flows
+            .dispatch_order(&owners)
+            .into_iter()
+            .filter_map(|flow_id| flows.get_mut(flow_id).map(|name| *name))
+            .collect()
+    }
+
+    #[test]
+    fn flows_dispatch_by_priority_then_insertion() {
+        let mut flows = Flows::new();
+        let world = flows.insert("world", 0);
+        let menu = flows.insert("menu", 10);
+        let hud = flows.insert("hud", 0);
+        let debug = flows.insert("debug", -5);
+        assert_eq!(
+            flows.iter().copied().collect::<Vec<_>>(),
+            ["menu", "world", "hud", "debug"]
+        );
+        assert_eq!(
+            flows.dispatch_order(&[debug, hud, world, menu].into()),
+            [menu, world, hud, debug]
+        );
+    }
+
+    #[test]
+    fn reordered_flows_swap_their_places() {
+        let mut flows = Flows::new();
+        let gui = flows.insert("gui", 0);
+        let menu = flows.insert("menu", 10);
+        let world = flows.insert("world", 0);
+        let debug = flows.insert("debug", -5);
+        flows.reorder(&[world, FlowId(42), gui, world]);
+        assert_eq!(
+            flows.iter().copied().collect::<Vec<_>>(),
+            ["menu", "world", "gui", "debug"]
+        );
+        flows.reorder(&[debug, menu]);
+        assert_eq!(
+            flows.iter().copied().collect::<Vec<_>>(),
+            ["debug", "world", "gui", "menu"]
+        );
+        // new flows still find their place by priority
+        flows.insert("hud", 0);
+        flows.insert("top", 20);
+        assert_eq!(
+            flows.iter().copied().collect::<Vec<_>>(),
+            ["top", "debug", "world", "gui", "hud", "menu"]
+        );
+    }
+
+    #[test]
+    fn flow_ids_survive_removal() {
+        let mut flows = Flows::new();
+        let a = flows.insert("a", 0);
+        let b = flows.insert("b", 0);
+        let c = flows.insert("c", 0);
+        assert_eq!(flows.remove(b), Some("b"));
+        assert_eq!(flows.remove(b), None);
+        let d = flows.insert("d", 0);
+        assert_ne!(d, b, "ids are never reused");
+        assert_eq!(flows.get_mut(a).copied(), Some("a"));
+        assert_eq!(flows.get_mut(c).copied(), Some("c"));
+        assert_eq!(flows.get_mut(d).copied(), Some("d"));
+        assert!(!flows.contains(b));
+    }
+
+    #[test]
+    fn reserved_flows_keep_their_place() {
+        let mut flows = Flows::new();
+        flows.insert("menu", 0);
+        let slow = flows.reserve_id();
+        let fast = flows.reserve_id();
+        // constructors resolving out of order still dispatch in the order they were spawned
+        flows.insert_as(fast, "fast", 0);
+        flows.insert_as(slow, "slow", 0);
+        flows.insert("hud", 0);
+        assert_eq!(
+            flows.iter().copied().collect::<Vec<_>>(),
+            ["menu", "slow", "fast", "hud"]
+        );
+    }
+
+    #[test]
+    fn events_pass_flows_in_order_until_consumed() {
+        let mut flows = Flows::new();
+        let menu = flows.insert("menu", 10);
+        let world = flows.insert("world", 0);
+        let hud = flows.insert("hud", 0);
+        let mut seen = Vec::new();
+        let mut removed = Vec::new();
+        // The menu removes itself while handling the event, which only happens afterwards
+        let unconsumed = flows.deliver(None, 1, |name, event| {
+            seen.push(*name);
+            if *name == "menu" {
+                removed.push(menu);
+            }
+            (*name != "world").then_some(event)
+        });
+        assert_eq!(unconsumed, None);
+        assert_eq!(seen, ["menu", "world"]);
+        for id in removed {
+            flows.remove(id);
+        }
+
+        seen.clear();
+        let unconsumed = flows.deliver(None, 2, |name, event| {
+            seen.push(*name);
+            Some(event)
+        });
+        assert_eq!(unconsumed, Some(2));
+        assert_eq!(seen, ["world", "hud"]);
+
+        seen.clear();
+        let unconsumed = flows.deliver(Some(hud), 3, |name, _| {
+            seen.push(*name);
+            None
+        });
+        assert_eq!(unconsumed, None);
+        assert_eq!(seen, ["hud"]);
+        assert_eq!(flows.deliver(Some(menu), 4, |_, _| None), Some(4));
+        assert!(flows.contains(world));
+    }
+
+    #[test]
+    fn clicks_reach_owner_after_flows_are_added_and_removed() {
+        let mut flows = Flows::new();
+        let mut translation = HashMap::new();
+        let terrain = flows.insert("terrain", 0);
+        let units = flows.insert("units", 0);
+        let buildings = flows.insert("buildings", 0);
+        map_id_list(&[PickId(1)], terrain, &mut translation);
+        map_id_list(&[PickId(2), PickId(3)], units, &mut translation);
+        map_id_list(&[PickId(4)], buildings, &mut translation);
+
+        flows.remove(units);
+        let menu = flows.insert("menu", 1);
+        map_id_list(&[PickId(5)], menu, &mut translation);
+
+        assert_eq!(route(&mut flows, &translation, 1), ["terrain"]);
+        // a stale translation for a removed flow routes nowhere instead of to its successor
+        assert!(route(&mut flows, &translation, 2).is_empty());
+        assert_eq!(route(&mut flows, &translation, 4), ["buildings"]);
+        assert_eq!(route(&mut flows, &translation, 5), ["menu"]);
+    }
+
+    #[test]
+    fn shared_pick_id_reaches_owners_in_dispatch_order() {
+        let mut flows = Flows::new();
+        let mut translation = HashMap::new();
+        let late = flows.insert("late", 0);
+        let early = flows.insert("early", 3);
+        map_id_list(&[PickId(9)], late, &mut translation);
+        map_id_list(&[PickId(9)], early, &mut translation);
+        assert_eq!(route(&mut flows, &translation, 9), ["early", "late"]);
+    }
+
+    fn at(x: f64, y: f64) -> PhysicalPosition<f64> {
+        PhysicalPosition::new(x, y)
+    }
+
+    #[test]
+    fn tap_presses_releases_and_clicks() {
+        let mut touch = TouchGestures::default();
+        assert_eq!(
+            touch.handle(1, TouchPhase::Started, at(100.0, 100.0)),
+            [Gesture::Press(at(100.0, 100.0))]
+        );
+        touch.handle(1, TouchPhase::Moved, at(103.0, 101.0));
+        assert_eq!(
+            touch.handle(1, TouchPhase::Ended, at(103.0, 101.0)),
+            [Gesture::Release, Gesture::Tap(at(103.0, 101.0))]
+        );
+    }
+
+    #[test]
+    fn one_finger_drag_rotates_instead_of_tapping() {
+        let mut touch = TouchGestures::default();
+        touch.handle(1, TouchPhase::Started, at(100.0, 100.0));
+        assert_eq!(
+            touch.handle(1, TouchPhase::Moved, at(130.0, 90.0)),
+            [Gesture::Rotate {
+                position: at(130.0, 90.0),
+                dx: 30.0,
+                dy: -10.0
+            }]
+        );
+        touch.handle(1, TouchPhase::Moved, at(101.0, 100.0));
+        assert_eq!(
+            touch.handle(1, TouchPhase::Ended, at(101.0, 100.0)),
+            [Gesture::Release]
+        );
+    }
+
+    #[test]
+    fn drags_suppress_the_click() {
+        let settings = InputSettings::default();
+        let mut pointer = PointerGestures::default();
+        let owners = BTreeSet::from([FlowId(2)]);
+        pointer.press(
+            MouseButton::Left,
+            Some((5, owners.clone())),
+            at(10.0, 10.0),
+            false,
+        );
+        assert_eq!(
+            pointer.moved(at(12.0, 10.0), &settings),
+            None,
+            "within the threshold"
+        );
+
+        let (to, events) = pointer.moved(at(30.0, 10.0), &settings).unwrap();
+        assert_eq!(to, owners);
+        assert_eq!(
+            events,
+            [
+                PointerEvent::DragStart {
+                    id: PickId(5),
+                    start: at(10.0, 10.0)
+                },
+                PointerEvent::Dragging {
+                    id: PickId(5),
+                    current: at(30.0, 10.0),
+                    delta: (20.0, 0.0)
+                }
+            ]
+        );
+        let (_, events) = pointer
+            .release(MouseButton::Left, at(30.0, 10.0), Instant::now(), &settings)
+            .unwrap();
+        assert_eq!(events, [PointerEvent::DragEnd { id: PickId(5) }]);
+        assert_eq!(pointer.moved(at(40.0, 10.0), &settings), None);
+    }
+
+    #[test]
+    fn second_click_in_time_is_a_double_click() {
+        let settings = InputSettings::default();
+        let mut pointer = PointerGestures::default();
+        let now = Instant::now();
+        let click = |pointer: &mut PointerGestures, id, after| {
+            pointer.press(
+                MouseButton::Left,
+                Some((id, BTreeSet::new())),
+                at(0.0, 0.0),
+                false,
+            );
+            pointer
+                .release(MouseButton::Left, at(1.0, 0.0), now + after, &settings)
+                .unwrap()
+                .1
+        };
+        let clicked = PointerEvent::Clicked {
+            id: PickId(1),
+            button: MouseButton::Left,
+        };
+        assert_eq!(click(&mut pointer, 1, Duration::ZERO), [clicked]);
+        assert_eq!(
+            click(&mut pointer, 1, Duration::from_millis(200)),
+            [clicked, PointerEvent::DoubleClicked { id: PickId(1) }]
+        );
+        // A third click starts over
+        assert_eq!(
+            click(&mut pointer, 1, Duration::from_millis(300)),
+            [clicked]
+        );
+        assert_eq!(
+            click(&mut pointer, 1, Duration::from_secs(2)),
+            [clicked],
+            "too slow"
+        );
+        assert_eq!(
+            click(&mut pointer, 9, Duration::from_secs(2)).len(),
+            1,
+            "other object"
+        );
+
+        pointer.press(MouseButton::Left, None, at(0.0, 0.0), false);
+        assert_eq!(
+            pointer.release(MouseButton::Left, at(0.0, 0.0), now, &settings),
+            None
+        );
+    }
+
+    #[test]
+    fn presses_on_a_gui_hold_it_until_released() {
+        let settings = InputSettings::default();
+        let mut pointer = PointerGestures::default();
+        pointer.press(
+            MouseButton::Left,
+            Some((3, BTreeSet::new())),
+            at(0.0, 0.0),
+            true,
+        );
+        assert!(pointer.holds_object());
+        assert!(pointer.holds_gui());
+        pointer.release(MouseButton::Left, at(0.0, 0.0), Instant::now(), &settings);
+        assert!(!pointer.holds_gui());
+
+        pointer.press(
+            MouseButton::Left,
+            Some((4, BTreeSet::new())),
+            at(0.0, 0.0),
+            false,
+        );
+        assert!(pointer.holds_object());
+        assert!(!pointer.holds_gui(), "an object of the scene");
+    }
+
+    #[test]
+    fn two_fingers_pinch_and_pan() {
+        let mut touch = TouchGestures::default();
+        touch.handle(1, TouchPhase::Started, at(100.0, 100.0));
+        assert_eq!(
+            touch.handle(2, TouchPhase::Started, at(200.0, 100.0)),
+            [Gesture::Release]
+        );
+        // spreading apart
+        assert_eq!(
+            touch.handle(2, TouchPhase::Moved, at(220.0, 100.0)),
+            [Gesture::Pinch(20.0), Gesture::Pan(10.0, 0.0)]
+        );
+        // both fingers move down together
+        touch.handle(1, TouchPhase::Moved, at(100.0, 140.0));
+        touch.handle(2, TouchPhase::Moved, at(220.0, 140.0));
+        let gestures = touch.handle(1, TouchPhase::Moved, at(100.0, 150.0));
+        assert!(gestures.contains(&Gesture::Pan(0.0, 5.0)));
+        // lifting one finger neither releases nor taps, the other keeps rotating
+        assert!(
+            touch
+                .handle(1, TouchPhase::Ended, at(100.0, 150.0))
+                .is_empty()
+        );
+        assert!(matches!(
+            touch.handle(2, TouchPhase::Moved, at(230.0, 140.0))[..],
+            [Gesture::Rotate { dx: 10.0, .. }]
+        ));
+        assert_eq!(
+            touch.handle(2, TouchPhase::Ended, at(230.0, 140.0)),
+            [Gesture::Release]
+        );
+    }
+
+    #[test]
+    fn cancelled_touch_does_not_click() {
+        let mut touch = TouchGestures::default();
+        touch.handle(7, TouchPhase::Started, at(10.0, 10.0));
+        assert_eq!(
+            touch.handle(7, TouchPhase::Cancelled, at(10.0, 10.0)),
+            [Gesture::Release]
+        );
+        // unknown ids are ignored
+        assert!(touch.handle(8, TouchPhase::Moved, at(0.0, 0.0)).is_empty());
+        assert!(touch.handle(8, TouchPhase::Ended, at(0.0, 0.0)).is_empty());
+    }
 }
@@ -0,0 +1,111 @@
+//! Gamepad input through gilrs, enabled by the `gamepad` feature on native targets.
+//!
+//! The event loop polls connected gamepads whenever it wakes up and passes what happened to
+//! [`GraphicsFlow::on_gamepad_events`]. The left stick moves the camera through
+//! [`Controller::handle_move`] and the south button (A on Xbox layouts) clicks the object
+//! under the cursor, see [`InputSettings::gamepad_camera`].
+//!
+//! [`GraphicsFlow::on_gamepad_events`]: crate::flow::GraphicsFlow::on_gamepad_events
+//! [`Controller::handle_move`]: crate::camera::Controller::handle_move
+//! [`InputSettings::gamepad_camera`]: crate::context::InputSettings::gamepad_camera
+
+pub use gilrs::{Axis as GamepadAxis, Button as GamepadButton};
+
+/// Id of a connected gamepad. A gamepad plugged in again keeps its id.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GamepadId(pub usize);
+
+/// Input of a gamepad since the previous frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GamepadEvent {
+    Connected(GamepadId),
+    Disconnected(GamepadId),
+    ButtonPressed(GamepadId, GamepadButton),
+    ButtonReleased(GamepadId, GamepadButton),
+    /// Deflection of a stick axis in `[-1, 1]`, up and right being positive.
+    AxisChanged(GamepadId, GamepadAxis, f32),
+}
+
+/// Connected gamepads and the deflection of the left stick that moves the camera.
+pub(crate) struct Gamepads {
+    // `None` where gilrs can't access gamepads, e.g. without permissions on `/dev/input`
+    gilrs: Option<gilrs::Gilrs>,
+    left_stick: (f32, f32),
+    // whether the left stick moved the camera at the last poll
+    drives_camera: bool,
+}
+
+impl Gamepads {
+    pub(crate) fn new() -> Self {
+        let gilrs = gilrs::Gilrs::new()
+            .inspect_err(|e| log::warn!("Gamepads are unavailable: {e}"))
+            .ok();
+        Self {
+            gilrs,
+            left_stick: (0.0, 0.0),
+            drives_camera: false,
+        }
+    }
+
+    /// Events since the last poll. Hot-plugged gamepads show up as `Connected`.
+    pub(crate) fn poll(&mut self) -> Vec<GamepadEvent> {
+        let Some(gilrs) = &mut self.gilrs else {
+            return Vec::new();
+        };
+        let mut events = Vec::new();
+        while let Some(gilrs::Event { id, event, .. }) = gilrs.next_event() {
+            let Some(event) = translate(GamepadId(id.into()), event) else {
+                continue;
+            };
+            match event {
+                GamepadEvent::AxisChanged(_, GamepadAxis::LeftStickX, x) => self.left_stick.0 = x,
+                GamepadEvent::AxisChanged(_, GamepadAxis::LeftStickY, y) => self.left_stick.1 = y,
+                // A stick held while unplugging would keep the camera moving
+                GamepadEvent::Disconnected(_) => self.left_stick = (0.0, 0.0),
+                _ => (),
+            }
+            events.push(event);
+        }
+        events
+    }
+
+    /// Latest left stick deflection of any gamepad, right and up being positive.
+    pub(crate) fn left_stick(&self) -> (f32, f32) {
+        self.left_stick
+    }
+
+    /// Whether any gamepad is connected, their input only arrives by polling.
+    pub(crate) fn is_connected(&self) -> bool {
+        self.gilrs
+            .as_ref()
+            .is_some_and(|gilrs| gilrs.gamepads().next().is_some())
+    }
+
+    /// Records whether the stick drives the camera and returns whether it did before.
+    pub(crate) fn set_drives_camera(&mut self, drives_camera: bool) -> bool {
+        std::mem::replace(&mut self.drives_camera, drives_camera)
+    }
+}
+
+impl std::fmt::Debug for Gamepads {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Gamepads")
+            .field("available", &self.gilrs.is_some())
+            .field("left_stick", &self.left_stick)
+            .field("drives_camera", &self.drives_camera)
+            .finish()
+    }
+}
+
+fn translate(id: GamepadId, event: gilrs::EventType) -> Option<GamepadEvent> {
+    use gilrs::EventType;
+    match event {
+        EventType::Connected => Some(GamepadEvent::Connected(id)),
+        EventType::Disconnected => Some(GamepadEvent::Disconnected(id)),
+        EventType::ButtonPressed(button, _) => Some(GamepadEvent::ButtonPressed(id, button)),
+        EventType::ButtonReleased(button, _) => Some(GamepadEvent::ButtonReleased(id, button)),
+        EventType::AxisChanged(axis, value, _) => Some(GamepadEvent::AxisChanged(id, axis, value)),
+        // Repeats, analog trigger values and filtered events
+        _ => None,
+    }
+}
@@ -0,0 +1,231 @@
+//! Keyboard and mouse state collected from window events, see [`Context::input`].
+//!
+//! The engine feeds every window event into [`InputState`] before the flows see it. Presses
+//! and releases are remembered until the end of the next frame, so `on_update` and `on_tick`
+//! can ask whether a key went down since the previous frame instead of tracking
+//! `WindowEvent::KeyboardInput` themselves.
+//!
+//! Keys can also be bound to named actions through [`ActionMap`], e.g. to let players rebind
+//! them, and queried through [`Context::was_action_pressed`].
+//!
+//! [`Context::input`]: crate::context::Context::input
+//! [`Context::was_action_pressed`]: crate::context::Context::was_action_pressed
+
+use std::collections::{HashMap, HashSet};
+
+use winit::{
+    dpi::PhysicalPosition,
+    event::{ElementState, MouseButton, WindowEvent},
+    keyboard::{KeyCode, ModifiersState, PhysicalKey},
+};
+
+/// Keys and mouse buttons held down, pressed or released this frame.
+#[derive(Debug, Clone, Default)]
+pub struct InputState {
+    keys_down: HashSet<KeyCode>,
+    keys_pressed: HashSet<KeyCode>,
+    keys_released: HashSet<KeyCode>,
+    modifiers: ModifiersState,
+    buttons_down: HashSet<MouseButton>,
+    buttons_pressed: HashSet<MouseButton>,
+    buttons_released: HashSet<MouseButton>,
+    // `None` while the cursor is outside of the window
+    cursor: Option<PhysicalPosition<f64>>,
+}
+
+impl InputState {
+    /// Whether `key` is held down, by its physical position on the keyboard.
+    pub fn is_key_down(&self, key: KeyCode) -> bool {
+        self.keys_down.contains(&key)
+    }
+
+    /// Whether `key` went down since the previous frame. Key repeats don't count.
+    pub fn was_key_pressed(&self, key: KeyCode) -> bool {
+        self.keys_pressed.contains(&key)
+    }
+
+    /// Whether `key` was let go since the previous frame.
+    pub fn was_key_released(&self, key: KeyCode) -> bool {
+        self.keys_released.contains(&key)
+    }
+
+    /// Shift, control, alt and super held down.
+    pub fn modifiers(&self) -> ModifiersState {
+        self.modifiers
+    }
+
+    pub fn is_button_down(&self, button: MouseButton) -> bool {
+        self.buttons_down.contains(&button)
+    }
+
+    /// Whether `button` went down since the previous frame.
+    pub fn was_button_pressed(&self, button: MouseButton) -> bool {
+        self.buttons_pressed.contains(&button)
+    }
+
+    /// Whether `button` was let go since the previous frame.
+    pub fn was_button_released(&self, button: MouseButton) -> bool {
+        self.buttons_released.contains(&button)
+    }
+
+    /// Position of the cursor in window coordinates, `None` while it's outside the window.
+    pub fn cursor_position(&self) -> Option<PhysicalPosition<f64>> {
+        self.cursor
+    }
+
+    pub(crate) fn handle_window_event(&mut self, event: &WindowEvent) {
+        match event {
+            WindowEvent::KeyboardInput { event, .. } => {
+                if let PhysicalKey::Code(key) = event.physical_key {
+                    self.key(key, event.state, event.repeat);
+                }
+            }
+            WindowEvent::ModifiersChanged(modifiers) => self.modifiers = modifiers.state(),
+            WindowEvent::MouseInput { state, button, .. } => self.button(*button, *state),
+            WindowEvent::CursorMoved { position, .. } => self.cursor = Some(*position),
+            WindowEvent::CursorLeft { .. } => self.cursor = None,
+            // Releases happening while the window isn't focused never arrive
+            WindowEvent::Focused(false) => self.release_all(),
+            _ => (),
+        }
+    }
+
+    pub(crate) fn key(&mut self, key: KeyCode, state: ElementState, repeat: bool) {
+        match state {
+            ElementState::Pressed if !repeat && self.keys_down.insert(key) => {
+                self.keys_pressed.insert(key);
+            }
+            ElementState::Released if self.keys_down.remove(&key) => {
+                self.keys_released.insert(key);
+            }
+            _ => (),
+        }
+    }
+
+    fn button(&mut self, button: MouseButton, state: ElementState) {
+        match state {
+            ElementState::Pressed if self.buttons_down.insert(button) => {
+                self.buttons_pressed.insert(button);
+            }
+            ElementState::Released if self.buttons_down.remove(&button) => {
+                self.buttons_released.insert(button);
+            }
+            _ => (),
+        }
+    }
+
+    fn release_all(&mut self) {
+        self.keys_released.extend(self.keys_down.drain());
+        self.buttons_released.extend(self.buttons_down.drain());
+        self.modifiers = ModifiersState::empty();
+    }
+
+    /// Forgets this frame's presses and releases, called once the flows were updated.
+    pub(crate) fn end_frame(&mut self) {
+        self.keys_pressed.clear();
+        self.keys_released.clear();
+        self.buttons_pressed.clear();
+        self.buttons_released.clear();
+    }
+}
+
+/// Keys bound to named actions, see [`Context::actions`]. An action may have several keys.
+///
+/// [`Context::actions`]: crate::context::Context::actions
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ActionMap {
+    bindings: HashMap<String, Vec<KeyCode>>,
+}
+
+impl ActionMap {
+    /// Toggles [`Context::set_paused`], bound to Escape by default.
+    ///
+    /// [`Context::set_paused`]: crate::context::Context::set_paused
+    pub const PAUSE: &'static str = "pause";
+
+    /// Adds `key` to the keys triggering `action`.
+    pub fn bind(&mut self, action: impl Into<String>, key: KeyCode) -> &mut Self {
+        let keys = self.bindings.entry(action.into()).or_default();
+        if !keys.contains(&key) {
+            keys.push(key);
+        }
+        self
+    }
+
+    /// Removes every key of `action`, e.g. to disable the pause key.
+    pub fn unbind(&mut self, action: &str) -> &mut Self {
+        self.bindings.remove(action);
+        self
+    }
+
+    /// Keys triggering `action`, in the order they were bound.
+    pub fn keys(&self, action: &str) -> &[KeyCode] {
+        self.bindings.get(action).map_or(&[], Vec::as_slice)
+    }
+
+    /// Whether `key` triggers `action`.
+    pub fn triggers(&self, action: &str, key: KeyCode) -> bool {
+        self.keys(action).contains(&key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presses_are_edges_until_the_frame_ends() {
+        let mut input = InputState::default();
+        input.key(KeyCode::KeyW, ElementState::Pressed, false);
+        assert!(input.is_key_down(KeyCode::KeyW));
+        assert!(input.was_key_pressed(KeyCode::KeyW));
+        input.end_frame();
+        assert!(input.is_key_down(KeyCode::KeyW));
+        assert!(!input.was_key_pressed(KeyCode::KeyW));
+
+        input.key(KeyCode::KeyW, ElementState::Pressed, true);
+        assert!(!input.was_key_pressed(KeyCode::KeyW), "repeats don't press again");
+        input.key(KeyCode::KeyW, ElementState::Released, false);
+        assert!(!input.is_key_down(KeyCode::KeyW));
+        assert!(input.was_key_released(KeyCode::KeyW));
+    }
+
+    #[test]
+    fn tapping_within_a_frame_reports_both_edges() {
+        let mut input = InputState::default();
+        input.button(MouseButton::Left, ElementState::Pressed);
+        input.button(MouseButton::Left, ElementState::Released);
+        assert!(!input.is_button_down(MouseButton::Left));
+        assert!(input.was_button_pressed(MouseButton::Left));
+        assert!(input.was_button_released(MouseButton::Left));
+    }
+
+    #[test]
+    fn losing_focus_releases_everything() {
+        let mut input = InputState::default();
+        input.key(KeyCode::ShiftLeft, ElementState::Pressed, false);
+        input.button(MouseButton::Right, ElementState::Pressed);
+        input.end_frame();
+        input.handle_window_event(&WindowEvent::Focused(false));
+        assert!(!input.is_key_down(KeyCode::ShiftLeft));
+        assert!(input.was_key_released(KeyCode::ShiftLeft));
+        assert!(input.was_button_released(MouseButton::Right));
+    }
+
+    #[test]
+    fn actions_are_rebound_by_name() {
+        let mut actions = ActionMap::default();
+        actions
+            .bind(ActionMap::PAUSE, KeyCode::Escape)
+            .bind(ActionMap::PAUSE, KeyCode::KeyP)
+            .bind(ActionMap::PAUSE, KeyCode::KeyP);
+        assert_eq!(
+            actions.keys(ActionMap::PAUSE),
+            [KeyCode::Escape, KeyCode::KeyP]
+        );
+        assert!(actions.triggers(ActionMap::PAUSE, KeyCode::KeyP));
+        actions.unbind(ActionMap::PAUSE);
+        assert!(!actions.triggers(ActionMap::PAUSE, KeyCode::Escape));
+        assert!(actions.keys("jump").is_empty());
+    }
+}
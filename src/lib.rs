@@ -10,21 +10,43 @@
 //! - `camera`: camera types, controller and uniforms for view/projection
 //! - `context`: central GPU and window context that owns device/queue/pipelines
 //! - `data_structures`: engine data models (meshes, instances, textures)
+//! - `error`: the [`Error`] returned by loaders, constructors and [`flow::run`]
 //! - `flow`: high level flow control (scenes / update loops)
+//! - `gamepad`: gamepad events, with the `gamepad` feature on native targets
+//! - `input`: keyboard and mouse state tracked across events
+//! - `loading`: loading screen and progress shown while the flows are constructed
+//! - `persist`: snapshots of the state and view for save games
 //! - `pick`: object picking utilities and shaders
 //! - `pipelines`: definitions for various render pipelines (basic, light, gui)
+//! - `profiling`: frame pacing statistics and frame time spike reports
+//! - `record`: recording sessions to replay them exactly, with the `record` feature
 //! - `resources`: helpers to load textures/models and create GPU resources
 //! - `render`: render composition for efficient pipeline reuse
+//! - `stream`: loading assets during gameplay with GPU uploads spread over frames
+//! - `window`: secondary OS windows with their own surfaces
 //!
 
 pub mod camera;
 pub mod context;
 pub mod data_structures;
+pub mod error;
 pub mod flow;
+#[cfg(all(feature = "gamepad", not(target_arch = "wasm32")))]
+pub mod gamepad;
+pub mod input;
+pub mod loading;
+#[cfg(feature = "catch-flow-panics")]
+mod panic_guard;
+pub mod persist;
 pub mod pick;
 pub mod pipelines;
+pub mod profiling;
+#[cfg(feature = "record")]
+pub mod record;
 pub mod resources;
 pub mod render;
+pub mod stream;
+pub mod window;
 #[cfg(feature = "ui")]
 pub mod ui;
 
@@ -33,4 +55,7 @@ pub use winit::dpi::PhysicalPosition;
 pub use cgmath::*;
 pub use winit::event::DeviceEvent;
 pub use winit::event::WindowEvent;
+pub use winit::window::{CursorGrabMode, CursorIcon};
 pub use wgpu::*;
+// Shadows `wgpu::Error` from the glob above
+pub use error::Error;
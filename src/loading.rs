@@ -0,0 +1,219 @@
+//! Loading screen drawn while the flow constructors run, see [`RunConfig::with_loading_screen`].
+//!
+//! The window opens and handles events as soon as the GPU device is ready, before any flow
+//! exists. Until every constructor resolved, each frame clears to [`Context::clear_colour`]
+//! and draws the selected [`LoadingScreen`]. Constructors report how far they got through
+//! [`InitContext::report_progress`], which loading screens read from
+//! [`Context::loading_progress`].
+//!
+//! [`RunConfig::with_loading_screen`]: crate::flow::RunConfig::with_loading_screen
+
+use std::{
+    collections::BTreeMap,
+    fmt::Debug,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use bytemuck::Zeroable;
+use instant::Duration;
+use wgpu::util::DeviceExt;
+
+use crate::{
+    context::{Context, InitContext},
+    flow::{FlowId, GraphicsFlow, NoEvent, Out},
+    pipelines::spinner::{
+        SPINNER_DOTS, SpinnerUniform, mk_spinner_bind_group, mk_spinner_bind_group_layout,
+        mk_spinner_pipeline,
+    },
+    render::Render,
+};
+
+/// Assets the flow constructors loaded so far, summed over all of them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LoadProgress {
+    pub loaded: u32,
+    pub total: u32,
+}
+
+impl LoadProgress {
+    /// Loaded share of the assets in `[0, 1]`, `None` while no constructor reported a total.
+    pub fn fraction(&self) -> Option<f32> {
+        (self.total > 0).then(|| self.loaded.min(self.total) as f32 / self.total as f32)
+    }
+}
+
+/// Latest progress of each constructor, shared by their `InitContext`s and the `Context`.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ProgressTracker(Arc<Mutex<BTreeMap<FlowId, LoadProgress>>>);
+
+impl ProgressTracker {
+    pub(crate) fn report(&self, flow_id: FlowId, progress: LoadProgress) {
+        let mut flows = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        flows.insert(flow_id, progress);
+    }
+
+    pub(crate) fn progress(&self) -> LoadProgress {
+        let flows = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        flows
+            .values()
+            .fold(LoadProgress::default(), |sum, flow| LoadProgress {
+                loaded: sum.loaded + flow.loaded,
+                total: sum.total + flow.total,
+            })
+    }
+}
+
+/// Builds the flow of [`LoadingScreen::Flow`] once the GPU device is ready.
+pub type LoadingFlowConstructor = dyn Fn(InitContext) -> Box<dyn GraphicsFlow<(), NoEvent>>;
+
+/// What's drawn while the flows are constructed.
+#[derive(Clone, Default)]
+pub enum LoadingScreen {
+    /// Only clears to [`Context::clear_colour`].
+    #[default]
+    Blank,
+    /// A ring of dots circling in the centre of the window, lit up to the reported progress.
+    Spinner,
+    /// A flow of its own that gets `on_update` and `on_render` until the app's flows are
+    /// initialized, e.g. a label showing [`Context::loading_progress`]. It's dropped after.
+    Flow(Arc<LoadingFlowConstructor>),
+}
+
+impl LoadingScreen {
+    /// Draws the flow `constructor` returns as the loading screen.
+    pub fn flow<G>(constructor: impl Fn(InitContext) -> G + 'static) -> Self
+    where
+        G: GraphicsFlow<(), NoEvent> + 'static,
+    {
+        Self::Flow(Arc::new(move |ctx| Box::new(constructor(ctx))))
+    }
+
+    pub(crate) fn construct(&self, ctx: InitContext) -> Option<Box<dyn GraphicsFlow<(), NoEvent>>> {
+        match self {
+            LoadingScreen::Blank => None,
+            LoadingScreen::Spinner => Some(Box::new(SpinnerFlow::new(&ctx))),
+            LoadingScreen::Flow(constructor) => Some(constructor(ctx)),
+        }
+    }
+}
+
+impl Debug for LoadingScreen {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Blank => f.write_str("Blank"),
+            Self::Spinner => f.write_str("Spinner"),
+            Self::Flow(_) => f.write_str("Flow(|InitContext| -> {...})"),
+        }
+    }
+}
+
+/// The flow of [`LoadingScreen::Spinner`].
+struct SpinnerFlow {
+    pipeline: wgpu::RenderPipeline,
+    buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    time: Duration,
+}
+
+impl SpinnerFlow {
+    fn new(ctx: &InitContext) -> Self {
+        // HDR can only be enabled by the flows, so the scene is still drawn to the surface
+        let sample_count = ctx.anti_aliasing.sample_count();
+        let pipeline = mk_spinner_pipeline(&ctx.device, ctx.config.format, sample_count, ctx.depth);
+        let buffer = ctx
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Spinner Buffer"),
+                contents: bytemuck::bytes_of(&SpinnerUniform::zeroed()),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            });
+        let layout = mk_spinner_bind_group_layout(&ctx.device);
+        let bind_group = mk_spinner_bind_group(&ctx.device, &buffer, &layout);
+        Self {
+            pipeline,
+            buffer,
+            bind_group,
+            time: Duration::ZERO,
+        }
+    }
+}
+
+impl GraphicsFlow<(), NoEvent> for SpinnerFlow {
+    fn on_update(&mut self, ctx: &Context, _: &mut (), dt: Duration) -> Out<(), NoEvent> {
+        self.time += dt;
+        let progress = ctx
+            .loading_progress()
+            .and_then(|progress| progress.fraction());
+        let uniform = SpinnerUniform {
+            size: [
+                ctx.config.width.max(1) as f32,
+                ctx.config.height.max(1) as f32,
+            ],
+            time: self.time.as_secs_f32(),
+            progress: progress.unwrap_or(-1.0),
+            color: contrasting(ctx.clear_colour),
+        };
+        ctx.queue
+            .write_buffer(&self.buffer, 0, bytemuck::bytes_of(&uniform));
+        Out::Empty
+    }
+
+    fn on_render<'pass>(&self) -> Render<'_, 'pass> {
+        Render::Custom(Box::new(|_, pass| {
+            pass.set_pipeline(&self.pipeline);
+            pass.set_bind_group(0, &self.bind_group, &[]);
+            pass.draw(0..6, 0..SPINNER_DOTS);
+        }))
+    }
+}
+
+/// White on dark backgrounds, black on light ones.
+fn contrasting(background: wgpu::Color) -> [f32; 4] {
+    let luminance = 0.2126 * background.r + 0.7152 * background.g + 0.0722 * background.b;
+    if luminance > 0.5 {
+        [0.0, 0.0, 0.0, 0.9]
+    } else {
+        [1.0, 1.0, 1.0, 0.9]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn progress_sums_the_constructors() {
+        let tracker = ProgressTracker::default();
+        assert_eq!(tracker.progress().fraction(), None);
+        tracker.report(
+            FlowId(0),
+            LoadProgress {
+                loaded: 1,
+                total: 3,
+            },
+        );
+        tracker.report(
+            FlowId(1),
+            LoadProgress {
+                loaded: 0,
+                total: 4,
+            },
+        );
+        // Reports replace the earlier ones of the same constructor
+        tracker.report(
+            FlowId(0),
+            LoadProgress {
+                loaded: 3,
+                total: 3,
+            },
+        );
+        assert_eq!(
+            tracker.progress(),
+            LoadProgress {
+                loaded: 3,
+                total: 7
+            }
+        );
+        assert_eq!(tracker.progress().fraction(), Some(3.0 / 7.0));
+    }
+}
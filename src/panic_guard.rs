@@ -0,0 +1,375 @@
+//! Keeps a flow that panics from taking the app down, with the `catch-flow-panics` feature.
+//!
+//! Every flow is wrapped in a [`PanicGuard`] when it's added. A panic in any of its hooks is
+//! caught, the flow is poisoned and never called or drawn again, and the other flows learn
+//! about it through [`GraphicsFlow::on_flow_panic`]. Unless one of them handles it the app
+//! exits, calling `on_exit` on the others. Whatever the hook mutated before it panicked stays
+//! as it is, so the state may be half updated.
+//!
+//! Catching needs `panic = "unwind"`, the default. Apps built with `panic = "abort"` still
+//! abort.
+
+use std::{
+    any::Any,
+    cell::Cell,
+    panic::{self, AssertUnwindSafe},
+};
+
+use cgmath::Vector3;
+use instant::Duration;
+use winit::{
+    dpi::PhysicalSize,
+    event::{DeviceEvent, ElementState, MouseButton, WindowEvent},
+    keyboard::KeyCode,
+};
+
+#[cfg(all(feature = "gamepad", not(target_arch = "wasm32")))]
+use crate::gamepad::GamepadEvent;
+use crate::{
+    context::{Context, TextEvent},
+    flow::{FlowId, FlowPanic, GraphicsFlow, Out, PointerEvent},
+    persist::EngineSnapshot,
+    pick::{PickHit, PickId},
+    render::{Layer, Render},
+    window::WindowId,
+};
+#[cfg(feature = "integration-tests")]
+use crate::{flow::ImageTestResult, pick::PickImage};
+
+/// Runs the hooks of `inner` until one of them panics.
+pub(crate) struct PanicGuard<F> {
+    inner: F,
+    id: FlowId,
+    // `on_render` only gets `&self`
+    poisoned: Cell<bool>,
+    notify: Box<dyn Fn(FlowPanic)>,
+}
+
+impl<F> PanicGuard<F> {
+    /// Guards the flow `id`, `notify` is called once it panicked.
+    pub(crate) fn new(inner: F, id: FlowId, notify: impl Fn(FlowPanic) + 'static) -> Self {
+        Self {
+            inner,
+            id,
+            poisoned: Cell::new(false),
+            notify: Box::new(notify),
+        }
+    }
+
+    /// Calls `hook` on the inner flow, `None` if it's poisoned or panicked.
+    fn guard<T>(&mut self, hook: &'static str, call: impl FnOnce(&mut F) -> T) -> Option<T> {
+        if self.poisoned.get() {
+            return None;
+        }
+        let result = panic::catch_unwind(AssertUnwindSafe(|| call(&mut self.inner)));
+        self.caught(hook, result)
+    }
+
+    fn guard_ref<'a, T>(&'a self, hook: &'static str, call: impl FnOnce(&'a F) -> T) -> Option<T> {
+        if self.poisoned.get() {
+            return None;
+        }
+        let result = panic::catch_unwind(AssertUnwindSafe(|| call(&self.inner)));
+        self.caught(hook, result)
+    }
+
+    fn caught<T>(&self, hook: &'static str, result: std::thread::Result<T>) -> Option<T> {
+        let payload = match result {
+            Ok(value) => return Some(value),
+            Err(payload) => payload,
+        };
+        self.poisoned.set(true);
+        let panic = FlowPanic {
+            flow: self.id,
+            hook,
+            message: panic_message(payload.as_ref()),
+        };
+        log::error!("Flow {:?} panicked in {hook}: {}", self.id, panic.message);
+        (self.notify)(panic);
+        None
+    }
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "panic without a message".to_string()
+    }
+}
+
+impl<S, E: Send> GraphicsFlow<S, E> for PanicGuard<Box<dyn GraphicsFlow<S, E>>> {
+    fn on_init(&mut self, ctx: &mut Context, state: &mut S) -> Out<S, E> {
+        self.guard("on_init", |flow| flow.on_init(ctx, state))
+            .unwrap_or_default()
+    }
+
+    fn on_restore(&mut self, ctx: &Context, state: &mut S, snapshot: &EngineSnapshot) -> Out<S, E> {
+        self.guard("on_restore", |flow| flow.on_restore(ctx, state, snapshot))
+            .unwrap_or_default()
+    }
+
+    fn on_click(&mut self, ctx: &Context, state: &mut S, id: PickId) -> Out<S, E> {
+        self.guard("on_click", |flow| flow.on_click(ctx, state, id))
+            .unwrap_or_default()
+    }
+
+    fn on_click_at(
+        &mut self,
+        ctx: &Context,
+        state: &mut S,
+        id: PickId,
+        at: Option<Vector3<f32>>,
+    ) -> Out<S, E> {
+        self.guard("on_click_at", |flow| flow.on_click_at(ctx, state, id, at))
+            .unwrap_or_default()
+    }
+
+    fn on_click_instance(
+        &mut self,
+        ctx: &Context,
+        state: &mut S,
+        id: PickId,
+        instance: u32,
+        at: Option<Vector3<f32>>,
+    ) -> Out<S, E> {
+        self.guard("on_click_instance", |flow| {
+            flow.on_click_instance(ctx, state, id, instance, at)
+        })
+        .unwrap_or_default()
+    }
+
+    fn on_click_with(
+        &mut self,
+        ctx: &Context,
+        state: &mut S,
+        id: PickId,
+        hit: PickHit,
+        button: MouseButton,
+    ) -> Out<S, E> {
+        self.guard("on_click_with", |flow| {
+            flow.on_click_with(ctx, state, id, hit, button)
+        })
+        .unwrap_or_default()
+    }
+
+    fn on_hover(&mut self, ctx: &Context, state: &mut S, id: Option<PickId>) -> Out<S, E> {
+        self.guard("on_hover", |flow| flow.on_hover(ctx, state, id))
+            .unwrap_or_default()
+    }
+
+    fn on_pointer(&mut self, ctx: &Context, state: &mut S, event: PointerEvent) -> Out<S, E> {
+        self.guard("on_pointer", |flow| flow.on_pointer(ctx, state, event))
+            .unwrap_or_default()
+    }
+
+    fn on_update(&mut self, ctx: &Context, state: &mut S, dt: Duration) -> Out<S, E> {
+        self.guard("on_update", |flow| flow.on_update(ctx, state, dt))
+            .unwrap_or_default()
+    }
+
+    fn on_fixed_update(&mut self, ctx: &Context, state: &mut S, fixed_dt: Duration) -> Out<S, E> {
+        self.guard("on_fixed_update", |flow| {
+            flow.on_fixed_update(ctx, state, fixed_dt)
+        })
+        .unwrap_or_default()
+    }
+
+    fn on_tick(&mut self, ctx: &Context, state: &mut S) -> Out<S, E> {
+        self.guard("on_tick", |flow| flow.on_tick(ctx, state))
+            .unwrap_or_default()
+    }
+
+    fn on_device_events(&mut self, ctx: &Context, state: &mut S, event: &DeviceEvent) -> Out<S, E> {
+        self.guard("on_device_events", |flow| {
+            flow.on_device_events(ctx, state, event)
+        })
+        .unwrap_or_default()
+    }
+
+    fn on_window_events(&mut self, ctx: &Context, state: &mut S, event: &WindowEvent) -> Out<S, E> {
+        self.guard("on_window_events", |flow| {
+            flow.on_window_events(ctx, state, event)
+        })
+        .unwrap_or_default()
+    }
+
+    fn on_key(
+        &mut self,
+        ctx: &Context,
+        state: &mut S,
+        key: KeyCode,
+        key_state: ElementState,
+    ) -> Out<S, E> {
+        self.guard("on_key", |flow| flow.on_key(ctx, state, key, key_state))
+            .unwrap_or_default()
+    }
+
+    #[cfg(all(feature = "gamepad", not(target_arch = "wasm32")))]
+    fn on_gamepad_events(
+        &mut self,
+        ctx: &Context,
+        state: &mut S,
+        events: &[GamepadEvent],
+    ) -> Out<S, E> {
+        self.guard("on_gamepad_events", |flow| {
+            flow.on_gamepad_events(ctx, state, events)
+        })
+        .unwrap_or_default()
+    }
+
+    fn on_text_input(&mut self, ctx: &Context, state: &mut S, event: TextEvent) -> Out<S, E> {
+        self.guard("on_text_input", |flow| {
+            flow.on_text_input(ctx, state, event)
+        })
+        .unwrap_or_default()
+    }
+
+    fn on_focus_changed(&mut self, ctx: &Context, state: &mut S, focused: bool) -> Out<S, E> {
+        self.guard("on_focus_changed", |flow| {
+            flow.on_focus_changed(ctx, state, focused)
+        })
+        .unwrap_or_default()
+    }
+
+    fn on_resize(&mut self, ctx: &Context, state: &mut S, size: PhysicalSize<u32>) -> Out<S, E> {
+        self.guard("on_resize", |flow| flow.on_resize(ctx, state, size))
+            .unwrap_or_default()
+    }
+
+    fn on_gpu_reset(&mut self, ctx: &Context, state: &mut S) -> Out<S, E> {
+        self.guard("on_gpu_reset", |flow| flow.on_gpu_reset(ctx, state))
+            .unwrap_or_default()
+    }
+
+    fn on_gpu_recovered(&mut self, ctx: &Context, state: &mut S) -> Out<S, E> {
+        self.guard("on_gpu_recovered", |flow| flow.on_gpu_recovered(ctx, state))
+            .unwrap_or_default()
+    }
+
+    fn runs_while_paused(&self) -> bool {
+        self.inner.runs_while_paused()
+    }
+
+    fn priority(&self) -> i32 {
+        self.inner.priority()
+    }
+
+    fn on_pause_changed(&mut self, ctx: &Context, state: &mut S, paused: bool) -> Out<S, E> {
+        self.guard("on_pause_changed", |flow| {
+            flow.on_pause_changed(ctx, state, paused)
+        })
+        .unwrap_or_default()
+    }
+
+    fn on_focus(&mut self, ctx: &Context, state: &mut S, focused: bool) -> Out<S, E> {
+        self.guard("on_focus", |flow| flow.on_focus(ctx, state, focused))
+            .unwrap_or_default()
+    }
+
+    fn on_suspend(&mut self, ctx: &Context, state: &mut S) -> Out<S, E> {
+        self.guard("on_suspend", |flow| flow.on_suspend(ctx, state))
+            .unwrap_or_default()
+    }
+
+    fn on_resume(&mut self, ctx: &Context, state: &mut S) -> Out<S, E> {
+        self.guard("on_resume", |flow| flow.on_resume(ctx, state))
+            .unwrap_or_default()
+    }
+
+    fn on_exit(&mut self, ctx: &Context, state: &mut S) {
+        self.guard("on_exit", |flow| flow.on_exit(ctx, state));
+    }
+
+    fn on_flow_panic(&mut self, ctx: &Context, state: &mut S, panic: &FlowPanic) -> Out<S, E> {
+        self.guard("on_flow_panic", |flow| {
+            flow.on_flow_panic(ctx, state, panic)
+        })
+        .unwrap_or_default()
+    }
+
+    // A poisoned flow consumes the event, it's lost with the flow's state
+    fn on_custom_events(&mut self, ctx: &Context, state: &mut S, event: E) -> Option<E> {
+        self.guard("on_custom_events", |flow| {
+            flow.on_custom_events(ctx, state, event)
+        })
+        .flatten()
+    }
+
+    fn window(&self) -> WindowId {
+        self.inner.window()
+    }
+
+    fn layer(&self) -> Layer {
+        self.inner.layer()
+    }
+
+    // A half updated flow may hand out buffers it was rebuilding
+    fn on_render<'pass>(&self) -> Render<'_, 'pass> {
+        self.guard_ref("on_render", |flow| flow.on_render())
+            .unwrap_or_default()
+    }
+
+    #[cfg(feature = "integration-tests")]
+    fn render_to_texture(
+        &self,
+        ctx: &Context,
+        state: &mut S,
+        texture: &mut image::ImageBuffer<image::Rgba<u8>, wgpu::BufferView>,
+    ) -> Result<ImageTestResult, anyhow::Error> {
+        self.inner.render_to_texture(ctx, state, texture)
+    }
+
+    #[cfg(feature = "integration-tests")]
+    fn pick_to_texture(
+        &self,
+        ctx: &Context,
+        state: &mut S,
+        ids: &PickImage,
+    ) -> Result<ImageTestResult, anyhow::Error> {
+        self.inner.pick_to_texture(ctx, state, ids)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::*;
+
+    #[test]
+    fn panicking_hooks_poison_the_flow() {
+        let panics = Rc::new(RefCell::new(Vec::new()));
+        let notified = panics.clone();
+        let mut guard =
+            PanicGuard::new(0, FlowId(3), move |panic| notified.borrow_mut().push(panic));
+
+        assert_eq!(guard.guard("on_update", |calls| *calls += 1), Some(()));
+        assert_eq!(guard.guard_ref("on_render", |calls| *calls), Some(1));
+        // Keeps the test output free of the default hook's report
+        let hook = panic::take_hook();
+        panic::set_hook(Box::new(|_| ()));
+        let panicked = guard.guard("on_tick", |_| -> u32 { panic!("out of bounds") });
+        panic::set_hook(hook);
+        assert_eq!(panicked, None);
+        assert_eq!(
+            *panics.borrow(),
+            [FlowPanic {
+                flow: FlowId(3),
+                hook: "on_tick",
+                message: "out of bounds".to_string(),
+            }]
+        );
+
+        assert_eq!(
+            guard.guard("on_update", |calls| *calls += 1),
+            None,
+            "poisoned"
+        );
+        assert_eq!(guard.guard_ref("on_render", |calls| *calls), None);
+        assert_eq!(guard.inner, 1);
+        assert_eq!(panics.borrow().len(), 1, "notified once");
+    }
+}
@@ -0,0 +1,88 @@
+//! Save games, the app's state together with the view of the engine, see [`Snapshot`].
+//!
+//! Flows return `Out::Snapshot` to get a [`SnapshotWriter`] once the current event is handled,
+//! between frames, and serialize [`SnapshotWriter::snapshot`] in a serde format of their
+//! choice. Hooks that return nothing, like `on_exit`, build one from
+//! [`Context::engine_snapshot`] instead. [`run_with_restore`] starts the app from a
+//! deserialized [`Snapshot`]: its state replaces `State::default()`, the view is restored after
+//! `on_init` and every flow gets `on_restore`.
+//!
+//! Enable the `serde` feature to serialize snapshots.
+//!
+//! [`run_with_restore`]: crate::flow::run_with_restore
+
+use crate::{camera::CameraPose, context::Context, pick::PickId};
+
+/// Parts of the [`Context`] a restored app starts with, so it shows the same view.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EngineSnapshot {
+    pub camera: CameraPose,
+    #[cfg_attr(feature = "serde", serde(with = "colour"))]
+    pub clear_colour: wgpu::Color,
+    /// See [`MouseState::selection`](crate::context::MouseState::selection).
+    pub selection: Option<PickId>,
+}
+
+/// A saved app, e.g. deserialized from a save game file.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Snapshot<S> {
+    pub engine: EngineSnapshot,
+    pub state: S,
+}
+
+/// Serializes the app, handed to the closure of `Out::Snapshot` between frames.
+#[derive(Debug)]
+pub struct SnapshotWriter<'a, S> {
+    engine: EngineSnapshot,
+    state: &'a S,
+}
+
+impl<'a, S> SnapshotWriter<'a, S> {
+    pub(crate) fn new(ctx: &Context, state: &'a S) -> Self {
+        Self {
+            engine: ctx.engine_snapshot(),
+            state,
+        }
+    }
+
+    pub fn engine(&self) -> &EngineSnapshot {
+        &self.engine
+    }
+
+    pub fn state(&self) -> &'a S {
+        self.state
+    }
+
+    /// Serializes like a `Snapshot<S>`, so it deserializes into one.
+    pub fn snapshot(&self) -> Snapshot<&'a S> {
+        Snapshot {
+            engine: self.engine,
+            state: self.state,
+        }
+    }
+}
+
+/// Closure of `Out::Snapshot`.
+pub type SnapshotFn<S> = Box<dyn for<'a> FnOnce(SnapshotWriter<'a, S>)>;
+
+// `wgpu::Color` is only serializable with wgpu's own `serde` feature
+#[cfg(feature = "serde")]
+mod colour {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub(super) fn serialize<Ser: Serializer>(
+        colour: &wgpu::Color,
+        serializer: Ser,
+    ) -> Result<Ser::Ok, Ser::Error> {
+        [colour.r, colour.g, colour.b, colour.a].serialize(serializer)
+    }
+
+    pub(super) fn deserialize<'de, De: Deserializer<'de>>(
+        deserializer: De,
+    ) -> Result<wgpu::Color, De::Error> {
+        let [r, g, b, a] = <[f64; 4]>::deserialize(deserializer)?;
+        Ok(wgpu::Color { r, g, b, a })
+    }
+}
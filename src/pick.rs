@@ -7,26 +7,41 @@
 //!
 //! The picking pipeline works as follows:
 //! 1. Render all objects to an offscreen texture using unique IDs as RGBA values for the fragment shader
-//! 2. Read the pixel at the mouse cursor position (scaled according to platform limitations on texture sizes)
-//! 3. Map the pick ID back to the flow that owns the object (determined by the render tree)
+//! 2. Copy and read back only the pixel at the mouse cursor position
+//! 3. Map the pick ID back to the flow that owns the object (determined by the render tree),
+//!    and to the instance for draws picked per instance
 //! 4. Return the selected object ID and owning flows
 //!
 //! Especially step 4 makes sure that only those flows are invoked that were responsible for selected object.
 
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, BTreeSet, HashMap},
     iter,
+    sync::{Arc, Mutex},
 };
 
+use cgmath::{Matrix4, Vector3};
+use instant::{Duration, Instant};
+use winit::dpi::PhysicalPosition;
+
 use crate::{
+    camera::unproject_from_screen,
     context::{Context, MouseState},
-    data_structures::model::DrawModel,
-    flow::GraphicsFlow,
-    render::{Flat, Geometry, Instanced},
-    resources::pick::{load_pick_model, load_pick_texture},
+    data_structures::{
+        model::{DrawModel, Material},
+        visibility::VisibilitySet,
+    },
+    flow::{FlowId, FlowList},
+    render::{Batches, Layers},
+    window::WindowId,
+    resources::{
+        memory::{MemoryCategory, TrackedBuffer, TrackedMemory},
+        pick::load_pick_material_with_layout,
+    },
 };
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PickId(pub u32);
 
 impl From<u32> for PickId {
@@ -38,6 +53,406 @@ impl From<u32> for PickId {
 #[cfg(target_arch = "wasm32")]
 use crate::flow::FlowEvent;
 
+/// What a pick read back asynchronously on WASM is sent to the event loop as.
+#[cfg(target_arch = "wasm32")]
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum PickPurpose {
+    /// A click with this button, sent only if an object was picked.
+    Click(winit::event::MouseButton),
+    Hover,
+}
+
+/// Picked id and the flows owning it, `None` if no pickable object was under the cursor.
+pub(crate) type Picked = Option<(u32, BTreeSet<FlowId>)>;
+
+/// Throttled picking under the resting cursor, see [`Context::enable_hover_picking`].
+#[derive(Debug, Default)]
+pub(crate) struct HoverPicking {
+    // `None` while disabled
+    interval: Option<Duration>,
+    last_pick: Option<Instant>,
+    // Cursor position of the latest pick and what it found
+    latest: Option<(PhysicalPosition<f64>, Picked, PickHit)>,
+    // Id the owners were told about through `on_hover`
+    hovered: Picked,
+    // A pick is being read back on WASM
+    pending: bool,
+}
+
+/// Flows to notify after the hovered id changed.
+#[derive(Debug, PartialEq)]
+pub(crate) struct HoverChange {
+    /// Owners of the previous id that don't own the new one, they get `on_hover(None)`.
+    pub(crate) left: BTreeSet<FlowId>,
+    pub(crate) entered: Picked,
+}
+
+impl HoverPicking {
+    pub(crate) fn enable(&mut self, interval: Duration) {
+        self.interval = Some(interval);
+    }
+
+    pub(crate) fn disable(&mut self) {
+        self.interval = None;
+        self.latest = None;
+    }
+
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.interval.is_some()
+    }
+
+    /// Whether hovering is enabled and the cursor moved since the latest pick.
+    pub(crate) fn moved(&self, cursor: PhysicalPosition<f64>) -> bool {
+        self.is_enabled()
+            && !self.pending
+            && self.latest.as_ref().is_none_or(|(position, ..)| *position != cursor)
+    }
+
+    /// Whether the previous pick was less than the interval ago.
+    pub(crate) fn throttled(&self, now: Instant) -> bool {
+        self.interval.zip(self.last_pick).is_some_and(|(interval, last)| {
+            now.saturating_duration_since(last) < interval
+        })
+    }
+
+    pub(crate) fn start_pick(&mut self, now: Instant) {
+        self.last_pick = Some(now);
+        self.pending = true;
+    }
+
+    /// Result of the latest pick if the cursor is still where it was taken.
+    pub(crate) fn cached(&self, cursor: PhysicalPosition<f64>) -> Option<(Picked, PickHit)> {
+        self.is_enabled()
+            .then_some(self.latest.as_ref())
+            .flatten()
+            .filter(|(position, ..)| *position == cursor)
+            .map(|(_, picked, hit)| (picked.clone(), *hit))
+    }
+
+    /// Stores a finished pick at `cursor`, returning who to notify if the hovered id changed.
+    pub(crate) fn finish_pick(
+        &mut self,
+        cursor: PhysicalPosition<f64>,
+        picked: Picked,
+        hit: PickHit,
+    ) -> Option<HoverChange> {
+        self.pending = false;
+        self.latest = Some((cursor, picked.clone(), hit));
+        self.hover(picked)
+    }
+
+    /// Changes the hovered id to `picked`, e.g. `None` once the cursor left the window.
+    pub(crate) fn hover(&mut self, picked: Picked) -> Option<HoverChange> {
+        // Empty space isn't hovered even if a flow renders with the default id
+        let picked = picked.filter(|(id, _)| *id != PickId::default().0);
+        if self.hovered.as_ref().map(|(id, _)| id) == picked.as_ref().map(|(id, _)| id) {
+            return None;
+        }
+        let previous = std::mem::replace(&mut self.hovered, picked.clone());
+        let left = match (previous, &picked) {
+            (Some((_, before)), Some((_, after))) => before.difference(after).copied().collect(),
+            (Some((_, before)), None) => before,
+            (None, _) => BTreeSet::new(),
+        };
+        Some(HoverChange {
+            left,
+            entered: picked,
+        })
+    }
+}
+
+/// Window pixels of the pick texture copied into a [`PickBuffer`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct PickRegion {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+}
+
+impl PickRegion {
+    /// The pixel under `cursor`, clamped into the `width` x `height` window. Reading a
+    /// cursor outside of the window still finds no pick.
+    pub(crate) fn at(cursor: PhysicalPosition<f64>, width: u32, height: u32) -> Self {
+        Self {
+            x: (cursor.x.max(0.0) as u32).min(width - 1),
+            y: (cursor.y.max(0.0) as u32).min(height - 1),
+            width: 1,
+            height: 1,
+        }
+    }
+
+    #[cfg(feature = "integration-tests")]
+    pub(crate) fn whole(width: u32, height: u32) -> Self {
+        Self {
+            x: 0,
+            y: 0,
+            width,
+            height,
+        }
+    }
+
+    /// Bytes per row of a readback of this region, padded as the buffer layout requires.
+    fn row_bytes(&self) -> u32 {
+        (TEXEL_SIZE * self.width).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT)
+    }
+
+    /// Offset of the depths in a readback, which follow the ids.
+    fn depth_offset(&self) -> u32 {
+        self.row_bytes() * self.height
+    }
+
+    fn readback_size(&self) -> wgpu::BufferAddress {
+        wgpu::BufferAddress::from(self.depth_offset()) * 2
+    }
+}
+
+// Bytes of an `R32Uint` pick texel and of a `Depth32Float` one
+const TEXEL_SIZE: u32 = std::mem::size_of::<u32>() as u32;
+
+/// Depth format of the pick pass whatever the [`Context::depth`] is, the depth under the
+/// cursor is read back and `Depth24Plus` can't be copied.
+pub(crate) const PICK_DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+/// Where a click or hover hit the picked object, next to its [`PickId`].
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PickHit {
+    /// World position of the surface under the cursor, `None` over empty space, UI
+    /// elements and flows that don't write the pick depth.
+    pub position: Option<Vector3<f32>>,
+    /// Index of the picked instance of draws with [`Instanced::pick_instances`].
+    pub instance: Option<u32>,
+}
+
+/// Id ranges of the draws picked per instance, see [`Instanced::pick_instances`].
+#[derive(Debug, Default)]
+pub(crate) struct InstanceRanges(BTreeMap<u32, InstanceRange>);
+
+#[derive(Debug)]
+struct InstanceRange {
+    len: u32,
+    // instances the draw was culled to, indexed by the instance index of the draw
+    visibility: Option<VisibilitySet>,
+}
+
+/// Why a draw is picked by its id only instead of per instance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RangeConflict {
+    /// Its ids would pass `u32::MAX`.
+    Overflow,
+    /// Its ids overlap those of the object with this id, the default id marks empty space.
+    Overlaps(PickId),
+}
+
+impl InstanceRanges {
+    /// Reserves ids from `id` on for the `amount` instances of a draw culled to `visibility`,
+    /// unless they include one of `ids`, those of the other draws, or the ids of an earlier
+    /// draw.
+    pub(crate) fn insert(
+        &mut self,
+        id: PickId,
+        amount: usize,
+        visibility: Option<&VisibilitySet>,
+        ids: &BTreeSet<u32>,
+    ) -> Result<(), RangeConflict> {
+        let start = id.0;
+        let len = u32::try_from(amount).map_err(|_| RangeConflict::Overflow)?;
+        let end = start.checked_add(len).ok_or(RangeConflict::Overflow)?;
+        // Nothing drawn reads as the default id
+        if start == PickId::default().0 {
+            return Err(RangeConflict::Overlaps(PickId::default()));
+        }
+        if let Some(&id) = ids.range(start..end).next() {
+            return Err(RangeConflict::Overlaps(PickId(id)));
+        }
+        let earlier = self.0.range(..end).next_back();
+        if let Some((&other, _)) = earlier.filter(|(other, range)| *other + range.len > start) {
+            return Err(RangeConflict::Overlaps(PickId(other)));
+        }
+        let visibility = visibility.cloned();
+        self.0.insert(start, InstanceRange { len, visibility });
+        Ok(())
+    }
+
+    /// Whether the draw with `id` is picked per instance.
+    fn contains(&self, id: PickId) -> bool {
+        self.0.contains_key(&id.0)
+    }
+
+    /// Id of the draw that drew `id` and the index of the instance, `None` if no draw picked
+    /// per instance drew it.
+    pub(crate) fn resolve(&self, id: u32) -> Option<(u32, u32)> {
+        let (&start, range) = self.0.range(..=id).next_back()?;
+        let drawn = id - start;
+        if drawn >= range.len {
+            return None;
+        }
+        let instance = match &range.visibility {
+            Some(visible) => u32::try_from(visible.iter_visible().nth(drawn as usize)?).ok()?,
+            None => drawn,
+        };
+        Some((start, instance))
+    }
+}
+
+/// Pick textures and the readback of a cursor pixel, kept on the [`Context`] between picks.
+///
+/// They are recreated once the window size changed.
+#[derive(Debug)]
+pub(crate) struct PickTargets {
+    size: (u32, u32),
+    ids: wgpu::Texture,
+    ids_view: wgpu::TextureView,
+    depth: wgpu::Texture,
+    depth_view: wgpu::TextureView,
+    readback: ReadbackSlot,
+    _memory: [TrackedMemory; 2],
+}
+
+impl PickTargets {
+    fn new(device: &wgpu::Device, width: u32, height: u32) -> Self {
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        let texture = |label, format| {
+            device.create_texture(&wgpu::TextureDescriptor {
+                label: Some(label),
+                size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: wgpu::TextureUsages::COPY_SRC | wgpu::TextureUsages::RENDER_ATTACHMENT,
+                view_formats: &[],
+            })
+        };
+        let ids = texture("Pick texture", wgpu::TextureFormat::R32Uint);
+        let depth = texture("Pick depth texture", PICK_DEPTH_FORMAT);
+        Self {
+            size: (width, height),
+            ids_view: ids.create_view(&wgpu::TextureViewDescriptor::default()),
+            depth_view: depth.create_view(&wgpu::TextureViewDescriptor::default()),
+            readback: Arc::new(Mutex::new(Some(pixel_readback(device)))),
+            _memory: [
+                TrackedMemory::texture(&ids, "Pick texture"),
+                TrackedMemory::texture(&depth, "Pick depth texture"),
+            ],
+            ids,
+            depth,
+        }
+    }
+}
+
+/// Readback of the cursor pixel, empty while a pick still maps it, e.g. on WASM.
+type ReadbackSlot = Arc<Mutex<Option<TrackedBuffer>>>;
+
+fn pixel_readback(device: &wgpu::Device) -> TrackedBuffer {
+    let pixel = PickRegion::at(PhysicalPosition::new(0.0, 0.0), 1, 1);
+    readback_buffer(device, pixel.readback_size())
+}
+
+fn readback_buffer(device: &wgpu::Device, size: wgpu::BufferAddress) -> TrackedBuffer {
+    let desc = wgpu::BufferDescriptor {
+        size,
+        usage: wgpu::BufferUsages::COPY_DST
+                    // this tells wpgu that we want to read this buffer from the cpu
+                    | wgpu::BufferUsages::MAP_READ,
+        label: Some("Pick readback buffer"),
+        mapped_at_creation: false,
+    };
+    TrackedBuffer::new(device, &desc, MemoryCategory::Readback)
+}
+
+/// Pick materials by id, kept on the [`Context`] so picks only create them for new ids.
+///
+/// Instanced draws override their materials with these, flat draws bind their bind group.
+#[derive(Debug, Default)]
+pub(crate) struct PickMaterials(HashMap<(PickId, bool), Material>);
+
+impl PickMaterials {
+    /// Creates the materials of the ids seen for the first time and drops those of ids that
+    /// aren't rendered anymore, e.g. after an object's id changed. The ids come with whether
+    /// they're picked per instance.
+    fn sync(
+        &mut self,
+        ids: impl IntoIterator<Item = (PickId, bool)>,
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+    ) {
+        let mut materials = HashMap::new();
+        for key @ (id, per_instance) in ids {
+            if materials.contains_key(&key) {
+                continue;
+            }
+            let material = self.0.remove(&key).unwrap_or_else(|| {
+                load_pick_material_with_layout(device, id, per_instance, layout)
+            });
+            materials.insert(key, material);
+        }
+        self.0 = materials;
+    }
+
+    fn get(&self, id: PickId, per_instance: bool) -> &Material {
+        &self.0[&(id, per_instance)]
+    }
+}
+
+/// Ids and depths of the objects under the pixels of a [`PickRegion`], rendered by
+/// [`render_pick_buffer`] and waiting to be mapped.
+pub(crate) struct PickBuffer {
+    buffer: TrackedBuffer,
+    region: PickRegion,
+    // texels per row in the buffer, the width padded to `COPY_BYTES_PER_ROW_ALIGNMENT`
+    row_texels: u32,
+    // what the depths were drawn with, to find the world positions of hits
+    view_proj: Matrix4<f32>,
+    far: f32,
+    window: (u32, u32),
+    // which flows own which rendered ids, and which ids belong to instances
+    translation: HashMap<PickId, BTreeSet<FlowId>>,
+    instances: InstanceRanges,
+    // where to return the buffer to once it's unmapped
+    recycle: Option<ReadbackSlot>,
+}
+
+impl PickBuffer {
+    /// The picked object of the id read back and its owners, and where it was hit.
+    fn resolve(&self, id: u32, position: Option<Vector3<f32>>) -> (Picked, PickHit) {
+        let (id, instance) = match self.instances.resolve(id) {
+            Some((id, instance)) => (id, Some(instance)),
+            None => (id, None),
+        };
+        let picked = self.translation.get(&PickId(id)).map(|flow_ids| (id, flow_ids.clone()));
+        (picked, PickHit { position, instance })
+    }
+
+    /// World position of `depth` under `cursor`, `None` where the depth stayed cleared.
+    fn hit_position(
+        &self,
+        cursor: PhysicalPosition<f64>,
+        depth: Option<f32>,
+    ) -> Option<Vector3<f32>> {
+        let depth = depth.filter(|depth| *depth != self.far)?;
+        let (width, height) = (self.window.0 as f32, self.window.1 as f32);
+        unproject_from_screen(self.view_proj, cursor, depth, width, height)
+    }
+
+    fn unmap(self) {
+        self.buffer.unmap();
+        if let Some(slot) = self.recycle {
+            *slot.lock().expect("pick readback poisoned") = Some(self.buffer);
+        }
+    }
+}
+
+/// Pick ids of a whole frame in window coordinates, the same pixel a click at `(x, y)` reads.
+///
+/// `0` marks pixels not covered by any pickable object.
+#[cfg(feature = "integration-tests")]
+pub type PickImage = image::ImageBuffer<image::Luma<u32>, Vec<u32>>;
+
 /// Render all flows to pick texture and determine which object was clicked.
 ///
 /// # Arguments
@@ -45,296 +460,371 @@ use crate::flow::FlowEvent;
 /// * `async_runtime` using the tokio runtime for async resource loading if not on WASM
 /// * `flows` represent all active graphics flows with their renderable objects
 /// * `ctx` is the rendering context
-/// * `mouse_state` is required for getting the mouse coordinates at the time of picking
+/// * `mouse_state` is required for getting the mouse coordinates at the time of picking. They
+///   are physical window pixels like the pick texture, the GUI is picked with the same
+///   [UI pixel scale](Context::ui_pixel_scale) it is drawn with, so both line up at any scale
+///   factor.
 /// * `proxy` WASM futures can only resolve using the winit event loop proxy by sending events
+/// * `purpose` decides whether WASM sends the result as a click or a hover
 ///
 /// # Returns
 ///
-/// `Some((pick_id, flow_ids))` if an object was picked, or `None` picking is done via the event loop.
+/// `Some((pick_id, flow_ids))` if an object was picked, or `None` picking is done via the event loop,
+/// next to where it was hit. Ids of instances picked per instance are translated back to the
+/// id of their draw.
 pub(crate) fn draw_to_pick_buffer<State, Event: Send>(
     #[cfg(not(target_arch = "wasm32"))] async_runtime: &tokio::runtime::Runtime,
-    flows: &mut Vec<Box<dyn GraphicsFlow<State, Event>>>,
+    flows: &mut FlowList<State, Event>,
     ctx: &Context,
     mouse_state: &MouseState,
-    #[cfg(target_arch = "wasm32")] proxy: winit::event_loop::EventLoopProxy<
-        crate::flow::FlowEvent<State, Event>,
-    >,
-) -> Option<(u32, HashSet<usize>)> {
-    // Prepare data for picking:
-    let u32_size = std::mem::size_of::<u32>() as u32;
-    let mut width = ctx.config.width;
-    let mut height = ctx.config.height;
-    // The buffer layout requires width to be divisible by 256
-    let width_offset = (256 - (width % 256)) % 256;
-    let height_offset = (256 - (height % 256)) % 256;
-    width += width_offset;
-    height += height_offset;
-    // On WASM, clamp to device texture limit (e.g. 2048 on WebGL)
+    #[cfg(target_arch = "wasm32")] proxy: crate::flow::EngineProxy<State, Event>,
+    #[cfg(target_arch = "wasm32")] purpose: PickPurpose,
+) -> (Picked, PickHit) {
+    crate::profiling::record_pick();
+    let mouse_coords = mouse_state.coords.clone();
+    let region = PickRegion::at(mouse_coords, ctx.config.width, ctx.config.height);
+    let pick = render_pick_buffer(flows, ctx, region);
+    let device = ctx.device.clone();
     #[cfg(target_arch = "wasm32")]
-    {
-        let max_dim = ctx.device.limits().max_texture_dimension_2d;
-        width = width.min(max_dim);
-        height = height.min(max_dim);
-    }
-    // Compute mouse-to-texture scale factors after all size adjustments
-    let width_factor = f64::from(width) / f64::from(ctx.config.width);
-    let height_factor = f64::from(height) / f64::from(ctx.config.height);
-
-    let extent3d = wgpu::Extent3d {
-        width: width,
-        height: height,
-        depth_or_array_layers: 1,
-    };
-
-    let pick_texture = &ctx.device.create_texture(&wgpu::TextureDescriptor {
-        label: Some("Pick texture"),
-        size: extent3d,
-        mip_level_count: 1,
-        sample_count: 1,
-        dimension: wgpu::TextureDimension::D2,
-        format: wgpu::TextureFormat::R32Uint,
-        usage: wgpu::TextureUsages::COPY_SRC | wgpu::TextureUsages::RENDER_ATTACHMENT,
-        view_formats: &[],
+    wasm_bindgen_futures::spawn_local(async move {
+        let (id, position) = read_texture_buffer(&pick, &device, mouse_coords).await;
+        let (picked, hit) = pick.resolve(id, position);
+        pick.unmap();
+        let event = match purpose {
+            PickPurpose::Hover => Some(FlowEvent::Hover(mouse_coords, picked, hit)),
+            PickPurpose::Click(button) => picked.map(|picked| FlowEvent::Id(picked, hit, button)),
+        };
+        if let Some(event) = event
+            && proxy.send_event(event).is_err()
+        {
+            log::warn!("The event loop closed before a pick was delivered");
+        }
     });
+    #[cfg(target_arch = "wasm32")]
+    return (None, PickHit::default());
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let future_id = read_texture_buffer(&pick, &device, mouse_coords);
+        // Depending on the average timing this hould not block but rather always send an event
+        let (id, position) = async_runtime.block_on(future_id);
+        // TODO: eventually filter for default ID and return empty flow_ids.
+        // `on_click` should not listen to default ID (Should rather listen to mouse events directly in that case)
+        let resolved = pick.resolve(id, position);
+        pick.unmap();
+        return resolved;
+    }
+}
 
-    let pick_depth_texture = &ctx.device.create_texture(&wgpu::TextureDescriptor {
-        label: Some("Pick depth texture"),
-        size: extent3d,
-        mip_level_count: 1,
-        sample_count: 1,
-        dimension: wgpu::TextureDimension::D2,
-        format: wgpu::TextureFormat::Depth24Plus,
-        usage: wgpu::TextureUsages::COPY_SRC | wgpu::TextureUsages::RENDER_ATTACHMENT,
-        view_formats: &[],
-    });
+/// Renders the ids of all pickable objects of `flows` and submits a copy of `region` into a
+/// mappable buffer.
+///
+/// The pick texture matches the window so mouse coordinates address it directly, only the
+/// rows of the readback are padded as the buffer layout requires.
+pub(crate) fn render_pick_buffer<State, Event: Send>(
+    flows: &mut FlowList<State, Event>,
+    ctx: &Context,
+    region: PickRegion,
+) -> PickBuffer {
+    let (width, height) = (ctx.config.width, ctx.config.height);
+    let depth = ctx.depth();
+    let mut cached = ctx.pick_targets.borrow_mut();
+    if cached.as_ref().is_none_or(|targets| targets.size != (width, height)) {
+        *cached = Some(PickTargets::new(&ctx.device, width, height));
+    }
+    let targets = cached.as_mut().expect("pick targets were created above");
+    let row_bytes = region.row_bytes();
+    let (output_buffer, recycle) = if region.width == 1 && region.height == 1 {
+        let cached = targets.readback.lock().expect("pick readback poisoned").take();
+        let buffer = cached.unwrap_or_else(|| pixel_readback(&ctx.device));
+        (buffer, Some(Arc::clone(&targets.readback)))
+    } else {
+        (readback_buffer(&ctx.device, region.readback_size()), None)
+    };
 
     let mut encoder = ctx
         .device
         .create_command_encoder(&wgpu::CommandEncoderDescriptor {
             label: Some("Pick Encoder"),
         });
-    let mut translation: HashMap<PickId, HashSet<usize>> = HashMap::new();
+    let mut translation: HashMap<PickId, BTreeSet<FlowId>> = HashMap::new();
+    let mut instances = InstanceRanges::default();
+    let pick_view = &targets.ids_view;
+    let pick_depth_view = &targets.depth_view;
 
     {
-        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-            label: Some("Render Pass"),
-            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                view: &pick_texture.create_view(&wgpu::TextureViewDescriptor {
-                    label: Some("Render texture"),
-                    format: Some(wgpu::TextureFormat::R32Uint),
-                    dimension: Some(wgpu::TextureViewDimension::D2),
-                    usage: None,
-                    aspect: wgpu::TextureAspect::All,
-                    base_mip_level: 0,
-                    mip_level_count: None,
-                    base_array_layer: 0,
-                    array_layer_count: None,
-                }),
-                resolve_target: None,
-                ops: wgpu::Operations {
-                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
-                    store: wgpu::StoreOp::Store,
-                },
-                depth_slice: None,
-            })],
-            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                view: &pick_depth_texture.create_view(&wgpu::TextureViewDescriptor {
-                    label: Some("Stencil texture"),
-                    format: Some(wgpu::TextureFormat::Depth24Plus),
-                    dimension: Some(wgpu::TextureViewDimension::D2),
-                    usage: None,
-                    aspect: wgpu::TextureAspect::All,
-                    base_mip_level: 0,
-                    mip_level_count: None,
-                    base_array_layer: 0,
-                    array_layer_count: None,
-                }),
-                depth_ops: Some(wgpu::Operations {
-                    load: wgpu::LoadOp::Clear(1.0),
-                    store: wgpu::StoreOp::Store,
-                }),
-                stencil_ops: None,
-            }),
-            ..Default::default()
-        });
+        let mut render_pass =
+            begin_pick_pass(&mut encoder, pick_view, pick_depth_view, true, depth.far());
 
-        let mut basics: Vec<Instanced> = Vec::new();
-        let mut flats: Vec<Flat> = Vec::new();
-        let mut geoms: Vec<Geometry> = Vec::new();
+        let mut layers = Layers::new();
         /*
            We support graphics flow that handle pick IDs internally. Thus, we store the
-           correspondance of the flow index and the model picked so that each flow only
+           correspondance of the flow id and the model picked so that each flow only
            gets invoked if one of the IDs it manages was picked.
 
            Example:
-           flow1 at index 0 owns the pick IDs [1, 2, 3, 4, 5]
-           flow2 at index 1 owns the pick IDs [5, 6, 7, 8, 9]
+           flow1 with id 0 owns the pick IDs [1, 2, 3, 4, 5]
+           flow2 with id 1 owns the pick IDs [5, 6, 7, 8, 9]
 
            Warning: Overlapping ID responsibility may not be the best design choice.
 
            On pick result 2 we invoke flow1.on_pick(2).
-           On pick result 5 we invoke flow1.on_pick(5) followed by flow2.on_pick(5).
+           On pick result 5 we invoke flow1.on_pick(5) and flow2.on_pick(5) in dispatch order.
         */
-        flows.iter_mut().enumerate().for_each(|(idx, flow)| {
+        // Only the main window and flows drawn there that take input are picked
+        let main_flows = flows.iter_with_ids().filter(|(flow_id, flow)| {
+            let activity = ctx.flow_activity(*flow_id);
+            flow.window() == WindowId::MAIN && activity.input && activity.render
+        });
+        main_flows.for_each(|(flow_id, flow)| {
             let render = flow.on_render();
-            render.map_ids(idx, &mut translation);
-            render.set_pick_pipelines(&ctx, &mut render_pass, &mut basics, &mut flats, &mut geoms);
+            render.map_ids(flow_id, &mut translation);
+            render.set_pipelines(flow.layer(), &mut layers);
         });
 
-        render_pass.set_pipeline(&ctx.pipelines.pick);
-        for instanced in basics.iter_mut() {
-            if instanced.amount == 0 || instanced.instance.size() == 0 {
-                log::debug!("Cannot pick empty render.");
-                continue;
-            }
-            let pick_model =
-                load_pick_model(&ctx.device, instanced.id, instanced.model.meshes.clone()).unwrap();
-            render_pass.set_vertex_buffer(1, instanced.instance.slice(..));
-            let amount: Result<u32, _> = instanced.amount.try_into();
-            match amount {
-                Err(e) => log::error!(
-                    "Failed to render flat object with id {:?}. Maximum amount of supported instances is {}. Error: {}",
+        // Draws picked per instance claim the ids of all of their instances
+        let draws = || layers.values().flat_map(Batches::instanced);
+        let plain_ids = draws()
+            .filter(|instanced| !instanced.pick_instances)
+            .map(|instanced| instanced.id)
+            .chain(layers.values().flat_map(|batches| batches.guis.iter().map(|flat| flat.id)))
+            .map(|id| id.0)
+            .collect();
+        let per_instance =
+            draws().filter(|instanced| instanced.pick_instances && instanced.amount > 0);
+        for instanced in per_instance {
+            let (id, amount, visibility) = (instanced.id, instanced.amount, instanced.visibility);
+            if let Err(conflict) = instances.insert(id, amount, visibility, &plain_ids) {
+                log::error!(
+                    "Picking the instances of {:?} by its id only, their ids conflict: {:?}",
                     instanced.id,
-                    u32::MAX,
-                    e
-                ),
-                Ok(amount) => render_pass.draw_model_instanced(
-                    &pick_model,
-                    0..amount,
-                    &ctx.camera.bind_group,
-                    &ctx.light.bind_group,
-                ),
+                    conflict
+                );
             }
         }
 
-        render_pass.set_pipeline(&ctx.pipelines.flat_pick);
-        render_pass.set_bind_group(1, &ctx.screen_size.bind_group, &[]);
-        for flat in flats {
-            let pick_group = load_pick_texture(flat.id, &ctx.device);
-            render_pass.set_bind_group(0, &pick_group, &[]);
-            render_pass.set_vertex_buffer(0, flat.vertex.slice(..));
-            render_pass.set_index_buffer(flat.index.slice(..), wgpu::IndexFormat::Uint16);
-            let amount: Result<u32, _> = flat.amount.try_into();
-            match amount {
-                Err(e) => log::error!(
-                    "Failed to render flat object with id {:?}. Maximum amount of supported instances is {}. Error: {}",
-                    flat.id,
-                    u32::MAX,
-                    e
-                ),
-                Ok(amount) => render_pass.draw_indexed(0..amount, 0, 0..1),
+        let mut materials = ctx.pick_materials.borrow_mut();
+        let ids = layers.values().flat_map(|batches| {
+            let instanced = batches
+                .instanced()
+                .map(|instanced| (instanced.id, instances.contains(instanced.id)));
+            instanced.chain(batches.guis.iter().map(|flat| (flat.id, false)))
+        });
+        materials.sync(ids, &ctx.device, &ctx.layouts.pick);
+
+        // Layers hide each other like they do on screen
+        for (position, batches) in layers.into_values().enumerate() {
+            if position > 0 && batches.clear_depth {
+                drop(render_pass);
+                render_pass = begin_pick_pass(
+                    &mut encoder,
+                    pick_view,
+                    pick_depth_view,
+                    false,
+                    depth.far(),
+                );
+            }
+
+            for instanced in batches.instanced() {
+                if instanced.amount == 0 || instanced.instance.size() == 0 {
+                    log::debug!("Cannot pick empty render.");
+                    continue;
+                }
+                render_pass.set_pipeline(ctx.pipelines.pick_for(instanced));
+                render_pass.set_vertex_buffer(1, instanced.instance.slice(..));
+                let amount: Result<u32, _> = instanced.amount.try_into();
+                match amount {
+                    Err(e) => log::error!(
+                        "Failed to render flat object with id {:?}. Maximum amount of supported instances is {}. Error: {}",
+                        instanced.id,
+                        u32::MAX,
+                        e
+                    ),
+                    Ok(amount) => render_pass.draw_model_instanced_with(
+                        instanced.model,
+                        Some(materials.get(instanced.id, instances.contains(instanced.id))),
+                        0..amount,
+                        ctx.active_camera_bind_group(),
+                        &ctx.light.bind_group,
+                    ),
+                }
+            }
+
+            // Picking is not supported for terrain and custom renders
+            render_pass.set_pipeline(&ctx.pipelines.flat_pick);
+            render_pass.set_bind_group(1, &ctx.screen_size.bind_group, &[]);
+            for flat in batches.guis {
+                render_pass.set_bind_group(0, &materials.get(flat.id, false).bind_group, &[]);
+                render_pass.set_vertex_buffer(0, flat.vertex.slice(..));
+                render_pass.set_index_buffer(flat.index.slice(..), wgpu::IndexFormat::Uint16);
+                let amount: Result<u32, _> = flat.amount.try_into();
+                match amount {
+                    Err(e) => log::error!(
+                        "Failed to render flat object with id {:?}. Maximum amount of supported instances is {}. Error: {}",
+                        flat.id,
+                        u32::MAX,
+                        e
+                    ),
+                    Ok(amount) => render_pass.draw_indexed(0..amount, 0, 0..1),
+                }
             }
         }
     }
 
-    let output_buffer_size = (u32_size * (width) * (height)) as wgpu::BufferAddress;
-    let output_buffer_desc = wgpu::BufferDescriptor {
-        size: output_buffer_size,
-        usage: wgpu::BufferUsages::COPY_DST
-                    // this tells wpgu that we want to read this buffer from the cpu
-                    | wgpu::BufferUsages::MAP_READ,
-        label: None,
-        mapped_at_creation: false,
-    };
-    let output_buffer = ctx.device.create_buffer(&output_buffer_desc);
-
-    encoder.copy_texture_to_buffer(
-        wgpu::TexelCopyTextureInfo {
-            aspect: wgpu::TextureAspect::All,
-            texture: &pick_texture,
-            mip_level: 0,
-            origin: wgpu::Origin3d::ZERO,
-        },
-        wgpu::TexelCopyBufferInfo {
-            buffer: &output_buffer,
-            layout: wgpu::TexelCopyBufferLayout {
-                offset: 0,
-                bytes_per_row: Some(u32_size * (width)),
-                rows_per_image: Some(height),
+    // The depths follow the ids in the same readback
+    let copies = [
+        (&targets.ids, wgpu::TextureAspect::All, 0),
+        (&targets.depth, wgpu::TextureAspect::DepthOnly, region.depth_offset()),
+    ];
+    for (texture, aspect, offset) in copies {
+        encoder.copy_texture_to_buffer(
+            wgpu::TexelCopyTextureInfo {
+                aspect,
+                texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d {
+                    x: region.x,
+                    y: region.y,
+                    z: 0,
+                },
+            },
+            wgpu::TexelCopyBufferInfo {
+                buffer: &output_buffer,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: wgpu::BufferAddress::from(offset),
+                    bytes_per_row: Some(row_bytes),
+                    rows_per_image: Some(region.height),
+                },
             },
-        },
-        extent3d,
-    );
+            wgpu::Extent3d {
+                width: region.width,
+                height: region.height,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
 
     ctx.queue.submit(iter::once(encoder.finish()));
-    let device = ctx.device.clone();
-    let mouse_coords = mouse_state.coords.clone();
-    #[cfg(target_arch = "wasm32")]
-    wasm_bindgen_futures::spawn_local(async move {
-        let buffer_slice = output_buffer.slice(..);
-        let future_id = read_texture_buffer(
-            buffer_slice,
-            &device,
-            width_factor,
-            height_factor,
-            width,
-            height,
-            mouse_coords,
-        );
-        let id = future_id.await;
-        if let Some(flow_ids) = translation.get(&PickId(id)) {
-            assert!(
-                proxy
-                    .send_event(FlowEvent::Id((id, flow_ids.clone())))
-                    .is_ok()
-            );
-            output_buffer.unmap();
-        };
-    });
-    #[cfg(target_arch = "wasm32")]
-    return None;
-    #[cfg(not(target_arch = "wasm32"))]
-    {
-        let buffer_slice = output_buffer.slice(..);
-        let future_id = read_texture_buffer(
-            buffer_slice,
-            &device,
-            width_factor,
-            height_factor,
-            width,
-            height,
-            mouse_coords,
-        );
-        // Depending on the average timing this hould not block but rather always send an event
-        let id = async_runtime.block_on(future_id);
-        // TODO: eventually filter for default ID and return empty flow_ids.
-        // `on_click` should not listen to default ID (Should rather listen to mouse events directly in that case)
-        return translation.get(&PickId(id)).map(|flow_ids| (id, flow_ids.clone()));
+    PickBuffer {
+        buffer: output_buffer,
+        region,
+        row_texels: row_bytes / TEXEL_SIZE,
+        view_proj: ctx.active_view_proj(),
+        far: depth.far(),
+        window: (width, height),
+        translation,
+        instances,
+        recycle,
     }
 }
 
+/// Pass drawing pick ids, clearing the ids too unless only the depth of a new layer is cleared.
+/// The depth is cleared to `far`, the far plane of the [`Context::depth`].
+fn begin_pick_pass(
+    encoder: &mut wgpu::CommandEncoder,
+    view: &wgpu::TextureView,
+    depth_view: &wgpu::TextureView,
+    clear_ids: bool,
+    far: f32,
+) -> wgpu::RenderPass<'static> {
+    let load = if clear_ids {
+        wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT)
+    } else {
+        wgpu::LoadOp::Load
+    };
+    encoder
+        .begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Render Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load,
+                    store: wgpu::StoreOp::Store,
+                },
+                depth_slice: None,
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: depth_view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(far),
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            ..Default::default()
+        })
+        .forget_lifetime()
+}
+
+/// Maps a rendered [`PickBuffer`] and samples it for every window pixel like a click would.
+#[cfg(feature = "integration-tests")]
+pub(crate) async fn read_pick_image(pick: &PickBuffer, ctx: &Context) -> PickImage {
+    let (tx, rx) = futures_intrusive::channel::shared::oneshot_channel();
+    let buffer_slice = pick.buffer.slice(..);
+    buffer_slice.map_async(wgpu::MapMode::Read, move |result| {
+        tx.send(result).unwrap();
+    });
+    ctx.device
+        .poll(wgpu::PollType::Wait {
+            submission_index: None,
+            timeout: None,
+        })
+        .unwrap();
+    rx.receive().await.unwrap().unwrap();
+    let data = buffer_slice.get_mapped_range();
+    let ids = &data[..pick.region.depth_offset() as usize];
+    PickImage::from_fn(ctx.config.width, ctx.config.height, |x, y| {
+        image::Luma([pick_id_from_buffer(
+            bytemuck::cast_slice(ids),
+            pick.region,
+            pick.row_texels,
+            f64::from(x),
+            f64::from(y),
+        )])
+    })
+}
+
+/// Reads the id under the mouse from the `R32Uint` pick texels of `region`, one `u32` per
+/// pixel and `row_texels` per row.
+///
+/// Mouse positions outside of the region or the buffer return the empty space id `0`
+/// instead of reading the padding, a neighbouring row or past the end.
 pub(crate) fn pick_id_from_buffer(
-    data: &[u8],
-    width: u32,
-    width_factor: f64,
-    height_factor: f64,
+    data: &[u32],
+    region: PickRegion,
+    row_texels: u32,
     mouse_x: f64,
     mouse_y: f64,
 ) -> u32 {
+    texel_from_buffer(data, region, row_texels, mouse_x, mouse_y).unwrap_or(0)
+}
+
+/// Texel under the mouse like [`pick_id_from_buffer`], `None` outside of the region.
+fn texel_from_buffer<T: Copy>(
+    data: &[T],
+    region: PickRegion,
+    row_texels: u32,
+    mouse_x: f64,
+    mouse_y: f64,
+) -> Option<T> {
     if mouse_x < 0.0 || mouse_y < 0.0 {
-        return 0;
-    }
-    let x = (mouse_x * width_factor) as usize;
-    let y = (mouse_y * height_factor) as usize;
-    let pick_index = (y * width as usize + x) * 4;
-    if pick_index + 3 >= data.len() {
-        return 0;
-    }
-    u32::from(data[pick_index])
-        | u32::from(data[pick_index + 1]) << 8
-        | u32::from(data[pick_index + 2]) << 16
-        | u32::from(data[pick_index + 3]) << 24
+        return None;
+    }
+    let x = (mouse_x as usize).wrapping_sub(region.x as usize);
+    let y = (mouse_y as usize).wrapping_sub(region.y as usize);
+    if x >= region.width as usize || y >= region.height as usize {
+        return None;
+    }
+    data.get(y * row_texels as usize + x).copied()
 }
 
+/// Maps `pick` and reads the id and the world position of the hit under `mouse_coords`.
 async fn read_texture_buffer(
-    buffer_slice: wgpu::BufferSlice<'_>,
+    pick: &PickBuffer,
     device: &wgpu::Device,
-    width_factor: f64,
-    height_factor: f64,
-    width: u32,
-    _height: u32,
     mouse_coords: winit::dpi::PhysicalPosition<f64>,
-) -> u32 {
+) -> (u32, Option<Vector3<f32>>) {
+    let buffer_slice = pick.buffer.slice(..);
     // NOTE: We have to create the mapping THEN device.poll() before await
     // the future. Otherwise the application will freeze.
     let (tx, rx) = futures_intrusive::channel::shared::oneshot_channel();
@@ -353,76 +843,183 @@ async fn read_texture_buffer(
     rx.receive().await.unwrap().unwrap();
 
     let data = buffer_slice.get_mapped_range();
-    // [(0, 0, 0, 0), (0`, 255, 0, 255), (0, 0, 0, 0),
-    // (0, 0, 0, 0), (0, 255, 0, 255), (0, 0, 0, 0)]
-    let rgba_u32 = pick_id_from_buffer(
-        &data,
-        width,
-        width_factor,
-        height_factor,
-        mouse_coords.x,
-        mouse_coords.y,
-    );
+    let (region, row_texels) = (pick.region, pick.row_texels);
+    let (ids, depths) = data.split_at(region.depth_offset() as usize);
+    // Mapped ranges are aligned to `wgpu::MAP_ALIGNMENT`, so the texels cast without copying
+    let (x, y) = (mouse_coords.x, mouse_coords.y);
+    let id = pick_id_from_buffer(bytemuck::cast_slice(ids), region, row_texels, x, y);
+    let depth = texel_from_buffer::<f32>(bytemuck::cast_slice(depths), region, row_texels, x, y);
 
     // This is great for debugging. I'll keep it as I need it often.
     /*use image::{ImageBuffer, Rgba};
-    let buffer = ImageBuffer::<Rgba<u8>, _>::from_raw(width, height, data).unwrap();
+    let buffer = ImageBuffer::<Rgba<u8>, _>::from_raw(region.width, region.height, data).unwrap();
     buffer.save("image.png").unwrap();*/
 
-    log::info!("Selected obj with id {}", rgba_u32);
-    rgba_u32
+    log::info!("Selected obj with id {}", id);
+    (id, pick.hit_position(mouse_coords, depth))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn whole(width: u32, height: u32) -> PickRegion {
+        PickRegion {
+            x: 0,
+            y: 0,
+            width,
+            height,
+        }
+    }
+
     #[test]
-    fn pick_id_from_buffer_reconstructs_le_u32() {
-        // 4-byte little-endian encoding of 0x04030201 = 67305985
-        let data: Vec<u8> = vec![0x01, 0x02, 0x03, 0x04];
-        let id = pick_id_from_buffer(&data, 1, 1.0, 1.0, 0.0, 0.0);
-        assert_eq!(id, 0x04030201);
+    fn pick_id_from_buffer_reads_u32_texels() {
+        let data = [0x04030201, 5];
+        assert_eq!(pick_id_from_buffer(&data, whole(2, 1), 2, 0.0, 0.0), 0x04030201);
+        assert_eq!(pick_id_from_buffer(&data, whole(2, 1), 2, 1.0, 0.0), 5);
     }
 
     #[test]
-    fn pick_id_from_buffer_oob_returns_zero() {
-        // Buffer too small for the requested coordinates
-        let data: Vec<u8> = vec![0xFF; 4];
-        // mouse at (1,0) in a width=1 buffer → pick_index=4 which is past end
-        let id = pick_id_from_buffer(&data, 1, 1.0, 1.0, 1.0, 0.0);
-        assert_eq!(id, 0, "out-of-bounds pick should return 0");
+    fn pick_id_from_buffer_reads_the_corners() {
+        // 3x2 texture with the texel index + 1 as id
+        let data = [1, 2, 3, 4, 5, 6];
+        assert_eq!(pick_id_from_buffer(&data, whole(3, 2), 3, 0.0, 0.0), 1);
+        assert_eq!(pick_id_from_buffer(&data, whole(3, 2), 3, 2.0, 1.0), 6);
+        assert_eq!(pick_id_from_buffer(&data, whole(3, 2), 3, 2.9, 1.9), 6);
     }
 
     #[test]
-    fn pick_id_from_buffer_second_pixel() {
-        // Two pixels worth of data (8 bytes), width=2
-        let data: Vec<u8> = vec![0x00, 0x00, 0x00, 0x00, 0x05, 0x00, 0x00, 0x00];
-        // mouse at x=1, y=0, width=2 → pick_index = (0*2+1)*4 = 4
-        let id = pick_id_from_buffer(&data, 2, 1.0, 1.0, 1.0, 0.0);
-        assert_eq!(id, 5);
-    }
-
-    // BUG: The bounds check `pick_index + 3 >= data.len()` is off-by-one.
-    // When pick_index+3 == data.len()-1 (the very last byte), it should succeed
-    // but is rejected. A buffer of exactly 8 bytes with width=2 cannot read
-    // the second pixel at index 4 because 4+3=7 >= 8 would be false...
-    // Actually 4+3=7, 7 >= 8 is false, so this works. Let's test the exact boundary.
+    fn pick_id_from_buffer_beyond_the_edges_returns_zero() {
+        let data = [1, 2, 3, 4, 5, 6];
+        // Past the right edge must not wrap into the next row
+        assert_eq!(pick_id_from_buffer(&data, whole(3, 2), 3, 3.0, 0.0), 0);
+        assert_eq!(pick_id_from_buffer(&data, whole(3, 2), 3, 0.0, 2.0), 0);
+        assert_eq!(pick_id_from_buffer(&data, whole(3, 2), 3, 1e9, 1e9), 0);
+        // A stale size after a resize that doesn't match the buffer
+        assert_eq!(pick_id_from_buffer(&data, whole(4, 4), 4, 3.0, 3.0), 0);
+    }
+
     #[test]
-    fn pick_id_from_buffer_exact_boundary() {
-        // Exactly enough data: 2 pixels = 8 bytes, width=2
-        // Reading pixel at (1,0): pick_index=4, need bytes 4,5,6,7
-        let data: Vec<u8> = vec![0; 4].into_iter().chain(vec![0xAB, 0xCD, 0xEF, 0x12]).collect();
-        let id = pick_id_from_buffer(&data, 2, 1.0, 1.0, 1.0, 0.0);
-        assert_eq!(id, 0x12EFCDAB);
+    fn pick_id_from_buffer_skips_the_row_padding() {
+        // 2x2 window with rows padded to 4 texels, the padding must never be read
+        let data = [1, 2, 9, 9, 3, 4, 9, 9];
+        assert_eq!(pick_id_from_buffer(&data, whole(2, 2), 4, 0.0, 1.0), 3);
+        assert_eq!(pick_id_from_buffer(&data, whole(2, 2), 4, 1.0, 1.0), 4);
+        assert_eq!(pick_id_from_buffer(&data, whole(2, 2), 4, 2.0, 0.0), 0);
+    }
+
+    #[test]
+    fn pick_id_from_buffer_reads_the_cursor_pixel() {
+        let region = PickRegion::at(PhysicalPosition::new(7.5, 3.2), 10, 5);
+        assert_eq!((region.x, region.y), (7, 3));
+        let data = [9; 64];
+        assert_eq!(pick_id_from_buffer(&data, region, 64, 7.5, 3.2), 9);
+        assert_eq!(pick_id_from_buffer(&data, region, 64, 6.9, 3.2), 0);
+        assert_eq!(pick_id_from_buffer(&data, region, 64, 8.0, 3.2), 0);
+        assert_eq!(pick_id_from_buffer(&data, region, 64, 7.5, 4.0), 0);
+
+        // Outside of the window the region is clamped but nothing is picked
+        let region = PickRegion::at(PhysicalPosition::new(12.0, -4.0), 10, 5);
+        assert_eq!((region.x, region.y), (9, 0));
+        assert_eq!(pick_id_from_buffer(&data, region, 64, 12.0, -4.0), 0);
+        assert_eq!(region.row_bytes(), wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
     }
 
     // Negative mouse coordinates must return 0, not silently read the wrong pixel.
     #[test]
     fn pick_id_from_buffer_negative_mouse_returns_zero() {
-        let mut data: Vec<u8> = vec![0; 16];
-        data[0] = 42;
-        let id = pick_id_from_buffer(&data, 2, 1.0, 1.0, -1.0, 0.0);
+        let data = [42, 0, 0, 0];
+        let id = pick_id_from_buffer(&data, whole(2, 2), 2, -1.0, 0.0);
         assert_eq!(id, 0, "negative mouse coords must return 0, not read pixel (0,0)");
     }
+
+    fn owners(ids: &[usize]) -> BTreeSet<FlowId> {
+        ids.iter().map(|id| FlowId(*id)).collect()
+    }
+
+    #[test]
+    fn hover_out_is_delivered_once() {
+        let mut hover = HoverPicking::default();
+        let change = hover.hover(Some((7, owners(&[0, 1])))).unwrap();
+        assert!(change.left.is_empty());
+        assert_eq!(change.entered, Some((7, owners(&[0, 1]))));
+        assert_eq!(hover.hover(Some((7, owners(&[0, 1])))), None, "still hovering 7");
+
+        // Flow 1 owns both ids, so only flow 0 is told the cursor left
+        let change = hover.hover(Some((8, owners(&[1, 2])))).unwrap();
+        assert_eq!(change.left, owners(&[0]));
+
+        let change = hover.hover(Some((0, owners(&[2])))).unwrap();
+        assert_eq!(change.left, owners(&[1, 2]), "the default id is empty space");
+        assert_eq!(change.entered, None);
+        assert_eq!(hover.hover(None), None);
+    }
+
+    #[test]
+    fn clicks_reuse_picks_while_the_cursor_rests() {
+        let mut hover = HoverPicking::default();
+        let cursor = PhysicalPosition::new(10.0, 20.0);
+        assert!(!hover.moved(cursor), "disabled by default");
+
+        hover.enable(Duration::from_millis(100));
+        assert!(hover.moved(cursor));
+        let now = Instant::now();
+        assert!(!hover.throttled(now));
+        hover.start_pick(now);
+        assert!(hover.throttled(now));
+        let hit = PickHit {
+            position: Some(Vector3::new(1.0, 0.0, 2.0)),
+            instance: None,
+        };
+        hover.finish_pick(cursor, Some((3, owners(&[0]))), hit);
+
+        assert!(!hover.moved(cursor));
+        assert_eq!(hover.cached(cursor), Some((Some((3, owners(&[0]))), hit)));
+        assert_eq!(hover.cached(PhysicalPosition::new(11.0, 20.0)), None);
+        hover.disable();
+        assert_eq!(hover.cached(cursor), None);
+    }
+
+    #[test]
+    fn instance_ranges_resolve_ids_to_the_draw_and_instance() {
+        let mut ranges = InstanceRanges::default();
+        ranges.insert(PickId(100), 10, None, &BTreeSet::new()).unwrap();
+        // only instances 1, 5 and 6 of 8 were drawn
+        let culled = VisibilitySet::from_visible(8, [1, 5, 6]);
+        ranges.insert(PickId(10), 3, Some(&culled), &BTreeSet::new()).unwrap();
+
+        assert_eq!(ranges.resolve(100), Some((100, 0)));
+        assert_eq!(ranges.resolve(109), Some((100, 9)));
+        assert_eq!(ranges.resolve(110), None);
+        assert_eq!(ranges.resolve(99), None);
+        assert_eq!(ranges.resolve(10), Some((10, 1)));
+        assert_eq!(ranges.resolve(12), Some((10, 6)));
+        assert_eq!(ranges.resolve(13), None);
+        assert!(ranges.contains(PickId(10)));
+        assert!(!ranges.contains(PickId(11)));
+    }
+
+    #[test]
+    fn instance_ranges_reject_overlapping_ids() {
+        let mut ranges = InstanceRanges::default();
+        let plain = BTreeSet::from([7, 50]);
+        ranges.insert(PickId(20), 10, None, &plain).unwrap();
+
+        let overlaps = |id| Err(RangeConflict::Overlaps(PickId(id)));
+        assert_eq!(ranges.insert(PickId(25), 10, None, &plain), overlaps(20));
+        assert_eq!(ranges.insert(PickId(15), 6, None, &plain), overlaps(20));
+        assert_eq!(ranges.insert(PickId(20), 1, None, &plain), overlaps(20));
+        assert_eq!(ranges.insert(PickId(45), 10, None, &plain), overlaps(50));
+        assert_eq!(ranges.insert(PickId(5), 3, None, &plain), overlaps(7));
+        assert_eq!(ranges.insert(PickId(0), 3, None, &plain), overlaps(0));
+        assert_eq!(
+            ranges.insert(PickId(u32::MAX - 1), 2, None, &plain),
+            Err(RangeConflict::Overflow)
+        );
+        // adjacent ranges don't overlap
+        ranges.insert(PickId(30), 20, None, &plain).unwrap();
+        ranges.insert(PickId(15), 5, None, &plain).unwrap();
+        assert_eq!(ranges.resolve(19), Some((15, 4)));
+        assert_eq!(ranges.resolve(30), Some((30, 0)));
+    }
 }
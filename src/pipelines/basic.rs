@@ -3,28 +3,32 @@
 //! This is the primary pipeline for rendering opaque objects. It combines
 //! diffuse textures and normal maps for per-pixel lighting calculations.
 
-use crate::{data_structures::{instance::InstanceRaw, model::{self, Vertex}, texture::Texture}, resources::texture::diffuse_normal_layout};
+use crate::{context::Layouts, data_structures::{instance::InstanceRaw, model::{self, Vertex}, texture::DepthConfig}, pipelines::highlight::mk_highlight_bind_group_layout};
 
 /// Create the basic lighting pipeline for opaque 3D objects.
 ///
 /// The basic pipeline renders models with phong/standard lighting, supporting
-/// both diffuse textures and normal maps.
+/// both diffuse textures and normal maps into `color_format`, the surface format or
+/// [`crate::pipelines::tonemap::HDR_FORMAT`]. Group 3 holds the highlighted instances, see
+/// [`crate::pipelines::highlight`].
 pub fn mk_basic_pipeline(
     device: &wgpu::Device,
-    config: &wgpu::SurfaceConfiguration,
+    color_format: wgpu::TextureFormat,
     direction: wgpu::FrontFace,
-    light_bind_group_layout: &wgpu::BindGroupLayout,
-    camera_bind_group_layout: &wgpu::BindGroupLayout,
+    cull_mode: Option<wgpu::Face>,
+    layouts: &Layouts,
     sample_count: u32,
+    depth: DepthConfig,
 ) -> wgpu::RenderPipeline {
     let render_pipeline_layout =
         device
             .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
                 label: Some("Render Pipeline Layout"),
                 bind_group_layouts: &[
-                    Some(&diffuse_normal_layout(&device)),
-                    Some(&camera_bind_group_layout),
-                    Some(&light_bind_group_layout),
+                    Some(&layouts.diffuse_normal),
+                    Some(&layouts.camera),
+                    Some(&layouts.light),
+                    Some(&mk_highlight_bind_group_layout(device)),
                 ],
                 ..Default::default()
             });
@@ -37,13 +41,14 @@ pub fn mk_basic_pipeline(
     mk_render_pipeline(
         &device,
         direction,
+        cull_mode,
         &render_pipeline_layout,
-        config.format,
+        color_format,
         Some(wgpu::BlendState {
             alpha: wgpu::BlendComponent::REPLACE,
             color: wgpu::BlendComponent::REPLACE,
         }),
-        Some(Texture::DEPTH_FORMAT),
+        Some(depth),
         &[model::ModelVertex::desc(), InstanceRaw::desc()],
         shader,
         sample_count,
@@ -56,16 +61,18 @@ pub fn mk_basic_pipeline(
 pub fn mk_render_pipeline(
     device: &wgpu::Device,
     front_face: wgpu::FrontFace,
+    cull_mode: Option<wgpu::Face>,
     layout: &wgpu::PipelineLayout,
     color_format: wgpu::TextureFormat,
     blend: Option<wgpu::BlendState>,
-    depth_format: Option<wgpu::TextureFormat>,
+    depth: Option<DepthConfig>,
     vertex_layouts: &[wgpu::VertexBufferLayout],
     shader: wgpu::ShaderModuleDescriptor,
     sample_count: u32,
 ) -> wgpu::RenderPipeline {
     let shader = device.create_shader_module(shader);
 
+    crate::profiling::record_pipeline();
     device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
         cache: None,
         label: Some("Basic Pipeline"),
@@ -90,15 +97,15 @@ pub fn mk_render_pipeline(
             topology: wgpu::PrimitiveTopology::TriangleList,
             strip_index_format: None,
             front_face,
-            cull_mode: Some(wgpu::Face::Back),
+            cull_mode,
             polygon_mode: wgpu::PolygonMode::Fill,
             unclipped_depth: false,
             conservative: false,
         },
-        depth_stencil: depth_format.map(|format| wgpu::DepthStencilState {
-            format,
+        depth_stencil: depth.map(|depth| wgpu::DepthStencilState {
+            format: depth.format,
             depth_write_enabled: Some(true),
-            depth_compare: Some(wgpu::CompareFunction::LessEqual),
+            depth_compare: Some(depth.compare(wgpu::CompareFunction::LessEqual)),
             stencil: wgpu::StencilState::default(),
             bias: wgpu::DepthBiasState::default(),
         }),
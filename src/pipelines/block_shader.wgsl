@@ -3,6 +3,8 @@
 struct Camera {
     view_pos: vec4<f32>,
     view_proj: mat4x4<f32>,
+    // x holds the seconds since start
+    time: vec4<f32>,
 }
 @group(1) @binding(0)
 var<uniform> camera: Camera;
@@ -32,18 +34,43 @@ struct InstanceInput {
     @location(12) handedness: f32,
 }
 
+struct HighlightEntry {
+    // u32 max matches every instance
+    instance: u32,
+    color: vec4<f32>,
+}
+struct Highlights {
+    count: u32,
+    // MAX_HIGHLIGHTS
+    entries: array<HighlightEntry, 16>,
+}
+@group(3) @binding(0)
+var<uniform> highlights: Highlights;
+
+fn highlight_color(instance_index: u32) -> vec4<f32> {
+    for (var i = 0u; i < highlights.count; i++) {
+        let entry = highlights.entries[i];
+        if (entry.instance == instance_index || entry.instance == 0xffffffffu) {
+            return entry.color;
+        }
+    }
+    return vec4<f32>(0.0);
+}
+
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) tex_coords: vec2<f32>,
     @location(1) tangent_position: vec3<f32>,
     @location(2) tangent_light_position: vec3<f32>,
     @location(3) tangent_view_position: vec3<f32>,
+    @location(4) highlight: vec4<f32>,
 }
 
 @vertex
 fn vs_main(
     model: VertexInput,
     instance: InstanceInput,
+    @builtin(instance_index) instance_index: u32,
 ) -> VertexOutput {
     let model_matrix = mat4x4<f32>(
         instance.model_matrix_0,
@@ -76,6 +103,7 @@ fn vs_main(
     out.tangent_position = tangent_matrix * world_position.xyz;
     out.tangent_view_position = tangent_matrix * camera.view_pos.xyz;
     out.tangent_light_position = tangent_matrix * light.position;
+    out.highlight = highlight_color(instance_index);
     return out;
 }
 
@@ -90,10 +118,34 @@ var t_normal: texture_2d<f32>;
 @group(0) @binding(3)
 var s_normal: sampler;
 
+struct MaterialAnimation {
+    uv_offset: vec2<f32>,
+    uv_scale: vec2<f32>,
+    uv_scroll: vec2<f32>,
+    tint_period: f32,
+    tint_a: vec4<f32>,
+    tint_b: vec4<f32>,
+}
+@group(0) @binding(4)
+var<uniform> material: MaterialAnimation;
+
+fn animated_uv(uv: vec2<f32>) -> vec2<f32> {
+    return uv * material.uv_scale + material.uv_offset + material.uv_scroll * camera.time.x;
+}
+
+fn animated_tint() -> vec4<f32> {
+    if (material.tint_period <= 0.0) {
+        return material.tint_a;
+    }
+    let phase = 0.5 - 0.5 * cos(6.28318530718 * camera.time.x / material.tint_period);
+    return mix(material.tint_a, material.tint_b, phase);
+}
+
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    let object_color: vec4<f32> = textureSample(t_diffuse, s_diffuse, in.tex_coords);
-    let object_normal: vec4<f32> = textureSample(t_normal, s_normal, in.tex_coords);
+    let uv = animated_uv(in.tex_coords);
+    let object_color: vec4<f32> = textureSample(t_diffuse, s_diffuse, uv) * animated_tint();
+    let object_normal: vec4<f32> = textureSample(t_normal, s_normal, uv);
 
     // We don't need (or want) much ambient light, so 0.1 is fine
     let ambient_strength = 0.1;
@@ -114,7 +166,11 @@ fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
     let specular_color = specular_strength * light.color;
 
     // vec3:
-    let result = (ambient_color + diffuse_color + specular_color) * object_color.xyz;
+    let lit = (ambient_color + diffuse_color + specular_color) * object_color.xyz;
+
+    // Highlights tint the whole surface and brighten towards the silhouette
+    let rim = pow(1.0 - max(dot(tangent_normal, view_dir), 0.0), 2.0);
+    let result = mix(lit, in.highlight.rgb, in.highlight.a * (0.5 + 0.5 * rim));
 
     return vec4<f32>(result, object_color.a);
 }
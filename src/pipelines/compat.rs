@@ -0,0 +1,413 @@
+//! Checks WGSL shaders against the bind groups the engine sets before drawing.
+//!
+//! wgpu reports a shader that doesn't fit its pipeline layout, or a bind group that doesn't
+//! fit the pipeline, only once it's created or drawn, naming internal labels. [`check_shader`]
+//! compares the resource bindings of a shader with the layouts of an [`EngineGroups`] set up
+//! front and lists every mismatch, e.g.
+//!
+//! ```text
+//! group 1 expects a texture at binding 0, camera layout provides a uniform buffer
+//! ```
+//!
+//! [`Context::register_pipeline`](crate::context::Context::register_pipeline) runs it for
+//! custom pipelines, debug builds also run it for the engine's own shaders.
+
+use std::{collections::BTreeMap, fmt};
+
+use wgpu::naga;
+
+use crate::{
+    camera::CAMERA_ENTRIES,
+    error::{Error, Result},
+    pipelines::{
+        gui::{SCREEN_SIZE_ENTRIES, TEXTURE_ENTRIES},
+        highlight::HIGHLIGHT_ENTRIES,
+        light::LIGHT_ENTRIES,
+        transparent::TRANSPARENCY_ENTRIES,
+    },
+    resources::{pick::PICK_ENTRIES, texture::DIFFUSE_NORMAL_ENTRIES},
+};
+
+/// What a binding holds, as far as a shader and a layout have to agree on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BindingKind {
+    UniformBuffer,
+    StorageBuffer,
+    Texture,
+    DepthTexture,
+    MultisampledTexture,
+    StorageTexture,
+    Sampler,
+    ComparisonSampler,
+    /// E.g. external textures or acceleration structures.
+    Other,
+}
+
+impl BindingKind {
+    pub fn of_layout(ty: &wgpu::BindingType) -> Self {
+        match ty {
+            wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                ..
+            } => Self::UniformBuffer,
+            wgpu::BindingType::Buffer { .. } => Self::StorageBuffer,
+            wgpu::BindingType::Texture {
+                multisampled: true, ..
+            } => Self::MultisampledTexture,
+            wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Depth,
+                ..
+            } => Self::DepthTexture,
+            wgpu::BindingType::Texture { .. } => Self::Texture,
+            wgpu::BindingType::StorageTexture { .. } => Self::StorageTexture,
+            wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Comparison) => {
+                Self::ComparisonSampler
+            }
+            wgpu::BindingType::Sampler(_) => Self::Sampler,
+            _ => Self::Other,
+        }
+    }
+
+    fn of_global(module: &naga::Module, global: &naga::GlobalVariable) -> Self {
+        match global.space {
+            naga::AddressSpace::Uniform => Self::UniformBuffer,
+            naga::AddressSpace::Storage { .. } => Self::StorageBuffer,
+            naga::AddressSpace::Handle => Self::of_handle(module, global.ty),
+            _ => Self::Other,
+        }
+    }
+
+    fn of_handle(module: &naga::Module, ty: naga::Handle<naga::Type>) -> Self {
+        match &module.types[ty].inner {
+            naga::TypeInner::Image { class, .. } => match class {
+                naga::ImageClass::Sampled { multi: true, .. }
+                | naga::ImageClass::Depth { multi: true } => Self::MultisampledTexture,
+                naga::ImageClass::Sampled { .. } => Self::Texture,
+                naga::ImageClass::Depth { .. } => Self::DepthTexture,
+                naga::ImageClass::Storage { .. } => Self::StorageTexture,
+                naga::ImageClass::External => Self::Other,
+            },
+            naga::TypeInner::Sampler { comparison: true } => Self::ComparisonSampler,
+            naga::TypeInner::Sampler { comparison: false } => Self::Sampler,
+            naga::TypeInner::BindingArray { base, .. } => Self::of_handle(module, *base),
+            _ => Self::Other,
+        }
+    }
+}
+
+impl fmt::Display for BindingKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::UniformBuffer => "a uniform buffer",
+            Self::StorageBuffer => "a storage buffer",
+            Self::Texture => "a texture",
+            Self::DepthTexture => "a depth texture",
+            Self::MultisampledTexture => "a multisampled texture",
+            Self::StorageTexture => "a storage texture",
+            Self::Sampler => "a sampler",
+            Self::ComparisonSampler => "a comparison sampler",
+            Self::Other => "another resource",
+        })
+    }
+}
+
+/// A resource binding declared by a shader.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShaderBinding {
+    pub kind: BindingKind,
+    /// Stages of the entry points that use it, empty if none does.
+    pub stages: wgpu::ShaderStages,
+}
+
+/// The resource bindings of `wgsl` by group and binding index.
+pub fn shader_bindings(label: &str, wgsl: &str) -> Result<BTreeMap<(u32, u32), ShaderBinding>> {
+    let invalid = |message: String| Error::Shader {
+        label: label.to_string(),
+        source: message.into(),
+    };
+    let module = naga::front::wgsl::parse_str(wgsl).map_err(|e| invalid(e.emit_to_string(wgsl)))?;
+    let info = naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::all(),
+    )
+    .validate(&module)
+    .map_err(|e| invalid(e.emit_to_string(wgsl)))?;
+
+    let mut bindings = BTreeMap::new();
+    for (handle, global) in module.global_variables.iter() {
+        let Some(binding) = &global.binding else {
+            continue;
+        };
+        let stages = module
+            .entry_points
+            .iter()
+            .enumerate()
+            .filter(|&(idx, _)| !info.get_entry_point(idx)[handle].is_empty())
+            .fold(wgpu::ShaderStages::empty(), |stages, (_, entry)| {
+                stages | stage_of(entry.stage)
+            });
+        bindings.insert(
+            (binding.group, binding.binding),
+            ShaderBinding {
+                kind: BindingKind::of_global(&module, global),
+                stages,
+            },
+        );
+    }
+    Ok(bindings)
+}
+
+fn stage_of(stage: naga::ShaderStage) -> wgpu::ShaderStages {
+    match stage {
+        naga::ShaderStage::Vertex => wgpu::ShaderStages::VERTEX,
+        naga::ShaderStage::Fragment => wgpu::ShaderStages::FRAGMENT,
+        naga::ShaderStage::Compute => wgpu::ShaderStages::COMPUTE,
+        naga::ShaderStage::Task => wgpu::ShaderStages::TASK,
+        naga::ShaderStage::Mesh => wgpu::ShaderStages::MESH,
+        naga::ShaderStage::RayGeneration => wgpu::ShaderStages::RAY_GENERATION,
+        naga::ShaderStage::AnyHit => wgpu::ShaderStages::ANY_HIT,
+        naga::ShaderStage::ClosestHit => wgpu::ShaderStages::CLOSEST_HIT,
+        naga::ShaderStage::Miss => wgpu::ShaderStages::MISS,
+    }
+}
+
+fn stage_names(stages: wgpu::ShaderStages) -> String {
+    stages
+        .iter_names()
+        .map(|(name, _)| name.to_lowercase())
+        .collect::<Vec<_>>()
+        .join(" and ")
+}
+
+/// A bind group layout the engine binds at a fixed group index.
+#[derive(Debug, Clone, Copy)]
+pub struct EngineGroup {
+    pub name: &'static str,
+    pub entries: &'static [wgpu::BindGroupLayoutEntry],
+}
+
+const fn group(name: &'static str, entries: &'static [wgpu::BindGroupLayoutEntry]) -> EngineGroup {
+    EngineGroup { name, entries }
+}
+
+const MATERIAL: EngineGroup = group("material", &DIFFUSE_NORMAL_ENTRIES);
+const CAMERA: EngineGroup = group("camera", &CAMERA_ENTRIES);
+const LIGHT: EngineGroup = group("light", &LIGHT_ENTRIES);
+const PICK: EngineGroup = group("pick", &PICK_ENTRIES);
+const SCREEN_SIZE: EngineGroup = group("screen size", &SCREEN_SIZE_ENTRIES);
+
+const BASIC: &[EngineGroup] = &[
+    MATERIAL,
+    CAMERA,
+    LIGHT,
+    group("highlight", &HIGHLIGHT_ENTRIES),
+];
+const TRANSPARENT: &[EngineGroup] = &[
+    MATERIAL,
+    CAMERA,
+    LIGHT,
+    group("transparency", &TRANSPARENCY_ENTRIES),
+];
+const GUI: &[EngineGroup] = &[group("gui texture", &TEXTURE_ENTRIES), SCREEN_SIZE];
+
+/// The bind groups the batching code sets before drawing a kind of
+/// [`Render`](crate::render::Render), index `i` holding group `i`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EngineGroups {
+    /// Material, camera, light and highlighted instances.
+    Basic,
+    /// Material, camera, light and the transparency uniform with the scene depth.
+    Transparent,
+    /// Pick id and camera.
+    Pick,
+    /// Texture and screen size.
+    Gui,
+    /// Pick id and screen size.
+    FlatPick,
+}
+
+impl EngineGroups {
+    pub fn groups(self) -> &'static [EngineGroup] {
+        match self {
+            Self::Basic => BASIC,
+            Self::Transparent => TRANSPARENT,
+            Self::Pick => &[PICK, CAMERA],
+            Self::Gui => GUI,
+            Self::FlatPick => &[PICK, SCREEN_SIZE],
+        }
+    }
+
+    /// The `Render` variants drawn with these groups.
+    pub fn drawn_for(self) -> &'static str {
+        match self {
+            Self::Basic => "Render::Default and Render::Defaults",
+            Self::Transparent => "Render::Transparent and Render::Transparents",
+            Self::Pick => "picking Render::Default, Defaults, Transparent and Transparents",
+            Self::Gui => "Render::GUI",
+            Self::FlatPick => "picking Render::GUI",
+        }
+    }
+}
+
+/// Mismatches between `bindings` of a shader and the layouts of `groups`, one line each.
+fn mismatches(bindings: &BTreeMap<(u32, u32), ShaderBinding>, groups: EngineGroups) -> Vec<String> {
+    let layouts = groups.groups();
+    let mut mismatches = Vec::new();
+    for (&(group, binding), expected) in bindings {
+        let Some(layout) = layouts.get(group as usize) else {
+            mismatches.push(format!(
+                "group {group} expects {} at binding {binding}, but only groups 0 to {} are set",
+                expected.kind,
+                layouts.len() - 1
+            ));
+            continue;
+        };
+        let name = layout.name;
+        let Some(entry) = layout.entries.iter().find(|entry| entry.binding == binding) else {
+            mismatches.push(format!(
+                "group {group} expects {} at binding {binding}, {name} layout has no binding {binding}",
+                expected.kind
+            ));
+            continue;
+        };
+        let provided = BindingKind::of_layout(&entry.ty);
+        if provided != expected.kind {
+            mismatches.push(format!(
+                "group {group} expects {} at binding {binding}, {name} layout provides {provided}",
+                expected.kind
+            ));
+        }
+        let hidden = expected.stages - entry.visibility;
+        if !hidden.is_empty() {
+            mismatches.push(format!(
+                "group {group} binding {binding} is used in the {} stage, {name} layout doesn't expose it there",
+                stage_names(hidden)
+            ));
+        }
+    }
+    mismatches
+}
+
+/// Fails with [`Error::IncompatiblePipeline`] listing every binding of `wgsl` that the
+/// layouts of `groups` don't provide. Bindings of the layouts the shader doesn't use are fine.
+pub fn check_shader(label: &str, wgsl: &str, groups: EngineGroups) -> Result<()> {
+    let mismatches = mismatches(&shader_bindings(label, wgsl)?, groups);
+    if mismatches.is_empty() {
+        return Ok(());
+    }
+    Err(Error::IncompatiblePipeline {
+        label: label.to_string(),
+        drawn_for: groups.drawn_for(),
+        mismatches,
+    })
+}
+
+/// Checks the engine's shaders against the groups their pipelines are drawn with.
+#[cfg(any(debug_assertions, test))]
+pub(crate) fn check_engine_shaders() -> Result<()> {
+    let shaders = [
+        (
+            "basic",
+            include_str!("block_shader.wgsl"),
+            EngineGroups::Basic,
+        ),
+        (
+            "transparent",
+            include_str!("transparent.wgsl"),
+            EngineGroups::Transparent,
+        ),
+        ("pick", include_str!("pick_basic.wgsl"), EngineGroups::Pick),
+        ("gui", include_str!("icon.wgsl"), EngineGroups::Gui),
+        (
+            "flat pick",
+            include_str!("pick_gui.wgsl"),
+            EngineGroups::FlatPick,
+        ),
+    ];
+    for (label, wgsl, groups) in shaders {
+        check_shader(label, wgsl, groups)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn engine_shaders_match_their_groups() {
+        if let Err(error) = check_engine_shaders() {
+            panic!("{error}");
+        }
+    }
+
+    #[test]
+    fn mismatches_name_the_group_and_layout() {
+        let wgsl = "
+            @group(1) @binding(0) var t_camera: texture_2d<f32>;
+            @group(0) @binding(7) var<uniform> extra: vec4<f32>;
+            @group(2) @binding(0) var<uniform> light: vec4<f32>;
+            @group(4) @binding(0) var<uniform> custom: vec4<f32>;
+            @group(0) @binding(0) var t_diffuse: texture_2d<f32>;
+            @group(0) @binding(1) var s_diffuse: sampler;
+
+            @vertex
+            fn vs_main() -> @builtin(position) vec4<f32> {
+                return light + custom;
+            }
+
+            @fragment
+            fn fs_main() -> @location(0) vec4<f32> {
+                let uv = vec2<f32>(0.0);
+                return textureSample(t_camera, s_diffuse, uv)
+                    + textureSample(t_diffuse, s_diffuse, uv) + extra;
+            }
+        ";
+        let error = check_shader("water", wgsl, EngineGroups::Basic).unwrap_err();
+        let Error::IncompatiblePipeline { mismatches, .. } = &error else {
+            panic!("unexpected error {error}");
+        };
+        assert_eq!(
+            mismatches,
+            &[
+                "group 0 expects a uniform buffer at binding 7, material layout has no binding 7",
+                "group 1 expects a texture at binding 0, camera layout provides a uniform buffer",
+                "group 4 expects a uniform buffer at binding 0, but only groups 0 to 3 are set",
+            ]
+        );
+        assert!(error.to_string().starts_with(
+            "pipeline water doesn't match the bind groups set for Render::Default and Render::Defaults:\n"
+        ));
+    }
+
+    #[test]
+    fn bindings_hidden_from_a_stage_are_reported() {
+        let wgsl = "
+            @group(0) @binding(0) var t_diffuse: texture_2d<f32>;
+            @group(1) @binding(0) var<uniform> screen: vec4<f32>;
+
+            @vertex
+            fn vs_main() -> @builtin(position) vec4<f32> {
+                return textureLoad(t_diffuse, vec2<i32>(0), 0) + screen;
+            }
+        ";
+        let error = check_shader("icons", wgsl, EngineGroups::Gui).unwrap_err();
+        let Error::IncompatiblePipeline { mismatches, .. } = &error else {
+            panic!("unexpected error {error}");
+        };
+        assert_eq!(
+            mismatches,
+            &[
+                "group 0 binding 0 is used in the vertex stage, gui texture layout doesn't expose it there"
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_wgsl_is_a_shader_error() {
+        assert!(matches!(
+            check_shader("broken", "fn (", EngineGroups::Gui),
+            Err(Error::Shader { label, .. }) if label == "broken"
+        ));
+    }
+}
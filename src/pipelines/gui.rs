@@ -1,4 +1,4 @@
-use crate::data_structures::texture::{self, Texture};
+use crate::{context::Layouts, data_structures::texture::{DepthConfig, Texture}};
 
 #[repr(C)]
 #[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
@@ -37,26 +37,29 @@ fn mk_shader(device: &wgpu::Device) -> wgpu::ShaderModule {
     })
 }
 
+/// Entries of [`mk_bind_group_layout`].
+pub const TEXTURE_ENTRIES: [wgpu::BindGroupLayoutEntry; 2] = [
+    wgpu::BindGroupLayoutEntry {
+        binding: 0,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Texture {
+            multisampled: false,
+            view_dimension: wgpu::TextureViewDimension::D2,
+            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+        },
+        count: None,
+    },
+    wgpu::BindGroupLayoutEntry {
+        binding: 1,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+        count: None,
+    },
+];
+
 pub fn mk_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
     device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-        entries: &[
-            wgpu::BindGroupLayoutEntry {
-                binding: 0,
-                visibility: wgpu::ShaderStages::FRAGMENT,
-                ty: wgpu::BindingType::Texture {
-                    multisampled: false,
-                    view_dimension: wgpu::TextureViewDimension::D2,
-                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                },
-                count: None,
-            },
-            wgpu::BindGroupLayoutEntry {
-                binding: 1,
-                visibility: wgpu::ShaderStages::FRAGMENT,
-                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-                count: None,
-            },
-        ],
+        entries: &TEXTURE_ENTRIES,
         label: Some("Menu texture_bind_group_layout"),
     })
 }
@@ -86,18 +89,23 @@ pub fn mk_bind_group(
     })
 }
 
+/// Entries of [`mk_screen_size_bind_group_layout`].
+pub const SCREEN_SIZE_ENTRIES: [wgpu::BindGroupLayoutEntry; 1] = [
+    wgpu::BindGroupLayoutEntry {
+        binding: 0,
+        visibility: wgpu::ShaderStages::VERTEX,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    },
+];
+
 pub fn mk_screen_size_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
     device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-        entries: &[wgpu::BindGroupLayoutEntry {
-            binding: 0,
-            visibility: wgpu::ShaderStages::VERTEX,
-            ty: wgpu::BindingType::Buffer {
-                ty: wgpu::BufferBindingType::Uniform,
-                has_dynamic_offset: false,
-                min_binding_size: None,
-            },
-            count: None,
-        }],
+        entries: &SCREEN_SIZE_ENTRIES,
         label: Some("screen_size_bind_group_layout"),
     })
 }
@@ -119,47 +127,25 @@ pub fn mk_screen_size_bind_group(
 
 fn mk_pipeline_layout(
     device: &wgpu::Device,
-    texture_bind_group_layout: wgpu::BindGroupLayout,
+    texture_bind_group_layout: &wgpu::BindGroupLayout,
     screen_size_bind_group_layout: &wgpu::BindGroupLayout,
 ) -> wgpu::PipelineLayout {
     device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
         label: Some("Menu Render Pipeline Layout"),
-        bind_group_layouts: &[Some(&texture_bind_group_layout), Some(screen_size_bind_group_layout)],
+        bind_group_layouts: &[Some(texture_bind_group_layout), Some(screen_size_bind_group_layout)],
         ..Default::default()
     })
 }
 
-fn mk_texture_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
-    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-        entries: &[
-            wgpu::BindGroupLayoutEntry {
-                binding: 0,
-                visibility: wgpu::ShaderStages::FRAGMENT,
-                ty: wgpu::BindingType::Texture {
-                    multisampled: false,
-                    view_dimension: wgpu::TextureViewDimension::D2,
-                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                },
-                count: None,
-            },
-            wgpu::BindGroupLayoutEntry {
-                binding: 1,
-                visibility: wgpu::ShaderStages::FRAGMENT,
-                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-                count: None,
-            },
-        ],
-        label: Some("Menu texture_bind_group_layout"),
-    })
-}
-
 fn mk_render_pipeline(
     device: &wgpu::Device,
     config: &wgpu::SurfaceConfiguration,
     render_pipeline_layout: &wgpu::PipelineLayout,
     shader: &wgpu::ShaderModule,
     sample_count: u32,
+    depth: DepthConfig,
 ) -> wgpu::RenderPipeline {
+    crate::profiling::record_pipeline();
     device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
         label: Some("Menu Render Pipeline"),
         layout: Some(&render_pipeline_layout),
@@ -192,9 +178,9 @@ fn mk_render_pipeline(
             conservative: false,
         },
         depth_stencil: Some(wgpu::DepthStencilState {
-            format: texture::Texture::DEPTH_FORMAT,
+            format: depth.format,
             depth_write_enabled: Some(true),
-            depth_compare: Some(wgpu::CompareFunction::LessEqual),
+            depth_compare: Some(depth.compare(wgpu::CompareFunction::LessEqual)),
             stencil: wgpu::StencilState::default(),
             bias: wgpu::DepthBiasState::default(),
         }),
@@ -209,12 +195,11 @@ fn mk_render_pipeline(
     })
 }
 
-pub fn mk_gui_pipeline(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration, screen_size_layout: &wgpu::BindGroupLayout, sample_count: u32) -> wgpu::RenderPipeline {
+pub fn mk_gui_pipeline(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration, layouts: &Layouts, screen_size_layout: &wgpu::BindGroupLayout, sample_count: u32, depth: DepthConfig) -> wgpu::RenderPipeline {
     let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
         label: Some("Shader"),
         source: wgpu::ShaderSource::Wgsl(include_str!("icon.wgsl").into()),
     });
-    let texture_bind_group_layout = mk_texture_bind_group_layout(device);
-    let render_pipeline_layout = &mk_pipeline_layout(device, texture_bind_group_layout, screen_size_layout);
-    mk_render_pipeline(device, config, render_pipeline_layout, &shader, sample_count)
+    let render_pipeline_layout = &mk_pipeline_layout(device, &layouts.gui, screen_size_layout);
+    mk_render_pipeline(device, config, render_pipeline_layout, &shader, sample_count, depth)
 }
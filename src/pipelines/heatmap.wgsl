@@ -0,0 +1,35 @@
+// Maps the overdraw counts onto the surface through a colour ramp.
+
+@group(0) @binding(0)
+var counts: texture_2d<f32>;
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> @builtin(position) vec4<f32> {
+    // A single triangle covering the whole screen
+    let x = f32((vertex_index << 1u) & 2u);
+    let y = f32(vertex_index & 2u);
+    return vec4<f32>(x * 2.0 - 1.0, y * 2.0 - 1.0, 0.0, 1.0);
+}
+
+// Blue for a single fragment, then green, yellow, red and white at 2, 4, 8 and 16+
+fn ramp(count: f32) -> vec3<f32> {
+    var stops = array<vec3<f32>, 5>(
+        vec3<f32>(0.0, 0.0, 1.0),
+        vec3<f32>(0.0, 1.0, 0.0),
+        vec3<f32>(1.0, 1.0, 0.0),
+        vec3<f32>(1.0, 0.0, 0.0),
+        vec3<f32>(1.0, 1.0, 1.0),
+    );
+    let s = clamp(log2(count), 0.0, 4.0);
+    let i = min(u32(s), 3u);
+    return mix(stops[i], stops[i + 1u], s - f32(i));
+}
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let count = textureLoad(counts, vec2<i32>(position.xy), 0).r;
+    if count < 0.5 {
+        return vec4<f32>(0.0, 0.0, 0.0, 1.0);
+    }
+    return vec4<f32>(ramp(count), 1.0);
+}
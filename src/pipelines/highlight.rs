@@ -0,0 +1,318 @@
+//! Per-instance highlights of opaque `Instanced` draws, see [`Context::highlight`].
+//!
+//! Every draw of the basic pipeline binds a small uniform listing which of its instances to
+//! tint. The vertex shader compares `instance_index` against it, so hovering a single
+//! asteroid out of thousands neither needs a second draw nor touches the instance buffer.
+//! Draws without highlights share one empty uniform, the uniforms of highlighted draws are
+//! kept and only rewritten when their highlights change.
+//!
+//! [`Context::highlight`]: crate::context::Context::highlight
+
+use std::cell::{Cell, RefCell};
+
+use wgpu::util::DeviceExt;
+
+use crate::{data_structures::visibility::VisibilitySet, pick::PickId};
+
+/// Highlights a single draw compares against. If more instances of a draw are highlighted
+/// the whole draw is tinted instead.
+pub const MAX_HIGHLIGHTS: usize = 16;
+
+// Instance index matching every instance of a draw
+const ALL_INSTANCES: u32 = u32::MAX;
+
+/// Tints instances of the draw with pick id `id`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Highlight {
+    pub id: PickId,
+    /// Index of the instance, `None` tints all instances. Culled draws are mapped to the
+    /// index the instance was drawn at, instances culled this frame aren't tinted.
+    pub instance: Option<u32>,
+    /// RGB tint and its strength in alpha.
+    pub color: [f32; 4],
+}
+
+impl Highlight {
+    pub fn group(id: impl Into<PickId>, color: [f32; 4]) -> Self {
+        Self {
+            id: id.into(),
+            instance: None,
+            color,
+        }
+    }
+
+    pub fn instance(id: impl Into<PickId>, instance: u32, color: [f32; 4]) -> Self {
+        Self {
+            id: id.into(),
+            instance: Some(instance),
+            color,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+struct HighlightEntryRaw {
+    instance: u32,
+    _padding: [u32; 3],
+    color: [f32; 4],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub(crate) struct HighlightUniform {
+    count: u32,
+    _padding: [u32; 3],
+    entries: [HighlightEntryRaw; MAX_HIGHLIGHTS],
+}
+
+impl HighlightUniform {
+    fn zeroed() -> Self {
+        bytemuck::Zeroable::zeroed()
+    }
+
+    fn push(&mut self, instance: u32, color: [f32; 4]) {
+        self.entries[self.count as usize] = HighlightEntryRaw {
+            instance,
+            _padding: [0; 3],
+            color,
+        };
+        self.count += 1;
+    }
+
+    /// Entries of `highlights` for the draw `id`, `None` if it has none.
+    ///
+    /// A whole group highlight wins over single instances. Past [`MAX_HIGHLIGHTS`] the
+    /// first colour is applied to the whole draw. Instances are mapped through the draw's
+    /// `visibility`, as culling compacts its instance buffer.
+    pub(crate) fn for_draw(
+        highlights: &[Highlight],
+        id: PickId,
+        visibility: Option<&VisibilitySet>,
+    ) -> Option<Self> {
+        let matching: Vec<_> = highlights
+            .iter()
+            .filter(|highlight| highlight.id == id)
+            .collect();
+        let first = matching.first()?;
+        let mut uniform = Self::zeroed();
+        if let Some(group) = matching
+            .iter()
+            .find(|highlight| highlight.instance.is_none())
+        {
+            uniform.push(ALL_INSTANCES, group.color);
+        } else if matching.len() > MAX_HIGHLIGHTS {
+            log::debug!(
+                "{} instances of {:?} highlighted, tinting the whole draw.",
+                matching.len(),
+                id
+            );
+            uniform.push(ALL_INSTANCES, first.color);
+        } else {
+            for highlight in matching {
+                let Some(instance) = highlight.instance else {
+                    continue;
+                };
+                let drawn = match visibility {
+                    Some(visibility) => visibility.drawn_index(instance as usize),
+                    None => Some(instance as usize),
+                };
+                if let Some(drawn) = drawn {
+                    uniform.push(drawn as u32, highlight.color);
+                }
+            }
+            if uniform.count == 0 {
+                return None;
+            }
+        }
+        Some(uniform)
+    }
+}
+
+/// Entries of [`mk_highlight_bind_group_layout`].
+pub const HIGHLIGHT_ENTRIES: [wgpu::BindGroupLayoutEntry; 1] = [
+    wgpu::BindGroupLayoutEntry {
+        binding: 0,
+        visibility: wgpu::ShaderStages::VERTEX,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    },
+];
+
+pub fn mk_highlight_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        entries: &HIGHLIGHT_ENTRIES,
+        label: Some("highlight_bind_group_layout"),
+    })
+}
+
+/// Uniform buffer and bind group of one highlighted draw.
+#[derive(Debug)]
+struct HighlightSlot {
+    buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    // what `buffer` holds
+    uniform: HighlightUniform,
+}
+
+impl HighlightSlot {
+    fn new(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        uniform: HighlightUniform,
+    ) -> Self {
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Highlight Buffer"),
+            contents: bytemuck::bytes_of(&uniform),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+            label: Some("highlight_bind_group"),
+        });
+        Self {
+            buffer,
+            bind_group,
+            uniform,
+        }
+    }
+}
+
+/// Layout, the shared bind group of draws without highlights and the slots of highlighted
+/// draws.
+///
+/// The n-th highlighted draw of a frame reuses the slot of the n-th one of the previous
+/// frame, so a steady highlight neither allocates nor uploads anything.
+#[derive(Debug)]
+pub(crate) struct HighlightResources {
+    layout: wgpu::BindGroupLayout,
+    none: HighlightSlot,
+    slots: RefCell<Vec<HighlightSlot>>,
+    // slots handed out since `begin_frame`
+    used: Cell<usize>,
+}
+
+impl HighlightResources {
+    pub(crate) fn new(device: &wgpu::Device) -> Self {
+        let layout = mk_highlight_bind_group_layout(device);
+        let none = HighlightSlot::new(device, &layout, HighlightUniform::zeroed());
+        Self {
+            layout,
+            none,
+            slots: RefCell::new(Vec::new()),
+            used: Cell::new(0),
+        }
+    }
+
+    /// Bind group of draws without highlights.
+    pub(crate) fn none(&self) -> &wgpu::BindGroup {
+        &self.none.bind_group
+    }
+
+    /// Hands out the slots from the first again, call it before the draws of a frame.
+    pub(crate) fn begin_frame(&self) {
+        self.used.set(0);
+    }
+
+    /// Bind group of the draw `id` of `visibility`, `None` if [`HighlightResources::none`]
+    /// applies.
+    pub(crate) fn bind_group(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        highlights: &[Highlight],
+        id: PickId,
+        visibility: Option<&VisibilitySet>,
+    ) -> Option<wgpu::BindGroup> {
+        let uniform = HighlightUniform::for_draw(highlights, id, visibility)?;
+        let mut slots = self.slots.borrow_mut();
+        let idx = self.used.replace(self.used.get() + 1);
+        match slots.get_mut(idx) {
+            Some(slot) => {
+                if slot.uniform != uniform {
+                    queue.write_buffer(&slot.buffer, 0, bytemuck::bytes_of(&uniform));
+                    slot.uniform = uniform;
+                }
+                Some(slot.bind_group.clone())
+            }
+            None => {
+                let slot = HighlightSlot::new(device, &self.layout, uniform);
+                let bind_group = slot.bind_group.clone();
+                slots.push(slot);
+                Some(bind_group)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: [f32; 4] = [1.0, 0.0, 0.0, 0.5];
+    const BLUE: [f32; 4] = [0.0, 0.0, 1.0, 0.5];
+
+    fn instances(uniform: &HighlightUniform) -> Vec<u32> {
+        uniform.entries[..uniform.count as usize]
+            .iter()
+            .map(|entry| entry.instance)
+            .collect()
+    }
+
+    #[test]
+    fn only_entries_of_the_draw_are_uploaded() {
+        let highlights = [
+            Highlight::instance(1, 4, RED),
+            Highlight::instance(2, 7, BLUE),
+            Highlight::instance(1, 9, BLUE),
+        ];
+        let uniform = HighlightUniform::for_draw(&highlights, PickId(1), None).unwrap();
+        assert_eq!(instances(&uniform), [4, 9]);
+        assert_eq!(uniform.entries[1].color, BLUE);
+        assert!(HighlightUniform::for_draw(&highlights, PickId(3), None).is_none());
+    }
+
+    #[test]
+    fn group_highlight_wins_over_instances() {
+        let highlights = [Highlight::instance(1, 4, RED), Highlight::group(1, BLUE)];
+        let uniform = HighlightUniform::for_draw(&highlights, PickId(1), None).unwrap();
+        assert_eq!(instances(&uniform), [ALL_INSTANCES]);
+        assert_eq!(uniform.entries[0].color, BLUE);
+    }
+
+    #[test]
+    fn exceeding_the_budget_tints_the_whole_draw() {
+        let highlights: Vec<_> = (0..=MAX_HIGHLIGHTS as u32)
+            .map(|instance| Highlight::instance(1, instance, RED))
+            .collect();
+        let uniform = HighlightUniform::for_draw(&highlights, PickId(1), None).unwrap();
+        assert_eq!(instances(&uniform), [ALL_INSTANCES]);
+
+        let uniform =
+            HighlightUniform::for_draw(&highlights[..MAX_HIGHLIGHTS], PickId(1), None).unwrap();
+        assert_eq!(uniform.count as usize, MAX_HIGHLIGHTS);
+    }
+
+    #[test]
+    fn culled_draws_tint_the_drawn_index() {
+        let highlights = [
+            Highlight::instance(1, 5, RED),
+            Highlight::instance(1, 2, BLUE),
+        ];
+        // only instances 1 and 5 of 8 survived culling, compacted to 0 and 1
+        let visibility = VisibilitySet::from_visible(8, [1, 5]);
+        let uniform = HighlightUniform::for_draw(&highlights, PickId(1), Some(&visibility));
+        assert_eq!(instances(&uniform.unwrap()), [1]);
+
+        let culled = [Highlight::instance(1, 2, BLUE)];
+        assert!(HighlightUniform::for_draw(&culled, PickId(1), Some(&visibility)).is_none());
+    }
+}
@@ -2,7 +2,7 @@ use wgpu::util::DeviceExt;
 
 use crate::data_structures::{
     model::{Model, ModelVertex, Vertex},
-    texture,
+    texture::DepthConfig,
 };
 
 #[derive(Debug)]
@@ -19,12 +19,21 @@ impl LightResources {
         light_uniform: LightUniform,
         model: Option<Model>,
         device: &wgpu::Device,
+    ) -> Self {
+        Self::with_layout(light_uniform, model, device, &mk_bind_group_layout(device))
+    }
+
+    /// Like [`LightResources::new`] with the cached layout of [`crate::context::Layouts`].
+    pub fn with_layout(
+        light_uniform: LightUniform,
+        model: Option<Model>,
+        device: &wgpu::Device,
+        light_bind_group_layout: &wgpu::BindGroupLayout,
     ) -> Self {
         let light_buffer = mk_buffer(&device, light_uniform);
-        let light_bind_group_layout = mk_bind_group_layout(&device);
         let light_bind_group = mk_bind_group(
             &device,
-            &light_bind_group_layout,
+            light_bind_group_layout,
             light_buffer.as_entire_binding(),
         );
         Self {
@@ -57,18 +66,23 @@ fn mk_buffer(device: &wgpu::Device, light_uniform: LightUniform) -> wgpu::Buffer
     })
 }
 
-fn mk_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+/// Entries of the [`LightUniform`] bind group layout.
+pub const LIGHT_ENTRIES: [wgpu::BindGroupLayoutEntry; 1] = [
+    wgpu::BindGroupLayoutEntry {
+        binding: 0,
+        visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    },
+];
+
+pub(crate) fn mk_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
     device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-        entries: &[wgpu::BindGroupLayoutEntry {
-            binding: 0,
-            visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
-            ty: wgpu::BindingType::Buffer {
-                ty: wgpu::BufferBindingType::Uniform,
-                has_dynamic_offset: false,
-                min_binding_size: None,
-            },
-            count: None,
-        }],
+        entries: &LIGHT_ENTRIES,
         label: None,
     })
 }
@@ -94,6 +108,7 @@ pub fn mk_light_pipeline(
     light_bind_group_layout: &wgpu::BindGroupLayout,
     camera_bind_group_layout: &wgpu::BindGroupLayout,
     sample_count: u32,
+    depth: DepthConfig,
 ) -> wgpu::RenderPipeline {
     let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
         label: Some("Light Pipeline Layout"),
@@ -107,13 +122,14 @@ pub fn mk_light_pipeline(
     crate::pipelines::basic::mk_render_pipeline(
         &device,
         wgpu::FrontFace::Ccw,
+        Some(wgpu::Face::Back),
         &layout,
         config.format,
         Some(wgpu::BlendState {
             alpha: wgpu::BlendComponent::REPLACE,
             color: wgpu::BlendComponent::REPLACE,
         }),
-        Some(texture::Texture::DEPTH_FORMAT),
+        Some(depth),
         &[ModelVertex::desc()],
         shader,
         sample_count,
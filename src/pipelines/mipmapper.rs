@@ -5,7 +5,7 @@
 //! with [`Mipmapper::new`] and call [`Mipmapper::generate_mipmaps`] for each
 //! texture that needs a full mip chain.
 
-use anyhow::bail;
+use crate::error::{Error, Result};
 
 /// Generates a full mip chain for a texture by blitting each level from the
 /// previous one through a fullscreen-triangle render pass.
@@ -50,6 +50,7 @@ impl Mipmapper {
             ..Default::default()
         });
 
+        crate::profiling::record_pipeline();
         let blit_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
             label: Some("blit mipmap pipeline"),
             layout: Some(&pipeline_layout),
@@ -102,10 +103,14 @@ impl Mipmapper {
         device: &wgpu::Device,
         queue: &wgpu::Queue,
         texture: &wgpu::Texture,
-    ) -> anyhow::Result<()> {
+    ) -> Result<()> {
         match texture.format() {
             wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb => {}
-            _ => bail!("Unsupported mipmap format {:?}", texture.format()),
+            format => {
+                return Err(Error::Unsupported {
+                    feature: format!("mipmap generation for {format:?}"),
+                });
+            }
         }
 
         if texture.mip_level_count() <= 1 {
@@ -6,3 +6,10 @@ pub mod transparent;
 pub mod terrain;
 pub mod pick_gui;
 pub mod mipmapper;
+pub mod overlay;
+pub mod overdraw;
+pub mod scene_depth;
+pub mod highlight;
+pub mod compat;
+pub mod tonemap;
+pub mod spinner;
@@ -0,0 +1,427 @@
+//! Debug visualisation of fragment overdraw, see [`crate::context::DebugRender::Overdraw`].
+//!
+//! Opaque, transparent and terrain batches are drawn with additive blending into an `R16Float` target
+//! where every fragment adds `1.0`, so each texel ends up holding the number of fragments
+//! shaded for it. A fullscreen pass then maps the counts through a colour ramp onto the
+//! surface and a readback of the target feeds [`OverdrawStats`].
+
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+};
+
+use crate::{
+    context::Layouts,
+    data_structures::{
+        instance::InstanceRaw,
+        model::{self, Vertex},
+        texture::DepthConfig,
+    },
+    pipelines::terrain,
+};
+
+pub const OVERDRAW_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R16Float;
+
+// Textures of the engine's terrain pipeline
+const TERRAIN_TEXTURES: usize = 8;
+
+/// Overdraw numbers of a single frame.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct OverdrawStats {
+    /// Mean fragments per covered pixel. Pixels nothing was drawn to are left out so an
+    /// empty sky doesn't hide expensive geometry.
+    pub average: f32,
+    /// Fragments shaded for the worst pixel.
+    pub max: f32,
+    /// Number of pixels at least one fragment was drawn to.
+    pub covered_pixels: u32,
+    /// Fragments shaded in total.
+    pub fragments: u64,
+    /// Share of `fragments` hidden behind others, all but one per covered pixel. At most this
+    /// much shading is saved by drawing front to back or culling occluded objects.
+    pub occluded_fraction: f32,
+}
+
+impl OverdrawStats {
+    pub(crate) fn from_counts(counts: impl IntoIterator<Item = f32>) -> Self {
+        let (sum, max, covered_pixels) = counts
+            .into_iter()
+            .filter(|&count| count > 0.0)
+            .fold((0.0f64, 0.0f32, 0u32), |(sum, max, covered), count| {
+                (sum + count as f64, max.max(count), covered + 1)
+            });
+        if covered_pixels == 0 {
+            return Self::default();
+        }
+        let fragments = sum.round() as u64;
+        Self {
+            average: (sum / covered_pixels as f64) as f32,
+            max,
+            covered_pixels,
+            fragments,
+            occluded_fraction: (fragments.saturating_sub(covered_pixels as u64) as f64
+                / fragments as f64) as f32,
+        }
+    }
+}
+
+/// Decodes an IEEE 754 half precision float as stored in [`OVERDRAW_FORMAT`] texels.
+pub(crate) fn f16_to_f32(bits: u16) -> f32 {
+    let sign = if bits & 0x8000 == 0 { 1.0 } else { -1.0 };
+    let exponent = ((bits >> 10) & 0x1f) as i32;
+    let mantissa = (bits & 0x3ff) as f32;
+    match exponent {
+        0 => sign * mantissa * 2f32.powi(-24),
+        0x1f if mantissa == 0.0 => sign * f32::INFINITY,
+        0x1f => f32::NAN,
+        _ => sign * (1.0 + mantissa / 1024.0) * 2f32.powi(exponent - 15),
+    }
+}
+
+/// Pipeline drawing `Instanced` batches additively into the overdraw target.
+///
+/// The layout matches the basic pipeline so batches can be drawn with `draw_model_instanced`.
+pub fn mk_overdraw_pipeline(
+    device: &wgpu::Device,
+    direction: wgpu::FrontFace,
+    layouts: &Layouts,
+) -> wgpu::RenderPipeline {
+    mk_counting_pipeline(device, direction, &layouts.diffuse_normal, layouts)
+}
+
+/// Pipeline drawing terrain batches additively into the overdraw target, its layout matches
+/// the terrain pipeline.
+pub fn mk_terrain_overdraw_pipeline(
+    device: &wgpu::Device,
+    layouts: &Layouts,
+) -> wgpu::RenderPipeline {
+    let terrain_layout = terrain::mk_bind_group_layout(device, TERRAIN_TEXTURES);
+    mk_counting_pipeline(device, wgpu::FrontFace::Ccw, &terrain_layout, layouts)
+}
+
+fn mk_counting_pipeline(
+    device: &wgpu::Device,
+    direction: wgpu::FrontFace,
+    material_layout: &wgpu::BindGroupLayout,
+    layouts: &Layouts,
+) -> wgpu::RenderPipeline {
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Overdraw Shader"),
+        source: wgpu::ShaderSource::Wgsl(include_str!("overdraw.wgsl").into()),
+    });
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Overdraw Pipeline Layout"),
+        bind_group_layouts: &[
+            Some(material_layout),
+            Some(&layouts.camera),
+            Some(&layouts.light),
+        ],
+        ..Default::default()
+    });
+    let additive = wgpu::BlendComponent {
+        src_factor: wgpu::BlendFactor::One,
+        dst_factor: wgpu::BlendFactor::One,
+        operation: wgpu::BlendOperation::Add,
+    };
+    crate::profiling::record_pipeline();
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Overdraw Pipeline"),
+        layout: Some(&layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: Some("vs_main"),
+            buffers: &[model::ModelVertex::desc(), InstanceRaw::desc()],
+            compilation_options: Default::default(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: Some("fs_main"),
+            targets: &[Some(wgpu::ColorTargetState {
+                format: OVERDRAW_FORMAT,
+                blend: Some(wgpu::BlendState {
+                    color: additive,
+                    alpha: additive,
+                }),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+            compilation_options: Default::default(),
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: direction,
+            cull_mode: Some(wgpu::Face::Back),
+            polygon_mode: wgpu::PolygonMode::Fill,
+            unclipped_depth: false,
+            conservative: false,
+        },
+        // Without depth testing every rasterised fragment is counted
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview_mask: None,
+        cache: None,
+    })
+}
+
+pub fn mk_heatmap_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        entries: &[wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                multisampled: false,
+                view_dimension: wgpu::TextureViewDimension::D2,
+                sample_type: wgpu::TextureSampleType::Float { filterable: false },
+            },
+            count: None,
+        }],
+        label: Some("heatmap_bind_group_layout"),
+    })
+}
+
+/// Fullscreen pass colouring the surface by the overdraw counts. Draw with three vertices.
+pub fn mk_heatmap_pipeline(
+    device: &wgpu::Device,
+    config: &wgpu::SurfaceConfiguration,
+    bind_group_layout: &wgpu::BindGroupLayout,
+    sample_count: u32,
+    depth: DepthConfig,
+) -> wgpu::RenderPipeline {
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Heatmap Shader"),
+        source: wgpu::ShaderSource::Wgsl(include_str!("heatmap.wgsl").into()),
+    });
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Heatmap Pipeline Layout"),
+        bind_group_layouts: &[Some(bind_group_layout)],
+        ..Default::default()
+    });
+    crate::profiling::record_pipeline();
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Heatmap Pipeline"),
+        layout: Some(&layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: Some("vs_main"),
+            buffers: &[],
+            compilation_options: Default::default(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: Some("fs_main"),
+            targets: &[Some(wgpu::ColorTargetState {
+                format: config.format,
+                blend: Some(wgpu::BlendState::REPLACE),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+            compilation_options: Default::default(),
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: depth.format,
+            depth_write_enabled: Some(false),
+            depth_compare: Some(wgpu::CompareFunction::Always),
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState {
+            count: sample_count,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        multiview_mask: None,
+        cache: None,
+    })
+}
+
+/// GPU state of the overdraw debug mode, created on first use.
+#[derive(Debug)]
+pub(crate) struct OverdrawResources {
+    pub(crate) size: [u32; 2],
+    pub(crate) sample_count: u32,
+    texture: wgpu::Texture,
+    pub(crate) view: wgpu::TextureView,
+    pub(crate) pipeline: wgpu::RenderPipeline,
+    pub(crate) pipeline_cw: wgpu::RenderPipeline,
+    pub(crate) pipeline_terrain: wgpu::RenderPipeline,
+    pub(crate) heatmap: wgpu::RenderPipeline,
+    pub(crate) heatmap_bind_group: wgpu::BindGroup,
+    readback: wgpu::Buffer,
+    padded_bytes_per_row: u32,
+    // Set between queueing a copy and reading the mapped buffer back
+    in_flight: bool,
+    // Set by the map callback once the readback can be read
+    mapped: Arc<AtomicBool>,
+    // Set by the map callback if mapping failed, the next copy may be recorded then
+    failed: Arc<AtomicBool>,
+}
+
+impl OverdrawResources {
+    pub(crate) fn new(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        layouts: &Layouts,
+        sample_count: u32,
+        depth: DepthConfig,
+    ) -> Self {
+        let size = [config.width.max(1), config.height.max(1)];
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Overdraw Target"),
+            size: wgpu::Extent3d {
+                width: size[0],
+                height: size[1],
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: OVERDRAW_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let heatmap_layout = mk_heatmap_bind_group_layout(device);
+        let heatmap_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &heatmap_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&view),
+            }],
+            label: Some("heatmap_bind_group"),
+        });
+        let padded_bytes_per_row = (size[0] * 2).div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT)
+            * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let readback = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Overdraw Readback Buffer"),
+            size: (padded_bytes_per_row * size[1]) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        Self {
+            size,
+            sample_count,
+            texture,
+            view,
+            pipeline: mk_overdraw_pipeline(device, wgpu::FrontFace::Ccw, layouts),
+            pipeline_cw: mk_overdraw_pipeline(device, wgpu::FrontFace::Cw, layouts),
+            pipeline_terrain: mk_terrain_overdraw_pipeline(device, layouts),
+            heatmap: mk_heatmap_pipeline(device, config, &heatmap_layout, sample_count, depth),
+            heatmap_bind_group,
+            readback,
+            padded_bytes_per_row,
+            in_flight: false,
+            mapped: Arc::new(AtomicBool::new(false)),
+            failed: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Copies the counts into the readback buffer unless the previous copy wasn't read yet.
+    ///
+    /// Returns whether a copy was recorded and [`OverdrawResources::map_readback`] has to be
+    /// called once `encoder` is submitted.
+    pub(crate) fn copy_to_readback(&mut self, encoder: &mut wgpu::CommandEncoder) -> bool {
+        if self.in_flight {
+            return false;
+        }
+        encoder.copy_texture_to_buffer(
+            wgpu::TexelCopyTextureInfo {
+                aspect: wgpu::TextureAspect::All,
+                texture: &self.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            wgpu::TexelCopyBufferInfo {
+                buffer: &self.readback,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(self.padded_bytes_per_row),
+                    rows_per_image: Some(self.size[1]),
+                },
+            },
+            wgpu::Extent3d {
+                width: self.size[0],
+                height: self.size[1],
+                depth_or_array_layers: 1,
+            },
+        );
+        self.in_flight = true;
+        true
+    }
+
+    pub(crate) fn map_readback(&self) {
+        let mapped = self.mapped.clone();
+        let failed = self.failed.clone();
+        self.readback
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| match result {
+                Ok(()) => mapped.store(true, Ordering::Release),
+                Err(e) => {
+                    log::warn!("Overdraw readback failed: {}", e);
+                    failed.store(true, Ordering::Release);
+                }
+            });
+    }
+
+    /// Stats of the last frame that finished reading back, `None` while it's still in flight.
+    ///
+    /// Readbacks complete asynchronously so the numbers usually lag a frame or two behind.
+    pub(crate) fn take_stats(&mut self) -> Option<OverdrawStats> {
+        // Otherwise no copy would be recorded again
+        if self.failed.swap(false, Ordering::Acquire) {
+            self.in_flight = false;
+        }
+        if !self.mapped.swap(false, Ordering::Acquire) {
+            return None;
+        }
+        let row_bytes = self.size[0] as usize * 2;
+        let stats = {
+            let data = self.readback.slice(..).get_mapped_range();
+            OverdrawStats::from_counts(
+                data.chunks(self.padded_bytes_per_row as usize)
+                    .take(self.size[1] as usize)
+                    .flat_map(|row| row[..row_bytes].chunks_exact(2))
+                    .map(|texel| f16_to_f32(u16::from_le_bytes([texel[0], texel[1]]))),
+            )
+        };
+        self.readback.unmap();
+        self.in_flight = false;
+        Some(stats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_half_floats() {
+        assert_eq!(f16_to_f32(0x0000), 0.0);
+        assert_eq!(f16_to_f32(0x3c00), 1.0);
+        assert_eq!(f16_to_f32(0x4000), 2.0);
+        assert_eq!(f16_to_f32(0x4b00), 14.0);
+        assert_eq!(f16_to_f32(0xc000), -2.0);
+        assert_eq!(f16_to_f32(0x0001), 2f32.powi(-24));
+        assert_eq!(f16_to_f32(0x7c00), f32::INFINITY);
+        assert!(f16_to_f32(0x7e00).is_nan());
+    }
+
+    #[test]
+    fn stats_ignore_uncovered_pixels() {
+        let stats = OverdrawStats::from_counts([0.0, 1.0, 0.0, 3.0, 2.0]);
+        assert_eq!(stats.covered_pixels, 3);
+        assert_eq!(stats.max, 3.0);
+        assert!((stats.average - 2.0).abs() < 1e-6);
+        assert_eq!(stats.fragments, 6);
+        assert!((stats.occluded_fraction - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn empty_frame_has_zero_stats() {
+        assert_eq!(
+            OverdrawStats::from_counts([0.0; 4]),
+            OverdrawStats::default()
+        );
+    }
+}
@@ -0,0 +1,324 @@
+//! Fullscreen tint drawn over the 3D scene, used to dim and blur the scene while the app is
+//! paused, see [`Context::pause_overlay`].
+//!
+//! Blurring samples the scene, which can't be read while it's the colour attachment. The scene
+//! pass is split like for the [scene depth](super::scene_depth) and the target is copied into a
+//! texture of the same format first, so the target needs `COPY_SRC`.
+//!
+//! [`Context::pause_overlay`]: crate::context::Context::pause_overlay
+
+use std::{
+    cell::{Cell, RefCell},
+    collections::BTreeMap,
+};
+
+use wgpu::util::DeviceExt;
+
+use crate::{data_structures::texture::DepthConfig, window::WindowId};
+
+/// Colour of the fullscreen overlay. Alpha controls how strongly the scene is covered.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct OverlayUniform {
+    pub color: [f32; 4],
+    /// Blur radius in pixels, only the blurring pipeline reads it.
+    pub blur_radius: f32,
+    _padding: [f32; 3],
+}
+
+impl OverlayUniform {
+    pub fn new(color: wgpu::Color, blur_radius: f32) -> Self {
+        Self {
+            blur_radius,
+            ..color.into()
+        }
+    }
+}
+
+impl From<wgpu::Color> for OverlayUniform {
+    fn from(color: wgpu::Color) -> Self {
+        Self {
+            color: [
+                color.r as f32,
+                color.g as f32,
+                color.b as f32,
+                color.a as f32,
+            ],
+            blur_radius: 0.0,
+            _padding: [0.0; 3],
+        }
+    }
+}
+
+/// Bind group layout for the overlay colour uniform.
+pub fn mk_overlay_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        entries: &[wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        }],
+        label: Some("overlay_bind_group_layout"),
+    })
+}
+
+/// Bind group wrapping an [`OverlayUniform`] buffer.
+pub fn mk_overlay_bind_group(
+    device: &wgpu::Device,
+    buffer: &wgpu::Buffer,
+    layout: &wgpu::BindGroupLayout,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout,
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: buffer.as_entire_binding(),
+        }],
+        label: Some("overlay_bind_group"),
+    })
+}
+
+/// Bind group layout of the scene copy the blurring pipeline samples.
+pub fn mk_overlay_source_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            },
+        ],
+        label: Some("overlay_source_bind_group_layout"),
+    })
+}
+
+/**
+ * Pipeline blending a single colour over the whole screen.
+ *
+ * Draw it with three vertices and no vertex buffers. It ignores and keeps the depth
+ * buffer so everything rendered afterwards (e.g. the GUI) is unaffected.
+ */
+pub fn mk_overlay_pipeline(
+    device: &wgpu::Device,
+    config: &wgpu::SurfaceConfiguration,
+    sample_count: u32,
+    depth: DepthConfig,
+) -> wgpu::RenderPipeline {
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Overlay Pipeline Layout"),
+        bind_group_layouts: &[Some(&mk_overlay_bind_group_layout(device))],
+        ..Default::default()
+    });
+    mk_fullscreen_pipeline(device, &layout, "fs_main", config, sample_count, depth)
+}
+
+/**
+ * Pipeline replacing the screen with a blurred copy of the scene, tinted like
+ * [`mk_overlay_pipeline`].
+ *
+ * Binds the [`OverlayUniform`] at group 0 and the scene copy at group 1, see
+ * [`mk_overlay_source_bind_group_layout`].
+ */
+pub fn mk_overlay_blur_pipeline(
+    device: &wgpu::Device,
+    config: &wgpu::SurfaceConfiguration,
+    sample_count: u32,
+    depth: DepthConfig,
+) -> wgpu::RenderPipeline {
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Overlay Blur Pipeline Layout"),
+        bind_group_layouts: &[
+            Some(&mk_overlay_bind_group_layout(device)),
+            Some(&mk_overlay_source_bind_group_layout(device)),
+        ],
+        ..Default::default()
+    });
+    mk_fullscreen_pipeline(device, &layout, "fs_blur", config, sample_count, depth)
+}
+
+fn mk_fullscreen_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    fragment_entry: &str,
+    config: &wgpu::SurfaceConfiguration,
+    sample_count: u32,
+    depth: DepthConfig,
+) -> wgpu::RenderPipeline {
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Overlay Shader"),
+        source: wgpu::ShaderSource::Wgsl(include_str!("overlay.wgsl").into()),
+    });
+    crate::profiling::record_pipeline();
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Overlay Pipeline"),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: Some("vs_main"),
+            buffers: &[],
+            compilation_options: Default::default(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: Some(fragment_entry),
+            targets: &[Some(wgpu::ColorTargetState {
+                format: config.format,
+                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+            compilation_options: Default::default(),
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: None,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            unclipped_depth: false,
+            conservative: false,
+        },
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: depth.format,
+            depth_write_enabled: Some(false),
+            depth_compare: Some(wgpu::CompareFunction::Always),
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState {
+            count: sample_count,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        multiview_mask: None,
+        cache: None,
+    })
+}
+
+/// Copy of a window's scene the blurring pipeline samples.
+#[derive(Debug)]
+struct OverlaySource {
+    texture: wgpu::Texture,
+    bind_group: wgpu::BindGroup,
+}
+
+/// GPU state of the pause overlay, created once and rewritten only when the overlay changes,
+/// see [`crate::context::Context`].
+#[derive(Debug)]
+pub(crate) struct OverlayResources {
+    buffer: wgpu::Buffer,
+    pub(crate) bind_group: wgpu::BindGroup,
+    // Last written to `buffer`
+    uniform: Cell<OverlayUniform>,
+    source_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    // Recreated once the size or format of a window's target changes
+    sources: RefCell<BTreeMap<WindowId, OverlaySource>>,
+}
+
+impl OverlayResources {
+    pub(crate) fn new(device: &wgpu::Device, layout: &wgpu::BindGroupLayout) -> Self {
+        let uniform = OverlayUniform::from(wgpu::Color::TRANSPARENT);
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Overlay Buffer"),
+            contents: bytemuck::bytes_of(&uniform),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        Self {
+            bind_group: mk_overlay_bind_group(device, &buffer, layout),
+            buffer,
+            uniform: Cell::new(uniform),
+            source_layout: mk_overlay_source_bind_group_layout(device),
+            sampler: device.create_sampler(&wgpu::SamplerDescriptor {
+                label: Some("Overlay Source Sampler"),
+                mag_filter: wgpu::FilterMode::Linear,
+                min_filter: wgpu::FilterMode::Linear,
+                ..Default::default()
+            }),
+            sources: RefCell::new(BTreeMap::new()),
+        }
+    }
+
+    /// Writes `uniform` unless the buffer already holds it.
+    pub(crate) fn write(&self, queue: &wgpu::Queue, uniform: OverlayUniform) {
+        if self.uniform.replace(uniform) != uniform {
+            crate::profiling::write_buffer(queue, &self.buffer, 0, bytemuck::bytes_of(&uniform));
+        }
+    }
+
+    /// Records a copy of `scene`, the target of `window`, and returns the bind group sampling
+    /// it. The copy is created on first use and recreated with the target's size or format.
+    pub(crate) fn copy_source(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        window: WindowId,
+        scene: &wgpu::Texture,
+    ) -> wgpu::BindGroup {
+        let mut sources = self.sources.borrow_mut();
+        let outdated = sources.get(&window).is_none_or(|source| {
+            source.texture.size() != scene.size() || source.texture.format() != scene.format()
+        });
+        if outdated {
+            let texture = device.create_texture(&wgpu::TextureDescriptor {
+                label: Some("Overlay Source"),
+                size: scene.size(),
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: scene.format(),
+                usage: wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            });
+            let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                layout: &self.source_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(&self.sampler),
+                    },
+                ],
+                label: Some("overlay_source_bind_group"),
+            });
+            sources.insert(
+                window,
+                OverlaySource {
+                    texture,
+                    bind_group,
+                },
+            );
+        }
+        let source = &sources[&window];
+        encoder.copy_texture_to_texture(
+            scene.as_image_copy(),
+            source.texture.as_image_copy(),
+            scene.size(),
+        );
+        source.bind_group.clone()
+    }
+
+    /// Drops the scene copy of a closed window.
+    pub(crate) fn remove_window(&self, window: WindowId) {
+        self.sources.borrow_mut().remove(&window);
+    }
+}
@@ -0,0 +1,52 @@
+// Fullscreen tint blended over everything drawn so far, e.g. to dim a paused scene.
+// `fs_blur` replaces the screen with a blurred copy of the scene under the tint instead.
+
+struct Overlay {
+    // Straight (non-premultiplied) colour, alpha controls the strength
+    color: vec4<f32>,
+    // In pixels, read by `fs_blur` only
+    blur_radius: f32,
+}
+@group(0) @binding(0)
+var<uniform> overlay: Overlay;
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> @builtin(position) vec4<f32> {
+    // A single triangle covering the whole screen
+    let x = f32((vertex_index << 1u) & 2u);
+    let y = f32(vertex_index & 2u);
+    return vec4<f32>(x * 2.0 - 1.0, y * 2.0 - 1.0, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main() -> @location(0) vec4<f32> {
+    return overlay.color;
+}
+
+@group(1) @binding(0)
+var scene: texture_2d<f32>;
+@group(1) @binding(1)
+var scene_sampler: sampler;
+
+// Taps on each side of the centre along both axes
+const BLUR_TAPS: i32 = 4;
+
+@fragment
+fn fs_blur(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let size = vec2<f32>(textureDimensions(scene));
+    let uv = position.xy / size;
+    let step = overlay.blur_radius / f32(BLUR_TAPS) / size;
+    var sum = vec3<f32>(0.0);
+    var weights = 0.0;
+    for (var x = -BLUR_TAPS; x <= BLUR_TAPS; x++) {
+        for (var y = -BLUR_TAPS; y <= BLUR_TAPS; y++) {
+            let offset = vec2<f32>(f32(x), f32(y)) / f32(BLUR_TAPS);
+            // Gaussian falling to about 2% at the radius
+            let weight = exp(-2.0 * dot(offset, offset));
+            let tap = uv + vec2<f32>(f32(x), f32(y)) * step;
+            sum += textureSampleLevel(scene, scene_sampler, tap, 0.0).rgb * weight;
+            weights += weight;
+        }
+    }
+    return vec4<f32>(mix(sum / weights, overlay.color.rgb, overlay.color.a), 1.0);
+}
@@ -1,17 +1,15 @@
-use wgpu::{BindGroupLayout, PipelineLayout, ShaderModule};
+use wgpu::{PipelineLayout, ShaderModule};
 
-use crate::{data_structures::{
+use crate::{context::Layouts, data_structures::{
     instance::InstanceRaw,
     model::{self, Vertex},
-}, resources::pick::pick_layout};
+    texture::DepthConfig,
+}};
 
-fn pick_render_pipeline_layout(
-    device: &wgpu::Device,
-    camera_bind_group_layout: &BindGroupLayout,
-) -> PipelineLayout {
+fn pick_render_pipeline_layout(device: &wgpu::Device, layouts: &Layouts) -> PipelineLayout {
     device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
         label: Some("Render Pipeline Layout (For picking)"),
-        bind_group_layouts: &[Some(&pick_layout(device)), Some(&camera_bind_group_layout)],
+        bind_group_layouts: &[Some(&layouts.pick), Some(&layouts.camera)],
         ..Default::default()
     })
 }
@@ -26,13 +24,17 @@ fn pick_shader(device: &wgpu::Device) -> ShaderModule {
 
 pub fn mk_pick_pipeline(
     device: &wgpu::Device,
-    camera_bind_group_layout: &BindGroupLayout,
+    direction: wgpu::FrontFace,
+    cull_mode: Option<wgpu::Face>,
+    layouts: &Layouts,
+    depth: DepthConfig,
 ) -> wgpu::RenderPipeline {
-    let render_pipeline_layout = pick_render_pipeline_layout(device, camera_bind_group_layout);
+    let render_pipeline_layout = pick_render_pipeline_layout(device, layouts);
 
     let shader = pick_shader(device);
 
     let color_format = wgpu::TextureFormat::R32Uint;
+    crate::profiling::record_pipeline();
     device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
         cache: None,
         label: Some("Pick Pipeline"),
@@ -56,8 +58,8 @@ pub fn mk_pick_pipeline(
         primitive: wgpu::PrimitiveState {
             topology: wgpu::PrimitiveTopology::TriangleList,
             strip_index_format: None,
-            front_face: wgpu::FrontFace::Ccw,
-            cull_mode: Some(wgpu::Face::Back),
+            front_face: direction,
+            cull_mode,
             // Setting this to anything other than Fill requires Features::NON_FILL_POLYGON_MODE
             polygon_mode: wgpu::PolygonMode::Fill,
             // Requires Features::DEPTH_CLIP_CONTROL
@@ -66,9 +68,9 @@ pub fn mk_pick_pipeline(
             conservative: false,
         },
         depth_stencil: Some(wgpu::DepthStencilState {
-            format: wgpu::TextureFormat::Depth24Plus,
+            format: depth.format,
             depth_write_enabled: Some(true),
-            depth_compare: Some(wgpu::CompareFunction::Less),
+            depth_compare: Some(depth.compare(wgpu::CompareFunction::Less)),
             stencil: wgpu::StencilState::default(),
             bias: wgpu::DepthBiasState::default(),
         }),
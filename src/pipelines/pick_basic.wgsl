@@ -30,12 +30,14 @@ struct VertexOutput {
     @location(1) tangent_position: vec3<f32>,
     @location(2) tangent_light_position: vec3<f32>,
     @location(3) tangent_view_position: vec3<f32>,
+    @location(4) @interpolate(flat) instance_index: u32,
 }
 
 @vertex
 fn vs_main(
     model: VertexInput,
     instance: InstanceInput,
+    @builtin(instance_index) instance_index: u32,
 ) -> VertexOutput {
     let model_matrix = mat4x4<f32>(
         instance.model_matrix_0,
@@ -66,11 +68,13 @@ fn vs_main(
     out.tex_coords = model.tex_coords;
     out.tangent_position = tangent_matrix * world_position.xyz;
     out.tangent_view_position = tangent_matrix * camera.view_pos.xyz;
+    out.instance_index = instance_index;
     return out;
 }
 
 // Fragment shader
 
+// The id and whether the instance index is added to it
 struct PickUniforms {
     id: vec4<u32>,
 };
@@ -80,5 +84,5 @@ var<uniform> pickUniforms: PickUniforms;
 
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) u32 {
-    return pickUniforms.id[0];
+    return pickUniforms.id[0] + select(0u, in.instance_index, pickUniforms.id[1] != 0u);
 }
@@ -1,42 +1,36 @@
-use crate::pipelines::gui::Vertex;
+use crate::{
+    context::Layouts, data_structures::texture::DepthConfig, pipelines::gui::Vertex,
+    resources::pick::PICK_ENTRIES,
+};
 
 fn render_pipeline_layout(
     device: &wgpu::Device,
-    texture_bind_group_layout: wgpu::BindGroupLayout,
+    texture_bind_group_layout: &wgpu::BindGroupLayout,
     screen_size_bind_group_layout: &wgpu::BindGroupLayout,
 ) -> wgpu::PipelineLayout {
     device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
         label: Some("Render Pipeline Layout (For picking)"),
-        bind_group_layouts: &[Some(&texture_bind_group_layout), Some(screen_size_bind_group_layout)],
+        bind_group_layouts: &[Some(texture_bind_group_layout), Some(screen_size_bind_group_layout)],
         ..Default::default()
     })
 }
 
 pub fn mk_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
     device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-        entries: &[wgpu::BindGroupLayoutEntry {
-            binding: 0,
-            visibility: wgpu::ShaderStages::FRAGMENT,
-            ty: wgpu::BindingType::Buffer {
-                ty: wgpu::BufferBindingType::Uniform,
-                has_dynamic_offset: false,
-                min_binding_size: None,
-            },
-            count: None,
-        }],
+        entries: &PICK_ENTRIES,
         label: Some("pick_bind_group_layout"),
     })
 }
 
-pub fn mk_gui_pick_pipeline(device: &wgpu::Device, screen_size_layout: &wgpu::BindGroupLayout) -> wgpu::RenderPipeline {
-    let texture_bind_group_layout = mk_bind_group_layout(device);
+pub fn mk_gui_pick_pipeline(device: &wgpu::Device, layouts: &Layouts, screen_size_layout: &wgpu::BindGroupLayout, depth: DepthConfig) -> wgpu::RenderPipeline {
     let color_format = wgpu::TextureFormat::R32Uint;
     let shader = wgpu::ShaderModuleDescriptor {
         label: Some("Normal Shader"),
         source: wgpu::ShaderSource::Wgsl(include_str!("pick_gui.wgsl").into()),
     };
     let shader = device.create_shader_module(shader);
-    let render_pipeline_layout = render_pipeline_layout(device, texture_bind_group_layout, screen_size_layout);
+    let render_pipeline_layout = render_pipeline_layout(device, &layouts.pick, screen_size_layout);
+    crate::profiling::record_pipeline();
     device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
         label: Some("Menu Pick Render Pipeline"),
         layout: Some(&render_pipeline_layout),
@@ -70,9 +64,9 @@ pub fn mk_gui_pick_pipeline(device: &wgpu::Device, screen_size_layout: &wgpu::Bi
             conservative: false,
         },
         depth_stencil: Some(wgpu::DepthStencilState {
-            format: wgpu::TextureFormat::Depth24Plus,
+            format: depth.format,
             depth_write_enabled: Some(true),
-            depth_compare: Some(wgpu::CompareFunction::Less),
+            depth_compare: Some(depth.compare(wgpu::CompareFunction::Less)),
             stencil: wgpu::StencilState::default(),
             bias: wgpu::DepthBiasState::default(),
         }),
@@ -0,0 +1,168 @@
+//! Copy of the opaque scene depth, sampled by transparent surfaces to fade out where they
+//! intersect geometry, see [`TransparencyUniform::fade_distance`].
+//!
+//! The depth texture is still the depth attachment of the main pass, so it can't be bound
+//! while transparents are drawn. Instead the main pass is split after the opaque draws and a
+//! fullscreen pass copies the depth into an `R32Float` texture. Unlike the depth texture
+//! itself the copy is a plain float texture, which WebGL can sample as well.
+//!
+//! [`TransparencyUniform::fade_distance`]: crate::pipelines::transparent::TransparencyUniform::fade_distance
+
+pub const SCENE_DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R32Float;
+
+/// Bind group layout of the depth texture being copied.
+fn mk_copy_bind_group_layout(device: &wgpu::Device, multisampled: bool) -> wgpu::BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        entries: &[wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Depth,
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled,
+            },
+            count: None,
+        }],
+        label: Some("scene_depth_copy_bind_group_layout"),
+    })
+}
+
+/// Fullscreen pipeline writing the depth of every pixel into a [`SCENE_DEPTH_FORMAT`] target.
+fn mk_copy_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    multisampled: bool,
+) -> wgpu::RenderPipeline {
+    let depth_texture = if multisampled {
+        "texture_depth_multisampled_2d"
+    } else {
+        "texture_depth_2d"
+    };
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Scene Depth Copy Shader"),
+        source: wgpu::ShaderSource::Wgsl(
+            include_str!("scene_depth.wgsl")
+                .replace("DEPTH_TEXTURE", depth_texture)
+                .into(),
+        ),
+    });
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Scene Depth Copy Pipeline Layout"),
+        bind_group_layouts: &[Some(layout)],
+        ..Default::default()
+    });
+    crate::profiling::record_pipeline();
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Scene Depth Copy Pipeline"),
+        layout: Some(&pipeline_layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: Some("vs_main"),
+            buffers: &[],
+            compilation_options: Default::default(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: Some("fs_main"),
+            targets: &[Some(wgpu::ColorTargetState {
+                format: SCENE_DEPTH_FORMAT,
+                blend: None,
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+            compilation_options: Default::default(),
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview_mask: None,
+        cache: None,
+    })
+}
+
+/// GPU state of the depth copy, created once transparents are drawn, see
+/// [`crate::context::Context`].
+#[derive(Debug)]
+pub(crate) struct SceneDepthResources {
+    pub(crate) size: [u32; 2],
+    pub(crate) sample_count: u32,
+    pub(crate) view: wgpu::TextureView,
+    layout: wgpu::BindGroupLayout,
+    // `None` where multisampled depth can't be read, e.g. WebGL
+    pipeline: Option<wgpu::RenderPipeline>,
+}
+
+impl SceneDepthResources {
+    pub(crate) fn new(device: &wgpu::Device, size: [u32; 2], sample_count: u32) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Scene Depth Copy"),
+            size: wgpu::Extent3d {
+                width: size[0],
+                height: size[1],
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: SCENE_DEPTH_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let multisampled = sample_count > 1;
+        let layout = mk_copy_bind_group_layout(device, multisampled);
+        let readable = !(cfg!(target_arch = "wasm32") && multisampled);
+        if !readable {
+            log::warn!("Multisampled depth can't be read on WebGL, transparents won't fade.");
+        }
+        Self {
+            size,
+            sample_count,
+            view: texture.create_view(&wgpu::TextureViewDescriptor::default()),
+            pipeline: readable.then(|| mk_copy_pipeline(device, &layout, multisampled)),
+            layout,
+        }
+    }
+
+    /// Records a pass copying `depth` into [`SceneDepthResources::view`]. Where depth can't be
+    /// read the copy is cleared to the depth of the `far` plane, which disables fading.
+    pub(crate) fn copy(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        depth: &wgpu::TextureView,
+        far: f32,
+    ) {
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Scene Depth Copy Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &self.view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color {
+                        r: far.into(),
+                        ..wgpu::Color::WHITE
+                    }),
+                    store: wgpu::StoreOp::Store,
+                },
+                depth_slice: None,
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+            ..Default::default()
+        });
+        let Some(pipeline) = &self.pipeline else {
+            return;
+        };
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(depth),
+            }],
+            label: Some("scene_depth_copy_bind_group"),
+        });
+        pass.set_pipeline(pipeline);
+        pass.set_bind_group(0, &bind_group, &[]);
+        pass.draw(0..3, 0..1);
+    }
+}
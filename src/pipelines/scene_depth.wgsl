@@ -0,0 +1,20 @@
+// Copies the depth of the opaque scene into a colour target transparent surfaces can read.
+// `DEPTH_TEXTURE` is replaced with the multisampled variant when MSAA is enabled.
+
+@group(0) @binding(0)
+var scene_depth: DEPTH_TEXTURE;
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> @builtin(position) vec4<f32> {
+    // A single triangle covering the whole screen
+    let x = f32((vertex_index << 1u) & 2u);
+    let y = f32(vertex_index & 2u);
+    return vec4<f32>(x * 2.0 - 1.0, y * 2.0 - 1.0, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    // The first sample of a multisampled texture is close enough for fading
+    let depth = textureLoad(scene_depth, vec2<i32>(position.xy), 0);
+    return vec4<f32>(depth, 0.0, 0.0, 1.0);
+}
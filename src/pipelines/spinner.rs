@@ -0,0 +1,114 @@
+//! Ring of dots drawn by the built-in loading screen, see [`LoadingScreen::Spinner`].
+//!
+//! [`LoadingScreen::Spinner`]: crate::loading::LoadingScreen::Spinner
+
+use crate::data_structures::texture::DepthConfig;
+
+/// Number of dots in the ring, draw six vertices for each of them as instances.
+pub const SPINNER_DOTS: u32 = 12;
+
+/// Frame size, animation time and progress of the spinner.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct SpinnerUniform {
+    /// Size of the frame in pixels, the ring is drawn at its centre.
+    pub size: [f32; 2],
+    /// Seconds since loading started.
+    pub time: f32,
+    /// Fraction of the assets loaded, negative while unknown.
+    pub progress: f32,
+    /// Colour of the dots, the alpha is scaled by the animation.
+    pub color: [f32; 4],
+}
+
+/// Bind group layout for the [`SpinnerUniform`].
+pub fn mk_spinner_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        entries: &[wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        }],
+        label: Some("spinner_bind_group_layout"),
+    })
+}
+
+/// Bind group wrapping a [`SpinnerUniform`] buffer.
+pub fn mk_spinner_bind_group(
+    device: &wgpu::Device,
+    buffer: &wgpu::Buffer,
+    layout: &wgpu::BindGroupLayout,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout,
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: buffer.as_entire_binding(),
+        }],
+        label: Some("spinner_bind_group"),
+    })
+}
+
+/**
+ * Pipeline drawing the spinner into the scene pass.
+ *
+ * Draw it with six vertices and [`SPINNER_DOTS`] instances and no vertex buffers. Like the
+ * pause overlay it ignores and keeps the depth buffer.
+ */
+pub fn mk_spinner_pipeline(
+    device: &wgpu::Device,
+    format: wgpu::TextureFormat,
+    sample_count: u32,
+    depth: DepthConfig,
+) -> wgpu::RenderPipeline {
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Spinner Shader"),
+        source: wgpu::ShaderSource::Wgsl(include_str!("spinner.wgsl").into()),
+    });
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Spinner Pipeline Layout"),
+        bind_group_layouts: &[Some(&mk_spinner_bind_group_layout(device))],
+        ..Default::default()
+    });
+    crate::profiling::record_pipeline();
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Spinner Pipeline"),
+        layout: Some(&layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: Some("vs_main"),
+            buffers: &[],
+            compilation_options: Default::default(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: Some("fs_main"),
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+            compilation_options: Default::default(),
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: depth.format,
+            depth_write_enabled: Some(false),
+            depth_compare: Some(wgpu::CompareFunction::Always),
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState {
+            count: sample_count,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        multiview_mask: None,
+        cache: None,
+    })
+}
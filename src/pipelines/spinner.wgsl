@@ -0,0 +1,70 @@
+// Ring of dots drawn by the loading screen while the flows are constructed.
+
+struct Spinner {
+    // Size of the frame in pixels
+    size: vec2<f32>,
+    // Seconds since loading started
+    time: f32,
+    // Fraction of the assets loaded, negative while no constructor reported progress
+    progress: f32,
+    // Straight (non-premultiplied) colour of the dots
+    color: vec4<f32>,
+}
+@group(0) @binding(0)
+var<uniform> spinner: Spinner;
+
+const DOTS: u32 = 12u;
+// Distance of the dots from the centre of the frame and their size, in pixels
+const RADIUS: f32 = 28.0;
+const DOT_RADIUS: f32 = 4.5;
+const TAU: f32 = 6.28318530718;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    // Position within the dot, the unit circle being its outline
+    @location(0) local: vec2<f32>,
+    @location(1) alpha: f32,
+}
+
+@vertex
+fn vs_main(
+    @builtin(vertex_index) vertex_index: u32,
+    @builtin(instance_index) dot: u32,
+) -> VertexOutput {
+    // Two triangles covering the dot
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, 1.0),
+    );
+    let corner = corners[vertex_index];
+    // Clockwise from twelve o'clock
+    let turn = f32(dot) / f32(DOTS);
+    let center = vec2<f32>(sin(turn * TAU), cos(turn * TAU)) * RADIUS;
+    let position = (center + corner * DOT_RADIUS) * 2.0 / spinner.size;
+
+    // The head goes round once a second and the dots behind it fade out
+    let behind = fract(spinner.time - turn);
+    let trail = pow(1.0 - behind, 3.0);
+    // Dots up to the reported progress stay lit
+    let loaded = select(0.2, 0.6, spinner.progress >= 0.0 && turn < spinner.progress);
+
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(position, 0.0, 1.0);
+    out.local = corner;
+    out.alpha = max(trail, loaded);
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let distance = length(in.local);
+    if distance > 1.0 {
+        discard;
+    }
+    let edge = 1.0 - smoothstep(0.75, 1.0, distance);
+    return vec4<f32>(spinner.color.rgb, spinner.color.a * in.alpha * edge);
+}
@@ -3,7 +3,7 @@ use wgpu::BindGroupLayout;
 use crate::data_structures::{
     instance::InstanceRaw,
     model::{ModelVertex, Vertex},
-    texture
+    texture::DepthConfig,
 };
 
 pub fn mk_bind_group_layout(device: &wgpu::Device, amount: usize) -> wgpu::BindGroupLayout {
@@ -67,7 +67,8 @@ pub fn mk_terrain_pipeline(
     camera_bind_group_layout: &BindGroupLayout,
     light_bind_group_layout: &BindGroupLayout,
     sample_count: u32,
-    amount: usize
+    amount: usize,
+    depth: DepthConfig,
 ) -> wgpu::RenderPipeline {
     let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
         label: Some("Normal Shader"),
@@ -82,6 +83,7 @@ pub fn mk_terrain_pipeline(
         light_bind_group_layout,
     );
 
+    crate::profiling::record_pipeline();
     device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
         label: Some("Terrain Pipeline"),
         layout: Some(&render_pipeline_layout),
@@ -114,9 +116,9 @@ pub fn mk_terrain_pipeline(
             conservative: false,
         },
         depth_stencil: Some(wgpu::DepthStencilState {
-            format: texture::Texture::DEPTH_FORMAT,
+            format: depth.format,
             depth_write_enabled: Some(true),
-            depth_compare: Some(wgpu::CompareFunction::Less),
+            depth_compare: Some(depth.compare(wgpu::CompareFunction::Less)),
             stencil: wgpu::StencilState::default(),
             bias: wgpu::DepthBiasState::default(),
         }),
@@ -0,0 +1,226 @@
+//! Optional HDR scene target, see [`Context::set_hdr`].
+//!
+//! With HDR the scene pipelines draw into an [`HDR_FORMAT`] texture of the frame's size
+//! instead of the surface, so bright lights and materials keep values above one. A final
+//! fullscreen pass maps them into the surface with the selected [`Tonemapping`]. Without HDR
+//! none of this is created and the scene draws to the surface directly.
+//!
+//! [`Context::set_hdr`]: crate::context::Context::set_hdr
+
+/// Colour format the scene is drawn in with HDR enabled.
+pub const HDR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+/// Curve mapping HDR colours into the displayable range.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Tonemapping {
+    /// Filmic curve with a toe and shoulder, keeps highlights from washing out.
+    #[default]
+    Aces,
+    /// `c / (1 + c)`, simple and desaturates less but flattens contrast.
+    Reinhard,
+}
+
+impl Tonemapping {
+    fn entry_point(self) -> &'static str {
+        match self {
+            Tonemapping::Aces => "fs_aces",
+            Tonemapping::Reinhard => "fs_reinhard",
+        }
+    }
+}
+
+fn mk_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        entries: &[wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        }],
+        label: Some("tonemap_bind_group_layout"),
+    })
+}
+
+/// Fullscreen pipeline tonemapping the HDR target into `format`. Draw with three vertices.
+pub fn mk_tonemap_pipeline(
+    device: &wgpu::Device,
+    format: wgpu::TextureFormat,
+    tonemapping: Tonemapping,
+    bind_group_layout: &wgpu::BindGroupLayout,
+) -> wgpu::RenderPipeline {
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Tonemap Shader"),
+        source: wgpu::ShaderSource::Wgsl(include_str!("tonemap.wgsl").into()),
+    });
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Tonemap Pipeline Layout"),
+        bind_group_layouts: &[Some(bind_group_layout)],
+        ..Default::default()
+    });
+    crate::profiling::record_pipeline();
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Tonemap Pipeline"),
+        layout: Some(&layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: Some("vs_main"),
+            buffers: &[],
+            compilation_options: Default::default(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: Some(tonemapping.entry_point()),
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: None,
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+            compilation_options: Default::default(),
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview_mask: None,
+        cache: None,
+    })
+}
+
+/// HDR colour target and the tonemap pass, created on the first HDR frame and recreated
+/// once the frame size, sample count or output changes.
+#[derive(Debug)]
+pub(crate) struct HdrResources {
+    pub(crate) size: [u32; 2],
+    pub(crate) sample_count: u32,
+    pub(crate) format: wgpu::TextureFormat,
+    pub(crate) tonemapping: Tonemapping,
+    // Resolved scene colour the tonemap pass reads
+    view: wgpu::TextureView,
+    msaa_view: Option<wgpu::TextureView>,
+    bind_group: wgpu::BindGroup,
+    pipeline: wgpu::RenderPipeline,
+}
+
+impl HdrResources {
+    /// HDR target of `size` tonemapped into a `format` output.
+    pub(crate) fn new(
+        device: &wgpu::Device,
+        size: [u32; 2],
+        sample_count: u32,
+        format: wgpu::TextureFormat,
+        tonemapping: Tonemapping,
+    ) -> Self {
+        let mk_texture = |label, sample_count, usage| {
+            device
+                .create_texture(&wgpu::TextureDescriptor {
+                    label: Some(label),
+                    size: wgpu::Extent3d {
+                        width: size[0],
+                        height: size[1],
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count,
+                    dimension: wgpu::TextureDimension::D2,
+                    format: HDR_FORMAT,
+                    usage,
+                    view_formats: &[],
+                })
+                .create_view(&wgpu::TextureViewDescriptor::default())
+        };
+        let view = mk_texture(
+            "HDR Scene Target",
+            1,
+            // Copied for the blurred pause overlay
+            wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_SRC,
+        );
+        let msaa_view = (sample_count > 1).then(|| {
+            mk_texture(
+                "HDR Scene MSAA Target",
+                sample_count,
+                wgpu::TextureUsages::RENDER_ATTACHMENT,
+            )
+        });
+        let layout = mk_bind_group_layout(device);
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&view),
+            }],
+            label: Some("tonemap_bind_group"),
+        });
+        Self {
+            size,
+            sample_count,
+            format,
+            tonemapping,
+            pipeline: mk_tonemap_pipeline(device, format, tonemapping, &layout),
+            view,
+            msaa_view,
+            bind_group,
+        }
+    }
+
+    /// Colour and resolve attachments the scene passes draw to.
+    pub(crate) fn attachments(&self) -> (&wgpu::TextureView, Option<&wgpu::TextureView>) {
+        (
+            self.msaa_view.as_ref().unwrap_or(&self.view),
+            self.msaa_view.as_ref().map(|_| &self.view),
+        )
+    }
+
+    /// Records the pass tonemapping the scene into `output`, a single sampled view.
+    pub(crate) fn tonemap(&self, encoder: &mut wgpu::CommandEncoder, output: &wgpu::TextureView) {
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Tonemap Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: output,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                },
+                depth_slice: None,
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+            ..Default::default()
+        });
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        pass.draw(0..3, 0..1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wgpu::naga;
+
+    #[test]
+    fn every_tonemapping_has_an_entry_point() {
+        let module = naga::front::wgsl::parse_str(include_str!("tonemap.wgsl")).unwrap();
+        naga::valid::Validator::new(
+            naga::valid::ValidationFlags::all(),
+            naga::valid::Capabilities::empty(),
+        )
+        .validate(&module)
+        .unwrap();
+        for tonemapping in [Tonemapping::Aces, Tonemapping::Reinhard] {
+            assert!(
+                module
+                    .entry_points
+                    .iter()
+                    .any(|entry| entry.name == tonemapping.entry_point()),
+                "{tonemapping:?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,32 @@
+// Maps the HDR scene colour into the displayable range of the surface. The surface is sRGB,
+// so the output stays linear.
+
+@group(0) @binding(0)
+var hdr_texture: texture_2d<f32>;
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> @builtin(position) vec4<f32> {
+    // A single triangle covering the whole screen
+    let x = f32((vertex_index << 1u) & 2u);
+    let y = f32(vertex_index & 2u);
+    return vec4<f32>(x * 2.0 - 1.0, y * 2.0 - 1.0, 0.0, 1.0);
+}
+
+fn load(position: vec4<f32>) -> vec4<f32> {
+    return textureLoad(hdr_texture, vec2<i32>(position.xy), 0);
+}
+
+@fragment
+fn fs_reinhard(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let hdr = load(position);
+    return vec4<f32>(hdr.rgb / (hdr.rgb + vec3<f32>(1.0)), hdr.a);
+}
+
+// Krzysztof Narkowicz's fit of the ACES filmic curve
+@fragment
+fn fs_aces(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let hdr = load(position);
+    let x = max(hdr.rgb, vec3<f32>(0.0));
+    let mapped = (x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14);
+    return vec4<f32>(clamp(mapped, vec3<f32>(0.0), vec3<f32>(1.0)), hdr.a);
+}
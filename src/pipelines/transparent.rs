@@ -1,4 +1,4 @@
-use crate::{data_structures::{instance::InstanceRaw, model::{ModelVertex, Vertex}, texture::Texture}, pipelines::basic::mk_render_pipeline, resources::texture::diffuse_normal_layout};
+use crate::{context::Layouts, data_structures::{instance::InstanceRaw, model::{ModelVertex, Vertex}, texture::DepthConfig}, pipelines::basic::mk_render_pipeline};
 
 /// Per-object transparency parameters sent to the transparent fragment shader.
 #[repr(C)]
@@ -8,6 +8,9 @@ pub struct TransparencyUniform {
     pub tint: [f32; 3],
     /// Opacity in `0.0..=1.0` (0 = fully transparent, 1 = fully opaque).
     pub alpha: f32,
+    /// World units over which the surface fades out in front of opaque geometry, hiding
+    /// the hard edge where it intersects the scene. `0.0` disables fading.
+    pub fade_distance: f32,
 }
 
 impl Default for TransparencyUniform {
@@ -15,39 +18,88 @@ impl Default for TransparencyUniform {
         Self {
             tint: [1.0, 1.0, 1.0],
             alpha: 0.4,
+            fade_distance: 0.0,
         }
     }
 }
 
-/// Bind group layout for the per-object transparency uniform.
+impl TransparencyUniform {
+    /// Layout uploaded to the shader, `znear`, `zfar` and `reversed_z` linearise the scene
+    /// depth.
+    pub(crate) fn to_raw(self, znear: f32, zfar: f32, reversed_z: bool) -> TransparencyRaw {
+        TransparencyRaw {
+            color: [self.tint[0], self.tint[1], self.tint[2], self.alpha],
+            fade: [
+                self.fade_distance.max(0.0),
+                znear,
+                zfar,
+                if reversed_z { 1.0 } else { 0.0 },
+            ],
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub(crate) struct TransparencyRaw {
+    color: [f32; 4],
+    // fade distance, znear, zfar and 1 with reversed Z
+    fade: [f32; 4],
+}
+
+/// Entries of [`mk_transparency_bind_group_layout`].
+pub const TRANSPARENCY_ENTRIES: [wgpu::BindGroupLayoutEntry; 2] = [
+    wgpu::BindGroupLayoutEntry {
+        binding: 0,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    },
+    wgpu::BindGroupLayoutEntry {
+        binding: 1,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Texture {
+            multisampled: false,
+            view_dimension: wgpu::TextureViewDimension::D2,
+            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+        },
+        count: None,
+    },
+];
+
+/// Bind group layout for the per-object transparency uniform and the scene depth it fades
+/// against.
 pub fn mk_transparency_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
     device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-        entries: &[wgpu::BindGroupLayoutEntry {
-            binding: 0,
-            visibility: wgpu::ShaderStages::FRAGMENT,
-            ty: wgpu::BindingType::Buffer {
-                ty: wgpu::BufferBindingType::Uniform,
-                has_dynamic_offset: false,
-                min_binding_size: None,
-            },
-            count: None,
-        }],
+        entries: &TRANSPARENCY_ENTRIES,
         label: Some("transparency_bind_group_layout"),
     })
 }
 
-/// Bind group wrapping a `TransparencyUniform` buffer.
+/// Bind group wrapping a `TransparencyUniform` buffer and an `R32Float` copy of the scene
+/// depth.
 pub fn mk_transparency_bind_group(
     device: &wgpu::Device,
     buffer: &wgpu::Buffer,
+    scene_depth: &wgpu::TextureView,
     layout: &wgpu::BindGroupLayout,
 ) -> wgpu::BindGroup {
     device.create_bind_group(&wgpu::BindGroupDescriptor {
         layout,
-        entries: &[wgpu::BindGroupEntry {
-            binding: 0,
-            resource: buffer.as_entire_binding(),
-        }],
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::TextureView(scene_depth),
+            },
+        ],
         label: Some("transparency_bind_group"),
     })
 }
@@ -58,25 +110,28 @@ pub fn mk_transparency_bind_group(
  * This includes all textures wrapped around a mesh regardless of whether they
  * had already partially set to a transparency value lower than `1.0`.
  *
- * The alpha and RGB tint are supplied per object via the
- * transparency uniform (see [`TransparencyUniform`]).
+ * The alpha, RGB tint and depth fade are supplied per object via the
+ * transparency uniform (see [`TransparencyUniform`]). Like the basic pipeline it draws into
+ * `color_format`.
  */
 pub fn mk_transparent_pipeline(
     device: &wgpu::Device,
-    config: &wgpu::SurfaceConfiguration,
-    light_bind_group_layout: &wgpu::BindGroupLayout,
-    camera_bind_group_layout: &wgpu::BindGroupLayout,
+    color_format: wgpu::TextureFormat,
+    direction: wgpu::FrontFace,
+    cull_mode: Option<wgpu::Face>,
+    layouts: &Layouts,
     sample_count: u32,
+    depth: DepthConfig,
 ) -> wgpu::RenderPipeline {
     let render_pipeline_layout =
         device
             .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
                 label: Some("Render Pipeline Layout"),
                 bind_group_layouts: &[
-                    Some(&diffuse_normal_layout(&device)),
-                    Some(&camera_bind_group_layout),
-                    Some(&light_bind_group_layout),
-                    Some(&mk_transparency_bind_group_layout(&device)),
+                    Some(&layouts.diffuse_normal),
+                    Some(&layouts.camera),
+                    Some(&layouts.light),
+                    Some(&layouts.transparency),
                 ],
                 ..Default::default()
             });
@@ -86,11 +141,12 @@ pub fn mk_transparent_pipeline(
     };
     mk_render_pipeline(
         &device,
-        wgpu::FrontFace::Ccw,
+        direction,
+        cull_mode,
         &render_pipeline_layout,
-        config.format,
+        color_format,
         Some(wgpu::BlendState::ALPHA_BLENDING),
-        Some(Texture::DEPTH_FORMAT),
+        Some(depth),
         &[ModelVertex::desc(), InstanceRaw::desc()],
         shader,
         sample_count,
@@ -3,6 +3,8 @@
 struct Camera {
     view_pos: vec4<f32>,
     view_proj: mat4x4<f32>,
+    // x holds the seconds since start
+    time: vec4<f32>,
 }
 @group(1) @binding(0)
 var<uniform> camera: Camera;
@@ -88,13 +90,65 @@ var t_normal: texture_2d<f32>;
 @group(0) @binding(3)
 var s_normal: sampler;
 
+struct MaterialAnimation {
+    uv_offset: vec2<f32>,
+    uv_scale: vec2<f32>,
+    uv_scroll: vec2<f32>,
+    tint_period: f32,
+    tint_a: vec4<f32>,
+    tint_b: vec4<f32>,
+}
+@group(0) @binding(4)
+var<uniform> material: MaterialAnimation;
+
+fn animated_uv(uv: vec2<f32>) -> vec2<f32> {
+    return uv * material.uv_scale + material.uv_offset + material.uv_scroll * camera.time.x;
+}
+
+fn animated_tint() -> vec4<f32> {
+    if (material.tint_period <= 0.0) {
+        return material.tint_a;
+    }
+    let phase = 0.5 - 0.5 * cos(6.28318530718 * camera.time.x / material.tint_period);
+    return mix(material.tint_a, material.tint_b, phase);
+}
+
+struct Transparency {
+    // rgb tint and alpha
+    color: vec4<f32>,
+    // x fade distance, y znear, z zfar, w 1 with reversed Z
+    fade: vec4<f32>,
+}
 @group(3) @binding(0)
-var<uniform> transparency: vec4<f32>;
+var<uniform> transparency: Transparency;
+@group(3) @binding(1)
+var scene_depth: texture_2d<f32>;
+
+// Distance from the camera of a depth buffer value
+fn linear_depth(depth: f32) -> f32 {
+    let znear = transparency.fade.y;
+    let zfar = transparency.fade.z;
+    let standard = select(depth, 1.0 - depth, transparency.fade.w > 0.5);
+    let ndc = standard * 2.0 - 1.0;
+    return 2.0 * znear * zfar / (zfar + znear - ndc * (zfar - znear));
+}
+
+// Fades the surface out as it approaches the opaque geometry behind it
+fn depth_fade(position: vec4<f32>) -> f32 {
+    let fade_distance = transparency.fade.x;
+    if (fade_distance <= 0.0) {
+        return 1.0;
+    }
+    let scene = textureLoad(scene_depth, vec2<i32>(position.xy), 0).r;
+    let distance = linear_depth(scene) - linear_depth(position.z);
+    return clamp(distance / fade_distance, 0.0, 1.0);
+}
 
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    let object_color: vec4<f32> = textureSample(t_diffuse, s_diffuse, in.tex_coords);
-    let object_normal: vec4<f32> = textureSample(t_normal, s_normal, in.tex_coords);
+    let uv = animated_uv(in.tex_coords);
+    let object_color: vec4<f32> = textureSample(t_diffuse, s_diffuse, uv) * animated_tint();
+    let object_normal: vec4<f32> = textureSample(t_normal, s_normal, uv);
 
     // We don't need (or want) much ambient light, so 0.1 is fine
     let ambient_strength = 0.1;
@@ -115,7 +169,7 @@ fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
     // Replace/mix tint: the texture hue is overridden by the tint (`rgb`),
     // while lighting (ambient + diffuse + specular) is preserved.
     let lighting = ambient_color + diffuse_color + specular_color;
-    let result = lighting * transparency.rgb;
+    let result = lighting * transparency.color.rgb;
 
-    return vec4<f32>(result, transparency.a);
+    return vec4<f32>(result, transparency.color.a * depth_fade(in.clip_position));
 }
@@ -0,0 +1,458 @@
+//! Frame pacing statistics and attribution of frame time spikes, see
+//! [`RenderStats::last_spike`].
+//!
+//! The engine times every flow hook and counts buffer uploads, created GPU resources,
+//! pipelines and picks on every frame. The counters are plain atomics and a handful of
+//! `Instant::now` calls, so they stay on in release builds. Only when a frame takes longer
+//! than [`Context::spike_threshold`] times the rolling average they are turned into a
+//! [`SpikeReport`].
+//!
+//! [`RenderStats::last_spike`]: crate::context::RenderStats::last_spike
+//! [`Context::spike_threshold`]: crate::context::Context::spike_threshold
+
+use std::{
+    fmt,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use instant::Duration;
+
+use crate::flow::FlowId;
+
+// Loaders and pipeline constructors only receive a `wgpu::Device`, so like the memory
+// registry the counters are shared by the whole process.
+static UPLOADED_BYTES: AtomicU64 = AtomicU64::new(0);
+static RESOURCES_CREATED: AtomicU64 = AtomicU64::new(0);
+static RESOURCE_BYTES: AtomicU64 = AtomicU64::new(0);
+static PIPELINES_CREATED: AtomicU64 = AtomicU64::new(0);
+static PICKS: AtomicU64 = AtomicU64::new(0);
+
+/// Writes `data` to `buffer` and counts it as uploaded this frame.
+pub(crate) fn write_buffer(queue: &wgpu::Queue, buffer: &wgpu::Buffer, offset: u64, data: &[u8]) {
+    UPLOADED_BYTES.fetch_add(data.len() as u64, Ordering::Relaxed);
+    queue.write_buffer(buffer, offset, data);
+}
+
+pub(crate) fn record_resource(bytes: u64) {
+    RESOURCES_CREATED.fetch_add(1, Ordering::Relaxed);
+    RESOURCE_BYTES.fetch_add(bytes, Ordering::Relaxed);
+}
+
+pub(crate) fn record_pipeline() {
+    PIPELINES_CREATED.fetch_add(1, Ordering::Relaxed);
+}
+
+pub(crate) fn record_pick() {
+    PICKS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Counters since the last frame, resetting them.
+fn take_counters() -> FrameCounters {
+    FrameCounters {
+        uploaded_bytes: UPLOADED_BYTES.swap(0, Ordering::Relaxed),
+        resources_created: RESOURCES_CREATED.swap(0, Ordering::Relaxed),
+        resource_bytes: RESOURCE_BYTES.swap(0, Ordering::Relaxed),
+        pipelines_created: PIPELINES_CREATED.swap(0, Ordering::Relaxed),
+        picks: PICKS.swap(0, Ordering::Relaxed),
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct FrameCounters {
+    uploaded_bytes: u64,
+    resources_created: u64,
+    resource_bytes: u64,
+    pipelines_created: u64,
+    picks: u64,
+}
+
+/// Flow hooks the engine times.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Hook {
+    Init,
+    Update,
+    FixedUpdate,
+    Tick,
+    Resize,
+    Click,
+    Hover,
+    Pointer,
+    WindowEvents,
+    DeviceEvents,
+    GpuReset,
+}
+
+impl fmt::Display for Hook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Hook::Init => "on_init",
+            Hook::Update => "on_update",
+            Hook::FixedUpdate => "on_fixed_update",
+            Hook::Tick => "on_tick",
+            Hook::Resize => "on_resize",
+            Hook::Click => "on_click",
+            Hook::Hover => "on_hover",
+            Hook::Pointer => "on_pointer",
+            Hook::WindowEvents => "on_window_events",
+            Hook::DeviceEvents => "on_device_events",
+            Hook::GpuReset => "on_gpu_reset",
+        })
+    }
+}
+
+/// Time a flow spent in one hook during a frame, including handling its [`Out`].
+///
+/// [`Out`]: crate::flow::Out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HookTime {
+    pub flow: FlowId,
+    pub hook: Hook,
+    pub duration: Duration,
+    pub calls: u32,
+}
+
+/// Breakdown of a frame that took considerably longer than the ones before it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SpikeReport {
+    /// Time since the previous frame.
+    pub frame_time: Duration,
+    /// Rolling average of the frame time before this frame.
+    pub average: Duration,
+    /// Time spent recording and submitting the previous frame's draws.
+    pub render: Duration,
+    /// Hooks called since the previous frame, slowest first.
+    pub hooks: Vec<HookTime>,
+    /// Bytes written to buffers through the engine.
+    pub uploaded_bytes: u64,
+    /// Buffers and textures created by the engine.
+    pub resources_created: u64,
+    pub resource_bytes: u64,
+    pub pipelines_created: u64,
+    /// Whether the pick buffer was rendered and read back.
+    pub picked: bool,
+}
+
+impl fmt::Display for SpikeReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "frame took {:.1?}, {:.1}x the average of {:.1?} (render {:.1?}), uploaded {} bytes, \
+             created {} resources ({} bytes) and {} pipelines, picking {}",
+            self.frame_time,
+            self.frame_time.as_secs_f64() / self.average.as_secs_f64().max(f64::EPSILON),
+            self.average,
+            self.render,
+            self.uploaded_bytes,
+            self.resources_created,
+            self.resource_bytes,
+            self.pipelines_created,
+            if self.picked { "ran" } else { "didn't run" },
+        )?;
+        for hook in &self.hooks {
+            write!(
+                f,
+                "\n  flow {} {}: {:.1?} ({} calls)",
+                hook.flow.0, hook.hook, hook.duration, hook.calls
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Weight of the newest frame in [`Timing::smoothed_dt`].
+const SMOOTHING_WEIGHT: f64 = 0.1;
+/// Time [`Timing::fps`] counts frames over.
+const FPS_WINDOW: Duration = Duration::from_secs(1);
+
+/// Frame timing of the main window, see [`Context::timing`].
+///
+/// All durations but [`Timing::scaled_dt`] are real time, they keep running while the game is
+/// paused.
+///
+/// [`Context::timing`]: crate::context::Context::timing
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Timing {
+    dt: Duration,
+    scaled_dt: Duration,
+    smoothed_dt: Duration,
+    fps: f32,
+    elapsed: Duration,
+    frame: u64,
+    // Frames and time of the FPS window that is still open
+    window_frames: u32,
+    window_time: Duration,
+}
+
+impl Timing {
+    /// Real time since the previous frame, the `dt` passed to `on_update` of flows that run
+    /// while paused.
+    pub fn dt(&self) -> Duration {
+        self.dt
+    }
+
+    /// Game time since the previous frame, the `dt` passed to `on_update` of the other flows.
+    /// That's [`Timing::dt`] scaled by [`Context::time_scale`], zero while paused.
+    ///
+    /// [`Context::time_scale`]: crate::context::Context::time_scale
+    pub fn scaled_dt(&self) -> Duration {
+        self.scaled_dt
+    }
+
+    /// Exponentially smoothed [`Timing::dt`], steadier for animations and overlays.
+    pub fn smoothed_dt(&self) -> Duration {
+        self.smoothed_dt
+    }
+
+    /// Frames rendered during the last full second. Until one has passed it is estimated from
+    /// [`Timing::smoothed_dt`].
+    pub fn fps(&self) -> f32 {
+        self.fps
+    }
+
+    /// Time since the event loop started.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Index of the current frame, starting at 1 with the first frame. It only ever increases,
+    /// resizes or pauses don't reset it.
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    pub(crate) fn set_scaled_dt(&mut self, scaled_dt: Duration) {
+        self.scaled_dt = scaled_dt;
+    }
+
+    /// Starts a frame that took `dt`, `elapsed` after the event loop started.
+    pub(crate) fn advance(&mut self, dt: Duration, elapsed: Duration) {
+        self.smoothed_dt = if self.frame == 0 {
+            dt
+        } else {
+            let smoothed = self.smoothed_dt.as_secs_f64();
+            Duration::from_secs_f64(smoothed + (dt.as_secs_f64() - smoothed) * SMOOTHING_WEIGHT)
+        };
+        self.dt = dt;
+        self.elapsed = elapsed;
+        self.frame += 1;
+        self.window_frames += 1;
+        self.window_time += dt;
+        if self.window_time >= FPS_WINDOW {
+            self.fps = self.window_frames as f32 / self.window_time.as_secs_f32();
+            self.window_frames = 0;
+            self.window_time = Duration::ZERO;
+        } else if self.frame == u64::from(self.window_frames) && !self.smoothed_dt.is_zero() {
+            // Still in the first window
+            self.fps = 1.0 / self.smoothed_dt.as_secs_f32();
+        }
+    }
+}
+
+/// Frames until the rolling average is trusted, the first frames compile shaders and upload
+/// whole scenes.
+const WARMUP_FRAMES: u32 = 10;
+/// Weight of the newest frame in the rolling average.
+const AVERAGE_WEIGHT: f64 = 0.05;
+
+/// Collects hook timings of the current frame and detects spikes.
+#[derive(Debug, Default)]
+pub(crate) struct FrameProfiler {
+    // Seconds
+    average: f64,
+    frames: u32,
+    render: Duration,
+    // Reused so frames without spikes don't allocate
+    hooks: Vec<HookTime>,
+}
+
+impl FrameProfiler {
+    pub(crate) fn record(&mut self, flow: FlowId, hook: Hook, duration: Duration) {
+        match self
+            .hooks
+            .iter_mut()
+            .find(|time| time.flow == flow && time.hook == hook)
+        {
+            Some(time) => {
+                time.duration += duration;
+                time.calls += 1;
+            }
+            None => self.hooks.push(HookTime {
+                flow,
+                hook,
+                duration,
+                calls: 1,
+            }),
+        }
+    }
+
+    pub(crate) fn record_render(&mut self, duration: Duration) {
+        self.render += duration;
+    }
+
+    /// Ends the frame that took `frame_time`, returning a report if it exceeded `threshold`
+    /// times the rolling average.
+    pub(crate) fn finish_frame(
+        &mut self,
+        frame_time: Duration,
+        threshold: f32,
+    ) -> Option<SpikeReport> {
+        self.finish(frame_time, threshold, take_counters())
+    }
+
+    fn finish(
+        &mut self,
+        frame_time: Duration,
+        threshold: f32,
+        counters: FrameCounters,
+    ) -> Option<SpikeReport> {
+        let seconds = frame_time.as_secs_f64();
+        let spike = self.frames >= WARMUP_FRAMES && seconds > self.average * f64::from(threshold);
+        let report = spike.then(|| {
+            let mut hooks = self.hooks.clone();
+            hooks.sort_by_key(|time| std::cmp::Reverse(time.duration));
+            SpikeReport {
+                frame_time,
+                average: Duration::from_secs_f64(self.average),
+                render: self.render,
+                hooks,
+                uploaded_bytes: counters.uploaded_bytes,
+                resources_created: counters.resources_created,
+                resource_bytes: counters.resource_bytes,
+                pipelines_created: counters.pipelines_created,
+                picked: counters.picks > 0,
+            }
+        });
+        // Spikes count towards the average too, so a lasting slowdown stops being reported
+        self.average = if self.frames == 0 {
+            seconds
+        } else {
+            self.average + (seconds - self.average) * AVERAGE_WEIGHT
+        };
+        self.frames = self.frames.saturating_add(1);
+        self.render = Duration::ZERO;
+        self.hooks.clear();
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAME: Duration = Duration::from_millis(16);
+
+    fn warmed_up() -> FrameProfiler {
+        let mut profiler = FrameProfiler::default();
+        for _ in 0..WARMUP_FRAMES {
+            assert!(
+                profiler
+                    .finish(FRAME, 3.0, FrameCounters::default())
+                    .is_none()
+            );
+        }
+        profiler
+    }
+
+    #[test]
+    fn warmup_frames_are_never_reported() {
+        let mut profiler = FrameProfiler::default();
+        profiler.finish(FRAME, 3.0, FrameCounters::default());
+        let slow = profiler.finish(FRAME * 10, 3.0, FrameCounters::default());
+        assert!(slow.is_none());
+    }
+
+    #[test]
+    fn spike_reports_hooks_slowest_first() {
+        let mut profiler = warmed_up();
+        profiler.record(FlowId(0), Hook::Update, Duration::from_millis(2));
+        profiler.record(FlowId(1), Hook::Update, Duration::from_millis(30));
+        profiler.record(FlowId(0), Hook::WindowEvents, Duration::from_millis(1));
+        profiler.record(FlowId(0), Hook::WindowEvents, Duration::from_millis(4));
+        profiler.record_render(Duration::from_millis(10));
+        let counters = FrameCounters {
+            uploaded_bytes: 64,
+            pipelines_created: 1,
+            picks: 1,
+            ..Default::default()
+        };
+
+        let report = profiler.finish(FRAME * 4, 3.0, counters).unwrap();
+        assert_eq!(report.average, FRAME);
+        assert_eq!(report.render, Duration::from_millis(10));
+        assert_eq!(report.uploaded_bytes, 64);
+        assert_eq!(report.pipelines_created, 1);
+        assert!(report.picked);
+        let hooks: Vec<_> = report
+            .hooks
+            .iter()
+            .map(|time| (time.flow, time.hook, time.calls))
+            .collect();
+        assert_eq!(
+            hooks,
+            [
+                (FlowId(1), Hook::Update, 1),
+                (FlowId(0), Hook::WindowEvents, 2),
+                (FlowId(0), Hook::Update, 1),
+            ]
+        );
+
+        // Timings don't carry over into the next frame
+        let next = profiler
+            .finish(FRAME * 8, 3.0, FrameCounters::default())
+            .unwrap();
+        assert!(next.hooks.is_empty());
+        assert_eq!(next.render, Duration::ZERO);
+    }
+
+    #[test]
+    fn frames_below_the_threshold_are_not_reported() {
+        let mut profiler = warmed_up();
+        assert!(
+            profiler
+                .finish(FRAME * 2, 3.0, FrameCounters::default())
+                .is_none()
+        );
+        assert!(
+            profiler
+                .finish(FRAME * 2, f32::INFINITY, FrameCounters::default())
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn lasting_slowdowns_stop_being_reported() {
+        let mut profiler = warmed_up();
+        let reported = (0..100)
+            .filter(|_| {
+                profiler
+                    .finish(FRAME * 4, 3.0, FrameCounters::default())
+                    .is_some()
+            })
+            .count();
+        assert!(reported > 0 && reported < 100, "{reported}");
+    }
+
+    #[test]
+    fn timing_counts_frames_over_a_second() {
+        let mut timing = Timing::default();
+        let mut elapsed = Duration::ZERO;
+        for _ in 0..30 {
+            elapsed += Duration::from_millis(20);
+            timing.advance(Duration::from_millis(20), elapsed);
+        }
+        assert_eq!(timing.frame(), 30);
+        assert_eq!(timing.smoothed_dt(), Duration::from_millis(20));
+        assert!((timing.fps() - 50.0).abs() < 0.01, "{}", timing.fps());
+
+        // The first second ends after 10 slower frames, the next one is slow throughout
+        for _ in 0..35 {
+            elapsed += Duration::from_millis(40);
+            timing.advance(Duration::from_millis(40), elapsed);
+        }
+        assert!((timing.fps() - 25.0).abs() < 0.01, "{}", timing.fps());
+        assert!(timing.smoothed_dt() > Duration::from_millis(35));
+        assert_eq!(timing.dt(), Duration::from_millis(40));
+        assert_eq!(timing.elapsed(), elapsed);
+        assert_eq!(timing.frame(), 65);
+    }
+}
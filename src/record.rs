@@ -0,0 +1,564 @@
+//! Recording a session to replay it exactly, e.g. to debug a physics regression. Only with the
+//! `record` feature.
+//!
+//! A [`Recorder`] set through [`RunConfig::with_recorder`] collects every window and device
+//! input, the frame times and the picks with the index of the frame they belong to, and writes
+//! them to a JSON file every few seconds and once the app exits, so a crash keeps most of it. [`run_replay`] feeds a loaded [`Recording`] back
+//! instead of the live input. Frames advance by the recorded times rather than the wall clock,
+//! so `on_update`, `on_tick` and `on_fixed_update` run in the same sequence, and clicks and
+//! hovering get the recorded picks instead of reading back the pick buffer, whose timing
+//! differs between machines. Once the recording ends the app takes live input again.
+//!
+//! winit doesn't let the engine recreate keyboard events: replayed keys reach
+//! [`Context::input`], [`GraphicsFlow::on_key`] and [`Controller::handle_key`] like live ones,
+//! but not `on_window_events`. Flows reading keys from the former replay exactly. The window
+//! keeps its live size, so replay with the size the session was recorded with. Picks are read
+//! back asynchronously on the web and aren't recorded there.
+//!
+//! [`RunConfig::with_recorder`]: crate::flow::RunConfig::with_recorder
+//! [`run_replay`]: crate::flow::run_replay
+//! [`Context::input`]: crate::context::Context::input
+//! [`GraphicsFlow::on_key`]: crate::flow::GraphicsFlow::on_key
+//! [`Controller::handle_key`]: crate::camera::Controller::handle_key
+
+use std::{
+    collections::{BTreeSet, VecDeque},
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use winit::{
+    dpi::PhysicalPosition,
+    event::{
+        DeviceEvent, DeviceId, ElementState, Ime, MouseButton, MouseScrollDelta, RawKeyEvent,
+        Touch, TouchPhase, WindowEvent,
+    },
+    keyboard::{ModifiersState, PhysicalKey},
+};
+
+use crate::{
+    error::{Error, Result, Source},
+    flow::FlowId,
+    pick::{PickHit, PickId, Picked},
+};
+
+/// A window or device input the app got.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum RecordedInput {
+    CursorMoved(PhysicalPosition<f64>),
+    CursorEntered,
+    CursorLeft,
+    MouseInput {
+        button: MouseButton,
+        state: ElementState,
+    },
+    MouseWheel {
+        delta: MouseScrollDelta,
+        phase: TouchPhase,
+    },
+    /// `WindowEvent::KeyboardInput`, replayed without the `KeyboardInput` event, see the
+    /// [module docs](self).
+    Key {
+        key: PhysicalKey,
+        state: ElementState,
+        repeat: bool,
+    },
+    Modifiers(ModifiersState),
+    Ime(Ime),
+    Focused(bool),
+    Touch {
+        id: u64,
+        phase: TouchPhase,
+        location: PhysicalPosition<f64>,
+    },
+    /// `DeviceEvent::MouseMotion`.
+    MouseMotion {
+        delta: (f64, f64),
+    },
+    /// `DeviceEvent::MouseWheel`.
+    DeviceWheel {
+        delta: MouseScrollDelta,
+    },
+    /// `DeviceEvent::Motion`.
+    DeviceMotion {
+        axis: u32,
+        value: f64,
+    },
+    /// `DeviceEvent::Button`.
+    DeviceButton {
+        button: u32,
+        state: ElementState,
+    },
+    /// `DeviceEvent::Key`.
+    DeviceKey {
+        key: PhysicalKey,
+        state: ElementState,
+    },
+}
+
+/// What a [`RecordedInput`] is replayed as.
+pub(crate) enum ReplayedInput {
+    Window(WindowEvent),
+    Device(DeviceEvent),
+    Key {
+        key: PhysicalKey,
+        state: ElementState,
+        repeat: bool,
+    },
+}
+
+impl RecordedInput {
+    /// The input of `event`, `None` for events that aren't input, e.g. resizes.
+    pub(crate) fn from_window_event(event: &WindowEvent) -> Option<Self> {
+        Some(match event {
+            WindowEvent::CursorMoved { position, .. } => Self::CursorMoved(*position),
+            WindowEvent::CursorEntered { .. } => Self::CursorEntered,
+            WindowEvent::CursorLeft { .. } => Self::CursorLeft,
+            WindowEvent::MouseInput { state, button, .. } => Self::MouseInput {
+                button: *button,
+                state: *state,
+            },
+            WindowEvent::MouseWheel { delta, phase, .. } => Self::MouseWheel {
+                delta: *delta,
+                phase: *phase,
+            },
+            WindowEvent::KeyboardInput { event, .. } => Self::Key {
+                key: event.physical_key,
+                state: event.state,
+                repeat: event.repeat,
+            },
+            WindowEvent::ModifiersChanged(modifiers) => Self::Modifiers(modifiers.state()),
+            WindowEvent::Ime(ime) => Self::Ime(ime.clone()),
+            WindowEvent::Focused(focused) => Self::Focused(*focused),
+            WindowEvent::Touch(touch) => Self::Touch {
+                id: touch.id,
+                phase: touch.phase,
+                location: touch.location,
+            },
+            _ => return None,
+        })
+    }
+
+    /// The input of `event`, `None` for devices being added or removed.
+    pub(crate) fn from_device_event(event: &DeviceEvent) -> Option<Self> {
+        Some(match event {
+            DeviceEvent::MouseMotion { delta } => Self::MouseMotion { delta: *delta },
+            DeviceEvent::MouseWheel { delta } => Self::DeviceWheel { delta: *delta },
+            DeviceEvent::Motion { axis, value } => Self::DeviceMotion {
+                axis: *axis,
+                value: *value,
+            },
+            DeviceEvent::Button { button, state } => Self::DeviceButton {
+                button: *button,
+                state: *state,
+            },
+            DeviceEvent::Key(key) => Self::DeviceKey {
+                key: key.physical_key,
+                state: key.state,
+            },
+            DeviceEvent::Added | DeviceEvent::Removed => return None,
+        })
+    }
+
+    pub(crate) fn into_replayed(self) -> ReplayedInput {
+        let device_id = DeviceId::dummy();
+        let window = ReplayedInput::Window;
+        match self {
+            Self::CursorMoved(position) => window(WindowEvent::CursorMoved {
+                device_id,
+                position,
+            }),
+            Self::CursorEntered => window(WindowEvent::CursorEntered { device_id }),
+            Self::CursorLeft => window(WindowEvent::CursorLeft { device_id }),
+            Self::MouseInput { button, state } => window(WindowEvent::MouseInput {
+                device_id,
+                state,
+                button,
+            }),
+            Self::MouseWheel { delta, phase } => window(WindowEvent::MouseWheel {
+                device_id,
+                delta,
+                phase,
+            }),
+            Self::Key { key, state, repeat } => ReplayedInput::Key { key, state, repeat },
+            Self::Modifiers(modifiers) => window(WindowEvent::ModifiersChanged(modifiers.into())),
+            Self::Ime(ime) => window(WindowEvent::Ime(ime)),
+            Self::Focused(focused) => window(WindowEvent::Focused(focused)),
+            Self::Touch {
+                id,
+                phase,
+                location,
+            } => window(WindowEvent::Touch(Touch {
+                device_id,
+                phase,
+                location,
+                force: None,
+                id,
+            })),
+            Self::MouseMotion { delta } => {
+                ReplayedInput::Device(DeviceEvent::MouseMotion { delta })
+            }
+            Self::DeviceWheel { delta } => ReplayedInput::Device(DeviceEvent::MouseWheel { delta }),
+            Self::DeviceMotion { axis, value } => {
+                ReplayedInput::Device(DeviceEvent::Motion { axis, value })
+            }
+            Self::DeviceButton { button, state } => {
+                ReplayedInput::Device(DeviceEvent::Button { button, state })
+            }
+            Self::DeviceKey { key, state } => {
+                ReplayedInput::Device(DeviceEvent::Key(RawKeyEvent {
+                    physical_key: key,
+                    state,
+                }))
+            }
+        }
+    }
+}
+
+/// An input and when it arrived, since the app started.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedEvent {
+    pub at: Duration,
+    pub input: RecordedInput,
+}
+
+/// The id under the cursor and the flows owning it, `None` over empty space.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedPick {
+    pub id: Option<PickId>,
+    pub owners: BTreeSet<FlowId>,
+    /// World position under the cursor, `None` off the scene's geometry.
+    #[serde(default)]
+    pub position: Option<[f32; 3]>,
+    /// Index of the picked instance, see [`PickHit::instance`].
+    #[serde(default)]
+    pub instance: Option<u32>,
+}
+
+impl From<(&Picked, PickHit)> for RecordedPick {
+    fn from((picked, hit): (&Picked, PickHit)) -> Self {
+        let (id, owners) = match picked {
+            Some((id, owners)) => (Some(PickId(*id)), owners.clone()),
+            None => (None, BTreeSet::new()),
+        };
+        Self {
+            id,
+            owners,
+            position: hit.position.map(Into::into),
+            instance: hit.instance,
+        }
+    }
+}
+
+impl From<RecordedPick> for (Picked, PickHit) {
+    fn from(pick: RecordedPick) -> Self {
+        let hit = PickHit {
+            position: pick.position.map(Into::into),
+            instance: pick.instance,
+        };
+        (pick.id.map(|id| (id.0, pick.owners)), hit)
+    }
+}
+
+/// A frame and the inputs that arrived since the previous one.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RecordedFrame {
+    pub index: u64,
+    /// Real time since the previous frame.
+    pub dt: Duration,
+    pub since_start: Duration,
+    pub inputs: Vec<RecordedEvent>,
+    /// Picks of the clicks among `inputs`, in order.
+    pub clicks: Vec<RecordedPick>,
+    /// Pick under the resting cursor, if hover picking picked this frame.
+    pub hover: Option<RecordedPick>,
+}
+
+/// A recorded session, see the [module docs](self).
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Recording {
+    pub frames: Vec<RecordedFrame>,
+}
+
+impl Recording {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::open(path).map_err(|e| Error::read(&path.display().to_string(), e))?;
+        serde_json::from_reader(BufReader::new(file)).map_err(|e| Error::decode(path.display(), e))
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let io_error = |source: Source| Error::Io {
+            path: path.display().to_string(),
+            source,
+        };
+        let file = File::create(path).map_err(|e| io_error(e.into()))?;
+        serde_json::to_writer(BufWriter::new(file), self).map_err(|e| io_error(e.into()))
+    }
+}
+
+// How much of a session a crash may lose at most
+const SAVE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Records the app's session into the file at `path` every few seconds and once it exits, see
+/// the [module docs](self).
+#[derive(Debug, Clone)]
+pub struct Recorder {
+    path: PathBuf,
+    recording: Recording,
+    // Inputs and clicks since the previous frame
+    next: RecordedFrame,
+    // Time since the start the recording was last written at
+    saved_at: Duration,
+}
+
+impl Recorder {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            recording: Recording::default(),
+            next: RecordedFrame::default(),
+            saved_at: Duration::ZERO,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Frames recorded so far.
+    pub fn recording(&self) -> &Recording {
+        &self.recording
+    }
+
+    fn frame(&mut self, dt: Duration, since_start: Duration) {
+        let frame = RecordedFrame {
+            index: self.recording.frames.len() as u64,
+            dt,
+            since_start,
+            ..std::mem::take(&mut self.next)
+        };
+        self.recording.frames.push(frame);
+        if since_start.saturating_sub(self.saved_at) >= SAVE_INTERVAL {
+            self.saved_at = since_start;
+            if let Err(e) = self.save() {
+                log::error!(
+                    "Unable to save the recording to {}: {e}",
+                    self.path.display()
+                );
+            }
+        }
+    }
+
+    pub(crate) fn save(&self) -> Result<()> {
+        self.recording.save(&self.path)
+    }
+}
+
+/// Feeds a [`Recording`] back frame by frame.
+#[derive(Debug)]
+pub(crate) struct Replay {
+    frames: VecDeque<RecordedFrame>,
+    // Of the frame whose inputs were fed
+    clicks: VecDeque<RecordedPick>,
+    hover: Option<RecordedPick>,
+    // Times of the frame whose inputs were fed until it's drawn
+    times: Option<(Duration, Duration)>,
+    // Arrival of the input being fed
+    feeding: Option<Duration>,
+}
+
+impl Replay {
+    pub(crate) fn new(recording: Recording) -> Self {
+        Self {
+            frames: recording.frames.into(),
+            clicks: VecDeque::new(),
+            hover: None,
+            times: None,
+            feeding: None,
+        }
+    }
+
+    /// Inputs of the next frame once the previous one was drawn, `None` while a frame whose
+    /// inputs were fed still waits to be drawn.
+    pub(crate) fn start_frame(&mut self) -> Option<Vec<RecordedEvent>> {
+        if self.times.is_some() {
+            return None;
+        }
+        let frame = self.frames.pop_front()?;
+        self.clicks = frame.clicks.into();
+        self.hover = frame.hover;
+        self.times = Some((frame.dt, frame.since_start));
+        Some(frame.inputs)
+    }
+
+    /// Whether every frame was drawn.
+    pub(crate) fn is_finished(&self) -> bool {
+        self.frames.is_empty() && self.times.is_none()
+    }
+
+    /// Marks the input arriving at `at` as fed, or none with `None`.
+    pub(crate) fn feed(&mut self, at: Option<Duration>) {
+        self.feeding = at;
+    }
+}
+
+/// Recording or replaying the app's session.
+#[derive(Debug)]
+pub(crate) enum Session {
+    Record(Recorder),
+    Replay(Replay),
+}
+
+impl Session {
+    /// Records `input`, which arrived `at` after the start. Returns whether to handle it,
+    /// replays only handle the inputs they feed.
+    pub(crate) fn input(&mut self, at: Duration, input: RecordedInput) -> bool {
+        match self {
+            Self::Record(recorder) => {
+                recorder.next.inputs.push(RecordedEvent { at, input });
+                true
+            }
+            Self::Replay(replay) => replay.feeding.is_some(),
+        }
+    }
+
+    /// Time since the start the input being replayed arrived at.
+    pub(crate) fn replay_clock(&self) -> Option<Duration> {
+        match self {
+            Self::Replay(replay) => replay.feeding,
+            Self::Record(_) => None,
+        }
+    }
+
+    /// Records a frame drawn `dt` after the previous one, returning the times to advance by,
+    /// the recorded ones while replaying.
+    pub(crate) fn frame(&mut self, dt: Duration, since_start: Duration) -> (Duration, Duration) {
+        match self {
+            Self::Record(recorder) => {
+                recorder.frame(dt, since_start);
+                (dt, since_start)
+            }
+            Self::Replay(replay) => replay.times.take().unwrap_or((dt, since_start)),
+        }
+    }
+
+    /// The recorded pick of the next click while replaying.
+    pub(crate) fn replayed_click(&mut self) -> Option<(Picked, PickHit)> {
+        let Self::Replay(replay) = self else {
+            return None;
+        };
+        let pick = replay.clicks.pop_front();
+        if pick.is_none() {
+            log::warn!("The replay clicked more often than the recording, picking live instead");
+        }
+        pick.map(Into::into)
+    }
+
+    pub(crate) fn record_click(&mut self, picked: &Picked, hit: PickHit) {
+        if let Self::Record(recorder) = self {
+            recorder.next.clicks.push((picked, hit).into());
+        }
+    }
+
+    /// While replaying, the recorded pick under the resting cursor this frame. `Some(None)`
+    /// if hover picking didn't pick in the recorded frame.
+    pub(crate) fn replayed_hover(&mut self) -> Option<Option<(Picked, PickHit)>> {
+        match self {
+            Self::Replay(replay) => Some(replay.hover.take().map(Into::into)),
+            Self::Record(_) => None,
+        }
+    }
+
+    pub(crate) fn record_hover(&mut self, picked: &Picked, hit: PickHit) {
+        if let Self::Record(recorder) = self
+            && let Some(frame) = recorder.recording.frames.last_mut()
+        {
+            frame.hover = Some((picked, hit).into());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::Vector3;
+
+    #[test]
+    fn replays_recorded_frames_in_order() {
+        let mut recording = Session::Record(Recorder::new("unused.json"));
+        let moved = RecordedInput::CursorMoved(PhysicalPosition::new(4.0, 2.0));
+        assert!(recording.input(Duration::from_millis(3), moved.clone()));
+        let picked: Picked = Some((7, BTreeSet::from([FlowId(1)])));
+        let hit = PickHit {
+            position: Some(Vector3::new(1.0, 0.5, -2.0)),
+            instance: Some(42),
+        };
+        recording.record_click(&picked, hit);
+        let first = (Duration::from_millis(16), Duration::from_millis(20));
+        assert_eq!(recording.frame(first.0, first.1), first);
+        recording.record_hover(&None, PickHit::default());
+        recording.frame(Duration::from_millis(17), Duration::from_millis(37));
+        let Session::Record(recorder) = recording else {
+            unreachable!()
+        };
+
+        let json = serde_json::to_string(recorder.recording()).unwrap();
+        let loaded: Recording = serde_json::from_str(&json).unwrap();
+        assert_eq!(&loaded, recorder.recording());
+        assert_eq!(loaded.frames[1].index, 1);
+
+        let mut replay = Session::Replay(Replay::new(loaded));
+        assert!(
+            !replay.input(Duration::ZERO, moved.clone()),
+            "live input is dropped"
+        );
+        let Session::Replay(inner) = &mut replay else {
+            unreachable!()
+        };
+        let inputs = inner.start_frame().unwrap();
+        assert_eq!(
+            inputs,
+            [RecordedEvent {
+                at: Duration::from_millis(3),
+                input: moved
+            }]
+        );
+        assert_eq!(inner.start_frame(), None, "the frame wasn't drawn yet");
+        assert_eq!(replay.replayed_click(), Some((picked, hit)));
+        let live = (Duration::from_secs(1), Duration::from_secs(2));
+        assert_eq!(replay.frame(live.0, live.1), first);
+        assert_eq!(
+            replay.replayed_hover(),
+            Some(Some((None, PickHit::default())))
+        );
+        assert_eq!(replay.replayed_hover(), Some(None), "picked once");
+
+        let Session::Replay(inner) = &mut replay else {
+            unreachable!()
+        };
+        assert_eq!(inner.start_frame(), Some(Vec::new()));
+        assert_eq!(replay.frame(live.0, live.1).0, Duration::from_millis(17));
+        let Session::Replay(inner) = &replay else {
+            unreachable!()
+        };
+        assert!(inner.is_finished());
+        assert_eq!(replay.replayed_hover(), Some(None));
+    }
+
+    #[test]
+    fn saves_the_recording_while_recording() {
+        let path = std::env::temp_dir().join("flow_ngin_saves_while_recording.json");
+        let _ = std::fs::remove_file(&path);
+        let mut recorder = Recorder::new(&path);
+        recorder.frame(Duration::from_millis(16), Duration::from_millis(16));
+        assert!(!path.exists(), "saved too early");
+        recorder.frame(Duration::from_millis(16), SAVE_INTERVAL);
+        assert_eq!(&Recording::load(&path).unwrap(), recorder.recording());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
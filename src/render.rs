@@ -10,15 +10,22 @@
 //! - [`Render<'a, 'pass>`] is the primary enum describing render operations
 //! - [`Instanced<'a>`] contains data for instanced rendering (model + instance buffer)
 //! - [`Flat<'a>`] contains data for flat (2D / GUI) rendering (vertex + index buffers)
+//! - [`Layer`] orders renders, e.g. to draw the GUI over the 3D world
 //!
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 
-use wgpu::{FrontFace, RenderPass};
+use wgpu::FrontFace;
 
 use crate::{
     context::{Context, GPUResource},
-    data_structures::{block::BuildingBlocks, model::Model, scene_graph::SceneNode},
+    data_structures::{
+        block::BuildingBlocks,
+        model::{Material, Model},
+        scene_graph::SceneNode,
+        visibility::VisibilitySet,
+    },
+    flow::FlowId,
     pick::PickId,
     pipelines::transparent::TransparencyUniform,
 };
@@ -34,6 +41,74 @@ pub struct Instanced<'a> {
     pub front_face: wgpu::FrontFace,
     pub amount: usize,
     pub id: PickId,
+    /// Face culling for this draw only. `None` keeps the pipeline's back-face culling,
+    /// `Some(None)` draws both sides and `Some(Some(Face::Front))` culls front faces, e.g. to
+    /// see the inside of a skydome whose triangles face outwards. Picking honours it too.
+    pub cull_override: Option<Option<wgpu::Face>>,
+    /// Instances that survived culling, indexed like the instances the buffer was culled
+    /// from. `None` if all `amount` instances are drawn.
+    pub visibility: Option<&'a VisibilitySet>,
+    /// Drawn instead of the material of every mesh, e.g. a hologram material for the
+    /// blueprint of a building, without copying the model. It must be created with the
+    /// diffuse and normal layout of [`Context::layouts`]. Picking ignores it.
+    pub material_override: Option<&'a Material>,
+    /// Picks every instance with its own id, `id` plus the instance's index, so flows learn
+    /// which one was clicked through [`GraphicsFlow::on_click_instance`]. The ids up to
+    /// `id + amount` must not be used by other pickable objects, draws whose ids overlap are
+    /// picked by `id` only.
+    ///
+    /// [`GraphicsFlow::on_click_instance`]: crate::flow::GraphicsFlow::on_click_instance
+    pub pick_instances: bool,
+}
+
+impl<'a> Instanced<'a> {
+    /// This draw with every mesh drawn in `material`, see [`Instanced::material_override`].
+    pub fn with_material(mut self, material: &'a Material) -> Self {
+        self.material_override = Some(material);
+        self
+    }
+
+    /// This draw with every instance picked on its own, see [`Instanced::pick_instances`].
+    pub fn with_instance_picking(mut self) -> Self {
+        self.pick_instances = true;
+        self
+    }
+
+    /// Winding of the triangles this draw culls, `None` if it draws both sides.
+    pub fn culled_winding(&self) -> Option<FrontFace> {
+        culled_winding(self.front_face, self.cull_override)
+    }
+}
+
+/// Visible instances of `draws` by pick id, draws sharing an id are appended in order.
+pub(crate) fn collect_visibility<'a>(
+    draws: impl IntoIterator<Item = &'a Instanced<'a>>,
+) -> HashMap<PickId, VisibilitySet> {
+    let mut visibility: HashMap<PickId, VisibilitySet> = HashMap::new();
+    for draw in draws {
+        let set = match draw.visibility {
+            Some(set) => set.clone(),
+            None => VisibilitySet::all(draw.amount),
+        };
+        match visibility.get_mut(&draw.id) {
+            Some(existing) => existing.append(&set),
+            None => {
+                visibility.insert(draw.id, set);
+            }
+        }
+    }
+    visibility
+}
+
+fn culled_winding(
+    front_face: FrontFace,
+    cull_override: Option<Option<wgpu::Face>>,
+) -> Option<FrontFace> {
+    let face = cull_override.unwrap_or(Some(wgpu::Face::Back))?;
+    Some(match (front_face, face) {
+        (FrontFace::Ccw, wgpu::Face::Back) | (FrontFace::Cw, wgpu::Face::Front) => FrontFace::Cw,
+        (FrontFace::Ccw, wgpu::Face::Front) | (FrontFace::Cw, wgpu::Face::Back) => FrontFace::Ccw,
+    })
 }
 
 /// Data for flat (2D / GUI) object rendering: vertex and index buffers with a bind group.
@@ -63,6 +138,80 @@ pub struct Geometry<'a> {
     pub id: PickId,
 }
 
+/// Draw order of renders, see [`Render::on_layer`] and [`GraphicsFlow::layer`].
+///
+/// Layers are drawn by ascending `index` into the same colour target. A layer with
+/// `clear_depth` starts with a cleared depth buffer, so nothing of the layers below can hide
+/// it, e.g. GUI elements close to world geometry. Renders sharing an index share the layer,
+/// which clears the depth if any of them asks for it.
+///
+/// [`GraphicsFlow::layer`]: crate::flow::GraphicsFlow::layer
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Layer {
+    pub index: u32,
+    pub clear_depth: bool,
+}
+
+impl Layer {
+    /// The 3D world, where renders end up unless they or their flow pick another layer.
+    pub const WORLD: Layer = Layer::new(0);
+    /// Above the world with its own depth, e.g. for the GUI.
+    pub const OVERLAY: Layer = Layer::new(1).with_depth_clear();
+
+    pub const fn new(index: u32) -> Self {
+        Self {
+            index,
+            clear_depth: false,
+        }
+    }
+
+    pub const fn with_depth_clear(mut self) -> Self {
+        self.clear_depth = true;
+        self
+    }
+}
+
+/// Closure of a [`Render::Custom`].
+pub(crate) type CustomDraw<'a, 'pass> =
+    Box<dyn 'a + FnOnce(&Context, &mut wgpu::RenderPass<'pass>) -> ()>;
+
+/// Draws of one [`Layer`] grouped by pipeline.
+#[derive(Default)]
+pub(crate) struct Batches<'a, 'pass> {
+    pub(crate) clear_depth: bool,
+    pub(crate) basics: Vec<Instanced<'a>>,
+    pub(crate) trans: Vec<(Instanced<'a>, TransparencyUniform)>,
+    pub(crate) guis: Vec<Flat<'a>>,
+    pub(crate) terrain: Vec<Geometry<'a>>,
+    pub(crate) customs: Vec<CustomDraw<'a, 'pass>>,
+}
+
+impl<'a> Batches<'a, '_> {
+    /// Opaque and transparent instanced draws.
+    pub(crate) fn instanced(&self) -> impl Iterator<Item = &Instanced<'a>> {
+        self.basics
+            .iter()
+            .chain(self.trans.iter().map(|(instanced, _)| instanced))
+    }
+
+    /// Whether the layer draws any 3D geometry.
+    pub(crate) fn has_world(&self) -> bool {
+        !self.basics.is_empty() || !self.trans.is_empty() || !self.terrain.is_empty()
+    }
+}
+
+/// [`Batches`] by layer index, in draw order.
+pub(crate) type Layers<'a, 'pass> = BTreeMap<u32, Batches<'a, 'pass>>;
+
+fn batches<'l, 'a, 'pass>(
+    layers: &'l mut Layers<'a, 'pass>,
+    layer: Layer,
+) -> &'l mut Batches<'a, 'pass> {
+    let batches = layers.entry(layer.index).or_default();
+    batches.clear_depth |= layer.clear_depth;
+    batches
+}
+
 /// Specifies how a scene object should be rendered.
 ///
 /// `Render` is an enum that allows flexible composition of render operations.
@@ -80,6 +229,7 @@ pub struct Geometry<'a> {
 /// - `GUI(Flat)` renders 2D elements (flat geometry)
 /// - `Terrain(Flat)` renders terrain mesh
 /// - `Composed(Vec<Render>)` recursively renders composition of multiple renders
+/// - `Layered(Layer, Box<Render>)` renders the inner render on another [`Layer`]
 /// - `Custom(...)` invokes a user-defined closure for custom rendering
 ///
 #[derive(Default)]
@@ -96,13 +246,14 @@ where
     GUI(Flat<'a>),
     Terrain(Geometry<'a>),
     Composed(Vec<Render<'a, 'pass>>),
-    Custom(Box<dyn 'a + FnOnce(&Context, &mut wgpu::RenderPass<'pass>) -> ()>),
+    Layered(Layer, Box<Render<'a, 'pass>>),
+    Custom(CustomDraw<'a, 'pass>),
 }
 
 pub(crate) fn map_id_list(
     ids: &[PickId],
-    flow_id: usize,
-    map: &mut HashMap<PickId, HashSet<usize>>,
+    flow_id: FlowId,
+    map: &mut HashMap<PickId, BTreeSet<FlowId>>,
 ) {
     for &id in ids {
         map.entry(id)
@@ -122,8 +273,8 @@ impl<'a, 'pass> Render<'a, 'pass> {
     pub(crate) fn map_ids(
         &self,
         // TODO: introduce id caching in ctx
-        flow_id: usize,
-        map: &mut HashMap<PickId, HashSet<usize>>,
+        flow_id: FlowId,
+        map: &mut HashMap<PickId, BTreeSet<FlowId>>,
     ) {
         match self {
             Render::Default(instanced) => map_id_list(&[instanced.id], flow_id, map),
@@ -141,65 +292,44 @@ impl<'a, 'pass> Render<'a, 'pass> {
             Render::Composed(renders) => renders
                 .into_iter()
                 .for_each(|render| render.map_ids(flow_id, map)),
+            Render::Layered(_, render) => render.map_ids(flow_id, map),
             Render::None | Render::Custom(_) => (),
         }
     }
 
-    pub(crate) fn set_pipelines(
-        self,
-        ctx: &Context,
-        render_pass: &mut RenderPass<'pass>,
-        basics: &mut Vec<Instanced<'a>>,
-        trans: &mut Vec<(Instanced<'a>, TransparencyUniform)>,
-        guis: &mut Vec<Flat<'a>>,
-        terrain: &mut Vec<Geometry<'a>>,
-        customs: &mut Vec<Box<dyn 'a + FnOnce(&Context, &mut wgpu::RenderPass<'pass>) -> ()>>,
-    ) {
+    /// Whether an element of a GUI in this render has the pick id `id`.
+    pub(crate) fn draws_gui(&self, id: PickId) -> bool {
         match self {
-            Render::Default(instanced) => {
-                basics.push(instanced);
-            }
-            Render::Defaults(mut vec) => basics.append(&mut vec),
-            Render::Transparent(instanced, transparency) => trans.push((instanced, transparency)),
-            Render::Transparents(vec, transparency) => {
-                trans.extend(vec.into_iter().map(|i| (i, transparency)))
-            }
-            Render::GUI(flat) => guis.push(flat),
-            Render::Terrain(flat) => terrain.push(flat),
-            Render::Composed(renders) => renders
-                .into_iter()
-                .map(|render| {
-                    render.set_pipelines(ctx, render_pass, basics, trans, guis, terrain, customs)
-                })
-                .collect(),
-            Render::Custom(f) => customs.push(f),
-            Render::None => (),
+            Render::GUI(flat) => flat.id == id,
+            Render::Composed(renders) => renders.iter().any(|render| render.draws_gui(id)),
+            Render::Layered(_, render) => render.draws_gui(id),
+            _ => false,
         }
     }
 
-    pub(crate) fn set_pick_pipelines(
-        self,
-        ctx: &Context,
-        render_pass: &mut RenderPass<'pass>,
-        basics: &mut Vec<Instanced<'a>>,
-        flats: &mut Vec<Flat<'a>>,
-        geoms: &mut Vec<Geometry<'a>>,
-    ) {
+    /// Draws this render on `layer`.
+    pub fn on_layer(self, layer: Layer) -> Self {
+        Render::Layered(layer, Box::new(self))
+    }
+
+    /// Sorts the draws into the batches of their layer, `layer` unless they're `Layered`.
+    pub(crate) fn set_pipelines(self, layer: Layer, layers: &mut Layers<'a, 'pass>) {
         match self {
-            Render::Default(instanced) => {
-                basics.push(instanced);
+            Render::Default(instanced) => batches(layers, layer).basics.push(instanced),
+            Render::Defaults(mut vec) => batches(layers, layer).basics.append(&mut vec),
+            Render::Transparent(instanced, transparency) => {
+                batches(layers, layer).trans.push((instanced, transparency))
             }
-            Render::Defaults(mut vec) => basics.append(&mut vec),
-            Render::Transparent(instanced, _) => basics.push(instanced),
-            Render::Transparents(mut vec, _) => basics.append(&mut vec),
-            Render::GUI(flat) => flats.push(flat),
-            Render::Terrain(flat) => geoms.push(flat),
+            Render::Transparents(vec, transparency) => batches(layers, layer)
+                .trans
+                .extend(vec.into_iter().map(|i| (i, transparency))),
+            Render::GUI(flat) => batches(layers, layer).guis.push(flat),
+            Render::Terrain(flat) => batches(layers, layer).terrain.push(flat),
             Render::Composed(renders) => renders
                 .into_iter()
-                .map(|render| render.set_pick_pipelines(ctx, render_pass, basics, flats, geoms))
-                .collect(),
-            // Picking is not supported for custom renders
-            Render::Custom(_) => (),
+                .for_each(|render| render.set_pipelines(layer, layers)),
+            Render::Layered(layer, render) => render.set_pipelines(layer, layers),
+            Render::Custom(f) => batches(layers, layer).customs.push(f),
             Render::None => (),
         }
     }
@@ -214,6 +344,10 @@ impl<'a, 'pass> Render<'a, 'pass> {
                     amount: instanced.amount,
                     front_face: instanced.front_face,
                     id: instanced.id,
+                    cull_override: instanced.cull_override,
+                    visibility: instanced.visibility,
+                    material_override: instanced.material_override,
+                    pick_instances: instanced.pick_instances,
                 },
                 tu,
             ),
@@ -226,10 +360,43 @@ impl<'a, 'pass> Render<'a, 'pass> {
                         amount: instanced.amount,
                         front_face: instanced.front_face,
                         id: instanced.id,
+                        cull_override: instanced.cull_override,
+                        visibility: instanced.visibility,
+                        material_override: instanced.material_override,
+                        pick_instances: instanced.pick_instances,
                     })
                     .collect(),
                 tu,
             ),
+            Render::Layered(layer, render) => {
+                Render::Layered(layer, Box::new(render.transparent(tu)))
+            }
+            other => other,
+        }
+    }
+
+    /// Sets [`Instanced::cull_override`] on every instanced draw in this render.
+    pub fn with_cull_override(self, cull: Option<Option<wgpu::Face>>) -> Self {
+        let set = |mut instanced: Instanced<'a>| {
+            instanced.cull_override = cull;
+            instanced
+        };
+        match self {
+            Render::Default(instanced) => Render::Default(set(instanced)),
+            Render::Defaults(vec) => Render::Defaults(vec.into_iter().map(set).collect()),
+            Render::Transparent(instanced, tu) => Render::Transparent(set(instanced), tu),
+            Render::Transparents(vec, tu) => {
+                Render::Transparents(vec.into_iter().map(set).collect(), tu)
+            }
+            Render::Composed(renders) => Render::Composed(
+                renders
+                    .into_iter()
+                    .map(|render| render.with_cull_override(cull))
+                    .collect(),
+            ),
+            Render::Layered(layer, render) => {
+                Render::Layered(layer, Box::new(render.with_cull_override(cull)))
+            }
             other => other,
         }
     }
@@ -273,57 +440,115 @@ mod tests {
     #[test]
     fn single_id_single_flow() {
         let mut map = HashMap::new();
-        map_id_list(&[PickId(5)], 0, &mut map);
+        map_id_list(&[PickId(5)], FlowId(0), &mut map);
         assert_eq!(map.len(), 1);
-        assert!(map[&PickId(5)].contains(&0));
+        assert!(map[&PickId(5)].contains(&FlowId(0)));
     }
 
     #[test]
     fn single_id_two_flows() {
         let mut map = HashMap::new();
-        map_id_list(&[PickId(5)], 0, &mut map);
-        map_id_list(&[PickId(5)], 1, &mut map);
+        map_id_list(&[PickId(5)], FlowId(0), &mut map);
+        map_id_list(&[PickId(5)], FlowId(1), &mut map);
         assert_eq!(map[&PickId(5)].len(), 2);
-        assert!(map[&PickId(5)].contains(&0));
-        assert!(map[&PickId(5)].contains(&1));
+        assert!(map[&PickId(5)].contains(&FlowId(0)));
+        assert!(map[&PickId(5)].contains(&FlowId(1)));
     }
 
     #[test]
     fn two_ids_one_flow() {
         let mut map = HashMap::new();
-        map_id_list(&[PickId(1), PickId(2)], 0, &mut map);
-        assert!(map[&PickId(1)].contains(&0));
-        assert!(map[&PickId(2)].contains(&0));
+        map_id_list(&[PickId(1), PickId(2)], FlowId(0), &mut map);
+        assert!(map[&PickId(1)].contains(&FlowId(0)));
+        assert!(map[&PickId(2)].contains(&FlowId(0)));
     }
 
     #[test]
     fn empty_ids_no_change() {
         let mut map = HashMap::new();
-        map_id_list(&[], 0, &mut map);
+        map_id_list(&[], FlowId(0), &mut map);
         assert!(map.is_empty());
     }
 
     #[test]
     fn duplicate_flow_id_is_idempotent() {
         let mut map = HashMap::new();
-        map_id_list(&[PickId(7)], 0, &mut map);
-        map_id_list(&[PickId(7)], 0, &mut map);
+        map_id_list(&[PickId(7)], FlowId(0), &mut map);
+        map_id_list(&[PickId(7)], FlowId(0), &mut map);
         assert_eq!(map[&PickId(7)].len(), 1);
     }
 
+    // --- culled_winding ---
+
+    #[test]
+    fn default_culls_back_faces() {
+        assert_eq!(culled_winding(FrontFace::Ccw, None), Some(FrontFace::Cw));
+        assert_eq!(culled_winding(FrontFace::Cw, None), Some(FrontFace::Ccw));
+    }
+
+    #[test]
+    fn cull_override_flips_or_disables_culling() {
+        let front = Some(Some(wgpu::Face::Front));
+        assert_eq!(culled_winding(FrontFace::Ccw, front), Some(FrontFace::Ccw));
+        assert_eq!(culled_winding(FrontFace::Cw, front), Some(FrontFace::Cw));
+        assert_eq!(culled_winding(FrontFace::Ccw, Some(None)), None);
+        assert_eq!(culled_winding(FrontFace::Cw, Some(None)), None);
+    }
+
     // --- Render::map_ids (GPU-free variants) ---
 
     #[test]
     fn none_maps_nothing() {
         let mut map = HashMap::new();
-        Render::<'_, '_>::None.map_ids(0, &mut map);
+        Render::<'_, '_>::None.map_ids(FlowId(0), &mut map);
         assert!(map.is_empty());
     }
 
     #[test]
     fn composed_empty_maps_nothing() {
         let mut map = HashMap::new();
-        Render::<'_, '_>::Composed(vec![]).map_ids(0, &mut map);
+        Render::<'_, '_>::Composed(vec![]).map_ids(FlowId(0), &mut map);
         assert!(map.is_empty());
     }
+
+    // --- Render::set_pipelines (GPU-free variants) ---
+
+    fn custom<'a, 'pass>() -> Render<'a, 'pass> {
+        Render::Custom(Box::new(|_, _| ()))
+    }
+
+    #[test]
+    fn layered_renders_are_batched_by_layer() {
+        let mut layers = Layers::new();
+        Render::Composed(vec![
+            custom().on_layer(Layer::new(3)),
+            custom(),
+            Render::Composed(vec![custom(), custom()]).on_layer(Layer::OVERLAY),
+        ])
+        .set_pipelines(Layer::WORLD, &mut layers);
+        let batches: Vec<_> = layers
+            .iter()
+            .map(|(&index, batches)| (index, batches.customs.len(), batches.clear_depth))
+            .collect();
+        assert_eq!(batches, [(0, 1, false), (1, 2, true), (3, 1, false)]);
+    }
+
+    #[test]
+    fn any_render_of_a_layer_can_clear_its_depth() {
+        let mut layers = Layers::new();
+        custom().set_pipelines(Layer::new(2), &mut layers);
+        custom().set_pipelines(Layer::new(2).with_depth_clear(), &mut layers);
+        custom().set_pipelines(Layer::new(2), &mut layers);
+        assert!(layers[&2].clear_depth);
+        assert_eq!(layers[&2].customs.len(), 3);
+    }
+
+    #[test]
+    fn renders_without_draws_add_no_layer() {
+        let mut layers = Layers::new();
+        Render::<'_, '_>::None
+            .on_layer(Layer::OVERLAY)
+            .set_pipelines(Layer::WORLD, &mut layers);
+        assert!(layers.is_empty());
+    }
 }
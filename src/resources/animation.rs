@@ -4,7 +4,10 @@ use instant::{Duration, Instant};
 
 use cgmath::{AbsDiffEq, num_traits::Float};
 
-use crate::data_structures::{instance::Instance, scene_graph::SceneNode};
+use crate::{
+    context,
+    data_structures::{instance::Instance, scene_graph::SceneNode},
+};
 
 const EPSILON: f32 = 1e-2;
 
@@ -57,6 +60,7 @@ impl<'a> Animation {
         if self.time.elapsed().as_secs_f32() > self.rep_after_sec {
             self.time = Instant::now();
         }
+        context::request_frame();
     }
 
     /**
@@ -97,7 +101,11 @@ impl<'a> Animation {
                 log::warn!("Warning, animation with index {} not found.", idx)
             }
         }
-        all_lts.into_iter().fold(true, BitAnd::bitand)
+        let done = all_lts.into_iter().fold(true, BitAnd::bitand);
+        if !done {
+            context::request_frame();
+        }
+        done
     }
 }
 
@@ -0,0 +1,413 @@
+//! Cache of loaded textures, materials and models, see [`Context::assets`].
+//!
+//! Assets are handed out as clones sharing the GPU resources of the cached original, so the
+//! cache sees an asset is in use as long as the [`TrackedMemory`] of any of its buffers or
+//! textures has more holders than the cache itself. Cached entries remember what they were
+//! built from (model → materials → textures), which keeps the textures of a retained model
+//! loaded even if nothing holds them right now.
+//!
+//! Loads can be tagged, e.g. with the name of a level, so switching levels becomes
+//! [`AssetCache::retain_only`] with the tags of the next level.
+//!
+//! [`Context::assets`]: crate::context::Context::assets
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    sync::{
+        Arc, Mutex, MutexGuard, PoisonError,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use crate::{
+    context::Layouts,
+    data_structures::{
+        model::{Material, Model},
+        texture::Texture,
+    },
+    error::{Error, Result},
+    resources::{
+        memory::TrackedMemory,
+        mesh, obj_meshes,
+        texture::{load_obj_materials, load_texture},
+    },
+};
+
+#[derive(Debug, Clone)]
+enum Asset {
+    Texture(Texture),
+    Material(Material),
+    Model(Model),
+}
+
+impl Asset {
+    /// Every allocation the asset keeps alive, including those of its dependencies.
+    fn memory(&self) -> Vec<&TrackedMemory> {
+        match self {
+            Asset::Texture(texture) => vec![&texture.memory],
+            Asset::Material(material) => material.memory.iter().collect(),
+            Asset::Model(model) => model
+                .meshes
+                .iter()
+                .flat_map(|mesh| &mesh.memory)
+                .chain(model.materials.iter().flat_map(|material| &material.memory))
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Entry {
+    asset: Asset,
+    tags: BTreeSet<String>,
+    /// Keys of the entries the asset was built from.
+    dependencies: Vec<String>,
+}
+
+/// Assets that left the cache, dropped once the GPU finished the work submitted before.
+#[derive(Debug)]
+struct Retired {
+    done: Arc<AtomicBool>,
+    #[allow(dead_code)]
+    assets: Vec<Asset>,
+}
+
+#[derive(Debug, Default)]
+struct Assets {
+    entries: BTreeMap<String, Entry>,
+    retired: Vec<Retired>,
+}
+
+/// Keys reachable from `roots` through `dependencies`.
+fn reachable<'a>(
+    roots: impl IntoIterator<Item = &'a str>,
+    dependencies: impl Fn(&str) -> Option<&'a [String]>,
+) -> BTreeSet<String> {
+    let mut stack: Vec<&str> = roots.into_iter().collect();
+    let mut reached = BTreeSet::new();
+    while let Some(key) = stack.pop() {
+        if !reached.insert(key.to_string()) {
+            continue;
+        }
+        if let Some(dependencies) = dependencies(key) {
+            stack.extend(dependencies.iter().map(String::as_str));
+        }
+    }
+    reached
+}
+
+/// Allocations of `entry` it doesn't share with its dependencies. Holding a texture
+/// shouldn't keep the models using it.
+fn own_memory<'a>(
+    entries: &'a BTreeMap<String, Entry>,
+    entry: &'a Entry,
+) -> Vec<&'a TrackedMemory> {
+    let shared: HashSet<_> = entry
+        .dependencies
+        .iter()
+        .filter_map(|key| entries.get(key))
+        .flat_map(|dependency| dependency.asset.memory())
+        .map(TrackedMemory::id)
+        .collect();
+    let mut memory = entry.asset.memory();
+    memory.retain(|memory| !shared.contains(&memory.id()));
+    memory
+}
+
+/// Whether anything outside the cache holds one of the allocations in `memory`. `internal`
+/// counts the holders within the cache by [`TrackedMemory::id`].
+fn in_use(memory: &[&TrackedMemory], internal: &HashMap<usize, usize>) -> bool {
+    memory
+        .iter()
+        .any(|memory| memory.holders() > internal.get(&memory.id()).copied().unwrap_or_default())
+}
+
+fn texture_key(file_name: &str, is_normal_map: bool) -> String {
+    // Normal maps are decoded into a linear format, so they're a different asset
+    if is_normal_map {
+        format!("{file_name}#normal")
+    } else {
+        file_name.to_string()
+    }
+}
+
+/// Loaded assets shared by all flows, see the [module docs](self).
+///
+/// Clones share the same cache, e.g. to load from a flow constructor through
+/// [`crate::context::InitContext::assets`].
+#[derive(Debug, Clone)]
+pub struct AssetCache {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    layouts: Layouts,
+    assets: Arc<Mutex<Assets>>,
+}
+
+impl AssetCache {
+    pub(crate) fn new(device: wgpu::Device, queue: wgpu::Queue, layouts: Layouts) -> Self {
+        Self {
+            device,
+            queue,
+            layouts,
+            assets: Arc::default(),
+        }
+    }
+
+    /// The layouts of the context the cache belongs to, see [`crate::context::Context::layouts`].
+    pub fn layouts(&self) -> &Layouts {
+        &self.layouts
+    }
+
+    pub(crate) fn device(&self) -> &wgpu::Device {
+        &self.device
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Assets> {
+        self.assets.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// The cached asset under `key`, tagged with `tag`.
+    fn get(&self, key: &str, tag: Option<&str>) -> Option<Asset> {
+        let mut assets = self.lock();
+        let entry = assets.entries.get_mut(key)?;
+        entry.tags.extend(tag.map(str::to_string));
+        Some(entry.asset.clone())
+    }
+
+    /// Caches `asset` unless another load of `key` finished first, returning the cached one.
+    fn insert(
+        &self,
+        key: String,
+        asset: Asset,
+        dependencies: Vec<String>,
+        tag: Option<&str>,
+    ) -> Asset {
+        let mut assets = self.lock();
+        let entry = assets.entries.entry(key).or_insert(Entry {
+            asset,
+            tags: BTreeSet::new(),
+            dependencies,
+        });
+        entry.tags.extend(tag.map(str::to_string));
+        entry.asset.clone()
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.lock().entries.contains_key(key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Loads the texture `file_name`, or shares the one loaded before.
+    pub async fn load_texture(
+        &self,
+        file_name: &str,
+        is_normal_map: bool,
+        tag: Option<&str>,
+    ) -> Result<Texture> {
+        let key = texture_key(file_name, is_normal_map);
+        let asset = match self.get(&key, tag) {
+            Some(asset) => asset,
+            None => {
+                let texture =
+                    load_texture(file_name, is_normal_map, &self.device, &self.queue, None).await?;
+                self.insert(key, Asset::Texture(texture), Vec::new(), tag)
+            }
+        };
+        match asset {
+            Asset::Texture(texture) => Ok(texture),
+            _ => Err(Error::decode(file_name, "cached as another kind of asset")),
+        }
+    }
+
+    /// Caches a texture created at runtime, e.g. a generated one, under `key`.
+    pub fn insert_texture(&self, key: &str, texture: Texture, tag: Option<&str>) -> Texture {
+        match self.insert(
+            key.to_string(),
+            Asset::Texture(texture.clone()),
+            Vec::new(),
+            tag,
+        ) {
+            Asset::Texture(cached) => cached,
+            _ => texture,
+        }
+    }
+
+    /// Loads the obj model `file_name` like [`crate::resources::load_model_obj`], sharing
+    /// textures with models loaded before.
+    pub async fn load_model_obj(
+        &self,
+        file_name: &str,
+        tag: Option<&str>,
+    ) -> Result<Model> {
+        if let Some(asset) = self.get(file_name, tag) {
+            return match asset {
+                Asset::Model(model) => Ok(model),
+                _ => Err(Error::decode(file_name, "cached as another kind of asset")),
+            };
+        }
+        let (obj_materials, models) = load_obj_materials(
+            file_name,
+            &self.queue,
+            &self.device,
+            &self.layouts.diffuse_normal,
+            async |texture_name: &str, is_normal_map: bool| {
+                self.load_texture(texture_name, is_normal_map, None).await
+            },
+        )
+        .await?;
+
+        let mut materials = Vec::new();
+        let mut dependencies = Vec::new();
+        for obj_material in obj_materials {
+            let key = format!("{file_name}#{}", obj_material.material.name);
+            let textures = obj_material
+                .textures
+                .iter()
+                .map(|(texture_name, is_normal_map)| texture_key(texture_name, *is_normal_map))
+                .collect();
+            self.insert(
+                key.clone(),
+                Asset::Material(obj_material.material.clone()),
+                textures,
+                None,
+            );
+            materials.push(obj_material.material);
+            dependencies.push(key);
+        }
+        let meshes = obj_meshes(
+            mesh::load_meshes(&models, file_name, &self.device),
+            file_name,
+        );
+        let model = Model { meshes, materials };
+        match self.insert(
+            file_name.to_string(),
+            Asset::Model(model.clone()),
+            dependencies,
+            tag,
+        ) {
+            Asset::Model(cached) => Ok(cached),
+            _ => Ok(model),
+        }
+    }
+
+    /**
+     * Forgets every asset nothing outside the cache uses, together with the materials and
+     * textures only they used. Returns how many assets were forgotten.
+     *
+     * Tags don't keep assets, see [`AssetCache::retain_only`]. The GPU resources are dropped
+     * once the frames submitted so far are done with them.
+     */
+    pub fn unload_unused(&self) -> usize {
+        self.unload(|_| false)
+    }
+
+    /**
+     * Keeps the assets tagged with any of `tags` and what they're built from, e.g. the
+     * assets of the next level, and forgets all others nothing uses. Other tags are removed
+     * from the retained assets. Returns how many assets were forgotten.
+     */
+    pub fn retain_only(&self, tags: &[&str]) -> usize {
+        {
+            let mut assets = self.lock();
+            for entry in assets.entries.values_mut() {
+                entry.tags.retain(|tag| tags.contains(&tag.as_str()));
+            }
+        }
+        self.unload(|entry| !entry.tags.is_empty())
+    }
+
+    fn unload(&self, keep: impl Fn(&Entry) -> bool) -> usize {
+        let mut assets = self.lock();
+        let mut internal = HashMap::new();
+        for entry in assets.entries.values() {
+            for memory in entry.asset.memory() {
+                *internal.entry(memory.id()).or_insert(0) += 1;
+            }
+        }
+        let roots = assets
+            .entries
+            .iter()
+            .filter(|(_, entry)| {
+                keep(entry) || in_use(&own_memory(&assets.entries, entry), &internal)
+            })
+            .map(|(key, _)| key.as_str());
+        let live = reachable(roots, |key| {
+            assets
+                .entries
+                .get(key)
+                .map(|entry| entry.dependencies.as_slice())
+        });
+        let (kept, unloaded) = std::mem::take(&mut assets.entries)
+            .into_iter()
+            .partition(|(key, _)| live.contains(key));
+        assets.entries = kept;
+        let unloaded: Vec<_> = unloaded.into_values().map(|entry| entry.asset).collect();
+        let count = unloaded.len();
+        if count > 0 {
+            let done = Arc::new(AtomicBool::new(false));
+            let signal = done.clone();
+            self.queue
+                .on_submitted_work_done(move || signal.store(true, Ordering::Release));
+            assets.retired.push(Retired {
+                done,
+                assets: unloaded,
+            });
+        }
+        count
+    }
+
+    /// Drops unloaded assets the GPU is done with, called by the engine every frame.
+    pub(crate) fn collect(&self) {
+        self.lock()
+            .retired
+            .retain(|retired| !retired.done.load(Ordering::Acquire));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resources::memory::MemoryCategory;
+
+    #[test]
+    fn dependencies_of_roots_are_reachable() {
+        let graph: BTreeMap<&str, Vec<String>> = BTreeMap::from([
+            ("level1.obj", vec!["level1.obj#stone".to_string()]),
+            ("level1.obj#stone", vec!["stone.png".to_string()]),
+            ("level2.obj", vec!["level2.obj#grass".to_string()]),
+            (
+                "level2.obj#grass",
+                vec!["grass.png".to_string(), "stone.png".to_string()],
+            ),
+        ]);
+        let live = reachable(["level2.obj"], |key| graph.get(key).map(Vec::as_slice));
+        let live: Vec<_> = live.iter().map(String::as_str).collect();
+        assert_eq!(
+            live,
+            ["grass.png", "level2.obj", "level2.obj#grass", "stone.png"]
+        );
+    }
+
+    #[test]
+    fn holders_outside_the_cache_mark_assets_in_use() {
+        let cached = TrackedMemory::new(
+            "holders_outside_the_cache_mark_assets_in_use",
+            MemoryCategory::Uniform,
+            16,
+        );
+        let internal = HashMap::from([(cached.id(), 1)]);
+        assert!(!in_use(&[&cached], &internal));
+
+        let handed_out = cached.clone();
+        assert!(in_use(&[&cached], &internal));
+        drop(handed_out);
+        assert!(!in_use(&[&cached], &internal));
+    }
+}
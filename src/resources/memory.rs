@@ -0,0 +1,286 @@
+//! Accounting of the GPU memory held by engine resources, see [`Context::memory_report`].
+//!
+//! Buffers and textures created by the engine register their size here and are released
+//! once the last clone of the owning resource is dropped. Resources created directly
+//! through `wgpu` by the application aren't counted.
+//!
+//! [`Context::memory_report`]: crate::context::Context::memory_report
+
+use std::{
+    collections::BTreeMap,
+    ops::Deref,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
+
+use wgpu::util::DeviceExt;
+
+use crate::profiling;
+
+/// What an allocation is used for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum MemoryCategory {
+    Texture,
+    Vertex,
+    Index,
+    Instance,
+    Uniform,
+    Readback,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemoryAllocation {
+    pub label: String,
+    pub category: MemoryCategory,
+    pub bytes: u64,
+}
+
+/// Snapshot of the GPU memory held by engine resources.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MemoryReport {
+    /// Bytes per category. Categories without live allocations are missing.
+    pub totals: BTreeMap<MemoryCategory, u64>,
+    /// The largest live allocations, biggest first.
+    pub largest: Vec<MemoryAllocation>,
+}
+
+impl MemoryReport {
+    /// Bytes over all categories.
+    pub fn total(&self) -> u64 {
+        self.totals.values().sum()
+    }
+
+    pub fn category(&self, category: MemoryCategory) -> u64 {
+        self.totals.get(&category).copied().unwrap_or(0)
+    }
+}
+
+struct Registry {
+    next_id: u64,
+    allocations: BTreeMap<u64, MemoryAllocation>,
+}
+
+impl Registry {
+    const fn new() -> Self {
+        Self {
+            next_id: 0,
+            allocations: BTreeMap::new(),
+        }
+    }
+
+    fn insert(&mut self, allocation: MemoryAllocation) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.allocations.insert(id, allocation);
+        id
+    }
+
+    fn remove(&mut self, id: u64) {
+        self.allocations.remove(&id);
+    }
+
+    fn report(&self, top: usize) -> MemoryReport {
+        let mut totals = BTreeMap::new();
+        for allocation in self.allocations.values() {
+            *totals.entry(allocation.category).or_default() += allocation.bytes;
+        }
+        let mut largest: Vec<_> = self.allocations.values().collect();
+        // stable, so equally sized allocations stay in creation order
+        largest.sort_by_key(|allocation| std::cmp::Reverse(allocation.bytes));
+        MemoryReport {
+            totals,
+            largest: largest.into_iter().take(top).cloned().collect(),
+        }
+    }
+}
+
+// Loaders only receive a `wgpu::Device`, so the registry is shared by the whole process
+// rather than threaded through every constructor.
+static REGISTRY: Mutex<Registry> = Mutex::new(Registry::new());
+
+fn registry() -> MutexGuard<'static, Registry> {
+    REGISTRY.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Report over all live allocations with the `top` largest listed.
+pub(crate) fn report(top: usize) -> MemoryReport {
+    registry().report(top)
+}
+
+/// Keeps an allocation counted until the last clone is dropped.
+///
+/// Store it next to the `wgpu` resource it describes so both are dropped together.
+#[derive(Debug, Clone)]
+pub(crate) struct TrackedMemory(Arc<Entry>);
+
+#[derive(Debug)]
+struct Entry(u64);
+
+impl Drop for Entry {
+    fn drop(&mut self) {
+        registry().remove(self.0);
+    }
+}
+
+impl TrackedMemory {
+    pub(crate) fn new(label: &str, category: MemoryCategory, bytes: u64) -> Self {
+        profiling::record_resource(bytes);
+        let id = registry().insert(MemoryAllocation {
+            label: label.to_string(),
+            category,
+            bytes,
+        });
+        Self(Arc::new(Entry(id)))
+    }
+
+    pub(crate) fn buffer(buffer: &wgpu::Buffer, label: &str, category: MemoryCategory) -> Self {
+        Self::new(label, category, buffer.size())
+    }
+
+    /// Identifies the allocation, equal for all clones.
+    pub(crate) fn id(&self) -> usize {
+        Arc::as_ptr(&self.0) as usize
+    }
+
+    /// Number of clones alive.
+    pub(crate) fn holders(&self) -> usize {
+        Arc::strong_count(&self.0)
+    }
+
+    pub(crate) fn texture(texture: &wgpu::Texture, label: &str) -> Self {
+        let bytes = texture_bytes(
+            texture.format(),
+            texture.size(),
+            texture.mip_level_count(),
+            texture.sample_count(),
+        );
+        Self::new(label, MemoryCategory::Texture, bytes)
+    }
+}
+
+/// A buffer counted as long as it's alive, for buffers that are replaced when they grow.
+#[derive(Debug, Clone)]
+pub(crate) struct TrackedBuffer {
+    buffer: wgpu::Buffer,
+    _memory: TrackedMemory,
+}
+
+impl TrackedBuffer {
+    pub(crate) fn new(
+        device: &wgpu::Device,
+        desc: &wgpu::BufferDescriptor,
+        category: MemoryCategory,
+    ) -> Self {
+        Self::track(device.create_buffer(desc), desc.label, category)
+    }
+
+    pub(crate) fn init(
+        device: &wgpu::Device,
+        desc: &wgpu::util::BufferInitDescriptor,
+        category: MemoryCategory,
+    ) -> Self {
+        Self::track(device.create_buffer_init(desc), desc.label, category)
+    }
+
+    fn track(buffer: wgpu::Buffer, label: Option<&str>, category: MemoryCategory) -> Self {
+        Self {
+            _memory: TrackedMemory::buffer(&buffer, label.unwrap_or("unlabeled buffer"), category),
+            buffer,
+        }
+    }
+}
+
+impl Deref for TrackedBuffer {
+    type Target = wgpu::Buffer;
+
+    fn deref(&self) -> &wgpu::Buffer {
+        &self.buffer
+    }
+}
+
+/// Size of a 2D texture including its mip chain and samples.
+fn texture_bytes(
+    format: wgpu::TextureFormat,
+    size: wgpu::Extent3d,
+    mip_level_count: u32,
+    sample_count: u32,
+) -> u64 {
+    let (block_width, block_height) = format.block_dimensions();
+    // Formats like Depth24Plus leave their layout to the driver, assume four bytes
+    let block_size = format.block_copy_size(None).unwrap_or(4);
+    (0..mip_level_count)
+        .map(|level| {
+            let width = (size.width >> level).max(1).div_ceil(block_width);
+            let height = (size.height >> level).max(1).div_ceil(block_height);
+            u64::from(width) * u64::from(height) * u64::from(block_size)
+        })
+        .sum::<u64>()
+        * u64::from(size.depth_or_array_layers)
+        * u64::from(sample_count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn allocation(label: &str, category: MemoryCategory, bytes: u64) -> MemoryAllocation {
+        MemoryAllocation {
+            label: label.to_string(),
+            category,
+            bytes,
+        }
+    }
+
+    #[test]
+    fn report_sums_categories_and_lists_largest() {
+        let mut registry = Registry::new();
+        registry.insert(allocation("a", MemoryCategory::Vertex, 100));
+        let b = registry.insert(allocation("b", MemoryCategory::Texture, 400));
+        registry.insert(allocation("c", MemoryCategory::Vertex, 300));
+
+        let report = registry.report(2);
+        assert_eq!(report.category(MemoryCategory::Vertex), 400);
+        assert_eq!(report.category(MemoryCategory::Texture), 400);
+        assert_eq!(report.category(MemoryCategory::Readback), 0);
+        assert_eq!(report.total(), 800);
+        let labels: Vec<_> = report.largest.iter().map(|a| a.label.as_str()).collect();
+        assert_eq!(labels, ["b", "c"]);
+
+        registry.remove(b);
+        let report = registry.report(10);
+        assert!(!report.totals.contains_key(&MemoryCategory::Texture));
+        assert_eq!(report.largest.len(), 2);
+    }
+
+    #[test]
+    fn tracked_memory_is_released_with_the_last_clone() {
+        let label = "tracked_memory_is_released_with_the_last_clone";
+        let counted = || {
+            report(usize::MAX)
+                .largest
+                .iter()
+                .any(|allocation| allocation.label == label)
+        };
+        let tracked = TrackedMemory::new(label, MemoryCategory::Uniform, 16);
+        let clone = tracked.clone();
+        drop(tracked);
+        assert!(counted());
+        drop(clone);
+        assert!(!counted());
+    }
+
+    #[test]
+    fn texture_size_includes_mips_and_samples() {
+        let size = wgpu::Extent3d {
+            width: 4,
+            height: 2,
+            depth_or_array_layers: 1,
+        };
+        let rgba = wgpu::TextureFormat::Rgba8Unorm;
+        assert_eq!(texture_bytes(rgba, size, 1, 1), 32);
+        // 4x2, 2x1 and 1x1
+        assert_eq!(texture_bytes(rgba, size, 3, 1), 44);
+        assert_eq!(texture_bytes(rgba, size, 1, 4), 128);
+        let bc1 = wgpu::TextureFormat::Bc1RgbaUnorm;
+        assert_eq!(texture_bytes(bc1, size, 1, 1), 8);
+    }
+}
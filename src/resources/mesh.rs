@@ -3,7 +3,7 @@ use std::num::TryFromIntError;
 use cgmath::{InnerSpace, Zero};
 use wgpu::util::DeviceExt;
 
-use crate::data_structures::model;
+use crate::data_structures::{aabb::Aabb, model};
 
 pub fn load_meshes(
     models: &Vec<tobj::Model>,
@@ -12,53 +12,79 @@ pub fn load_meshes(
 ) -> Vec<Result<model::Mesh, TryFromIntError>> {
     models
         .into_iter()
-        .map(|m| {
-            let mut vertices = (0..m.mesh.positions.len() / 3)
-                .map(|i| model::ModelVertex {
-                    position: [
-                        m.mesh.positions[i * 3],
-                        m.mesh.positions[i * 3 + 1],
-                        m.mesh.positions[i * 3 + 2],
-                    ],
-                    tex_coords: [
-                        m.mesh.texcoords.get(i * 2).map_or(0.0, |f| *f),
-                        1.0 - m.mesh.texcoords.get(i * 2 + 1).map_or(0.0, |f| *f),
-                    ],
-                    normal: [
-                        m.mesh.normals.get(i * 3).map_or(0.0, |f| *f),
-                        m.mesh.normals.get(i * 3 + 1).map_or(0.0, |f| *f),
-                        m.mesh.normals.get(i * 3 + 2).map_or(0.0, |f| *f),
-                    ],
-                    tangent: [0.0; 3],
-                    bitangent: [0.0; 3],
-                })
-                .collect::<Vec<_>>();
-
-            let indices = &m.mesh.indices;
-            compute_tangents(&mut vertices, indices);
-
-            let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some(&format!("{:?} Vertex Buffer", file_name)),
-                contents: bytemuck::cast_slice(&vertices),
-                usage: wgpu::BufferUsages::VERTEX,
-            });
-
-            let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some(&format!("{:?} Index Buffer", file_name)),
-                // The indices are for positions, texels, and normals because wet set `single_index` to true
-                contents: bytemuck::cast_slice(&m.mesh.indices),
-                usage: wgpu::BufferUsages::INDEX,
-            });
-
-            Ok(model::Mesh {
-                name: file_name.to_string(),
-                vertex_buffer,
-                index_buffer,
-                num_elements: u32::try_from(m.mesh.indices.len())?,
-                material: m.mesh.material_id.unwrap_or(0),
+        .map(|m| Ok(MeshData::from_obj(m)?.upload(file_name, device)))
+        .collect::<Vec<_>>()
+}
+
+/// Vertices and indices of a mesh before they're uploaded, e.g. built off the main thread.
+pub(crate) struct MeshData {
+    pub(crate) vertices: Vec<model::ModelVertex>,
+    pub(crate) indices: Vec<u32>,
+    pub(crate) num_elements: u32,
+    pub(crate) material: usize,
+}
+
+impl MeshData {
+    pub(crate) fn from_obj(m: &tobj::Model) -> Result<Self, TryFromIntError> {
+        let mut vertices = (0..m.mesh.positions.len() / 3)
+            .map(|i| model::ModelVertex {
+                position: [
+                    m.mesh.positions[i * 3],
+                    m.mesh.positions[i * 3 + 1],
+                    m.mesh.positions[i * 3 + 2],
+                ],
+                tex_coords: [
+                    m.mesh.texcoords.get(i * 2).map_or(0.0, |f| *f),
+                    1.0 - m.mesh.texcoords.get(i * 2 + 1).map_or(0.0, |f| *f),
+                ],
+                normal: [
+                    m.mesh.normals.get(i * 3).map_or(0.0, |f| *f),
+                    m.mesh.normals.get(i * 3 + 1).map_or(0.0, |f| *f),
+                    m.mesh.normals.get(i * 3 + 2).map_or(0.0, |f| *f),
+                ],
+                tangent: [0.0; 3],
+                bitangent: [0.0; 3],
             })
+            .collect::<Vec<_>>();
+
+        let indices = &m.mesh.indices;
+        compute_tangents(&mut vertices, indices);
+        Ok(Self {
+            vertices,
+            // The indices are for positions, texels, and normals because wet set `single_index` to true
+            indices: indices.clone(),
+            num_elements: u32::try_from(indices.len())?,
+            material: m.mesh.material_id.unwrap_or(0),
         })
-        .collect::<Vec<_>>()
+    }
+
+    pub(crate) fn upload(&self, file_name: &str, device: &wgpu::Device) -> model::Mesh {
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(&format!("{:?} Vertex Buffer", file_name)),
+            contents: bytemuck::cast_slice(&self.vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(&format!("{:?} Index Buffer", file_name)),
+            contents: bytemuck::cast_slice(&self.indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        model::Mesh {
+            memory: model::Mesh::track_buffers(&vertex_buffer, &index_buffer, file_name),
+            name: file_name.to_string(),
+            vertex_buffer,
+            index_buffer,
+            num_elements: self.num_elements,
+            material: self.material,
+            bounds: self.bounds(),
+        }
+    }
+
+    pub(crate) fn bounds(&self) -> Option<Aabb> {
+        Aabb::from_vertex_positions(self.vertices.iter().map(|v| v.position))
+    }
 }
 
 pub(crate) fn compute_tangents(vertices: &mut Vec<model::ModelVertex>, indices: &[u32]) {
@@ -2,6 +2,7 @@ use std::{
     collections::HashMap,
     convert::identity,
     io::{BufReader, Cursor},
+    num::TryFromIntError,
 };
 
 use crate::{
@@ -9,7 +10,7 @@ use crate::{
         model::{self},
         scene_graph::{AnimationClip, ContainerNode, SceneNode, to_scene_node},
         texture::Texture,
-    }, pick::PickId, resources::{
+    }, error::{Error, Result}, pick::PickId, resources::{
         animation::Keyframes,
         texture::{diffuse_normal_layout, load_binary, load_texture},
     }
@@ -19,21 +20,45 @@ use crate::{
  * This module contains all logic for loading mesh/textures/etc. from external files.
  */
 pub mod animation;
+pub mod assets;
+pub mod memory;
 pub mod mesh;
 pub mod pick;
 pub mod texture;
+pub mod thumbnail;
+pub mod validate;
+
+pub use thumbnail::render_thumbnail;
 
 pub async fn load_model_obj(
     file_name: &str,
     device: &wgpu::Device,
     queue: &wgpu::Queue,
-) -> anyhow::Result<model::Model> {
-    let bind_group_layout = diffuse_normal_layout(device);
+) -> Result<model::Model> {
+    load_model_obj_with_layout(file_name, device, queue, &diffuse_normal_layout(device)).await
+}
+
+/// Like [`load_model_obj`] with materials bound to `layout`, usually
+/// [`crate::context::Layouts::diffuse_normal`].
+pub async fn load_model_obj_with_layout(
+    file_name: &str,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    layout: &wgpu::BindGroupLayout,
+) -> Result<model::Model> {
+    let (materials, models) = texture::load_textures(file_name, queue, device, layout).await?;
+    let meshes = obj_meshes(mesh::load_meshes(&models, file_name, device), file_name);
 
-    let (materials, models) =
-        texture::load_textures(file_name, queue, device, &bind_group_layout).await?;
-    let meshes = mesh::load_meshes(&models, file_name, device);
-    let meshes = meshes.into_iter().enumerate().filter_map(|(idx, result)| {
+    let model = model::Model { meshes, materials };
+    Ok(model)
+}
+
+/// Meshes of an obj file, skipping the ones that couldn't be loaded.
+fn obj_meshes(
+    meshes: Vec<Result<model::Mesh, TryFromIntError>>,
+    file_name: &str,
+) -> Vec<model::Mesh> {
+    meshes.into_iter().enumerate().filter_map(|(idx, result)| {
         match result {
             Ok(mesh) => Some(mesh),
             Err(_) => {
@@ -41,10 +66,7 @@ pub async fn load_model_obj(
                 None
             },
         }
-    }).collect();
-
-    let model = model::Model { meshes, materials };
-    Ok(model)
+    }).collect()
 }
 
 /// Loads a gltf model incl. aninmations into a `SceneNode`.
@@ -55,11 +77,23 @@ pub async fn load_model_gltf(
     file_name: &str,
     device: &wgpu::Device,
     queue: &wgpu::Queue,
-) -> anyhow::Result<Box<dyn SceneNode + Send>> {
+) -> Result<Box<dyn SceneNode + Send>> {
+    load_model_gltf_with_layout(id, file_name, device, queue, &diffuse_normal_layout(device)).await
+}
+
+/// Like [`load_model_gltf`] with materials bound to `layout`, usually
+/// [`crate::context::Layouts::diffuse_normal`].
+pub async fn load_model_gltf_with_layout(
+    id: impl Into<PickId>,
+    file_name: &str,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    layout: &wgpu::BindGroupLayout,
+) -> Result<Box<dyn SceneNode + Send>> {
     let gltf_text = load_binary(file_name).await?;
     let gltf_cursor = Cursor::new(gltf_text);
     let gltf_reader = BufReader::new(gltf_cursor);
-    let gltf = gltf::Gltf::from_reader(gltf_reader)?;
+    let gltf = gltf::Gltf::from_reader(gltf_reader).map_err(|e| Error::decode(file_name, e))?;
 
     // Load buffers
     let mut buffer_data = Vec::new();
@@ -149,6 +183,12 @@ pub async fn load_model_gltf(
     // Load materials
     let mut materials = Vec::new();
     for material in gltf.materials() {
+        // Names embedded textures in errors
+        let material_path = match (material.name(), material.index()) {
+            (Some(name), _) => format!("{file_name}#{name}"),
+            (None, Some(index)) => format!("{file_name}#{index}"),
+            (None, None) => format!("{file_name}#default"),
+        };
         let pbr = material.pbr_metallic_roughness();
         let texture_source = &pbr
             .base_color_texture()
@@ -159,11 +199,10 @@ pub async fn load_model_gltf(
                     device,
                     queue,
                     &buffer_data[view.buffer().index()],
-                    file_name,
+                    &material_path,
                     mime_type.split('/').last(),
                     false,
-                )
-                .expect("Couldn't load diffuse");
+                )?;
                 diffuse_texture
             }
             Some(gltf::image::Source::Uri { uri, mime_type }) => {
@@ -200,11 +239,10 @@ pub async fn load_model_gltf(
                         device,
                         queue,
                         &buffer_data[view.buffer().index()],
-                        file_name,
+                        &material_path,
                         None,
                         true,
-                    )
-                    .expect("Couldn't load normal");
+                    )?;
                     texture
                 }
                 // TODO: parse and pass the mime_type so that the img lib does't have to guess
@@ -218,7 +256,6 @@ pub async fn load_model_gltf(
         };
         let name = format!("{}.gltf", file_name);
         let name = name.as_str();
-        let layout = &diffuse_normal_layout(device);
         if let Ok(material) =
             model::Material::new(device, name, diffuse_texture, normal_texture, layout)
         {
@@ -1,19 +1,29 @@
-use crate::{data_structures::model, pick::PickId, pipelines::pick_gui::mk_bind_group_layout};
+use crate::{
+    data_structures::model,
+    pick::PickId,
+    pipelines::pick_gui::mk_bind_group_layout,
+    resources::memory::{MemoryCategory, TrackedMemory},
+};
 
 use wgpu::util::DeviceExt;
 
+/// Entries of the pick id bind group layout, shared by pick models and flat pick draws.
+pub const PICK_ENTRIES: [wgpu::BindGroupLayoutEntry; 1] = [
+    wgpu::BindGroupLayoutEntry {
+        binding: 0,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    },
+];
+
 pub(crate) fn pick_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
     device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-        entries: &[wgpu::BindGroupLayoutEntry {
-            binding: 0,
-            visibility: wgpu::ShaderStages::FRAGMENT,
-            ty: wgpu::BindingType::Buffer {
-                ty: wgpu::BufferBindingType::Uniform,
-                has_dynamic_offset: false,
-                min_binding_size: None,
-            },
-            count: None,
-        }],
+        entries: &PICK_ENTRIES,
         label: Some("pick_bind_group_layout"),
     })
 }
@@ -28,6 +38,34 @@ pub fn load_pick_model(
     id: impl Into<PickId>,
     meshes: Vec<model::Mesh>,
 ) -> anyhow::Result<model::Model> {
+    load_pick_model_with_layout(device, id, meshes, &pick_layout(device))
+}
+
+/// Like [`load_pick_model`] with materials bound to `layout`, usually
+/// [`crate::context::Layouts::pick`].
+pub fn load_pick_model_with_layout(
+    device: &wgpu::Device,
+    id: impl Into<PickId>,
+    meshes: Vec<model::Mesh>,
+    layout: &wgpu::BindGroupLayout,
+) -> anyhow::Result<model::Model> {
+    let max_idx = meshes.iter().map(|m| m.material).max().unwrap_or(0);
+    // We just do this to keep the API consistant. The pick material is just an ID stretched over the mesh
+    let materials = vec![load_pick_material_with_layout(device, id, false, layout); max_idx + 1];
+
+    let model = model::Model { meshes, materials };
+    Ok(model)
+}
+
+/// Material writing `id` wherever a mesh is drawn with it, plus the instance index with
+/// `per_instance`, bound to `layout` like [`load_pick_model_with_layout`]. Its bind group
+/// also fits flat pick draws, which ignore `per_instance`.
+pub(crate) fn load_pick_material_with_layout(
+    device: &wgpu::Device,
+    id: impl Into<PickId>,
+    per_instance: bool,
+    layout: &wgpu::BindGroupLayout,
+) -> model::Material {
     // cutting the significant bits is intended in this conversion
     let id = id.into().0;
     let r = id as u8;
@@ -37,24 +75,29 @@ pub fn load_pick_model(
     // Current browsers don't support downscaling Uniform Buffers so I have to provide the full 16B
     let mut buf = [0; 16];
     buf[..4].copy_from_slice(&[r, g, b, a]);
+    buf[4] = u8::from(per_instance);
     let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
         label: Some("Pick color buffer"),
         contents: bytemuck::cast_slice(&buf),
         usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
     });
-    let max_idx = meshes.iter().map(|m| m.material).max().unwrap_or(0);
-
-    // We just do this to keep the API consistant. The pick material is just an ID stretched over the mesh
-    let materials = (0..max_idx + 1)
-        .map(|_| model::Material::new_pick_material(device, &"Pick Material", buffer.clone()))
-        .collect();
-
-    let model = model::Model { meshes, materials };
-    Ok(model)
+    let memory = TrackedMemory::buffer(&buffer, "Pick color buffer", MemoryCategory::Uniform);
+    let mut material =
+        model::Material::new_pick_material_with_layout(device, "Pick Material", buffer, layout);
+    material.memory.push(memory);
+    material
 }
 
 pub fn load_pick_texture(id: PickId, device: &wgpu::Device) -> wgpu::BindGroup {
-    let texture_bind_group_layout = mk_bind_group_layout(device);
+    load_pick_texture_with_layout(id, device, &mk_bind_group_layout(device))
+}
+
+/// Like [`load_pick_texture`] bound to `layout`, usually [`crate::context::Layouts::pick`].
+pub fn load_pick_texture_with_layout(
+    id: PickId,
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+) -> wgpu::BindGroup {
     let color = id.0;
     // cutting the significant bits is intended in this conversion
     let r = color as u8;
@@ -70,7 +113,7 @@ pub fn load_pick_texture(id: PickId, device: &wgpu::Device) -> wgpu::BindGroup {
     });
 
     device.create_bind_group(&wgpu::BindGroupDescriptor {
-        layout: &texture_bind_group_layout,
+        layout,
         entries: &[
             wgpu::BindGroupEntry {
                 binding: 0,
@@ -1,43 +1,63 @@
-use std::io::{BufReader, Cursor};
+use std::{
+    cell::RefCell,
+    io::{BufReader, Cursor},
+};
 
-use crate::data_structures::{model, texture};
+use crate::{
+    data_structures::{model, texture},
+    error::{Error, Result},
+};
+
+/// Entries of [`diffuse_normal_layout`].
+pub const DIFFUSE_NORMAL_ENTRIES: [wgpu::BindGroupLayoutEntry; 5] = [
+    wgpu::BindGroupLayoutEntry {
+        binding: 0,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Texture {
+            multisampled: false,
+            view_dimension: wgpu::TextureViewDimension::D2,
+            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+        },
+        count: None,
+    },
+    wgpu::BindGroupLayoutEntry {
+        binding: 1,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+        count: None,
+    },
+    wgpu::BindGroupLayoutEntry {
+        binding: 2,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Texture {
+            multisampled: false,
+            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            view_dimension: wgpu::TextureViewDimension::D2,
+        },
+        count: None,
+    },
+    wgpu::BindGroupLayoutEntry {
+        binding: 3,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+        count: None,
+    },
+    // Material animation parameters, see `model::MaterialAnimator`
+    wgpu::BindGroupLayoutEntry {
+        binding: 4,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    },
+];
 
 pub fn diffuse_normal_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
     device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-        entries: &[
-            wgpu::BindGroupLayoutEntry {
-                binding: 0,
-                visibility: wgpu::ShaderStages::FRAGMENT,
-                ty: wgpu::BindingType::Texture {
-                    multisampled: false,
-                    view_dimension: wgpu::TextureViewDimension::D2,
-                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                },
-                count: None,
-            },
-            wgpu::BindGroupLayoutEntry {
-                binding: 1,
-                visibility: wgpu::ShaderStages::FRAGMENT,
-                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-                count: None,
-            },
-            wgpu::BindGroupLayoutEntry {
-                binding: 2,
-                visibility: wgpu::ShaderStages::FRAGMENT,
-                ty: wgpu::BindingType::Texture {
-                    multisampled: false,
-                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                    view_dimension: wgpu::TextureViewDimension::D2,
-                },
-                count: None,
-            },
-            wgpu::BindGroupLayoutEntry {
-                binding: 3,
-                visibility: wgpu::ShaderStages::FRAGMENT,
-                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-                count: None,
-            },
-        ],
+        entries: &DIFFUSE_NORMAL_ENTRIES,
         label: Some("Model texture_bind_group_layout"),
     })
 }
@@ -54,30 +74,56 @@ pub fn format_url(file_name: &str) -> reqwest::Url {
     base.join(file_name).unwrap()
 }
 
-pub async fn load_string(file_name: &str) -> anyhow::Result<String> {
+#[cfg(target_arch = "wasm32")]
+async fn fetch(file_name: &str) -> Result<reqwest::Response> {
+    let response = reqwest::get(format_url(file_name))
+        .await
+        .map_err(|e| Error::Io {
+            path: file_name.to_string(),
+            source: e.into(),
+        })?;
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Err(Error::AssetNotFound {
+            path: file_name.to_string(),
+        });
+    }
+    response.error_for_status().map_err(|e| Error::Io {
+        path: file_name.to_string(),
+        source: e.into(),
+    })
+}
+
+pub async fn load_string(file_name: &str) -> Result<String> {
     #[cfg(target_arch = "wasm32")]
-    let txt = {
-        let url = format_url(file_name);
-        reqwest::get(url).await?.text().await?
-    };
+    let txt = fetch(file_name).await?.text().await.map_err(|e| Error::Io {
+        path: file_name.to_string(),
+        source: e.into(),
+    })?;
     #[cfg(not(target_arch = "wasm32"))]
     let txt = {
         // TODO: pass env for absolute path from lib caller
         let path = std::path::Path::new("./")
             .join("assets")
             .join(file_name);
-        tokio::fs::read_to_string(path).await?
+        tokio::fs::read_to_string(path)
+            .await
+            .map_err(|e| Error::read(file_name, e))?
     };
 
     Ok(txt)
 }
 
-pub async fn load_binary(file_name: &str) -> anyhow::Result<Vec<u8>> {
+pub async fn load_binary(file_name: &str) -> Result<Vec<u8>> {
     #[cfg(target_arch = "wasm32")]
-    let data = {
-        let url = format_url(file_name);
-        reqwest::get(url).await?.bytes().await?.to_vec()
-    };
+    let data = fetch(file_name)
+        .await?
+        .bytes()
+        .await
+        .map_err(|e| Error::Io {
+            path: file_name.to_string(),
+            source: e.into(),
+        })?
+        .to_vec();
     #[cfg(not(target_arch = "wasm32"))]
     // TODO make async
     let data = {
@@ -85,7 +131,9 @@ pub async fn load_binary(file_name: &str) -> anyhow::Result<Vec<u8>> {
         let path = std::path::Path::new("./")
             .join("assets")
             .join(file_name);
-        tokio::fs::read(path).await?
+        tokio::fs::read(path)
+            .await
+            .map_err(|e| Error::read(file_name, e))?
     };
 
     Ok(data)
@@ -97,7 +145,7 @@ pub async fn load_texture(
     device: &wgpu::Device,
     queue: &wgpu::Queue,
     format: Option<&str>,
-) -> anyhow::Result<texture::Texture> {
+) -> Result<texture::Texture> {
     let data = load_binary(file_name).await?;
     texture::Texture::from_bytes(device, queue, &data, file_name, format, is_normal_map)
 }
@@ -107,12 +155,46 @@ pub async fn load_textures(
     queue: &wgpu::Queue,
     device: &wgpu::Device,
     layout: &wgpu::BindGroupLayout,
-) -> anyhow::Result<(Vec<model::Material>, Vec<tobj::Model>)> {
+) -> Result<(Vec<model::Material>, Vec<tobj::Model>)> {
+    let (materials, models) = load_obj_materials(
+        file_name,
+        queue,
+        device,
+        layout,
+        async |texture_name: &str, is_normal_map: bool| {
+            load_texture(texture_name, is_normal_map, device, queue, None).await
+        },
+    )
+    .await?;
+    let materials = materials
+        .into_iter()
+        .map(|material| material.material)
+        .collect();
+    Ok((materials, models))
+}
+
+/// A material of an obj file with the textures it was created from.
+pub(crate) struct ObjMaterial {
+    pub(crate) material: model::Material,
+    /// File names and whether they're normal maps.
+    pub(crate) textures: Vec<(String, bool)>,
+}
+
+/// Like [`load_textures`] but textures are loaded through `load`, e.g. from a cache.
+pub(crate) async fn load_obj_materials(
+    file_name: &str,
+    queue: &wgpu::Queue,
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    mut load: impl AsyncFnMut(&str, bool) -> Result<texture::Texture>,
+) -> Result<(Vec<ObjMaterial>, Vec<tobj::Model>)> {
     let obj_text: String = load_string(file_name).await?;
     // TODO: also make async if not wasm
     let obj_cursor = Cursor::new(obj_text);
     let mut obj_reader = BufReader::new(obj_cursor);
 
+    // tobj only reports that a material library failed to load, so keep the reason
+    let mtl_error = RefCell::new(None);
     let (models, obj_materials) = tobj::load_obj_buf_async(
         &mut obj_reader,
         &tobj::LoadOptions {
@@ -120,35 +202,51 @@ pub async fn load_textures(
             single_index: true,
             ..Default::default()
         },
-        |p| async move {
-            let mat_text = load_string(&p)
-                .await
-                .expect(format!("Material Texture not found for {p}.").as_str());
-            tobj::load_mtl_buf(&mut BufReader::new(Cursor::new(mat_text)))
+        |p| {
+            let mtl_error = &mtl_error;
+            async move {
+                match load_string(&p).await {
+                    Ok(mat_text) => tobj::load_mtl_buf(&mut BufReader::new(Cursor::new(mat_text))),
+                    Err(e) => {
+                        mtl_error.replace(Some(e));
+                        Err(tobj::LoadError::OpenFileFailed)
+                    }
+                }
+            }
         },
     )
-    .await?;
+    .await
+    .map_err(|e| Error::decode(file_name, e))?;
+    let obj_materials = match obj_materials {
+        Ok(obj_materials) => obj_materials,
+        Err(e) => {
+            return Err(mtl_error
+                .take()
+                .unwrap_or_else(|| Error::decode(file_name, e)));
+        }
+    };
 
     // We rather use a default normal map when none is passed instead of changing the pipeline
     let mut materials = Vec::new();
-    for m in obj_materials? {
+    for m in obj_materials {
         if let Some(m_diffuse_texture) = &m.diffuse_texture {
-            let diffuse_texture =
-                load_texture(&m_diffuse_texture, false, device, queue, None).await?;
+            let diffuse_texture = load(m_diffuse_texture, false).await?;
+            let mut textures = vec![(m_diffuse_texture.clone(), false)];
             let normal_texture = match &m.normal_texture {
                 Some(m_normal_texture) => {
-                    load_texture(&m_normal_texture, true, device, queue, None).await?
+                    textures.push((m_normal_texture.clone(), true));
+                    load(m_normal_texture, true).await?
                 },
                 None => texture::Texture::create_default_normal_map(1, 1, device, queue)
             };
-            if let Ok(model) = model::Material::new(
+            if let Ok(material) = model::Material::new(
                 device,
                 &m.name,
                 diffuse_texture,
                 normal_texture,
                 layout,
             ) {
-                materials.push(model);
+                materials.push(ObjMaterial { material, textures });
             } else {
                 log::warn!("Failed to create material for mtl ({}) in obj ({})", m.name, file_name);
             }
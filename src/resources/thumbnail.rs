@@ -0,0 +1,784 @@
+//! Offscreen rendering of model thumbnails.
+//!
+//! [`render_thumbnail`] renders a single [`Model`] or [`SceneNode`] into an image. To render
+//! many assets, e.g. for an asset browser, create a [`ThumbnailRenderer`] once and call
+//! [`ThumbnailRenderer::render`] per model so the render target, camera and pipelines are
+//! reused. On native targets [`ThumbnailCache`] stores the results on disk keyed by a hash
+//! of the asset file and the files it references.
+//!
+//! Thumbnails are drawn with the basic pipeline, lit by a key light above and to the left of
+//! the camera instead of the scene's light, so they look the same in every `Context`.
+
+use std::iter;
+
+use anyhow::bail;
+use cgmath::{Deg, EuclideanSpace, InnerSpace, MetricSpace, Point3, Rad, Vector3};
+use image::RgbaImage;
+use wgpu::util::DeviceExt;
+
+use crate::{
+    camera::{Camera, CameraUniform, Projection},
+    context::Layouts,
+    data_structures::{
+        aabb::Aabb,
+        instance::Instance,
+        model::{DrawModel, Model},
+        scene_graph::SceneNode,
+        texture::{DepthConfig, Texture},
+    },
+    pipelines::{
+        basic::mk_basic_pipeline,
+        highlight::HighlightResources,
+        light::{LightResources, LightUniform},
+    },
+    profiling,
+};
+
+/// Format of the offscreen target and of the returned images.
+const THUMBNAIL_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+/// Camera and background settings for thumbnails.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ThumbnailStyle {
+    /// Clear colour in linear space. The default is fully transparent.
+    pub background: wgpu::Color,
+    /// Viewing direction, see [`Camera::new`].
+    pub yaw: Deg<f32>,
+    pub pitch: Deg<f32>,
+    /// Vertical field of view.
+    pub fov: Deg<f32>,
+    /// Distance multiplier, `1.0` makes the bounding sphere touch the image border.
+    pub padding: f32,
+}
+
+impl Default for ThumbnailStyle {
+    fn default() -> Self {
+        Self {
+            background: wgpu::Color::TRANSPARENT,
+            yaw: Deg(-135.0),
+            pitch: Deg(-25.0),
+            fov: Deg(30.0),
+            padding: 1.1,
+        }
+    }
+}
+
+impl ThumbnailStyle {
+    fn view_direction(&self) -> Vector3<f32> {
+        let (sin_pitch, cos_pitch) = Rad::from(self.pitch).0.sin_cos();
+        let (sin_yaw, cos_yaw) = Rad::from(self.yaw).0.sin_cos();
+        Vector3::new(cos_pitch * cos_yaw, sin_pitch, cos_pitch * sin_yaw)
+    }
+}
+
+/// What to render into a thumbnail.
+///
+/// Scene nodes are borrowed mutably because their world transforms and instance buffers
+/// are brought up to date before rendering.
+pub enum ThumbnailSubject<'a> {
+    Model(&'a Model),
+    Scene(&'a mut dyn SceneNode),
+}
+
+impl<'a> From<&'a Model> for ThumbnailSubject<'a> {
+    fn from(model: &'a Model) -> Self {
+        Self::Model(model)
+    }
+}
+
+impl<'a> From<&'a mut dyn SceneNode> for ThumbnailSubject<'a> {
+    fn from(node: &'a mut dyn SceneNode) -> Self {
+        Self::Scene(node)
+    }
+}
+
+/// Light above and to the left of `eye`, shining on `target` from as far away as the eye.
+pub(crate) fn key_light(eye: Point3<f32>, target: Point3<f32>) -> LightUniform {
+    let forward = (target - eye).normalize();
+    let right = forward.cross(Vector3::unit_y());
+    // Looking straight up or down, any horizontal axis will do
+    let right = if right.magnitude2() < 1e-6 {
+        Vector3::unit_x()
+    } else {
+        right.normalize()
+    };
+    let up = right.cross(forward);
+    let direction = (-forward * 0.8 + up * 0.7 - right * 0.6).normalize();
+    let position = target + direction * eye.distance(target);
+    LightUniform {
+        position: position.into(),
+        _padding: 0,
+        color: [1.0, 1.0, 1.0],
+        _padding2: 0,
+    }
+}
+
+/// Places the camera so the bounding sphere of `bounds` fits into an image of `size`.
+pub(crate) fn frame(
+    bounds: &Aabb,
+    size: [u32; 2],
+    style: &ThumbnailStyle,
+) -> anyhow::Result<(Camera, Projection)> {
+    let radius = bounds.radius().max(1e-3);
+    let aspect = size[0].max(1) as f32 / size[1].max(1) as f32;
+    let half_fovy = Rad::from(style.fov).0 * 0.5;
+    let half_fovx = (half_fovy.tan() * aspect).atan();
+    let distance = radius / half_fovy.min(half_fovx).sin() * style.padding;
+    let position = bounds.center() - style.view_direction() * distance;
+    // Keep the depth range tight around the subject for best precision
+    let znear = (distance - radius * 1.1).max(distance * 1e-3);
+    let zfar = distance + radius * 1.1;
+    let camera = Camera::new(position, style.yaw, style.pitch);
+    let projection = Projection::new(size[0], size[1], style.fov, znear, zfar)?;
+    Ok((camera, projection))
+}
+
+/// Reusable offscreen renderer for batches of thumbnails of the same size.
+pub struct ThumbnailRenderer {
+    size: [u32; 2],
+    style: ThumbnailStyle,
+    target: wgpu::Texture,
+    target_view: wgpu::TextureView,
+    depth: Texture,
+    readback: wgpu::Buffer,
+    padded_bytes_per_row: u32,
+    pipeline: wgpu::RenderPipeline,
+    pipeline_cw: wgpu::RenderPipeline,
+    camera_buffer: wgpu::Buffer,
+    camera_bind_group: wgpu::BindGroup,
+    light: LightResources,
+    // the basic pipeline's highlights, thumbnails have none
+    highlight: HighlightResources,
+    identity_instance: wgpu::Buffer,
+}
+
+impl ThumbnailRenderer {
+    pub fn new(device: &wgpu::Device, size: [u32; 2], style: ThumbnailStyle) -> Self {
+        let size = size.map(|v| v.max(1));
+        let layouts = Layouts::new(device);
+        let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Thumbnail Camera Buffer"),
+            contents: bytemuck::cast_slice(&[CameraUniform::new()]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let camera_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &layouts.camera,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: camera_buffer.as_entire_binding(),
+            }],
+            label: Some("thumbnail_camera_bind_group"),
+        });
+        let light = LightResources::with_layout(
+            key_light(Point3::origin() - style.view_direction(), Point3::origin()),
+            None,
+            device,
+            &layouts.light,
+        );
+
+        let identity_instance = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Thumbnail Instance Buffer"),
+            contents: bytemuck::cast_slice(&[Instance::new().to_raw()]),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        // Thumbnails have their own projection and depth texture
+        let pipeline = |direction| {
+            mk_basic_pipeline(
+                device,
+                THUMBNAIL_FORMAT,
+                direction,
+                Some(wgpu::Face::Back),
+                &layouts,
+                1,
+                DepthConfig::default(),
+            )
+        };
+        let pipeline_cw = pipeline(wgpu::FrontFace::Cw);
+        let pipeline = pipeline(wgpu::FrontFace::Ccw);
+
+        let (target, target_view, depth, readback, padded_bytes_per_row) = mk_targets(device, size);
+
+        Self {
+            size,
+            style,
+            target,
+            target_view,
+            depth,
+            readback,
+            padded_bytes_per_row,
+            pipeline,
+            pipeline_cw,
+            camera_buffer,
+            camera_bind_group,
+            light,
+            highlight: HighlightResources::new(device),
+            identity_instance,
+        }
+    }
+
+    pub fn size(&self) -> [u32; 2] {
+        self.size
+    }
+
+    /// Recreates the render target. Prefer one renderer per thumbnail size for batches.
+    pub fn resize(&mut self, device: &wgpu::Device, size: [u32; 2]) {
+        let size = size.map(|v| v.max(1));
+        if size == self.size {
+            return;
+        }
+        let (target, target_view, depth, readback, padded_bytes_per_row) = mk_targets(device, size);
+        self.size = size;
+        self.target = target;
+        self.target_view = target_view;
+        self.depth = depth;
+        self.readback = readback;
+        self.padded_bytes_per_row = padded_bytes_per_row;
+    }
+
+    pub fn style(&self) -> &ThumbnailStyle {
+        &self.style
+    }
+
+    pub fn set_style(&mut self, style: ThumbnailStyle) {
+        self.style = style;
+    }
+
+    /// Renders `subject` framed by its bounding box and reads the image back.
+    ///
+    /// Fails if the subject has no geometry.
+    pub async fn render<'a>(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        subject: impl Into<ThumbnailSubject<'a>>,
+    ) -> anyhow::Result<RgbaImage> {
+        let mut subject = subject.into();
+        let bounds = match subject {
+            ThumbnailSubject::Model(model) => model.bounds(),
+            ThumbnailSubject::Scene(ref mut node) => {
+                node.update_world_transform_all();
+                node.write_to_buffers(queue, device);
+                node.bounds()
+            }
+        };
+        let Some(bounds) = bounds else {
+            bail!("Cannot render a thumbnail of a model without geometry.");
+        };
+
+        let (camera, projection) = frame(&bounds, self.size, &self.style)?;
+        let mut camera_uniform = CameraUniform::new();
+        camera_uniform.update_view_proj(&camera, &projection);
+        profiling::write_buffer(
+            queue,
+            &self.camera_buffer,
+            0,
+            bytemuck::cast_slice(&[camera_uniform]),
+        );
+        self.light.uniform = key_light(camera.position, bounds.center());
+        profiling::write_buffer(
+            queue,
+            &self.light.buffer,
+            0,
+            bytemuck::cast_slice(&[self.light.uniform]),
+        );
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Thumbnail Encoder"),
+        });
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Thumbnail Render Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &self.target_view,
+                    depth_slice: None,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(self.style.background),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &self.depth.view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: wgpu::StoreOp::Store,
+                    }),
+                    stencil_ops: None,
+                }),
+                ..Default::default()
+            });
+            render_pass.set_bind_group(3, self.highlight.none(), &[]);
+
+            match subject {
+                ThumbnailSubject::Model(model) => {
+                    render_pass.set_pipeline(&self.pipeline);
+                    render_pass.set_vertex_buffer(1, self.identity_instance.slice(..));
+                    render_pass.draw_model_instanced(
+                        model,
+                        0..1,
+                        &self.camera_bind_group,
+                        &self.light.bind_group,
+                    );
+                }
+                ThumbnailSubject::Scene(node) => {
+                    for instanced in node.get_renders() {
+                        let Ok(amount) = u32::try_from(instanced.amount) else {
+                            bail!("Too many instances to render ({}).", instanced.amount);
+                        };
+                        match instanced.front_face {
+                            wgpu::FrontFace::Cw => render_pass.set_pipeline(&self.pipeline_cw),
+                            wgpu::FrontFace::Ccw => render_pass.set_pipeline(&self.pipeline),
+                        }
+                        render_pass.set_vertex_buffer(1, instanced.instance.slice(..));
+                        render_pass.draw_model_instanced(
+                            instanced.model,
+                            0..amount,
+                            &self.camera_bind_group,
+                            &self.light.bind_group,
+                        );
+                    }
+                }
+            }
+        }
+
+        let [width, height] = self.size;
+        encoder.copy_texture_to_buffer(
+            wgpu::TexelCopyTextureInfo {
+                aspect: wgpu::TextureAspect::All,
+                texture: &self.target,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            wgpu::TexelCopyBufferInfo {
+                buffer: &self.readback,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(self.padded_bytes_per_row),
+                    rows_per_image: Some(height),
+                },
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+        queue.submit(iter::once(encoder.finish()));
+
+        let buffer_slice = self.readback.slice(..);
+        // NOTE: map first, then poll, then await. Otherwise the future never resolves.
+        let (tx, rx) = futures_intrusive::channel::shared::oneshot_channel();
+        buffer_slice.map_async(wgpu::MapMode::Read, move |result| {
+            tx.send(result).unwrap();
+        });
+        #[cfg(target_arch = "wasm32")]
+        device.poll(wgpu::PollType::Poll)?;
+        #[cfg(not(target_arch = "wasm32"))]
+        device.poll(wgpu::PollType::Wait {
+            submission_index: None,
+            timeout: None,
+        })?;
+        match rx.receive().await {
+            Some(result) => result?,
+            None => bail!("Thumbnail readback was cancelled."),
+        }
+
+        let row_bytes = (width * 4) as usize;
+        let pixels = {
+            let data = buffer_slice.get_mapped_range();
+            data.chunks(self.padded_bytes_per_row as usize)
+                .take(height as usize)
+                .flat_map(|row| &row[..row_bytes])
+                .copied()
+                .collect::<Vec<u8>>()
+        };
+        self.readback.unmap();
+
+        RgbaImage::from_raw(width, height, pixels).ok_or(anyhow::anyhow!(
+            "Thumbnail readback has an unexpected size."
+        ))
+    }
+}
+
+fn mk_targets(
+    device: &wgpu::Device,
+    size: [u32; 2],
+) -> (wgpu::Texture, wgpu::TextureView, Texture, wgpu::Buffer, u32) {
+    let [width, height] = size;
+    let target = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Thumbnail Target"),
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: THUMBNAIL_FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    });
+    let target_view = target.create_view(&Default::default());
+    let depth = Texture::create_depth_texture(
+        device,
+        [width, height],
+        "thumbnail_depth_texture",
+        1,
+        Texture::DEPTH_FORMAT,
+    );
+    let padded_bytes_per_row = padded_bytes_per_row(width);
+    let readback = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Thumbnail Readback Buffer"),
+        size: (padded_bytes_per_row * height) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+    (target, target_view, depth, readback, padded_bytes_per_row)
+}
+
+/// Texture to buffer copies require rows aligned to [`wgpu::COPY_BYTES_PER_ROW_ALIGNMENT`].
+pub(crate) fn padded_bytes_per_row(width: u32) -> u32 {
+    let unpadded = width * 4;
+    unpadded.div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT) * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT
+}
+
+/// Renders a single thumbnail of `size` pixels.
+///
+/// This sets up a fresh [`ThumbnailRenderer`], use one directly for batches.
+pub async fn render_thumbnail<'a>(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    subject: impl Into<ThumbnailSubject<'a>>,
+    size: [u32; 2],
+    style: ThumbnailStyle,
+) -> anyhow::Result<RgbaImage> {
+    ThumbnailRenderer::new(device, size, style)
+        .render(device, queue, subject)
+        .await
+}
+
+/// 64-bit FNV-1a. Unlike `std`'s hasher its output is guaranteed not to change between
+/// Rust releases, which would invalidate caches on disk.
+fn fnv1a(bytes: impl IntoIterator<Item = u8>) -> u64 {
+    const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+    bytes.into_iter().fold(OFFSET, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(PRIME)
+    })
+}
+
+/// Stable cache key for an asset file rendered with `size` and `style`.
+pub fn thumbnail_key(file: &[u8], size: [u32; 2], style: &ThumbnailStyle) -> u64 {
+    let bg = style.background;
+    let params = size
+        .iter()
+        .flat_map(|v| v.to_le_bytes())
+        .chain(
+            [bg.r, bg.g, bg.b, bg.a]
+                .into_iter()
+                .flat_map(f64::to_le_bytes),
+        )
+        .chain(
+            [style.yaw.0, style.pitch.0, style.fov.0, style.padding]
+                .into_iter()
+                .flat_map(f32::to_le_bytes),
+        );
+    fnv1a(file.iter().copied().chain(params))
+}
+
+/// Material libraries referenced by the OBJ source `obj`.
+#[cfg(not(target_arch = "wasm32"))]
+fn obj_material_libraries(obj: &str) -> Vec<String> {
+    obj.lines()
+        .filter_map(|line| line.trim().strip_prefix("mtllib "))
+        .flat_map(str::split_whitespace)
+        .map(str::to_string)
+        .collect()
+}
+
+/// Texture maps referenced by the MTL source `mtl`. Options before the file name are
+/// skipped.
+#[cfg(not(target_arch = "wasm32"))]
+fn mtl_textures(mtl: &str) -> Vec<String> {
+    mtl.lines()
+        .filter_map(|line| {
+            let mut tokens = line.split_whitespace();
+            let keyword = tokens.next()?;
+            let is_map = keyword.starts_with("map_")
+                || matches!(keyword, "bump" | "disp" | "decal" | "norm" | "refl");
+            if !is_map {
+                return None;
+            }
+            tokens.next_back().map(str::to_string)
+        })
+        .collect()
+}
+
+/// External buffers and images of the glTF source `file`, embedded data excluded.
+#[cfg(not(target_arch = "wasm32"))]
+fn gltf_uris(file: &[u8]) -> Vec<String> {
+    let Ok(gltf) = gltf::Gltf::from_slice(file) else {
+        return Vec::new();
+    };
+    let buffers = gltf.buffers().filter_map(|buffer| match buffer.source() {
+        gltf::buffer::Source::Uri(uri) => Some(uri),
+        gltf::buffer::Source::Bin => None,
+    });
+    let images = gltf.images().filter_map(|image| match image.source() {
+        gltf::image::Source::Uri { uri, .. } => Some(uri),
+        gltf::image::Source::View { .. } => None,
+    });
+    buffers
+        .chain(images)
+        .filter(|uri| !uri.starts_with("data:"))
+        .map(str::to_string)
+        .collect()
+}
+
+/**
+ * Bytes of the asset `file_name` followed by the names and bytes of the files it references,
+ * so the [`thumbnail_key`] changes when a texture or material library does.
+ *
+ * References resolve like the loaders resolve them. Files that can't be read only add their
+ * name, the loaders fail on the ones they need anyway.
+ */
+#[cfg(not(target_arch = "wasm32"))]
+async fn load_asset(file_name: &str) -> anyhow::Result<Vec<u8>> {
+    use crate::resources::texture::{load_binary, load_string};
+
+    let mut asset = load_binary(file_name).await?;
+    let extension = std::path::Path::new(file_name)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_ascii_lowercase);
+    let mut references = match extension.as_deref() {
+        Some("obj") => obj_material_libraries(&String::from_utf8_lossy(&asset)),
+        Some("gltf" | "glb") => gltf_uris(&asset),
+        _ => Vec::new(),
+    };
+    let mut idx = 0;
+    while let Some(reference) = references.get(idx).cloned() {
+        idx += 1;
+        asset.extend_from_slice(reference.as_bytes());
+        let Ok(bytes) = load_binary(&reference).await else {
+            continue;
+        };
+        if reference.to_ascii_lowercase().ends_with(".mtl") {
+            let mtl = load_string(&reference).await.unwrap_or_default();
+            references.extend(mtl_textures(&mtl));
+        }
+        asset.extend_from_slice(&(bytes.len() as u64).to_le_bytes());
+        asset.extend_from_slice(&bytes);
+    }
+    Ok(asset)
+}
+
+/// Directory of PNG thumbnails keyed by [`thumbnail_key`].
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Clone, Debug)]
+pub struct ThumbnailCache {
+    dir: std::path::PathBuf,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl ThumbnailCache {
+    /// Opens the cache in `dir`, creating the directory if needed.
+    pub fn new(dir: impl Into<std::path::PathBuf>) -> anyhow::Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+
+    fn path(&self, key: u64) -> std::path::PathBuf {
+        self.dir.join(format!("{:016x}.png", key))
+    }
+
+    /// Returns the cached thumbnail, `None` if there is none or it can't be decoded.
+    pub fn load(&self, file: &[u8], size: [u32; 2], style: &ThumbnailStyle) -> Option<RgbaImage> {
+        let path = self.path(thumbnail_key(file, size, style));
+        let image = image::open(path).ok()?.into_rgba8();
+        (image.dimensions() == (size[0], size[1])).then_some(image)
+    }
+
+    pub fn store(
+        &self,
+        file: &[u8],
+        style: &ThumbnailStyle,
+        image: &RgbaImage,
+    ) -> anyhow::Result<()> {
+        let size = [image.width(), image.height()];
+        let path = self.path(thumbnail_key(file, size, style));
+        image.save_with_format(path, image::ImageFormat::Png)?;
+        Ok(())
+    }
+
+    /// Returns the thumbnail of the OBJ or glTF asset `file_name`, rendering and storing it
+    /// on a cache miss.
+    ///
+    /// The asset is resolved like [`load_model_obj`](crate::resources::load_model_obj). Its
+    /// key also covers the material libraries, textures and buffers it references, so
+    /// changing one renders the thumbnail again.
+    pub async fn load_or_render(
+        &self,
+        renderer: &mut ThumbnailRenderer,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        file_name: &str,
+    ) -> anyhow::Result<RgbaImage> {
+        let file = load_asset(file_name).await?;
+        let style = *renderer.style();
+        if let Some(image) = self.load(&file, renderer.size(), &style) {
+            return Ok(image);
+        }
+        let extension = std::path::Path::new(file_name)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(str::to_ascii_lowercase);
+        let image = match extension.as_deref() {
+            Some("obj") => {
+                let model = crate::resources::load_model_obj(file_name, device, queue).await?;
+                renderer.render(device, queue, &model).await?
+            }
+            Some("gltf" | "glb") => {
+                let mut node =
+                    crate::resources::load_model_gltf(0, file_name, device, queue).await?;
+                renderer
+                    .render(device, queue, ThumbnailSubject::Scene(node.as_mut()))
+                    .await?
+            }
+            _ => bail!("Unsupported model format for thumbnails: {}", file_name),
+        };
+        self.store(&file, &style, &image)?;
+        Ok(image)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::project_to_screen;
+    use cgmath::Point3;
+
+    fn unit_box() -> Aabb {
+        Aabb::new(Point3::new(-1.0, 0.0, -1.0), Point3::new(1.0, 2.0, 1.0))
+    }
+
+    #[test]
+    fn framing_keeps_all_corners_on_screen() {
+        for size in [[128, 128], [256, 64], [64, 256]] {
+            let bounds = unit_box();
+            let style = ThumbnailStyle::default();
+            let (camera, projection) = frame(&bounds, size, &style).unwrap();
+            let view_proj = projection.calc_matrix() * camera.calc_matrix();
+            for corner in bounds.corners() {
+                let p = project_to_screen(view_proj, corner, size[0] as f32, size[1] as f32, false)
+                    .expect("corner in front of the camera");
+                assert!((0.0..=size[0] as f64).contains(&p.x), "{:?} {:?}", size, p);
+                assert!((0.0..=size[1] as f64).contains(&p.y), "{:?} {:?}", size, p);
+            }
+        }
+    }
+
+    #[test]
+    fn framing_centres_the_subject() {
+        let bounds = unit_box();
+        let (camera, projection) = frame(&bounds, [100, 100], &ThumbnailStyle::default()).unwrap();
+        let view_proj = projection.calc_matrix() * camera.calc_matrix();
+        let centre = project_to_screen(view_proj, bounds.center(), 100.0, 100.0, false).unwrap();
+        assert!((centre.x - 50.0).abs() < 1e-3 && (centre.y - 50.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn framing_depth_range_encloses_subject() {
+        let bounds = unit_box();
+        let (camera, projection) = frame(&bounds, [64, 64], &ThumbnailStyle::default()).unwrap();
+        let distance = cgmath::MetricSpace::distance(camera.position, bounds.center());
+        assert!(projection.znear > 0.0);
+        assert!(projection.znear < distance - bounds.radius());
+        assert!(projection.zfar > distance + bounds.radius());
+    }
+
+    #[test]
+    fn rows_are_padded_to_copy_alignment() {
+        assert_eq!(padded_bytes_per_row(64), 256);
+        assert_eq!(padded_bytes_per_row(65), 512);
+        assert_eq!(padded_bytes_per_row(1), 256);
+    }
+
+    #[test]
+    fn key_is_stable_and_depends_on_inputs() {
+        let style = ThumbnailStyle::default();
+        let key = thumbnail_key(b"model", [64, 64], &style);
+        assert_eq!(key, thumbnail_key(b"model", [64, 64], &style));
+        assert_ne!(key, thumbnail_key(b"model!", [64, 64], &style));
+        assert_ne!(key, thumbnail_key(b"model", [64, 32], &style));
+        let tilted = ThumbnailStyle {
+            pitch: Deg(-40.0),
+            ..style
+        };
+        assert_ne!(key, thumbnail_key(b"model", [64, 64], &tilted));
+    }
+
+    #[test]
+    fn key_light_shines_on_the_subject_from_the_camera_side() {
+        let eye = Point3::new(3.0, 4.0, 5.0);
+        let target = Point3::new(0.0, 1.0, 0.0);
+        let light = key_light(eye, target);
+        let position = Point3::from(light.position);
+        assert!((position - target).dot(eye - target) > 0.0);
+        assert!(position.y > target.y);
+        assert!((position.distance(target) - eye.distance(target)).abs() < 1e-4);
+        // Looking straight down has no horizontal right axis
+        let above = key_light(Point3::new(0.0, 5.0, 0.0), Point3::origin());
+        assert!(above.position.iter().all(|v| v.is_finite()));
+    }
+
+    #[test]
+    fn references_of_obj_and_mtl_files_are_found() {
+        let obj = "# cube\nmtllib cube.mtl extra.mtl\nv 0 0 0\nusemtl stone\n";
+        assert_eq!(obj_material_libraries(obj), ["cube.mtl", "extra.mtl"]);
+        let mtl = "newmtl stone\nKd 1 1 1\nmap_Kd stone.png\nmap_Bump -bm 0.5 stone_n.png\n";
+        assert_eq!(mtl_textures(mtl), ["stone.png", "stone_n.png"]);
+    }
+
+    #[test]
+    fn external_gltf_uris_are_found() {
+        let gltf = br#"{
+            "asset": { "version": "2.0" },
+            "buffers": [
+                { "uri": "mesh.bin", "byteLength": 4 },
+                { "uri": "data:application/octet-stream;base64,AAAAAA==", "byteLength": 4 }
+            ],
+            "images": [{ "uri": "albedo.png" }]
+        }"#;
+        assert_eq!(gltf_uris(gltf), ["mesh.bin", "albedo.png"]);
+    }
+
+    #[test]
+    fn fnv1a_matches_reference_values() {
+        assert_eq!(fnv1a(*b""), 0xcbf29ce484222325);
+        assert_eq!(fnv1a(*b"a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(fnv1a(*b"foobar"), 0x85944171f73967e8);
+    }
+
+    #[test]
+    fn cache_round_trip() {
+        let dir =
+            std::env::temp_dir().join(format!("flow_ngin_thumbnail_cache_{}", std::process::id()));
+        let cache = ThumbnailCache::new(&dir).unwrap();
+        let style = ThumbnailStyle::default();
+        let image = RgbaImage::from_fn(4, 2, |x, y| {
+            image::Rgba([x as u8 * 60, y as u8 * 100, 7, 255])
+        });
+
+        assert!(cache.load(b"asset", [4, 2], &style).is_none());
+        cache.store(b"asset", &style, &image).unwrap();
+        assert_eq!(cache.load(b"asset", [4, 2], &style), Some(image));
+        assert!(cache.load(b"other asset", [4, 2], &style).is_none());
+        assert!(cache.load(b"asset", [8, 4], &style).is_none());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,342 @@
+//! Checks the assets of [`RunConfig::validate_assets`] before any flow is constructed.
+//!
+//! Every manifest entry is loaded like the loaders would, including the files it references:
+//! the material libraries of an obj file and their textures, or the buffers and images of a
+//! gltf file. Instead of failing at the first missing file, all problems are collected into
+//! one [`AssetReport`], with a near-miss suggestion where a similarly named file exists.
+//!
+//! [`RunConfig::validate_assets`]: crate::flow::RunConfig::validate_assets
+
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fmt,
+    io::{BufReader, Cursor},
+};
+
+use crate::{
+    error::{Error, Result},
+    resources::texture::load_binary,
+};
+
+/// Why an asset failed to validate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AssetIssue {
+    Missing,
+    /// The file exists but couldn't be read, with the reason.
+    Unreadable(String),
+    /// The file was read but couldn't be parsed for the files it references.
+    Invalid(String),
+}
+
+/// An asset of the manifest, or a file referenced by one, that failed to validate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssetProblem {
+    pub path: String,
+    pub issue: AssetIssue,
+    /// The manifest entry and the file naming `path`, e.g. `Rock1.obj` and `Rock1.mtl`.
+    /// `None` for manifest entries themselves.
+    pub referenced_by: Option<(String, String)>,
+    /// An existing file with a similar name, e.g. `rock1_normal.png` for `Rock1_normal.png`.
+    pub suggestion: Option<String>,
+}
+
+/// All problems found by [`validate_assets`], empty if every asset loads.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AssetReport {
+    pub problems: Vec<AssetProblem>,
+}
+
+impl AssetReport {
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+
+    /// `Ok` without problems, otherwise [`Error::InvalidAssets`] carrying the report.
+    pub fn into_result(self) -> Result<()> {
+        if self.is_ok() {
+            return Ok(());
+        }
+        Err(Error::InvalidAssets { report: self })
+    }
+}
+
+impl fmt::Display for AssetProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let issue = match &self.issue {
+            AssetIssue::Missing => "missing".to_string(),
+            AssetIssue::Unreadable(reason) => format!("unreadable ({reason})"),
+            AssetIssue::Invalid(reason) => format!("invalid ({reason})"),
+        };
+        match &self.referenced_by {
+            Some((model, file)) if model == file => write!(f, "{model} → {issue} {}", self.path)?,
+            Some((model, file)) => write!(f, "{model} → {issue} {} from {file}", self.path)?,
+            None => write!(f, "{}: {issue}", self.path)?,
+        }
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, " (did you mean {suggestion}?)")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for AssetReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (idx, problem) in self.problems.iter().enumerate() {
+            if idx > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "  {problem}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Loads every asset of `manifest` and the files it references, relative to the `assets`
+/// directory (or URL on the web) like the loaders.
+pub async fn validate_assets(manifest: &[String]) -> AssetReport {
+    check(
+        manifest,
+        async |path: &str| load_binary(path).await,
+        siblings,
+    )
+    .await
+}
+
+/// Files next to `path` a missing file could have been meant as, relative to the assets.
+#[cfg(not(target_arch = "wasm32"))]
+fn siblings(path: &str) -> Vec<String> {
+    let dir = std::path::Path::new(path)
+        .parent()
+        .unwrap_or(std::path::Path::new(""));
+    let Ok(entries) = std::fs::read_dir(std::path::Path::new("./assets").join(dir)) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter(|entry| entry.file_type().is_ok_and(|ty| ty.is_file()))
+        .map(|entry| {
+            dir.join(entry.file_name())
+                .to_string_lossy()
+                .replace('\\', "/")
+        })
+        .collect()
+}
+
+/// Servers don't list directories, only the files that loaded are suggested on the web.
+#[cfg(target_arch = "wasm32")]
+fn siblings(_path: &str) -> Vec<String> {
+    Vec::new()
+}
+
+/// Files `bytes` of `path` references, relative to the assets like `path`.
+fn references(path: &str, bytes: &[u8]) -> Result<Vec<String>, String> {
+    let extension = path
+        .rsplit_once('.')
+        .map(|(_, ext)| ext.to_ascii_lowercase());
+    match extension.as_deref() {
+        Some("obj") => Ok(String::from_utf8_lossy(bytes)
+            .lines()
+            .filter_map(|line| line.trim().strip_prefix("mtllib "))
+            .map(|lib| lib.trim().to_string())
+            .collect()),
+        Some("mtl") => {
+            let (materials, _) = tobj::load_mtl_buf(&mut BufReader::new(Cursor::new(bytes)))
+                .map_err(|e| e.to_string())?;
+            // The textures the obj loader binds
+            Ok(materials
+                .into_iter()
+                .flat_map(|m| [m.diffuse_texture, m.normal_texture])
+                .flatten()
+                .collect())
+        }
+        Some("gltf" | "glb") => {
+            let gltf = gltf::Gltf::from_slice(bytes).map_err(|e| e.to_string())?;
+            let buffers = gltf.buffers().filter_map(|buffer| match buffer.source() {
+                gltf::buffer::Source::Uri(uri) => Some(uri),
+                gltf::buffer::Source::Bin => None,
+            });
+            let images = gltf.images().filter_map(|image| match image.source() {
+                gltf::image::Source::Uri { uri, .. } => Some(uri),
+                gltf::image::Source::View { .. } => None,
+            });
+            Ok(buffers
+                .chain(images)
+                .filter(|uri| !uri.starts_with("data:"))
+                .map(str::to_string)
+                .collect())
+        }
+        _ => Ok(Vec::new()),
+    }
+}
+
+async fn check(
+    manifest: &[String],
+    mut load: impl AsyncFnMut(&str) -> Result<Vec<u8>>,
+    siblings: impl Fn(&str) -> Vec<String>,
+) -> AssetReport {
+    // Files are shared between models, e.g. textures, so each is loaded once
+    let mut checked: HashMap<String, Result<Vec<String>, AssetIssue>> = HashMap::new();
+    let mut problems = Vec::new();
+    for entry in manifest {
+        // The entry and the files referencing each pending path
+        let mut pending = VecDeque::from([(entry.clone(), None::<String>)]);
+        let mut visited = HashSet::new();
+        while let Some((path, file)) = pending.pop_front() {
+            if !visited.insert(path.clone()) {
+                continue;
+            }
+            if !checked.contains_key(&path) {
+                let result = match load(&path).await {
+                    Ok(bytes) => references(&path, &bytes).map_err(AssetIssue::Invalid),
+                    Err(Error::AssetNotFound { .. }) => Err(AssetIssue::Missing),
+                    Err(e) => Err(AssetIssue::Unreadable(error_chain(&e))),
+                };
+                checked.insert(path.clone(), result);
+            }
+            match &checked[&path] {
+                Ok(references) => pending.extend(
+                    references
+                        .iter()
+                        .map(|reference| (reference.clone(), Some(path.clone()))),
+                ),
+                Err(issue) => problems.push(AssetProblem {
+                    referenced_by: file.map(|file| (entry.clone(), file)),
+                    path,
+                    issue: issue.clone(),
+                    suggestion: None,
+                }),
+            }
+        }
+    }
+
+    for problem in &mut problems {
+        if problem.issue != AssetIssue::Missing {
+            continue;
+        }
+        let loaded = checked
+            .iter()
+            .filter(|(_, result)| result.is_ok())
+            .map(|(path, _)| path.clone());
+        problem.suggestion = near_miss(
+            &problem.path,
+            siblings(&problem.path).into_iter().chain(loaded),
+        );
+    }
+    AssetReport { problems }
+}
+
+fn error_chain(error: &dyn std::error::Error) -> String {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(cause) = source {
+        message = format!("{message}: {cause}");
+        source = cause.source();
+    }
+    message
+}
+
+/// The candidate closest to `path` ignoring case, if it's within a typo or two.
+fn near_miss(path: &str, candidates: impl IntoIterator<Item = String>) -> Option<String> {
+    let target = path.to_lowercase();
+    let max_distance = (target.chars().count() / 4).clamp(1, 3);
+    candidates
+        .into_iter()
+        .filter(|candidate| candidate != path)
+        .map(|candidate| (edit_distance(&target, &candidate.to_lowercase()), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min()
+        .map(|(_, candidate)| candidate)
+}
+
+/// Levenshtein distance between `a` and `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(files: &[(&str, &str)], manifest: &[&str], listed: &[&str]) -> AssetReport {
+        let files: HashMap<String, Vec<u8>> = files
+            .iter()
+            .map(|(path, text)| (path.to_string(), text.as_bytes().to_vec()))
+            .collect();
+        let manifest: Vec<String> = manifest.iter().map(|path| path.to_string()).collect();
+        let listed: Vec<String> = listed.iter().map(|path| path.to_string()).collect();
+        futures::executor::block_on(check(
+            &manifest,
+            async |path: &str| {
+                files.get(path).cloned().ok_or(Error::AssetNotFound {
+                    path: path.to_string(),
+                })
+            },
+            |_| listed.clone(),
+        ))
+    }
+
+    #[test]
+    fn reports_every_missing_file_with_its_model() {
+        let report = run(
+            &[
+                ("Rock1.obj", "mtllib Rock1.mtl\nv 0 0 0\n"),
+                (
+                    "Rock1.mtl",
+                    "newmtl rock\nmap_Kd Rock1.png\nmap_Bump Rock1_normal.png\n",
+                ),
+                ("Rock1.png", ""),
+            ],
+            &["Rock1.obj", "Tree.obj"],
+            &["rock1_normal.png", "Rock1.png"],
+        );
+        assert_eq!(report.problems.len(), 2);
+        assert_eq!(
+            report.problems[0].to_string(),
+            "Rock1.obj → missing Rock1_normal.png from Rock1.mtl (did you mean rock1_normal.png?)"
+        );
+        assert_eq!(report.problems[1].to_string(), "Tree.obj: missing");
+        assert!(matches!(
+            report.into_result(),
+            Err(Error::InvalidAssets { report }) if report.problems.len() == 2
+        ));
+    }
+
+    #[test]
+    fn valid_manifests_pass() {
+        let report = run(
+            &[
+                ("cube.obj", "mtllib cube.mtl\n"),
+                ("cube.mtl", "newmtl a\n"),
+            ],
+            &["cube.obj"],
+            &[],
+        );
+        assert!(report.into_result().is_ok());
+    }
+
+    #[test]
+    fn suggestions_need_a_close_name() {
+        let candidates = || ["grass.png".to_string(), "stone_wall.png".to_string()];
+        assert_eq!(
+            near_miss("gras.png", candidates()),
+            Some("grass.png".into())
+        );
+        assert_eq!(
+            near_miss("Stone_Wall.png", candidates()),
+            Some("stone_wall.png".into())
+        );
+        assert_eq!(near_miss("water.png", candidates()), None);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
+}
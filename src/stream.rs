@@ -0,0 +1,482 @@
+//! Loading assets during gameplay without a hitch, see [`Context::spawn_load`].
+//!
+//! A streamed load runs in two parts. Its future reads and decodes the files in the
+//! background, on the tokio runtime on non-wasm environments, and resolves to [`Uploads`]:
+//! the GPU work split into small steps. Each frame runs steps of the resolved loads in the
+//! order they resolved until [`Context::stream_budget`] is used up, at least one per
+//! frame. Once all steps of a load ran, its value is passed to the `finish` closure of
+//! `spawn_load` and the returned event reaches the flows' `on_custom_events`.
+//!
+//! [`load_model_obj`] streams an obj model with its materials, one step per texture and
+//! a few per mesh.
+
+use std::{
+    any::Any,
+    collections::{HashMap, HashSet, VecDeque},
+    fmt::Debug,
+    io::{BufReader, Cursor},
+    pin::Pin,
+};
+
+use crate::{
+    context::Context,
+    data_structures::{
+        model::{Material, Mesh, Model},
+        texture::Texture,
+    },
+    error::{Error, Result},
+    resources::{
+        mesh::MeshData,
+        texture::{load_binary, load_string},
+    },
+};
+
+/// Bytes written to a buffer per upload step of [`load_model_obj`].
+const CHUNK_SIZE: usize = 1 << 20;
+
+/// Identifies a load of [`Context::spawn_load`], e.g. to cancel it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LoadTicket(u64);
+
+#[cfg(not(target_arch = "wasm32"))]
+type UploadStep<T> = Box<dyn FnOnce(&Context, &mut T) + Send>;
+#[cfg(target_arch = "wasm32")]
+type UploadStep<T> = Box<dyn FnOnce(&Context, &mut T)>;
+
+/**
+ * The GPU work of a streamed load, run a step at a time between frames.
+ *
+ * Each step gets the [`Context`] to create buffers and textures and the value built so far.
+ * Keep steps short: the frame budget is only checked between them.
+ */
+pub struct Uploads<T> {
+    value: T,
+    steps: VecDeque<UploadStep<T>>,
+}
+
+impl<T> Uploads<T> {
+    /// Starts from `value`, which is passed on as is without further steps.
+    pub fn new(value: T) -> Self {
+        Self {
+            value,
+            steps: VecDeque::new(),
+        }
+    }
+
+    /// Runs `step` after the steps added before.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn then(mut self, step: impl FnOnce(&Context, &mut T) + Send + 'static) -> Self {
+        self.steps.push_back(Box::new(step));
+        self
+    }
+
+    /// Runs `step` after the steps added before.
+    #[cfg(target_arch = "wasm32")]
+    pub fn then(mut self, step: impl FnOnce(&Context, &mut T) + 'static) -> Self {
+        self.steps.push_back(Box::new(step));
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+}
+
+/// A load whose future resolved, erased as the context isn't generic over values and events.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) trait Job: Send {
+    /// Runs the next step, `true` once none are left.
+    fn upload(&mut self, ctx: &Context) -> bool;
+    /// The event of the finished load.
+    fn finish(self: Box<Self>) -> Box<dyn Any + Send>;
+}
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) trait Job {
+    fn upload(&mut self, ctx: &Context) -> bool;
+    fn finish(self: Box<Self>) -> Box<dyn Any + Send>;
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) type JobFuture = Pin<Box<dyn Future<Output = Box<dyn Job>> + Send>>;
+#[cfg(target_arch = "wasm32")]
+pub(crate) type JobFuture = Pin<Box<dyn Future<Output = Box<dyn Job>>>>;
+
+#[cfg(not(target_arch = "wasm32"))]
+type FinishFn<T, E> = Box<dyn FnOnce(anyhow::Result<T>) -> E + Send>;
+#[cfg(target_arch = "wasm32")]
+type FinishFn<T, E> = Box<dyn FnOnce(anyhow::Result<T>) -> E>;
+
+struct Streamed<T, E> {
+    // The error of a failed future, passed on to `finish`
+    uploads: anyhow::Result<Uploads<T>>,
+    finish: FinishFn<T, E>,
+}
+
+macro_rules! impl_job {
+    ($($send:ident)?) => {
+        impl<T: $($send +)? 'static, E: Send + 'static> Job for Streamed<T, E> {
+            fn upload(&mut self, ctx: &Context) -> bool {
+                let Ok(uploads) = &mut self.uploads else {
+                    return true;
+                };
+                if let Some(step) = uploads.steps.pop_front() {
+                    step(ctx, &mut uploads.value);
+                }
+                uploads.steps.is_empty()
+            }
+
+            fn finish(self: Box<Self>) -> Box<dyn Any + Send> {
+                let value = self.uploads.map(|uploads| uploads.value);
+                Box::new((self.finish)(value))
+            }
+        }
+    };
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl_job!(Send);
+#[cfg(target_arch = "wasm32")]
+impl_job!();
+
+/// Loads of [`Context::spawn_load`] from their spawn to their last upload step.
+#[derive(Default)]
+pub(crate) struct Streams {
+    next_ticket: u64,
+    // Waiting for the app to run their futures
+    spawned: Vec<(LoadTicket, JobFuture)>,
+    // Futures running in the background
+    running: HashSet<LoadTicket>,
+    // Resolved, in the order they did
+    uploading: VecDeque<(LoadTicket, Box<dyn Job>)>,
+}
+
+impl Debug for Streams {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Streams")
+            .field("spawned", &self.spawned.len())
+            .field("running", &self.running)
+            .field("uploading", &self.uploading.len())
+            .finish()
+    }
+}
+
+impl Streams {
+    pub(crate) fn spawn(&mut self, future: JobFuture) -> LoadTicket {
+        let ticket = LoadTicket(self.next_ticket);
+        self.next_ticket += 1;
+        self.spawned.push((ticket, future));
+        ticket
+    }
+
+    /// Futures for the app to run, each handed back through [`Streams::resolve`].
+    pub(crate) fn take_spawned(&mut self) -> Vec<(LoadTicket, JobFuture)> {
+        let spawned = std::mem::take(&mut self.spawned);
+        self.running
+            .extend(spawned.iter().map(|(ticket, _)| *ticket));
+        spawned
+    }
+
+    /// Queues the uploads of a resolved future, dropped if the load was cancelled meanwhile.
+    pub(crate) fn resolve(&mut self, ticket: LoadTicket, job: Box<dyn Job>) {
+        if self.running.remove(&ticket) {
+            self.uploading.push_back((ticket, job));
+        }
+    }
+
+    pub(crate) fn cancel(&mut self, ticket: LoadTicket) {
+        self.spawned.retain(|(spawned, _)| *spawned != ticket);
+        self.running.remove(&ticket);
+        self.uploading.retain(|(uploading, _)| *uploading != ticket);
+    }
+
+    pub(crate) fn is_loading(&self, ticket: LoadTicket) -> bool {
+        self.spawned.iter().any(|(spawned, _)| *spawned == ticket)
+            || self.running.contains(&ticket)
+            || self
+                .uploading
+                .iter()
+                .any(|(uploading, _)| *uploading == ticket)
+    }
+
+    pub(crate) fn is_uploading(&self) -> bool {
+        !self.uploading.is_empty()
+    }
+
+    /// The load to run the next upload step of.
+    pub(crate) fn next_upload(&mut self) -> Option<(LoadTicket, Box<dyn Job>)> {
+        self.uploading.pop_front()
+    }
+
+    /// Puts back a load of [`Streams::next_upload`] with steps left.
+    pub(crate) fn requeue(&mut self, ticket: LoadTicket, job: Box<dyn Job>) {
+        self.uploading.push_front((ticket, job));
+    }
+}
+
+/// Wraps the future of [`Context::spawn_load`] so it resolves to an erased [`Job`].
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn job<T: Send + 'static, E: Send + 'static>(
+    load: impl Future<Output = anyhow::Result<Uploads<T>>> + Send + 'static,
+    finish: impl FnOnce(anyhow::Result<T>) -> E + Send + 'static,
+) -> JobFuture {
+    Box::pin(async move {
+        let uploads = load.await;
+        Box::new(Streamed {
+            uploads,
+            finish: Box::new(finish),
+        }) as Box<dyn Job>
+    })
+}
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn job<T: 'static, E: Send + 'static>(
+    load: impl Future<Output = anyhow::Result<Uploads<T>>> + 'static,
+    finish: impl FnOnce(anyhow::Result<T>) -> E + 'static,
+) -> JobFuture {
+    Box::pin(async move {
+        let uploads = load.await;
+        Box::new(Streamed {
+            uploads,
+            finish: Box::new(finish),
+        }) as Box<dyn Job>
+    })
+}
+
+/**
+ * Reads and parses an obj model with its materials for [`Context::spawn_load`].
+ *
+ * Files are read and textures decoded by the returned future. The uploads create one texture
+ * pair per step and write the meshes' buffers in chunks of 1 MiB, materials are bound to
+ * [`crate::context::Layouts::diffuse_normal`].
+ */
+pub async fn load_model_obj(file_name: &str) -> Result<Uploads<Model>> {
+    let obj_text = load_string(file_name).await?;
+    // tobj only loads material libraries synchronously, so they're read up front
+    let mut libraries = HashMap::new();
+    for library in material_libraries(&obj_text) {
+        libraries.insert(library.clone(), load_string(&library).await?);
+    }
+    let (models, obj_materials) = tobj::load_obj_buf(
+        &mut BufReader::new(Cursor::new(obj_text)),
+        &tobj::LoadOptions {
+            triangulate: true,
+            single_index: true,
+            ..Default::default()
+        },
+        |path| match libraries.get(path.to_string_lossy().as_ref()) {
+            Some(text) => tobj::load_mtl_buf(&mut BufReader::new(Cursor::new(text))),
+            None => Err(tobj::LoadError::OpenFileFailed),
+        },
+    )
+    .map_err(|e| Error::decode(file_name, e))?;
+    let obj_materials = obj_materials.map_err(|e| Error::decode(file_name, e))?;
+
+    let mut uploads = Uploads::new(Model {
+        meshes: Vec::new(),
+        materials: Vec::new(),
+    });
+    for m in obj_materials {
+        let Some(diffuse_name) = m.diffuse_texture else {
+            log::error!("This material's mtl ({file_name}) references no texture.");
+            continue;
+        };
+        let diffuse = decode(&diffuse_name).await?;
+        let normal = match m.normal_texture {
+            Some(normal_name) => Some((decode(&normal_name).await?, normal_name)),
+            None => None,
+        };
+        let file_name = file_name.to_string();
+        uploads = uploads.then(move |ctx, model| {
+            let (device, queue) = (&ctx.device, &ctx.queue);
+            let textures = Texture::from_image(device, queue, &diffuse, Some(&diffuse_name), false)
+                .and_then(|diffuse| {
+                    let normal = match &normal {
+                        Some((image, name)) => {
+                            Texture::from_image(device, queue, image, Some(name), true)?
+                        }
+                        None => Texture::create_default_normal_map(1, 1, device, queue),
+                    };
+                    Ok((diffuse, normal))
+                });
+            let material = textures
+                .map_err(anyhow::Error::from)
+                .and_then(|(diffuse, normal)| {
+                    Material::new(
+                        device,
+                        &m.name,
+                        diffuse,
+                        normal,
+                        &ctx.layouts.diffuse_normal,
+                    )
+                });
+            match material {
+                Ok(material) => model.materials.push(material),
+                Err(e) => log::warn!(
+                    "Failed to create material for mtl ({}) in obj ({file_name}): {e}",
+                    m.name
+                ),
+            }
+        });
+    }
+    for (idx, m) in models.iter().enumerate() {
+        match MeshData::from_obj(m) {
+            Ok(mesh) => uploads = upload_mesh(uploads, mesh, file_name),
+            Err(_) => log::warn!(
+                "Mesh at index {} in file {} could not be loaded due to overflows. Make sure you use the right scale in your .obj export settings.",
+                idx,
+                file_name
+            ),
+        }
+    }
+    Ok(uploads)
+}
+
+/// Names of the material libraries an obj file references.
+fn material_libraries(obj_text: &str) -> Vec<String> {
+    obj_text
+        .lines()
+        .filter_map(|line| line.trim_start().strip_prefix("mtllib"))
+        .flat_map(|names| names.split_whitespace().map(str::to_string))
+        .collect()
+}
+
+async fn decode(file_name: &str) -> Result<image::DynamicImage> {
+    let data = load_binary(file_name).await?;
+    image::load_from_memory(&data).map_err(|e| Error::decode(file_name, e))
+}
+
+/// Creates the mesh's buffers in one step and fills them in the following ones.
+fn upload_mesh(mut uploads: Uploads<Model>, mesh: MeshData, file_name: &str) -> Uploads<Model> {
+    let name = file_name.to_string();
+    let vertices: &[u8] = bytemuck::cast_slice(&mesh.vertices);
+    let indices: &[u8] = bytemuck::cast_slice(&mesh.indices);
+    let (vertex_size, index_size) = (vertices.len() as u64, indices.len() as u64);
+    let (num_elements, material, bounds) = (mesh.num_elements, mesh.material, mesh.bounds());
+    uploads = uploads.then(move |ctx, model| {
+        let buffer = |label: String, size: u64, usage: wgpu::BufferUsages| {
+            ctx.device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(&label),
+                size,
+                usage: usage | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })
+        };
+        let vertex_buffer = buffer(
+            format!("{:?} Vertex Buffer", name),
+            vertex_size,
+            wgpu::BufferUsages::VERTEX,
+        );
+        let index_buffer = buffer(
+            format!("{:?} Index Buffer", name),
+            index_size,
+            wgpu::BufferUsages::INDEX,
+        );
+        model.meshes.push(Mesh {
+            memory: Mesh::track_buffers(&vertex_buffer, &index_buffer, &name),
+            name,
+            vertex_buffer,
+            index_buffer,
+            num_elements,
+            material,
+            bounds,
+        });
+    });
+    // Steps run in order, so the mesh is the last one pushed when they do
+    for (offset, chunk) in chunks(vertices) {
+        uploads = uploads.then(move |ctx, model| {
+            if let Some(mesh) = model.meshes.last() {
+                ctx.queue.write_buffer(&mesh.vertex_buffer, offset, &chunk);
+            }
+        });
+    }
+    for (offset, chunk) in chunks(indices) {
+        uploads = uploads.then(move |ctx, model| {
+            if let Some(mesh) = model.meshes.last() {
+                ctx.queue.write_buffer(&mesh.index_buffer, offset, &chunk);
+            }
+        });
+    }
+    uploads
+}
+
+/// `data` split into pieces of [`CHUNK_SIZE`] with their offsets.
+fn chunks(data: &[u8]) -> Vec<(u64, Vec<u8>)> {
+    data.chunks(CHUNK_SIZE)
+        .enumerate()
+        .map(|(at, chunk)| ((at * CHUNK_SIZE) as u64, chunk.to_vec()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolved(streams: &mut Streams) -> Vec<(LoadTicket, Box<dyn Job>)> {
+        streams
+            .take_spawned()
+            .into_iter()
+            .map(|(ticket, future)| (ticket, futures::executor::block_on(future)))
+            .collect()
+    }
+
+    #[test]
+    fn cancelled_loads_never_upload() {
+        let mut streams = Streams::default();
+        let kept = streams.spawn(job(async { Ok(Uploads::new(1)) }, |value| value.ok()));
+        let dropped = streams.spawn(job(async { Ok(Uploads::new(2)) }, |value| value.ok()));
+        let queued = streams.spawn(job(async { Ok(Uploads::new(3)) }, |value| value.ok()));
+        assert!(streams.is_loading(kept) && streams.is_loading(dropped));
+
+        streams.cancel(queued);
+        let jobs = resolved(&mut streams);
+        assert_eq!(jobs.len(), 2, "cancelled before its future ran");
+        streams.cancel(dropped);
+        for (ticket, job) in jobs {
+            streams.resolve(ticket, job);
+        }
+        assert!(!streams.is_loading(dropped) && !streams.is_loading(queued));
+
+        let (ticket, job) = streams.next_upload().expect("resolved load");
+        assert_eq!(ticket, kept);
+        let event = job
+            .finish()
+            .downcast::<Option<i32>>()
+            .expect("event of `finish`");
+        assert_eq!(*event, Some(1));
+        assert!(streams.next_upload().is_none());
+        assert!(!streams.is_loading(kept));
+    }
+
+    #[test]
+    fn failed_loads_still_finish() {
+        let mut streams = Streams::default();
+        let load = async { Err::<Uploads<()>, _>(anyhow::anyhow!("missing file")) };
+        streams.spawn(job(load, |value| value.map_err(|e| e.to_string())));
+        for (ticket, job) in resolved(&mut streams) {
+            streams.resolve(ticket, job);
+        }
+        let (_, job) = streams.next_upload().expect("resolved load");
+        let event = job
+            .finish()
+            .downcast::<Result<(), String>>()
+            .expect("event of `finish`");
+        assert_eq!(*event, Err("missing file".to_string()));
+    }
+
+    #[test]
+    fn material_libraries_are_read_from_the_obj() {
+        let obj = "# cube\nmtllib cube.mtl\n  mtllib a.mtl b.mtl\nv 0 0 0\nusemtl Material\n";
+        assert_eq!(material_libraries(obj), ["cube.mtl", "a.mtl", "b.mtl"]);
+    }
+
+    #[test]
+    fn obj_models_load_in_the_background() {
+        fn assert_send(_: impl Send) {}
+        assert_send(load_model_obj("cube.obj"));
+    }
+}
@@ -1,6 +1,7 @@
 use std::sync::Arc;
 
 use crate::{
+    error::Result,
     pipelines::gui::{mk_bind_group, mk_bind_group_layout},
     resources::texture::load_texture,
 };
@@ -15,13 +16,11 @@ pub struct BackgroundTexture {
 
 impl BackgroundTexture {
     /// Load a single image file as a background texture.
-    pub async fn new(device: &wgpu::Device, queue: &wgpu::Queue, file_name: &str) -> Self {
-        let texture = load_texture(file_name, false, device, queue, None)
-            .await
-            .unwrap();
+    pub async fn new(device: &wgpu::Device, queue: &wgpu::Queue, file_name: &str) -> Result<Self> {
+        let texture = load_texture(file_name, false, device, queue, None).await?;
         let texture_bind_group_layout = mk_bind_group_layout(device);
         let bind_group = mk_bind_group(device, &texture, &texture_bind_group_layout);
-        BackgroundTexture { bind_group }
+        Ok(BackgroundTexture { bind_group })
     }
 }
 
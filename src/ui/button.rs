@@ -237,9 +237,10 @@ impl<S: 'static, E: Send + 'static> Layout for Button<S, E> {
 
 impl<S: 'static, E: Send + 'static> GraphicsFlow<S, E> for Button<S, E> {
     fn on_init(&mut self, ctx: &mut Context, _: &mut S) -> Out<S, E> {
-        // Resolve own placement against screen dimensions.
+        // Resolve own placement against the screen in UI pixels.
         // For nested buttons, the parent's Layout::resolve will override afterward.
-        let (x, y, w, h) = self.placement.resolve(0, 0, ctx.config.width, ctx.config.height);
+        let (width, height) = ctx.ui_size();
+        let (x, y, w, h) = self.placement.resolve(0, 0, width, height);
         self.x = x;
         self.y = y;
         self.width = w;
@@ -256,7 +257,7 @@ impl<S: 'static, E: Send + 'static> GraphicsFlow<S, E> for Button<S, E> {
     }
 
     fn on_update(&mut self, ctx: &Context, state: &mut S, _dt: Duration) -> Out<S, E> {
-        let pos = ctx.mouse.coords;
+        let pos = ctx.ui_mouse();
         let hovered = self.contains(pos.x, pos.y);
         let is_pressed = matches!(ctx.mouse.pressed, MouseButtonState::Left);
 
@@ -28,7 +28,7 @@ use crate::{
 ///
 /// // In on_init:
 /// let icon = Icon::new(ctx, atlas, 0).width(64).height(64);
-/// let card_bg = Arc::new(BackgroundTexture::new(&ctx.device, &ctx.queue, "card.png").await);
+/// let card_bg = Arc::new(BackgroundTexture::new(&ctx.device, &ctx.queue, "card.png").await?);
 ///
 /// let card = Card::<State, Event>::new()
 ///     .width(200)
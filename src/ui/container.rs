@@ -6,10 +6,10 @@ use wgpu::{
 };
 
 use instant::Duration;
-use winit::event::WindowEvent;
+use winit::{dpi::PhysicalSize, event::WindowEvent};
 
 use crate::{
-    context::Context, data_structures::texture::Texture, flow::{FlowConstructor, GraphicsFlow, Out}, pick::PickId, pipelines::gui::{mk_bind_group, mk_bind_group_layout}, render::{Flat, Render}, ui::{
+    context::Context, data_structures::texture::Texture, flow::{FlowConstructor, GraphicsFlow, Out}, pick::PickId, pipelines::gui::mk_bind_group, render::{Flat, Render}, ui::{
         HAlign, Placement, VAlign,
         background::{Background, BackgroundTexture},
         image::{Frame, pixels_to_frame, vertices_from_coords},
@@ -18,12 +18,7 @@ use crate::{
 };
 
 pub fn merge_outs<S, E: Send>(outs: impl Iterator<Item = Out<S, E>>) -> Out<S, E> {
-    let collected: Vec<Out<S, E>> = outs.filter(|o| !matches!(o, Out::Empty)).collect();
-    match collected.len() {
-        0 => Out::Empty,
-        1 => collected.into_iter().next().unwrap(),
-        _ => Out::Composed(collected),
-    }
+    outs.collect()
 }
 
 /// Backing GPU resources for the container background quad.
@@ -62,9 +57,10 @@ impl BgResources {
 ///     .halign(HAlign::Center)
 ///     .valign(VAlign::Center);
 ///
+/// let (width, height) = ctx.ui_size();
 /// let container = Container::<State, Event>::new()
-///     .width(ctx.config.width)
-///     .height(ctx.config.height)
+///     .width(width)
+///     .height(height)
 ///     .with_child(icon)
 ///     .with_child(TextLabel::new("Score: 0"));
 /// ```
@@ -176,16 +172,17 @@ impl<S: 'static, E: Send + 'static> Container<S, E> {
     pub fn into_constructor(self) -> FlowConstructor<S, E> {
         Box::new(|_ctx| {
             // TODO: find a way to limit the heavy boxing in general
-            Box::pin(async move { Box::new(self) as Box<dyn GraphicsFlow<S, E>> })
+            Box::pin(async move { Ok(Box::new(self) as Box<dyn GraphicsFlow<S, E>>) })
         })
     }
 }
 
 impl<S: 'static, E: Send + 'static> GraphicsFlow<S, E> for Container<S, E> {
     fn on_init(&mut self, ctx: &mut Context, state: &mut S) -> Out<S, E> {
-        // Resolve own placement against screen dimensions.
+        // Resolve own placement against the screen in UI pixels.
         // For nested containers, the parent's Layout::resolve will override afterward.
-        let (x, y, w, h) = self.placement.resolve(0, 0, ctx.config.width, ctx.config.height);
+        let (width, height) = ctx.ui_size();
+        let (x, y, w, h) = self.placement.resolve(0, 0, width, height);
         self.x = x;
         self.y = y;
         self.width = w;
@@ -199,8 +196,7 @@ impl<S: 'static, E: Send + 'static> GraphicsFlow<S, E> for Container<S, E> {
             let source = match bg {
                 Background::Color(rgba) => {
                     let tex = Texture::from_color(*rgba, &ctx.device, &ctx.queue);
-                    let layout = mk_bind_group_layout(&ctx.device);
-                    let bind_group = mk_bind_group(&ctx.device, &tex, &layout);
+                    let bind_group = mk_bind_group(&ctx.device, &tex, &ctx.layouts.gui);
                     BgSource::Color(bind_group)
                 }
                 Background::Texture(arc) => BgSource::Texture(Arc::clone(arc)),
@@ -241,13 +237,15 @@ impl<S: 'static, E: Send + 'static> GraphicsFlow<S, E> for Container<S, E> {
     }
 
     fn on_window_events(&mut self, ctx: &Context, state: &mut S, event: &WindowEvent) -> Out<S, E> {
-        if let WindowEvent::Resized(_) = event {
-            Layout::resolve(self, 0, 0, ctx.config.width, ctx.config.height, &ctx.queue);
-            return Out::Empty;
-        }
         merge_outs(self.children.iter_mut().map(|c| c.on_window_events(ctx, state, event)))
     }
 
+    fn on_resize(&mut self, ctx: &Context, _: &mut S, _: PhysicalSize<u32>) -> Out<S, E> {
+        let (width, height) = ctx.ui_size();
+        Layout::resolve(self, 0, 0, width, height, &ctx.queue);
+        Out::Empty
+    }
+
     fn on_render<'pass>(&self) -> Render<'_, 'pass> {
         let mut renders: Vec<Render<'_, 'pass>> = Vec::new();
 
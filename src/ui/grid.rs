@@ -1,7 +1,7 @@
 use cgmath::num_traits::ToPrimitive;
 use instant::Duration;
 
-use winit::event::WindowEvent;
+use winit::{dpi::PhysicalSize, event::WindowEvent};
 
 use crate::{
     context::Context,
@@ -139,7 +139,8 @@ impl<S: 'static, E: Send + 'static> Grid<S, E> {
 
 impl<S: 'static, E: Send + 'static> GraphicsFlow<S, E> for Grid<S, E> {
     fn on_init(&mut self, ctx: &mut Context, state: &mut S) -> Out<S, E> {
-        let (x, y, w, h) = self.placement.resolve(0, 0, ctx.config.width, ctx.config.height);
+        let (width, height) = ctx.ui_size();
+        let (x, y, w, h) = self.placement.resolve(0, 0, width, height);
         self.x = x;
         self.y = y;
         self.width = w;
@@ -158,13 +159,15 @@ impl<S: 'static, E: Send + 'static> GraphicsFlow<S, E> for Grid<S, E> {
     }
 
     fn on_window_events(&mut self, ctx: &Context, state: &mut S, event: &WindowEvent) -> Out<S, E> {
-        if let WindowEvent::Resized(_) = event {
-            Layout::resolve(self, 0, 0, ctx.config.width, ctx.config.height, &ctx.queue);
-            return Out::Empty;
-        }
         merge_outs(self.cells.iter_mut().map(|c| c.on_window_events(ctx, state, event)))
     }
 
+    fn on_resize(&mut self, ctx: &Context, _: &mut S, _: PhysicalSize<u32>) -> Out<S, E> {
+        let (width, height) = ctx.ui_size();
+        Layout::resolve(self, 0, 0, width, height, &ctx.queue);
+        Out::Empty
+    }
+
     fn on_render<'pass>(&self) -> Render<'_, 'pass> {
         Render::Composed(self.cells.iter().map(|c| c.on_render()).collect())
     }
@@ -7,7 +7,7 @@ use wgpu::{
 };
 
 use crate::{
-    context::Context, data_structures::texture::Texture, flow::GraphicsFlow, pick::PickId, pipelines::gui::{Vertex, mk_bind_group, mk_bind_group_layout}, render::{Flat, Render}, resources::texture::load_texture, ui::{Placement, layout::Layout}
+    context::Context, data_structures::texture::Texture, error::Result, flow::GraphicsFlow, pick::PickId, pipelines::gui::{Vertex, mk_bind_group, mk_bind_group_layout}, render::{Flat, Render}, resources::texture::load_texture, ui::{Placement, layout::Layout}
 };
 
 pub struct ImageResources {
@@ -47,16 +47,16 @@ pub struct Atlas {
     atlas_height_px: u32,
 }
 impl Atlas {
+    /// Loads the atlas image `file_name` split into `h_grids` by `v_grids` cells, failing if
+    /// the image is missing or can't be decoded.
     pub async fn new(
         device: &wgpu::Device,
         queue: &wgpu::Queue,
         file_name: &str,
         h_grids: u8,
         v_grids: u8,
-    ) -> Self {
-        let mut atlas = load_texture(file_name, false, device, queue, None)
-            .await
-            .expect(&format!("File does not exist: {}", file_name));
+    ) -> Result<Self> {
+        let mut atlas = load_texture(file_name, false, device, queue, None).await?;
         let size = atlas.texture.size();
 
         // Use ClampToEdge to prevent UV wrapping at atlas cell boundaries.
@@ -72,13 +72,13 @@ impl Atlas {
 
         let texture_bind_group_layout = mk_bind_group_layout(device);
         let bind_group = mk_bind_group(device, &atlas, &texture_bind_group_layout);
-        Atlas {
+        Ok(Atlas {
             bind_group,
             h_grids,
             v_grids,
             atlas_width_px: size.width,
             atlas_height_px: size.height,
-        }
+        })
     }
     fn to_tex_coords(&self, slot: u8) -> Option<Frame> {
         // Use u16 arithmetic to handle 16×16 = 256 cells without u8 overflow.
@@ -161,8 +161,7 @@ impl Icon {
         };
 
         let tex = Texture::from_color(rgba, &ctx.device, &ctx.queue);
-        let layout = mk_bind_group_layout(&ctx.device);
-        let normal = mk_bind_group(&ctx.device, &tex, &layout);
+        let normal = mk_bind_group(&ctx.device, &tex, &ctx.layouts.gui);
 
         let vertices = vertices_from_coords(&screen_pos, &screen_pos);
         let vertex_buffer = ctx.device.create_buffer_init(&BufferInitDescriptor {
@@ -0,0 +1,374 @@
+use std::{cell::RefCell, collections::HashMap};
+
+use cgmath::{EuclideanSpace, InnerSpace, Point3, Vector3};
+use glyphon::{
+    Attrs, Buffer, Color, Family, FontSystem, Metrics, Resolution, Shaping, SwashCache, TextArea,
+    TextBounds,
+};
+
+use crate::{
+    context::Context,
+    flow::{GraphicsFlow, Out},
+    render::Render,
+    ui::text_label::{GlyphonRenderer, new_font_system},
+};
+
+/// Handle of a label added to [`Labels`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LabelId(u32);
+
+struct Label {
+    target: Point3<f32>,
+    offset: Vector3<f32>,
+    text: String,
+    max_distance: f32,
+    buffer: Option<Buffer>,
+    // Unscaled size of the shaped text in pixels
+    size: (f32, f32),
+    // Top left corner and scale this frame, `None` while hidden
+    screen: Option<(f32, f32, f32)>,
+}
+
+struct LabelResources {
+    font_system: FontSystem,
+    swash_cache: SwashCache,
+    renderer: GlyphonRenderer,
+}
+
+/// Name tags drawn over positions in the 3D scene, e.g. above units.
+///
+/// Every label is centred above the screen position of its target plus offset. It is hidden
+/// behind the camera or beyond its `max_distance`. All labels are drawn in a single text
+/// batch on top of the scene. Text is only reshaped when it changes and follows the
+/// [UI scale](Context::set_ui_scale). Labels are never pickable.
+///
+/// ```ignore
+/// let mut labels = Labels::new().clamp_to_edges(true);
+/// let tag = labels.add([0.0, 0.0, 0.0], "Worker", Vector3::unit_y(), 50.0);
+/// // every frame, e.g. in `on_update`:
+/// labels.set_target(tag, unit.position);
+/// labels.update(ctx);
+/// ```
+pub struct Labels {
+    labels: HashMap<LabelId, Label>,
+    next_id: u32,
+    font_size: f32,
+    line_height: f32,
+    color: [u8; 3],
+    clamp_to_edges: bool,
+    reference_distance: Option<f32>,
+    resources: RefCell<Option<LabelResources>>,
+}
+
+impl Labels {
+    pub fn new() -> Self {
+        Self {
+            labels: HashMap::new(),
+            next_id: 0,
+            font_size: 18.0,
+            line_height: 24.0,
+            color: [255, 255, 255],
+            clamp_to_edges: false,
+            reference_distance: None,
+            resources: RefCell::new(None),
+        }
+    }
+
+    pub fn font_size(mut self, size: f32) -> Self {
+        self.font_size = size;
+        self
+    }
+
+    pub fn line_height(mut self, height: f32) -> Self {
+        self.line_height = height;
+        self
+    }
+
+    pub fn color(mut self, color: [u8; 3]) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Keeps labels of targets outside the window at the window border instead of hiding them.
+    pub fn clamp_to_edges(mut self, clamp: bool) -> Self {
+        self.clamp_to_edges = clamp;
+        self
+    }
+
+    /// Shrinks labels further away than `reference` proportionally to their distance.
+    /// `None`, the default, keeps a constant size.
+    pub fn scale_with_distance(mut self, reference: Option<f32>) -> Self {
+        self.reference_distance = reference;
+        self
+    }
+
+    /// Adds a label drawn above `target + offset` while the camera is within `max_distance`.
+    pub fn add(
+        &mut self,
+        target: impl Into<Point3<f32>>,
+        text: impl Into<String>,
+        offset: Vector3<f32>,
+        max_distance: f32,
+    ) -> LabelId {
+        let id = LabelId(self.next_id);
+        self.next_id += 1;
+        self.labels.insert(
+            id,
+            Label {
+                target: target.into(),
+                offset,
+                text: text.into(),
+                max_distance,
+                buffer: None,
+                size: (0.0, 0.0),
+                screen: None,
+            },
+        );
+        id
+    }
+
+    pub fn remove(&mut self, id: LabelId) -> bool {
+        self.labels.remove(&id).is_some()
+    }
+
+    /// Moves the label along with its target, call it whenever the target moves.
+    pub fn set_target(&mut self, id: LabelId, target: impl Into<Point3<f32>>) {
+        if let Some(label) = self.labels.get_mut(&id) {
+            label.target = target.into();
+        }
+    }
+
+    pub fn set_text(&mut self, id: LabelId, text: &str) {
+        if let Some(label) = self.labels.get_mut(&id)
+            && label.text != text
+        {
+            label.text = text.to_string();
+            // reshaped on the next update
+            label.buffer = None;
+        }
+    }
+
+    pub fn set_offset(&mut self, id: LabelId, offset: Vector3<f32>) {
+        if let Some(label) = self.labels.get_mut(&id) {
+            label.offset = offset;
+        }
+    }
+
+    pub fn set_max_distance(&mut self, id: LabelId, max_distance: f32) {
+        if let Some(label) = self.labels.get_mut(&id) {
+            label.max_distance = max_distance;
+        }
+    }
+
+    /// Initialize GPU resources. Called automatically by `GraphicsFlow::on_init`;
+    /// call directly when embedding in a custom flow.
+    pub fn init(&mut self, ctx: &mut Context) {
+        *self.resources.borrow_mut() = Some(LabelResources {
+            font_system: new_font_system(),
+            swash_cache: SwashCache::new(),
+            renderer: GlyphonRenderer::new(ctx),
+        });
+    }
+
+    /// Shapes changed texts and projects all labels for this frame. Call it every frame after
+    /// moving targets or the camera, e.g. in `on_update`.
+    pub fn update(&mut self, ctx: &Context) {
+        let mut guard = self.resources.borrow_mut();
+        let Some(res) = guard.as_mut() else { return };
+        let camera = ctx.camera.view_camera();
+        let screen = (ctx.config.width as f32, ctx.config.height as f32);
+        let text_scale = ctx.ui_text_scale(self.font_size);
+        for label in self.labels.values_mut() {
+            if label.buffer.is_none() {
+                let mut buffer = Buffer::new(
+                    &mut res.font_system,
+                    Metrics::new(self.font_size, self.line_height),
+                );
+                buffer.set_size(&mut res.font_system, None, None);
+                buffer.set_text(
+                    &mut res.font_system,
+                    &label.text,
+                    &Attrs::new().family(Family::SansSerif),
+                    Shaping::Advanced,
+                    None,
+                );
+                buffer.shape_until_scroll(&mut res.font_system, false);
+                label.size = buffer.layout_runs().fold((0.0, 0.0), |(w, h), run| {
+                    (f32::max(w, run.line_w), h + run.line_height)
+                });
+                label.buffer = Some(buffer);
+            }
+
+            let world = label.target + label.offset;
+            let distance = (world - camera.position).magnitude();
+            label.screen = if distance > label.max_distance {
+                None
+            } else {
+                camera
+                    .world_to_screen(world.to_vec(), screen.0, screen.1, &ctx.projection)
+                    .and_then(|anchor| {
+                        let scale =
+                            distance_scale(distance, self.reference_distance) * text_scale;
+                        let size = (label.size.0 * scale, label.size.1 * scale);
+                        let anchor = (anchor.x as f32, anchor.y as f32);
+                        place_label(anchor, size, screen, self.clamp_to_edges)
+                            .map(|(left, top)| (left, top, scale))
+                    })
+            };
+        }
+    }
+
+    /// Return a [`Render`] drawing all visible labels. Use this when embedding in a custom
+    /// flow's `on_render`.
+    pub fn render<'a, 'pass>(&'a self) -> Render<'a, 'pass> {
+        let [r, g, b] = self.color;
+        Render::Custom(Box::new(move |ctx, render_pass| {
+            let mut guard = self.resources.borrow_mut();
+            let Some(res) = guard.as_mut() else { return };
+
+            res.renderer.sync(ctx);
+            let LabelResources {
+                font_system,
+                swash_cache,
+                renderer:
+                    GlyphonRenderer {
+                        viewport,
+                        atlas,
+                        text_renderer,
+                        ..
+                    },
+            } = res;
+
+            viewport.update(
+                &ctx.queue,
+                Resolution {
+                    width: ctx.config.width,
+                    height: ctx.config.height,
+                },
+            );
+
+            let areas = self.labels.values().filter_map(|label| {
+                let (left, top, scale) = label.screen?;
+                let buffer = label.buffer.as_ref()?;
+                Some(TextArea {
+                    buffer,
+                    left,
+                    top,
+                    scale,
+                    bounds: TextBounds {
+                        left: left.floor() as i32,
+                        top: top.floor() as i32,
+                        right: (left + label.size.0 * scale).ceil() as i32,
+                        bottom: (top + label.size.1 * scale).ceil() as i32,
+                    },
+                    default_color: Color::rgb(r, g, b),
+                    custom_glyphs: &[],
+                })
+            });
+
+            text_renderer
+                .prepare(
+                    &ctx.device,
+                    &ctx.queue,
+                    font_system,
+                    atlas,
+                    viewport,
+                    areas,
+                    swash_cache,
+                )
+                .unwrap();
+
+            text_renderer
+                .render(&*atlas, viewport, render_pass)
+                .unwrap();
+
+            atlas.trim();
+        }))
+    }
+}
+
+impl Default for Labels {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S, E: Send> GraphicsFlow<S, E> for Labels {
+    fn on_init(&mut self, ctx: &mut Context, _: &mut S) -> Out<S, E> {
+        self.init(ctx);
+        Out::Empty
+    }
+
+    fn on_update(&mut self, ctx: &Context, _: &mut S, _: instant::Duration) -> Out<S, E> {
+        self.update(ctx);
+        Out::Empty
+    }
+
+    fn on_render<'pass>(&self) -> Render<'_, 'pass> {
+        self.render()
+    }
+}
+
+/// Scale of a label `distance` away, shrinking beyond `reference`.
+fn distance_scale(distance: f32, reference: Option<f32>) -> f32 {
+    match reference {
+        Some(reference) if distance > reference => reference / distance,
+        _ => 1.0,
+    }
+}
+
+/// Top left corner of a label of `size` centred above `anchor`. `None` if it lies completely
+/// outside the screen and isn't clamped to its edges.
+fn place_label(
+    anchor: (f32, f32),
+    size: (f32, f32),
+    screen: (f32, f32),
+    clamp: bool,
+) -> Option<(f32, f32)> {
+    let left = anchor.0 - size.0 / 2.0;
+    let top = anchor.1 - size.1;
+    if clamp {
+        return Some((
+            left.clamp(0.0, (screen.0 - size.0).max(0.0)),
+            top.clamp(0.0, (screen.1 - size.1).max(0.0)),
+        ));
+    }
+    let visible = left + size.0 > 0.0 && left < screen.0 && top + size.1 > 0.0 && top < screen.1;
+    visible.then_some((left, top))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn label_sits_centred_above_anchor() {
+        let placed = place_label((400.0, 300.0), (100.0, 20.0), (800.0, 600.0), false);
+        assert_eq!(placed, Some((350.0, 280.0)));
+    }
+
+    #[test]
+    fn off_screen_label_is_hidden_or_clamped() {
+        let anchor = (-200.0, 300.0);
+        assert_eq!(
+            place_label(anchor, (100.0, 20.0), (800.0, 600.0), false),
+            None
+        );
+        assert_eq!(
+            place_label(anchor, (100.0, 20.0), (800.0, 600.0), true),
+            Some((0.0, 280.0))
+        );
+        let below = (900.0, 700.0);
+        assert_eq!(
+            place_label(below, (100.0, 20.0), (800.0, 600.0), true),
+            Some((700.0, 580.0))
+        );
+    }
+
+    #[test]
+    fn labels_shrink_beyond_reference_distance() {
+        assert_eq!(distance_scale(50.0, None), 1.0);
+        assert_eq!(distance_scale(5.0, Some(10.0)), 1.0);
+        assert_eq!(distance_scale(40.0, Some(10.0)), 0.25);
+    }
+}
@@ -1,5 +1,6 @@
 pub mod layout;
 pub mod text_label;
+pub mod labels;
 pub mod image;
 pub mod container;
 pub mod background;
@@ -22,6 +23,7 @@ pub use checkbox::Checkbox;
 pub use slider::Slider;
 pub use text_input::TextInput;
 pub use grid::Grid;
+pub use labels::{LabelId, Labels};
 pub use vstack::VStack;
 pub use value::Value;
 
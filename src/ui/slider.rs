@@ -202,7 +202,8 @@ impl<S: 'static, E: Send + 'static> Layout for Slider<S, E> {
 
 impl<S: 'static, E: Send + 'static> GraphicsFlow<S, E> for Slider<S, E> {
     fn on_init(&mut self, ctx: &mut Context, _: &mut S) -> Out<S, E> {
-        let (x, y, w, h) = self.placement.resolve(0, 0, ctx.config.width, ctx.config.height);
+        let (width, height) = ctx.ui_size();
+        let (x, y, w, h) = self.placement.resolve(0, 0, width, height);
         self.x = x;
         self.y = y;
         self.width = w;
@@ -214,7 +215,7 @@ impl<S: 'static, E: Send + 'static> GraphicsFlow<S, E> for Slider<S, E> {
     }
 
     fn on_update(&mut self, ctx: &Context, _state: &mut S, _dt: Duration) -> Out<S, E> {
-        let pos = ctx.mouse.coords;
+        let pos = ctx.ui_mouse();
         let is_pressed = matches!(ctx.mouse.pressed, MouseButtonState::Left);
 
         if is_pressed && !self.dragging && self.contains(pos.x, pos.y) {
@@ -238,7 +238,8 @@ impl<S: 'static, E: Send + 'static> Layout for TextInput<S, E> {
 
 impl<S: 'static, E: Send + 'static> GraphicsFlow<S, E> for TextInput<S, E> {
     fn on_init(&mut self, ctx: &mut Context, _: &mut S) -> Out<S, E> {
-        let (x, y, w, h) = self.placement.resolve(0, 0, ctx.config.width, ctx.config.height);
+        let (width, height) = ctx.ui_size();
+        let (x, y, w, h) = self.placement.resolve(0, 0, width, height);
         self.x = x;
         self.y = y;
         self.width = w;
@@ -272,7 +273,7 @@ impl<S: 'static, E: Send + 'static> GraphicsFlow<S, E> for TextInput<S, E> {
     }
 
     fn on_update(&mut self, ctx: &Context, _state: &mut S, dt: Duration) -> Out<S, E> {
-        let pos = ctx.mouse.coords;
+        let pos = ctx.ui_mouse();
         let is_pressed = matches!(ctx.mouse.pressed, MouseButtonState::Left);
         let clicked = self.was_pressed && !is_pressed;
         self.was_pressed = is_pressed;
@@ -1,4 +1,4 @@
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 
 use glyphon::{
     Attrs, Buffer, Cache, Color, Family, FontSystem, Metrics, Resolution, Shaping, SwashCache,
@@ -8,17 +8,100 @@ use glyphon::{
 
 use crate::{
     context::Context,
+    data_structures::texture::DepthConfig,
     flow::{FlowConstructor, GraphicsFlow, Out},
     render::Render,
     ui::{HAlign, Placement, VAlign, layout::Layout},
 };
 
+/// Font system with the system fonts, or the bundled Roboto on the web where those aren't
+/// available.
+pub(crate) fn new_font_system() -> FontSystem {
+    #[cfg(not(target_arch = "wasm32"))]
+    let font_system = FontSystem::new();
+    #[cfg(target_arch = "wasm32")]
+    let font_system = {
+        let mut fs = FontSystem::new_with_locale_and_db(
+            "en-US".into(),
+            glyphon::cosmic_text::fontdb::Database::new(),
+        );
+        fs.db_mut()
+            .load_font_data(include_bytes!("../../assets/fonts/Roboto-Regular.ttf").to_vec());
+        fs
+    };
+    font_system
+}
+
+/// Attachments a glyphon renderer draws into, see [`GlyphonRenderer`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct RenderTarget {
+    format: wgpu::TextureFormat,
+    sample_count: u32,
+    depth: DepthConfig,
+}
+
+impl RenderTarget {
+    fn of(ctx: &Context) -> Self {
+        Self {
+            format: ctx.color_format(),
+            sample_count: ctx.anti_aliasing.sample_count(),
+            depth: ctx.depth(),
+        }
+    }
+}
+
+/// Viewport, atlas and renderer for the scene pass of `ctx`, rebuilt by
+/// [`GlyphonRenderer::sync`] once the pass changes, e.g. through [`Context::set_hdr`].
+pub(crate) struct GlyphonRenderer {
+    target: RenderTarget,
+    pub(crate) viewport: Viewport,
+    pub(crate) atlas: TextAtlas,
+    pub(crate) text_renderer: TextRenderer,
+}
+
+impl GlyphonRenderer {
+    pub(crate) fn new(ctx: &Context) -> Self {
+        let target = RenderTarget::of(ctx);
+        let cache = Cache::new(&ctx.device);
+        let viewport = Viewport::new(&ctx.device, &cache);
+        let mut atlas = TextAtlas::new(&ctx.device, &ctx.queue, &cache, target.format);
+        let text_renderer = TextRenderer::new(
+            &mut atlas,
+            &ctx.device,
+            wgpu::MultisampleState {
+                count: target.sample_count,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            Some(wgpu::DepthStencilState {
+                format: target.depth.format,
+                depth_write_enabled: Some(false),
+                depth_compare: Some(target.depth.compare(wgpu::CompareFunction::Always)),
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+        );
+        Self {
+            target,
+            viewport,
+            atlas,
+            text_renderer,
+        }
+    }
+
+    /// Rebuilds the renderer if the colour format, sample count or depth of the scene pass
+    /// changed since it was built.
+    pub(crate) fn sync(&mut self, ctx: &Context) {
+        if self.target != RenderTarget::of(ctx) {
+            *self = Self::new(ctx);
+        }
+    }
+}
+
 struct GlyphonResources {
     font_system: FontSystem,
     swash_cache: SwashCache,
-    viewport: Viewport,
-    atlas: TextAtlas,
-    text_renderer: TextRenderer,
+    renderer: GlyphonRenderer,
     text_buffer: Buffer,
 }
 
@@ -70,6 +153,8 @@ pub struct TextLabel {
     resolved_y: f32,
     resolved_w: f32,
     resolved_h: f32,
+    // Text scale over UI scale in the last frame, above 1 while raised to the minimum size
+    glyph_scale: Cell<f32>,
     resources: RefCell<Option<GlyphonResources>>,
 }
 
@@ -85,6 +170,7 @@ impl TextLabel {
             resolved_y: 0.0,
             resolved_w: 0.0,
             resolved_h: 0.0,
+            glyph_scale: Cell::new(1.0),
             resources: RefCell::new(None),
         }
     }
@@ -164,40 +250,12 @@ impl TextLabel {
     /// Initialize GPU resources. Called automatically by `GraphicsFlow::on_init`;
     /// call directly when embedding in a custom flow.
     pub fn init(&mut self, ctx: &mut Context) {
-        self.resolve_placement(0, 0, ctx.config.width, ctx.config.height);
-
-        #[cfg(not(target_arch = "wasm32"))]
-        let mut font_system = FontSystem::new();
-        #[cfg(target_arch = "wasm32")]
-        let mut font_system = {
-            let mut fs = FontSystem::new_with_locale_and_db(
-                "en-US".into(),
-                glyphon::cosmic_text::fontdb::Database::new(),
-            );
-            fs.db_mut()
-                .load_font_data(include_bytes!("../../assets/fonts/Roboto-Regular.ttf").to_vec());
-            fs
-        };
+        let (width, height) = ctx.ui_size();
+        self.resolve_placement(0, 0, width, height);
+
+        let mut font_system = new_font_system();
         let swash_cache = SwashCache::new();
-        let cache = Cache::new(&ctx.device);
-        let viewport = Viewport::new(&ctx.device, &cache);
-        let mut atlas = TextAtlas::new(&ctx.device, &ctx.queue, &cache, ctx.config.format);
-        let text_renderer = TextRenderer::new(
-            &mut atlas,
-            &ctx.device,
-            wgpu::MultisampleState {
-                count: ctx.anti_aliasing.sample_count(),
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
-            Some(wgpu::DepthStencilState {
-                format: wgpu::TextureFormat::Depth32Float,
-                depth_write_enabled: Some(false),
-                depth_compare: Some(wgpu::CompareFunction::Always),
-                stencil: wgpu::StencilState::default(),
-                bias: wgpu::DepthBiasState::default(),
-            }),
-        );
+        let renderer = GlyphonRenderer::new(ctx);
 
         let mut text_buffer =
             Buffer::new(&mut font_system, Metrics::new(self.font_size, self.line_height));
@@ -218,9 +276,7 @@ impl TextLabel {
         *self.resources.borrow_mut() = Some(GlyphonResources {
             font_system,
             swash_cache,
-            viewport,
-            atlas,
-            text_renderer,
+            renderer,
             text_buffer,
         });
     }
@@ -233,12 +289,17 @@ impl TextLabel {
             let mut guard = self.resources.borrow_mut();
             let Some(res) = guard.as_mut() else { return };
 
+            res.renderer.sync(ctx);
             let GlyphonResources {
                 font_system,
                 swash_cache,
-                viewport,
-                atlas,
-                text_renderer,
+                renderer:
+                    GlyphonRenderer {
+                        viewport,
+                        atlas,
+                        text_renderer,
+                        ..
+                    },
                 text_buffer,
             } = res;
 
@@ -250,14 +311,23 @@ impl TextLabel {
                 },
             );
 
+            // Placement is in UI pixels, text may be scaled further up to the minimum size
+            let ui_scale = ctx.ui_pixel_scale();
+            let text_scale = ctx.ui_text_scale(self.font_size);
+            let (x, y) = (self.resolved_x * ui_scale, self.resolved_y * ui_scale);
+            let (w, h) = (self.resolved_w * ui_scale, self.resolved_h * ui_scale);
+            text_buffer.set_size(font_system, Some(w / text_scale), Some(h / text_scale));
+            text_buffer.shape_until_scroll(font_system, false);
+            self.glyph_scale.set(text_scale / ui_scale);
+
             let text_height: f32 = text_buffer
                 .layout_runs()
-                .map(|run| run.line_height)
+                .map(|run| run.line_height * text_scale)
                 .sum();
             let top = match self.placement.valign {
-                VAlign::Top => self.resolved_y,
-                VAlign::Center => self.resolved_y + (self.resolved_h - text_height) / 2.0,
-                VAlign::Bottom => self.resolved_y + self.resolved_h - text_height,
+                VAlign::Top => y,
+                VAlign::Center => y + (h - text_height) / 2.0,
+                VAlign::Bottom => y + h - text_height,
             };
 
             text_renderer
@@ -269,14 +339,14 @@ impl TextLabel {
                     viewport,
                     [TextArea {
                         buffer: text_buffer,
-                        left: self.resolved_x,
+                        left: x,
                         top,
-                        scale: 1.0,
+                        scale: text_scale,
                         bounds: TextBounds {
-                            left: self.resolved_x as i32,
-                            top: self.resolved_y as i32,
-                            right: (self.resolved_x + self.resolved_w) as i32,
-                            bottom: (self.resolved_y + self.resolved_h) as i32,
+                            left: x as i32,
+                            top: y as i32,
+                            right: (x + w) as i32,
+                            bottom: (y + h) as i32,
                         },
                         default_color: Color::rgb(r, g, b),
                         custom_glyphs: &[],
@@ -291,7 +361,7 @@ impl TextLabel {
         }))
     }
 
-    /// Return the x-offset (in pixels) where a cursor at the given byte position
+    /// Return the x-offset (in UI pixels) where a cursor at the given byte position
     /// should be placed, relative to the start of the text.
     pub fn cursor_x_for_byte_pos(&self, byte_pos: usize) -> f32 {
         let guard = self.resources.borrow();
@@ -301,11 +371,11 @@ impl TextLabel {
         for run in res.text_buffer.layout_runs() {
             for glyph in run.glyphs.iter() {
                 if byte_pos >= glyph.start && byte_pos < glyph.end {
-                    return glyph.x;
+                    return glyph.x * self.glyph_scale.get();
                 }
             }
             // Cursor is past last glyph => return end of line.
-            return run.line_w;
+            return run.line_w * self.glyph_scale.get();
         }
         0.0
     }
@@ -318,7 +388,7 @@ impl TextLabel {
     /// Wrap this label in a [`FlowConstructor`] for use with [`flow_ngin::flow::run`].
     pub fn into_constructor<S: 'static, E: Send + 'static>(self) -> FlowConstructor<S, E> {
         Box::new(|_ctx| {
-            Box::pin(async move { Box::new(self) as Box<dyn GraphicsFlow<S, E>> })
+            Box::pin(async move { Ok(Box::new(self) as Box<dyn GraphicsFlow<S, E>>) })
         })
     }
 }
@@ -1,6 +1,6 @@
 use instant::Duration;
 
-use winit::event::WindowEvent;
+use winit::{dpi::PhysicalSize, event::WindowEvent};
 
 use crate::{
     context::Context,
@@ -117,7 +117,8 @@ impl<S: 'static, E: Send + 'static> Layout for VStack<S, E> {
 
 impl<S: 'static, E: Send + 'static> GraphicsFlow<S, E> for VStack<S, E> {
     fn on_init(&mut self, ctx: &mut Context, state: &mut S) -> Out<S, E> {
-        let (x, y, w, h) = self.placement.resolve(0, 0, ctx.config.width, ctx.config.height);
+        let (width, height) = ctx.ui_size();
+        let (x, y, w, h) = self.placement.resolve(0, 0, width, height);
         self.x = x;
         self.y = y;
         self.width = w;
@@ -136,13 +137,15 @@ impl<S: 'static, E: Send + 'static> GraphicsFlow<S, E> for VStack<S, E> {
     }
 
     fn on_window_events(&mut self, ctx: &Context, state: &mut S, event: &WindowEvent) -> Out<S, E> {
-        if let WindowEvent::Resized(_) = event {
-            Layout::resolve(self, 0, 0, ctx.config.width, ctx.config.height, &ctx.queue);
-            return Out::Empty;
-        }
         merge_outs(self.children.iter_mut().map(|(_, c)| c.on_window_events(ctx, state, event)))
     }
 
+    fn on_resize(&mut self, ctx: &Context, _: &mut S, _: PhysicalSize<u32>) -> Out<S, E> {
+        let (width, height) = ctx.ui_size();
+        Layout::resolve(self, 0, 0, width, height, &ctx.queue);
+        Out::Empty
+    }
+
     fn on_render<'pass>(&self) -> Render<'_, 'pass> {
         Render::Composed(self.children.iter().map(|(_, c)| c.on_render()).collect())
     }
@@ -0,0 +1,177 @@
+//! Secondary OS windows, see [`Context::create_window`].
+//!
+//! Each window has its own surface, depth and MSAA textures and draws the flows whose
+//! [`GraphicsFlow::window`] returns its id through one of the registered cameras. Device,
+//! pipelines and assets are shared with the main window, so closing a secondary window
+//! releases only its surface and textures.
+//!
+//! [`Context::create_window`]: crate::context::Context::create_window
+//! [`GraphicsFlow::window`]: crate::flow::GraphicsFlow::window
+
+use std::sync::Arc;
+
+use winit::window::{Window, WindowAttributes};
+
+use crate::{data_structures::texture, error::Error};
+
+/// Window renders are drawn into, see [`Context::create_window`].
+///
+/// [`Context::create_window`]: crate::context::Context::create_window
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct WindowId(pub(crate) u32);
+
+impl WindowId {
+    /// The window the app was started with.
+    pub const MAIN: WindowId = WindowId(0);
+}
+
+/// A window of `create_window` the event loop hasn't opened yet.
+#[derive(Debug)]
+pub(crate) struct PendingWindow {
+    pub(crate) id: WindowId,
+    pub(crate) attributes: WindowAttributes,
+    pub(crate) camera: String,
+}
+
+/// An open secondary window and its render targets.
+#[derive(Debug)]
+pub(crate) struct SecondaryWindow {
+    pub(crate) window: Arc<Window>,
+    /// Name of the camera the window is drawn through.
+    pub(crate) camera: String,
+    surface: wgpu::Surface<'static>,
+    config: wgpu::SurfaceConfiguration,
+    depth_texture: texture::Texture,
+    msaa_view: Option<wgpu::TextureView>,
+}
+
+impl SecondaryWindow {
+    /// Creates the surface of `window` in `format`, the one the pipelines were built for, and
+    /// a depth buffer of `depth_format`.
+    pub(crate) fn new(
+        window: Arc<Window>,
+        camera: String,
+        (instance, adapter): (&wgpu::Instance, &wgpu::Adapter),
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+        depth_format: wgpu::TextureFormat,
+    ) -> Result<Self, Error> {
+        let surface = instance
+            .create_surface(window.clone())
+            .map_err(|e| Error::Gpu(e.into()))?;
+        let caps = surface.get_capabilities(adapter);
+        if !caps.formats.contains(&format) {
+            return Err(Error::Unsupported {
+                feature: format!("a secondary window without the {format:?} surface format"),
+            });
+        }
+        let size = window.inner_size();
+        let config = wgpu::SurfaceConfiguration {
+            // Copied for the blurred pause overlay where supported
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | (caps.usages & wgpu::TextureUsages::COPY_SRC),
+            format,
+            width: size.width.max(1),
+            height: size.height.max(1),
+            // The only mode every surface supports
+            present_mode: wgpu::PresentMode::Fifo,
+            alpha_mode: caps.alpha_modes[0],
+            view_formats: vec![],
+            desired_maximum_frame_latency: 2,
+        };
+        surface.configure(device, &config);
+        let (depth_texture, msaa_view) = mk_targets(device, &config, sample_count, depth_format);
+        Ok(Self {
+            window,
+            camera,
+            surface,
+            config,
+            depth_texture,
+            msaa_view,
+        })
+    }
+
+    pub(crate) fn size(&self) -> [u32; 2] {
+        [self.config.width, self.config.height]
+    }
+
+    /// Reconfigures the surface and textures for `width` x `height`, zero sizes are skipped.
+    pub(crate) fn resize(
+        &mut self,
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        sample_count: u32,
+        depth_format: wgpu::TextureFormat,
+    ) {
+        if width == 0 || height == 0 {
+            return;
+        }
+        self.config.width = width;
+        self.config.height = height;
+        self.surface.configure(device, &self.config);
+        self.rebuild_targets(device, sample_count, depth_format);
+    }
+
+    /// Recreates the depth and MSAA textures, e.g. after the anti-aliasing changed.
+    pub(crate) fn rebuild_targets(
+        &mut self,
+        device: &wgpu::Device,
+        sample_count: u32,
+        depth_format: wgpu::TextureFormat,
+    ) {
+        (self.depth_texture, self.msaa_view) =
+            mk_targets(device, &self.config, sample_count, depth_format);
+    }
+
+    /// The texture to draw the next frame into, `None` if the window can't be drawn now. A
+    /// lost or outdated surface is reconfigured for the next frame.
+    pub(crate) fn current_texture(&self, device: &wgpu::Device) -> Option<wgpu::SurfaceTexture> {
+        match self.surface.get_current_texture() {
+            wgpu::CurrentSurfaceTexture::Success(tex)
+            | wgpu::CurrentSurfaceTexture::Suboptimal(tex) => Some(tex),
+            wgpu::CurrentSurfaceTexture::Outdated | wgpu::CurrentSurfaceTexture::Lost => {
+                self.surface.configure(device, &self.config);
+                None
+            }
+            wgpu::CurrentSurfaceTexture::Timeout
+            | wgpu::CurrentSurfaceTexture::Occluded
+            | wgpu::CurrentSurfaceTexture::Validation => None,
+        }
+    }
+
+    /// Colour, resolve and depth attachments drawing to `view` of the current texture.
+    pub(crate) fn attachments<'v>(
+        &'v self,
+        view: &'v wgpu::TextureView,
+    ) -> (
+        &'v wgpu::TextureView,
+        Option<&'v wgpu::TextureView>,
+        &'v wgpu::TextureView,
+    ) {
+        (
+            self.msaa_view.as_ref().unwrap_or(view),
+            self.msaa_view.as_ref().map(|_| view),
+            &self.depth_texture.view,
+        )
+    }
+}
+
+fn mk_targets(
+    device: &wgpu::Device,
+    config: &wgpu::SurfaceConfiguration,
+    sample_count: u32,
+    depth_format: wgpu::TextureFormat,
+) -> (texture::Texture, Option<wgpu::TextureView>) {
+    let depth_texture = texture::Texture::create_depth_texture(
+        device,
+        [config.width, config.height],
+        "secondary_window_depth_texture",
+        sample_count,
+        depth_format,
+    );
+    let msaa_view = (sample_count > 1)
+        .then(|| texture::Texture::create_msaa_texture(device, config, sample_count));
+    (depth_texture, msaa_view)
+}
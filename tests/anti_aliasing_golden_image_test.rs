@@ -124,11 +124,12 @@ fn anti_aliased_render_should_differ_from_non_aa() {
                 1,
                 "Rock1.obj",
             )
-            .await;
-            Box::new(AAComparisonFlow {
+            .await
+            .expect("failed to load Rock1.obj");
+            Ok(Box::new(AAComparisonFlow {
                 model,
                 baseline: RefCell::new(None),
-            }) as Box<dyn GraphicsFlow<_, _>>
+            }) as Box<dyn GraphicsFlow<_, _>>)
         })
     });
 
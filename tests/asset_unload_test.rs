@@ -0,0 +1,83 @@
+#[cfg(feature = "integration-tests")]
+mod common;
+
+/// Switching from level 1 to level 2 keeps the textures of level 2 and frees the ones only
+/// level 1 loaded, once the GPU is done with the frames in flight.
+#[test]
+#[cfg(feature = "integration-tests")]
+fn retain_only_reclaims_textures_of_previous_level() {
+    use flow_ngin::{
+        context::{Context, InitContext},
+        data_structures::texture::Texture,
+        flow::{FlowConstructor, GraphicsFlow, ImageTestResult, Out},
+        resources::memory::MemoryCategory,
+    };
+
+    use crate::common::test_utils::FrameCounter;
+
+    const LEVEL1_SIZE: u32 = 64;
+    const LEVEL2_SIZE: u32 = 32;
+    // Frames to wait for the GPU to release the unloaded textures
+    const MAX_FRAMES: u32 = 10;
+
+    #[derive(Default)]
+    struct LevelSwitchFlow {
+        texture_bytes_before: u64,
+    }
+
+    impl GraphicsFlow<FrameCounter, ()> for LevelSwitchFlow {
+        fn on_init(&mut self, ctx: &mut Context, _s: &mut FrameCounter) -> Out<FrameCounter, ()> {
+            self.texture_bytes_before = ctx.memory_report(0).category(MemoryCategory::Texture);
+            let unloaded = ctx.assets.retain_only(&["level2"]);
+            assert_eq!(unloaded, 1);
+            assert!(!ctx.assets.contains("level1/rocks"));
+            assert!(ctx.assets.contains("level2/grass"));
+            Out::Empty
+        }
+
+        fn on_update(
+            &mut self,
+            _ctx: &Context,
+            state: &mut FrameCounter,
+            _dt: std::time::Duration,
+        ) -> Out<FrameCounter, ()> {
+            state.progress();
+            Out::Empty
+        }
+
+        fn render_to_texture(
+            &self,
+            ctx: &Context,
+            s: &mut FrameCounter,
+            _texture: &mut image::ImageBuffer<image::Rgba<u8>, wgpu::BufferView>,
+        ) -> Result<ImageTestResult, anyhow::Error> {
+            let texture_bytes = ctx.memory_report(0).category(MemoryCategory::Texture);
+            let reclaimed = self.texture_bytes_before.saturating_sub(texture_bytes);
+            let expected = u64::from(LEVEL1_SIZE * LEVEL1_SIZE * 4);
+            if reclaimed == expected {
+                Ok(ImageTestResult::Passed)
+            } else if s.frame() < MAX_FRAMES {
+                Ok(ImageTestResult::Waiting)
+            } else {
+                anyhow::bail!(
+                    "reclaimed {reclaimed} texture bytes after {MAX_FRAMES} frames, expected {expected}"
+                )
+            }
+        }
+    }
+
+    let constructor: FlowConstructor<FrameCounter, ()> = Box::new(|ctx: InitContext| {
+        Box::pin(async move {
+            let level_texture =
+                |size| Texture::create_default_normal_map(size, size, &ctx.device, &ctx.queue);
+            // Only the cache holds on to the textures afterwards
+            ctx.assets
+                .insert_texture("level1/rocks", level_texture(LEVEL1_SIZE), Some("level1"));
+            ctx.assets
+                .insert_texture("level2/grass", level_texture(LEVEL2_SIZE), Some("level2"));
+            Ok(Box::new(LevelSwitchFlow::default()) as Box<dyn GraphicsFlow<_, _>>)
+        })
+    });
+
+    flow_ngin::flow::run(vec![constructor]).expect("Integration test failed");
+}
@@ -18,7 +18,9 @@ fn atlas_should_not_bleed_neighbouring_cell_colors() {
 
     golden_image_test!(async move |ctx: InitContext| {
         let atlas = Arc::new(
-            Atlas::new(&ctx.device, &ctx.queue, "bleeding_atlas.png", 2, 1).await,
+            Atlas::new(&ctx.device, &ctx.queue, "bleeding_atlas.png", 2, 1)
+                .await
+                .expect("failed to load bleeding_atlas.png"),
         );
 
         TestUIRender::with_validator(
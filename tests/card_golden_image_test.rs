@@ -19,10 +19,14 @@ fn should_match_card_render() {
 
     golden_image_test!(async move |ctx: InitContext| {
         let atlas = Arc::new(
-            Atlas::new(&ctx.device, &ctx.queue, "card_atlas.png", 16, 16).await,
+            Atlas::new(&ctx.device, &ctx.queue, "card_atlas.png", 16, 16)
+                .await
+                .expect("failed to load card_atlas.png"),
         );
         let bg = Arc::new(
-            BackgroundTexture::new(&ctx.device, &ctx.queue, "card_bg.png").await,
+            BackgroundTexture::new(&ctx.device, &ctx.queue, "card_bg.png")
+                .await
+                .expect("failed to load card_bg.png"),
         );
 
         TestUIRender::new(
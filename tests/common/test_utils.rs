@@ -9,7 +9,9 @@ use flow_ngin::{
     context::{Context, GPUResource},
     data_structures::block::BuildingBlocks,
     flow::{GraphicsFlow, ImageTestResult, Out},
+    pick::{PickId, PickImage},
     render::Render,
+    PhysicalPosition,
 };
 #[cfg(feature = "integration-tests")]
 use wgpu::RenderPass;
@@ -235,6 +237,102 @@ where
     }
 }
 
+/// Validation closure of [`PickTestRender`]. Receives the pick id of every window pixel and
+/// the ids `on_click` was called with so far.
+#[cfg(feature = "integration-tests")]
+pub(crate) type PickValidator<'a> = &'a dyn Fn(
+    &Context,
+    &mut FrameCounter,
+    &PickImage,
+    &[PickId],
+) -> Result<ImageTestResult, anyhow::Error>;
+
+/// Like [`TestRender`] but validates the pick buffer instead of the colour frame.
+///
+/// With [`PickTestRender::with_click`] a left click is scripted after the first frame, so the
+/// validator can also assert which ids reached `on_click`.
+#[cfg(feature = "integration-tests")]
+pub(crate) struct PickTestRender<'a, T> {
+    pub(crate) data: T,
+    pub(crate) setup: &'a dyn Fn(&mut Context),
+    click: Option<&'a dyn Fn(&Context) -> PhysicalPosition<f64>>,
+    clicked: Vec<PickId>,
+    validate: PickValidator<'a>,
+}
+
+#[cfg(feature = "integration-tests")]
+impl<'a, T> PickTestRender<'a, T> {
+    pub(crate) fn new(
+        data: T,
+        setup: &'a dyn Fn(&mut Context),
+        validate: PickValidator<'a>,
+    ) -> Self {
+        Self {
+            data,
+            setup,
+            click: None,
+            clicked: Vec::new(),
+            validate,
+        }
+    }
+
+    /// Clicks at the window position returned by `at` once the first frame was rendered.
+    pub(crate) fn with_click(mut self, at: &'a dyn Fn(&Context) -> PhysicalPosition<f64>) -> Self {
+        self.click = Some(at);
+        self
+    }
+}
+
+#[cfg(feature = "integration-tests")]
+impl<'a, T> GraphicsFlow<FrameCounter, ()> for PickTestRender<'a, T>
+where
+    T: for<'b, 'pass> GPUResource<'b, 'pass>,
+{
+    fn on_init(&mut self, ctx: &mut Context, _s: &mut FrameCounter) -> Out<FrameCounter, ()> {
+        (self.setup)(ctx);
+        Out::Empty
+    }
+
+    fn on_click(&mut self, _ctx: &Context, _s: &mut FrameCounter, id: PickId) -> Out<FrameCounter, ()> {
+        self.clicked.push(id);
+        Out::Empty
+    }
+
+    fn on_render<'pass>(&self) -> Render<'_, 'pass> {
+        self.data.get_render()
+    }
+
+    fn pick_to_texture(
+        &self,
+        ctx: &Context,
+        s: &mut FrameCounter,
+        ids: &PickImage,
+    ) -> Result<ImageTestResult, anyhow::Error> {
+        // the scripted click is dispatched right before the second frame renders
+        if s.frame() == 0 {
+            return Ok(ImageTestResult::Waiting);
+        }
+        (self.validate)(ctx, s, ids, &self.clicked)
+    }
+
+    fn on_update(
+        &mut self,
+        ctx: &Context,
+        state: &mut FrameCounter,
+        _: std::time::Duration,
+    ) -> Out<FrameCounter, ()> {
+        state.progress();
+        self.data.write_to_buffer(&ctx.queue, &ctx.device);
+        match self.click {
+            Some(at) if state.frame() == 1 => {
+                let position = at(ctx);
+                Out::Configure(Box::new(move |ctx: &mut Context| ctx.click_at(position)))
+            }
+            _ => Out::Empty,
+        }
+    }
+}
+
 /// Simplified flow wrapper for UI elements (anything that impls `GraphicsFlow`).
 #[cfg(feature = "integration-tests")]
 pub(crate) struct TestUIRender<'a, T> {
@@ -322,9 +420,9 @@ macro_rules! golden_image_test {
         use flow_ngin::flow::FlowConstructor;
         use flow_ngin::flow::GraphicsFlow;
         let model_constructor: FlowConstructor<FrameCounter, ()> = Box::new(|ctx| {
-            Box::pin(
-                async move { Box::new($graphics_elem(ctx).await) as Box<dyn GraphicsFlow<_, _>> },
-            )
+            Box::pin(async move {
+                Ok(Box::new($graphics_elem(ctx).await) as Box<dyn GraphicsFlow<_, _>>)
+            })
         });
 
         flow_ngin::flow::run(vec![model_constructor])
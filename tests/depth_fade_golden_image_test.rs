@@ -0,0 +1,150 @@
+#[cfg(feature = "integration-tests")]
+mod common;
+
+/// A transparent plane slicing through an opaque cube with a fade distance set. The plane
+/// should fade out towards the cube instead of showing a hard intersection line.
+#[test]
+#[cfg(feature = "integration-tests")]
+fn transparent_plane_should_fade_into_cube() {
+    use cgmath::One;
+    use flow_ngin::{
+        context::{Context, GPUResource, InitContext},
+        data_structures::{
+            block::BuildingBlocks,
+            builder::{MeshBuilder, ModelBuilder},
+            model::{Material, Model},
+            texture::Texture,
+        },
+        flow::{FlowConstructor, GraphicsFlow, ImageTestResult, Out},
+        pipelines::transparent::TransparencyUniform,
+        render::Render,
+        resources::texture::diffuse_normal_layout,
+    };
+    use wgpu::Color;
+
+    use crate::common::test_utils::{FrameCounter, save_or_compare, to_rgba};
+
+    fn solid_model(ctx: &InitContext, name: &str, rgba: [u8; 4], mesh: MeshBuilder) -> Model {
+        let material = Material::new(
+            &ctx.device,
+            name,
+            Texture::from_color(rgba, &ctx.device, &ctx.queue),
+            Texture::create_default_normal_map(1, 1, &ctx.device, &ctx.queue),
+            &diffuse_normal_layout(&ctx.device),
+        )
+        .expect("failed to create material");
+        ModelBuilder::new()
+            .mesh(mesh.with_tangents())
+            .material(material)
+            .build(&ctx.device)
+            .expect("failed to build model")
+    }
+
+    fn cube() -> MeshBuilder {
+        let mut mesh = MeshBuilder::new("cube");
+        let faces: [([f32; 3], [f32; 3], [f32; 3]); 6] = [
+            ([1.0, 0.0, 0.0], [0.0, 0.0, -1.0], [0.0, 1.0, 0.0]),
+            ([-1.0, 0.0, 0.0], [0.0, 0.0, 1.0], [0.0, 1.0, 0.0]),
+            ([0.0, 1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, -1.0]),
+            ([0.0, -1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]),
+            ([0.0, 0.0, 1.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+            ([0.0, 0.0, -1.0], [-1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+        ];
+        for (normal, u, v) in faces {
+            let corner = |su: f32, sv: f32| [0, 1, 2].map(|i| normal[i] + su * u[i] + sv * v[i]);
+            let a = mesh.push_vertex(corner(-1.0, -1.0), [0.0, 1.0], normal);
+            let b = mesh.push_vertex(corner(1.0, -1.0), [1.0, 1.0], normal);
+            let c = mesh.push_vertex(corner(1.0, 1.0), [1.0, 0.0], normal);
+            let d = mesh.push_vertex(corner(-1.0, 1.0), [0.0, 0.0], normal);
+            mesh.push_triangle(a, b, c);
+            mesh.push_triangle(a, c, d);
+        }
+        mesh
+    }
+
+    fn plane() -> MeshBuilder {
+        let mut mesh = MeshBuilder::new("plane");
+        let up = [0.0, 1.0, 0.0];
+        let a = mesh.push_vertex([-3.0, 0.0, 3.0], [0.0, 1.0], up);
+        let b = mesh.push_vertex([3.0, 0.0, 3.0], [1.0, 1.0], up);
+        let c = mesh.push_vertex([3.0, 0.0, -3.0], [1.0, 0.0], up);
+        let d = mesh.push_vertex([-3.0, 0.0, -3.0], [0.0, 0.0], up);
+        mesh.push_triangle(a, b, c);
+        mesh.push_triangle(a, c, d);
+        mesh
+    }
+
+    struct DepthFadeFlow {
+        cube: BuildingBlocks,
+        plane: BuildingBlocks,
+    }
+
+    impl GraphicsFlow<FrameCounter, ()> for DepthFadeFlow {
+        fn on_init(&mut self, ctx: &mut Context, _s: &mut FrameCounter) -> Out<FrameCounter, ()> {
+            ctx.clear_colour = Color::WHITE;
+            ctx.camera.camera.position = [0.0, 5.0, 4.0].into();
+            Out::Empty
+        }
+
+        fn on_render<'pass>(&self) -> Render<'_, 'pass> {
+            Render::Composed(vec![
+                self.cube.get_render(),
+                self.plane.get_render().transparent(TransparencyUniform {
+                    tint: [0.2, 0.4, 1.0],
+                    alpha: 0.8,
+                    fade_distance: 0.5,
+                }),
+            ])
+        }
+
+        fn on_update(
+            &mut self,
+            ctx: &Context,
+            state: &mut FrameCounter,
+            _dt: std::time::Duration,
+        ) -> Out<FrameCounter, ()> {
+            state.progress();
+            self.cube.write_to_buffer(&ctx.queue, &ctx.device);
+            self.plane.write_to_buffer(&ctx.queue, &ctx.device);
+            Out::Empty
+        }
+
+        fn render_to_texture(
+            &self,
+            ctx: &Context,
+            s: &mut FrameCounter,
+            texture: &mut image::ImageBuffer<image::Rgba<u8>, wgpu::BufferView>,
+        ) -> Result<ImageTestResult, anyhow::Error> {
+            if s.frame() == 0 {
+                return Ok(ImageTestResult::Waiting);
+            }
+            save_or_compare(
+                "tests/fixtures/depth_fade_golden.png",
+                &to_rgba(ctx, texture),
+            )
+        }
+    }
+
+    let constructor: FlowConstructor<FrameCounter, ()> = Box::new(|ctx: InitContext| {
+        Box::pin(async move {
+            let cube = solid_model(&ctx, "cube", [200, 80, 60, 255], cube());
+            let plane = solid_model(&ctx, "plane", [255, 255, 255, 255], plane());
+            let at_origin = |id: u32, model| {
+                BuildingBlocks::from_model(
+                    id,
+                    &ctx.device,
+                    model,
+                    [0.0; 3].into(),
+                    flow_ngin::Quaternion::one(),
+                    1,
+                )
+            };
+            Ok(Box::new(DepthFadeFlow {
+                cube: at_origin(0, cube),
+                plane: at_origin(1, plane),
+            }) as Box<dyn GraphicsFlow<_, _>>)
+        })
+    });
+
+    flow_ngin::flow::run(vec![constructor]).expect("Integration test failed");
+}
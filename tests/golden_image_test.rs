@@ -23,7 +23,8 @@ fn should_match_rock_collection_render() {
             1,
             "Rock1.obj",
         )
-        .await;
+        .await
+        .expect("failed to load Rock1.obj");
         TestRender::new(
             model,
             &|ctx: &mut Context| {
@@ -34,3 +35,38 @@ fn should_match_rock_collection_render() {
         )
     });
 }
+
+/// Reversed Z only changes how depth is stored, the rocks render like with the standard range.
+#[test]
+#[cfg(feature = "integration-tests")]
+fn should_match_rock_collection_render_with_reversed_z() {
+    use cgmath::One;
+    use flow_ngin::{
+        context::{Context, InitContext},
+        data_structures::{block::BuildingBlocks, texture::DepthConfig},
+    };
+    use wgpu::Color;
+    golden_image_test!(async move |ctx: InitContext| {
+        let model = BuildingBlocks::new(
+            0,
+            &ctx.queue,
+            &ctx.device,
+            [0.0; 3].into(),
+            flow_ngin::Quaternion::one(),
+            1,
+            "Rock1.obj",
+        )
+        .await
+        .expect("failed to load Rock1.obj");
+        TestRender::new(
+            model,
+            &|ctx: &mut Context| {
+                ctx.configure_depth(DepthConfig::default().with_reversed_z(true))
+                    .expect("Depth32Float is a depth format");
+                ctx.clear_colour = Color::WHITE;
+                ctx.camera.camera.position = [0.0, 5.0, 2.0].into();
+            },
+            "tests/fixtures/golden_image.png",
+        )
+    });
+}
@@ -0,0 +1,58 @@
+/// A headless context draws the rock scene of the golden image test into an image of the
+/// requested size, without a window or event loop.
+#[test]
+#[cfg(feature = "integration-tests")]
+fn headless_context_renders_without_a_window() {
+    use cgmath::One;
+    use flow_ngin::{
+        context::{Context, GPUResource},
+        data_structures::block::BuildingBlocks,
+        flow::GraphicsFlow,
+        render::Render,
+    };
+    use wgpu::Color;
+
+    struct Rocks(BuildingBlocks);
+
+    impl GraphicsFlow<(), ()> for Rocks {
+        fn on_render<'pass>(&self) -> Render<'_, 'pass> {
+            self.0.get_render()
+        }
+    }
+
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    runtime.block_on(async {
+        let mut ctx = Context::new_headless(320, 240)
+            .await
+            .expect("failed to create a headless context");
+        assert!(ctx.is_headless() && ctx.window().is_none() && ctx.surface().is_none());
+        ctx.clear_colour = Color::WHITE;
+        ctx.camera.camera.position = [0.0, 5.0, 2.0].into();
+
+        let mut rocks = BuildingBlocks::new(
+            0,
+            &ctx.queue,
+            &ctx.device,
+            [0.0; 3].into(),
+            flow_ngin::Quaternion::one(),
+            1,
+            "Rock1.obj",
+        )
+        .await
+        .expect("failed to load Rock1.obj");
+        rocks.write_to_buffer(&ctx.queue, &ctx.device);
+        let flows: Vec<Box<dyn GraphicsFlow<(), ()>>> = vec![Box::new(Rocks(rocks))];
+
+        let image = ctx.render_once(&flows).await.expect("failed to render");
+        assert_eq!(image.dimensions(), (320, 240));
+        assert_eq!(image.get_pixel(0, 0).0, [255, 255, 255, 255]);
+        assert!(
+            image.pixels().any(|pixel| pixel.0 != [255, 255, 255, 255]),
+            "the rock wasn't drawn"
+        );
+
+        ctx.set_window_size(64, 32);
+        let image = ctx.render_once(&flows).await.expect("failed to render");
+        assert_eq!(image.dimensions(), (64, 32));
+    });
+}
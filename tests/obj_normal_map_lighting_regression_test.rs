@@ -17,6 +17,16 @@ impl<'a, 'pass> flow_ngin::context::GPUResource<'a, 'pass> for TwoModels {
         self.1.write_to_buffer(queue, device);
     }
 
+    fn write_to_buffer_offset(
+        &mut self,
+        queue: &wgpu::Queue,
+        device: &wgpu::Device,
+        offset: &flow_ngin::data_structures::instance::Instance,
+    ) {
+        self.0.write_to_buffer_offset(queue, device, offset);
+        self.1.write_to_buffer_offset(queue, device, offset);
+    }
+
     fn get_render(&'a self) -> flow_ngin::render::Render<'a, 'pass> {
         flow_ngin::render::Render::Composed(vec![
             self.0.get_render(),
@@ -42,11 +52,11 @@ fn obj_normal_map_lighting_direction_must_be_consistent() {
         let cube = BuildingBlocks::new(
             0, &ctx.queue, &ctx.device,
             [-1.5, 0.0, 0.0].into(), rotation, 1, "cube.obj",
-        ).await;
+        ).await.expect("failed to load cube.obj");
         let slab = BuildingBlocks::new(
             1, &ctx.queue, &ctx.device,
             [1.5, 0.0, 0.0].into(), rotation, 1, "half_slab.obj",
-        ).await;
+        ).await.expect("failed to load half_slab.obj");
         TestRender::new(
             TwoModels(cube, slab),
             &|ctx: &mut Context| {
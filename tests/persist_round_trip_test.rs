@@ -0,0 +1,131 @@
+#[cfg(all(feature = "integration-tests", feature = "serde"))]
+use std::sync::Mutex;
+
+#[cfg(all(feature = "integration-tests", feature = "serde"))]
+use flow_ngin::{
+    Deg,
+    camera::CameraPose,
+    context::Context,
+    flow::{FlowConstructor, GraphicsFlow, Out, RunConfig, run_with_restore},
+    persist::{EngineSnapshot, Snapshot},
+    pick::PickId,
+};
+#[cfg(all(feature = "integration-tests", feature = "serde"))]
+use wgpu::Color;
+
+#[cfg(all(feature = "integration-tests", feature = "serde"))]
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+struct SaveGame {
+    level: u32,
+    player: String,
+}
+
+// What the flow saw in `on_init` and `on_restore`, and the JSON written through `Out::Snapshot`
+#[cfg(all(feature = "integration-tests", feature = "serde"))]
+static INIT_STATE: Mutex<Option<SaveGame>> = Mutex::new(None);
+#[cfg(all(feature = "integration-tests", feature = "serde"))]
+static RESTORED: Mutex<Option<(EngineSnapshot, EngineSnapshot)>> = Mutex::new(None);
+#[cfg(all(feature = "integration-tests", feature = "serde"))]
+static SAVED: Mutex<Option<String>> = Mutex::new(None);
+
+#[cfg(all(feature = "integration-tests", feature = "serde"))]
+struct SaveFlow {
+    frames: u32,
+}
+
+#[cfg(all(feature = "integration-tests", feature = "serde"))]
+impl GraphicsFlow<SaveGame, ()> for SaveFlow {
+    fn on_init(&mut self, ctx: &mut Context, state: &mut SaveGame) -> Out<SaveGame, ()> {
+        *INIT_STATE.lock().unwrap() = Some(state.clone());
+        // The restored view wins over the one configured here
+        ctx.clear_colour = Color::BLACK;
+        Out::Empty
+    }
+
+    fn on_restore(
+        &mut self,
+        ctx: &Context,
+        state: &mut SaveGame,
+        snapshot: &EngineSnapshot,
+    ) -> Out<SaveGame, ()> {
+        *RESTORED.lock().unwrap() = Some((ctx.engine_snapshot(), *snapshot));
+        state.level += 1;
+        Out::Empty
+    }
+
+    fn on_update(
+        &mut self,
+        _: &Context,
+        _: &mut SaveGame,
+        _: std::time::Duration,
+    ) -> Out<SaveGame, ()> {
+        self.frames += 1;
+        match self.frames {
+            1 => Out::Snapshot(Box::new(|writer| {
+                let json = serde_json::to_string(&writer.snapshot())
+                    .expect("failed to serialize the snapshot");
+                *SAVED.lock().unwrap() = Some(json);
+            })),
+            2 => Out::Exit,
+            _ => Out::Empty,
+        }
+    }
+}
+
+/// A deserialized snapshot restores the state before `on_init` and the view after it, calls
+/// `on_restore` and is written back unchanged apart from what the flow changed.
+#[test]
+#[cfg(all(feature = "integration-tests", feature = "serde"))]
+fn snapshot_round_trips_through_the_app() {
+    let saved = Snapshot {
+        engine: EngineSnapshot {
+            camera: CameraPose {
+                position: (1.0, 4.0, -2.5).into(),
+                yaw: Deg(120.0).into(),
+                pitch: Deg(-30.0).into(),
+            },
+            clear_colour: Color {
+                r: 0.1,
+                g: 0.2,
+                b: 0.3,
+                a: 1.0,
+            },
+            selection: Some(PickId(7)),
+        },
+        state: SaveGame {
+            level: 3,
+            player: "Ada".to_string(),
+        },
+    };
+    let json = serde_json::to_string(&saved).expect("failed to serialize the snapshot");
+    let loaded: Snapshot<SaveGame> =
+        serde_json::from_str(&json).expect("failed to deserialize the snapshot");
+    assert_eq!(loaded, saved);
+
+    let constructor: FlowConstructor<SaveGame, ()> = Box::new(|_| {
+        Box::pin(async move { Ok(Box::new(SaveFlow { frames: 0 }) as Box<dyn GraphicsFlow<_, _>>) })
+    });
+    run_with_restore(RunConfig::default(), vec![constructor], Some(loaded))
+        .expect("the app failed");
+
+    assert_eq!(*INIT_STATE.lock().unwrap(), Some(saved.state.clone()));
+    let (restored_view, passed) = (*RESTORED.lock().unwrap()).expect("on_restore wasn't called");
+    assert_eq!(restored_view, saved.engine);
+    assert_eq!(passed, saved.engine);
+
+    let written = SAVED
+        .lock()
+        .unwrap()
+        .clone()
+        .expect("Out::Snapshot wasn't written");
+    let written: Snapshot<SaveGame> =
+        serde_json::from_str(&written).expect("failed to deserialize the written snapshot");
+    assert_eq!(written.engine, saved.engine);
+    assert_eq!(
+        written.state,
+        SaveGame {
+            level: 4,
+            ..saved.state
+        }
+    );
+}
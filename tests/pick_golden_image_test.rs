@@ -0,0 +1,53 @@
+#[cfg(feature = "integration-tests")]
+use crate::common::test_utils::PickTestRender;
+
+#[cfg(feature = "integration-tests")]
+mod common;
+
+#[test]
+#[cfg(feature = "integration-tests")]
+fn should_pick_rock_at_screen_center() {
+    use cgmath::{Deg, One};
+    use flow_ngin::{
+        camera::Camera,
+        context::{Context, InitContext},
+        data_structures::block::BuildingBlocks,
+        flow::ImageTestResult,
+        pick::PickId,
+        PhysicalPosition,
+    };
+    golden_image_test!(async move |ctx: InitContext| {
+        let model = BuildingBlocks::new(
+            7,
+            &ctx.queue,
+            &ctx.device,
+            [0.0; 3].into(),
+            flow_ngin::Quaternion::one(),
+            1,
+            "Rock1.obj",
+        )
+        .await
+        .expect("failed to load Rock1.obj");
+        PickTestRender::new(
+            model,
+            &|ctx: &mut Context| {
+                // looks straight at the rock from the front
+                ctx.camera.camera = Camera::new((0.0, 0.0, 5.0), Deg(-90.0), Deg(0.0));
+            },
+            &|ctx, _, ids, clicked| {
+                let (width, height) = (ctx.config.width, ctx.config.height);
+                assert_eq!(ids.dimensions(), (width, height));
+                assert_eq!(ids.get_pixel(width / 2, height / 2).0[0], 7);
+                assert_eq!(ids.get_pixel(0, 0).0[0], 0, "background must not be pickable");
+                assert_eq!(clicked, &[PickId(7)]);
+                Ok(ImageTestResult::Passed)
+            },
+        )
+        .with_click(&|ctx| {
+            PhysicalPosition::new(
+                f64::from(ctx.config.width) / 2.0,
+                f64::from(ctx.config.height) / 2.0,
+            )
+        })
+    });
+}
@@ -0,0 +1,135 @@
+#[cfg(feature = "integration-tests")]
+mod common;
+
+/// Both rocks share the pick id of their blocks, with instance picking the pick texture holds
+/// `id + instance` and clicks report the instance.
+#[test]
+#[cfg(feature = "integration-tests")]
+fn should_pick_each_instance_of_a_block() {
+    use crate::common::test_utils::FrameCounter;
+    use cgmath::{Deg, One, Vector3};
+    use flow_ngin::{
+        PhysicalPosition,
+        camera::Camera,
+        context::{Context, GPUResource, InitContext},
+        data_structures::{block::BuildingBlocks, instance::Instance},
+        flow::{GraphicsFlow, ImageTestResult, Out},
+        pick::{PickId, PickImage},
+        render::Render,
+    };
+
+    const ROCKS: PickId = PickId(7);
+
+    struct RockPair {
+        rocks: BuildingBlocks,
+        clicked: Vec<(PickId, u32, Option<Vector3<f32>>)>,
+    }
+
+    /// Window position of the rock at `x` on the horizon.
+    fn rock_at(ctx: &Context, x: f32) -> PhysicalPosition<f64> {
+        let (width, height) = (ctx.config.width as f32, ctx.config.height as f32);
+        let world = Vector3::new(x, 0.0, 0.0);
+        ctx.camera
+            .camera
+            .world_to_screen(world, width, height, &ctx.projection)
+            .unwrap()
+    }
+
+    impl GraphicsFlow<FrameCounter, ()> for RockPair {
+        fn on_init(&mut self, ctx: &mut Context, _: &mut FrameCounter) -> Out<FrameCounter, ()> {
+            // looks straight at both rocks from the front
+            ctx.camera.camera = Camera::new((0.0, 0.0, 8.0), Deg(-90.0), Deg(0.0));
+            Out::Empty
+        }
+
+        fn on_click_instance(
+            &mut self,
+            _: &Context,
+            _: &mut FrameCounter,
+            id: PickId,
+            instance: u32,
+            at: Option<Vector3<f32>>,
+        ) -> Out<FrameCounter, ()> {
+            self.clicked.push((id, instance, at));
+            Out::Empty
+        }
+
+        fn on_render<'pass>(&self) -> Render<'_, 'pass> {
+            self.rocks.get_render()
+        }
+
+        fn on_update(
+            &mut self,
+            ctx: &Context,
+            state: &mut FrameCounter,
+            _: std::time::Duration,
+        ) -> Out<FrameCounter, ()> {
+            state.progress();
+            self.rocks.write_to_buffer(&ctx.queue, &ctx.device);
+            if state.frame() != 1 {
+                return Out::Empty;
+            }
+            Out::Configure(Box::new(|ctx: &mut Context| {
+                let right = rock_at(ctx, 2.0);
+                ctx.click_at(right);
+            }))
+        }
+
+        fn pick_to_texture(
+            &self,
+            ctx: &Context,
+            s: &mut FrameCounter,
+            ids: &PickImage,
+        ) -> Result<ImageTestResult, anyhow::Error> {
+            // the scripted click is dispatched right before the second frame renders
+            if s.frame() == 0 {
+                return Ok(ImageTestResult::Waiting);
+            }
+            let id_at = |x: f32| {
+                let at = rock_at(ctx, x);
+                ids.get_pixel(at.x as u32, at.y as u32).0[0]
+            };
+            assert_eq!(id_at(-2.0), ROCKS.0);
+            assert_eq!(id_at(2.0), ROCKS.0 + 1);
+            assert_eq!(
+                ids.get_pixel(0, 0).0[0],
+                0,
+                "background must not be pickable"
+            );
+            let [(id, instance, at)] = self.clicked[..] else {
+                panic!("expected a single click, got {:?}", self.clicked);
+            };
+            assert_eq!((id, instance), (ROCKS, 1));
+            let at = at.expect("the rock's surface was clicked");
+            assert!(
+                at.x > 1.0 && at.z > 0.0,
+                "hit {at:?} isn't on the front of the right rock"
+            );
+            Ok(ImageTestResult::Passed)
+        }
+    }
+
+    golden_image_test!(async move |ctx: InitContext| {
+        let mut rocks = BuildingBlocks::new(
+            ROCKS,
+            &ctx.queue,
+            &ctx.device,
+            [0.0; 3].into(),
+            flow_ngin::Quaternion::one(),
+            1,
+            "Rock1.obj",
+        )
+        .await
+        .expect("failed to load Rock1.obj");
+        let rock = |x| Instance {
+            position: Vector3::new(x, 0.0, 0.0),
+            ..Instance::new()
+        };
+        rocks.set_instances(vec![rock(-2.0), rock(2.0)]);
+        rocks.set_instance_picking(true);
+        RockPair {
+            rocks,
+            clicked: Vec::new(),
+        }
+    });
+}
@@ -0,0 +1,104 @@
+#[cfg(feature = "integration-tests")]
+mod common;
+
+/// The pick texture used to be padded to multiples of 256 and the mouse scaled by the padded
+/// size, which shifted clicks near the right edge onto the neighbouring object.
+#[test]
+#[cfg(feature = "integration-tests")]
+fn should_pick_adjacent_quads_at_the_right_edge() {
+    use crate::common::test_utils::FrameCounter;
+    use flow_ngin::{
+        context::{Context, InitContext},
+        flow::{GraphicsFlow, ImageTestResult, Out},
+        pick::{PickId, PickImage},
+        render::Render,
+        ui::{container::Container, HAlign},
+        PhysicalPosition,
+    };
+
+    /// Right aligned quads, `outer` is `2 * QUAD` and `inner` the right half of it.
+    const QUAD: u32 = 10;
+
+    struct EdgeQuads {
+        quads: Container<FrameCounter, ()>,
+        clicked: Vec<PickId>,
+    }
+
+    /// Window pixels `ui_px` UI pixels left of the right edge, in the middle of the rows.
+    fn left_of_edge(ctx: &Context, ui_px: f32) -> PhysicalPosition<f64> {
+        let x = ctx.config.width as f32 - ui_px * ctx.ui_pixel_scale();
+        PhysicalPosition::new(f64::from(x), f64::from(ctx.config.height) / 2.0)
+    }
+
+    impl GraphicsFlow<FrameCounter, ()> for EdgeQuads {
+        fn on_init(&mut self, ctx: &mut Context, s: &mut FrameCounter) -> Out<FrameCounter, ()> {
+            self.quads.on_init(ctx, s)
+        }
+
+        fn on_click(&mut self, _: &Context, _: &mut FrameCounter, id: PickId) -> Out<FrameCounter, ()> {
+            self.clicked.push(id);
+            Out::Empty
+        }
+
+        fn on_render<'pass>(&self) -> Render<'_, 'pass> {
+            self.quads.on_render()
+        }
+
+        fn on_update(
+            &mut self,
+            _: &Context,
+            state: &mut FrameCounter,
+            _: std::time::Duration,
+        ) -> Out<FrameCounter, ()> {
+            state.progress();
+            if state.frame() != 1 {
+                return Out::Empty;
+            }
+            Out::Configure(Box::new(|ctx: &mut Context| {
+                let outer = left_of_edge(ctx, 1.5 * QUAD as f32);
+                let inner = left_of_edge(ctx, 0.5 * QUAD as f32);
+                ctx.click_at(outer);
+                ctx.click_at(inner);
+            }))
+        }
+
+        fn pick_to_texture(
+            &self,
+            ctx: &Context,
+            s: &mut FrameCounter,
+            ids: &PickImage,
+        ) -> Result<ImageTestResult, anyhow::Error> {
+            // the scripted clicks are dispatched right before the second frame renders
+            if s.frame() == 0 {
+                return Ok(ImageTestResult::Waiting);
+            }
+            let id_at = |ui_px: f32| {
+                let at = left_of_edge(ctx, ui_px);
+                ids.get_pixel(at.x as u32, at.y as u32).0[0]
+            };
+            assert_eq!(id_at(1.5 * QUAD as f32), 1);
+            assert_eq!(id_at(0.5 * QUAD as f32), 2);
+            assert_eq!(id_at(3.0 * QUAD as f32), 0, "left of the quads is empty");
+            assert_eq!(self.clicked, [PickId(1), PickId(2)]);
+            Ok(ImageTestResult::Passed)
+        }
+    }
+
+    golden_image_test!(async move |_: InitContext| {
+        let inner = Container::new()
+            .width(QUAD)
+            .halign(HAlign::Right)
+            .with_background_color([60, 60, 180, 255])
+            .clickable(2);
+        let quads = Container::new()
+            .width(2 * QUAD)
+            .halign(HAlign::Right)
+            .with_background_color([180, 60, 60, 255])
+            .clickable(1)
+            .with_child(inner);
+        EdgeQuads {
+            quads,
+            clicked: Vec::new(),
+        }
+    });
+}
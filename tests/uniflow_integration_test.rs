@@ -41,7 +41,7 @@ impl GraphicsFlow<State, Event> for GraphicsElement {
 
     fn on_update(
         &mut self,
-        _: &Context,
+        ctx: &Context,
         state: &mut State,
         _: std::time::Duration,
     ) -> Out<State, Event> {
@@ -51,27 +51,32 @@ impl GraphicsFlow<State, Event> for GraphicsElement {
         state.update();
 
         // test scenarios:
-        let serve_sencha: Box<dyn FnOnce(&mut State)> = Box::new(|state: &mut State| {
+        let serve_sencha: Box<dyn FnOnce(&mut State) + Send> = Box::new(|state: &mut State| {
             state.dummy_state.push('🍵');
         });
-        let serve_mate: Box<dyn FnOnce(&mut State)> = Box::new(|state: &mut State| {
+        let serve_mate: Box<dyn FnOnce(&mut State) + Send> = Box::new(|state: &mut State| {
             state.dummy_state.push('🧉');
         });
         match state.frame_counter() {
             3 => Out::FutEvent(vec![Box::new(async move { Event::Test })]),
-            5 => Out::FutFn(vec![
-                Box::new(async move { serve_sencha }),
-                Box::new(async move { serve_mate }),
+            // The Context is configured right away, before the mutations are dispatched
+            5 => Out::Composed(vec![
+                Out::FutFn(vec![
+                    Box::new(async move { serve_sencha }),
+                    Box::new(async move { serve_mate }),
+                ]),
+                Out::Configure(Box::new(|ctx| ctx.clear_colour = Color::BLACK)),
             ]),
             6 => {
+                assert_eq!(ctx.clear_colour, Color::BLACK);
                 // Hopefully this will kill the program?
                 println!("done");
                 Out::Empty
             }
-            x if x > 5 => {
-                assert!(state.dummy_state.contains('🧉'));
-                assert!(state.dummy_state.contains('🍵'));
-                // emojis are 4chars wide.
+            // The mutations resolve in the background, so they may arrive some frames later
+            x if x > 5 && !state.dummy_state.is_empty() => {
+                // Both at once in the order they were returned, emojis are 4 bytes wide
+                assert_eq!(state.dummy_state, "🍵🧉");
                 assert_eq!(state.dummy_state.len(), 8, "{}", state.dummy_state);
                 Out::Empty
             }
@@ -127,7 +132,7 @@ impl GraphicsFlow<State, Event> for GraphicsElement {
 fn should_not_be_emty_after_render() {
     let model_constructor: FlowConstructor<State, Event> = Box::new(|_| {
         Box::pin(async move {
-            Box::new(GraphicsElement()) as Box<dyn GraphicsFlow<_, _>>
+            Ok(Box::new(GraphicsElement()) as Box<dyn GraphicsFlow<_, _>>)
         })
     });
 